@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+
+use crate::app::dnd::JoinRequest;
+
+/// A join request waiting for the host, with its place in line
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueuedJoin {
+    /// Place in line, starting from 1
+    pub position: usize,
+    pub peer_id: String,
+    pub name: String,
+}
+
+/// What the host tells a peer that asked to join
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JoinReply {
+    /// The host hasn't decided yet; keep waiting
+    Waiting,
+    /// Come in
+    Admitted,
+    /// Turned away, with the host's message
+    Denied(String),
+}
+
+/// Join requests waiting for the host to let them in or turn them away
+///
+/// Requests are kept in the order they arrived, so when several peers ask
+/// at once the host sees them, and admits them, first come first served.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AdmissionQueue {
+    waiting: Vec<JoinRequest>,
+}
+
+impl AdmissionQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a request to the end of the line, returning false if the peer was already waiting
+    ///
+    /// A peer asking again keeps its place.
+    pub fn push(&mut self, request: JoinRequest) -> bool {
+        if self.contains(&request.peer_id) {
+            return false;
+        }
+        self.waiting.push(request);
+        true
+    }
+
+    pub fn contains(&self, peer_id: &str) -> bool {
+        self.waiting
+            .iter()
+            .any(|request| request.peer_id == peer_id)
+    }
+
+    /// Takes a peer out of the line, moving everyone behind it up
+    pub fn remove(&mut self, peer_id: &str) -> Option<JoinRequest> {
+        let index = self
+            .waiting
+            .iter()
+            .position(|request| request.peer_id == peer_id)?;
+        Some(self.waiting.remove(index))
+    }
+
+    /// Takes every request, oldest first
+    pub fn take_all(&mut self) -> Vec<JoinRequest> {
+        std::mem::take(&mut self.waiting)
+    }
+
+    pub fn len(&self) -> usize {
+        self.waiting.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.waiting.is_empty()
+    }
+
+    /// The line as it stands, for showing to the host
+    pub fn snapshot(&self) -> Vec<QueuedJoin> {
+        self.waiting
+            .iter()
+            .enumerate()
+            .map(|(index, request)| QueuedJoin {
+                position: index + 1,
+                peer_id: request.peer_id.clone(),
+                name: request.name.clone(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(peer_id: &str) -> JoinRequest {
+        JoinRequest {
+            peer_id: peer_id.to_string(),
+            name: peer_id.to_uppercase(),
+        }
+    }
+
+    #[test]
+    fn test_queue_keeps_arrival_order() {
+        let mut queue = AdmissionQueue::new();
+        assert!(queue.push(request("alice")));
+        assert!(queue.push(request("bob")));
+        assert!(queue.push(request("carol")));
+        assert!(!queue.push(request("alice")));
+        assert_eq!(queue.len(), 3);
+
+        // Leaving the line moves everyone behind up
+        assert_eq!(queue.remove("alice"), Some(request("alice")));
+        assert_eq!(queue.remove("alice"), None);
+        let positions: Vec<(usize, String)> = queue
+            .snapshot()
+            .into_iter()
+            .map(|queued| (queued.position, queued.peer_id))
+            .collect();
+        assert_eq!(
+            positions,
+            [(1, "bob".to_string()), (2, "carol".to_string())]
+        );
+
+        assert_eq!(queue.take_all(), [request("bob"), request("carol")]);
+        assert!(queue.is_empty());
+    }
+}
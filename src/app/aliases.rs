@@ -0,0 +1,110 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Local names for peers, keyed by their public key
+///
+/// A peer picks its own name and can change it at any time, but its key
+/// stays the same across sessions. Aliases let recurring contacts keep a
+/// name we chose, whatever they call themselves.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PeerAliases {
+    aliases: BTreeMap<[u8; 32], String>,
+}
+
+impl PeerAliases {
+    pub fn get(&self, key: &[u8; 32]) -> Option<&str> {
+        self.aliases.get(key).map(String::as_str)
+    }
+
+    /// Sets the alias for a key, or removes it if `alias` is blank
+    ///
+    /// Characters the config file can't hold are dropped.
+    pub fn set(&mut self, key: [u8; 32], alias: &str) {
+        let alias: String = alias
+            .chars()
+            .filter(|c| *c != ';' && !c.is_control())
+            .collect();
+        let alias = alias.trim();
+        if alias.is_empty() {
+            self.aliases.remove(&key);
+        } else {
+            self.aliases.insert(key, alias.to_string());
+        }
+    }
+
+    /// The name to show for a peer: our alias if we set one, else the name it sent
+    pub fn name_for(&self, key: &[u8; 32], broadcast_name: &str) -> String {
+        self.get(key).unwrap_or(broadcast_name).to_string()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.aliases.is_empty()
+    }
+}
+
+pub(crate) fn key_to_hex(key: &[u8; 32]) -> String {
+    key.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub(crate) fn key_from_hex(hex: &str) -> Option<[u8; 32]> {
+    if hex.len() != 64 || !hex.is_ascii() {
+        return None;
+    }
+    let mut key = [0u8; 32];
+    for (byte, pair) in key.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+    Some(key)
+}
+
+impl fmt::Display for PeerAliases {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries: Vec<String> = self
+            .aliases
+            .iter()
+            .map(|(key, alias)| format!("{}={}", key_to_hex(key), alias))
+            .collect();
+        write!(f, "{}", entries.join(";"))
+    }
+}
+
+impl FromStr for PeerAliases {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut aliases = PeerAliases::default();
+        for entry in s.split(';').filter(|entry| !entry.trim().is_empty()) {
+            let (key, alias) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid peer alias: {}", entry))?;
+            let key = key_from_hex(key.trim())
+                .ok_or_else(|| format!("Invalid peer key in alias: {}", key))?;
+            aliases.set(key, alias);
+        }
+        Ok(aliases)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aliases_round_trip() {
+        let mut aliases = PeerAliases::default();
+        aliases.set([7; 32], " Alice's laptop ");
+        aliases.set([9; 32], "a=b; c");
+        assert_eq!(aliases.get(&[7; 32]), Some("Alice's laptop"));
+        assert_eq!(aliases.get(&[9; 32]), Some("a=b c"));
+
+        let parsed: PeerAliases = aliases.to_string().parse().unwrap();
+        assert_eq!(parsed, aliases);
+        assert_eq!(parsed.name_for(&[7; 32], "xX_alice_Xx"), "Alice's laptop");
+        assert_eq!(parsed.name_for(&[1; 32], "Bob"), "Bob");
+
+        aliases.set([9; 32], "  ");
+        assert_eq!(aliases.get(&[9; 32]), None);
+        assert!("abcd=Alice".parse::<PeerAliases>().is_err());
+    }
+}
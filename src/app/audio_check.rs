@@ -0,0 +1,371 @@
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+use crate::app::config::Config;
+use crate::audio::conversions::downmix_to_mono;
+use crate::audio::{
+    negotiate, probe_devices, AudioCapture, AudioCodec, AudioConfig, AudioDecoder, AudioEncoder,
+    AudioFrame, AudioPlayback, CodecSet, DeviceCapabilities, SpatialAudioProcessor,
+};
+use crate::network::now_micros;
+
+/// How long the check runs unless asked otherwise
+pub const DEFAULT_CHECK_DURATION: Duration = Duration::from_secs(5);
+
+/// Gap between captured frames that counts as the microphone falling behind
+///
+/// Capture hands over audio every 20 ms, so this is three missed deliveries.
+const CAPTURE_GAP: Duration = Duration::from_millis(60);
+
+/// Source name the loopback is played under
+const LOOPBACK_SOURCE: &str = "audio-check";
+
+/// Time spent in one stage of the pipeline
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StageTiming {
+    pub count: u32,
+    pub total: Duration,
+    pub max: Duration,
+}
+
+impl StageTiming {
+    pub fn record(&mut self, elapsed: Duration) {
+        self.count += 1;
+        self.total += elapsed;
+        self.max = self.max.max(elapsed);
+    }
+
+    pub fn average(&self) -> Duration {
+        self.total.checked_div(self.count).unwrap_or_default()
+    }
+}
+
+impl fmt::Display for StageTiming {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "avg {:.2} ms, max {:.2} ms",
+            self.average().as_secs_f64() * 1000.0,
+            self.max.as_secs_f64() * 1000.0
+        )
+    }
+}
+
+/// Runs frames through resampling, the codec and spatial mixing, timing each stage
+///
+/// This is the path a frame takes from our microphone to a peer's speakers,
+/// with the network left out.
+pub struct PipelineCheck {
+    config: AudioConfig,
+    codec: AudioCodec,
+    encoder: AudioEncoder,
+    decoder: AudioDecoder,
+    spatial: SpatialAudioProcessor,
+    last_arrival: Option<Instant>,
+    last_seq: Option<u64>,
+    report: AudioCheckReport,
+}
+
+impl PipelineCheck {
+    pub fn new(config: AudioConfig, codec: AudioCodec, bitrate_kbps: u32) -> Self {
+        let mut spatial = SpatialAudioProcessor::new();
+        spatial.set_sample_rate(config.sample_rate);
+        // A little to the front right, so both ears are exercised
+        spatial.set_source_position(1.0, 0.0, 1.0);
+
+        Self {
+            config,
+            codec,
+            encoder: AudioEncoder::new(bitrate_kbps),
+            decoder: AudioDecoder::new(),
+            spatial,
+            last_arrival: None,
+            last_seq: None,
+            report: AudioCheckReport {
+                input: None,
+                output: None,
+                audio_config: config,
+                requested_codec: codec,
+                codec,
+                frames: 0,
+                encoded_bytes: 0,
+                resample: StageTiming::default(),
+                encode: StageTiming::default(),
+                decode: StageTiming::default(),
+                mix: StageTiming::default(),
+                end_to_end: StageTiming::default(),
+                capture_gaps: 0,
+                dropped_frames: 0,
+                playback_underruns: 0,
+                playback_error: None,
+            },
+        }
+    }
+
+    /// Processes a captured frame, returning the stereo audio to play
+    pub fn process(&mut self, frame: AudioFrame, arrived: Instant) -> Result<Vec<f32>> {
+        if let Some(last) = self.last_arrival {
+            if arrived.saturating_duration_since(last) > CAPTURE_GAP {
+                self.report.capture_gaps += 1;
+            }
+        }
+        if let Some(last) = self.last_seq {
+            self.report.dropped_frames += frame.seq.saturating_sub(last + 1);
+        }
+        self.last_arrival = Some(arrived);
+        self.last_seq = Some(frame.seq);
+        self.report.frames += 1;
+        let capture_ts = frame.capture_ts;
+
+        let started = Instant::now();
+        let frame = self.config.conform(frame);
+        self.report.resample.record(started.elapsed());
+
+        let started = Instant::now();
+        let (codec, data) = self.encoder.encode(self.codec, &frame);
+        self.report.encode.record(started.elapsed());
+        self.report.codec = codec;
+        self.report.encoded_bytes += data.len() as u64;
+
+        let started = Instant::now();
+        let samples = self
+            .decoder
+            .decode(codec, &data, frame.sample_rate, frame.channels)
+            .ok_or_else(|| anyhow!("{} failed to decode its own frame", codec))?;
+        self.report.decode.record(started.elapsed());
+
+        let started = Instant::now();
+        let mono = downmix_to_mono(&samples, frame.channels);
+        let stereo = self.spatial.process(&mono);
+        self.report.mix.record(started.elapsed());
+
+        let latency = now_micros().saturating_sub(capture_ts);
+        self.report
+            .end_to_end
+            .record(Duration::from_micros(latency));
+        Ok(stereo)
+    }
+
+    pub fn finish(self) -> AudioCheckReport {
+        self.report
+    }
+}
+
+/// What the audio check found
+#[derive(Debug, Clone)]
+pub struct AudioCheckReport {
+    pub input: Option<DeviceCapabilities>,
+    pub output: Option<DeviceCapabilities>,
+    pub audio_config: AudioConfig,
+    /// Codec from the configuration
+    pub requested_codec: AudioCodec,
+    /// Codec frames were actually sent with, after falling back if need be
+    pub codec: AudioCodec,
+    pub frames: u64,
+    pub encoded_bytes: u64,
+    pub resample: StageTiming,
+    pub encode: StageTiming,
+    pub decode: StageTiming,
+    pub mix: StageTiming,
+    /// From capture until the frame was ready to play, device buffers aside
+    pub end_to_end: StageTiming,
+    /// Times the microphone went quiet for longer than it should
+    pub capture_gaps: u64,
+    /// Frames lost between the microphone and the pipeline
+    pub dropped_frames: u64,
+    /// Times the speakers ran out of audio
+    pub playback_underruns: u64,
+    /// Why playback couldn't be opened, if it couldn't
+    pub playback_error: Option<String>,
+}
+
+impl AudioCheckReport {
+    /// Whether audio made it through without gaps
+    pub fn passed(&self) -> bool {
+        self.frames > 0
+            && self.playback_error.is_none()
+            && self.capture_gaps == 0
+            && self.dropped_frames == 0
+            && self.playback_underruns == 0
+    }
+}
+
+fn write_device(
+    f: &mut fmt::Formatter<'_>,
+    label: &str,
+    device: &Option<DeviceCapabilities>,
+) -> fmt::Result {
+    let Some(capabilities) = device else {
+        return writeln!(f, "{}: not found", label);
+    };
+    let rates = capabilities
+        .sample_rates
+        .iter()
+        .map(|rate| rate.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let latency = capabilities
+        .min_latency()
+        .map_or("unknown".to_string(), |latency| {
+            format!("{:.1} ms", latency.as_secs_f64() * 1000.0)
+        });
+    writeln!(f, "{}: {}", label, capabilities.device.name)?;
+    writeln!(f, "  sample rates: {}", rates)?;
+    writeln!(f, "  buffer latency: {}", latency)
+}
+
+impl fmt::Display for AudioCheckReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_device(f, "Input", &self.input)?;
+        write_device(f, "Output", &self.output)?;
+        writeln!(
+            f,
+            "Format: {} Hz, {} channel(s)",
+            self.audio_config.sample_rate, self.audio_config.channels
+        )?;
+        if self.codec == self.requested_codec {
+            writeln!(f, "Codec: {}", self.codec)?;
+        } else {
+            writeln!(
+                f,
+                "Codec: {} (fell back from {})",
+                self.codec, self.requested_codec
+            )?;
+        }
+        writeln!(f, "Frames: {}", self.frames)?;
+        if let Some(per_frame) = self.encoded_bytes.checked_div(self.frames) {
+            writeln!(f, "Encoded size: {} bytes per frame", per_frame)?;
+        }
+        writeln!(f, "Resample: {}", self.resample)?;
+        writeln!(f, "Encode: {}", self.encode)?;
+        writeln!(f, "Decode: {}", self.decode)?;
+        writeln!(f, "Mix: {}", self.mix)?;
+        writeln!(f, "Capture to playback: {}", self.end_to_end)?;
+        writeln!(f, "Capture gaps: {}", self.capture_gaps)?;
+        writeln!(f, "Dropped frames: {}", self.dropped_frames)?;
+        match &self.playback_error {
+            Some(error) => writeln!(f, "Playback: failed to open ({})", error)?,
+            None => writeln!(f, "Playback underruns: {}", self.playback_underruns)?,
+        }
+        write!(
+            f,
+            "Result: {}",
+            if self.passed() {
+                "OK"
+            } else {
+                "PROBLEMS FOUND"
+            }
+        )
+    }
+}
+
+/// Loops audio from the configured microphone to the configured speakers for `duration`
+///
+/// Each frame goes through the same stages as in a call. Playback that
+/// can't be opened is reported rather than failing the check, so capture
+/// and the codec can still be validated; a microphone that can't be opened
+/// is an error.
+pub async fn run(config: &Config, duration: Duration) -> Result<AudioCheckReport> {
+    let devices = probe_devices();
+    let mut config = config.clone();
+    config.match_devices(&devices);
+    let chosen = |is_input: bool, id: &Option<String>| {
+        devices
+            .iter()
+            .find(|capabilities| {
+                capabilities.device.is_input == is_input
+                    && id.as_ref().is_none_or(|id| *id == capabilities.device.id)
+            })
+            .cloned()
+    };
+    let input = chosen(true, &config.input_device);
+    let output = chosen(false, &config.output_device);
+    let audio_config = AudioConfig::resolve(input.as_ref(), output.as_ref());
+
+    let codec = negotiate(config.codec.codec, CodecSet::available());
+    let mut check = PipelineCheck::new(audio_config, codec, config.codec.bitrate_kbps);
+    check.report.requested_codec = config.codec.codec;
+
+    let (frames_tx, mut frames) = mpsc::unbounded_channel::<AudioFrame>();
+    let mut capture = AudioCapture::new();
+    capture.set_sample_rate(audio_config.sample_rate);
+    if let Some(input) = &input {
+        capture.set_device(input.device.clone())?;
+    }
+    capture.set_data_callback(move |frame| {
+        let _ = frames_tx.send(frame);
+    });
+    capture.start().await?;
+
+    let output_id = config.output_device.as_deref().unwrap_or("default");
+    let playback = match AudioPlayback::open(output_id, audio_config.sample_rate) {
+        Ok(playback) => Some(playback),
+        Err(e) => {
+            check.report.playback_error = Some(e.to_string());
+            None
+        }
+    };
+
+    let deadline = tokio::time::Instant::now() + duration;
+    let result = loop {
+        let frame = match tokio::time::timeout_at(deadline, frames.recv()).await {
+            Ok(Some(frame)) => frame,
+            Ok(None) => break Err(anyhow!("Capture stopped during the check")),
+            Err(_) => break Ok(()),
+        };
+        match check.process(frame, Instant::now()) {
+            Ok(stereo) => {
+                if let Some(playback) = &playback {
+                    playback.push(LOOPBACK_SOURCE, &stereo);
+                }
+            }
+            Err(e) => break Err(e),
+        }
+    };
+
+    if let Err(e) = capture.stop().await {
+        log::warn!("Failed to stop audio check capture: {}", e);
+    }
+    result?;
+
+    let mut report = check.finish();
+    report.input = input;
+    report.output = output;
+    report.playback_underruns = playback.as_ref().map_or(0, |playback| playback.underruns());
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pipeline_check_times_stages_and_counts_gaps() {
+        let config = AudioConfig::default();
+        let mut check = PipelineCheck::new(config, AudioCodec::Adpcm, 32);
+        let start = Instant::now();
+
+        // Captured at 44.1 kHz stereo, so the frames need resampling
+        for (i, seq) in [0u64, 1, 2, 4, 5].into_iter().enumerate() {
+            let frame = AudioFrame::new(vec![0.1; 1764], 44100, 2).with_seq(seq);
+            // The frame after the lost one also arrives late
+            let arrived =
+                start + Duration::from_millis(20 * i as u64 + if i >= 3 { 80 } else { 0 });
+            let stereo = check.process(frame, arrived).unwrap();
+            // Spatial processing may add a short tail for the far ear
+            assert!(stereo.len() >= config.frame_len(20) * 2);
+        }
+
+        let report = check.finish();
+        assert_eq!(report.frames, 5);
+        assert_eq!(report.dropped_frames, 1);
+        assert_eq!(report.capture_gaps, 1);
+        assert_eq!(report.encode.count, 5);
+        assert_eq!(report.codec, AudioCodec::Adpcm);
+        assert!(report.end_to_end.max >= report.end_to_end.average());
+        assert!(!report.passed());
+        assert!(report.to_string().contains("Codec: adpcm"));
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// How long we send to a peer without hearing back before calling the audio one-way
+pub const ONE_WAY_AUDIO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What we know about one peer's audio
+#[derive(Debug, Clone)]
+struct PeerAudio {
+    // When we started sending to the peer, or last heard from it
+    last_heard: Instant,
+    // Whether the current silence has already been reported
+    reported: bool,
+}
+
+/// Notices peers we keep sending audio to but never hear from
+///
+/// Peers send frames or comfort noise for as long as they're connected and
+/// allowed to speak, even when quiet or muted, so a long gap means their
+/// audio is being lost on the way to us: a blocked microphone, a firewall
+/// letting traffic through in only one direction, or a broken relay.
+#[derive(Debug, Clone)]
+pub struct AudioWatchdog {
+    timeout: Duration,
+    peers: HashMap<String, PeerAudio>,
+}
+
+impl AudioWatchdog {
+    pub fn new() -> Self {
+        Self {
+            timeout: ONE_WAY_AUDIO_TIMEOUT,
+            peers: HashMap::new(),
+        }
+    }
+
+    /// Sets how long a peer may stay silent while we send to it
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Records audio arriving from a peer
+    pub fn heard(&mut self, peer_id: &str, now: Instant) {
+        let peer = self.peers.entry(peer_id.to_string()).or_insert(PeerAudio {
+            last_heard: now,
+            reported: false,
+        });
+        if peer.reported {
+            log::info!("Audio from {} is arriving again", peer_id);
+        }
+        peer.last_heard = now;
+        peer.reported = false;
+    }
+
+    /// Records audio sent to a peer, returning true the first time its audio looks one-way
+    pub fn sent(&mut self, peer_id: &str, now: Instant) -> bool {
+        let peer = self.peers.entry(peer_id.to_string()).or_insert(PeerAudio {
+            last_heard: now,
+            reported: false,
+        });
+        if peer.reported || now.duration_since(peer.last_heard) < self.timeout {
+            return false;
+        }
+        peer.reported = true;
+        true
+    }
+
+    /// Stops watching a peer, such as one that left or was muted by the host
+    pub fn forget(&mut self, peer_id: &str) {
+        self.peers.remove(peer_id);
+    }
+
+    /// Stops watching every peer
+    pub fn clear(&mut self) {
+        self.peers.clear();
+    }
+}
+
+impl Default for AudioWatchdog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_one_way_audio_is_reported_once() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut watchdog = AudioWatchdog::new().with_timeout(Duration::from_secs(5));
+
+        // Hearing back keeps the peer healthy however long we send
+        for secs in 0..10 {
+            watchdog.heard("alice", at(secs));
+            assert!(!watchdog.sent("alice", at(secs)));
+        }
+
+        // Silence is reported once the timeout passes, and only once
+        assert!(!watchdog.sent("bob", at(0)));
+        assert!(!watchdog.sent("bob", at(4)));
+        assert!(watchdog.sent("bob", at(5)));
+        assert!(!watchdog.sent("bob", at(6)));
+
+        // Audio arriving again re-arms the report
+        watchdog.heard("bob", at(7));
+        assert!(!watchdog.sent("bob", at(8)));
+        assert!(watchdog.sent("bob", at(12)));
+
+        // A forgotten peer starts over
+        watchdog.forget("bob");
+        assert!(!watchdog.sent("bob", at(20)));
+    }
+}
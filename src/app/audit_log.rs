@@ -0,0 +1,459 @@
+use anyhow::{anyhow, Result};
+use hmac::{Hmac, Mac};
+use rand::{thread_rng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use crate::app::events::SessionEvent;
+use crate::app::secrets::SecretStore;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Name the signing key is filed under in the secret store
+const KEY_SECRET: &str = "audit_log_key";
+
+/// The key audit logs are signed with, made and filed in `store` on first use
+///
+/// Kept away from the log, so whoever can edit the log can't sign what
+/// they put in it.
+pub fn signing_key(store: &dyn SecretStore) -> Result<[u8; 32]> {
+    if let Some(encoded) = store.get(KEY_SECRET)? {
+        return base64::decode(encoded)?
+            .try_into()
+            .map_err(|_| anyhow!("The audit log key is damaged"));
+    }
+    let mut key = [0u8; 32];
+    thread_rng().fill_bytes(&mut key);
+    store.set(KEY_SECRET, &base64::encode(key))?;
+    Ok(key)
+}
+
+/// A room event worth recording for later review
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AuditEvent {
+    /// A session was created by the local user
+    SessionCreated { session_id: String },
+    /// A peer joined the session
+    PeerJoined { peer_id: String, name: String },
+    /// A peer left the session
+    PeerLeft { peer_id: String },
+    /// A peer was removed from the session by the host
+    PeerKicked { peer_id: String, by: String },
+    /// The session host changed
+    HostChanged { peer_id: String },
+    /// Many packets from a peer failed authentication or were replayed
+    PossibleTampering { peer_id: String },
+    /// The host muted everyone else, or let everyone speak again
+    MeetingModeChanged { enabled: bool },
+    /// The host let a muted peer speak
+    SpeakGranted { peer_id: String },
+    /// A peer started or stopped recording the room
+    RecordingChanged { peer_id: String, recording: bool },
+    /// A peer asking to join was turned away by do-not-disturb or the host
+    JoinDenied { peer_id: String },
+}
+
+impl AuditEvent {
+    /// Gets the audit record for a session event, if it is one worth keeping
+    pub fn from_session_event(event: &SessionEvent) -> Option<Self> {
+        match event {
+            SessionEvent::PeerJoined { peer_id, name } => Some(AuditEvent::PeerJoined {
+                peer_id: peer_id.clone(),
+                name: name.clone(),
+            }),
+            SessionEvent::PeerLeft { peer_id } => Some(AuditEvent::PeerLeft {
+                peer_id: peer_id.clone(),
+            }),
+            SessionEvent::HostChanged { peer_id } => Some(AuditEvent::HostChanged {
+                peer_id: peer_id.clone(),
+            }),
+            SessionEvent::PossibleTampering { peer_id } => Some(AuditEvent::PossibleTampering {
+                peer_id: peer_id.clone(),
+            }),
+            SessionEvent::MeetingModeChanged { enabled } => {
+                Some(AuditEvent::MeetingModeChanged { enabled: *enabled })
+            }
+            SessionEvent::SpeakGranted { peer_id, .. } => Some(AuditEvent::SpeakGranted {
+                peer_id: peer_id.clone(),
+            }),
+            SessionEvent::RecordingChanged {
+                peer_id, recording, ..
+            } => Some(AuditEvent::RecordingChanged {
+                peer_id: peer_id.clone(),
+                recording: *recording,
+            }),
+            SessionEvent::StreamEnded { .. }
+            | SessionEvent::HandRaised { .. }
+            | SessionEvent::JoinRequested { .. }
+            | SessionEvent::JoinQueueUpdated { .. }
+            | SessionEvent::ConnectionIssue { .. }
+            | SessionEvent::ConnectionAttempt { .. }
+            | SessionEvent::HandshakeTimedOut { .. }
+            | SessionEvent::AudioLevel { .. }
+            | SessionEvent::PositionChanged { .. }
+            | SessionEvent::PresenceChanged { .. }
+            | SessionEvent::LayoutChanged { .. }
+            | SessionEvent::TimeWarning { .. }
+            | SessionEvent::TimeLimitReached => None,
+        }
+    }
+}
+
+/// A single entry in the audit log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Position of the entry in the log, starting at 0
+    pub index: u64,
+    /// Unix timestamp (seconds) when the entry was recorded
+    pub timestamp: u64,
+    /// The recorded event
+    pub event: AuditEvent,
+    /// HMAC over this entry and the previous entry's MAC (base64)
+    pub mac: String,
+}
+
+/// Where a file-backed log's chain ended when it was last written
+///
+/// Kept beside the log and sealed with the log's key, so entries cut off
+/// the end of the log are missed too.
+#[derive(Debug, Serialize, Deserialize)]
+struct Anchor {
+    /// Number of entries in the log
+    entries: u64,
+    /// MAC of the last of them (base64)
+    mac: String,
+    /// HMAC over the two fields above (base64)
+    seal: String,
+}
+
+/// Append-only log of room events with HMAC-chained entries
+///
+/// Each entry's MAC covers the previous entry's MAC, so removing, reordering
+/// or editing any entry breaks verification of everything after it. A
+/// file-backed log also keeps a sealed anchor to the end of the chain, so
+/// removing the last entries does too.
+pub struct AuditLog {
+    key: [u8; 32],
+    entries: Vec<AuditEntry>,
+    path: Option<PathBuf>,
+}
+
+impl AuditLog {
+    /// Create an in-memory audit log
+    pub fn new(key: [u8; 32]) -> Self {
+        Self {
+            key,
+            entries: Vec::new(),
+            path: None,
+        }
+    }
+
+    /// Open a file-backed audit log, loading any existing entries
+    ///
+    /// Fails if the existing entries do not verify under `key`.
+    pub fn open<P: AsRef<Path>>(path: P, key: [u8; 32]) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut entries = Vec::new();
+
+        if path.exists() {
+            let reader = BufReader::new(File::open(&path)?);
+            for line in reader.lines() {
+                let line = line?;
+                if !line.trim().is_empty() {
+                    entries.push(serde_json::from_str(&line)?);
+                }
+            }
+        }
+
+        let log = Self {
+            key,
+            entries,
+            path: Some(path),
+        };
+        log.verify()?;
+
+        Ok(log)
+    }
+
+    /// File the log is kept in, if it isn't in memory only
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Get all recorded entries
+    pub fn entries(&self) -> &[AuditEntry] {
+        &self.entries
+    }
+
+    /// Record an event, appending it to the backing file if there is one
+    pub fn record(&mut self, event: AuditEvent) -> Result<&AuditEntry> {
+        let index = self.entries.len() as u64;
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let previous_mac = self.entries.last().map(|e| e.mac.as_str()).unwrap_or("");
+        let mac = self.compute_mac(previous_mac, index, timestamp, &event)?;
+
+        let entry = AuditEntry {
+            index,
+            timestamp,
+            event,
+            mac,
+        };
+
+        if let Some(path) = &self.path {
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+            self.write_anchor(path, index + 1, &entry.mac)?;
+        }
+
+        self.entries.push(entry);
+        Ok(self.entries.last().unwrap())
+    }
+
+    /// Verify the MAC chain over all entries, and that none are missing from its end
+    pub fn verify(&self) -> Result<()> {
+        self.verify_chain()?;
+        match &self.path {
+            Some(path) => self.verify_anchor(path),
+            None => Ok(()),
+        }
+    }
+
+    fn verify_chain(&self) -> Result<()> {
+        let mut previous_mac = "";
+
+        for (i, entry) in self.entries.iter().enumerate() {
+            if entry.index != i as u64 {
+                return Err(anyhow!("Audit log entry {} is out of sequence", i));
+            }
+
+            let expected =
+                self.compute_mac(previous_mac, entry.index, entry.timestamp, &entry.event)?;
+            if expected != entry.mac {
+                return Err(anyhow!("Audit log entry {} failed verification", i));
+            }
+
+            previous_mac = &entry.mac;
+        }
+
+        Ok(())
+    }
+
+    /// Checks the chain reaches at least as far as its anchor
+    ///
+    /// Entries past the anchor are allowed: they were appended but the
+    /// anchor wasn't moved on before we stopped.
+    fn verify_anchor(&self, path: &Path) -> Result<()> {
+        let anchor_path = anchor_path(path);
+        let anchor: Anchor = match fs::read_to_string(&anchor_path) {
+            Ok(content) => serde_json::from_str(&content)?,
+            Err(_) if self.entries.is_empty() => return Ok(()),
+            Err(_) => return Err(anyhow!("The audit log's anchor is missing")),
+        };
+        if anchor.seal != self.seal_anchor(anchor.entries, &anchor.mac) {
+            return Err(anyhow!("The audit log's anchor failed verification"));
+        }
+
+        let reached = match anchor.entries.checked_sub(1) {
+            Some(last) => self
+                .entries
+                .get(last as usize)
+                .is_some_and(|entry| entry.mac == anchor.mac),
+            None => true,
+        };
+        if !reached {
+            return Err(anyhow!(
+                "The audit log ends before its last {} entries",
+                anchor
+                    .entries
+                    .saturating_sub(self.entries.len() as u64)
+                    .max(1)
+            ));
+        }
+        Ok(())
+    }
+
+    /// Moves the anchor to the end of the chain, replacing the old one whole
+    fn write_anchor(&self, path: &Path, entries: u64, mac: &str) -> Result<()> {
+        let anchor = Anchor {
+            entries,
+            mac: mac.to_string(),
+            seal: self.seal_anchor(entries, mac),
+        };
+        let anchor_path = anchor_path(path);
+        let mut partial = anchor_path.clone().into_os_string();
+        partial.push(".tmp");
+        fs::write(&partial, serde_json::to_string(&anchor)?)?;
+        fs::rename(&partial, &anchor_path)?;
+        Ok(())
+    }
+
+    fn seal_anchor(&self, entries: u64, mac: &str) -> String {
+        let mut seal = HmacSha256::new_from_slice(&self.key).expect("HMAC accepts any key length");
+        seal.update(b"anchor");
+        seal.update(&entries.to_be_bytes());
+        seal.update(mac.as_bytes());
+        base64::encode(seal.finalize().into_bytes())
+    }
+
+    /// Export all entries as a JSON array
+    pub fn export<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(&self.entries)?)?;
+        Ok(())
+    }
+
+    fn compute_mac(
+        &self,
+        previous_mac: &str,
+        index: u64,
+        timestamp: u64,
+        event: &AuditEvent,
+    ) -> Result<String> {
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC accepts any key length");
+        mac.update(previous_mac.as_bytes());
+        mac.update(&index.to_be_bytes());
+        mac.update(&timestamp.to_be_bytes());
+        mac.update(&serde_json::to_vec(event)?);
+
+        Ok(base64::encode(mac.finalize().into_bytes()))
+    }
+}
+
+/// The anchor file kept beside the log at `path`
+fn anchor_path(path: &Path) -> PathBuf {
+    let mut anchor = path.as_os_str().to_owned();
+    anchor.push(".anchor");
+    PathBuf::from(anchor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::secrets::EncryptedFileStore;
+
+    #[test]
+    fn test_record_and_verify() {
+        let mut log = AuditLog::new([4u8; 32]);
+        log.record(AuditEvent::SessionCreated {
+            session_id: "s1".to_string(),
+        })
+        .unwrap();
+        log.record(AuditEvent::PeerJoined {
+            peer_id: "p1".to_string(),
+            name: "Alice".to_string(),
+        })
+        .unwrap();
+
+        assert_eq!(log.entries().len(), 2);
+        assert!(log.verify().is_ok());
+    }
+
+    #[test]
+    fn test_tampering_is_detected() {
+        let mut log = AuditLog::new([4u8; 32]);
+        log.record(AuditEvent::PeerJoined {
+            peer_id: "p1".to_string(),
+            name: "Alice".to_string(),
+        })
+        .unwrap();
+        log.record(AuditEvent::PeerLeft {
+            peer_id: "p1".to_string(),
+        })
+        .unwrap();
+
+        log.entries[0].event = AuditEvent::PeerJoined {
+            peer_id: "p1".to_string(),
+            name: "Mallory".to_string(),
+        };
+        assert!(log.verify().is_err());
+    }
+
+    #[test]
+    fn test_file_backed_log_reopens() {
+        let path =
+            std::env::temp_dir().join(format!("resonance-audit-reopen-{}.log", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(anchor_path(&path));
+
+        {
+            let mut log = AuditLog::open(&path, [6u8; 32]).unwrap();
+            log.record(AuditEvent::HostChanged {
+                peer_id: "p2".to_string(),
+            })
+            .unwrap();
+        }
+
+        let log = AuditLog::open(&path, [6u8; 32]).unwrap();
+        assert_eq!(log.entries().len(), 1);
+
+        // The wrong key cannot open the log
+        assert!(AuditLog::open(&path, [7u8; 32]).is_err());
+
+        fs::remove_file(anchor_path(&path)).unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_truncation_is_detected() {
+        let path = std::env::temp_dir().join(format!(
+            "resonance-audit-truncation-{}.log",
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(anchor_path(&path));
+
+        let mut log = AuditLog::open(&path, [8u8; 32]).unwrap();
+        for peer_id in ["p1", "p2", "p3"] {
+            log.record(AuditEvent::PeerLeft {
+                peer_id: peer_id.to_string(),
+            })
+            .unwrap();
+        }
+        drop(log);
+        let content = fs::read_to_string(&path).unwrap();
+        let anchor = fs::read_to_string(anchor_path(&path)).unwrap();
+
+        // Cutting off the last entry leaves a chain that verifies by itself
+        let truncated: Vec<&str> = content.lines().take(2).collect();
+        fs::write(&path, truncated.join("\n") + "\n").unwrap();
+        assert!(AuditLog::open(&path, [8u8; 32]).is_err());
+
+        // So does cutting it off along with the anchor
+        fs::remove_file(anchor_path(&path)).unwrap();
+        assert!(AuditLog::open(&path, [8u8; 32]).is_err());
+
+        // And the anchor can't be moved back by whoever lacks the key
+        let mut forged: Anchor = serde_json::from_str(&anchor).unwrap();
+        forged.entries = 2;
+        forged.mac = serde_json::from_str::<AuditEntry>(truncated[1])
+            .unwrap()
+            .mac;
+        fs::write(anchor_path(&path), serde_json::to_string(&forged).unwrap()).unwrap();
+        assert!(AuditLog::open(&path, [8u8; 32]).is_err());
+
+        // The whole log still opens
+        fs::write(&path, content).unwrap();
+        fs::write(anchor_path(&path), anchor).unwrap();
+        assert_eq!(AuditLog::open(&path, [8u8; 32]).unwrap().entries().len(), 3);
+
+        fs::remove_file(anchor_path(&path)).unwrap();
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_signing_key_is_made_once() {
+        let directory = std::env::temp_dir().join("resonance-audit-key-test");
+        let _ = fs::remove_dir_all(&directory);
+        let store = EncryptedFileStore::new(&directory);
+
+        let key = signing_key(&store).unwrap();
+        assert_eq!(signing_key(&store).unwrap(), key);
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
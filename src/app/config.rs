@@ -1,5 +1,15 @@
 use std::str::FromStr;
 use std::fmt;
+use std::path::PathBuf;
+use log::LevelFilter;
+use crate::ui::accessibility::ContrastRatio;
+use crate::ui::i18n::Locale;
+use crate::app::aliases::PeerAliases;
+use crate::app::contacts::ContactList;
+use crate::app::dnd::DndPolicy;
+use crate::app::mixing::MixingTopology;
+use crate::network::{BandwidthCaps, BindAddress, IceServerList, Proxy, MtuConfig, NonceStrategy, RetrySettings, MAX_FRAMES_PER_BUNDLE, MIN_DATAGRAM_SIZE};
+use crate::audio::{AudioCodec, ChannelMap, CodecSettings, DeviceCalibrations, DeviceCapabilities, DeviceFingerprint, FrameDuration, HoldSource, MotionSettings, OutputRouting, SpatialLayout, StreamRole, UiSoundSettings};
 
 /// Audio quality settings for the application
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -16,6 +26,131 @@ pub struct Config {
     pub username: String,
     pub input_device: Option<String>,
     pub output_device: Option<String>,
+    /// Recognises the chosen microphone again if the system renames it
+    pub input_fingerprint: Option<DeviceFingerprint>,
+    /// Recognises the chosen speakers again if the system renames them
+    pub output_fingerprint: Option<DeviceFingerprint>,
+    /// What our audio streams are tagged as, so the system can duck other sounds during calls
+    pub stream_role: StreamRole,
+    /// Use the Windows "communications device" rather than the default one
+    pub communications_device: bool,
+    /// Stand the likeliest microphone in for a missing one, rather than the first listed
+    pub auto_microphone: bool,
+    /// Open audio devices at startup so joining a session is faster
+    pub prewarm_audio: bool,
+    /// Directory for log files; a temp directory is used when unset
+    pub log_dir: Option<String>,
+    /// Default log level
+    pub log_level: LevelFilter,
+    /// Per-module log level overrides
+    pub log_module_levels: Vec<(String, LevelFilter)>,
+    /// Rotate the log file after it reaches this size
+    pub log_max_size_kb: u64,
+    /// Rotate the log file after it has been open this long
+    pub log_rotate_hours: u64,
+    /// Number of rotated log files to keep
+    pub log_max_files: usize,
+    /// Write a diagnostic bundle when the app crashes
+    pub crash_reports: bool,
+    /// Language for UI strings
+    pub locale: Locale,
+    /// External speech-to-text command used to transcribe participants
+    pub transcription_command: Option<String>,
+    /// File that transcript lines are appended to
+    pub transcript_file: Option<String>,
+    /// Audio routing for rooms we host
+    pub mixing_topology: MixingTopology,
+    /// Forward a port with UPnP/NAT-PMP when hosting
+    pub port_mapping: bool,
+    /// Include LAN addresses in copied join links
+    pub share_lan_addresses: bool,
+    /// Secondary output devices for the room mix or single participants
+    pub output_routes: OutputRouting,
+    /// Keep the microphone mute in step with the system's (mic-mute keys, volume controls)
+    pub follow_system_mute: bool,
+    /// End rooms we host after this many minutes
+    pub session_time_limit_minutes: Option<u64>,
+    /// Short local sounds for session events, and their volume
+    pub ui_sounds: UiSoundSettings,
+    /// Sent instead of the microphone in "be right back" mode: none, tone or a clip's path
+    pub brb_audio: HoldSource,
+    /// How participants are seated in rooms we host
+    pub spatial_layout: SpatialLayout,
+    /// Doppler and motion effects for moving participants, and their quality
+    pub motion_effects: MotionSettings,
+    /// Gain, noise gate and AGC measured for each input device
+    pub calibrations: DeviceCalibrations,
+    /// Whether each peer's path is probed for its largest datagram, and the most to try
+    pub path_mtu: MtuConfig,
+    /// Audio frames packed into each datagram; more means fewer packets but more latency
+    pub audio_frames_per_packet: usize,
+    /// Whether silence is sent as comfort noise descriptors rather than frames, to peers that support it
+    pub silence_suppression: bool,
+    /// Whether we hear ourselves, quietly, as peers do: after processing and the codec
+    pub network_monitor: bool,
+    /// Our own names for peers, by public key, shown instead of the names they send
+    pub peer_aliases: PeerAliases,
+    /// Peers we saved, with where and when we last saw them
+    pub contacts: ContactList,
+    /// Program run to deliver call invites, given the contact, their key, our name and the link
+    pub invite_command: Option<String>,
+    /// Hold back join requests and notification sounds
+    pub do_not_disturb: bool,
+    /// What happens to join requests under do-not-disturb: deny, deny:<message> or queue
+    pub dnd_policy: DndPolicy,
+    /// Most the room may upload and download, in kilobits per second
+    pub bandwidth_caps: BandwidthCaps,
+    /// Codec we'd like to send audio with (opus, adpcm or pcm) and the Opus bitrate
+    pub codec: CodecSettings,
+    /// Audio per frame in rooms we create, 10, 20 or 40 ms; shorter is less delay but more bandwidth
+    pub frame_duration: FrameDuration,
+    /// http:// URL room events are posted to in headless mode
+    pub webhook_url: Option<String>,
+    /// Key webhook posts are signed with, so the receiver can check they're ours
+    pub webhook_secret: Option<String>,
+    /// http:// URL of a self-hosted shortener for join links; none shares them in full
+    pub link_shortener_url: Option<String>,
+    /// Percent of a core audio processing may use before quality is lowered; `None` never lowers it
+    pub cpu_load_limit_percent: Option<u32>,
+    /// STUN and TURN servers to try, first to last; none for the built-in STUN servers
+    pub ice_servers: IceServerList,
+    /// Interface name or local address our sockets are bound to, or auto for any
+    pub bind_address: BindAddress,
+    /// How signaling connections over TCP are made: direct or socks5://[user:password@]host:port
+    pub proxy: Proxy,
+    /// How connecting to the host and to peers is retried, with overrides per operation
+    pub retry: RetrySettings,
+    /// How the mix is laid onto output channels: stereo, or mono, swap and a pair like 3+4
+    pub output_channels: ChannelMap,
+    /// Level other apps' audio is shared into the room at, in percent; `None` doesn't share it
+    pub system_audio_percent: Option<u32>,
+    /// Whether guests' recordings in rooms we host wait for us to acknowledge them
+    pub recording_consent: bool,
+    /// Trade quality for bandwidth: a lower bitrate, fuller packets and plain panning
+    pub low_bandwidth: bool,
+    /// Have everyone play our audio at the same moment while sharing system
+    /// audio, so music or a video is heard together
+    pub sync_shared_audio: bool,
+    /// Show who's speaking in reverse video with a label, not just in green
+    pub large_speaking_indicators: bool,
+    /// Flash the screen when someone asks to join
+    pub flash_join_requests: bool,
+    /// Contrast participant names and notifications are raised to, from 1 to 21; `None` leaves colours alone
+    pub min_contrast: Option<ContrastRatio>,
+    /// Keep the machine from sleeping or dimming the screen while in a room
+    pub keep_awake: bool,
+    /// Receive buffer asked of our UDP sockets, from 8 to 65536 KiB; `None` leaves the OS default
+    pub udp_receive_buffer_kb: Option<u32>,
+    /// Send buffer asked of our UDP sockets, from 8 to 65536 KiB; `None` leaves the OS default
+    pub udp_send_buffer_kb: Option<u32>,
+    /// File room events are kept in, each signed to show tampering; `None` keeps no record
+    pub audit_log_file: Option<String>,
+    /// Ratchet message keys and agree fresh ones every few minutes, with peers that support it
+    pub forward_secrecy: bool,
+    /// How nonces are made for the messages we encrypt
+    pub nonce_strategy: NonceStrategy,
+    /// How long the microphone may send nothing before its stream is reopened, from 100 to 60000 ms
+    pub capture_stall_timeout_ms: u32,
 }
 
 impl Default for Config {
@@ -25,26 +160,306 @@ impl Default for Config {
             username: "User".to_string(),
             input_device: None,
             output_device: None,
+            input_fingerprint: None,
+            output_fingerprint: None,
+            stream_role: StreamRole::Communication,
+            communications_device: false,
+            auto_microphone: true,
+            prewarm_audio: false,
+            log_dir: None,
+            log_level: LevelFilter::Info,
+            log_module_levels: Vec::new(),
+            log_max_size_kb: 1024,
+            log_rotate_hours: 24,
+            log_max_files: 5,
+            crash_reports: false,
+            locale: Locale::English,
+            transcription_command: None,
+            transcript_file: None,
+            mixing_topology: MixingTopology::FullMesh,
+            port_mapping: false,
+            share_lan_addresses: false,
+            output_routes: OutputRouting::default(),
+            follow_system_mute: true,
+            session_time_limit_minutes: None,
+            ui_sounds: UiSoundSettings::default(),
+            brb_audio: HoldSource::default(),
+            spatial_layout: SpatialLayout::default(),
+            motion_effects: MotionSettings::default(),
+            calibrations: DeviceCalibrations::default(),
+            path_mtu: MtuConfig::default(),
+            audio_frames_per_packet: 1,
+            silence_suppression: true,
+            network_monitor: false,
+            peer_aliases: PeerAliases::default(),
+            contacts: ContactList::default(),
+            invite_command: None,
+            do_not_disturb: false,
+            dnd_policy: DndPolicy::default(),
+            bandwidth_caps: BandwidthCaps::default(),
+            codec: CodecSettings::default(),
+            frame_duration: FrameDuration::default(),
+            webhook_url: None,
+            link_shortener_url: None,
+            webhook_secret: None,
+            cpu_load_limit_percent: Some(80),
+            ice_servers: IceServerList::default(),
+            bind_address: BindAddress::Auto,
+            proxy: Proxy::Direct,
+            retry: RetrySettings::default(),
+            output_channels: ChannelMap::default(),
+            system_audio_percent: None,
+            recording_consent: true,
+            low_bandwidth: false,
+            sync_shared_audio: false,
+            large_speaking_indicators: false,
+            flash_join_requests: false,
+            min_contrast: None,
+            keep_awake: true,
+            udp_receive_buffer_kb: None,
+            udp_send_buffer_kb: None,
+            audit_log_file: None,
+            forward_secrecy: true,
+            nonce_strategy: NonceStrategy::default(),
+            capture_stall_timeout_ms: 2000,
         }
     }
 }
 
-impl Config {
-    /// Serializes the configuration to a string
-    pub fn to_string(&self) -> String {
+impl fmt::Display for Config {
+    /// Serializes the configuration, in the form `from_str` reads
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let input_device = self.input_device.as_deref().unwrap_or("none");
         let output_device = self.output_device.as_deref().unwrap_or("none");
+        let input_fingerprint = self.input_fingerprint.as_ref()
+            .map_or("none".to_string(), |fingerprint| fingerprint.to_string());
+        let output_fingerprint = self.output_fingerprint.as_ref()
+            .map_or("none".to_string(), |fingerprint| fingerprint.to_string());
+        let log_dir = self.log_dir.as_deref().unwrap_or("none");
+        let transcription_command = self.transcription_command.as_deref().unwrap_or("none");
+        let transcript_file = self.transcript_file.as_deref().unwrap_or("none");
+        let audit_log_file = self.audit_log_file.as_deref().unwrap_or("none");
+        let session_time_limit_minutes = self.session_time_limit_minutes
+            .map_or("none".to_string(), |minutes| minutes.to_string());
+        let upload_cap_kbps = self.bandwidth_caps.upload_kbps
+            .map_or("none".to_string(), |kbps| kbps.to_string());
+        let download_cap_kbps = self.bandwidth_caps.download_kbps
+            .map_or("none".to_string(), |kbps| kbps.to_string());
+        let cpu_load_limit_percent = self.cpu_load_limit_percent
+            .map_or("none".to_string(), |percent| percent.to_string());
+        let system_audio_percent = self.system_audio_percent
+            .map_or("none".to_string(), |percent| percent.to_string());
+        let min_contrast = self.min_contrast
+            .map_or("none".to_string(), |ratio| ratio.to_string());
+        let udp_receive_buffer_kb = self.udp_receive_buffer_kb
+            .map_or("none".to_string(), |kb| kb.to_string());
+        let udp_send_buffer_kb = self.udp_send_buffer_kb
+            .map_or("none".to_string(), |kb| kb.to_string());
+        let calibrations = if self.calibrations.is_empty() {
+            "none".to_string()
+        } else {
+            self.calibrations.to_string()
+        };
+        let peer_aliases = if self.peer_aliases.is_empty() {
+            "none".to_string()
+        } else {
+            self.peer_aliases.to_string()
+        };
+        let contacts = if self.contacts.is_empty() {
+            "none".to_string()
+        } else {
+            self.contacts.to_string()
+        };
+        let invite_command = self.invite_command.as_deref().unwrap_or("none");
+        let webhook_url = self.webhook_url.as_deref().unwrap_or("none");
+        let webhook_secret = self.webhook_secret.as_deref().unwrap_or("none");
+        let link_shortener_url = self.link_shortener_url.as_deref().unwrap_or("none");
+        let ice_servers = if self.ice_servers.is_empty() {
+            "none".to_string()
+        } else {
+            self.ice_servers.to_string()
+        };
+        let output_routes = if self.output_routes.is_empty() {
+            "none".to_string()
+        } else {
+            self.output_routes.to_string()
+        };
+        let log_module_levels = if self.log_module_levels.is_empty() {
+            "none".to_string()
+        } else {
+            self.log_module_levels.iter()
+                .map(|(module, level)| format!("{}={}", module, level))
+                .collect::<Vec<_>>()
+                .join(",")
+        };
         
-        format!(
-            "audio_quality={:?}\nusername={}\ninput_device={}\noutput_device={}", 
+        write!(
+            f,
+            "audio_quality={:?}\nusername={}\ninput_device={}\noutput_device={}\nprewarm_audio={}\nlog_dir={}\nlog_level={}\nlog_module_levels={}\nlog_max_size_kb={}\nlog_rotate_hours={}\nlog_max_files={}\ncrash_reports={}\nlocale={}\ntranscription_command={}\ntranscript_file={}\nmixing_topology={}\nport_mapping={}\nshare_lan_addresses={}\noutput_routes={}\nfollow_system_mute={}\nsession_time_limit_minutes={}\nui_sounds={}\nui_sound_volume={}\nbrb_audio={}\nspatial_layout={}\nmotion_effects={}\nmotion_effects_quality={}\ncalibrations={}\npath_mtu_discovery={}\nmax_datagram_size={}\naudio_frames_per_packet={}\nsilence_suppression={}\npeer_aliases={}\ncontacts={}\ninvite_command={}\ndo_not_disturb={}\ndnd_policy={}\nupload_cap_kbps={}\ndownload_cap_kbps={}\naudio_codec={}\naudio_bitrate_kbps={}\ncpu_load_limit_percent={}\nice_servers={}\nbind_address={}\nproxy={}\ninput_device_fingerprint={}\noutput_device_fingerprint={}\nstream_role={}\ncommunications_device={}\nframe_duration_ms={}\nwebhook_url={}\nwebhook_secret={}\nnetwork_monitor={}\nlink_shortener_url={}\nauto_microphone={}\nretry_policy={}\nretry_overrides={}\noutput_channels={}\nsystem_audio_percent={}\nrecording_consent={}\nlow_bandwidth={}\nsync_shared_audio={}\nlarge_speaking_indicators={}\nflash_join_requests={}\nmin_contrast={}\nkeep_awake={}\nudp_receive_buffer_kb={}\nudp_send_buffer_kb={}\naudit_log_file={}\nforward_secrecy={}\nnonce_strategy={}\ncapture_stall_timeout_ms={}", 
             self.audio_quality, 
             self.username,
             input_device,
-            output_device
+            output_device,
+            self.prewarm_audio,
+            log_dir,
+            self.log_level,
+            log_module_levels,
+            self.log_max_size_kb,
+            self.log_rotate_hours,
+            self.log_max_files,
+            self.crash_reports,
+            self.locale.code(),
+            transcription_command,
+            transcript_file,
+            self.mixing_topology.code(),
+            self.port_mapping,
+            self.share_lan_addresses,
+            output_routes,
+            self.follow_system_mute,
+            session_time_limit_minutes,
+            self.ui_sounds,
+            self.ui_sounds.volume,
+            self.brb_audio,
+            self.spatial_layout.code(),
+            self.motion_effects.enabled,
+            self.motion_effects.quality,
+            calibrations,
+            self.path_mtu.discovery,
+            self.path_mtu.max_datagram,
+            self.audio_frames_per_packet,
+            self.silence_suppression,
+            peer_aliases,
+            contacts,
+            invite_command,
+            self.do_not_disturb,
+            self.dnd_policy,
+            upload_cap_kbps,
+            download_cap_kbps,
+            self.codec.codec,
+            self.codec.bitrate_kbps,
+            cpu_load_limit_percent,
+            ice_servers,
+            self.bind_address,
+            self.proxy,
+            input_fingerprint,
+            output_fingerprint,
+            self.stream_role,
+            self.communications_device,
+            self.frame_duration,
+            webhook_url,
+            webhook_secret,
+            self.network_monitor,
+            link_shortener_url,
+            self.auto_microphone,
+            self.retry.default,
+            self.retry.overrides,
+            self.output_channels,
+            system_audio_percent,
+            self.recording_consent,
+            self.low_bandwidth,
+            self.sync_shared_audio,
+            self.large_speaking_indicators,
+            self.flash_join_requests,
+            min_contrast,
+            self.keep_awake,
+            udp_receive_buffer_kb,
+            udp_send_buffer_kb,
+            audit_log_file,
+            self.forward_secrecy,
+            self.nonce_strategy,
+            self.capture_stall_timeout_ms
         )
     }
 }
 
+impl Config {
+    /// Remembers which devices the device settings point at, so they're found again if renamed
+    pub fn remember_devices(&mut self, devices: &[DeviceCapabilities]) {
+        let fingerprint = |is_input: bool, id: &Option<String>| {
+            let id = id.as_ref()?;
+            devices.iter()
+                .find(|capabilities| capabilities.device.is_input == is_input && capabilities.device.id == *id)
+                .map(|capabilities| capabilities.fingerprint.clone())
+        };
+        let input = fingerprint(true, &self.input_device);
+        let output = fingerprint(false, &self.output_device);
+        // A device that isn't plugged in keeps the fingerprint it had
+        if input.is_some() || self.input_device.is_none() {
+            self.input_fingerprint = input;
+        }
+        if output.is_some() || self.output_device.is_none() {
+            self.output_fingerprint = output;
+        }
+    }
+
+    /// Points the device settings at whatever the remembered devices are called now
+    ///
+    /// Returns the remembered devices that couldn't be found. Their settings
+    /// are left alone, so they're used again once plugged back in.
+    pub fn match_devices(&mut self, devices: &[DeviceCapabilities]) -> Vec<DeviceFingerprint> {
+        let mut missing = Vec::new();
+        for (fingerprint, device) in [
+            (&self.input_fingerprint, &mut self.input_device),
+            (&self.output_fingerprint, &mut self.output_device),
+        ] {
+            let Some(fingerprint) = fingerprint else {
+                continue;
+            };
+            match fingerprint.find(devices) {
+                Some(found) if device.as_ref() != Some(&found.device.id) => {
+                    log::info!("{} is now called {}", fingerprint.name, found.device.id);
+                    *device = Some(found.device.id.clone());
+                },
+                Some(_) => {},
+                None => missing.push(fingerprint.clone()),
+            }
+        }
+        missing
+    }
+
+    /// Gets one key's value as it's written in the configuration file
+    pub fn value(&self, key: &str) -> Option<String> {
+        self.to_string().lines().find_map(|line| {
+            let (name, value) = line.split_once('=')?;
+            (name == key).then(|| value.to_string())
+        })
+    }
+
+    /// Sets one key from its configuration file form, checked as if the file were loaded
+    pub fn with_value(&self, key: &str, value: &str) -> Result<Config, ConfigParseError> {
+        if value.contains('\n') {
+            return Err(ConfigParseError {
+                message: format!("Invalid value for {}: {}", key, value)
+            });
+        }
+
+        let mut found = false;
+        let lines: Vec<String> = self.to_string().lines().map(|line| match line.split_once('=') {
+            Some((name, _)) if name == key => {
+                found = true;
+                format!("{}={}", key, value)
+            },
+            _ => line.to_string(),
+        }).collect();
+        if !found {
+            return Err(ConfigParseError {
+                message: format!("Unknown configuration key: {}", key)
+            });
+        }
+        lines.join("\n").parse()
+    }
+}
+
+/// Where settings are saved: `$XDG_CONFIG_HOME/resonance/config`, else under `~/.config`
+pub fn default_path() -> PathBuf {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_default()
+        .join("resonance")
+        .join("config")
+}
+
 // Custom error for configuration parsing
 #[derive(Debug)]
 pub struct ConfigParseError {
@@ -57,8 +472,27 @@ impl fmt::Display for ConfigParseError {
     }
 }
 
+impl ConfigParseError {
+    /// What was wrong, without the "Configuration error" prefix
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
 impl std::error::Error for ConfigParseError {}
 
+fn parse_log_level(value: &str) -> Result<LevelFilter, ConfigParseError> {
+    value.parse().map_err(|_| ConfigParseError {
+        message: format!("Unknown log level: {}", value)
+    })
+}
+
+fn parse_value<T: FromStr>(key: &str, value: &str) -> Result<T, ConfigParseError> {
+    value.parse().map_err(|_| ConfigParseError {
+        message: format!("Invalid value for {}: {}", key, value)
+    })
+}
+
 impl FromStr for Config {
     type Err = ConfigParseError;
 
@@ -98,6 +532,261 @@ impl FromStr for Config {
                 "output_device" => {
                     config.output_device = if value == "none" { None } else { Some(value.to_string()) };
                 },
+                "prewarm_audio" => {
+                    config.prewarm_audio = value.parse().map_err(|_| ConfigParseError {
+                        message: format!("Invalid value for prewarm_audio: {}", value)
+                    })?;
+                },
+                "log_dir" => {
+                    config.log_dir = if value == "none" { None } else { Some(value.to_string()) };
+                },
+                "log_level" => {
+                    config.log_level = parse_log_level(value)?;
+                },
+                "log_module_levels" => {
+                    config.log_module_levels = Vec::new();
+                    if value != "none" {
+                        for entry in value.split(',') {
+                            let (module, level) = entry.split_once('=').ok_or_else(|| ConfigParseError {
+                                message: format!("Invalid module log level: {}", entry)
+                            })?;
+                            config.log_module_levels.push((module.trim().to_string(), parse_log_level(level.trim())?));
+                        }
+                    }
+                },
+                "log_max_size_kb" => config.log_max_size_kb = parse_value(key, value)?,
+                "log_rotate_hours" => config.log_rotate_hours = parse_value(key, value)?,
+                "log_max_files" => config.log_max_files = parse_value(key, value)?,
+                "crash_reports" => config.crash_reports = parse_value(key, value)?,
+                "locale" => {
+                    config.locale = value.parse().map_err(|message| ConfigParseError { message })?;
+                },
+                "transcription_command" => {
+                    config.transcription_command = if value == "none" { None } else { Some(value.to_string()) };
+                },
+                "transcript_file" => {
+                    config.transcript_file = if value == "none" { None } else { Some(value.to_string()) };
+                },
+                "mixing_topology" => {
+                    config.mixing_topology = value.parse().map_err(|message| ConfigParseError { message })?;
+                },
+                "port_mapping" => config.port_mapping = parse_value(key, value)?,
+                "share_lan_addresses" => config.share_lan_addresses = parse_value(key, value)?,
+                "follow_system_mute" => config.follow_system_mute = parse_value(key, value)?,
+                "ui_sounds" => {
+                    let volume = config.ui_sounds.volume;
+                    config.ui_sounds = value.parse().map_err(|message| ConfigParseError { message })?;
+                    config.ui_sounds.volume = volume;
+                },
+                "ui_sound_volume" => {
+                    config.ui_sounds.volume = parse_value(key, value)?;
+                    if config.ui_sounds.volume > 100 {
+                        return Err(ConfigParseError {
+                            message: format!("Invalid value for {}: {}", key, value)
+                        });
+                    }
+                },
+                "brb_audio" => {
+                    config.brb_audio = value.parse().map_err(|message| ConfigParseError { message })?;
+                },
+                "motion_effects" => config.motion_effects.enabled = parse_value(key, value)?,
+                "motion_effects_quality" => {
+                    config.motion_effects.quality = value.parse().map_err(|message| ConfigParseError { message })?;
+                },
+                "calibrations" => {
+                    config.calibrations = if value == "none" {
+                        DeviceCalibrations::default()
+                    } else {
+                        value.parse().map_err(|message| ConfigParseError { message })?
+                    };
+                },
+                "path_mtu_discovery" => config.path_mtu.discovery = parse_value(key, value)?,
+                "max_datagram_size" => {
+                    config.path_mtu.max_datagram = parse_value(key, value)?;
+                    if config.path_mtu.max_datagram < MIN_DATAGRAM_SIZE {
+                        return Err(ConfigParseError {
+                            message: format!("Invalid value for {}: {}", key, value)
+                        });
+                    }
+                },
+                "audio_frames_per_packet" => {
+                    config.audio_frames_per_packet = parse_value(key, value)?;
+                    if !(1..=MAX_FRAMES_PER_BUNDLE).contains(&config.audio_frames_per_packet) {
+                        return Err(ConfigParseError {
+                            message: format!("Invalid value for {}: {}", key, value)
+                        });
+                    }
+                },
+                "silence_suppression" => config.silence_suppression = parse_value(key, value)?,
+                "network_monitor" => config.network_monitor = parse_value(key, value)?,
+                "auto_microphone" => config.auto_microphone = parse_value(key, value)?,
+                "retry_policy" => config.retry.default = parse_value(key, value)?,
+                "retry_overrides" => config.retry.overrides = parse_value(key, value)?,
+                "output_channels" => config.output_channels = parse_value(key, value)?,
+                "system_audio_percent" => {
+                    config.system_audio_percent = if value == "none" { None } else { Some(parse_value(key, value)?) };
+                    if config.system_audio_percent.is_some_and(|percent| !(1..=100).contains(&percent)) {
+                        return Err(ConfigParseError {
+                            message: format!("Invalid value for {}: {}", key, value)
+                        });
+                    }
+                },
+                "recording_consent" => config.recording_consent = parse_value(key, value)?,
+                "low_bandwidth" => config.low_bandwidth = parse_value(key, value)?,
+                "sync_shared_audio" => config.sync_shared_audio = parse_value(key, value)?,
+                "large_speaking_indicators" => config.large_speaking_indicators = parse_value(key, value)?,
+                "flash_join_requests" => config.flash_join_requests = parse_value(key, value)?,
+                "keep_awake" => config.keep_awake = parse_value(key, value)?,
+                "udp_receive_buffer_kb" => {
+                    config.udp_receive_buffer_kb = if value == "none" { None } else { Some(parse_value(key, value)?) };
+                    if config.udp_receive_buffer_kb.is_some_and(|kb| !(8..=65536).contains(&kb)) {
+                        return Err(ConfigParseError {
+                            message: format!("Invalid value for {}: {}", key, value)
+                        });
+                    }
+                },
+                "udp_send_buffer_kb" => {
+                    config.udp_send_buffer_kb = if value == "none" { None } else { Some(parse_value(key, value)?) };
+                    if config.udp_send_buffer_kb.is_some_and(|kb| !(8..=65536).contains(&kb)) {
+                        return Err(ConfigParseError {
+                            message: format!("Invalid value for {}: {}", key, value)
+                        });
+                    }
+                },
+                "audit_log_file" => {
+                    config.audit_log_file = if value == "none" { None } else { Some(value.to_string()) };
+                },
+                "forward_secrecy" => config.forward_secrecy = parse_value(key, value)?,
+                "nonce_strategy" => {
+                    config.nonce_strategy = value.parse().map_err(|message| ConfigParseError { message })?;
+                },
+                "capture_stall_timeout_ms" => {
+                    config.capture_stall_timeout_ms = parse_value(key, value)?;
+                    if !(100..=60000).contains(&config.capture_stall_timeout_ms) {
+                        return Err(ConfigParseError {
+                            message: format!("Invalid value for {}: {}", key, value)
+                        });
+                    }
+                },
+                "min_contrast" => {
+                    config.min_contrast = if value == "none" {
+                        None
+                    } else {
+                        Some(value.parse().map_err(|message| ConfigParseError { message })?)
+                    };
+                },
+                "contacts" => {
+                    config.contacts = if value == "none" {
+                        ContactList::default()
+                    } else {
+                        value.parse().map_err(|message| ConfigParseError { message })?
+                    };
+                },
+                "do_not_disturb" => config.do_not_disturb = parse_value(key, value)?,
+                "dnd_policy" => {
+                    config.dnd_policy = value.parse().map_err(|message| ConfigParseError { message })?;
+                },
+                "invite_command" => {
+                    config.invite_command = if value == "none" { None } else { Some(value.to_string()) };
+                },
+                "webhook_url" => {
+                    if value != "none" && !value.starts_with("http://") {
+                        return Err(ConfigParseError {
+                            message: format!("Invalid value for {}: {} (only http:// URLs are supported)", key, value)
+                        });
+                    }
+                    config.webhook_url = if value == "none" { None } else { Some(value.to_string()) };
+                },
+                "link_shortener_url" => {
+                    if value != "none" && !value.starts_with("http://") {
+                        return Err(ConfigParseError {
+                            message: format!("Invalid value for {}: {} (only http:// URLs are supported)", key, value)
+                        });
+                    }
+                    config.link_shortener_url = if value == "none" { None } else { Some(value.to_string()) };
+                },
+                "webhook_secret" => {
+                    config.webhook_secret = if value == "none" { None } else { Some(value.to_string()) };
+                },
+                "peer_aliases" => {
+                    config.peer_aliases = if value == "none" {
+                        PeerAliases::default()
+                    } else {
+                        value.parse().map_err(|message| ConfigParseError { message })?
+                    };
+                },
+                "spatial_layout" => {
+                    config.spatial_layout = value.parse().map_err(|message| ConfigParseError { message })?;
+                },
+                "audio_codec" => {
+                    config.codec.codec = value.parse().map_err(|message| ConfigParseError { message })?;
+                },
+                "audio_bitrate_kbps" => {
+                    let settings: CodecSettings = format!("{}:{}", AudioCodec::Opus, value)
+                        .parse()
+                        .map_err(|message| ConfigParseError { message })?;
+                    config.codec.bitrate_kbps = settings.bitrate_kbps;
+                },
+                "cpu_load_limit_percent" => {
+                    config.cpu_load_limit_percent = if value == "none" { None } else { Some(parse_value(key, value)?) };
+                    if config.cpu_load_limit_percent.is_some_and(|percent| !(1..=100).contains(&percent)) {
+                        return Err(ConfigParseError {
+                            message: format!("Invalid value for {}: {}", key, value)
+                        });
+                    }
+                },
+                "stream_role" => {
+                    config.stream_role = value.parse().map_err(|message| ConfigParseError { message })?;
+                },
+                "communications_device" => {
+                    config.communications_device = parse_value(key, value)?;
+                },
+                "frame_duration_ms" => {
+                    config.frame_duration = value.parse().map_err(|message| ConfigParseError { message })?;
+                },
+                "input_device_fingerprint" => {
+                    config.input_fingerprint = if value == "none" {
+                        None
+                    } else {
+                        Some(value.parse().map_err(|message| ConfigParseError { message })?)
+                    };
+                },
+                "output_device_fingerprint" => {
+                    config.output_fingerprint = if value == "none" {
+                        None
+                    } else {
+                        Some(value.parse().map_err(|message| ConfigParseError { message })?)
+                    };
+                },
+                "proxy" => {
+                    config.proxy = value.parse().map_err(|message| ConfigParseError { message })?;
+                },
+                "bind_address" => {
+                    config.bind_address = value.parse().map_err(|message| ConfigParseError { message })?;
+                },
+                "ice_servers" => {
+                    config.ice_servers = if value == "none" {
+                        IceServerList::default()
+                    } else {
+                        value.parse().map_err(|message| ConfigParseError { message })?
+                    };
+                },
+                "upload_cap_kbps" => {
+                    config.bandwidth_caps.upload_kbps = if value == "none" { None } else { Some(parse_value(key, value)?) };
+                },
+                "download_cap_kbps" => {
+                    config.bandwidth_caps.download_kbps = if value == "none" { None } else { Some(parse_value(key, value)?) };
+                },
+                "session_time_limit_minutes" => {
+                    config.session_time_limit_minutes = if value == "none" { None } else { Some(parse_value(key, value)?) };
+                },
+                "output_routes" => {
+                    config.output_routes = if value == "none" {
+                        OutputRouting::default()
+                    } else {
+                        value.parse().map_err(|message| ConfigParseError { message })?
+                    };
+                },
                 _ => return Err(ConfigParseError {
                     message: format!("Unknown configuration key: {}", key)
                 }),
@@ -111,6 +800,7 @@ impl FromStr for Config {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::audio::MotionQuality;
     
     #[test]
     fn test_default_config() {
@@ -128,13 +818,114 @@ mod tests {
     
     #[test]
     fn test_custom_config() {
-        let mut config = Config::default();
-        config.audio_quality = AudioQuality::High;
-        config.username = "TestUser".to_string();
-        config.input_device = Some("Microphone".to_string());
+        let mut config = Config {
+            audio_quality: AudioQuality::High,
+            username: "TestUser".to_string(),
+            input_device: Some("Microphone".to_string()),
+            prewarm_audio: true,
+            log_dir: Some("/var/log/resonance".to_string()),
+            log_level: LevelFilter::Warn,
+            log_module_levels: vec![
+                ("network".to_string(), LevelFilter::Debug),
+                ("audio::capture".to_string(), LevelFilter::Trace),
+            ],
+            log_max_files: 3,
+            crash_reports: true,
+            locale: Locale::Spanish,
+            transcription_command: Some("whisper-stream --model base.en".to_string()),
+            transcript_file: Some("/tmp/transcript.txt".to_string()),
+            mixing_topology: MixingTopology::HostMixed,
+            port_mapping: true,
+            share_lan_addresses: true,
+            output_routes: "mix=CABLE Input;peer:Alice=Headphones".parse().unwrap(),
+            follow_system_mute: false,
+            session_time_limit_minutes: Some(90),
+            ui_sounds: "join_requests,mute".parse().unwrap(),
+            brb_audio: HoldSource::Tone,
+            spatial_layout: SpatialLayout::PresenterFront,
+            motion_effects: MotionSettings { enabled: true, quality: MotionQuality::High },
+            calibrations: "default=2,0.02,0.1;USB Mic=0.5,0.004,0.1".parse().unwrap(),
+            path_mtu: MtuConfig { discovery: false, max_datagram: 1400 },
+            audio_frames_per_packet: 3,
+            silence_suppression: false,
+            network_monitor: true,
+            auto_microphone: false,
+            link_shortener_url: Some("http://127.0.0.1:8081/shorten".to_string()),
+            output_channels: "swap,3+4".parse().unwrap(),
+            system_audio_percent: Some(40),
+            recording_consent: false,
+            low_bandwidth: true,
+            sync_shared_audio: true,
+            large_speaking_indicators: true,
+            flash_join_requests: true,
+            min_contrast: Some("4.5".parse().unwrap()),
+            keep_awake: false,
+            udp_receive_buffer_kb: Some(4096),
+            udp_send_buffer_kb: None,
+            audit_log_file: Some("/tmp/resonance-audit.log".to_string()),
+            forward_secrecy: false,
+            nonce_strategy: NonceStrategy::Counter,
+            capture_stall_timeout_ms: 500,
+            invite_command: Some("notify-send --app-name=resonance".to_string()),
+            do_not_disturb: true,
+            dnd_policy: "deny:Back at 3pm".parse().unwrap(),
+            bandwidth_caps: BandwidthCaps { upload_kbps: Some(256), download_kbps: None },
+            codec: CodecSettings { codec: AudioCodec::Adpcm, bitrate_kbps: 64 },
+            cpu_load_limit_percent: None,
+            bind_address: "wg0".parse().unwrap(),
+            proxy: "socks5://127.0.0.1:9050".parse().unwrap(),
+            stream_role: StreamRole::Media,
+            communications_device: true,
+            frame_duration: FrameDuration::Ms40,
+            webhook_url: Some("http://127.0.0.1:8080/resonance".to_string()),
+            webhook_secret: Some("s3cret".to_string()),
+            input_fingerprint: Some("in/ALSA/1c9d44a5/USB Audio (2)".parse().unwrap()),
+            ice_servers: "stun:stun.example.org:3478;turn:alice:secret@turn.example.org:3478".parse().unwrap(),
+            ..Config::default()
+        };
+        config.ui_sounds.volume = 30;
+        config.retry.default = "5,250,4000,10".parse().unwrap();
+        config.retry.overrides = "join:8,1000,8000,20".parse().unwrap();
+        config.peer_aliases.set([7; 32], "Alice's laptop");
+        config.contacts.add([7; 32], "Alice");
+        config.contacts.record_seen(&[7; 32], "203.0.113.5:4000".parse().unwrap(), 1_700_000_000);
         
         let serialized = config.to_string();
         let deserialized = Config::from_str(&serialized).unwrap();
         assert_eq!(config, deserialized);
     }
+
+    #[test]
+    fn test_match_devices_after_rename() {
+        let device = |id: &str, channels: &[u16]| DeviceCapabilities {
+            device: crate::audio::AudioDevice {
+                id: id.to_string(),
+                name: id.to_string(),
+                is_input: true,
+            },
+            sample_rates: vec![48000],
+            default_sample_rate: Some(48000),
+            min_buffer_frames: None,
+            exclusive: crate::audio::ExclusiveMode::SharedOnly,
+            channel_counts: channels.to_vec(),
+            fingerprint: DeviceFingerprint::new(true, "ALSA", id, channels),
+        };
+
+        let mut config = Config {
+            input_device: Some("USB Audio".to_string()),
+            ..Config::default()
+        };
+        config.remember_devices(&[device("Built-in Mic", &[1]), device("USB Audio", &[1, 2])]);
+        assert_eq!(config.input_fingerprint.as_ref().map(|f| f.name.as_str()), Some("USB Audio"));
+
+        // Replugged, the system numbers it; the setting follows
+        let replugged = [device("Built-in Mic", &[1]), device("USB Audio (2)", &[1, 2])];
+        assert!(config.match_devices(&replugged).is_empty());
+        assert_eq!(config.input_device.as_deref(), Some("USB Audio (2)"));
+
+        // Unplugged, it's reported and the setting kept for when it's back
+        let missing = config.match_devices(&[device("Built-in Mic", &[1])]);
+        assert_eq!(missing.len(), 1);
+        assert_eq!(config.input_device.as_deref(), Some("USB Audio (2)"));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,283 @@
+use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+use crate::app::aliases::{key_from_hex, key_to_hex};
+
+/// How long after we last saw a contact its address is still worth trying
+///
+/// Roughly how long a NAT keeps an idle binding open.
+pub const REACHABLE_WINDOW_SECS: u64 = 10 * 60;
+
+/// A peer we saved, known by its public key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Contact {
+    pub key: [u8; 32],
+    pub name: String,
+    /// Where we last reached them, or a rendezvous address they gave us
+    pub address: Option<SocketAddr>,
+    /// Unix time in seconds when they were last in a session with us
+    pub last_seen: Option<u64>,
+}
+
+/// Whether a contact can be called at the address we know
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reachability {
+    /// In the session with us right now
+    InSession,
+    /// Seen recently enough that their last address should still work
+    Recent,
+    /// Not seen for a while, or we have no address for them
+    Unknown,
+}
+
+impl fmt::Display for Reachability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reachability::InSession => write!(f, "in_session"),
+            Reachability::Recent => write!(f, "recent"),
+            Reachability::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// Saved contacts, kept in the configuration
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContactList {
+    contacts: BTreeMap<[u8; 32], Contact>,
+}
+
+impl ContactList {
+    /// Saves a contact, or renames it if it's already saved
+    ///
+    /// Characters the config file can't hold are dropped from the name.
+    pub fn add(&mut self, key: [u8; 32], name: &str) {
+        let name: String = name
+            .chars()
+            .filter(|c| *c != ';' && !c.is_control())
+            .collect();
+        self.contacts
+            .entry(key)
+            .and_modify(|contact| contact.name = name.trim().to_string())
+            .or_insert_with(|| Contact {
+                key,
+                name: name.trim().to_string(),
+                address: None,
+                last_seen: None,
+            });
+    }
+
+    pub fn remove(&mut self, key: &[u8; 32]) -> Option<Contact> {
+        self.contacts.remove(key)
+    }
+
+    pub fn get(&self, key: &[u8; 32]) -> Option<&Contact> {
+        self.contacts.get(key)
+    }
+
+    /// Finds a contact by name, ignoring case, or by the start of its key in hex
+    pub fn find(&self, query: &str) -> Option<&Contact> {
+        let query = query.trim();
+        self.contacts
+            .values()
+            .find(|contact| contact.name.eq_ignore_ascii_case(query))
+            .or_else(|| {
+                let prefix = query.to_ascii_lowercase();
+                let mut matches = self.contacts.values().filter(|contact| {
+                    prefix.len() >= 4 && key_to_hex(&contact.key).starts_with(&prefix)
+                });
+                // An ambiguous prefix matches nobody
+                match (matches.next(), matches.next()) {
+                    (Some(contact), None) => Some(contact),
+                    _ => None,
+                }
+            })
+    }
+
+    /// Records that a contact was in a session with us, at this address
+    pub fn record_seen(&mut self, key: &[u8; 32], address: SocketAddr, now: u64) {
+        if let Some(contact) = self.contacts.get_mut(key) {
+            contact.address = Some(address);
+            contact.last_seen = Some(now);
+        }
+    }
+
+    /// Whether a contact can be called, given who is in our session
+    pub fn reachability(
+        &self,
+        contact: &Contact,
+        in_session: &[[u8; 32]],
+        now: u64,
+    ) -> Reachability {
+        if in_session.contains(&contact.key) {
+            return Reachability::InSession;
+        }
+        match (contact.address, contact.last_seen) {
+            (Some(_), Some(seen)) if now.saturating_sub(seen) <= REACHABLE_WINDOW_SECS => {
+                Reachability::Recent
+            }
+            _ => Reachability::Unknown,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Contact> {
+        self.contacts.values()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.contacts.is_empty()
+    }
+}
+
+impl fmt::Display for ContactList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries: Vec<String> = self
+            .contacts
+            .values()
+            .map(|contact| {
+                let address = contact
+                    .address
+                    .map_or("-".to_string(), |addr| addr.to_string());
+                let last_seen = contact
+                    .last_seen
+                    .map_or("-".to_string(), |at| at.to_string());
+                format!(
+                    "{}={}@{}@{}",
+                    key_to_hex(&contact.key),
+                    contact.name,
+                    address,
+                    last_seen
+                )
+            })
+            .collect();
+        write!(f, "{}", entries.join(";"))
+    }
+}
+
+impl FromStr for ContactList {
+    type Err = String;
+
+    /// Parses `key=name@address@last_seen;...`, with `-` for an unknown address or time
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut contacts = ContactList::default();
+        for entry in s.split(';').filter(|entry| !entry.trim().is_empty()) {
+            let invalid = || format!("Invalid contact: {}", entry);
+            let (key, rest) = entry.split_once('=').ok_or_else(invalid)?;
+            let key = key_from_hex(key.trim()).ok_or_else(invalid)?;
+
+            // Names may contain '@', so split from the right
+            let mut parts = rest.rsplitn(3, '@');
+            let last_seen = parts.next().ok_or_else(invalid)?.trim();
+            let address = parts.next().ok_or_else(invalid)?.trim();
+            let name = parts.next().ok_or_else(invalid)?;
+
+            contacts.add(key, name);
+            let contact = contacts.contacts.get_mut(&key).ok_or_else(invalid)?;
+            contact.address = match address {
+                "-" => None,
+                address => Some(address.parse().map_err(|_| invalid())?),
+            };
+            contact.last_seen = match last_seen {
+                "-" => None,
+                last_seen => Some(last_seen.parse().map_err(|_| invalid())?),
+            };
+        }
+        Ok(contacts)
+    }
+}
+
+/// An invitation to a room we created to call a contact
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallInvite {
+    /// Our name, as the caller
+    pub from: String,
+    /// Link the contact joins the room with
+    pub link: String,
+}
+
+/// Delivers call invites outside the app, by chat, email or the like
+pub trait InviteNotifier: Send {
+    fn notify(&mut self, contact: &Contact, invite: &CallInvite) -> Result<()>;
+}
+
+/// Runs an external program per invite
+///
+/// After its own arguments the program receives the contact's name, their
+/// key in hex, our name, the link and the contact's last address (or `-`).
+pub struct CommandNotifier {
+    program: String,
+    args: Vec<String>,
+}
+
+impl CommandNotifier {
+    pub fn new(program: &str, args: &[&str]) -> Self {
+        Self {
+            program: program.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        }
+    }
+}
+
+impl InviteNotifier for CommandNotifier {
+    fn notify(&mut self, contact: &Contact, invite: &CallInvite) -> Result<()> {
+        let status = Command::new(&self.program)
+            .args(&self.args)
+            .arg(&contact.name)
+            .arg(key_to_hex(&contact.key))
+            .arg(&invite.from)
+            .arg(&invite.link)
+            .arg(
+                contact
+                    .address
+                    .map_or("-".to_string(), |addr| addr.to_string()),
+            )
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()?;
+        if !status.success() {
+            return Err(anyhow!("Invite command failed: {}", status));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contacts_round_trip_and_reachability() {
+        let mut contacts = ContactList::default();
+        contacts.add([7; 32], "alice@work");
+        contacts.add([9; 32], "Bob");
+        contacts.record_seen(&[7; 32], "203.0.113.5:4000".parse().unwrap(), 1_000);
+
+        let parsed: ContactList = contacts.to_string().parse().unwrap();
+        assert_eq!(parsed, contacts);
+        assert_eq!(parsed.find("ALICE@WORK").map(|c| c.key), Some([7; 32]));
+        assert_eq!(parsed.find("0909").map(|c| c.key), Some([9; 32]));
+        assert!(parsed.find("09").is_none());
+
+        let alice = parsed.get(&[7; 32]).unwrap();
+        let bob = parsed.get(&[9; 32]).unwrap();
+        assert_eq!(
+            parsed.reachability(alice, &[], 1_000 + 60),
+            Reachability::Recent
+        );
+        assert_eq!(
+            parsed.reachability(alice, &[], 1_000 + REACHABLE_WINDOW_SECS + 1),
+            Reachability::Unknown
+        );
+        assert_eq!(
+            parsed.reachability(bob, &[[9; 32]], 0),
+            Reachability::InSession
+        );
+        assert_eq!(parsed.reachability(bob, &[], 0), Reachability::Unknown);
+
+        assert!("abcd=Alice@-@-".parse::<ContactList>().is_err());
+    }
+}
@@ -0,0 +1,685 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::app::events::SessionEvent;
+use crate::app::session::SessionError;
+use crate::audio::CodecSettings;
+use crate::network::Proxy;
+
+/// Version of the JSON control messages
+///
+/// Adding variants or optional fields keeps the version; renaming or removing
+/// anything, or changing what a field means, bumps it.
+pub const CONTROL_PROTOCOL_VERSION: u32 = 1;
+
+/// A command sent to a headless instance over its control interface
+///
+/// The control interface is line based: one command per line on stdin, and
+/// one reply or event per line on stdout. Lines are either plain text
+/// (`join <link>`) or JSON wrapped in a [`Versioned`] envelope.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ControlCommand {
+    /// Host a new session
+    Create,
+    /// Join a session from its link
+    Join { link: String },
+    /// Let a peer that asked to join into the session
+    Approve { peer_id: String },
+    /// Let everyone waiting to join in, first come first served (host only)
+    ApproveAll,
+    /// Turn away everyone waiting to join (host only)
+    DenyAll,
+    /// Send a test tone to the session for this many milliseconds
+    Tone { millis: u64 },
+    /// Mute everyone but the host, or let everyone speak again (host only)
+    MuteAll { enabled: bool },
+    /// Add a bot that plays our audio back a second later, or take it out (host only)
+    EchoBot { enabled: bool },
+    /// Ask the host to let us speak, or take the request back
+    Hand { raised: bool },
+    /// Let a muted peer speak (host only)
+    Grant { peer_id: String },
+    /// Remove a peer from the room (host only)
+    Kick { peer_id: String },
+    /// List our recent host actions, as `id:description`, undone ones marked `(undone)`
+    Actions,
+    /// Take a host action from the list again (host only)
+    Replay { id: u64 },
+    /// Reverse a host action from the list, where it can be (host only)
+    Undo { id: u64 },
+    /// Name a peer ourselves, remembered by its key; an empty alias removes ours
+    Alias { peer_id: String, alias: String },
+    /// Save a peer in the session as a contact
+    Contact { peer_id: String },
+    /// List saved contacts and whether each can be called
+    Contacts,
+    /// Host a room and send a contact an invite to it
+    Call { contact: String },
+    /// Hold back join requests, or stop
+    Dnd { enabled: bool },
+    /// Write the room events recorded in the audit log to a file, as JSON
+    AuditExport { path: String },
+    /// Send the room's audio with this codec (`opus[:kbps]`, `adpcm` or `pcm`),
+    /// or `None` to go back to our configured one
+    Codec { codec: Option<String> },
+    /// Make the room's signaling connections through this proxy (`direct` or
+    /// `socks5://...`), or `None` to go back to our configured one
+    Proxy { proxy: Option<String> },
+    /// Move the call to another machine, replying with the key and the sealed call
+    Handoff,
+    /// Carry on a call moved from another machine
+    Resume { key: String, handoff: String },
+    /// Impair what we send, for testing: `none`, a preset name, or settings
+    /// like `drop=5,latency=80,jitter=40,dup=1,rate=128`
+    Impair { impairment: String },
+    /// Report connection and audio state
+    Status,
+    /// Leave the current session
+    Leave,
+    /// Shut the instance down
+    Quit,
+}
+
+impl FromStr for ControlCommand {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.trim().splitn(2, ' ');
+        let name = parts.next().unwrap_or_default();
+        let argument = parts.next().map(str::trim).filter(|arg| !arg.is_empty());
+
+        let required = |what: &str| {
+            argument
+                .map(str::to_string)
+                .ok_or_else(|| format!("{} requires {}", name, what))
+        };
+
+        match name {
+            "create" => Ok(ControlCommand::Create),
+            "join" => Ok(ControlCommand::Join {
+                link: required("a link")?,
+            }),
+            "approve" => Ok(ControlCommand::Approve {
+                peer_id: required("a peer id")?,
+            }),
+            "approve_all" => Ok(ControlCommand::ApproveAll),
+            "deny_all" => Ok(ControlCommand::DenyAll),
+            "tone" => {
+                let millis = required("a duration")?;
+                millis
+                    .parse()
+                    .map(|millis| ControlCommand::Tone { millis })
+                    .map_err(|_| format!("Invalid tone duration: {}", millis))
+            }
+            "mute_all" => match required("on or off")?.as_str() {
+                "on" => Ok(ControlCommand::MuteAll { enabled: true }),
+                "off" => Ok(ControlCommand::MuteAll { enabled: false }),
+                other => Err(format!("Invalid mute_all setting: {}", other)),
+            },
+            "echo_bot" => match required("on or off")?.as_str() {
+                "on" => Ok(ControlCommand::EchoBot { enabled: true }),
+                "off" => Ok(ControlCommand::EchoBot { enabled: false }),
+                other => Err(format!("Invalid echo_bot setting: {}", other)),
+            },
+            "hand" => match required("up or down")?.as_str() {
+                "up" => Ok(ControlCommand::Hand { raised: true }),
+                "down" => Ok(ControlCommand::Hand { raised: false }),
+                other => Err(format!("Invalid hand position: {}", other)),
+            },
+            "grant" => Ok(ControlCommand::Grant {
+                peer_id: required("a peer id")?,
+            }),
+            "kick" => Ok(ControlCommand::Kick {
+                peer_id: required("a peer id")?,
+            }),
+            "actions" => Ok(ControlCommand::Actions),
+            "replay" | "undo" => {
+                let id = required("an action id")?;
+                let id = id
+                    .parse()
+                    .map_err(|_| format!("Invalid action id: {}", id))?;
+                Ok(if name == "replay" {
+                    ControlCommand::Replay { id }
+                } else {
+                    ControlCommand::Undo { id }
+                })
+            }
+            "alias" => {
+                let argument = required("a peer id")?;
+                let (peer_id, alias) = argument.split_once(' ').unwrap_or((&argument, ""));
+                Ok(ControlCommand::Alias {
+                    peer_id: peer_id.to_string(),
+                    alias: alias.trim().to_string(),
+                })
+            }
+            "contact" => Ok(ControlCommand::Contact {
+                peer_id: required("a peer id")?,
+            }),
+            "contacts" => Ok(ControlCommand::Contacts),
+            "call" => Ok(ControlCommand::Call {
+                contact: required("a contact")?,
+            }),
+            "dnd" => match required("on or off")?.as_str() {
+                "on" => Ok(ControlCommand::Dnd { enabled: true }),
+                "off" => Ok(ControlCommand::Dnd { enabled: false }),
+                other => Err(format!("Invalid dnd setting: {}", other)),
+            },
+            "audit_export" => Ok(ControlCommand::AuditExport {
+                path: required("a file")?,
+            }),
+            "codec" => match required("a codec or default")?.as_str() {
+                "default" => Ok(ControlCommand::Codec { codec: None }),
+                codec => codec
+                    .parse::<CodecSettings>()
+                    .map(|settings| ControlCommand::Codec {
+                        codec: Some(settings.to_string()),
+                    }),
+            },
+            "proxy" => match required("a proxy or default")?.as_str() {
+                "default" => Ok(ControlCommand::Proxy { proxy: None }),
+                proxy => proxy.parse::<Proxy>().map(|proxy| ControlCommand::Proxy {
+                    proxy: Some(proxy.to_string()),
+                }),
+            },
+            "handoff" => Ok(ControlCommand::Handoff),
+            "resume" => {
+                let argument = required("a key and a handoff")?;
+                match argument.split_once(' ') {
+                    Some((key, handoff)) => Ok(ControlCommand::Resume {
+                        key: key.to_string(),
+                        handoff: handoff.trim().to_string(),
+                    }),
+                    None => Err("resume requires a key and a handoff".to_string()),
+                }
+            }
+            "impair" => Ok(ControlCommand::Impair {
+                impairment: required("an impairment or none")?,
+            }),
+            "status" => Ok(ControlCommand::Status),
+            "leave" => Ok(ControlCommand::Leave),
+            "quit" => Ok(ControlCommand::Quit),
+            "" => Err("Empty command".to_string()),
+            _ => Err(format!("Unknown command: {}", name)),
+        }
+    }
+}
+
+/// Connection and audio state reported by the `status` command
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ControlStatus {
+    pub connected: bool,
+    pub participants: usize,
+    /// Remote streams we have received audio on
+    #[serde(default)]
+    pub receiving: Vec<String>,
+}
+
+/// A line written by a headless instance on its control interface
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ControlReply {
+    /// The command succeeded, with an optional result
+    Ok {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        result: Option<String>,
+    },
+    /// The command failed
+    Error {
+        message: String,
+        /// Kind of failure, for session errors
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        code: Option<String>,
+        /// What the user could try
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        hint: Option<String>,
+    },
+    /// Reply to `status`
+    Status(ControlStatus),
+    /// Something happened in the session
+    Event { event: SessionEvent },
+}
+
+impl ControlReply {
+    /// Success without a result
+    pub fn ok() -> Self {
+        ControlReply::Ok { result: None }
+    }
+
+    /// Success with a result
+    pub fn ok_with(result: impl ToString) -> Self {
+        ControlReply::Ok {
+            result: Some(result.to_string()),
+        }
+    }
+
+    /// Failure
+    pub fn error(message: impl ToString) -> Self {
+        ControlReply::Error {
+            message: message.to_string(),
+            code: None,
+            hint: None,
+        }
+    }
+
+    /// Failure of a session operation, with its code and any hint
+    pub fn session_error(error: &SessionError) -> Self {
+        ControlReply::Error {
+            message: error.to_string(),
+            code: Some(error.code().to_string()),
+            hint: error.user_hint().map(str::to_string),
+        }
+    }
+}
+
+impl fmt::Display for ControlReply {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ControlReply::Ok { result: None } => write!(f, "ok"),
+            ControlReply::Ok {
+                result: Some(result),
+            } => write!(f, "ok {}", result),
+            ControlReply::Error {
+                message,
+                code,
+                hint,
+            } => {
+                write!(f, "error {}", message.replace('\n', " "))?;
+                match (code, hint) {
+                    (Some(code), Some(hint)) => write!(f, " ({}: {})", code, hint),
+                    (Some(code), None) => write!(f, " ({})", code),
+                    (None, Some(hint)) => write!(f, " ({})", hint),
+                    (None, None) => Ok(()),
+                }
+            }
+            ControlReply::Status(status) => write!(
+                f,
+                "status connected={} participants={} receiving={}",
+                status.connected,
+                status.participants,
+                status.receiving.join(",")
+            ),
+            ControlReply::Event { event } => match event {
+                SessionEvent::PeerJoined { peer_id, name } => {
+                    write!(f, "event joined {} {}", peer_id, name)
+                }
+                SessionEvent::PeerLeft { peer_id } => write!(f, "event left {}", peer_id),
+                SessionEvent::StreamEnded { peer_id, .. } => {
+                    write!(f, "event stream_end {}", peer_id)
+                }
+                SessionEvent::HostChanged { peer_id } => write!(f, "event host {}", peer_id),
+                SessionEvent::AudioLevel { peer_id, level } => {
+                    write!(f, "event level {} {:.3}", peer_id, level)
+                }
+                SessionEvent::PositionChanged { peer_id, position } => write!(
+                    f,
+                    "event position {} {} {} {}",
+                    peer_id, position.0, position.1, position.2
+                ),
+                SessionEvent::PresenceChanged {
+                    peer_id, status, ..
+                } => write!(f, "event presence {} {:?}", peer_id, status),
+                SessionEvent::LayoutChanged { layout, .. } => {
+                    write!(f, "event layout {}", layout)
+                }
+                SessionEvent::TimeWarning { remaining_secs } => {
+                    write!(f, "event time_warning {}", remaining_secs)
+                }
+                SessionEvent::TimeLimitReached => write!(f, "event time_limit"),
+                SessionEvent::PossibleTampering { peer_id } => {
+                    write!(f, "event tampering {}", peer_id)
+                }
+                SessionEvent::MeetingModeChanged { enabled } => {
+                    write!(f, "event meeting {}", if *enabled { "on" } else { "off" })
+                }
+                SessionEvent::HandRaised {
+                    peer_id, raised, ..
+                } => write!(
+                    f,
+                    "event hand {} {}",
+                    peer_id,
+                    if *raised { "up" } else { "down" }
+                ),
+                SessionEvent::SpeakGranted { peer_id, .. } => {
+                    write!(f, "event granted {}", peer_id)
+                }
+                SessionEvent::RecordingChanged {
+                    peer_id, recording, ..
+                } => write!(
+                    f,
+                    "event recording {} {}",
+                    peer_id,
+                    if *recording { "on" } else { "off" }
+                ),
+                SessionEvent::JoinRequested { peer_id, .. } => {
+                    write!(f, "event join_request {}", peer_id)
+                }
+                SessionEvent::JoinQueueUpdated { queue } => {
+                    let waiting: Vec<&str> =
+                        queue.iter().map(|queued| queued.peer_id.as_str()).collect();
+                    write!(f, "event join_queue {}", waiting.join(","))
+                }
+                SessionEvent::ConnectionIssue { peer_id, issue, .. } => {
+                    write!(f, "event issue {} {}", peer_id, issue.code())
+                }
+                SessionEvent::HandshakeTimedOut { peer_id, address } => {
+                    write!(f, "event handshake_timeout {} {}", peer_id, address)
+                }
+                SessionEvent::ConnectionAttempt {
+                    peer_id,
+                    attempt,
+                    attempts,
+                    ..
+                } => write!(f, "event attempt {} {} {}", peer_id, attempt, attempts),
+            },
+        }
+    }
+}
+
+/// A JSON control message tagged with the protocol version it was written for
+///
+/// The version sits alongside the message's own fields:
+/// `{"version":1,"type":"join","link":"resonance://..."}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Versioned<T> {
+    pub version: u32,
+    #[serde(flatten)]
+    pub body: T,
+}
+
+impl<T: Serialize + DeserializeOwned> Versioned<T> {
+    /// Wraps a message in the current protocol version
+    pub fn new(body: T) -> Self {
+        Self {
+            version: CONTROL_PROTOCOL_VERSION,
+            body,
+        }
+    }
+
+    /// Serializes to a single JSON line
+    pub fn to_json(&self) -> String {
+        // Control messages contain only strings, numbers and maps
+        serde_json::to_string(self).expect("control messages always serialize")
+    }
+
+    /// Parses a JSON line, rejecting versions this build doesn't understand
+    pub fn from_json(line: &str) -> Result<T, String> {
+        let message: Self =
+            serde_json::from_str(line).map_err(|e| format!("Invalid message: {}", e))?;
+        if message.version == 0 || message.version > CONTROL_PROTOCOL_VERSION {
+            return Err(format!(
+                "Unsupported protocol version {} (this build speaks {})",
+                message.version, CONTROL_PROTOCOL_VERSION
+            ));
+        }
+        Ok(message.body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::events::ConnectionIssue;
+    use crate::app::presence::PresenceStatus;
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!("create".parse(), Ok(ControlCommand::Create));
+        assert_eq!(
+            "join resonance://1.2.3.4:5000/abc".parse(),
+            Ok(ControlCommand::Join {
+                link: "resonance://1.2.3.4:5000/abc".to_string()
+            })
+        );
+        assert_eq!(
+            " tone 500 ".parse(),
+            Ok(ControlCommand::Tone { millis: 500 })
+        );
+        assert_eq!("approve_all".parse(), Ok(ControlCommand::ApproveAll));
+        assert_eq!(
+            "mute_all on".parse(),
+            Ok(ControlCommand::MuteAll { enabled: true })
+        );
+        assert_eq!(
+            "echo_bot off".parse(),
+            Ok(ControlCommand::EchoBot { enabled: false })
+        );
+        assert_eq!(
+            "hand down".parse(),
+            Ok(ControlCommand::Hand { raised: false })
+        );
+        assert_eq!("undo 3".parse(), Ok(ControlCommand::Undo { id: 3 }));
+        assert_eq!(
+            "kick peer-1".parse(),
+            Ok(ControlCommand::Kick {
+                peer_id: "peer-1".to_string()
+            })
+        );
+        assert_eq!(
+            "audit_export /tmp/room events.json".parse(),
+            Ok(ControlCommand::AuditExport {
+                path: "/tmp/room events.json".to_string()
+            })
+        );
+        assert_eq!(
+            "alias peer-1 Alice's laptop".parse(),
+            Ok(ControlCommand::Alias {
+                peer_id: "peer-1".to_string(),
+                alias: "Alice's laptop".to_string()
+            })
+        );
+        assert_eq!(
+            "alias peer-1".parse(),
+            Ok(ControlCommand::Alias {
+                peer_id: "peer-1".to_string(),
+                alias: String::new()
+            })
+        );
+        assert_eq!(
+            "call Alice Smith".parse(),
+            Ok(ControlCommand::Call {
+                contact: "Alice Smith".to_string()
+            })
+        );
+        assert_eq!(
+            "resume k3y resonance-handoff-1:abc".parse(),
+            Ok(ControlCommand::Resume {
+                key: "k3y".to_string(),
+                handoff: "resonance-handoff-1:abc".to_string()
+            })
+        );
+        assert_eq!(
+            "impair lossy".parse(),
+            Ok(ControlCommand::Impair {
+                impairment: "lossy".to_string()
+            })
+        );
+        assert!("resume k3y".parse::<ControlCommand>().is_err());
+        assert!("mute_all".parse::<ControlCommand>().is_err());
+        assert!("hand sideways".parse::<ControlCommand>().is_err());
+        assert!("join".parse::<ControlCommand>().is_err());
+        assert!("kick".parse::<ControlCommand>().is_err());
+        assert!("tone soon".parse::<ControlCommand>().is_err());
+        assert!("replay last".parse::<ControlCommand>().is_err());
+        assert!("dance".parse::<ControlCommand>().is_err());
+    }
+
+    #[test]
+    fn test_reply_lines() {
+        let status = ControlStatus {
+            connected: true,
+            participants: 2,
+            receiving: vec!["Host".to_string()],
+        };
+        assert_eq!(
+            ControlReply::Status(status).to_string(),
+            "status connected=true participants=2 receiving=Host"
+        );
+        assert_eq!(
+            ControlReply::Event {
+                event: SessionEvent::PeerLeft {
+                    peer_id: "peer-1".to_string()
+                }
+            }
+            .to_string(),
+            "event left peer-1"
+        );
+        assert_eq!(
+            ControlReply::error("bad\nlink").to_string(),
+            "error bad link"
+        );
+        assert_eq!(
+            ControlReply::session_error(&SessionError::CreationError("no socket".to_string()))
+                .to_string(),
+            "error Failed to create session: no socket (CREATE_FAILED)"
+        );
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let commands = [
+            ControlCommand::Create,
+            ControlCommand::Join {
+                link: "resonance://join?ip=1.2.3.4".to_string(),
+            },
+            ControlCommand::Approve {
+                peer_id: "peer-1".to_string(),
+            },
+            ControlCommand::ApproveAll,
+            ControlCommand::DenyAll,
+            ControlCommand::Tone { millis: 250 },
+            ControlCommand::MuteAll { enabled: true },
+            ControlCommand::EchoBot { enabled: true },
+            ControlCommand::Hand { raised: true },
+            ControlCommand::Grant {
+                peer_id: "peer-1".to_string(),
+            },
+            ControlCommand::Kick {
+                peer_id: "peer-1".to_string(),
+            },
+            ControlCommand::Actions,
+            ControlCommand::Replay { id: 2 },
+            ControlCommand::Undo { id: 2 },
+            ControlCommand::Alias {
+                peer_id: "peer-1".to_string(),
+                alias: "Alice's laptop".to_string(),
+            },
+            ControlCommand::Contact {
+                peer_id: "peer-1".to_string(),
+            },
+            ControlCommand::Contacts,
+            ControlCommand::Dnd { enabled: true },
+            ControlCommand::AuditExport {
+                path: "/tmp/audit.json".to_string(),
+            },
+            ControlCommand::Codec {
+                codec: Some("opus:64".to_string()),
+            },
+            ControlCommand::Codec { codec: None },
+            ControlCommand::Proxy {
+                proxy: Some("socks5://127.0.0.1:9050".to_string()),
+            },
+            ControlCommand::Proxy { proxy: None },
+            ControlCommand::Call {
+                contact: "Alice".to_string(),
+            },
+            ControlCommand::Impair {
+                impairment: "drop=5,latency=80".to_string(),
+            },
+            ControlCommand::Status,
+            ControlCommand::Leave,
+            ControlCommand::Quit,
+        ];
+        for command in commands {
+            let line = Versioned::new(command.clone()).to_json();
+            assert_eq!(Versioned::from_json(&line), Ok(command));
+        }
+
+        let replies = [
+            ControlReply::ok(),
+            ControlReply::ok_with("resonance://join?ip=1.2.3.4"),
+            ControlReply::error("timeout"),
+            ControlReply::session_error(&SessionError::NotHost),
+            ControlReply::Status(ControlStatus::default()),
+            ControlReply::Event {
+                event: SessionEvent::PositionChanged {
+                    peer_id: "peer-1".to_string(),
+                    position: (1.0, 0.0, -2.5),
+                },
+            },
+            ControlReply::Event {
+                event: SessionEvent::PresenceChanged {
+                    peer_id: "peer-1".to_string(),
+                    name: "Alice".to_string(),
+                    status: PresenceStatus::Away,
+                },
+            },
+            ControlReply::Event {
+                event: SessionEvent::TimeWarning { remaining_secs: 60 },
+            },
+            ControlReply::Event {
+                event: SessionEvent::TimeLimitReached,
+            },
+            ControlReply::Event {
+                event: SessionEvent::ConnectionIssue {
+                    peer_id: "peer-1".to_string(),
+                    name: "Alice".to_string(),
+                    issue: ConnectionIssue::OneWayAudio,
+                },
+            },
+        ];
+        for reply in replies {
+            let line = Versioned::new(reply.clone()).to_json();
+            assert_eq!(Versioned::from_json(&line), Ok(reply));
+        }
+    }
+
+    /// Messages as version 1 clients write them; these must keep parsing
+    #[test]
+    fn test_version_1_wire_format() {
+        assert_eq!(
+            Versioned::from_json(r#"{"version":1,"type":"join","link":"resonance://x"}"#),
+            Ok(ControlCommand::Join {
+                link: "resonance://x".to_string()
+            })
+        );
+        assert_eq!(
+            Versioned::from_json(r#"{"version":1,"type":"tone","millis":500}"#),
+            Ok(ControlCommand::Tone { millis: 500 })
+        );
+        assert_eq!(
+            Versioned::<ControlReply>::new(ControlReply::Event {
+                event: SessionEvent::PeerJoined {
+                    peer_id: "p".to_string(),
+                    name: "Bob".to_string(),
+                },
+            })
+            .to_json(),
+            r#"{"version":1,"type":"event","event":{"type":"peer_joined","peer_id":"p","name":"Bob"}}"#
+        );
+
+        // Fields added later are optional, and unknown fields are ignored
+        assert_eq!(
+            Versioned::from_json(
+                r#"{"version":1,"type":"status","connected":true,"participants":3}"#
+            ),
+            Ok(ControlReply::Status(ControlStatus {
+                connected: true,
+                participants: 3,
+                receiving: Vec::new(),
+            }))
+        );
+        assert_eq!(
+            Versioned::from_json(r#"{"version":1,"type":"leave","reason":"done"}"#),
+            Ok(ControlCommand::Leave)
+        );
+    }
+
+    #[test]
+    fn test_rejects_unknown_versions() {
+        assert!(Versioned::<ControlCommand>::from_json(r#"{"version":2,"type":"quit"}"#).is_err());
+        assert!(Versioned::<ControlCommand>::from_json(r#"{"version":0,"type":"quit"}"#).is_err());
+        assert!(Versioned::<ControlCommand>::from_json(r#"{"type":"quit"}"#).is_err());
+    }
+}
@@ -0,0 +1,202 @@
+use anyhow::Result;
+use std::backtrace::Backtrace;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Write};
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+use crate::app::config::Config;
+use crate::app::topology::{runtime_report, TaskReport};
+use crate::audio::{total_faults, SampleFaults};
+
+/// Number of log lines included in a bundle
+const LOG_TAIL_LINES: usize = 200;
+
+/// Configuration keys whose values are replaced in bundles
+const REDACTED_KEYS: [&str; 8] = [
+    "username",
+    "key",
+    "secret",
+    "token",
+    "password",
+    "link",
+    "ice_servers",
+    "proxy",
+];
+
+/// Information gathered for a bug report
+#[derive(Debug, Clone)]
+pub struct DiagnosticBundle {
+    /// Panic message and location, if the bundle was created by a crash
+    pub panic_message: Option<String>,
+    /// Backtrace at the point the bundle was created
+    pub backtrace: String,
+    /// Last lines of the current log file
+    pub log_tail: Vec<String>,
+    /// Configuration with sensitive values redacted
+    pub config: String,
+    /// Bad audio samples replaced before mixing since startup
+    pub audio_faults: SampleFaults,
+    /// Tasks running when the bundle was created, and where
+    pub tasks: Vec<TaskReport>,
+}
+
+impl DiagnosticBundle {
+    /// Gathers diagnostics from the current process
+    pub fn collect(
+        config: &Config,
+        log_file: Option<&Path>,
+        panic_message: Option<String>,
+    ) -> Self {
+        Self {
+            panic_message,
+            backtrace: Backtrace::force_capture().to_string(),
+            log_tail: log_file.map(read_log_tail).unwrap_or_default(),
+            config: redact_config(&config.to_string()),
+            audio_faults: total_faults(),
+            tasks: runtime_report(),
+        }
+    }
+
+    /// Writes the bundle as a zip archive
+    pub fn write_zip(&self, path: &Path) -> Result<()> {
+        let mut zip = ZipWriter::new(File::create(path)?);
+        let options = FileOptions::default();
+
+        let mut summary = format!(
+            "resonance {}\nos: {}\narch: {}\n",
+            env!("CARGO_PKG_VERSION"),
+            std::env::consts::OS,
+            std::env::consts::ARCH
+        );
+        if let Some(message) = &self.panic_message {
+            summary.push_str(&format!("panic: {}\n", message));
+        }
+        summary.push_str(&format!(
+            "bad audio samples replaced: {}\n",
+            self.audio_faults
+        ));
+        summary.push_str("tasks:\n");
+        for task in &self.tasks {
+            summary.push_str(&format!("  {}\n", task));
+        }
+
+        zip.start_file("summary.txt", options)?;
+        zip.write_all(summary.as_bytes())?;
+        zip.start_file("backtrace.txt", options)?;
+        zip.write_all(self.backtrace.as_bytes())?;
+        zip.start_file("log_tail.txt", options)?;
+        zip.write_all(self.log_tail.join("\n").as_bytes())?;
+        zip.start_file("config.txt", options)?;
+        zip.write_all(self.config.as_bytes())?;
+
+        zip.finish()?;
+        Ok(())
+    }
+}
+
+/// Replaces the values of sensitive configuration keys
+pub fn redact_config(config: &str) -> String {
+    config
+        .lines()
+        .map(|line| match line.split_once('=') {
+            Some((key, _)) if REDACTED_KEYS.iter().any(|k| key.trim().contains(k)) => {
+                format!("{}=<redacted>", key)
+            }
+            _ => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn read_log_tail(path: &Path) -> Vec<String> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(_) => return Vec::new(),
+    };
+
+    let lines: Vec<String> = BufReader::new(file).lines().map_while(|l| l.ok()).collect();
+    let start = lines.len().saturating_sub(LOG_TAIL_LINES);
+    lines[start..].to_vec()
+}
+
+/// Gets a fresh path for a bundle in `directory`
+pub fn bundle_path(directory: &Path) -> PathBuf {
+    let stamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    directory.join(format!("resonance-diagnostics-{}.zip", stamp))
+}
+
+/// Writes a diagnostic bundle into `directory`, returning its path
+pub fn generate_bundle(
+    config: &Config,
+    log_file: Option<&Path>,
+    directory: &Path,
+) -> Result<PathBuf> {
+    fs::create_dir_all(directory)?;
+    let path = bundle_path(directory);
+    DiagnosticBundle::collect(config, log_file, None).write_zip(&path)?;
+    Ok(path)
+}
+
+/// Writes a diagnostic bundle into `directory` whenever the app panics
+///
+/// The previous panic hook still runs afterwards.
+pub fn install_panic_hook(config: Config, log_file: Option<PathBuf>, directory: PathBuf) {
+    let previous_hook = panic::take_hook();
+
+    panic::set_hook(Box::new(move |info| {
+        let path = bundle_path(&directory);
+        let bundle =
+            DiagnosticBundle::collect(&config, log_file.as_deref(), Some(info.to_string()));
+
+        match fs::create_dir_all(&directory)
+            .map_err(anyhow::Error::from)
+            .and_then(|_| bundle.write_zip(&path))
+        {
+            Ok(()) => eprintln!("A diagnostic bundle was written to {}", path.display()),
+            Err(e) => eprintln!("Failed to write diagnostic bundle: {}", e),
+        }
+
+        previous_hook(info);
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_config() {
+        let redacted = redact_config("username=alice\naudio_quality=High\napi_key=abc");
+
+        assert!(!redacted.contains("alice"));
+        assert!(!redacted.contains("abc"));
+        assert!(redacted.contains("audio_quality=High"));
+    }
+
+    #[test]
+    fn test_generate_bundle() {
+        let directory =
+            std::env::temp_dir().join(format!("resonance-diagnostics-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+
+        let log_file = directory.join("test.log");
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(&log_file, "first\nsecond\n").unwrap();
+
+        let bundle = DiagnosticBundle::collect(&Config::default(), Some(&log_file), None);
+        assert_eq!(bundle.log_tail, vec!["first", "second"]);
+
+        let path = generate_bundle(&Config::default(), Some(&log_file), &directory).unwrap();
+        let archive = zip::ZipArchive::new(File::open(&path).unwrap()).unwrap();
+        assert_eq!(archive.len(), 4);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
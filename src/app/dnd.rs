@@ -0,0 +1,173 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Sent to peers turned away while do-not-disturb is on, unless configured otherwise
+pub const DEFAULT_DENY_MESSAGE: &str = "The host isn't taking calls right now";
+
+/// What happens to join requests while do-not-disturb is on
+///
+/// Written as `deny`, `deny:<message>` or `queue` in the configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DndPolicy {
+    /// Turn the peer away with this message
+    Deny(String),
+    /// Hold the request quietly until do-not-disturb is turned off
+    Queue,
+}
+
+impl Default for DndPolicy {
+    fn default() -> Self {
+        DndPolicy::Deny(DEFAULT_DENY_MESSAGE.to_string())
+    }
+}
+
+impl fmt::Display for DndPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DndPolicy::Deny(message) if message == DEFAULT_DENY_MESSAGE => write!(f, "deny"),
+            DndPolicy::Deny(message) => write!(f, "deny:{}", message),
+            DndPolicy::Queue => write!(f, "queue"),
+        }
+    }
+}
+
+impl FromStr for DndPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().split_once(':') {
+            Some(("deny", message)) if !message.trim().is_empty() => {
+                Ok(DndPolicy::Deny(message.trim().to_string()))
+            }
+            _ => match s.trim() {
+                "deny" => Ok(DndPolicy::default()),
+                "queue" => Ok(DndPolicy::Queue),
+                other => Err(format!("Invalid do-not-disturb policy: {}", other)),
+            },
+        }
+    }
+}
+
+/// A peer asking to join a room we host
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JoinRequest {
+    pub peer_id: String,
+    pub name: String,
+}
+
+/// What to do with a join request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JoinDecision {
+    /// Let the host decide, as usual
+    Ask,
+    /// Turn the peer away with this message
+    Deny(String),
+    /// Held until do-not-disturb is turned off
+    Queued,
+}
+
+/// Do-not-disturb mode for incoming join requests
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DoNotDisturb {
+    enabled: bool,
+    policy: DndPolicy,
+    // Requests held while enabled, oldest first
+    queued: Vec<JoinRequest>,
+}
+
+impl DoNotDisturb {
+    pub fn new(enabled: bool, policy: DndPolicy) -> Self {
+        Self {
+            enabled,
+            policy,
+            queued: Vec::new(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Turns do-not-disturb on or off, returning the requests held meanwhile once it's off
+    pub fn set_enabled(&mut self, enabled: bool) -> Vec<JoinRequest> {
+        self.enabled = enabled;
+        if enabled {
+            Vec::new()
+        } else {
+            std::mem::take(&mut self.queued)
+        }
+    }
+
+    pub fn set_policy(&mut self, policy: DndPolicy) {
+        self.policy = policy;
+    }
+
+    /// Decides what to do with a join request, holding it if the policy says so
+    pub fn screen(&mut self, request: JoinRequest) -> JoinDecision {
+        if !self.enabled {
+            return JoinDecision::Ask;
+        }
+        match &self.policy {
+            DndPolicy::Deny(message) => JoinDecision::Deny(message.clone()),
+            DndPolicy::Queue => {
+                // A peer asking again keeps its place
+                if !self
+                    .queued
+                    .iter()
+                    .any(|queued| queued.peer_id == request.peer_id)
+                {
+                    self.queued.push(request);
+                }
+                JoinDecision::Queued
+            }
+        }
+    }
+
+    /// Requests held so far
+    pub fn queued(&self) -> &[JoinRequest] {
+        &self.queued
+    }
+
+    /// Stops holding a peer's request, as it's no longer waiting
+    pub fn withdraw(&mut self, peer_id: &str) {
+        self.queued.retain(|request| request.peer_id != peer_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(peer_id: &str) -> JoinRequest {
+        JoinRequest {
+            peer_id: peer_id.to_string(),
+            name: peer_id.to_uppercase(),
+        }
+    }
+
+    #[test]
+    fn test_policies() {
+        for policy in ["deny", "deny:Back at 3pm", "queue"] {
+            assert_eq!(policy.parse::<DndPolicy>().unwrap().to_string(), policy);
+        }
+        assert!("ignore".parse::<DndPolicy>().is_err());
+
+        let mut dnd = DoNotDisturb::new(false, "deny:Back at 3pm".parse().unwrap());
+        assert_eq!(dnd.screen(request("alice")), JoinDecision::Ask);
+        dnd.set_enabled(true);
+        assert_eq!(
+            dnd.screen(request("alice")),
+            JoinDecision::Deny("Back at 3pm".to_string())
+        );
+
+        dnd.set_policy(DndPolicy::Queue);
+        assert_eq!(dnd.screen(request("alice")), JoinDecision::Queued);
+        assert_eq!(dnd.screen(request("bob")), JoinDecision::Queued);
+        assert_eq!(dnd.screen(request("alice")), JoinDecision::Queued);
+        assert_eq!(dnd.queued().len(), 2);
+
+        let released = dnd.set_enabled(false);
+        assert_eq!(released, [request("alice"), request("bob")]);
+        assert!(dnd.queued().is_empty());
+    }
+}
@@ -0,0 +1,91 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::audio::{AudioDecoder, AudioEncoder, AudioFrame, CodecSettings};
+use crate::network::Message;
+
+/// Peer ID the echo bot's audio is attributed to
+pub const ECHO_BOT_ID: &str = "echo-bot";
+
+/// Name the echo bot has in the room
+pub const ECHO_BOT_NAME: &str = "Echo bot";
+
+/// How long the echo bot waits before playing audio back
+///
+/// Long enough to hear yourself after you stop talking rather than over
+/// the top of it.
+pub const ECHO_DELAY: Duration = Duration::from_secs(1);
+
+/// A pretend peer that plays back whatever we send it, a second later
+///
+/// Lets someone alone in a room check their microphone, sending, codec and
+/// playback without a second person. Audio goes through the room's codec
+/// both ways, as it would to a real peer, but never leaves the machine, so
+/// it can't show whether peers can reach us.
+pub struct EchoBot {
+    codec: CodecSettings,
+    encoder: AudioEncoder,
+    decoder: AudioDecoder,
+    // Encoded audio waiting to be played back, with when it's due
+    pending: VecDeque<(Instant, Message)>,
+}
+
+impl EchoBot {
+    pub fn new(codec: CodecSettings) -> Self {
+        Self {
+            codec,
+            encoder: AudioEncoder::new(codec.bitrate_kbps),
+            decoder: AudioDecoder::new(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Takes a frame we sent to the room, to play back after the delay
+    pub fn hear(&mut self, frame: &AudioFrame, now: Instant) {
+        let message = Message::encode_audio(frame, self.codec.codec, &mut self.encoder);
+        self.pending.push_back((now + ECHO_DELAY, message));
+    }
+
+    /// The frames due to be played back by `now`, oldest first
+    pub fn replies(&mut self, now: Instant) -> Vec<AudioFrame> {
+        let mut frames = Vec::new();
+        while let Some((due, _)) = self.pending.front() {
+            if *due > now {
+                break;
+            }
+            let (_, message) = self.pending.pop_front().unwrap();
+            if let Some(frame) = message.decode_audio(&mut self.decoder, ECHO_BOT_ID) {
+                frames.push(frame);
+            }
+        }
+        frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::AudioCodec;
+
+    #[test]
+    fn test_echo_comes_back_after_delay() {
+        let codec = CodecSettings {
+            codec: AudioCodec::Pcm,
+            bitrate_kbps: 64,
+        };
+        let mut bot = EchoBot::new(codec);
+        let start = Instant::now();
+        let frame = AudioFrame::new(vec![0.25; 960], 48000, 1).with_seq(7);
+        bot.hear(&frame, start);
+
+        assert!(bot.replies(start + Duration::from_millis(999)).is_empty());
+        let replies = bot.replies(start + ECHO_DELAY);
+        assert_eq!(replies.len(), 1);
+        assert_eq!(replies[0].seq, 7);
+        assert_eq!(replies[0].peer_id.as_deref(), Some(ECHO_BOT_ID));
+        assert!((replies[0].samples[0] - 0.25).abs() < 0.01);
+
+        // Played back once only
+        assert!(bot.replies(start + ECHO_DELAY * 2).is_empty());
+    }
+}
@@ -0,0 +1,316 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::admission::QueuedJoin;
+use crate::app::presence::PresenceStatus;
+use crate::audio::SpatialLayout;
+
+/// Events emitted by the session for the UI to react to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SessionEvent {
+    /// A peer joined the session
+    PeerJoined { peer_id: String, name: String },
+    /// A peer left the session
+    PeerLeft { peer_id: String },
+    /// A peer's audio ended cleanly, ahead of them leaving
+    StreamEnded { peer_id: String, name: String },
+    /// The session host changed
+    HostChanged { peer_id: String },
+    /// A peer's current audio level (0.0 - 1.0)
+    AudioLevel { peer_id: String, level: f32 },
+    /// A peer moved in virtual space
+    PositionChanged {
+        peer_id: String,
+        position: (f32, f32, f32),
+    },
+    /// A peer became active, idle or away
+    PresenceChanged {
+        peer_id: String,
+        name: String,
+        status: PresenceStatus,
+    },
+    /// The host rearranged everyone, with new positions by peer ID
+    LayoutChanged {
+        layout: SpatialLayout,
+        positions: Vec<(String, (f32, f32, f32))>,
+    },
+    /// The session's time limit ends it in `remaining_secs`
+    TimeWarning { remaining_secs: u64 },
+    /// The session's time limit was reached
+    TimeLimitReached,
+    /// Many packets from a peer failed authentication or were replayed
+    PossibleTampering { peer_id: String },
+    /// The host muted everyone else, or let everyone speak again
+    MeetingModeChanged { enabled: bool },
+    /// A muted peer raised or lowered their hand
+    HandRaised {
+        peer_id: String,
+        name: String,
+        raised: bool,
+    },
+    /// The host let a muted peer speak
+    SpeakGranted { peer_id: String, name: String },
+    /// A peer started or stopped recording the room
+    RecordingChanged {
+        peer_id: String,
+        name: String,
+        recording: bool,
+    },
+    /// A peer asks to join a room we host
+    JoinRequested { peer_id: String, name: String },
+    /// The line of peers waiting to join a room we host changed
+    JoinQueueUpdated { queue: Vec<QueuedJoin> },
+    /// Something is wrong with a peer's connection that the user may be able to fix
+    ConnectionIssue {
+        peer_id: String,
+        name: String,
+        issue: ConnectionIssue,
+    },
+    /// A peer or the host never answered after we connected, and was given up on
+    HandshakeTimedOut {
+        peer_id: String,
+        address: std::net::SocketAddr,
+    },
+    /// We're trying to connect to a peer or the host, `attempt` of `attempts`
+    ConnectionAttempt {
+        peer_id: String,
+        name: String,
+        attempt: u32,
+        attempts: u32,
+    },
+}
+
+/// A problem noticed on a connection that still looks up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionIssue {
+    /// We're sending audio to the peer but hearing nothing back
+    OneWayAudio,
+}
+
+impl ConnectionIssue {
+    /// Short name used in control replies
+    pub fn code(&self) -> &'static str {
+        match self {
+            ConnectionIssue::OneWayAudio => "one_way_audio",
+        }
+    }
+}
+
+/// The type of a `SessionEvent`, used to select a coalescing policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    PeerJoined,
+    PeerLeft,
+    StreamEnded,
+    HostChanged,
+    AudioLevel,
+    PositionChanged,
+    PresenceChanged,
+    LayoutChanged,
+    TimeWarning,
+    TimeLimitReached,
+    PossibleTampering,
+    MeetingModeChanged,
+    HandRaised,
+    SpeakGranted,
+    RecordingChanged,
+    JoinRequested,
+    JoinQueueUpdated,
+    ConnectionIssue,
+    HandshakeTimedOut,
+    ConnectionAttempt,
+}
+
+impl SessionEvent {
+    /// Gets the type of this event
+    pub fn kind(&self) -> EventKind {
+        match self {
+            SessionEvent::PeerJoined { .. } => EventKind::PeerJoined,
+            SessionEvent::PeerLeft { .. } => EventKind::PeerLeft,
+            SessionEvent::StreamEnded { .. } => EventKind::StreamEnded,
+            SessionEvent::HostChanged { .. } => EventKind::HostChanged,
+            SessionEvent::AudioLevel { .. } => EventKind::AudioLevel,
+            SessionEvent::PositionChanged { .. } => EventKind::PositionChanged,
+            SessionEvent::PresenceChanged { .. } => EventKind::PresenceChanged,
+            SessionEvent::LayoutChanged { .. } => EventKind::LayoutChanged,
+            SessionEvent::TimeWarning { .. } => EventKind::TimeWarning,
+            SessionEvent::TimeLimitReached => EventKind::TimeLimitReached,
+            SessionEvent::PossibleTampering { .. } => EventKind::PossibleTampering,
+            SessionEvent::MeetingModeChanged { .. } => EventKind::MeetingModeChanged,
+            SessionEvent::HandRaised { .. } => EventKind::HandRaised,
+            SessionEvent::SpeakGranted { .. } => EventKind::SpeakGranted,
+            SessionEvent::RecordingChanged { .. } => EventKind::RecordingChanged,
+            SessionEvent::JoinRequested { .. } => EventKind::JoinRequested,
+            SessionEvent::JoinQueueUpdated { .. } => EventKind::JoinQueueUpdated,
+            SessionEvent::ConnectionIssue { .. } => EventKind::ConnectionIssue,
+            SessionEvent::HandshakeTimedOut { .. } => EventKind::HandshakeTimedOut,
+            SessionEvent::ConnectionAttempt { .. } => EventKind::ConnectionAttempt,
+        }
+    }
+
+    /// Gets the peer this event is about; empty for events about the whole session
+    pub fn peer_id(&self) -> &str {
+        match self {
+            SessionEvent::PeerJoined { peer_id, .. }
+            | SessionEvent::PeerLeft { peer_id }
+            | SessionEvent::StreamEnded { peer_id, .. }
+            | SessionEvent::HostChanged { peer_id }
+            | SessionEvent::AudioLevel { peer_id, .. }
+            | SessionEvent::PositionChanged { peer_id, .. }
+            | SessionEvent::PresenceChanged { peer_id, .. }
+            | SessionEvent::PossibleTampering { peer_id }
+            | SessionEvent::HandRaised { peer_id, .. }
+            | SessionEvent::SpeakGranted { peer_id, .. }
+            | SessionEvent::RecordingChanged { peer_id, .. }
+            | SessionEvent::JoinRequested { peer_id, .. }
+            | SessionEvent::ConnectionIssue { peer_id, .. }
+            | SessionEvent::HandshakeTimedOut { peer_id, .. }
+            | SessionEvent::ConnectionAttempt { peer_id, .. } => peer_id,
+            SessionEvent::LayoutChanged { .. }
+            | SessionEvent::MeetingModeChanged { .. }
+            | SessionEvent::JoinQueueUpdated { .. }
+            | SessionEvent::TimeWarning { .. }
+            | SessionEvent::TimeLimitReached => "",
+        }
+    }
+}
+
+/// How bursts of one type of event are merged within a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoalescePolicy {
+    /// Deliver every event
+    KeepAll,
+    /// Keep only the latest event for each peer
+    LatestPerPeer,
+    /// Keep only the latest event overall
+    LatestOnly,
+}
+
+/// Merges high-frequency events so the UI sees at most one update per frame
+pub struct EventCoalescer {
+    frame_interval: Duration,
+    policies: HashMap<EventKind, CoalescePolicy>,
+    pending: Vec<SessionEvent>,
+    last_flush: Instant,
+}
+
+impl EventCoalescer {
+    /// Creates a coalescer that flushes at most once per `frame_interval`
+    pub fn new(frame_interval: Duration) -> Self {
+        let mut policies = HashMap::new();
+        policies.insert(EventKind::AudioLevel, CoalescePolicy::LatestPerPeer);
+        policies.insert(EventKind::PositionChanged, CoalescePolicy::LatestPerPeer);
+        policies.insert(EventKind::PresenceChanged, CoalescePolicy::LatestPerPeer);
+        policies.insert(EventKind::ConnectionAttempt, CoalescePolicy::LatestPerPeer);
+        policies.insert(EventKind::HostChanged, CoalescePolicy::LatestOnly);
+        policies.insert(EventKind::LayoutChanged, CoalescePolicy::LatestOnly);
+        // Requests arriving together reach the host as one snapshot of the line
+        policies.insert(EventKind::JoinQueueUpdated, CoalescePolicy::LatestOnly);
+
+        Self {
+            frame_interval,
+            policies,
+            pending: Vec::new(),
+            last_flush: Instant::now(),
+        }
+    }
+
+    /// Sets the coalescing policy for one type of event
+    pub fn with_policy(mut self, kind: EventKind, policy: CoalescePolicy) -> Self {
+        self.policies.insert(kind, policy);
+        self
+    }
+
+    /// Gets the policy used for a type of event
+    pub fn policy(&self, kind: EventKind) -> CoalescePolicy {
+        self.policies
+            .get(&kind)
+            .copied()
+            .unwrap_or(CoalescePolicy::KeepAll)
+    }
+
+    /// Queues an event, replacing any pending event it supersedes
+    pub fn push(&mut self, event: SessionEvent) {
+        let kind = event.kind();
+        let superseded = match self.policy(kind) {
+            CoalescePolicy::KeepAll => None,
+            CoalescePolicy::LatestPerPeer => self
+                .pending
+                .iter()
+                .position(|e| e.kind() == kind && e.peer_id() == event.peer_id()),
+            CoalescePolicy::LatestOnly => self.pending.iter().position(|e| e.kind() == kind),
+        };
+
+        match superseded {
+            // Update in place so the event keeps its position relative to others
+            Some(index) => self.pending[index] = event,
+            None => self.pending.push(event),
+        }
+    }
+
+    /// Number of events waiting to be delivered
+    pub fn pending_len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Returns the compacted events if a frame interval has passed since the last flush
+    pub fn flush(&mut self) -> Vec<SessionEvent> {
+        if self.pending.is_empty() || self.last_flush.elapsed() < self.frame_interval {
+            return Vec::new();
+        }
+
+        self.last_flush = Instant::now();
+        std::mem::take(&mut self.pending)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(peer_id: &str, level: f32) -> SessionEvent {
+        SessionEvent::AudioLevel {
+            peer_id: peer_id.to_string(),
+            level,
+        }
+    }
+
+    #[test]
+    fn test_latest_per_peer_merges_bursts() {
+        let mut coalescer = EventCoalescer::new(Duration::ZERO);
+        coalescer.push(level("a", 0.1));
+        coalescer.push(level("b", 0.2));
+        coalescer.push(level("a", 0.3));
+
+        let events = coalescer.flush();
+        assert_eq!(events, vec![level("a", 0.3), level("b", 0.2)]);
+    }
+
+    #[test]
+    fn test_keep_all_delivers_every_event() {
+        let mut coalescer = EventCoalescer::new(Duration::ZERO);
+        for _ in 0..3 {
+            coalescer.push(SessionEvent::PeerLeft {
+                peer_id: "a".to_string(),
+            });
+        }
+
+        assert_eq!(coalescer.flush().len(), 3);
+        assert_eq!(coalescer.pending_len(), 0);
+    }
+
+    #[test]
+    fn test_flush_waits_for_frame_interval() {
+        let mut coalescer = EventCoalescer::new(Duration::from_secs(60))
+            .with_policy(EventKind::AudioLevel, CoalescePolicy::LatestOnly);
+        coalescer.push(level("a", 0.1));
+        coalescer.push(level("b", 0.2));
+
+        assert!(coalescer.flush().is_empty());
+        assert_eq!(coalescer.pending_len(), 1);
+    }
+}
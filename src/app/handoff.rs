@@ -0,0 +1,188 @@
+use chacha20poly1305::{aead::Aead, KeyInit, XChaCha20Poly1305, XNonce};
+use rand::{thread_rng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::app::session::Peer;
+use crate::network::ConnectionHandoff;
+
+/// How long an exported call waits to be picked up on the other machine
+///
+/// Peers keep sending to the old machine meanwhile, and what they send is
+/// lost, so a handoff is meant to be carried over straight away.
+pub const HANDOFF_VALIDITY_SECS: u64 = 120;
+
+/// Start of every sealed handoff, with the format version
+const SEALED_PREFIX: &str = "resonance-handoff-1:";
+
+const NONCE_LEN: usize = 24;
+
+/// Why a sealed handoff couldn't be opened
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum HandoffError {
+    #[error("Not a call handoff")]
+    Malformed,
+    #[error("The handoff key doesn't match")]
+    WrongKey,
+    #[error("The handoff expired; export the call again")]
+    Expired,
+}
+
+/// Key a handoff is sealed with
+///
+/// Shown on the machine the call leaves and typed on the one it moves to,
+/// so the sealed handoff itself can travel any way, even in the clear.
+#[derive(Clone, PartialEq, Eq)]
+pub struct HandoffKey([u8; 32]);
+
+impl HandoffKey {
+    pub fn generate() -> Self {
+        let mut key = [0u8; 32];
+        thread_rng().fill_bytes(&mut key);
+        Self(key)
+    }
+}
+
+impl fmt::Display for HandoffKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            base64::encode_config(self.0, base64::URL_SAFE_NO_PAD)
+        )
+    }
+}
+
+impl fmt::Debug for HandoffKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "HandoffKey(..)")
+    }
+}
+
+impl FromStr for HandoffKey {
+    type Err = HandoffError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = base64::decode_config(s.trim(), base64::URL_SAFE_NO_PAD)
+            .map_err(|_| HandoffError::WrongKey)?;
+        let key = bytes.try_into().map_err(|_| HandoffError::WrongKey)?;
+        Ok(Self(key))
+    }
+}
+
+/// A call in progress, packed up to carry on from another machine
+///
+/// Holds our identity in the room and each connection's keys, so whoever
+/// has it can speak as us; it only ever leaves the machine sealed.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CallHandoff {
+    /// Our peer ID, which the other peers know us by
+    pub self_id: String,
+    pub session_id: String,
+    pub connection_link: String,
+    pub is_host: bool,
+    pub original_host_id: String,
+    pub created_at: u64,
+    /// Names and positions of everyone in the call, ourselves first
+    pub participants: Vec<(String, (f32, f32, f32))>,
+    pub peers: Vec<Peer>,
+    /// Each connection, by the peer at the other end
+    pub connections: Vec<(String, ConnectionHandoff)>,
+    /// Unix time after which the handoff is refused
+    pub expires_at: u64,
+}
+
+impl CallHandoff {
+    /// Encrypts the handoff, returning it as text along with the key to open it
+    pub fn seal(&self) -> anyhow::Result<(String, HandoffKey)> {
+        let key = HandoffKey::generate();
+        let mut nonce = [0u8; NONCE_LEN];
+        thread_rng().fill_bytes(&mut nonce);
+
+        let plaintext = bincode::serialize(self)?;
+        let ciphertext = XChaCha20Poly1305::new(&key.0.into())
+            .encrypt(XNonce::from_slice(&nonce), plaintext.as_slice())
+            .map_err(|e| anyhow::anyhow!("Encryption error: {}", e))?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        let text = format!(
+            "{}{}",
+            SEALED_PREFIX,
+            base64::encode_config(sealed, base64::URL_SAFE_NO_PAD)
+        );
+        Ok((text, key))
+    }
+
+    /// Decrypts a sealed handoff, refusing it once expired at `now`
+    pub fn open(sealed: &str, key: &HandoffKey, now: u64) -> Result<Self, HandoffError> {
+        let encoded = sealed
+            .trim()
+            .strip_prefix(SEALED_PREFIX)
+            .ok_or(HandoffError::Malformed)?;
+        let sealed = base64::decode_config(encoded, base64::URL_SAFE_NO_PAD)
+            .map_err(|_| HandoffError::Malformed)?;
+        if sealed.len() < NONCE_LEN {
+            return Err(HandoffError::Malformed);
+        }
+
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let plaintext = XChaCha20Poly1305::new(&key.0.into())
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| HandoffError::WrongKey)?;
+        let handoff: CallHandoff =
+            bincode::deserialize(&plaintext).map_err(|_| HandoffError::Malformed)?;
+
+        if now > handoff.expires_at {
+            return Err(HandoffError::Expired);
+        }
+        Ok(handoff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handoff(expires_at: u64) -> CallHandoff {
+        CallHandoff {
+            self_id: "me".to_string(),
+            session_id: "room".to_string(),
+            connection_link: "resonance://room".to_string(),
+            is_host: false,
+            original_host_id: "host-room".to_string(),
+            created_at: 1_000,
+            participants: vec![("Me".to_string(), (0.0, 0.0, 0.0))],
+            peers: Vec::new(),
+            connections: Vec::new(),
+            expires_at,
+        }
+    }
+
+    #[test]
+    fn test_sealed_handoff_opens_only_with_its_key() {
+        let (sealed, key) = handoff(2_000).seal().unwrap();
+        assert!(sealed.starts_with(SEALED_PREFIX));
+
+        // The key survives being written down and typed back in
+        let key: HandoffKey = key.to_string().parse().unwrap();
+        let opened = CallHandoff::open(&sealed, &key, 1_500).unwrap();
+        assert_eq!(opened.self_id, "me");
+        assert_eq!(opened.original_host_id, "host-room");
+
+        assert_eq!(
+            CallHandoff::open(&sealed, &HandoffKey::generate(), 1_500).err(),
+            Some(HandoffError::WrongKey)
+        );
+        assert_eq!(
+            CallHandoff::open(&sealed, &key, 2_001).err(),
+            Some(HandoffError::Expired)
+        );
+        assert_eq!(
+            CallHandoff::open("resonance://room", &key, 1_500).err(),
+            Some(HandoffError::Malformed)
+        );
+        assert!("not a key".parse::<HandoffKey>().is_err());
+    }
+}
@@ -0,0 +1,234 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::app::config::Config;
+use crate::audio::DeviceCapabilities;
+use crate::network::{bind_udp, detect_nat, socket_buffers, GrantedBuffers};
+
+/// Longest the STUN check waits for any server to answer
+const STUN_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// A clock set before this, the start of 2024, is taken to be wrong; join
+/// links, session time limits and handoffs all carry wall-clock times
+const EARLIEST_PLAUSIBLE_TIME: u64 = 1_704_067_200;
+
+/// What a startup check concluded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Passed,
+    /// Calls may work, with something missing or degraded
+    Warning,
+    /// Calls aren't likely to work until it's fixed
+    Failed,
+}
+
+/// One line of the startup checklist
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthCheck {
+    /// Translation key naming what was checked
+    pub label: &'static str,
+    pub status: CheckStatus,
+    /// What was found, such as the error hit
+    pub detail: String,
+    /// Translation key of what to try, unless it passed
+    pub hint: Option<&'static str>,
+}
+
+impl HealthCheck {
+    fn passed(label: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            label,
+            status: CheckStatus::Passed,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn problem(
+        label: &'static str,
+        status: CheckStatus,
+        detail: impl Into<String>,
+        hint: &'static str,
+    ) -> Self {
+        Self {
+            label,
+            status,
+            detail: detail.into(),
+            hint: Some(hint),
+        }
+    }
+}
+
+/// Runs every startup check, in the order they're shown
+///
+/// `config_error` is why the configuration file couldn't be loaded, if it
+/// couldn't, and `devices` the audio devices found.
+pub async fn run_checks(
+    config: &Config,
+    config_error: Option<&str>,
+    devices: &[DeviceCapabilities],
+) -> Vec<HealthCheck> {
+    let unix_now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let mut checks = vec![check_config(config_error), check_devices(config, devices)];
+    checks.extend(check_network(config).await);
+    checks.push(check_clock(unix_now));
+    checks
+}
+
+/// Whether any check calls for the user's attention
+pub fn needs_attention(checks: &[HealthCheck]) -> bool {
+    checks
+        .iter()
+        .any(|check| check.status != CheckStatus::Passed)
+}
+
+fn check_config(error: Option<&str>) -> HealthCheck {
+    match error {
+        None => HealthCheck::passed("health.config", ""),
+        Some(error) => HealthCheck::problem(
+            "health.config",
+            CheckStatus::Warning,
+            error,
+            "health.hint_config",
+        ),
+    }
+}
+
+fn check_devices(config: &Config, devices: &[DeviceCapabilities]) -> HealthCheck {
+    let count = |is_input: bool| {
+        devices
+            .iter()
+            .filter(|capabilities| capabilities.device.is_input == is_input)
+            .count()
+    };
+    let (inputs, outputs) = (count(true), count(false));
+    if inputs == 0 || outputs == 0 {
+        let missing = if inputs == 0 {
+            "No microphone found"
+        } else {
+            "No speakers or headphones found"
+        };
+        return HealthCheck::problem(
+            "health.audio",
+            CheckStatus::Failed,
+            missing,
+            "health.hint_audio",
+        );
+    }
+
+    let present = |is_input: bool, id: &Option<String>| {
+        id.as_ref().is_none_or(|id| {
+            devices.iter().any(|capabilities| {
+                capabilities.device.is_input == is_input && capabilities.device.id == *id
+            })
+        })
+    };
+    for (is_input, id) in [(true, &config.input_device), (false, &config.output_device)] {
+        if !present(is_input, id) {
+            return HealthCheck::problem(
+                "health.audio",
+                CheckStatus::Warning,
+                format!("{} isn't connected", id.as_deref().unwrap_or_default()),
+                "health.hint_audio_missing",
+            );
+        }
+    }
+    HealthCheck::passed(
+        "health.audio",
+        format!("{} inputs, {} outputs", inputs, outputs),
+    )
+}
+
+/// Binds a socket as calls would, then asks STUN for our public address on it
+async fn check_network(config: &Config) -> Vec<HealthCheck> {
+    let socket = match bind_udp(&config.bind_address).await {
+        Ok(socket) => socket,
+        Err(e) => {
+            return vec![
+                HealthCheck::problem(
+                    "health.udp",
+                    CheckStatus::Failed,
+                    e.to_string(),
+                    "health.hint_udp",
+                ),
+                HealthCheck::problem(
+                    "health.stun",
+                    CheckStatus::Warning,
+                    "Skipped without a socket",
+                    "health.hint_udp",
+                ),
+            ];
+        }
+    };
+    let local = socket
+        .local_addr()
+        .map(|address| address.to_string())
+        .unwrap_or_default();
+    let udp = match GrantedBuffers::of(&socket) {
+        Ok(granted) if granted.capped(socket_buffers()) => HealthCheck::problem(
+            "health.udp",
+            CheckStatus::Warning,
+            format!("{}, buffers capped at {}", local, granted),
+            "health.hint_udp_buffers",
+        ),
+        Ok(granted) => HealthCheck::passed("health.udp", format!("{}, {}", local, granted)),
+        Err(_) => HealthCheck::passed("health.udp", local),
+    };
+
+    let stun_config = config.ice_servers.stun_config();
+    let stun = match tokio::time::timeout(STUN_TIMEOUT, detect_nat(&socket, &stun_config)).await {
+        Ok(Ok(report)) => HealthCheck::passed(
+            "health.stun",
+            format!("{}:{}", report.mapped.ip, report.mapped.port),
+        ),
+        Ok(Err(e)) => HealthCheck::problem(
+            "health.stun",
+            CheckStatus::Warning,
+            e.to_string(),
+            "health.hint_stun",
+        ),
+        Err(_) => HealthCheck::problem(
+            "health.stun",
+            CheckStatus::Warning,
+            format!("No answer within {} s", STUN_TIMEOUT.as_secs()),
+            "health.hint_stun",
+        ),
+    };
+    vec![udp, stun]
+}
+
+fn check_clock(unix_now: u64) -> HealthCheck {
+    if unix_now < EARLIEST_PLAUSIBLE_TIME {
+        HealthCheck::problem(
+            "health.clock",
+            CheckStatus::Warning,
+            format!("The clock reads {} seconds since 1970", unix_now),
+            "health.hint_clock",
+        )
+    } else {
+        HealthCheck::passed("health.clock", "")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offline_checks() {
+        assert_eq!(check_config(None).status, CheckStatus::Passed);
+        let unreadable = check_config(Some("Failed to parse config"));
+        assert_eq!(unreadable.status, CheckStatus::Warning);
+        assert_eq!(unreadable.hint, Some("health.hint_config"));
+
+        let no_devices = check_devices(&Config::default(), &[]);
+        assert_eq!(no_devices.status, CheckStatus::Failed);
+        assert_eq!(no_devices.detail, "No microphone found");
+
+        assert_eq!(check_clock(0).status, CheckStatus::Warning);
+        assert_eq!(check_clock(1_800_000_000).status, CheckStatus::Passed);
+        assert!(needs_attention(&[no_devices, check_clock(1_800_000_000)]));
+        assert!(!needs_attention(&[check_config(None)]));
+    }
+}
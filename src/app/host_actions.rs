@@ -0,0 +1,164 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::Instant;
+
+use crate::app::dnd::JoinRequest;
+
+/// Most host actions the history keeps
+pub const ACTION_HISTORY_LEN: usize = 20;
+
+/// Something the host did to the room, recorded so it can be taken again or undone
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HostAction {
+    /// Turned away peers waiting to join
+    DenyJoins { requests: Vec<JoinRequest> },
+    /// Put peers turned away back in line, for the host to let in
+    RequeueJoins { requests: Vec<JoinRequest> },
+    /// Muted everyone but the host, or let everyone speak again
+    MuteAll { enabled: bool },
+    /// Let a muted peer speak
+    GrantSpeak { peer_id: String, name: String },
+}
+
+impl HostAction {
+    /// The action that reverses this one, if anything can
+    ///
+    /// Peers turned away are put back in line, if they haven't been told
+    /// yet; a peer let speak can't be muted again on its own.
+    pub fn inverse(&self) -> Option<HostAction> {
+        match self {
+            HostAction::DenyJoins { requests } => Some(HostAction::RequeueJoins {
+                requests: requests.clone(),
+            }),
+            HostAction::RequeueJoins { requests } => Some(HostAction::DenyJoins {
+                requests: requests.clone(),
+            }),
+            HostAction::MuteAll { enabled } => Some(HostAction::MuteAll { enabled: !enabled }),
+            HostAction::GrantSpeak { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for HostAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = |requests: &[JoinRequest]| {
+            requests
+                .iter()
+                .map(|request| request.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match self {
+            HostAction::DenyJoins { requests } => write!(f, "Denied {}", names(requests)),
+            HostAction::RequeueJoins { requests } => {
+                write!(f, "Invited back {}", names(requests))
+            }
+            HostAction::MuteAll { enabled: true } => write!(f, "Muted everyone"),
+            HostAction::MuteAll { enabled: false } => write!(f, "Let everyone speak"),
+            HostAction::GrantSpeak { name, .. } => write!(f, "Let {} speak", name),
+        }
+    }
+}
+
+/// A host action in the history
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedAction {
+    /// Refers to the action when taking it again or undoing it
+    pub id: u64,
+    pub at: Instant,
+    pub action: HostAction,
+    /// Whether the action has been undone
+    pub undone: bool,
+}
+
+/// The host's most recent actions in the room
+#[derive(Debug, Clone, Default)]
+pub struct HostActionHistory {
+    actions: VecDeque<RecordedAction>,
+    next_id: u64,
+}
+
+impl HostActionHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an action, forgetting the oldest past [`ACTION_HISTORY_LEN`], and returns its id
+    pub fn record(&mut self, action: HostAction) -> u64 {
+        if self.actions.len() == ACTION_HISTORY_LEN {
+            self.actions.pop_front();
+        }
+        self.next_id += 1;
+        self.actions.push_back(RecordedAction {
+            id: self.next_id,
+            at: Instant::now(),
+            action,
+            undone: false,
+        });
+        self.next_id
+    }
+
+    pub fn get(&self, id: u64) -> Option<&RecordedAction> {
+        self.actions.iter().find(|recorded| recorded.id == id)
+    }
+
+    pub fn mark_undone(&mut self, id: u64) {
+        if let Some(recorded) = self.actions.iter_mut().find(|recorded| recorded.id == id) {
+            recorded.undone = true;
+        }
+    }
+
+    /// Actions kept, oldest first
+    pub fn actions(&self) -> Vec<RecordedAction> {
+        self.actions.iter().cloned().collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.actions.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_undoes_what_it_can() {
+        let requests = vec![JoinRequest {
+            peer_id: "alice".to_string(),
+            name: "Alice".to_string(),
+        }];
+        let deny = HostAction::DenyJoins {
+            requests: requests.clone(),
+        };
+        assert_eq!(deny.to_string(), "Denied Alice");
+        assert_eq!(
+            deny.inverse(),
+            Some(HostAction::RequeueJoins {
+                requests: requests.clone()
+            })
+        );
+        assert_eq!(deny.inverse().unwrap().inverse(), Some(deny.clone()));
+        assert_eq!(
+            HostAction::MuteAll { enabled: true }.inverse(),
+            Some(HostAction::MuteAll { enabled: false })
+        );
+        let grant = HostAction::GrantSpeak {
+            peer_id: "alice".to_string(),
+            name: "Alice".to_string(),
+        };
+        assert_eq!(grant.inverse(), None);
+
+        let mut history = HostActionHistory::new();
+        let first = history.record(deny);
+        for _ in 0..ACTION_HISTORY_LEN {
+            history.record(grant.clone());
+        }
+        assert!(history.get(first).is_none());
+        assert_eq!(history.actions().len(), ACTION_HISTORY_LEN);
+
+        let last = history.actions().last().unwrap().id;
+        history.mark_undone(last);
+        assert!(history.get(last).unwrap().undone);
+    }
+}
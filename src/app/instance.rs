@@ -0,0 +1,271 @@
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::io::{self, Write};
+use std::net::Ipv4Addr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+
+use crate::app::handoff::HandoffKey;
+use crate::app::topology::{self, Plane};
+
+/// Lock file held by the running instance, in the configuration directory
+const LOCK_FILE_NAME: &str = "instance.lock";
+
+/// Longest a lock's port takes to answer before the lock is taken as stale
+const PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Longest a takeover waits on the running instance to answer, then to exit
+pub const TAKEOVER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Marks us as the one instance using a configuration directory
+///
+/// The lock file holds our pid, a loopback port we listen on, and a token
+/// only those who can read the file know. A second instance sends the
+/// token to the port to take over our call. A lock whose port no longer
+/// answers was left by an instance that crashed, and is replaced. The file
+/// is removed when the lock is dropped.
+pub struct InstanceLock {
+    path: PathBuf,
+    token: String,
+    listener: Option<TcpListener>,
+}
+
+/// Another instance, holding the lock
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunningInstance {
+    pub pid: u32,
+    port: u16,
+    token: String,
+}
+
+/// What claiming the lock found
+pub enum Claim {
+    /// We're the only instance
+    Acquired(InstanceLock),
+    /// Another instance is already running
+    Running(RunningInstance),
+}
+
+/// A second instance asking for our call, after which we're expected to exit
+pub struct TakeoverRequest {
+    stream: TcpStream,
+}
+
+/// Claims the lock in `directory`, unless an instance that's still running holds it
+pub async fn claim(directory: &Path) -> Result<Claim> {
+    fs::create_dir_all(directory)?;
+    let path = directory.join(LOCK_FILE_NAME);
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+    let port = listener.local_addr()?.port();
+    let token: String = rand::random::<[u8; 16]>()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+
+    // A second try, once a stale lock is out of the way
+    for _ in 0..2 {
+        match create_private(&path) {
+            Ok(mut file) => {
+                writeln!(file, "{} {} {}", std::process::id(), port, token)?;
+                return Ok(Claim::Acquired(InstanceLock {
+                    path,
+                    token,
+                    listener: Some(listener),
+                }));
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                let contents = fs::read_to_string(&path).unwrap_or_default();
+                if let Some(running) = RunningInstance::parse(&contents) {
+                    if running.is_alive().await {
+                        return Ok(Claim::Running(running));
+                    }
+                }
+                log::info!("Replacing the stale lock at {}", path.display());
+                match fs::remove_file(&path) {
+                    Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+                    _ => {}
+                }
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Err(anyhow!("Couldn't take the lock at {}", path.display()))
+}
+
+/// Claims the lock once the instance holding it has exited, as it does
+/// after a takeover
+pub async fn claim_after_exit(directory: &Path) -> Result<InstanceLock> {
+    let deadline = tokio::time::Instant::now() + TAKEOVER_TIMEOUT;
+    loop {
+        match claim(directory).await? {
+            Claim::Acquired(lock) => return Ok(lock),
+            Claim::Running(running) if tokio::time::Instant::now() >= deadline => {
+                return Err(anyhow!(
+                    "The running instance (pid {}) didn't exit",
+                    running.pid
+                ));
+            }
+            Claim::Running(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+        }
+    }
+}
+
+/// Creates a file only its owner can read, failing if it's already there
+fn create_private(path: &Path) -> io::Result<fs::File> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)
+}
+
+impl InstanceLock {
+    /// Starts listening for takeovers, which arrive on the returned channel
+    pub fn listen(&mut self) -> mpsc::UnboundedReceiver<TakeoverRequest> {
+        let (requests_tx, requests) = mpsc::unbounded_channel();
+        if let Some(listener) = self.listener.take() {
+            let token = self.token.clone();
+            topology::spawn(Plane::Control, "takeover", async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let (requests_tx, token) = (requests_tx.clone(), token.clone());
+                    // Each on its own, so a connection that never writes can't hold up the rest
+                    topology::spawn(Plane::Control, "takeover-request", async move {
+                        if let Some(request) = TakeoverRequest::read(stream, &token).await {
+                            let _ = requests_tx.send(request);
+                        }
+                    });
+                }
+            });
+        }
+        requests
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+impl TakeoverRequest {
+    /// Reads a request from `stream`, ignoring liveness probes and anyone
+    /// without the token
+    async fn read(mut stream: TcpStream, token: &str) -> Option<Self> {
+        let mut line = String::new();
+        BufReader::new(&mut stream)
+            .read_line(&mut line)
+            .await
+            .ok()?;
+        let mut fields = line.split_whitespace();
+        (fields.next() == Some("takeover") && fields.next() == Some(token))
+            .then_some(Self { stream })
+    }
+
+    /// Hands over our call, `None` if we aren't in one
+    pub async fn answer(mut self, call: Option<(String, HandoffKey)>) -> io::Result<()> {
+        let answer = match call {
+            Some((sealed, key)) => format!("handoff {} {}\n", key, sealed),
+            None => "idle\n".to_string(),
+        };
+        self.stream.write_all(answer.as_bytes()).await?;
+        self.stream.shutdown().await
+    }
+}
+
+impl RunningInstance {
+    fn parse(contents: &str) -> Option<Self> {
+        let mut fields = contents.split_whitespace();
+        Some(Self {
+            pid: fields.next()?.parse().ok()?,
+            port: fields.next()?.parse().ok()?,
+            token: fields.next()?.to_string(),
+        })
+    }
+
+    async fn is_alive(&self) -> bool {
+        let connect = TcpStream::connect((Ipv4Addr::LOCALHOST, self.port));
+        matches!(
+            tokio::time::timeout(PROBE_TIMEOUT, connect).await,
+            Ok(Ok(_))
+        )
+    }
+
+    /// Asks the instance to hand over its call and exit
+    ///
+    /// Returns the sealed call and its key, or `None` if it wasn't in one.
+    pub async fn take_over(&self) -> Result<Option<(String, HandoffKey)>> {
+        let exchange = async {
+            let mut stream = TcpStream::connect((Ipv4Addr::LOCALHOST, self.port)).await?;
+            stream
+                .write_all(format!("takeover {}\n", self.token).as_bytes())
+                .await?;
+            let mut line = String::new();
+            BufReader::new(stream).read_line(&mut line).await?;
+            parse_answer(&line)
+        };
+        tokio::time::timeout(TAKEOVER_TIMEOUT, exchange)
+            .await
+            .map_err(|_| anyhow!("The running instance (pid {}) didn't answer", self.pid))?
+    }
+}
+
+fn parse_answer(line: &str) -> Result<Option<(String, HandoffKey)>> {
+    let mut fields = line.split_whitespace();
+    match (fields.next(), fields.next(), fields.next()) {
+        (Some("idle"), _, _) => Ok(None),
+        (Some("handoff"), Some(key), Some(sealed)) => Ok(Some((sealed.to_string(), key.parse()?))),
+        _ => Err(anyhow!("The running instance didn't hand over its call")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_second_instance_takes_over() {
+        let directory =
+            std::env::temp_dir().join(format!("resonance-instance-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+
+        let Claim::Acquired(mut lock) = claim(&directory).await.unwrap() else {
+            panic!("the first claim should get the lock");
+        };
+        let Claim::Running(running) = claim(&directory).await.unwrap() else {
+            panic!("the lock is held");
+        };
+        assert_eq!(running.pid, std::process::id());
+
+        // Without the token, nothing reaches the running instance
+        let mut requests = lock.listen();
+        let stranger = RunningInstance {
+            token: "guess".to_string(),
+            ..running.clone()
+        };
+        let refused = tokio::spawn(async move { stranger.take_over().await });
+
+        let taking_over = tokio::spawn(async move { running.take_over().await });
+        requests.recv().await.unwrap().answer(None).await.unwrap();
+        assert!(taking_over.await.unwrap().unwrap().is_none());
+        drop(lock);
+        assert!(refused.await.unwrap().is_err());
+        assert!(requests.try_recv().is_err());
+
+        let lock = claim_after_exit(&directory).await.unwrap();
+        drop(lock);
+
+        // A lock left by a crash is replaced
+        fs::write(directory.join(LOCK_FILE_NAME), "1 1 stale").unwrap();
+        assert!(matches!(
+            claim(&directory).await.unwrap(),
+            Claim::Acquired(_)
+        ));
+        let _ = fs::remove_dir_all(&directory);
+    }
+}
@@ -0,0 +1,232 @@
+use anyhow::{anyhow, Result};
+
+/// Something holding the machine awake, which lets it sleep again when dropped
+type Hold = Box<dyn Send>;
+
+/// Keeps the machine from sleeping or dimming the screen while we're in a room
+///
+/// A laptop that sleeps mid-call drops everyone's audio. Held through
+/// logind's `systemd-inhibit` on Linux, `caffeinate` on macOS and
+/// `SetThreadExecutionState` on Windows, and released as soon as we leave.
+/// Both helper programs go away by themselves should we exit without
+/// releasing them.
+pub struct KeepAwake {
+    start: fn() -> Result<Hold>,
+    hold: Option<Hold>,
+    // Holding failed, so isn't tried again until we next leave a room
+    failed: bool,
+}
+
+impl Default for KeepAwake {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KeepAwake {
+    pub fn new() -> Self {
+        Self::with_start(|| Ok(Box::new(Inhibitor::start()?) as Hold))
+    }
+
+    fn with_start(start: fn() -> Result<Hold>) -> Self {
+        Self {
+            start,
+            hold: None,
+            failed: false,
+        }
+    }
+
+    /// Keeps the machine awake while `wanted`, and lets it sleep again once not
+    pub fn set_active(&mut self, wanted: bool) {
+        if !wanted {
+            if self.hold.take().is_some() {
+                log::info!("No longer keeping the machine awake");
+            }
+            self.failed = false;
+            return;
+        }
+        if self.hold.is_some() || self.failed {
+            return;
+        }
+        match (self.start)() {
+            Ok(hold) => {
+                log::info!("Keeping the machine awake while in the room");
+                self.hold = Some(hold);
+            }
+            Err(e) => {
+                log::warn!("Couldn't keep the machine awake: {}", e);
+                self.failed = true;
+            }
+        }
+    }
+}
+
+/// `systemd-inhibit` blocking idle and sleep for as long as `cat`, which
+/// it runs, has its input open; that closes with us however we exit
+#[cfg(target_os = "linux")]
+struct Inhibitor {
+    child: std::process::Child,
+}
+
+#[cfg(target_os = "linux")]
+impl Inhibitor {
+    fn start() -> Result<Self> {
+        use std::process::{Command, Stdio};
+
+        let child = Command::new("systemd-inhibit")
+            .args([
+                "--what=idle:sleep",
+                "--who=resonance",
+                "--why=In a call",
+                "--mode=block",
+                "cat",
+            ])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| anyhow!("Couldn't run systemd-inhibit: {}", e))?;
+        Ok(Self { child })
+    }
+}
+
+/// `caffeinate` keeping the display and system awake, for no longer than we run
+#[cfg(target_os = "macos")]
+struct Inhibitor {
+    child: std::process::Child,
+}
+
+#[cfg(target_os = "macos")]
+impl Inhibitor {
+    fn start() -> Result<Self> {
+        use std::process::{Command, Stdio};
+
+        let child = Command::new("caffeinate")
+            .args(["-dis", "-w", &std::process::id().to_string()])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| anyhow!("Couldn't run caffeinate: {}", e))?;
+        Ok(Self { child })
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+impl Drop for Inhibitor {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// A thread of its own holding the system and display awake, as the
+/// execution state belongs to the thread that set it
+#[cfg(target_os = "windows")]
+struct Inhibitor {
+    stop: Option<std::sync::mpsc::Sender<()>>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+#[cfg(target_os = "windows")]
+impl Inhibitor {
+    fn start() -> Result<Self> {
+        use crate::app::topology::{self, Plane};
+
+        #[link(name = "kernel32")]
+        extern "system" {
+            fn SetThreadExecutionState(flags: u32) -> u32;
+        }
+        const ES_CONTINUOUS: u32 = 0x8000_0000;
+        const ES_SYSTEM_REQUIRED: u32 = 0x0000_0001;
+        const ES_DISPLAY_REQUIRED: u32 = 0x0000_0002;
+
+        let (stop, stopped) = std::sync::mpsc::channel::<()>();
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+        let thread = topology::spawn_thread(Plane::Control, "keep-awake", move || {
+            let previous = unsafe {
+                SetThreadExecutionState(ES_CONTINUOUS | ES_SYSTEM_REQUIRED | ES_DISPLAY_REQUIRED)
+            };
+            let _ = ready_tx.send(previous != 0);
+            if previous == 0 {
+                return;
+            }
+            // Until told to stop, or the sender is dropped
+            let _ = stopped.recv();
+            unsafe {
+                SetThreadExecutionState(ES_CONTINUOUS);
+            }
+        })?;
+
+        match ready_rx.recv() {
+            Ok(true) => Ok(Self {
+                stop: Some(stop),
+                thread: Some(thread),
+            }),
+            _ => {
+                let _ = thread.join();
+                Err(anyhow!("SetThreadExecutionState failed"))
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl Drop for Inhibitor {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+struct Inhibitor;
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+impl Inhibitor {
+    fn start() -> Result<Self> {
+        Err(anyhow!(
+            "Keeping the machine awake isn't supported on this platform"
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static HELD: AtomicUsize = AtomicUsize::new(0);
+
+    struct Counted;
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            HELD.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_holds_only_while_wanted() {
+        let mut keep_awake = KeepAwake::with_start(|| {
+            HELD.fetch_add(1, Ordering::SeqCst);
+            Ok(Box::new(Counted))
+        });
+        keep_awake.set_active(true);
+        keep_awake.set_active(true);
+        assert!(keep_awake.hold.is_some());
+        assert_eq!(HELD.load(Ordering::SeqCst), 1);
+
+        keep_awake.set_active(false);
+        assert!(keep_awake.hold.is_none());
+        assert_eq!(HELD.load(Ordering::SeqCst), 0);
+
+        // A failure isn't retried on every check, only on the next room
+        let mut failing = KeepAwake::with_start(|| Err(anyhow!("no logind")));
+        failing.set_active(true);
+        assert!(failing.failed);
+        failing.set_active(false);
+        assert!(!failing.failed);
+    }
+}
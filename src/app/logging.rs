@@ -0,0 +1,294 @@
+use log::{LevelFilter, Log, Metadata, Record};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use crate::app::config::Config;
+
+const LOG_FILE_NAME: &str = "resonance.log";
+const ROTATED_PREFIX: &str = "resonance-";
+
+/// Where logs go, how verbose they are, and when they are rotated
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogSettings {
+    /// Directory holding the current and rotated log files
+    pub directory: PathBuf,
+    /// Level used for modules without an override
+    pub level: LevelFilter,
+    /// Per-module overrides, e.g. `("network", LevelFilter::Debug)`
+    pub module_levels: Vec<(String, LevelFilter)>,
+    /// Rotate once the current file reaches this many bytes
+    pub max_file_size: u64,
+    /// Rotate once the current file has been open this long
+    pub max_file_age: Duration,
+    /// Number of rotated files to keep
+    pub max_files: usize,
+}
+
+impl LogSettings {
+    /// Builds log settings from the application configuration
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            directory: config
+                .log_dir
+                .as_ref()
+                .map(PathBuf::from)
+                .unwrap_or_else(default_log_dir),
+            level: config.log_level,
+            module_levels: config.log_module_levels.clone(),
+            max_file_size: config.log_max_size_kb * 1024,
+            max_file_age: Duration::from_secs(config.log_rotate_hours * 60 * 60),
+            max_files: config.log_max_files,
+        }
+    }
+
+    /// Gets the level for a log target, using the most specific module override
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        // Targets are full module paths; overrides are relative to the crate
+        let target = target.strip_prefix("resonance::").unwrap_or(target);
+
+        self.module_levels
+            .iter()
+            .filter(|(module, _)| target == module || target.starts_with(&format!("{}::", module)))
+            .max_by_key(|(module, _)| module.len())
+            .map(|(_, level)| *level)
+            .unwrap_or(self.level)
+    }
+
+    /// The most verbose level any module is logged at
+    fn max_level(&self) -> LevelFilter {
+        self.module_levels
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.level, Ord::max)
+    }
+}
+
+/// Default log directory when none is configured
+pub fn default_log_dir() -> PathBuf {
+    std::env::temp_dir().join("resonance-logs")
+}
+
+struct LogFile {
+    file: File,
+    size: u64,
+    opened_at: SystemTime,
+}
+
+/// Logger that writes to a file and rotates it by size and age
+pub struct RotatingFileLogger {
+    settings: LogSettings,
+    current: Mutex<LogFile>,
+}
+
+impl RotatingFileLogger {
+    /// Creates the log directory if needed and opens the current log file
+    pub fn new(settings: LogSettings) -> io::Result<Self> {
+        fs::create_dir_all(&settings.directory)?;
+        let current = open_log_file(&settings.directory.join(LOG_FILE_NAME))?;
+
+        Ok(Self {
+            settings,
+            current: Mutex::new(current),
+        })
+    }
+
+    /// Path of the file currently being written
+    pub fn current_log_file(&self) -> PathBuf {
+        self.settings.directory.join(LOG_FILE_NAME)
+    }
+
+    fn rotate(&self, current: &mut LogFile) -> io::Result<()> {
+        current.file.flush()?;
+
+        let stamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let rotated = self
+            .settings
+            .directory
+            .join(format!("{}{}.log", ROTATED_PREFIX, stamp));
+        fs::rename(self.current_log_file(), rotated)?;
+
+        *current = open_log_file(&self.current_log_file())?;
+        prune_rotated_files(&self.settings.directory, self.settings.max_files)
+    }
+
+    fn needs_rotation(&self, current: &LogFile) -> bool {
+        let age = current.opened_at.elapsed().unwrap_or_default();
+        current.size >= self.settings.max_file_size || age >= self.settings.max_file_age
+    }
+}
+
+impl Log for RotatingFileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.settings.level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        let line = format!(
+            "{:.3} {:<5} {}: {}\n",
+            timestamp,
+            record.level(),
+            record.target(),
+            record.args()
+        );
+
+        let mut current = self.current.lock().unwrap();
+        if self.needs_rotation(&current) {
+            // Keep logging to the old file rather than lose the message
+            let _ = self.rotate(&mut current);
+        }
+
+        if current.file.write_all(line.as_bytes()).is_ok() {
+            current.size += line.len() as u64;
+        }
+    }
+
+    fn flush(&self) {
+        let _ = self.current.lock().unwrap().file.flush();
+    }
+}
+
+fn open_log_file(path: &Path) -> io::Result<LogFile> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let size = file.metadata()?.len();
+
+    Ok(LogFile {
+        file,
+        size,
+        opened_at: SystemTime::now(),
+    })
+}
+
+/// Rotated log files in a directory, oldest first
+fn rotated_files(directory: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = fs::read_dir(directory)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .map(|name| name.starts_with(ROTATED_PREFIX) && name.ends_with(".log"))
+                .unwrap_or(false)
+        })
+        .collect();
+
+    // The timestamp in the name sorts the same way as the files' ages
+    files.sort();
+    Ok(files)
+}
+
+fn prune_rotated_files(directory: &Path, max_files: usize) -> io::Result<()> {
+    let files = rotated_files(directory)?;
+    let excess = files.len().saturating_sub(max_files);
+
+    for path in &files[..excess] {
+        fs::remove_file(path)?;
+    }
+
+    Ok(())
+}
+
+/// Installs the rotating file logger as the global logger
+///
+/// Returns the path of the current log file.
+pub fn init(settings: LogSettings) -> anyhow::Result<PathBuf> {
+    let max_level = settings.max_level();
+    let logger = RotatingFileLogger::new(settings)?;
+    let path = logger.current_log_file();
+
+    log::set_boxed_logger(Box::new(logger))?;
+    log::set_max_level(max_level);
+
+    Ok(path)
+}
+
+/// Opens a file or directory with the platform's default handler
+pub fn reveal(path: &Path) -> io::Result<()> {
+    let opener = if cfg!(target_os = "macos") {
+        "open"
+    } else if cfg!(target_os = "windows") {
+        "explorer"
+    } else {
+        "xdg-open"
+    };
+
+    Command::new(opener).arg(path).spawn().map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_settings(directory: &Path) -> LogSettings {
+        LogSettings {
+            directory: directory.to_path_buf(),
+            level: LevelFilter::Info,
+            module_levels: vec![
+                ("network".to_string(), LevelFilter::Debug),
+                ("network::p2p".to_string(), LevelFilter::Off),
+            ],
+            max_file_size: 64,
+            max_file_age: Duration::from_secs(3600),
+            max_files: 2,
+        }
+    }
+
+    #[test]
+    fn test_module_level_overrides() {
+        let settings = test_settings(Path::new("unused"));
+
+        assert_eq!(
+            settings.level_for("resonance::app::session"),
+            LevelFilter::Info
+        );
+        assert_eq!(
+            settings.level_for("resonance::network::connection_manager"),
+            LevelFilter::Debug
+        );
+        assert_eq!(
+            settings.level_for("resonance::network::p2p"),
+            LevelFilter::Off
+        );
+        assert_eq!(settings.max_level(), LevelFilter::Debug);
+    }
+
+    #[test]
+    fn test_rotation_keeps_max_files() {
+        let directory = std::env::temp_dir().join(format!(
+            "resonance-logging-rotation-test-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&directory);
+
+        let logger = RotatingFileLogger::new(test_settings(&directory)).unwrap();
+        for i in 0..20 {
+            logger.log(
+                &Record::builder()
+                    .args(format_args!("message number {}", i))
+                    .level(log::Level::Info)
+                    .target("resonance::app")
+                    .build(),
+            );
+            // Rotated files are named by millisecond
+            std::thread::sleep(Duration::from_millis(2));
+        }
+
+        assert!(logger.current_log_file().exists());
+        assert_eq!(rotated_files(&directory).unwrap().len(), 2);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
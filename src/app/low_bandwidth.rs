@@ -0,0 +1,64 @@
+use crate::app::config::Config;
+use crate::audio::CodecSettings;
+use crate::network::MAX_FRAMES_PER_BUNDLE;
+
+/// Highest Opus bitrate in low-bandwidth mode, in kilobits per second;
+/// speech stays clear at it
+pub const LOW_BANDWIDTH_BITRATE_KBPS: u32 = 12;
+
+/// Fewest audio frames packed into each datagram in low-bandwidth mode,
+/// saving a packet header for each frame bundled at a frame of latency each
+pub const LOW_BANDWIDTH_FRAMES_PER_PACKET: usize = MAX_FRAMES_PER_BUNDLE;
+
+/// Codec we send with: the configured one, held to a low bitrate in
+/// low-bandwidth mode
+///
+/// Only Opus has a bitrate to lower; the other codecs send the same either way.
+pub fn send_codec(config: &Config) -> CodecSettings {
+    if !config.low_bandwidth {
+        return config.codec;
+    }
+    CodecSettings {
+        bitrate_kbps: config.codec.bitrate_kbps.min(LOW_BANDWIDTH_BITRATE_KBPS),
+        ..config.codec
+    }
+}
+
+/// Audio frames packed into each datagram, more of them in low-bandwidth mode
+pub fn frames_per_packet(config: &Config) -> usize {
+    if config.low_bandwidth {
+        config
+            .audio_frames_per_packet
+            .max(LOW_BANDWIDTH_FRAMES_PER_PACKET)
+    } else {
+        config.audio_frames_per_packet
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::AudioCodec;
+
+    #[test]
+    fn test_preset_only_lowers_what_is_configured_higher() {
+        let mut config = Config {
+            codec: CodecSettings {
+                codec: AudioCodec::Opus,
+                bitrate_kbps: 64,
+            },
+            audio_frames_per_packet: 2,
+            ..Config::default()
+        };
+        assert_eq!(send_codec(&config).bitrate_kbps, 64);
+        assert_eq!(frames_per_packet(&config), 2);
+
+        config.low_bandwidth = true;
+        assert_eq!(send_codec(&config).codec, AudioCodec::Opus);
+        assert_eq!(send_codec(&config).bitrate_kbps, LOW_BANDWIDTH_BITRATE_KBPS);
+        assert_eq!(frames_per_packet(&config), LOW_BANDWIDTH_FRAMES_PER_PACKET);
+
+        config.codec.bitrate_kbps = 8;
+        assert_eq!(send_codec(&config).bitrate_kbps, 8);
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::HashSet;
+
+/// Room-wide "mute all", where peers raise a hand and the host lets them speak
+///
+/// The host is never muted, so it isn't tracked here; callers check for
+/// it themselves. Both the muted peer and everyone hearing it apply the
+/// same state, so ignoring the host's command on one side isn't enough to
+/// be heard.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MeetingMode {
+    enabled: bool,
+    // Peers the host let speak since everyone was muted
+    speakers: HashSet<String>,
+    // Peers asking to speak, longest waiting first
+    raised_hands: Vec<String>,
+}
+
+impl MeetingMode {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether everyone but the host is muted
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Mutes everyone but the host, or lets everyone speak again
+    ///
+    /// Either way everyone starts over: muted, with their hand down.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.speakers.clear();
+        self.raised_hands.clear();
+    }
+
+    /// Whether a peer other than the host may be heard
+    pub fn may_speak(&self, peer_id: &str) -> bool {
+        !self.enabled || self.speakers.contains(peer_id)
+    }
+
+    /// Raises or lowers a peer's hand, returning whether anything changed
+    ///
+    /// Hands only go up for peers that are muted.
+    pub fn set_hand_raised(&mut self, peer_id: &str, raised: bool) -> bool {
+        let is_raised = self.is_hand_raised(peer_id);
+        if raised && !is_raised && !self.may_speak(peer_id) {
+            self.raised_hands.push(peer_id.to_string());
+            true
+        } else if !raised && is_raised {
+            self.raised_hands.retain(|id| id != peer_id);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn is_hand_raised(&self, peer_id: &str) -> bool {
+        self.raised_hands.iter().any(|id| id == peer_id)
+    }
+
+    /// Peers with their hand up, longest waiting first
+    pub fn raised_hands(&self) -> &[String] {
+        &self.raised_hands
+    }
+
+    /// Lets a peer speak and lowers their hand
+    pub fn grant(&mut self, peer_id: &str) {
+        if self.enabled {
+            self.speakers.insert(peer_id.to_string());
+        }
+        self.raised_hands.retain(|id| id != peer_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hands_and_grants() {
+        let mut meeting = MeetingMode::new();
+        assert!(meeting.may_speak("alice"));
+        assert!(!meeting.set_hand_raised("alice", true));
+
+        meeting.set_enabled(true);
+        assert!(!meeting.may_speak("alice"));
+        assert!(meeting.set_hand_raised("bob", true));
+        assert!(meeting.set_hand_raised("alice", true));
+        assert!(!meeting.set_hand_raised("alice", true));
+        assert_eq!(meeting.raised_hands(), ["bob", "alice"]);
+
+        meeting.grant("bob");
+        assert!(meeting.may_speak("bob"));
+        assert_eq!(meeting.raised_hands(), ["alice"]);
+        assert!(!meeting.set_hand_raised("bob", true));
+
+        // Muting everyone again starts over
+        meeting.set_enabled(true);
+        assert!(!meeting.may_speak("bob"));
+        assert!(meeting.raised_hands().is_empty());
+    }
+}
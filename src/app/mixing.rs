@@ -0,0 +1,446 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::MissedTickBehavior;
+
+use crate::audio::{sanitize, AudioFrame};
+
+/// How audio is routed between peers in a room
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MixingTopology {
+    /// Every peer sends its audio to every other peer
+    #[default]
+    FullMesh,
+    /// Peers send to the host, which sends each peer one mix of everyone else
+    HostMixed,
+}
+
+impl MixingTopology {
+    /// Short name used in config files and join links
+    pub fn code(&self) -> &'static str {
+        match self {
+            MixingTopology::FullMesh => "mesh",
+            MixingTopology::HostMixed => "host",
+        }
+    }
+
+    /// Reads the topology from a join link's `mix` parameter, defaulting to full mesh
+    pub fn from_link(link: &str) -> Self {
+        link.split(['?', '&'])
+            .find_map(|param| param.strip_prefix("mix="))
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_default()
+    }
+
+    /// Adds the topology to a join link; full mesh is implied when absent
+    pub fn append_to_link(&self, link: &str) -> String {
+        match self {
+            MixingTopology::FullMesh => link.to_string(),
+            MixingTopology::HostMixed => format!("{}&mix={}", link, self.code()),
+        }
+    }
+}
+
+impl fmt::Display for MixingTopology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl FromStr for MixingTopology {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mesh" => Ok(MixingTopology::FullMesh),
+            "host" => Ok(MixingTopology::HostMixed),
+            _ => Err(format!("Unknown mixing topology: {}", s)),
+        }
+    }
+}
+
+/// Most frames held for one peer between mixes; jitter and bundled packets
+/// can bring a few at once, and beyond this the oldest are dropped
+const MAX_PENDING_FRAMES: usize = 4;
+
+/// Mixes peers' audio on the host, one stream per recipient without their own voice
+///
+/// Each peer's frames wait in line, one going into each mix. The frames at
+/// the front are summed into a running total, so a mix only has to take the
+/// recipient's own frame back out instead of re-summing everyone.
+pub struct HostMixer {
+    host_id: String,
+    pending: HashMap<String, VecDeque<AudioFrame>>,
+    // Sum of the frames at the front of each line, and the format all share
+    total: Vec<f32>,
+    format: Option<(u32, u16)>,
+    seq: HashMap<String, u64>,
+}
+
+impl HostMixer {
+    pub fn new(host_id: &str) -> Self {
+        Self {
+            host_id: host_id.to_string(),
+            pending: HashMap::new(),
+            total: Vec::new(),
+            format: None,
+            seq: HashMap::new(),
+        }
+    }
+
+    /// Queues a peer's frame behind any of its frames not yet mixed
+    ///
+    /// Frames without a `peer_id` are treated as the host's own voice. Frames
+    /// in a different format from those already queued are dropped, as is a
+    /// peer's oldest frame once [`MAX_PENDING_FRAMES`] are waiting.
+    pub fn push(&mut self, mut frame: AudioFrame) {
+        let format = (frame.sample_rate, frame.channels);
+        if *self.format.get_or_insert(format) != format {
+            log::debug!("Dropping frame in a format the mix doesn't use");
+            return;
+        }
+
+        let source = frame
+            .peer_id
+            .clone()
+            .unwrap_or_else(|| self.host_id.clone());
+        // A NaN would stay in the running total even after its frame is taken back out
+        let faults = sanitize(&mut frame.samples);
+        if faults.non_finite > 0 {
+            log::debug!("Replaced {} from {}", faults, source);
+        }
+        let queue = self.pending.entry(source).or_default();
+        if queue.is_empty() {
+            if self.total.len() < frame.len() {
+                self.total.resize(frame.len(), 0.0);
+            }
+            add_into(&mut self.total, &frame.samples);
+        } else if queue.len() == MAX_PENDING_FRAMES {
+            log::debug!("Dropping the oldest frame held for the mix");
+            let dropped = queue.pop_front().unwrap();
+            sub_into(&mut self.total, &dropped.samples);
+            let next = queue.front().unwrap_or(&frame);
+            if self.total.len() < next.len() {
+                self.total.resize(next.len(), 0.0);
+            }
+            add_into(&mut self.total, &next.samples);
+        }
+        queue.push_back(frame);
+    }
+
+    /// Forgets a peer that left the room
+    pub fn remove_peer(&mut self, peer_id: &str) {
+        if let Some(frame) = self
+            .pending
+            .remove(peer_id)
+            .and_then(|mut queue| queue.pop_front())
+        {
+            sub_into(&mut self.total, &frame.samples);
+        }
+        if self.pending.is_empty() {
+            self.total.clear();
+            self.format = None;
+        }
+        self.seq.remove(peer_id);
+    }
+
+    /// Mixes each peer's oldest queued frame for each recipient, leaving out
+    /// the recipient's own audio
+    ///
+    /// Recipients with nothing to hear are skipped. The frames mixed are
+    /// consumed; any queued behind them go into the next mix.
+    pub fn mix(&mut self, recipients: &[String]) -> Vec<(String, AudioFrame)> {
+        let mut mixes = Vec::new();
+
+        if let Some((sample_rate, channels)) = self.format {
+            for recipient in recipients {
+                let own = self.pending.get(recipient).and_then(VecDeque::front);
+
+                // Latency is measured from the oldest contribution
+                let Some(capture_ts) = self
+                    .pending
+                    .iter()
+                    .filter(|(source, _)| *source != recipient)
+                    .filter_map(|(_, queue)| queue.front())
+                    .map(|frame| frame.capture_ts)
+                    .min()
+                else {
+                    continue;
+                };
+
+                let mut samples = vec![0.0; self.total.len()];
+                mix_excluding(
+                    &mut samples,
+                    &self.total,
+                    own.map_or(&[][..], |frame| &frame.samples),
+                );
+
+                let seq = self.seq.entry(recipient.clone()).or_insert(0);
+                let frame = AudioFrame::new(samples, sample_rate, channels)
+                    .with_capture_ts(capture_ts)
+                    .with_seq(*seq)
+                    .with_peer_id(&self.host_id);
+                *seq += 1;
+                mixes.push((recipient.clone(), frame));
+            }
+        }
+
+        // The next round starts from the frames queued behind these,
+        // keeping the total's allocation
+        self.total.clear();
+        for queue in self.pending.values_mut() {
+            queue.pop_front();
+            if let Some(next) = queue.front() {
+                if self.total.len() < next.len() {
+                    self.total.resize(next.len(), 0.0);
+                }
+                add_into(&mut self.total, &next.samples);
+            }
+        }
+        self.pending.retain(|_, queue| !queue.is_empty());
+        if self.pending.is_empty() {
+            self.format = None;
+        }
+
+        mixes
+    }
+}
+
+/// Where the host sends each round of mixes
+#[async_trait]
+pub trait MixSink: Send + Sync {
+    /// Peers who should get a mix this round
+    async fn recipients(&self) -> Vec<String>;
+    async fn send_mix(&self, peer_id: &str, mix: &AudioFrame) -> Result<()>;
+}
+
+/// Mixes for the room every `interval` until the task is stopped
+///
+/// Runs on its own clock rather than the host's capture, so peers keep
+/// hearing each other while the host is muted or says nothing.
+pub async fn run_host_mix(mixer: Arc<Mutex<HostMixer>>, sink: impl MixSink, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    loop {
+        ticker.tick().await;
+        let recipients = sink.recipients().await;
+        let mixes = mixer.lock().unwrap().mix(&recipients);
+        for (peer_id, mix) in mixes {
+            if let Err(e) = sink.send_mix(&peer_id, &mix).await {
+                log::debug!("Failed to send the mix to {}: {}", peer_id, e);
+            }
+        }
+    }
+}
+
+/// Samples handled per step; a multiple of common SIMD widths so the loops vectorize
+const LANES: usize = 8;
+
+/// Adds `src` into the start of `dst`
+fn add_into(dst: &mut [f32], src: &[f32]) {
+    let len = dst.len().min(src.len());
+    let (dst, src) = (&mut dst[..len], &src[..len]);
+
+    let mut dst_chunks = dst.chunks_exact_mut(LANES);
+    let mut src_chunks = src.chunks_exact(LANES);
+    for (out, input) in (&mut dst_chunks).zip(&mut src_chunks) {
+        for lane in 0..LANES {
+            out[lane] += input[lane];
+        }
+    }
+    for (out, input) in dst_chunks
+        .into_remainder()
+        .iter_mut()
+        .zip(src_chunks.remainder())
+    {
+        *out += input;
+    }
+}
+
+/// Subtracts `src` from the start of `dst`
+fn sub_into(dst: &mut [f32], src: &[f32]) {
+    let len = dst.len().min(src.len());
+    let (dst, src) = (&mut dst[..len], &src[..len]);
+
+    let mut dst_chunks = dst.chunks_exact_mut(LANES);
+    let mut src_chunks = src.chunks_exact(LANES);
+    for (out, input) in (&mut dst_chunks).zip(&mut src_chunks) {
+        for lane in 0..LANES {
+            out[lane] -= input[lane];
+        }
+    }
+    for (out, input) in dst_chunks
+        .into_remainder()
+        .iter_mut()
+        .zip(src_chunks.remainder())
+    {
+        *out -= input;
+    }
+}
+
+/// Writes `total - own` into `out`, clamping to [-1, 1]
+fn mix_excluding(out: &mut [f32], total: &[f32], own: &[f32]) {
+    out.copy_from_slice(total);
+    sub_into(out, own);
+
+    let mut chunks = out.chunks_exact_mut(LANES);
+    for chunk in &mut chunks {
+        for sample in chunk.iter_mut() {
+            *sample = sample.clamp(-1.0, 1.0);
+        }
+    }
+    for sample in chunks.into_remainder() {
+        *sample = sample.clamp(-1.0, 1.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(peer_id: &str, value: f32) -> AudioFrame {
+        AudioFrame::new(vec![value; 4], 48000, 1).with_peer_id(peer_id)
+    }
+
+    #[test]
+    fn test_topology_in_link() {
+        let link = "resonance://join?ip=1.2.3.4&port=5&sid=abc&key=xyz";
+
+        assert_eq!(MixingTopology::from_link(link), MixingTopology::FullMesh);
+        assert_eq!(MixingTopology::FullMesh.append_to_link(link), link);
+
+        let mixed = MixingTopology::HostMixed.append_to_link(link);
+        assert_eq!(MixingTopology::from_link(&mixed), MixingTopology::HostMixed);
+    }
+
+    #[test]
+    fn test_mix_excludes_recipient() {
+        let mut mixer = HostMixer::new("host");
+        mixer.push(AudioFrame::new(vec![0.1; 4], 48000, 1));
+        mixer.push(frame("a", 0.2));
+        mixer.push(frame("b", 0.4));
+
+        let recipients = vec!["a".to_string(), "b".to_string()];
+        let mixes: HashMap<String, AudioFrame> = mixer.mix(&recipients).into_iter().collect();
+
+        assert!((mixes["a"].samples[0] - 0.5).abs() < 1e-6);
+        assert!((mixes["b"].samples[0] - 0.3).abs() < 1e-6);
+        assert_eq!(mixes["a"].peer_id.as_deref(), Some("host"));
+
+        // Frames are consumed by the mix
+        assert!(mixer.mix(&recipients).is_empty());
+    }
+
+    #[test]
+    fn test_mix_clamps_and_sequences() {
+        let mut mixer = HostMixer::new("host");
+        let recipients = vec!["c".to_string()];
+
+        mixer.push(frame("a", 0.8));
+        mixer.push(frame("b", 0.8));
+        let (_, first) = mixer.mix(&recipients).remove(0);
+        assert_eq!(first.samples, vec![1.0; 4]);
+        assert_eq!(first.seq, 0);
+
+        mixer.push(frame("a", 0.1));
+        let (_, second) = mixer.mix(&recipients).remove(0);
+        assert_eq!(second.seq, 1);
+    }
+
+    #[test]
+    fn test_mix_follows_queued_and_removed_frames() {
+        let mut mixer = HostMixer::new("host");
+        let recipients = vec!["a".to_string(), "c".to_string()];
+
+        // Two frames from "a" in one tick go into consecutive mixes
+        mixer.push(AudioFrame::new(vec![0.3; 20], 48000, 1).with_peer_id("a"));
+        mixer.push(AudioFrame::new(vec![0.1; 20], 48000, 1).with_peer_id("a"));
+        mixer.push(AudioFrame::new(vec![0.2; 20], 48000, 1).with_peer_id("b"));
+        // A frame in another format is left out
+        mixer.push(AudioFrame::new(vec![0.5; 20], 44100, 1).with_peer_id("d"));
+
+        let mixes: HashMap<String, AudioFrame> = mixer.mix(&recipients).into_iter().collect();
+        assert!(mixes["a"].samples.iter().all(|s| (s - 0.2).abs() < 1e-6));
+        assert!(mixes["c"].samples.iter().all(|s| (s - 0.5).abs() < 1e-6));
+        assert_eq!(mixes["c"].samples.len(), 20);
+
+        // Only "a" had a frame left, and "a" doesn't hear itself
+        let mixes: HashMap<String, AudioFrame> = mixer.mix(&recipients).into_iter().collect();
+        assert!(!mixes.contains_key("a"));
+        assert!(mixes["c"].samples.iter().all(|s| (s - 0.1).abs() < 1e-6));
+        assert!(mixer.mix(&recipients).is_empty());
+
+        // A burst longer than the line drops its oldest frames
+        for i in 0..MAX_PENDING_FRAMES + 2 {
+            mixer.push(frame("a", i as f32 / 10.0));
+        }
+        let heard: Vec<f32> = std::iter::from_fn(|| mixer.mix(&recipients).pop())
+            .map(|(_, mix)| mix.samples[0])
+            .collect();
+        let expected: Vec<f32> = (2..MAX_PENDING_FRAMES + 2)
+            .map(|i| i as f32 / 10.0)
+            .collect();
+        assert_eq!(heard.len(), expected.len());
+        assert!(heard
+            .iter()
+            .zip(&expected)
+            .all(|(h, e)| (h - e).abs() < 1e-6));
+
+        // A peer that leaves drops out of the pending mix
+        mixer.push(frame("a", 0.4));
+        mixer.push(frame("b", 0.2));
+        mixer.remove_peer("b");
+        let (_, only_a) = mixer.mix(&["c".to_string()]).remove(0);
+        assert!((only_a.samples[0] - 0.4).abs() < 1e-6);
+    }
+
+    #[derive(Clone, Default)]
+    struct Collected(Arc<Mutex<HashMap<String, AudioFrame>>>);
+
+    #[async_trait]
+    impl MixSink for Collected {
+        async fn recipients(&self) -> Vec<String> {
+            vec!["a".to_string(), "b".to_string()]
+        }
+
+        async fn send_mix(&self, peer_id: &str, mix: &AudioFrame) -> Result<()> {
+            // The first mix each peer gets
+            self.0
+                .lock()
+                .unwrap()
+                .entry(peer_id.to_string())
+                .or_insert_with(|| mix.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_peers_hear_each_other_while_the_host_is_silent() {
+        let mixer = Arc::new(Mutex::new(HostMixer::new("host")));
+        let sink = Collected::default();
+        let task = tokio::spawn(run_host_mix(
+            mixer.clone(),
+            sink.clone(),
+            Duration::from_millis(5),
+        ));
+
+        // Only the peers send; the host never pushes a frame of its own
+        mixer.lock().unwrap().push(frame("a", 0.2));
+        mixer.lock().unwrap().push(frame("b", 0.4));
+        for _ in 0..200 {
+            if sink.0.lock().unwrap().len() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        task.abort();
+
+        let mixes = sink.0.lock().unwrap();
+        assert!((mixes["a"].samples[0] - 0.4).abs() < 1e-6);
+        assert!((mixes["b"].samples[0] - 0.2).abs() < 1e-6);
+    }
+}
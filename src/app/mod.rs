@@ -1,16 +1,92 @@
+pub mod admission;
+pub mod aliases;
+pub mod audio_check;
+pub mod audio_watchdog;
+pub mod audit_log;
 pub mod config;
+pub mod contacts;
+pub mod control;
+pub mod diagnostics;
+pub mod dnd;
+pub mod echo_bot;
+pub mod events;
+pub mod handoff;
+pub mod health;
+pub mod host_actions;
+pub mod instance;
+pub mod keep_awake;
+pub mod logging;
+pub mod low_bandwidth;
+pub mod meeting;
+pub mod mixing;
+pub mod presence;
+pub mod recording;
+pub mod secrets;
 pub mod session;
+pub mod session_timer;
+pub mod shortener;
+pub mod shutdown;
+pub mod sleep;
+pub mod synced_media;
 pub mod test_session;
+pub mod timeline;
+pub mod topology;
+pub mod webhook;
 
 use std::fs;
 use std::path::Path;
 use std::str::FromStr;
 
-use crate::network::ConnectionState;
+use crate::audio::{CodecSettings, SpatialLayout};
+use crate::network::{BandwidthCaps, ConnectionState, Proxy};
+use audit_log::AuditLog;
 use config::Config;
+use contacts::{CallInvite, CommandNotifier, Contact, InviteNotifier, Reachability};
+use handoff::HandoffKey;
+use secrets::{open_secrets, seal_secrets, SecretStore};
 use session::{Session, SessionError, SessionManager};
 use test_session::TestSessionManager;
 
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Applies the session settings in `config` to a session manager
+async fn configure_session_manager(session_manager: &mut SessionManager, config: &Config) {
+    session_manager.set_mixing_topology(config.mixing_topology);
+    session_manager.set_port_mapping(config.port_mapping);
+    session_manager.set_ice_servers(config.ice_servers.clone());
+    session_manager.set_bind_address(config.bind_address.clone());
+    session_manager.set_proxy(config.proxy.clone());
+    session_manager.set_mtu(config.path_mtu);
+    session_manager.set_retry(config.retry.clone());
+    session_manager.set_audio_bundling(low_bandwidth::frames_per_packet(config));
+    session_manager.set_silence_suppression(config.silence_suppression);
+    session_manager.set_forward_secrecy(config.forward_secrecy);
+    session_manager.set_nonce_strategy(config.nonce_strategy);
+    session_manager.set_codec(low_bandwidth::send_codec(config));
+    session_manager.set_frame_duration(config.frame_duration);
+    session_manager.set_username(config.username.clone());
+    session_manager.set_peer_aliases(config.peer_aliases.clone());
+    session_manager.set_dnd_policy(config.dnd_policy.clone());
+    session_manager.set_do_not_disturb(config.do_not_disturb);
+    session_manager
+        .set_bandwidth_caps(config.bandwidth_caps)
+        .await;
+    session_manager.set_spatial_layout(config.spatial_layout);
+    session_manager.set_recording_consent(config.recording_consent);
+    session_manager
+        .set_synced_playout(config.sync_shared_audio && config.system_audio_percent.is_some());
+    session_manager.set_time_limit(
+        config
+            .session_time_limit_minutes
+            .map(|minutes| std::time::Duration::from_secs(minutes * 60)),
+    );
+}
+
 /// Main application struct that coordinates all components
 pub struct App {
     initialized: bool,
@@ -18,6 +94,16 @@ pub struct App {
     pub session_manager: Option<SessionManager>,
     current_session: Option<Session>,
     test_session_manager: Option<TestSessionManager>,
+    // Delivers call invites to contacts, if configured
+    invite_notifier: Option<Box<dyn InviteNotifier>>,
+    // Keeps passwords out of the configuration file; without one they're written into it
+    secrets: Option<Box<dyn SecretStore>>,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl App {
@@ -29,6 +115,8 @@ impl App {
             session_manager: None,
             current_session: None,
             test_session_manager: None,
+            invite_notifier: None,
+            secrets: None,
         }
     }
 
@@ -40,14 +128,29 @@ impl App {
             session_manager: None,
             current_session: None,
             test_session_manager: None,
+            invite_notifier: None,
+            secrets: None,
         }
     }
 
     /// Initializes the application
     pub async fn initialize(&mut self) -> Result<(), String> {
         if self.session_manager.is_none() {
-            let session_manager = SessionManager::new();
+            let mut session_manager = SessionManager::new();
+            configure_session_manager(&mut session_manager, &self.config).await;
             self.session_manager = Some(session_manager);
+            self.configure_audit_log();
+        }
+
+        // Deliver call invites through an external command
+        if self.invite_notifier.is_none() {
+            if let Some(command) = &self.config.invite_command {
+                let mut parts = command.split_whitespace();
+                if let Some(program) = parts.next() {
+                    let args: Vec<&str> = parts.collect();
+                    self.invite_notifier = Some(Box::new(CommandNotifier::new(program, &args)));
+                }
+            }
         }
 
         if self.test_session_manager.is_none() {
@@ -73,21 +176,266 @@ impl App {
         self.config = config;
     }
 
+    /// Replaces the configuration and applies what can change while running
+    ///
+    /// Session settings reach the session manager at once, each taking effect
+    /// as its setter describes. Devices and logging change from the next start.
+    pub async fn apply_settings(&mut self, config: Config) {
+        self.config = config;
+        if let Some(session_manager) = self.session_manager.as_mut() {
+            configure_session_manager(session_manager, &self.config).await;
+        }
+        self.configure_audit_log();
+    }
+
+    /// Opens the audit log the configuration names, or stops keeping one if it names none
+    ///
+    /// Its signing key lives in the secret store, so without one no log is kept.
+    fn configure_audit_log(&mut self) {
+        let Some(session_manager) = self.session_manager.as_mut() else {
+            return;
+        };
+        let Some(path) = &self.config.audit_log_file else {
+            session_manager.set_audit_log(None);
+            return;
+        };
+        let open = session_manager.audit_log();
+        if open.is_some_and(|log| log.lock().unwrap().path() == Some(Path::new(path))) {
+            return;
+        }
+
+        let log = match &self.secrets {
+            Some(store) => {
+                audit_log::signing_key(store.as_ref()).and_then(|key| AuditLog::open(path, key))
+            }
+            None => Err(anyhow::anyhow!("no secret store to keep its key in")),
+        };
+        match log {
+            Ok(log) => session_manager.set_audit_log(Some(log)),
+            Err(e) => {
+                log::warn!("Couldn't open the audit log {}: {}", path, e);
+                session_manager.set_audit_log(None);
+            }
+        }
+    }
+
+    /// Writes the room events recorded so far to `path` as JSON, returning how many there were
+    pub fn export_audit_log<P: AsRef<Path>>(&self, path: P) -> Result<usize, String> {
+        let log = self
+            .session_manager
+            .as_ref()
+            .and_then(SessionManager::audit_log)
+            .ok_or_else(|| "No audit log is kept; set audit_log_file".to_string())?;
+        let log = log.lock().unwrap();
+        log.export(path)
+            .map_err(|e| format!("Failed to export the audit log: {}", e))?;
+        Ok(log.entries().len())
+    }
+
+    /// Gives a peer in the session our own name, or removes ours if `alias` is empty
+    ///
+    /// The alias is kept in the configuration by the peer's key, so it follows
+    /// the peer into later sessions. It shows from the next time they join.
+    pub fn set_peer_alias(&mut self, peer_id: &str, alias: &str) -> Result<(), String> {
+        let session_manager = self
+            .session_manager
+            .as_mut()
+            .ok_or_else(|| "Session manager not initialized".to_string())?;
+        let key = session_manager
+            .peer_key(peer_id)
+            .ok_or_else(|| format!("Unknown peer: {}", peer_id))?;
+
+        self.config.peer_aliases.set(key, alias);
+        session_manager.set_peer_aliases(self.config.peer_aliases.clone());
+        Ok(())
+    }
+
+    /// Turns do-not-disturb on or off and keeps the choice in the configuration
+    pub fn set_do_not_disturb(&mut self, enabled: bool) {
+        self.config.do_not_disturb = enabled;
+        if let Some(session_manager) = self.session_manager.as_mut() {
+            session_manager.set_do_not_disturb(enabled);
+        }
+    }
+
+    /// Caps the room's upload and download and keeps the caps in the configuration
+    pub async fn set_bandwidth_caps(&mut self, caps: BandwidthCaps) {
+        self.config.bandwidth_caps = caps;
+        if let Some(session_manager) = self.session_manager.as_mut() {
+            session_manager.set_bandwidth_caps(caps).await;
+        }
+    }
+
+    /// Turns low-bandwidth mode on or off and keeps the choice in the configuration
+    ///
+    /// The session sends with the lower bitrate and fuller packets at once;
+    /// plain panning is up to whoever owns the audio.
+    pub async fn set_low_bandwidth(&mut self, enabled: bool) {
+        self.config.low_bandwidth = enabled;
+        if let Some(session_manager) = self.session_manager.as_mut() {
+            configure_session_manager(session_manager, &self.config).await;
+        }
+    }
+
+    /// Sets the codec for the room we're in or create next, or `None` for our own
+    pub fn set_room_codec(&mut self, settings: Option<CodecSettings>) -> Result<(), String> {
+        self.session_manager
+            .as_mut()
+            .ok_or_else(|| "Session manager not initialized".to_string())?
+            .set_room_codec(settings);
+        Ok(())
+    }
+
+    /// Sets the proxy for the room we're in or create next, or `None` for our own
+    pub fn set_room_proxy(&mut self, proxy: Option<Proxy>) -> Result<(), String> {
+        self.session_manager
+            .as_mut()
+            .ok_or_else(|| "Session manager not initialized".to_string())?
+            .set_room_proxy(proxy);
+        Ok(())
+    }
+
+    /// Sets how call invites reach contacts, replacing any configured command
+    pub fn set_invite_notifier(&mut self, notifier: Box<dyn InviteNotifier>) {
+        self.invite_notifier = Some(notifier);
+    }
+
+    /// Saves a peer in the session as a contact, returning the name saved
+    ///
+    /// Our alias for the peer is used as the contact's name if we set one.
+    pub fn save_contact(&mut self, peer_id: &str) -> Result<String, String> {
+        let session_manager = self
+            .session_manager
+            .as_ref()
+            .ok_or_else(|| "Session manager not initialized".to_string())?;
+        let peer = session_manager
+            .other_peers()
+            .into_iter()
+            .find(|peer| peer.id == peer_id)
+            .ok_or_else(|| format!("Unknown peer: {}", peer_id))?;
+
+        let name = self
+            .config
+            .peer_aliases
+            .name_for(&peer.public_key, &peer.name);
+        self.config.contacts.add(peer.public_key, &name);
+        self.refresh_contacts();
+        Ok(name)
+    }
+
+    /// Records where contacts in the current session can be reached
+    pub fn refresh_contacts(&mut self) {
+        let Some(session_manager) = self.session_manager.as_ref() else {
+            return;
+        };
+        let now = unix_now();
+        for peer in session_manager.other_peers() {
+            let address = std::net::SocketAddr::new(peer.endpoint.ip, peer.endpoint.port);
+            self.config
+                .contacts
+                .record_seen(&peer.public_key, address, now);
+        }
+    }
+
+    /// Saved contacts and whether each can be called right now
+    pub fn contacts(&mut self) -> Vec<(Contact, Reachability)> {
+        self.refresh_contacts();
+        let in_session: Vec<[u8; 32]> = self
+            .session_manager
+            .as_ref()
+            .map(|manager| {
+                manager
+                    .other_peers()
+                    .iter()
+                    .map(|peer| peer.public_key)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let now = unix_now();
+        self.config
+            .contacts
+            .iter()
+            .map(|contact| {
+                let reachability = self.config.contacts.reachability(contact, &in_session, now);
+                (contact.clone(), reachability)
+            })
+            .collect()
+    }
+
+    /// Calls a contact: hosts a room, unless we already host one, and sends them the link
+    ///
+    /// The contact is found by name or key prefix. The link is returned
+    /// either way, so it can be shared by hand if no notifier is set up or
+    /// delivery fails.
+    pub async fn call_contact(&mut self, query: &str) -> Result<String, String> {
+        let contact = self
+            .config
+            .contacts
+            .find(query)
+            .cloned()
+            .ok_or_else(|| format!("Unknown contact: {}", query))?;
+
+        let link = match self.current_session() {
+            Some(session) if session.is_host => session.connection_link,
+            _ => {
+                self.create_p2p_session()
+                    .await
+                    .map_err(|e| e.to_string())?
+                    .connection_link
+            }
+        };
+
+        let invite = CallInvite {
+            from: self.config.username.clone(),
+            link: link.clone(),
+        };
+        match self.invite_notifier.as_mut() {
+            Some(notifier) => {
+                if let Err(e) = notifier.notify(&contact, &invite) {
+                    log::warn!("Failed to send call invite to {}: {}", contact.name, e);
+                }
+            }
+            None => log::info!(
+                "No invite notifier set; share the link with {} yourself",
+                contact.name
+            ),
+        }
+        Ok(link)
+    }
+
+    /// Keeps secrets in `store` rather than the configuration file, from the next load or save
+    pub fn set_secret_store(&mut self, store: Box<dyn SecretStore>) {
+        self.secrets = Some(store);
+    }
+
     /// Loads configuration from a file
     pub fn load_config<P: AsRef<Path>>(&mut self, path: P) -> Result<(), String> {
         let content =
             fs::read_to_string(path).map_err(|e| format!("Failed to read config file: {}", e))?;
 
-        let config =
+        let mut config =
             Config::from_str(&content).map_err(|e| format!("Failed to parse config: {}", e))?;
+        if let Some(store) = &self.secrets {
+            open_secrets(&mut config, store.as_ref());
+        }
 
         self.config = config;
         Ok(())
     }
 
-    /// Saves configuration to a file
+    /// Saves configuration to a file, with its secrets in the secret store if there is one
     pub fn save_config<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
-        let content = self.config.to_string();
+        let content = match &self.secrets {
+            Some(store) => seal_secrets(&self.config, store.as_ref())
+                .map_err(|e| format!("Failed to store secrets: {}", e))?
+                .to_string(),
+            None => self.config.to_string(),
+        };
+
+        if let Some(directory) = path.as_ref().parent() {
+            fs::create_dir_all(directory)
+                .map_err(|e| format!("Failed to create config directory: {}", e))?;
+        }
 
         fs::write(path, content).map_err(|e| format!("Failed to write config file: {}", e))?;
 
@@ -95,29 +443,43 @@ impl App {
     }
 
     /// Creates a new P2P audio session
-    pub async fn create_p2p_session(&mut self) -> Result<Session, String> {
-        let session_manager = self
-            .session_manager
+    pub async fn create_p2p_session(&mut self) -> Result<Session, SessionError> {
+        self.session_manager
             .as_mut()
-            .ok_or_else(|| "Session manager not initialized".to_string())?;
-
-        session_manager
+            .ok_or(SessionError::NotInitialized)?
             .create_p2p_session()
             .await
-            .map_err(|e| format!("Failed to create P2P session: {}", e))
     }
 
     /// Joins an existing P2P session using a connection link
-    pub async fn join_p2p_session(&mut self, link: &str) -> Result<(), String> {
-        let session_manager = self
-            .session_manager
+    pub async fn join_p2p_session(&mut self, link: &str) -> Result<(), SessionError> {
+        self.session_manager
             .as_mut()
-            .ok_or_else(|| "Session manager not initialized".to_string())?;
-
-        session_manager
+            .ok_or(SessionError::NotInitialized)?
             .join_p2p_session(link)
             .await
-            .map_err(|e| format!("Failed to join P2P session: {}", e))
+    }
+
+    /// Hands the current call over to another machine, returning it sealed with its key
+    pub async fn export_handoff(&mut self) -> Result<(String, HandoffKey), SessionError> {
+        self.session_manager
+            .as_mut()
+            .ok_or(SessionError::NotInitialized)?
+            .export_handoff()
+            .await
+    }
+
+    /// Carries on a call handed over from another machine
+    pub async fn import_handoff(
+        &mut self,
+        sealed: &str,
+        key: &HandoffKey,
+    ) -> Result<Session, SessionError> {
+        self.session_manager
+            .as_mut()
+            .ok_or(SessionError::NotInitialized)?
+            .import_handoff(sealed, key)
+            .await
     }
 
     /// Leaves the current session (regular or test)
@@ -191,50 +553,16 @@ impl App {
         false
     }
 
-    /// Arrange participants in a circle for spatial audio
-    pub fn arrange_participants_in_circle(&self) -> Vec<(String, (f32, f32, f32))> {
-        if let Some(session) = self.current_session() {
-            let participants = &session.participants;
-            let count = participants.len();
-
-            if count <= 1 {
-                return vec![(participants[0].name.clone(), (0.0, 0.0, 0.0))];
-            }
-
-            // Use SpatialAudioProcessor to arrange participants
-            let mut spatial_processor = crate::audio::SpatialAudioProcessor::new();
-
-            // Find our index in the participants list
-            let mut my_index = 0;
-            for (i, p) in participants.iter().enumerate() {
-                if p.name == "Me" {
-                    my_index = i;
-                    break;
-                }
-            }
-
-            // Arrange participants in a circle
-            let positions = spatial_processor.arrange_participants_in_circle(count, my_index);
-
-            // Map participant names to positions
-            participants
-                .iter()
-                .enumerate()
-                .map(|(i, p)| (p.name.clone(), positions[i]))
-                .collect()
-        } else {
-            Vec::new()
-        }
-    }
-
-    /// Update participant positions based on spatial arrangement
-    pub async fn update_participant_positions(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        if let Some(positions) = self.arrange_participants_in_circle().into_iter().next() {
-            if let Some(session_manager) = &mut self.session_manager {
-                // Update session manager with positions
-                // This would be implemented to update the peer positions
-                // in the actual implementation
-            }
+    /// Seats everyone with a layout preset and shares it with the room
+    ///
+    /// Only the host's arrangement counts; for anyone else this does nothing.
+    pub async fn arrange_participants(&mut self, layout: SpatialLayout) -> Result<(), String> {
+        if let Some(session_manager) = &mut self.session_manager {
+            session_manager.set_spatial_layout(layout);
+            session_manager
+                .arrange_participants()
+                .await
+                .map_err(|e| format!("Failed to arrange participants: {}", e))?;
         }
         Ok(())
     }
@@ -334,9 +662,11 @@ mod tests {
         let mut app = App::new();
         assert_eq!(app.config().audio_quality, AudioQuality::Medium);
 
-        let mut custom_config = Config::default();
-        custom_config.audio_quality = AudioQuality::High;
-        custom_config.username = "TestUser".to_string();
+        let custom_config = Config {
+            audio_quality: AudioQuality::High,
+            username: "TestUser".to_string(),
+            ..Config::default()
+        };
 
         app.update_config(custom_config.clone());
         assert_eq!(app.config().audio_quality, AudioQuality::High);
@@ -349,9 +679,11 @@ mod tests {
         let temp_file = "test_config.tmp";
 
         // Create a custom config and app
-        let mut custom_config = Config::default();
-        custom_config.audio_quality = AudioQuality::High;
-        custom_config.username = "TestUser".to_string();
+        let custom_config = Config {
+            audio_quality: AudioQuality::High,
+            username: "TestUser".to_string(),
+            ..Config::default()
+        };
 
         let app = App::with_config(custom_config);
 
@@ -369,4 +701,29 @@ mod tests {
         // Clean up
         fs::remove_file(temp_file).unwrap();
     }
+
+    #[test]
+    fn test_audit_log_follows_settings() {
+        let directory = std::env::temp_dir().join("resonance-app-audit-test");
+        let _ = fs::remove_dir_all(&directory);
+        let mut config = Config {
+            audit_log_file: Some(directory.join("audit.log").display().to_string()),
+            ..Config::default()
+        };
+
+        let mut app = App::with_config(config.clone());
+        app.set_secret_store(Box::new(secrets::EncryptedFileStore::new(&directory)));
+        tokio_test::block_on(async {
+            app.initialize().await.unwrap();
+            let export = directory.join("audit.json");
+            assert_eq!(app.export_audit_log(&export), Ok(0));
+            assert!(export.exists());
+
+            config.audit_log_file = None;
+            app.apply_settings(config).await;
+            assert!(app.export_audit_log(&export).is_err());
+        });
+
+        fs::remove_dir_all(directory).unwrap();
+    }
 }
@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Whether a participant is currently engaged with the session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum PresenceStatus {
+    /// Speaking or interacting recently
+    #[default]
+    Active,
+    /// Quiet for a while
+    Idle,
+    /// Quiet for a long time
+    Away,
+}
+
+/// Derives the local user's presence from voice and input activity
+pub struct PresenceTracker {
+    idle_after: Duration,
+    away_after: Duration,
+    last_activity: Instant,
+    track_input: bool,
+    away: bool,
+    reported: PresenceStatus,
+}
+
+impl PresenceTracker {
+    /// Creates a tracker that starts out active
+    pub fn new(idle_after: Duration, away_after: Duration) -> Self {
+        Self {
+            idle_after,
+            away_after,
+            last_activity: Instant::now(),
+            track_input: true,
+            away: false,
+            reported: PresenceStatus::Active,
+        }
+    }
+
+    /// Sets whether keyboard input counts as activity, not just speaking
+    pub fn with_input_tracking(mut self, enabled: bool) -> Self {
+        self.track_input = enabled;
+        self
+    }
+
+    /// Records that voice activity was detected at `at`
+    pub fn record_voice_activity(&mut self, at: Instant) {
+        if at > self.last_activity {
+            self.last_activity = at;
+        }
+    }
+
+    /// Records local input, if input tracking is enabled
+    pub fn record_input(&mut self) {
+        if self.track_input {
+            self.last_activity = Instant::now();
+        }
+    }
+
+    /// Marks us away until cleared, whatever our activity
+    ///
+    /// Coming back counts as activity, so we're active again straight away.
+    pub fn set_away(&mut self, away: bool) {
+        if self.away && !away {
+            self.last_activity = Instant::now();
+        }
+        self.away = away;
+    }
+
+    /// Whether we've marked ourselves away
+    pub fn is_away(&self) -> bool {
+        self.away
+    }
+
+    /// Gets the current status
+    pub fn status(&self) -> PresenceStatus {
+        if self.away {
+            return PresenceStatus::Away;
+        }
+
+        let quiet_for = self.last_activity.elapsed();
+        if quiet_for >= self.away_after {
+            PresenceStatus::Away
+        } else if quiet_for >= self.idle_after {
+            PresenceStatus::Idle
+        } else {
+            PresenceStatus::Active
+        }
+    }
+
+    /// Returns the new status if it changed since the last call
+    pub fn poll(&mut self) -> Option<PresenceStatus> {
+        let status = self.status();
+        if status == self.reported {
+            return None;
+        }
+
+        self.reported = status;
+        Some(status)
+    }
+}
+
+impl Default for PresenceTracker {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(60), Duration::from_secs(5 * 60))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_thresholds() {
+        let mut tracker =
+            PresenceTracker::new(Duration::from_millis(20), Duration::from_millis(60));
+        assert_eq!(tracker.poll(), None);
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(tracker.poll(), Some(PresenceStatus::Idle));
+
+        std::thread::sleep(Duration::from_millis(40));
+        assert_eq!(tracker.poll(), Some(PresenceStatus::Away));
+
+        tracker.record_voice_activity(Instant::now());
+        assert_eq!(tracker.poll(), Some(PresenceStatus::Active));
+    }
+
+    #[test]
+    fn test_away_overrides_activity() {
+        let mut tracker = PresenceTracker::default();
+        tracker.set_away(true);
+        tracker.record_input();
+        tracker.record_voice_activity(Instant::now());
+        assert_eq!(tracker.poll(), Some(PresenceStatus::Away));
+
+        tracker.set_away(false);
+        assert_eq!(tracker.poll(), Some(PresenceStatus::Active));
+    }
+
+    #[test]
+    fn test_input_tracking_can_be_disabled() {
+        let mut tracker = PresenceTracker::new(Duration::from_millis(10), Duration::from_secs(60))
+            .with_input_tracking(false);
+
+        std::thread::sleep(Duration::from_millis(20));
+        tracker.record_input();
+        assert_eq!(tracker.status(), PresenceStatus::Idle);
+    }
+}
@@ -0,0 +1,109 @@
+use std::time::Duration;
+
+/// Longest a recording waits for the host to acknowledge it, in rooms that
+/// ask for consent
+pub const RECORDING_ACK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Who in the room is recording, so everyone can show it
+///
+/// In rooms that ask for consent, a peer's recording only starts once the
+/// host has acknowledged hearing of it. The host doesn't wait on anyone,
+/// so only whether we're waiting for it is tracked here.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordingRoster {
+    consent_required: bool,
+    // Peers recording, in the order they started
+    recorders: Vec<String>,
+    // We announced a recording the host hasn't acknowledged yet
+    awaiting_ack: bool,
+}
+
+impl RecordingRoster {
+    /// A roster for a room whose policy isn't known yet, which asks for consent
+    /// until the host says otherwise
+    pub fn new() -> Self {
+        Self {
+            consent_required: true,
+            recorders: Vec::new(),
+            awaiting_ack: false,
+        }
+    }
+
+    /// Whether recordings wait for the host to acknowledge them
+    pub fn consent_required(&self) -> bool {
+        self.consent_required
+    }
+
+    pub fn set_consent_required(&mut self, required: bool) {
+        self.consent_required = required;
+    }
+
+    /// Marks a peer as recording or not, returning whether anything changed
+    pub fn set_recording(&mut self, peer_id: &str, recording: bool) -> bool {
+        let was_recording = self.is_recording(peer_id);
+        if recording && !was_recording {
+            self.recorders.push(peer_id.to_string());
+            true
+        } else if !recording && was_recording {
+            self.recorders.retain(|id| id != peer_id);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn is_recording(&self, peer_id: &str) -> bool {
+        self.recorders.iter().any(|id| id == peer_id)
+    }
+
+    /// Peers recording, in the order they started
+    pub fn recorders(&self) -> &[String] {
+        &self.recorders
+    }
+
+    /// Starts waiting for the host to acknowledge our recording
+    pub fn await_ack(&mut self) {
+        self.awaiting_ack = true;
+    }
+
+    pub fn awaiting_ack(&self) -> bool {
+        self.awaiting_ack
+    }
+
+    /// Stops waiting for the host, returning whether we still were
+    pub fn settle_ack(&mut self) -> bool {
+        std::mem::take(&mut self.awaiting_ack)
+    }
+}
+
+impl Default for RecordingRoster {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recorders_and_acknowledgement() {
+        let mut roster = RecordingRoster::new();
+        assert!(roster.consent_required());
+        assert!(roster.set_recording("bob", true));
+        assert!(roster.set_recording("alice", true));
+        assert!(!roster.set_recording("alice", true));
+        assert_eq!(roster.recorders(), ["bob", "alice"]);
+
+        assert!(roster.set_recording("bob", false));
+        assert!(!roster.set_recording("bob", false));
+        assert_eq!(roster.recorders(), ["alice"]);
+
+        // Only the first of an acknowledgement and a timeout settles it
+        roster.await_ack();
+        assert!(roster.awaiting_ack());
+        assert!(roster.settle_ack());
+        assert!(!roster.settle_ack());
+        assert!(!roster.awaiting_ack());
+    }
+}
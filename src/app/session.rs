@@ -1,15 +1,62 @@
 use anyhow::Result;
+use async_trait::async_trait;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
-use tokio::task::JoinHandle;
-
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, watch, Notify};
+use tokio::task::AbortHandle;
+
+use crate::app::admission::{AdmissionQueue, JoinReply, QueuedJoin};
+use crate::app::aliases::PeerAliases;
+use crate::app::audio_watchdog::AudioWatchdog;
+use crate::app::audit_log::{AuditEvent, AuditLog};
+use crate::app::dnd::{DndPolicy, DoNotDisturb, JoinDecision, JoinRequest};
+use crate::app::echo_bot::{EchoBot, ECHO_BOT_NAME};
+use crate::app::events::{ConnectionIssue, SessionEvent};
+use crate::app::handoff::{CallHandoff, HandoffKey, HANDOFF_VALIDITY_SECS};
+use crate::app::host_actions::{HostAction, HostActionHistory, RecordedAction};
+use crate::app::meeting::MeetingMode;
+use crate::app::mixing::{run_host_mix, HostMixer, MixSink, MixingTopology};
+use crate::app::presence::PresenceStatus;
+use crate::app::recording::{RecordingRoster, RECORDING_ACK_TIMEOUT};
+use crate::app::session_timer::{
+    append_ends_at, ends_at_from_link, unix_now, SessionTimer, TIME_WARNINGS,
+};
+use crate::app::shutdown::{ShutdownCoordinator, DRAIN_GRACE};
+use crate::app::synced_media::synced_delay;
+use crate::app::topology::{self, Plane};
+use crate::audio::{
+    append_codec, append_frame_duration, codec_from_link, frame_duration_from_link,
+    noise_frame_len, AudioDecoder, AudioFrame, CodecSettings, ComfortNoiseGenerator, FrameDuration,
+    Repacketizer, SpatialLayout, SyncedPlayout,
+};
 use crate::network::{
-    discover_public_endpoint, generate_connection_link, parse_connection_link, ConnectionManager,
-    ConnectionState, Endpoint, Message,
+    add_link_candidates, bind_udp, detect_nat, generate_connection_link, local_ipv4,
+    parse_connection_link, parse_link_candidates, BandwidthCaps, BandwidthMonitor, BandwidthUsage,
+    BindAddress, ConnectionDetails, ConnectionManager, ConnectionState, ConnectionStrategy,
+    CryptoStats, Endpoint, EvictionStats, HostListener, IceServerList, IncomingPeer, Keypair,
+    Message, MtuConfig, NatReport, NetworkError, NetworkStats, NonceStrategy, PacketCounts,
+    PeerTable, PortMapper, Proxy, Retry, RetryOperation, RetrySettings, HANDSHAKE_TIMEOUT,
 };
+use crate::ui::i18n::t;
 use crate::ui::Participant;
 
+/// How often a host should call [`SessionManager::accept_joins`]
+pub const JOIN_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long a peer that reached the host socket has to ask to join
+const JOIN_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long we wait for the host of a room to let us in
+const ADMISSION_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// How long peers the host turned away wait before they're told, so the
+/// host can still take it back
+const DENY_GRACE: Duration = Duration::from_secs(30);
+
+/// What peers the host turned away are told
+const DENIED_MESSAGE: &str = "Request declined";
+
 /// Represents a communication session
 #[derive(Debug, Clone)]
 pub struct Session {
@@ -25,9 +72,14 @@ pub struct Session {
     pub original_host_id: String,
     /// Time the session was created
     pub created_at: u64,
+    /// How audio is routed, chosen by the host when the room is created
+    pub topology: MixingTopology,
 }
 
 /// Error types for session operations
+///
+/// Each has a stable [`code`](SessionError::code) for scripts, and the
+/// common failures a [`user_hint`](SessionError::user_hint) on what to try.
 #[derive(Debug, thiserror::Error)]
 pub enum SessionError {
     #[error("Failed to create session: {0}")]
@@ -42,11 +94,95 @@ pub enum SessionError {
     #[error("No active session")]
     NoActiveSession,
 
+    #[error("Only the host can do that")]
+    NotHost,
+
     #[error("Network error: {0}")]
     NetworkError(String),
 
     #[error("UI error: {0}")]
     UiError(String),
+
+    #[error("Session manager not initialized")]
+    NotInitialized,
+
+    #[error("Invalid link: {0}")]
+    InvalidLink(String),
+
+    #[error("Couldn't find our public address: {0}")]
+    NatDiscovery(String),
+
+    #[error("Couldn't use the chosen network interface: {0}")]
+    BindFailed(String),
+
+    #[error("Couldn't reach the peer: {0}")]
+    ConnectionFailed(String),
+
+    #[error("No answer from {0} after connecting")]
+    HandshakeTimedOut(std::net::SocketAddr),
+
+    #[error("No peer {0} in the session")]
+    PeerNotFound(String),
+
+    #[error("Couldn't hand off the call: {0}")]
+    Handoff(String),
+
+    #[error("No host action {0} in the history")]
+    UnknownAction(u64),
+
+    #[error("Can't undo: {0}")]
+    CannotUndo(String),
+
+    #[error("The host didn't acknowledge the recording in time")]
+    RecordingNotAcknowledged,
+
+    #[error("The host turned us away: {0}")]
+    JoinDenied(String),
+}
+
+impl SessionError {
+    /// Short, stable name for the kind of failure
+    pub fn code(&self) -> &'static str {
+        match self {
+            SessionError::CreationError(_) => "CREATE_FAILED",
+            SessionError::JoinError(_) => "JOIN_FAILED",
+            SessionError::LeaveError(_) => "LEAVE_FAILED",
+            SessionError::NoActiveSession => "NO_SESSION",
+            SessionError::NotHost => "NOT_HOST",
+            SessionError::NetworkError(_) => "NETWORK",
+            SessionError::UiError(_) => "INTERNAL",
+            SessionError::NotInitialized => "NOT_INITIALIZED",
+            SessionError::InvalidLink(_) => "INVALID_LINK",
+            SessionError::NatDiscovery(_) => "NAT_DISCOVERY",
+            SessionError::BindFailed(_) => "BIND_FAILED",
+            SessionError::ConnectionFailed(_) => "CONNECTION_FAILED",
+            SessionError::HandshakeTimedOut(_) => "HANDSHAKE_TIMEOUT",
+            SessionError::PeerNotFound(_) => "PEER_NOT_FOUND",
+            SessionError::Handoff(_) => "HANDOFF_FAILED",
+            SessionError::UnknownAction(_) => "UNKNOWN_ACTION",
+            SessionError::CannotUndo(_) => "CANNOT_UNDO",
+            SessionError::RecordingNotAcknowledged => "RECORDING_NOT_ACKNOWLEDGED",
+            SessionError::JoinDenied(_) => "JOIN_DENIED",
+        }
+    }
+
+    /// What the user could try, for failures there's something to try about
+    pub fn user_hint(&self) -> Option<&'static str> {
+        let key = match self {
+            SessionError::NoActiveSession => "hint.no_session",
+            SessionError::NotHost => "hint.not_host",
+            SessionError::InvalidLink(_) => "hint.invalid_link",
+            SessionError::NatDiscovery(_) => "hint.nat_discovery",
+            SessionError::BindFailed(_) => "hint.bind_failed",
+            SessionError::ConnectionFailed(_) => "hint.connection_failed",
+            SessionError::HandshakeTimedOut(_) => "hint.handshake_timeout",
+            SessionError::PeerNotFound(_) => "hint.peer_not_found",
+            SessionError::Handoff(_) => "hint.handoff",
+            SessionError::RecordingNotAcknowledged => "hint.recording_consent",
+            _ => return None,
+        };
+        Some(t(key))
+    }
 }
 
 impl From<anyhow::Error> for SessionError {
@@ -77,514 +213,3050 @@ pub struct Peer {
 /// Manages audio communication sessions
 pub struct SessionManager {
     current_session: Option<Session>,
-    audio_streams: HashMap<String, Arc<Mutex<Vec<f32>>>>,
+    audio_streams: HashMap<String, Arc<Mutex<AudioFrame>>>,
     // Changed from Option<ConnectionManager> to a HashMap to support multiple peers
     peer_connections: HashMap<String, ConnectionManager>,
-    background_tasks: Vec<JoinHandle<()>>,
+    // Background tasks, drained in order when we leave, and each peer's
+    // message handler, so one can be stopped alone
+    shutdown: ShutdownCoordinator,
+    listeners: HashMap<String, AbortHandle>,
     host_public_endpoint: Option<Endpoint>,
-    // Track all peers in the session
-    peers: HashMap<String, Peer>,
+    // Track all peers in the session, shared with the message handlers
+    peers: Arc<Mutex<PeerTable<Peer>>>,
     // Current user's ID
     self_id: String,
+    // Optional record of room events
+    audit_log: Option<Arc<Mutex<AuditLog>>>,
+    // Events for the UI, and the receiving end until it is taken
+    event_tx: mpsc::UnboundedSender<SessionEvent>,
+    event_rx: Option<mpsc::UnboundedReceiver<SessionEvent>>,
+    // Topology used for rooms we create
+    topology: MixingTopology,
+    // Mixes peers' audio when we host a host-mixed room, and the connections
+    // the mix loop sends over
+    mixer: Option<Arc<Mutex<HostMixer>>>,
+    mix_targets: MixTargets,
+    // Whether to forward a port on the router when hosting
+    port_mapping: bool,
+    // STUN and TURN servers asked about our NAT, in priority order
+    ice_servers: IceServerList,
+    // Interface or address our sockets are bound to
+    bind_address: BindAddress,
+    // How signaling connections over TCP are made, and the room's own choice if it has one
+    proxy: Proxy,
+    room_proxy: Option<Proxy>,
+    // How the largest datagram sent to each peer is chosen
+    mtu: MtuConfig,
+    // How failed connection attempts are repeated
+    retry: RetrySettings,
+    // Audio frames packed into each datagram
+    audio_bundling: usize,
+    // Whether silent frames are replaced by comfort noise descriptors
+    silence_suppression: bool,
+    // Whether we offer peers ratcheted message keys
+    forward_secrecy: bool,
+    // How our channels make nonces
+    nonce_strategy: NonceStrategy,
+    // Codec we'd like to send audio with, and the room's own choice if it has one
+    codec: CodecSettings,
+    room_codec: Option<CodecSettings>,
+    // Frame duration for rooms we create, and captured audio cut to the room's
+    frame_duration: FrameDuration,
+    repacketizer: Arc<Mutex<Repacketizer>>,
+    // Plays our audio back to us, if the host added one to the room
+    echo_bot: Arc<Mutex<Option<EchoBot>>>,
+    // Whether encoders spend less CPU, because the machine can't keep up
+    low_complexity: bool,
+    // Traffic across all our connections, and the caps it's kept under
+    bandwidth: BandwidthMonitor,
+    // Who the host has muted, shared with the message handlers
+    meeting: Arc<Mutex<MeetingMode>>,
+    // Who is recording, shared with the message handlers, and word from them
+    // that the host acknowledged our recording
+    recording: Arc<Mutex<RecordingRoster>>,
+    recording_ack: Arc<Notify>,
+    // Whether recordings in rooms we host wait for us to acknowledge them
+    recording_consent: bool,
+    // Whether the room plays our audio in sync, the delay each peer last
+    // heard from us, and the delays peers asked for theirs, by peer id
+    synced_playout: bool,
+    synced_sent: HashMap<String, u32>,
+    playouts: Arc<Mutex<HashMap<String, u32>>>,
+    // When we last heard each peer we send audio to, shared with the message handlers
+    audio_watchdog: Arc<Mutex<AudioWatchdog>>,
+    // Our own names for peers, by public key
+    aliases: PeerAliases,
+    // Whether join requests are held back, and how
+    dnd: DoNotDisturb,
+    // Join requests waiting for us to decide, in the order they arrived
+    admission: AdmissionQueue,
+    // Peers reaching the host socket, those not let in yet by address, and
+    // what they tell us meanwhile
+    listener: Option<HostListener>,
+    pending_joins: HashMap<std::net::SocketAddr, PendingJoin>,
+    lobby_tx: mpsc::UnboundedSender<(std::net::SocketAddr, LobbyMessage)>,
+    lobby_rx: mpsc::UnboundedReceiver<(std::net::SocketAddr, LobbyMessage)>,
+    // The name we ask to join rooms as, and the host's answer when we do
+    username: String,
+    join_reply: Arc<watch::Sender<Option<JoinReply>>>,
+    // What we've done to the room as its host, to take again or undo
+    host_actions: Arc<Mutex<HostActionHistory>>,
+    // Socket peers reach us on, and its router mapping, while hosting
+    host_socket: Option<Arc<tokio::net::UdpSocket>>,
+    port_mapper: Option<PortMapper>,
+    // What STUN found out about our NAT when we last hosted
+    nat_report: Option<NatReport>,
+    // Longest a room we create may run
+    time_limit: Option<Duration>,
+    // Time in the current session and its limit, if any
+    timer: Option<SessionTimer>,
+    // How participants are seated in virtual space
+    layout: SpatialLayout,
+}
+
+/// A peer that reached the host socket and hasn't been let in yet
+struct PendingJoin {
+    connection: ConnectionManager,
+    handler: AbortHandle,
+    public_key: [u8; 32],
+    // What it asked to join as, once it has
+    request: Option<JoinRequest>,
+    // When it's dropped: if it never asks, or once it's been turned away
+    drop_at: Option<Instant>,
+    // When it's told the host turned it away, unless the host undoes that first
+    deny_at: Option<Instant>,
+}
+
+/// What a peer not yet let in tells us, passed on by its connection's handler
+enum LobbyMessage {
+    Asked(JoinRequest),
+    Left,
+}
+
+/// Connections to everyone in a room we mix for, shared with the mix loop
+#[derive(Clone, Default)]
+struct MixTargets(Arc<Mutex<HashMap<String, ConnectionManager>>>);
+
+#[async_trait]
+impl MixSink for MixTargets {
+    async fn recipients(&self) -> Vec<String> {
+        let connections: Vec<(String, ConnectionManager)> = self
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(peer_id, connection)| (peer_id.clone(), connection.clone()))
+            .collect();
+        let mut recipients = Vec::new();
+        for (peer_id, connection) in connections {
+            if connection.is_connected().await {
+                recipients.push(peer_id);
+            }
+        }
+        recipients
+    }
+
+    async fn send_mix(&self, peer_id: &str, mix: &AudioFrame) -> Result<()> {
+        let connection = self.0.lock().unwrap().get(peer_id).cloned();
+        match connection {
+            Some(connection) => connection.send_audio(mix).await,
+            None => Ok(()),
+        }
+    }
+}
+
+/// Sends an event to the UI and records it in the audit log, if one is enabled
+fn publish_event(
+    audit_log: &Option<Arc<Mutex<AuditLog>>>,
+    event_tx: &mpsc::UnboundedSender<SessionEvent>,
+    event: SessionEvent,
+) {
+    if let Some(audit_event) = AuditEvent::from_session_event(&event) {
+        record_audit_event(audit_log, audit_event);
+    }
+
+    // The receiver may not have been taken, or may have been dropped
+    let _ = event_tx.send(event);
+}
+
+/// Records an event in the audit log, if one is enabled
+fn record_audit_event(audit_log: &Option<Arc<Mutex<AuditLog>>>, event: AuditEvent) {
+    if let Some(log) = audit_log {
+        if let Err(e) = log.lock().unwrap().record(event) {
+            log::warn!("Failed to record audit event: {}", e);
+        }
+    }
+}
+
+/// The name to show for a peer, preferring our alias for it over the name it sent
+fn display_name(peers: &PeerTable<Peer>, aliases: &PeerAliases, peer_id: &str) -> String {
+    peers
+        .get(peer_id)
+        .map(|peer| aliases.name_for(&peer.public_key, &peer.name))
+        .unwrap_or_else(|| peer_id.to_string())
+}
+
+/// Records how long after capture a peer asked for its audio to be played
+fn set_playout(playouts: &Mutex<HashMap<String, u32>>, peer_id: String, delay_ms: u32) {
+    let mut playouts = playouts.lock().unwrap();
+    if delay_ms == 0 {
+        playouts.remove(&peer_id);
+    } else {
+        playouts.insert(peer_id, delay_ms);
+    }
+}
+
+impl Default for SessionManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SessionManager {
     /// Creates a new session manager
     pub fn new() -> Self {
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        let (lobby_tx, lobby_rx) = mpsc::unbounded_channel();
+
         Self {
             current_session: None,
             audio_streams: HashMap::new(),
             peer_connections: HashMap::new(),
-            background_tasks: Vec::new(),
+            shutdown: ShutdownCoordinator::new(),
+            listeners: HashMap::new(),
             host_public_endpoint: None,
-            peers: HashMap::new(),
+            peers: Arc::new(Mutex::new(PeerTable::new())),
             self_id: uuid::Uuid::new_v4().to_string(),
+            audit_log: None,
+            event_tx,
+            event_rx: Some(event_rx),
+            topology: MixingTopology::default(),
+            mixer: None,
+            mix_targets: MixTargets::default(),
+            port_mapping: false,
+            ice_servers: IceServerList::default(),
+            bind_address: BindAddress::Auto,
+            proxy: Proxy::Direct,
+            room_proxy: None,
+            mtu: MtuConfig::default(),
+            retry: RetrySettings::default(),
+            audio_bundling: 1,
+            silence_suppression: true,
+            forward_secrecy: true,
+            nonce_strategy: NonceStrategy::default(),
+            codec: CodecSettings::default(),
+            room_codec: None,
+            frame_duration: FrameDuration::default(),
+            repacketizer: Arc::new(Mutex::new(Repacketizer::new(FrameDuration::default()))),
+            echo_bot: Arc::new(Mutex::new(None)),
+            low_complexity: false,
+            bandwidth: BandwidthMonitor::default(),
+            meeting: Arc::new(Mutex::new(MeetingMode::new())),
+            recording: Arc::new(Mutex::new(RecordingRoster::new())),
+            recording_ack: Arc::new(Notify::new()),
+            recording_consent: true,
+            synced_playout: false,
+            synced_sent: HashMap::new(),
+            playouts: Arc::new(Mutex::new(HashMap::new())),
+            audio_watchdog: Arc::new(Mutex::new(AudioWatchdog::new())),
+            aliases: PeerAliases::default(),
+            dnd: DoNotDisturb::default(),
+            admission: AdmissionQueue::new(),
+            listener: None,
+            pending_joins: HashMap::new(),
+            lobby_tx,
+            lobby_rx,
+            username: "User".to_string(),
+            join_reply: Arc::new(watch::channel(None).0),
+            host_actions: Arc::new(Mutex::new(HostActionHistory::new())),
+            host_socket: None,
+            port_mapper: None,
+            nat_report: None,
+            time_limit: None,
+            timer: None,
+            layout: SpatialLayout::default(),
         }
     }
 
-    /// Creates a new P2P session
-    pub async fn create_p2p_session(&mut self) -> Result<Session, SessionError> {
-        // First leave any existing session
-        if self.current_session.is_some() {
-            self.leave_session().await?;
-        }
+    /// Takes the receiver for session events
+    ///
+    /// Only one receiver exists per manager; later calls return `None`.
+    pub fn take_event_receiver(&mut self) -> Option<mpsc::UnboundedReceiver<SessionEvent>> {
+        self.event_rx.take()
+    }
 
-        // Discover public IP and port via STUN
-        let endpoint = discover_public_endpoint()
-            .await
-            .map_err(|e| SessionError::CreationError(format!("IP discovery failed: {}", e)))?;
+    /// Records room events in `log` from now on, or stops recording them
+    ///
+    /// Handlers already listening to peers keep the log they started with
+    /// until the session ends.
+    pub fn set_audit_log(&mut self, log: Option<AuditLog>) {
+        self.audit_log = log.map(|log| Arc::new(Mutex::new(log)));
+    }
 
-        // Save host endpoint
-        self.host_public_endpoint = Some(endpoint.clone());
+    /// Gets the audit log, if one is enabled
+    pub fn audit_log(&self) -> Option<Arc<Mutex<AuditLog>>> {
+        self.audit_log.clone()
+    }
 
-        // Generate session ID
-        let session_id = uuid::Uuid::new_v4().to_string();
+    /// Sets how audio is routed in rooms created from now on
+    ///
+    /// Joined rooms use the topology chosen by their host.
+    pub fn set_mixing_topology(&mut self, topology: MixingTopology) {
+        self.topology = topology;
+    }
 
-        // Generate keypair
-        let keypair = crate::network::Keypair::generate();
-        let public_key = keypair.public.to_bytes();
+    /// Limits how long rooms created from now on may run
+    ///
+    /// Joined rooms end when their host's limit says so.
+    pub fn set_time_limit(&mut self, limit: Option<Duration>) {
+        self.time_limit = limit;
+    }
 
-        // Current timestamp for session creation
-        let timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
+    /// Sets whether recordings in rooms we host wait for us to acknowledge them
+    ///
+    /// Guests hear of it when they join, or at the next peer list.
+    pub fn set_recording_consent(&mut self, required: bool) {
+        self.recording_consent = required;
+    }
 
-        // Generate shareable link
-        let connection_link = generate_connection_link(&endpoint, &session_id, &public_key);
+    /// Sets whether the room plays our audio in sync, for sharing music or
+    /// video everyone hears together
+    ///
+    /// Takes effect at the next [`SessionManager::update_synced_playout`].
+    pub fn set_synced_playout(&mut self, enabled: bool) {
+        self.synced_playout = enabled;
+    }
 
-        // Add ourselves as a peer
-        let self_peer = Peer {
-            id: self.self_id.clone(),
-            name: "Me".to_string(),
-            endpoint: endpoint.clone(),
-            public_key,
-            position: (0.0, 0.0, 0.0),
-            is_host: true,
-            joined_at: timestamp,
-        };
+    /// Sets how participants are seated in rooms we host
+    ///
+    /// Takes effect the next time the room is arranged.
+    pub fn set_spatial_layout(&mut self, layout: SpatialLayout) {
+        self.layout = layout;
+    }
 
-        self.peers.insert(self.self_id.clone(), self_peer);
+    /// How participants are seated in the current room
+    pub fn spatial_layout(&self) -> SpatialLayout {
+        self.layout
+    }
 
-        // Create session
-        let current_user = Participant::new("Me").with_position(0.0, 0.0, 0.0);
-        let session = Session {
-            id: session_id.clone(),
-            connection_link: connection_link.clone(),
-            participants: vec![current_user],
-            is_host: true,
-            original_host_id: self.self_id.clone(),
-            created_at: timestamp,
+    /// Seats everyone with the current layout and sends the seats to the room
+    ///
+    /// Only the host arranges the room. The host takes the first seat, then
+    /// peers in the order they joined.
+    pub async fn arrange_participants(&mut self) -> Result<(), SessionError> {
+        let session_id = match &self.current_session {
+            Some(session) if session.is_host => session.id.clone(),
+            Some(_) => return Ok(()),
+            None => return Err(SessionError::NoActiveSession),
         };
 
-        // Start listening for incoming connections
-        // TODO: Implement a listener for incoming connections
+        let mut seated = self.other_peers();
+        seated.sort_by(|a, b| (a.joined_at, &a.id).cmp(&(b.joined_at, &b.id)));
 
-        self.current_session = Some(session.clone());
-        Ok(session)
-    }
+        // Peers know us by the host ID in the join link
+        let mut ids = vec![format!("host-{}", session_id)];
+        ids.extend(seated.into_iter().map(|peer| peer.id));
 
-    /// Joins an existing session using a connection link
-    pub async fn join_p2p_session(&mut self, link: &str) -> Result<(), SessionError> {
-        // First leave any existing session
-        if self.current_session.is_some() {
-            self.leave_session().await?;
+        let seats = self.peers.lock().unwrap().len().max(1);
+        let positions: Vec<(String, (f32, f32, f32))> =
+            ids.into_iter().zip(self.layout.positions(seats)).collect();
+
+        for connection in self.peer_connections.values() {
+            if connection.is_connected().await {
+                connection
+                    .send_layout(self.layout, &positions)
+                    .await
+                    .map_err(|e| SessionError::NetworkError(e.to_string()))?;
+            }
         }
 
-        // Parse connection link
-        let (remote_ip, remote_port, session_id, remote_key) = parse_connection_link(link)
-            .map_err(|e| SessionError::JoinError(format!("Invalid link: {}", e)))?;
+        // We're in the first seat under our own ID
+        let mut local = positions;
+        local[0].0 = self.self_id.clone();
+        self.apply_layout(self.layout, &local);
+        Ok(())
+    }
 
-        // Host endpoint
-        let host_endpoint = Endpoint {
-            ip: remote_ip,
-            port: remote_port,
-        };
+    /// Moves peers and their participants to the seats of a layout
+    ///
+    /// Seats for peers we don't know are skipped. Each peer that moved is
+    /// announced with a position change.
+    pub fn apply_layout(&mut self, layout: SpatialLayout, positions: &[(String, (f32, f32, f32))]) {
+        self.layout = layout;
 
-        // Host ID
-        let host_id = format!("host-{}", session_id);
+        let mut peers = self.peers.lock().unwrap();
+        for (peer_id, position) in positions {
+            let Some(peer) = peers.get_mut(peer_id) else {
+                continue;
+            };
+            if peer.position != *position {
+                publish_event(
+                    &self.audit_log,
+                    &self.event_tx,
+                    SessionEvent::PositionChanged {
+                        peer_id: peer_id.clone(),
+                        position: *position,
+                    },
+                );
+            }
+            peer.position = *position;
+
+            let name = self.aliases.name_for(&peer.public_key, &peer.name);
+            if let Some(session) = &mut self.current_session {
+                for participant in session
+                    .participants
+                    .iter_mut()
+                    .filter(|participant| participant.name == name)
+                {
+                    participant.position = *position;
+                }
+            }
+        }
+    }
 
-        // Create connection manager for the host
-        let connection_manager =
-            ConnectionManager::new(remote_ip, remote_port, session_id.clone(), remote_key);
+    /// Time in the current session and until it ends
+    pub fn session_timer(&self) -> Option<&SessionTimer> {
+        self.timer.as_ref()
+    }
 
-        // Connect to remote peer
-        connection_manager
-            .connect()
-            .await
-            .map_err(|e| SessionError::JoinError(format!("Connection failed: {}", e)))?;
+    /// Starts the session timer and schedules warnings before the end time
+    fn start_timer(&mut self, ends_at: Option<u64>) {
+        let timer = SessionTimer::start(ends_at);
+
+        if let Some(ends_at) = timer.ends_at() {
+            let audit_log = self.audit_log.clone();
+            let event_tx = self.event_tx.clone();
+            self.shutdown.track(topology::spawn(
+                Plane::Control,
+                "session-timer",
+                async move {
+                    for warning in TIME_WARNINGS {
+                        // Warnings already past when we joined are skipped
+                        let Some(at) = ends_at.checked_sub(warning) else {
+                            continue;
+                        };
+                        if at <= std::time::Instant::now() {
+                            continue;
+                        }
+                        tokio::time::sleep_until(at.into()).await;
+                        publish_event(
+                            &audit_log,
+                            &event_tx,
+                            SessionEvent::TimeWarning {
+                                remaining_secs: warning.as_secs(),
+                            },
+                        );
+                    }
 
-        // Current timestamp for joining
-        let timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
+                    tokio::time::sleep_until(ends_at.into()).await;
+                    publish_event(&audit_log, &event_tx, SessionEvent::TimeLimitReached);
+                },
+            ));
+        }
 
-        // Add the host to peers list
-        let host_peer = Peer {
-            id: host_id.clone(),
-            name: "Host".to_string(),
-            endpoint: host_endpoint,
-            public_key: remote_key,
-            position: (0.0, 0.0, -1.0),
-            is_host: true,
-            joined_at: timestamp - 1, // Host joined before us
-        };
-        self.peers.insert(host_id.clone(), host_peer);
+        self.timer = Some(timer);
+    }
 
-        // Add ourselves to the peers list
-        let self_peer = Peer {
-            id: self.self_id.clone(),
-            name: "Me".to_string(),
-            endpoint: Endpoint {
-                ip: "0.0.0.0".parse().unwrap(),
-                port: 0,
-            }, // Will be updated after STUN
-            public_key: [0; 32], // Will be set properly later
-            position: (0.0, 0.0, 0.0),
-            is_host: false,
-            joined_at: timestamp,
-        };
-        self.peers.insert(self.self_id.clone(), self_peer);
+    /// Enables UPnP/NAT-PMP port mapping for rooms created from now on
+    pub fn set_port_mapping(&mut self, enabled: bool) {
+        self.port_mapping = enabled;
+    }
 
-        // Start message handler
-        let audio_streams = self.audio_streams.clone();
-        let peers = Arc::new(Mutex::new(self.peers.clone()));
-        let session_id_clone = session_id.clone();
-        let self_id_clone = self.self_id.clone();
+    /// Sets the STUN and TURN servers asked about our NAT; empty uses the built-in ones
+    pub fn set_ice_servers(&mut self, servers: IceServerList) {
+        self.ice_servers = servers;
+    }
 
-        let handler_task = connection_manager
-            .start_listening(move |message| {
-                match message {
-                    Message::Audio { data, timestamp: _ } => {
-                        // Convert audio data to f32 samples
-                        // This is a simplified example - real implementation would properly convert
-                        let samples: Vec<f32> = data.iter().map(|&b| (b as f32) / 255.0).collect();
+    /// Binds sockets to an interface or address instead of any, for connections from now on
+    pub fn set_bind_address(&mut self, bind: BindAddress) {
+        self.bind_address = bind;
+    }
 
-                        // Store audio stream for "Host"
-                        if let Some(stream) = audio_streams.get("Host") {
-                            let mut stream = stream.lock().unwrap();
-                            *stream = samples;
-                        }
-                    }
-                    Message::PeerList { peers: peer_list } => {
-                        // Received peer list from host
-                        let mut peers_lock = peers.lock().unwrap();
+    /// Sets how signaling connections over TCP are made, unless a room overrides it
+    pub fn set_proxy(&mut self, proxy: Proxy) {
+        self.proxy = proxy;
+    }
 
-                        // Update our peer list with the received information
-                        for peer in peer_list {
-                            // Don't update ourselves
-                            if peer.id != self_id_clone {
-                                peers_lock.insert(peer.id.clone(), peer);
-                            }
-                        }
+    /// Overrides the proxy for the room we're in or create next; `None` goes back to ours
+    pub fn set_room_proxy(&mut self, proxy: Option<Proxy>) {
+        self.room_proxy = proxy;
+    }
 
-                        // TODO: Connect to other peers in the list
-                    }
-                    Message::NewPeer { peer } => {
-                        // A new peer joined the session
-                        let mut peers_lock = peers.lock().unwrap();
+    /// Proxy signaling connections for the current room go through
+    pub fn proxy(&self) -> &Proxy {
+        self.room_proxy.as_ref().unwrap_or(&self.proxy)
+    }
 
-                        // Add to our peer list
-                        if peer.id != self_id_clone {
-                            peers_lock.insert(peer.id.clone(), peer);
-                        }
+    /// Sets how the largest datagram sent to each peer is chosen, for new connections
+    pub fn set_mtu(&mut self, config: MtuConfig) {
+        self.mtu = config;
+    }
 
-                        // TODO: Connect to this new peer
-                    }
-                    Message::PeerLeft { peer_id } => {
-                        // A peer left the session
-                        let mut peers_lock = peers.lock().unwrap();
+    /// Sets how failed attempts to reach the host and peers are repeated
+    pub fn set_retry(&mut self, settings: RetrySettings) {
+        self.retry = settings;
+    }
 
-                        // Remove from our peer list
-                        peers_lock.remove(&peer_id);
+    /// Sets how many audio frames go in each datagram
+    pub fn set_audio_bundling(&mut self, frames: usize) {
+        self.audio_bundling = frames;
+        for connection in self.peer_connections.values() {
+            connection.set_audio_bundling(frames);
+        }
+    }
 
-                        // If the host left, elect a new host
-                        let mut new_host = false;
-                        for peer in peers_lock.values() {
-                            if peer.is_host && peer.id == peer_id {
-                                new_host = true;
-                                break;
-                            }
-                        }
+    /// Sets whether silence is sent as comfort noise descriptors, for new connections
+    pub fn set_silence_suppression(&mut self, enabled: bool) {
+        self.silence_suppression = enabled;
+    }
 
-                        if new_host {
-                            // Simple host election: oldest peer becomes host
-                            let mut oldest_time = u64::MAX;
-                            let mut oldest_id = String::new();
+    /// Sets whether message keys are ratcheted with peers that support it, for new connections
+    pub fn set_forward_secrecy(&mut self, enabled: bool) {
+        self.forward_secrecy = enabled;
+    }
 
-                            for (id, peer) in peers_lock.iter() {
-                                if peer.joined_at < oldest_time {
-                                    oldest_time = peer.joined_at;
-                                    oldest_id = id.clone();
-                                }
-                            }
+    /// Sets how nonces are made for what we send, for new connections
+    pub fn set_nonce_strategy(&mut self, strategy: NonceStrategy) {
+        self.nonce_strategy = strategy;
+    }
 
-                            // If we're the oldest, we become host
-                            if oldest_id == self_id_clone {
-                                if let Some(peer) = peers_lock.get_mut(&self_id_clone) {
-                                    peer.is_host = true;
+    /// Sets the codec we'd like to send audio with, unless the room sets its own
+    pub fn set_codec(&mut self, settings: CodecSettings) {
+        self.codec = settings;
+        self.apply_codec();
+    }
 
-                                    // TODO: Notify other peers that we're the new host
-                                }
-                            }
-                        }
-                    }
-                    // Handle other message types as needed
-                    _ => {}
-                }
+    /// Sets the frame duration for rooms we create
+    ///
+    /// It goes in the link, so everyone who joins sends frames of the same
+    /// length. Joining a room uses the room's own.
+    pub fn set_frame_duration(&mut self, duration: FrameDuration) {
+        self.frame_duration = duration;
+    }
 
-                Ok(())
-            })
-            .await;
+    /// Sets the codec for the room, or `None` to leave it to each peer
+    ///
+    /// For a room we create the choice goes in the link, so everyone who
+    /// joins with it uses it too. Joining a room replaces it with the
+    /// room's own.
+    pub fn set_room_codec(&mut self, settings: Option<CodecSettings>) {
+        self.room_codec = settings;
+        self.apply_codec();
+    }
 
-        self.background_tasks.push(handler_task);
-        self.peer_connections
-            .insert(host_id.clone(), connection_manager);
+    /// The codec we send audio with, before negotiation with each peer
+    pub fn codec(&self) -> CodecSettings {
+        self.room_codec.unwrap_or(self.codec)
+    }
 
-        // Create local session representation with host and current user
-        let current_user = Participant::new("Me").with_position(0.0, 0.0, 0.0);
-        let host = Participant::new("Host").with_position(0.0, 0.0, -1.0);
+    /// Has every encoder spend less CPU per frame, or the usual amount again
+    pub fn set_low_complexity(&mut self, low: bool) {
+        self.low_complexity = low;
+        self.apply_codec();
+    }
 
-        let session = Session {
-            id: session_id,
-            connection_link: link.to_string(),
-            participants: vec![current_user, host],
-            is_host: false,
-            original_host_id: host_id,
-            created_at: timestamp - 1, // Host created before we joined
+    fn apply_codec(&self) {
+        for connection in self.peer_connections.values() {
+            connection.set_codec(self.codec());
+            connection.set_low_complexity(self.low_complexity);
+        }
+    }
+
+    /// Caps the room's upload and download
+    ///
+    /// Audio sent adapts at once. Pacing follows the upload cap from the
+    /// next connection on.
+    pub async fn set_bandwidth_caps(&mut self, caps: BandwidthCaps) {
+        self.bandwidth.set_caps(caps);
+        self.share_download_cap().await;
+    }
+
+    /// Traffic across all our connections, against the caps
+    pub fn bandwidth_usage(&self) -> BandwidthUsage {
+        self.bandwidth.usage()
+    }
+
+    /// Traffic, loss and round trips across all our connections, for the status area
+    pub async fn network_stats(&self) -> NetworkStats {
+        let mut packets = PacketCounts::default();
+        let (mut round_trip, mut answered) = (Duration::ZERO, 0);
+        for connection in self.peer_connections.values() {
+            packets = packets + connection.packet_counts().await;
+            if let Some(rtt) = connection.round_trip() {
+                round_trip += rtt;
+                answered += 1;
+            }
+        }
+        NetworkStats {
+            usage: self.bandwidth.usage(),
+            peers: self.peer_connections.len(),
+            packets,
+            rtt: (answered > 0).then(|| round_trip / answered),
+            evictions: self.peer_evictions(),
+        }
+    }
+
+    /// Asks each peer to send us no more than its share of our download cap
+    async fn share_download_cap(&self) {
+        let share = self.bandwidth.caps().download_kbps.map_or(0, |cap| {
+            (cap / self.peer_connections.len().max(1) as u32).max(1)
+        });
+        for connection in self.peer_connections.values() {
+            if let Err(e) = connection.send_bandwidth_limit(share).await {
+                log::debug!("Failed to send bandwidth limit: {}", e);
+            }
+        }
+    }
+
+    /// Sets the name we ask to join rooms as
+    pub fn set_username(&mut self, name: String) {
+        self.username = name;
+    }
+
+    /// Sets our own names for peers, used from the next time they join
+    pub fn set_peer_aliases(&mut self, aliases: PeerAliases) {
+        self.aliases = aliases;
+    }
+
+    /// Turns do-not-disturb on or off for join requests
+    ///
+    /// Requests held while it was on reach the host now, as if they had just
+    /// arrived, joining the admission queue in the order they were held.
+    pub fn set_do_not_disturb(&mut self, enabled: bool) {
+        let released = self.dnd.set_enabled(enabled);
+        if released.is_empty() {
+            return;
+        }
+        for request in released {
+            self.enqueue_join(request);
+        }
+        self.publish_join_queue();
+    }
+
+    pub fn do_not_disturb(&self) -> bool {
+        self.dnd.is_enabled()
+    }
+
+    pub fn set_dnd_policy(&mut self, policy: DndPolicy) {
+        self.dnd.set_policy(policy);
+    }
+
+    /// Screens a request to join a room we host
+    ///
+    /// Normally the host is told and decides. Under do-not-disturb the
+    /// request is denied, with the message to send back, or held quietly.
+    pub fn screen_join_request(&mut self, request: JoinRequest) -> JoinDecision {
+        let peer_id = request.peer_id.clone();
+        let name = request.name.clone();
+        let decision = self.dnd.screen(request);
+        match &decision {
+            JoinDecision::Ask => {
+                self.enqueue_join(JoinRequest { peer_id, name });
+                self.publish_join_queue();
+            }
+            JoinDecision::Deny(_) => {
+                record_audit_event(&self.audit_log, AuditEvent::JoinDenied { peer_id })
+            }
+            JoinDecision::Queued => {}
+        }
+        decision
+    }
+
+    /// Takes in peers reaching the host socket and answers their requests to join
+    ///
+    /// Called regularly while hosting. A peer's keys are agreed as soon as
+    /// its handshake arrives, but it only joins the room once its request
+    /// gets past do-not-disturb and the host approves it. Peers that never
+    /// ask, or that leave while waiting, are dropped.
+    pub async fn accept_joins(&mut self) {
+        while let Some(incoming) = self.listener.as_mut().and_then(HostListener::try_accept) {
+            self.accept_joiner(incoming).await;
+        }
+
+        while let Ok((addr, message)) = self.lobby_rx.try_recv() {
+            match message {
+                LobbyMessage::Asked(request) => self.answer_join_request(addr, request).await,
+                LobbyMessage::Left => self.drop_pending_join(addr).await,
+            }
+        }
+
+        let now = Instant::now();
+        let denied: Vec<std::net::SocketAddr> = self
+            .pending_joins
+            .iter()
+            .filter(|(_, pending)| pending.deny_at.is_some_and(|at| at <= now))
+            .map(|(addr, _)| *addr)
+            .collect();
+        for addr in denied {
+            self.send_denial(addr, DENIED_MESSAGE.to_string()).await;
+        }
+
+        let expired: Vec<std::net::SocketAddr> = self
+            .pending_joins
+            .iter()
+            .filter(|(_, pending)| pending.drop_at.is_some_and(|at| at <= now))
+            .map(|(addr, _)| *addr)
+            .collect();
+        for addr in expired {
+            self.drop_pending_join(addr).await;
+        }
+    }
+
+    /// Agrees keys with a peer that reached the host socket, then waits for it to ask to join
+    async fn accept_joiner(&mut self, incoming: IncomingPeer) {
+        let (Some(session), Some(listener)) = (&self.current_session, &self.listener) else {
+            return;
         };
+        let addr = incoming.addr;
+        let mut connection = self.new_connection(
+            addr,
+            session.id.clone(),
+            incoming.public_key,
+            &addr.to_string(),
+        );
+        if let Err(e) = connection
+            .accept(listener.socket(), listener.keypair(), incoming.inbox)
+            .await
+        {
+            log::warn!("Couldn't take on the peer at {}: {}", addr, e);
+            listener.forget(addr);
+            return;
+        }
 
-        // Initialize audio stream for host
-        self.audio_streams
-            .insert("Host".to_string(), Arc::new(Mutex::new(Vec::new())));
+        // Nothing else it sends counts until it's let in
+        let lobby = self.lobby_tx.clone();
+        let handler = connection
+            .start_listening(move |message| {
+                let message = match message {
+                    Message::JoinRequest { peer_id, name } => {
+                        LobbyMessage::Asked(JoinRequest { peer_id, name })
+                    }
+                    Message::PeerLeft { .. } => LobbyMessage::Left,
+                    _ => return Ok(()),
+                };
+                let _ = lobby.send((addr, message));
+                Ok(())
+            })
+            .await;
 
-        self.current_session = Some(session);
-        Ok(())
+        self.pending_joins.insert(
+            addr,
+            PendingJoin {
+                connection,
+                handler: handler.abort_handle(),
+                public_key: incoming.public_key,
+                request: None,
+                drop_at: Some(Instant::now() + JOIN_REQUEST_TIMEOUT),
+                deny_at: None,
+            },
+        );
     }
 
-    /// Leaves the current session
-    pub async fn leave_session(&mut self) -> Result<(), SessionError> {
-        if self.current_session.is_some() {
-            // Notify all peers that we're leaving
-            for (peer_id, connection) in &self.peer_connections {
-                // Skip sending if connection is not active
-                if connection.is_connected().await {
-                    let _ = connection.send_peer_left(&self.self_id).await;
+    /// Screens a request that came in on the host socket and tells the peer what happens next
+    async fn answer_join_request(&mut self, addr: std::net::SocketAddr, request: JoinRequest) {
+        let Some(pending) = self.pending_joins.get_mut(&addr) else {
+            return;
+        };
+        pending.request = Some(request.clone());
+        pending.drop_at = None;
+        let connection = pending.connection.clone();
+
+        match self.screen_join_request(request) {
+            JoinDecision::Deny(message) => self.send_denial(addr, message).await,
+            JoinDecision::Ask | JoinDecision::Queued => {
+                if let Err(e) = connection.send_join_reply(JoinReply::Waiting).await {
+                    log::debug!("Failed to answer the join request from {}: {}", addr, e);
                 }
             }
+        }
+    }
+
+    /// Tells a peer waiting on the host socket it was turned away, keeping
+    /// it only until the reply has gone out
+    async fn send_denial(&mut self, addr: std::net::SocketAddr, message: String) {
+        let Some(pending) = self.pending_joins.get_mut(&addr) else {
+            return;
+        };
+        pending.deny_at = None;
+        pending.drop_at = Some(Instant::now() + DRAIN_GRACE);
+        let connection = pending.connection.clone();
+        if let Err(e) = connection.send_join_reply(JoinReply::Denied(message)).await {
+            log::debug!("Failed to turn away the peer at {}: {}", addr, e);
+        }
+    }
+
+    /// Whether a peer that asked to join on the host socket is still there to let in
+    async fn still_waiting(&self, peer_id: &str) -> bool {
+        let Some(pending) = self
+            .pending_join_addr(peer_id)
+            .and_then(|addr| self.pending_joins.get(&addr))
+        else {
+            return false;
+        };
+        // Peers on their way out have already been told they were turned away
+        pending.drop_at.is_none()
+            && pending.connection.is_connected().await
+            && !pending.connection.gone_quiet()
+    }
 
-            // Clear audio streams for all participants
-            self.audio_streams.clear();
+    /// Closes the connection to a peer we haven't let in, taking it out of line
+    async fn drop_pending_join(&mut self, addr: std::net::SocketAddr) {
+        let Some(pending) = self.pending_joins.remove(&addr) else {
+            return;
+        };
+        pending.handler.abort();
+        pending.connection.close().await;
+        if let Some(listener) = &self.listener {
+            listener.forget(addr);
+        }
 
-            // Abort all background tasks
-            for task in self.background_tasks.drain(..) {
-                task.abort();
+        if let Some(request) = pending.request {
+            self.dnd.withdraw(&request.peer_id);
+            if self.admission.remove(&request.peer_id).is_some() {
+                self.publish_join_queue();
             }
+        }
+    }
 
-            // Clear connection managers
-            self.peer_connections.clear();
+    /// Where a peer that asked to join on the host socket is waiting, if it is
+    fn pending_join_addr(&self, peer_id: &str) -> Option<std::net::SocketAddr> {
+        self.pending_joins
+            .iter()
+            .find(|(_, pending)| {
+                pending
+                    .request
+                    .as_ref()
+                    .is_some_and(|request| request.peer_id == peer_id)
+            })
+            .map(|(addr, _)| *addr)
+    }
 
-            // Clear peers list
-            self.peers.clear();
+    /// Lets a peer waiting on the host socket into the room, or connects to
+    /// one we know of some other way
+    ///
+    /// The peer is told it's in, everyone else is told about it, and it's
+    /// sent the room's peer list.
+    async fn admit(&mut self, peer_id: &str) -> Result<(), SessionError> {
+        let Some(pending) = self
+            .pending_join_addr(peer_id)
+            .and_then(|addr| self.pending_joins.remove(&addr))
+        else {
+            return self.connect_to_peer(peer_id).await;
+        };
+        pending.handler.abort();
 
-            // Clear current session
-            self.current_session = None;
+        let addr = pending.connection.remote_addr();
+        let name = pending
+            .request
+            .map(|request| request.name)
+            .unwrap_or_default();
+        let peer = Peer {
+            id: peer_id.to_string(),
+            name,
+            endpoint: Endpoint {
+                ip: addr.ip(),
+                port: addr.port(),
+            },
+            public_key: pending.public_key,
+            position: (0.0, 0.0, 0.0),
+            is_host: false,
+            joined_at: unix_now(),
+        };
+        self.peers
+            .lock()
+            .unwrap()
+            .insert_half_open(peer.id.clone(), peer.clone());
+
+        self.listen_to_peer(&pending.connection, &peer).await;
+        if let Err(e) = pending
+            .connection
+            .send_join_reply(JoinReply::Admitted)
+            .await
+        {
+            self.peers.lock().unwrap().remove(&peer.id);
+            pending.connection.close().await;
+            return Err(SessionError::ConnectionFailed(e.to_string()));
+        }
+        self.add_connection(&peer.id, pending.connection);
+        self.peers.lock().unwrap().mark_established(&peer.id);
+
+        let name = self.aliases.name_for(&peer.public_key, &peer.name);
+        self.add_participant(self.participant_for(&name))?;
+        publish_event(
+            &self.audit_log,
+            &self.event_tx,
+            SessionEvent::PeerJoined {
+                peer_id: peer.id.clone(),
+                name,
+            },
+        );
+        self.sync_peers().await?;
+        self.notify_new_peer(&peer).await?;
+        self.share_download_cap().await;
+        Ok(())
+    }
 
-            // Clear host endpoint
-            self.host_public_endpoint = None;
+    /// Asks the host of a room we connected to to let us in, and waits for its answer
+    async fn ask_to_join(&self, connection: &ConnectionManager) -> Result<(), SessionError> {
+        self.join_reply.send_replace(None);
+        let mut replies = self.join_reply.subscribe();
+        connection
+            .send_join_request(&self.self_id, &self.username)
+            .await
+            .map_err(|e| SessionError::ConnectionFailed(e.to_string()))?;
 
-            Ok(())
-        } else {
-            Err(SessionError::NoActiveSession)
+        let decided = |reply: &Option<JoinReply>| {
+            matches!(reply, Some(JoinReply::Admitted | JoinReply::Denied(_)))
+        };
+        let reply = match tokio::time::timeout(ADMISSION_TIMEOUT, replies.wait_for(decided)).await {
+            Ok(Ok(reply)) => reply.clone(),
+            _ => {
+                return Err(SessionError::JoinError(
+                    "The host didn't let us in".to_string(),
+                ))
+            }
+        };
+        match reply {
+            Some(JoinReply::Denied(message)) => Err(SessionError::JoinDenied(message)),
+            _ => Ok(()),
         }
     }
 
-    /// Gets the current session if available
-    pub fn current_session(&self) -> Option<Session> {
-        self.current_session.clone()
+    /// Adds a request to the admission queue, telling the host about it if it's new
+    fn enqueue_join(&mut self, request: JoinRequest) {
+        let event = SessionEvent::JoinRequested {
+            peer_id: request.peer_id.clone(),
+            name: request.name.clone(),
+        };
+        if self.admission.push(request) {
+            publish_event(&self.audit_log, &self.event_tx, event);
+        }
     }
 
-    /// Adds a participant to the current session
-    pub fn add_participant(&mut self, participant: Participant) -> Result<(), SessionError> {
-        if let Some(session) = &mut self.current_session {
-            session.participants.push(participant.clone());
+    /// Sends the host the admission queue as it now stands
+    fn publish_join_queue(&self) {
+        publish_event(
+            &self.audit_log,
+            &self.event_tx,
+            SessionEvent::JoinQueueUpdated {
+                queue: self.admission.snapshot(),
+            },
+        );
+    }
 
-            // Initialize audio stream buffer for this participant
-            self.audio_streams
-                .insert(participant.name.clone(), Arc::new(Mutex::new(Vec::new())));
-            Ok(())
-        } else {
-            Err(SessionError::NoActiveSession)
-        }
+    /// Peers waiting for us to let them in, in the order they asked
+    pub fn join_queue(&self) -> Vec<QueuedJoin> {
+        self.admission.snapshot()
     }
 
-    /// Removes a participant from the current session
-    pub fn remove_participant(&mut self, name: &str) -> Result<(), SessionError> {
-        if let Some(session) = &mut self.current_session {
-            session.participants.retain(|p| p.name != name);
+    /// Lets a peer that asked to join into the session
+    pub async fn approve_join(&mut self, peer_id: &str) -> Result<(), SessionError> {
+        self.admit(peer_id).await?;
+        if self.admission.remove(peer_id).is_some() {
+            self.publish_join_queue();
+        }
+        Ok(())
+    }
 
-            // Remove audio stream for this participant
-            self.audio_streams.remove(name);
-            Ok(())
-        } else {
-            Err(SessionError::NoActiveSession)
+    /// Lets everyone waiting in, first come first served, returning how many got in
+    ///
+    /// Peers we couldn't connect to stay in line, so the host can try them again.
+    pub async fn approve_all_joins(&mut self) -> Result<usize, SessionError> {
+        self.require_host()?;
+        let mut approved = 0;
+        for queued in self.admission.snapshot() {
+            match self.admit(&queued.peer_id).await {
+                Ok(()) => {
+                    self.admission.remove(&queued.peer_id);
+                    approved += 1;
+                }
+                Err(e) => log::warn!("Couldn't let {} in: {}", queued.peer_id, e),
+            }
         }
+        self.publish_join_queue();
+        Ok(approved)
     }
 
-    /// Gets the audio stream for a specific participant
-    pub fn get_audio_stream(&self, name: &str) -> Option<Arc<Mutex<Vec<f32>>>> {
-        self.audio_streams.get(name).cloned()
+    /// Turns away everyone waiting to join, returning how many there were
+    pub fn deny_all_joins(&mut self) -> Result<usize, SessionError> {
+        self.require_host()?;
+        let denied = self.admission.take_all();
+        Ok(self.deny_joins(denied))
     }
 
-    /// Updates the audio stream for a specific participant
-    pub fn update_audio_stream(
-        &mut self,
-        name: &str,
-        audio_data: Vec<f32>,
-    ) -> Result<(), SessionError> {
-        if let Some(stream) = self.audio_streams.get(name) {
-            if let Ok(mut stream) = stream.lock() {
-                *stream = audio_data;
-                Ok(())
-            } else {
-                Err(SessionError::NetworkError(
-                    "Failed to lock audio stream".to_string(),
-                ))
+    /// Records requests taken out of line as denied, returning how many there were
+    ///
+    /// The peers are told after [`DENY_GRACE`], so an undo can still let them in.
+    fn deny_joins(&mut self, denied: Vec<JoinRequest>) -> usize {
+        let deny_at = Instant::now() + DENY_GRACE;
+        for request in &denied {
+            if let Some(addr) = self.pending_join_addr(&request.peer_id) {
+                if let Some(pending) = self.pending_joins.get_mut(&addr) {
+                    pending.deny_at = Some(deny_at);
+                }
             }
-        } else {
-            // If stream doesn't exist yet, create it
-            let stream = Arc::new(Mutex::new(audio_data));
-            self.audio_streams.insert(name.to_string(), stream);
-            Ok(())
+            record_audit_event(
+                &self.audit_log,
+                AuditEvent::JoinDenied {
+                    peer_id: request.peer_id.clone(),
+                },
+            );
+        }
+        self.publish_join_queue();
+        let count = denied.len();
+        if count > 0 {
+            self.record_host_action(HostAction::DenyJoins { requests: denied });
         }
+        count
     }
 
-    /// Sends audio data to all connected peers
-    pub async fn send_audio_data(&self, audio_data: &[f32]) -> Result<(), SessionError> {
-        let mut errors = Vec::new();
+    /// Puts peers we turned away back in line, returning how many weren't already
+    ///
+    /// Peers not yet told they were denied are still waiting to hear back,
+    /// and get in as soon as they're approved. Those that have been told,
+    /// have left or have gone quiet since are left out.
+    async fn requeue_joins(&mut self, requests: Vec<JoinRequest>) -> usize {
+        let mut requeued = Vec::new();
+        for request in requests {
+            if !self.admission.contains(&request.peer_id)
+                && self.still_waiting(&request.peer_id).await
+            {
+                requeued.push(request);
+            }
+        }
+        for request in &requeued {
+            if let Some(addr) = self.pending_join_addr(&request.peer_id) {
+                if let Some(pending) = self.pending_joins.get_mut(&addr) {
+                    pending.deny_at = None;
+                }
+            }
+            self.enqueue_join(request.clone());
+        }
+        self.publish_join_queue();
+        let count = requeued.len();
+        if count > 0 {
+            self.record_host_action(HostAction::RequeueJoins { requests: requeued });
+        }
+        count
+    }
 
-        // Send to all connected peers
-        for (peer_id, connection) in &self.peer_connections {
-            // Skip sending to ourselves
-            if peer_id == &self.self_id {
-                continue;
+    fn record_host_action(&self, action: HostAction) {
+        log::info!("Host action: {}", action);
+        self.host_actions.lock().unwrap().record(action);
+    }
+
+    /// What we've done to the room as its host, oldest first
+    pub fn host_actions(&self) -> Vec<RecordedAction> {
+        self.host_actions.lock().unwrap().actions()
+    }
+
+    fn recorded_host_action(&self, id: u64) -> Result<RecordedAction, SessionError> {
+        self.host_actions
+            .lock()
+            .unwrap()
+            .get(id)
+            .cloned()
+            .ok_or(SessionError::UnknownAction(id))
+    }
+
+    /// Takes a host action from the history again (host only)
+    ///
+    /// Denying again turns away only those of the peers that asked again.
+    pub async fn replay_host_action(&mut self, id: u64) -> Result<(), SessionError> {
+        self.require_host()?;
+        let recorded = self.recorded_host_action(id)?;
+        self.perform_host_action(recorded.action).await
+    }
+
+    /// Reverses a host action from the history, where it can be (host only)
+    ///
+    /// Peers turned away are put back in line and muting everyone is lifted,
+    /// or the other way round; a peer let speak can't be muted again alone.
+    /// The reversal goes in the history too.
+    pub async fn undo_host_action(&mut self, id: u64) -> Result<(), SessionError> {
+        self.require_host()?;
+        let recorded = self.recorded_host_action(id)?;
+        let inverse = recorded
+            .action
+            .inverse()
+            .ok_or_else(|| SessionError::CannotUndo(recorded.action.to_string()))?;
+        self.perform_host_action(inverse).await?;
+        self.host_actions.lock().unwrap().mark_undone(id);
+        Ok(())
+    }
+
+    async fn perform_host_action(&mut self, action: HostAction) -> Result<(), SessionError> {
+        match action {
+            HostAction::DenyJoins { requests } => {
+                let waiting = requests
+                    .iter()
+                    .filter_map(|request| self.admission.remove(&request.peer_id))
+                    .collect();
+                self.deny_joins(waiting);
             }
+            HostAction::RequeueJoins { requests } => {
+                self.requeue_joins(requests).await;
+            }
+            HostAction::MuteAll { enabled } => self.set_meeting_mode(enabled).await?,
+            HostAction::GrantSpeak { peer_id, .. } => self.grant_speak(&peer_id).await?,
+        }
+        Ok(())
+    }
 
-            // Skip if connection is not active
-            if !connection.is_connected().await {
-                continue;
+    /// Removes a peer from a room we host (host only)
+    ///
+    /// To the peer it's as if we left: we say goodbye, stop listening to it
+    /// and drop our connection. Everyone else is told it left. Nothing stops
+    /// it asking to join again.
+    pub async fn kick_peer(&mut self, peer_id: &str) -> Result<(), SessionError> {
+        self.require_host()?;
+        let peer = match self.peers.lock().unwrap().get(peer_id) {
+            Some(peer) if peer.id != self.self_id => peer.clone(),
+            _ => return Err(SessionError::PeerNotFound(peer_id.to_string())),
+        };
+
+        if let Some(connection) = self.peer_connections.remove(peer_id) {
+            if connection.is_connected().await {
+                let _ = connection.send_stream_end().await;
+                let _ = connection.send_peer_left(&self.self_id).await;
             }
+        }
+        if let Some(listener) = self.listeners.remove(peer_id) {
+            listener.abort();
+        }
+        if let Some(listener) = &self.listener {
+            listener.forget(std::net::SocketAddr::new(
+                peer.endpoint.ip,
+                peer.endpoint.port,
+            ));
+        }
+        self.mix_targets.0.lock().unwrap().remove(peer_id);
+        if let Some(mixer) = &self.mixer {
+            mixer.lock().unwrap().remove_peer(peer_id);
+        }
+        self.audio_watchdog.lock().unwrap().forget(peer_id);
+        self.peers.lock().unwrap().remove(peer_id);
+        let name = self.aliases.name_for(&peer.public_key, &peer.name);
+        self.remove_participant(&name)?;
 
-            // Send audio data
-            if let Err(e) = connection.send_audio_data(audio_data).await {
-                errors.push(format!("Failed to send audio to {}: {}", peer_id, e));
+        for connection in self.peer_connections.values() {
+            if connection.is_connected().await {
+                let _ = connection.send_peer_left(peer_id).await;
             }
         }
 
-        if errors.is_empty() {
-            Ok(())
-        } else {
-            Err(SessionError::NetworkError(errors.join("; ")))
+        record_audit_event(
+            &self.audit_log,
+            AuditEvent::PeerKicked {
+                peer_id: peer_id.to_string(),
+                by: self.self_id.clone(),
+            },
+        );
+        publish_event(
+            &self.audit_log,
+            &self.event_tx,
+            SessionEvent::PeerLeft {
+                peer_id: peer_id.to_string(),
+            },
+        );
+        Ok(())
+    }
+
+    fn require_host(&self) -> Result<(), SessionError> {
+        match &self.current_session {
+            Some(session) if session.is_host => Ok(()),
+            Some(_) => Err(SessionError::NotHost),
+            None => Err(SessionError::NoActiveSession),
         }
     }
 
-    /// Checks if the session has a valid connection manager
-    pub async fn connection_state(&self) -> Option<ConnectionState> {
+    /// The public key of another peer in the current session
+    pub fn peer_key(&self, peer_id: &str) -> Option<[u8; 32]> {
+        self.peers
+            .lock()
+            .unwrap()
+            .get(peer_id)
+            .filter(|peer| peer.id != self.self_id)
+            .map(|peer| peer.public_key)
+    }
+
+    /// Everyone else in the current session
+    pub fn other_peers(&self) -> Vec<Peer> {
+        self.peers
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|peer| peer.id != self.self_id)
+            .cloned()
+            .collect()
+    }
+
+    /// The name shown for the current host, if we joined someone else's room
+    pub fn host_name(&self) -> Option<String> {
+        self.peers
+            .lock()
+            .unwrap()
+            .values()
+            .find(|peer| peer.is_host && peer.id != self.self_id)
+            .map(|peer| self.aliases.name_for(&peer.public_key, &peer.name))
+    }
+
+    /// Socket peers reach us on while hosting
+    pub fn host_socket(&self) -> Option<Arc<tokio::net::UdpSocket>> {
+        self.host_socket.clone()
+    }
+
+    /// Our NAT's mapping behaviour, as detected when the room was created
+    pub fn nat_report(&self) -> Option<&NatReport> {
+        self.nat_report.as_ref()
+    }
+
+    /// Forwards the host socket's port on the router, returning the external address
+    async fn map_host_port(&mut self, socket: &tokio::net::UdpSocket) -> Result<Endpoint> {
+        let local_port = socket.local_addr()?.port();
+        let mapper = PortMapper::map(local_port, Duration::from_secs(3600)).await?;
+        let endpoint = mapper.external_endpoint().clone();
+
+        self.port_mapper = Some(mapper);
+        Ok(endpoint)
+    }
+
+    /// Queries STUN servers from the host socket to classify our NAT
+    ///
+    /// Asked from the socket peers will reach, so the address STUN sees is
+    /// the one to advertise.
+    async fn discover_nat(&self, socket: &tokio::net::UdpSocket) -> Result<NatReport> {
+        detect_nat(socket, &self.ice_servers.stun_config()).await
+    }
+
+    /// Addresses on the local network that peers may reach the host socket on
+    async fn lan_candidates(&self) -> Vec<std::net::SocketAddr> {
+        let Some(socket) = &self.host_socket else {
+            return Vec::new();
+        };
+
+        match socket.local_addr() {
+            // Bound to one interface, so that's the only address it's reachable on
+            Ok(local) if !local.ip().is_unspecified() => vec![local],
+            Ok(local) => match local_ipv4().await {
+                Ok(ip) => vec![std::net::SocketAddr::new(ip.into(), local.port())],
+                Err(_) => Vec::new(),
+            },
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Creates a new P2P session
+    pub async fn create_p2p_session(&mut self) -> Result<Session, SessionError> {
+        // First leave any existing session
+        if self.current_session.is_some() {
+            self.leave_session().await?;
+        }
+
+        self.bind_address
+            .resolve()
+            .map_err(|e| SessionError::BindFailed(e.to_string()))?;
+
+        // Peers reach us on the host socket, whichever of its addresses they use
+        let socket = bind_udp(&self.bind_address)
+            .await
+            .map(Arc::new)
+            .map_err(|e| SessionError::BindFailed(e.to_string()))?;
+
+        // Discover public IP and port, and how our NAT maps them, via STUN
+        let report = self
+            .discover_nat(&socket)
+            .await
+            .map_err(|e| SessionError::NatDiscovery(e.to_string()))?;
+        if report.mapping.strategy() == ConnectionStrategy::Relay {
+            log::warn!(
+                "{:?} NAT mapping; peers outside the local network may not reach us directly",
+                report.mapping
+            );
+        }
+        let mut endpoint = report.mapped.clone();
+        self.nat_report = Some(report);
+
+        // A mapped port accepts direct connections, so advertise it instead
+        if self.port_mapping {
+            match self.map_host_port(&socket).await {
+                Ok(mapped) => endpoint = mapped,
+                Err(e) => log::warn!("Port mapping unavailable, using STUN endpoint: {}", e),
+            }
+        }
+
+        // Save host endpoint
+        self.host_public_endpoint = Some(endpoint.clone());
+        self.host_socket = Some(socket.clone());
+
+        // Generate session ID
+        let session_id = uuid::Uuid::new_v4().to_string();
+
+        // Generate keypair
+        let keypair = Keypair::generate();
+        let public_key = keypair.public.to_bytes();
+
+        // Current timestamp for session creation
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        // Generate shareable link, carrying the end time if the room is limited
+        let ends_at = self.time_limit.map(|limit| unix_now() + limit.as_secs());
+        let connection_link = add_link_candidates(
+            &append_frame_duration(
+                &append_codec(
+                    &append_ends_at(
+                        &self.topology.append_to_link(&generate_connection_link(
+                            &endpoint,
+                            &session_id,
+                            &public_key,
+                        )),
+                        ends_at,
+                    ),
+                    self.room_codec,
+                ),
+                self.frame_duration,
+            ),
+            &self.lan_candidates().await,
+        );
+        self.repacketizer
+            .lock()
+            .unwrap()
+            .set_duration(self.frame_duration);
+
+        // Add ourselves as a peer
+        let self_peer = Peer {
+            id: self.self_id.clone(),
+            name: "Me".to_string(),
+            endpoint: endpoint.clone(),
+            public_key,
+            position: (0.0, 0.0, 0.0),
+            is_host: true,
+            joined_at: timestamp,
+        };
+
+        self.peers
+            .lock()
+            .unwrap()
+            .insert(self.self_id.clone(), self_peer);
+
+        // Create session
+        let current_user = Participant::new("Me")
+            .with_id(&self.self_id)
+            .with_position(0.0, 0.0, 0.0);
+        let session = Session {
+            id: session_id.clone(),
+            connection_link: connection_link.clone(),
+            participants: vec![current_user],
+            is_host: true,
+            original_host_id: self.self_id.clone(),
+            created_at: timestamp,
+            topology: self.topology,
+        };
+
+        if self.topology == MixingTopology::HostMixed {
+            self.start_host_mix(self.frame_duration);
+        }
+
+        // Peers agree keys with the link's public key; take them in as they arrive
+        self.listener = Some(HostListener::start(socket, keypair, session_id.clone()));
+
+        record_audit_event(
+            &self.audit_log,
+            AuditEvent::SessionCreated {
+                session_id: session_id.clone(),
+            },
+        );
+
+        self.start_timer(ends_at);
+        self.current_session = Some(session.clone());
+        Ok(session)
+    }
+
+    /// Joins an existing session using a connection link
+    pub async fn join_p2p_session(&mut self, link: &str) -> Result<(), SessionError> {
+        // First leave any existing session
+        if self.current_session.is_some() {
+            self.leave_session().await?;
+        }
+
+        // Parse connection link
+        let (remote_ip, remote_port, session_id, remote_key) =
+            parse_connection_link(link).map_err(|e| SessionError::InvalidLink(e.to_string()))?;
+
+        let candidates =
+            parse_link_candidates(link).map_err(|e| SessionError::InvalidLink(e.to_string()))?;
+
+        self.bind_address
+            .resolve()
+            .map_err(|e| SessionError::BindFailed(e.to_string()))?;
+
+        // Host ID, and what we call the host
+        let host_id = format!("host-{}", session_id);
+        let host_name = self.aliases.name_for(&remote_key, "Host");
+
+        // The room's codec, if the host chose one
+        self.room_codec = codec_from_link(link);
+        self.repacketizer
+            .lock()
+            .unwrap()
+            .set_duration(frame_duration_from_link(link).unwrap_or_default());
+
+        // Create connection manager for the host
+        let mut connection_manager = self.new_connection(
+            std::net::SocketAddr::new(remote_ip, remote_port),
+            session_id.clone(),
+            remote_key,
+            &host_id,
+        );
+
+        // Race the host's addresses and keep the first that answers,
+        // racing again after a backoff if none do
+        let mut retry = Retry::new(self.retry.policy(RetryOperation::Join));
+        loop {
+            self.publish_attempt(&host_id, &host_name, &retry);
+            match connection_manager.connect_racing(&candidates).await {
+                Ok(()) => break,
+                Err(e) => retry
+                    .after(e)
+                    .await
+                    .map_err(|e| SessionError::ConnectionFailed(e.to_string()))?,
+            }
+        }
+
+        // Host endpoint
+        let host_addr = connection_manager.remote_addr();
+        let host_endpoint = Endpoint {
+            ip: host_addr.ip(),
+            port: host_addr.port(),
+        };
+
+        // Current timestamp for joining
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        // Add the host to peers list
+        let host_peer = Peer {
+            id: host_id.clone(),
+            name: "Host".to_string(),
+            endpoint: host_endpoint,
+            public_key: remote_key,
+            position: (0.0, 0.0, -1.0),
+            is_host: true,
+            joined_at: timestamp - 1, // Host joined before us
+        };
+        self.peers
+            .lock()
+            .unwrap()
+            .insert(host_id.clone(), host_peer);
+        publish_event(
+            &self.audit_log,
+            &self.event_tx,
+            SessionEvent::PeerJoined {
+                peer_id: host_id.clone(),
+                name: host_name.clone(),
+            },
+        );
+
+        // Add ourselves to the peers list
+        let self_peer = Peer {
+            id: self.self_id.clone(),
+            name: "Me".to_string(),
+            endpoint: Endpoint {
+                ip: "0.0.0.0".parse().unwrap(),
+                port: 0,
+            }, // Will be updated after STUN
+            public_key: [0; 32], // Will be set properly later
+            position: (0.0, 0.0, 0.0),
+            is_host: false,
+            joined_at: timestamp,
+        };
+        self.peers
+            .lock()
+            .unwrap()
+            .insert(self.self_id.clone(), self_peer);
+
+        self.listen_to_host(&connection_manager, &host_id, &host_name)
+            .await;
+        if let Err(e) = connection_manager.await_answer(HANDSHAKE_TIMEOUT).await {
+            let error = self.handshake_failed(&host_id, e);
+            self.end_session().await;
+            return Err(error);
+        }
+
+        // Check every address the host gave and settle on the fastest
+        if candidates.len() > 1 {
+            let checks = connection_manager.clone();
+            self.shutdown.track(topology::spawn(
+                Plane::Control,
+                "connectivity-checks",
+                async move {
+                    match checks.check_candidates(&candidates).await {
+                        Ok(best) => log::debug!("Control channel settled on {}", best),
+                        Err(e) => log::warn!("Connectivity checks failed: {}", e),
+                    }
+                },
+            ));
+        }
+
+        // Ask the host to let us in, and wait for it to decide
+        if let Err(error) = self.ask_to_join(&connection_manager).await {
+            let _ = connection_manager.send_peer_left(&self.self_id).await;
+            self.end_session().await;
+            connection_manager.close().await;
+            return Err(error);
+        }
+
+        self.add_connection(&host_id, connection_manager);
+
+        // Create local session representation with host and current user
+        let current_user = Participant::new("Me")
+            .with_id(&self.self_id)
+            .with_position(0.0, 0.0, 0.0);
+        let host = Participant::new(&host_name)
+            .with_id(&host_id)
+            .with_position(0.0, 0.0, -1.0);
+
+        let session = Session {
+            id: session_id,
+            connection_link: link.to_string(),
+            participants: vec![current_user, host],
+            is_host: false,
+            original_host_id: host_id,
+            created_at: timestamp - 1, // Host created before we joined
+            topology: MixingTopology::from_link(link),
+        };
+
+        // Initialize audio stream for host
+        self.audio_streams
+            .insert(host_name, Arc::new(Mutex::new(AudioFrame::default())));
+
+        self.share_download_cap().await;
+
+        // The host's time limit applies to everyone in the room
+        self.start_timer(ends_at_from_link(link));
+        self.current_session = Some(session);
+        Ok(())
+    }
+
+    /// Leaves the current session
+    pub async fn leave_session(&mut self) -> Result<(), SessionError> {
+        if self.current_session.is_some() {
+            // Send any audio still waiting to be mixed
+            self.flush_audio().await;
+
+            // Notify all peers that our audio has ended and we're leaving
+            for connection in self.peer_connections.values() {
+                // Skip sending if connection is not active
+                if connection.is_connected().await {
+                    let _ = connection.send_stream_end().await;
+                    let _ = connection.send_peer_left(&self.self_id).await;
+                }
+            }
+
+            self.end_session().await;
+
+            Ok(())
+        } else {
+            Err(SessionError::NoActiveSession)
+        }
+    }
+
+    /// Stops the session's tasks and forgets everything about it
+    async fn end_session(&mut self) {
+        // Give queued messages time to go out before stopping the tasks sending them
+        let aborted = self.shutdown.drain(DRAIN_GRACE).await;
+        log::debug!("Session drained, {} tasks aborted", aborted);
+
+        // Clear audio streams for all participants
+        self.audio_streams.clear();
+        self.audio_watchdog.lock().unwrap().clear();
+        self.repacketizer.lock().unwrap().reset();
+        *self.echo_bot.lock().unwrap() = None;
+        self.admission.take_all();
+        self.host_actions.lock().unwrap().clear();
+
+        // Stop taking in peers, and drop those we hadn't let in
+        self.listener = None;
+        for (_, pending) in self.pending_joins.drain() {
+            pending.handler.abort();
+            pending.connection.close().await;
+        }
+        while self.lobby_rx.try_recv().is_ok() {}
+
+        // Clear connection managers
+        self.peer_connections.clear();
+        self.listeners.clear();
+
+        // Clear peers list
+        self.peers.lock().unwrap().clear();
+
+        // Clear current session
+        self.current_session = None;
+        self.timer = None;
+
+        // Clear host endpoint
+        self.host_public_endpoint = None;
+
+        self.mixer = None;
+        self.mix_targets.0.lock().unwrap().clear();
+        self.meeting.lock().unwrap().set_enabled(false);
+        *self.recording.lock().unwrap() = RecordingRoster::new();
+        self.synced_sent.clear();
+        self.playouts.lock().unwrap().clear();
+
+        // Remove the router mapping
+        if let Some(mapper) = self.port_mapper.take() {
+            if let Err(e) = mapper.release().await {
+                log::warn!("Failed to remove port mapping: {}", e);
+            }
+        }
+        self.host_socket = None;
+    }
+
+    /// Hands the call over to another machine, leaving it here without telling anyone
+    ///
+    /// Returns the sealed call and the key to open it, meant to reach the
+    /// other machine by separate routes, where
+    /// [`SessionManager::import_handoff`] picks it up. Experimental: meeting
+    /// mode, the host's mix and its port mapping stay behind, and new guests
+    /// still find the room at the old address.
+    pub async fn export_handoff(&mut self) -> Result<(String, HandoffKey), SessionError> {
+        let session = self
+            .current_session
+            .clone()
+            .ok_or(SessionError::NoActiveSession)?;
+
+        // Send any audio still waiting to be mixed
+        self.flush_audio().await;
+
+        let mut connections = Vec::new();
+        for (peer_id, connection) in &self.peer_connections {
+            match connection.hand_off().await {
+                Ok(handoff) => connections.push((peer_id.clone(), handoff)),
+                Err(e) => log::warn!("Couldn't hand off the connection to {}: {}", peer_id, e),
+            }
+        }
+
+        let handoff = CallHandoff {
+            self_id: self.self_id.clone(),
+            session_id: session.id.clone(),
+            connection_link: session.connection_link.clone(),
+            is_host: session.is_host,
+            original_host_id: session.original_host_id.clone(),
+            created_at: session.created_at,
+            participants: session
+                .participants
+                .iter()
+                .map(|participant| (participant.name.clone(), participant.position))
+                .collect(),
+            peers: self.peers.lock().unwrap().values().cloned().collect(),
+            connections,
+            expires_at: unix_now() + HANDOFF_VALIDITY_SECS,
+        };
+        let sealed = handoff
+            .seal()
+            .map_err(|e| SessionError::Handoff(e.to_string()))?;
+
+        // The connections are gone from here, so there's no one to say goodbye to
+        self.end_session().await;
+        Ok(sealed)
+    }
+
+    /// Carries on a call handed over with [`SessionManager::export_handoff`]
+    ///
+    /// Every connection moves here with its keys, and peers follow it as
+    /// they would a changed address: nobody sees us leave or join, and the
+    /// host doesn't have to let us in again.
+    pub async fn import_handoff(
+        &mut self,
+        sealed: &str,
+        key: &HandoffKey,
+    ) -> Result<Session, SessionError> {
+        let handoff = CallHandoff::open(sealed, key, unix_now())
+            .map_err(|e| SessionError::Handoff(e.to_string()))?;
+
+        // First leave any existing session
+        if self.current_session.is_some() {
+            self.leave_session().await?;
+        }
+
+        self.bind_address
+            .resolve()
+            .map_err(|e| SessionError::BindFailed(e.to_string()))?;
+
+        // The other peers know us by the ID we had there
+        self.self_id = handoff.self_id.clone();
+        self.room_codec = codec_from_link(&handoff.connection_link);
+        self.repacketizer
+            .lock()
+            .unwrap()
+            .set_duration(frame_duration_from_link(&handoff.connection_link).unwrap_or_default());
+        for peer in handoff.peers {
+            self.peers.lock().unwrap().insert(peer.id.clone(), peer);
+        }
+
+        let expected = handoff.connections.len();
+        for (peer_id, connection) in handoff.connections {
+            let Some(peer) = self.peers.lock().unwrap().get(&peer_id).cloned() else {
+                continue;
+            };
+            let connection_manager = self.new_connection(
+                connection.channel.remote,
+                handoff.session_id.clone(),
+                peer.public_key,
+                &peer.id,
+            );
+            if let Err(e) = connection_manager.resume(connection).await {
+                log::warn!("Couldn't move the connection to {}: {}", peer_id, e);
+                continue;
+            }
+
+            let name = self.aliases.name_for(&peer.public_key, &peer.name);
+            if !handoff.is_host && peer.id == handoff.original_host_id {
+                self.listen_to_host(&connection_manager, &peer.id, &name)
+                    .await;
+            } else {
+                self.listen_to_peer(&connection_manager, &peer).await;
+            }
+            self.add_connection(&peer.id, connection_manager);
+            self.peers.lock().unwrap().mark_established(&peer.id);
+            self.audio_streams
+                .insert(name, Arc::new(Mutex::new(AudioFrame::default())));
+        }
+
+        if expected > 0 && self.peer_connections.is_empty() {
+            self.end_session().await;
+            return Err(SessionError::ConnectionFailed(
+                "No peer could be reached from here".to_string(),
+            ));
+        }
+
+        let topology = MixingTopology::from_link(&handoff.connection_link);
+        if handoff.is_host && topology == MixingTopology::HostMixed {
+            self.start_host_mix(
+                frame_duration_from_link(&handoff.connection_link).unwrap_or_default(),
+            );
+        }
+
+        let session = Session {
+            id: handoff.session_id,
+            participants: handoff
+                .participants
+                .iter()
+                .map(|(name, (x, y, z))| self.participant_for(name).with_position(*x, *y, *z))
+                .collect(),
+            is_host: handoff.is_host,
+            original_host_id: handoff.original_host_id,
+            created_at: handoff.created_at,
+            topology,
+            connection_link: handoff.connection_link,
+        };
+
+        self.share_download_cap().await;
+        self.start_timer(ends_at_from_link(&session.connection_link));
+        self.current_session = Some(session.clone());
+        Ok(session)
+    }
+
+    /// Gets the current session if available
+    pub fn current_session(&self) -> Option<Session> {
+        self.current_session.clone()
+    }
+
+    /// Adds a participant to the current session
+    pub fn add_participant(&mut self, participant: Participant) -> Result<(), SessionError> {
+        if let Some(session) = &mut self.current_session {
+            session.participants.push(participant.clone());
+
+            // Initialize audio stream buffer for this participant
+            self.audio_streams.insert(
+                participant.name.clone(),
+                Arc::new(Mutex::new(AudioFrame::default())),
+            );
+            Ok(())
+        } else {
+            Err(SessionError::NoActiveSession)
+        }
+    }
+
+    /// Removes a participant from the current session
+    pub fn remove_participant(&mut self, name: &str) -> Result<(), SessionError> {
+        if let Some(session) = &mut self.current_session {
+            session.participants.retain(|p| p.name != name);
+
+            // Remove audio stream for this participant
+            self.audio_streams.remove(name);
+            Ok(())
+        } else {
+            Err(SessionError::NoActiveSession)
+        }
+    }
+
+    /// Adds an echo bot to the room, playing back what we send a second later (host only)
+    ///
+    /// Only we hear it; the other peers never receive its audio.
+    pub fn spawn_echo_bot(&mut self) -> Result<(), SessionError> {
+        self.require_host()?;
+        if self.has_echo_bot() {
+            return Ok(());
+        }
+        self.add_participant(
+            Participant::new(ECHO_BOT_NAME)
+                .with_id(ECHO_BOT_NAME)
+                .with_position(0.0, 0.0, -1.0),
+        )?;
+        *self.echo_bot.lock().unwrap() = Some(EchoBot::new(self.codec()));
+        Ok(())
+    }
+
+    /// Takes the echo bot out of the room again (host only)
+    pub fn dismiss_echo_bot(&mut self) -> Result<(), SessionError> {
+        self.require_host()?;
+        if self.echo_bot.lock().unwrap().take().is_some() {
+            self.remove_participant(ECHO_BOT_NAME)?;
+        }
+        Ok(())
+    }
+
+    pub fn has_echo_bot(&self) -> bool {
+        self.echo_bot.lock().unwrap().is_some()
+    }
+
+    /// Hands a frame we're sending to the echo bot, and plays back what's due
+    fn echo(&self, frame: &AudioFrame) {
+        let mut bot = self.echo_bot.lock().unwrap();
+        let Some(bot) = bot.as_mut() else {
+            return;
+        };
+
+        let now = Instant::now();
+        bot.hear(frame, now);
+        if let Some(reply) = bot.replies(now).pop() {
+            if let Some(stream) = self.audio_streams.get(ECHO_BOT_NAME) {
+                *stream.lock().unwrap() = reply;
+            }
+        }
+    }
+
+    /// Gets the audio stream for a specific participant
+    pub fn get_audio_stream(&self, name: &str) -> Option<Arc<Mutex<AudioFrame>>> {
+        self.audio_streams.get(name).cloned()
+    }
+
+    /// Names of the streams that have received audio so far
+    pub fn receiving_streams(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .audio_streams
+            .iter()
+            .filter(|(_, stream)| stream.lock().map(|f| !f.is_empty()).unwrap_or(false))
+            .map(|(name, _)| name.clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Updates the audio stream for a specific participant
+    pub fn update_audio_stream(
+        &mut self,
+        name: &str,
+        frame: AudioFrame,
+    ) -> Result<(), SessionError> {
+        if let Some(stream) = self.audio_streams.get(name) {
+            if let Ok(mut stream) = stream.lock() {
+                *stream = frame;
+                Ok(())
+            } else {
+                Err(SessionError::NetworkError(
+                    "Failed to lock audio stream".to_string(),
+                ))
+            }
+        } else {
+            // If stream doesn't exist yet, create it
+            let stream = Arc::new(Mutex::new(frame));
+            self.audio_streams.insert(name.to_string(), stream);
+            Ok(())
+        }
+    }
+
+    /// Sends captured audio to the peers that should hear it
+    ///
+    /// Audio is cut into frames of the room's duration first, so it may go
+    /// out in several frames or wait for the next call to fill one.
+    pub async fn send_audio_data(&self, frame: &AudioFrame) -> Result<(), SessionError> {
+        let frames = self.repacketizer.lock().unwrap().push(frame);
+        for frame in &frames {
+            self.send_frame(frame).await?;
+        }
+        Ok(())
+    }
+
+    /// Sends one frame to the peers that should hear it
+    ///
+    /// In a host-mixed room, peers send only to the host and the host sends
+    /// each peer a mix of everyone else.
+    async fn send_frame(&self, frame: &AudioFrame) -> Result<(), SessionError> {
+        // Muted by the host; peers would drop the audio anyway
+        if !self.may_speak() {
+            return Ok(());
+        }
+        self.echo(frame);
+
+        if let Some(mixer) = &self.mixer {
+            self.add_to_host_mix(mixer, frame).await;
+            return Ok(());
+        }
+
+        let host_only = self
+            .current_session
+            .as_ref()
+            .filter(|session| session.topology == MixingTopology::HostMixed)
+            .map(|session| session.original_host_id.clone());
+
+        let mut errors = Vec::new();
+
+        // Send to all connected peers
+        for (peer_id, connection) in &self.peer_connections {
+            // Skip sending to ourselves
+            if peer_id == &self.self_id {
+                continue;
+            }
+
+            // Only the host hears us directly in a host-mixed room
+            if host_only.as_ref().is_some_and(|host_id| host_id != peer_id) {
+                continue;
+            }
+
+            // Skip if connection is not active
+            if !connection.is_connected().await {
+                continue;
+            }
+
+            // Send audio data
+            match connection.send_audio(frame).await {
+                Ok(()) => self.watch_audio(peer_id),
+                Err(e) => errors.push(format!("Failed to send audio to {}: {}", peer_id, e)),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(SessionError::NetworkError(errors.join("; ")))
+        }
+    }
+
+    /// Sends each peer the audio the host mix still holds for them
+    async fn flush_audio(&self) {
+        let Some(mixer) = &self.mixer else {
+            return;
+        };
+
+        let mut recipients = Vec::new();
+        for (peer_id, connection) in &self.peer_connections {
+            if connection.is_connected().await {
+                recipients.push(peer_id.clone());
+            }
+        }
+
+        let mixes = mixer.lock().unwrap().mix(&recipients);
+        for (peer_id, mix) in mixes {
+            if let Some(connection) = self.peer_connections.get(&peer_id) {
+                if let Err(e) = connection.send_audio(&mix).await {
+                    log::debug!("Failed to flush audio to {}: {}", peer_id, e);
+                }
+            }
+        }
+    }
+
+    /// Adds our frame to the host mix, which the mix loop sends on
+    async fn add_to_host_mix(&self, mixer: &Mutex<HostMixer>, frame: &AudioFrame) {
+        mixer
+            .lock()
+            .unwrap()
+            .push(frame.clone().with_peer_id(&self.self_id));
+
+        // Everyone connected should be sending to us, whether or not they have a mix to hear
+        for (peer_id, connection) in &self.peer_connections {
+            if connection.is_connected().await {
+                self.watch_audio(peer_id);
+            }
+        }
+    }
+
+    /// Starts mixing for the room we host, one round every `frame_duration`
+    fn start_host_mix(&mut self, frame_duration: FrameDuration) {
+        let mixer = Arc::new(Mutex::new(HostMixer::new(&self.self_id)));
+        let mix = run_host_mix(
+            mixer.clone(),
+            self.mix_targets.clone(),
+            Duration::from_millis(frame_duration.millis() as u64),
+        );
+        // Stops as we leave, once flush_audio has sent what's left
+        let mut draining = self.shutdown.subscribe();
+        self.shutdown
+            .track(topology::spawn(Plane::Control, "host-mix", async move {
+                tokio::select! {
+                    _ = mix => {}
+                    _ = draining.wait_for(|draining| *draining) => {}
+                }
+            }));
+        self.mixer = Some(mixer);
+    }
+
+    /// Keeps a connection to a peer, for the host mix too
+    fn add_connection(&mut self, peer_id: &str, connection: ConnectionManager) {
+        self.mix_targets
+            .0
+            .lock()
+            .unwrap()
+            .insert(peer_id.to_string(), connection.clone());
+        self.peer_connections
+            .insert(peer_id.to_string(), connection);
+    }
+
+    /// Notes audio sent to a peer, raising an issue if none has come back for a while
+    fn watch_audio(&self, peer_id: &str) {
+        // Peers the host muted send nothing, and aren't expected to
+        let is_host = self
+            .current_session
+            .as_ref()
+            .is_some_and(|session| session.original_host_id == peer_id);
+        if !is_host && !self.meeting.lock().unwrap().may_speak(peer_id) {
+            self.audio_watchdog.lock().unwrap().forget(peer_id);
+            return;
+        }
+
+        if self
+            .audio_watchdog
+            .lock()
+            .unwrap()
+            .sent(peer_id, Instant::now())
+        {
+            log::warn!("Sending audio to {} but receiving none back", peer_id);
+            publish_event(
+                &self.audit_log,
+                &self.event_tx,
+                SessionEvent::ConnectionIssue {
+                    peer_id: peer_id.to_string(),
+                    name: display_name(&self.peers.lock().unwrap(), &self.aliases, peer_id),
+                    issue: ConnectionIssue::OneWayAudio,
+                },
+            );
+        }
+    }
+
+    /// Checks if the session has a valid connection manager
+    pub async fn connection_state(&self) -> Option<ConnectionState> {
+        for connection in self.peer_connections.values() {
+            let state = connection.connection_state().await;
+            if state == ConnectionState::Connected {
+                return Some(state);
+            }
+        }
+        None
+    }
+
+    /// Peers dropped from the session's peer table without leaving,
+    /// counting those whose handshake has timed out by now
+    pub fn peer_evictions(&self) -> EvictionStats {
+        let mut peers = self.peers.lock().unwrap();
+        peers.expire_stale();
+        peers.eviction_stats()
+    }
+
+    /// Connects to a peer in the session
+    pub async fn connect_to_peer(&mut self, peer_id: &str) -> Result<(), SessionError> {
+        // Get peer info
+        let peer = match self.peers.lock().unwrap().get(peer_id) {
+            Some(peer) => peer.clone(),
+            None => return Err(SessionError::PeerNotFound(peer_id.to_string())),
+        };
+
+        // Don't connect to ourselves
+        if peer.id == self.self_id {
+            return Ok(());
+        }
+
+        // Skip if already connected
+        if self.peer_connections.contains_key(&peer.id) {
+            return Ok(());
+        }
+
+        let session_id = match &self.current_session {
+            Some(session) => session.id.clone(),
+            None => return Err(SessionError::NoActiveSession),
+        };
+
+        // Create connection manager
+        let connection_manager = self.new_connection(
+            std::net::SocketAddr::new(peer.endpoint.ip, peer.endpoint.port),
+            session_id,
+            peer.public_key,
+            &peer.id,
+        );
+
+        // Connect to peer, retrying after a backoff
+        let mut retry = Retry::new(self.retry.policy(RetryOperation::Peer));
+        loop {
+            self.publish_attempt(&peer.id, &peer.name, &retry);
+            match connection_manager.connect().await {
+                Ok(()) => break,
+                Err(e) => retry
+                    .after(e)
+                    .await
+                    .map_err(|e| SessionError::ConnectionFailed(e.to_string()))?,
+            }
+        }
+
+        self.listen_to_peer(&connection_manager, &peer).await;
+        if let Err(e) = connection_manager.await_answer(HANDSHAKE_TIMEOUT).await {
+            // Forget the peer; the host's next peer list brings it back if it's there
+            self.peers.lock().unwrap().remove(&peer.id);
+            return Err(self.handshake_failed(&peer.id, e));
+        }
+        self.add_connection(&peer.id, connection_manager);
+        self.peers.lock().unwrap().mark_established(&peer.id);
+        self.share_download_cap().await;
+
+        // Initialize audio stream for this peer
+        self.audio_streams.insert(
+            self.aliases.name_for(&peer.public_key, &peer.name),
+            Arc::new(Mutex::new(AudioFrame::default())),
+        );
+
+        Ok(())
+    }
+
+    /// Handles what the host of a room we joined sends us
+    async fn listen_to_host(
+        &mut self,
+        connection_manager: &ConnectionManager,
+        host_id: &str,
+        host_name: &str,
+    ) {
+        let audio_streams = self.audio_streams.clone();
+        let peers = self.peers.clone();
+        let self_id_clone = self.self_id.clone();
+        let audit_log = self.audit_log.clone();
+        let event_tx = self.event_tx.clone();
+        let host_id_clone = host_id.to_string();
+        let host_name_clone = host_name.to_string();
+        let aliases = self.aliases.clone();
+        let mut comfort_noise = ComfortNoiseGenerator::new();
+        let mut decoder = AudioDecoder::new();
+        let meeting = self.meeting.clone();
+        let recording = self.recording.clone();
+        let recording_ack = self.recording_ack.clone();
+        let playouts = self.playouts.clone();
+        let watchdog = self.audio_watchdog.clone();
+        let join_reply = self.join_reply.clone();
+
+        let handler_task = connection_manager
+            .start_listening(move |message| {
+                match message {
+                    audio @ Message::Audio { .. } => {
+                        watchdog
+                            .lock()
+                            .unwrap()
+                            .heard(&host_id_clone, Instant::now());
+
+                        let Some(frame) = audio.decode_audio(&mut decoder, &host_id_clone) else {
+                            return Ok(());
+                        };
+                        publish_event(
+                            &audit_log,
+                            &event_tx,
+                            SessionEvent::AudioLevel {
+                                peer_id: host_id_clone.clone(),
+                                level: frame.level(),
+                            },
+                        );
+
+                        // Store audio stream for the host
+                        if let Some(stream) = audio_streams.get(&host_name_clone) {
+                            let mut stream = stream.lock().unwrap();
+                            *stream = frame;
+                        }
+                    }
+                    Message::ComfortNoise {
+                        sample_rate,
+                        channels,
+                        frame_len,
+                        noise,
+                    } => {
+                        watchdog
+                            .lock()
+                            .unwrap()
+                            .heard(&host_id_clone, Instant::now());
+
+                        let Some(frame_len) = noise_frame_len(sample_rate, channels, frame_len)
+                        else {
+                            log::debug!(
+                                "Ignoring an implausible comfort noise frame from the host"
+                            );
+                            return Ok(());
+                        };
+                        // The host went quiet; keep a little of its background noise going
+                        publish_event(
+                            &audit_log,
+                            &event_tx,
+                            SessionEvent::AudioLevel {
+                                peer_id: host_id_clone.clone(),
+                                level: 0.0,
+                            },
+                        );
+                        if let Some(stream) = audio_streams.get(&host_name_clone) {
+                            let samples = comfort_noise.generate(&noise, channels, frame_len);
+                            *stream.lock().unwrap() =
+                                AudioFrame::new(samples, sample_rate, channels)
+                                    .with_peer_id(&host_id_clone);
+                        }
+                    }
+                    Message::StreamEnd => {
+                        // The host's audio ended cleanly
+                        watchdog.lock().unwrap().forget(&host_id_clone);
+                        publish_event(
+                            &audit_log,
+                            &event_tx,
+                            SessionEvent::StreamEnded {
+                                peer_id: host_id_clone.clone(),
+                                name: host_name_clone.clone(),
+                            },
+                        );
+                    }
+                    Message::PeerList { peers: peer_list } => {
+                        // Received peer list from host
+                        let mut peers_lock = peers.lock().unwrap();
+
+                        // Update our peer list with the received information
+                        for peer in peer_list {
+                            // Don't update ourselves
+                            if peer.id != self_id_clone {
+                                peers_lock.insert_half_open(peer.id.clone(), peer);
+                            }
+                        }
+
+                        // TODO: Connect to other peers in the list
+                    }
+                    Message::NewPeer { peer } => {
+                        // A new peer joined the session
+                        let mut peers_lock = peers.lock().unwrap();
+
+                        // Add to our peer list
+                        if peer.id != self_id_clone {
+                            publish_event(
+                                &audit_log,
+                                &event_tx,
+                                SessionEvent::PeerJoined {
+                                    peer_id: peer.id.clone(),
+                                    name: aliases.name_for(&peer.public_key, &peer.name),
+                                },
+                            );
+                            peers_lock.insert_half_open(peer.id.clone(), peer);
+                        }
+
+                        // TODO: Connect to this new peer
+                    }
+                    Message::PeerLeft { peer_id } => {
+                        // A peer left the session
+                        let mut peers_lock = peers.lock().unwrap();
+                        if recording.lock().unwrap().set_recording(&peer_id, false) {
+                            publish_event(
+                                &audit_log,
+                                &event_tx,
+                                SessionEvent::RecordingChanged {
+                                    peer_id: peer_id.clone(),
+                                    name: display_name(&peers_lock, &aliases, &peer_id),
+                                    recording: false,
+                                },
+                            );
+                        }
+
+                        // Remove from our peer list
+                        peers_lock.remove(&peer_id);
+                        publish_event(
+                            &audit_log,
+                            &event_tx,
+                            SessionEvent::PeerLeft {
+                                peer_id: peer_id.clone(),
+                            },
+                        );
+
+                        // If the host left, elect a new host
+                        let mut new_host = false;
+                        for peer in peers_lock.values() {
+                            if peer.is_host && peer.id == peer_id {
+                                new_host = true;
+                                break;
+                            }
+                        }
+
+                        if new_host {
+                            // Simple host election: oldest peer becomes host
+                            let mut oldest_time = u64::MAX;
+                            let mut oldest_id = String::new();
+
+                            for (id, peer) in peers_lock.iter() {
+                                if peer.joined_at < oldest_time {
+                                    oldest_time = peer.joined_at;
+                                    oldest_id = id.clone();
+                                }
+                            }
+
+                            // If we're the oldest, we become host
+                            if oldest_id == self_id_clone {
+                                if let Some(peer) = peers_lock.get_mut(&self_id_clone) {
+                                    peer.is_host = true;
+                                    publish_event(
+                                        &audit_log,
+                                        &event_tx,
+                                        SessionEvent::HostChanged {
+                                            peer_id: self_id_clone.clone(),
+                                        },
+                                    );
+
+                                    // TODO: Notify other peers that we're the new host
+                                }
+                            }
+                        }
+                    }
+                    Message::Presence { peer_id, status } => {
+                        // A peer became active, idle or away
+                        let name = display_name(&peers.lock().unwrap(), &aliases, &peer_id);
+
+                        publish_event(
+                            &audit_log,
+                            &event_tx,
+                            SessionEvent::PresenceChanged {
+                                peer_id,
+                                name,
+                                status,
+                            },
+                        );
+                    }
+                    Message::Layout { layout, positions } => {
+                        // The host rearranged the room
+                        publish_event(
+                            &audit_log,
+                            &event_tx,
+                            SessionEvent::LayoutChanged { layout, positions },
+                        );
+                    }
+                    Message::MeetingMode { enabled } => {
+                        // The host muted everyone else, or let everyone speak again
+                        meeting.lock().unwrap().set_enabled(enabled);
+                        publish_event(
+                            &audit_log,
+                            &event_tx,
+                            SessionEvent::MeetingModeChanged { enabled },
+                        );
+                    }
+                    Message::SpeakGranted { peer_id } => {
+                        meeting.lock().unwrap().grant(&peer_id);
+                        let name = display_name(&peers.lock().unwrap(), &aliases, &peer_id);
+                        publish_event(
+                            &audit_log,
+                            &event_tx,
+                            SessionEvent::SpeakGranted { peer_id, name },
+                        );
+                    }
+                    Message::HandRaised { peer_id, raised } => {
+                        let changed = meeting.lock().unwrap().set_hand_raised(&peer_id, raised);
+                        if changed {
+                            let name = display_name(&peers.lock().unwrap(), &aliases, &peer_id);
+                            publish_event(
+                                &audit_log,
+                                &event_tx,
+                                SessionEvent::HandRaised {
+                                    peer_id,
+                                    name,
+                                    raised,
+                                },
+                            );
+                        }
+                    }
+                    Message::RecordingPolicy { consent } => {
+                        recording.lock().unwrap().set_consent_required(consent);
+                    }
+                    Message::JoinReply { reply } => {
+                        if reply == JoinReply::Waiting {
+                            log::info!("Waiting for the host to let us in");
+                        }
+                        join_reply.send_replace(Some(reply));
+                    }
+                    Message::RecordingAcknowledged { peer_id } if peer_id == self_id_clone => {
+                        let settled = recording.lock().unwrap().settle_ack();
+                        if settled {
+                            recording_ack.notify_one();
+                        }
+                    }
+                    Message::SyncedPlayout { peer_id, delay_ms } if peer_id == host_id_clone => {
+                        set_playout(&playouts, peer_id, delay_ms);
+                    }
+                    // The host tells us about itself, and about peers already recording as we join
+                    Message::RecordingStateChanged {
+                        peer_id,
+                        recording: active,
+                    } if peer_id != self_id_clone => {
+                        let changed = recording.lock().unwrap().set_recording(&peer_id, active);
+                        if changed {
+                            let name = display_name(&peers.lock().unwrap(), &aliases, &peer_id);
+                            publish_event(
+                                &audit_log,
+                                &event_tx,
+                                SessionEvent::RecordingChanged {
+                                    peer_id,
+                                    name,
+                                    recording: active,
+                                },
+                            );
+                        }
+                    }
+                    // Handle other message types as needed
+                    _ => {}
+                }
+
+                Ok(())
+            })
+            .await;
+
+        self.shutdown.track(handler_task);
+    }
+
+    /// Handles what another peer in the room sends us
+    async fn listen_to_peer(&mut self, connection_manager: &ConnectionManager, peer: &Peer) {
+        let audio_streams = self.audio_streams.clone();
+        let peers = self.peers.clone();
+        let self_id_clone = self.self_id.clone();
+        let peer_id_clone = peer.id.clone();
+        let peer_name = self.aliases.name_for(&peer.public_key, &peer.name);
+        let audit_log = self.audit_log.clone();
+        let event_tx = self.event_tx.clone();
+        let mixer = self.mixer.clone();
+        let mut comfort_noise = ComfortNoiseGenerator::new();
+        let mut decoder = AudioDecoder::new();
+        let meeting = self.meeting.clone();
+        let recording = self.recording.clone();
+        let playouts = self.playouts.clone();
+        let is_host = self
+            .current_session
+            .as_ref()
+            .is_some_and(|session| session.is_host);
+        let connection = connection_manager.clone();
+        let aliases = self.aliases.clone();
+        let watchdog = self.audio_watchdog.clone();
+
+        let handler_task = connection_manager
+            .start_listening(move |message| {
+                match message {
+                    // Peers the host muted aren't heard, whatever they send
+                    Message::Audio { .. } | Message::ComfortNoise { .. }
+                        if !meeting.lock().unwrap().may_speak(&peer_id_clone) => {}
+                    audio @ Message::Audio { .. } => {
+                        watchdog
+                            .lock()
+                            .unwrap()
+                            .heard(&peer_id_clone, Instant::now());
+                        if let Some(frame) = audio.decode_audio(&mut decoder, &peer_id_clone) {
+                            publish_event(
+                                &audit_log,
+                                &event_tx,
+                                SessionEvent::AudioLevel {
+                                    peer_id: peer_id_clone.clone(),
+                                    level: frame.level(),
+                                },
+                            );
+
+                            // Queue for the next mix if we are mixing for the room
+                            if let Some(mixer) = &mixer {
+                                mixer.lock().unwrap().push(frame.clone());
+                            }
+
+                            // Store audio stream for this peer
+                            if let Some(stream) = audio_streams.get(&peer_name) {
+                                let mut stream = stream.lock().unwrap();
+                                *stream = frame;
+                            }
+                        }
+                    }
+                    Message::ComfortNoise {
+                        sample_rate,
+                        channels,
+                        frame_len,
+                        noise,
+                    } => {
+                        // The peer went quiet; keep a little of its background noise going.
+                        // Left out of the mix, as everyone else's noise would add up.
+                        watchdog
+                            .lock()
+                            .unwrap()
+                            .heard(&peer_id_clone, Instant::now());
+                        let Some(frame_len) = noise_frame_len(sample_rate, channels, frame_len)
+                        else {
+                            log::debug!(
+                                "Ignoring an implausible comfort noise frame from {}",
+                                peer_id_clone
+                            );
+                            return Ok(());
+                        };
+                        publish_event(
+                            &audit_log,
+                            &event_tx,
+                            SessionEvent::AudioLevel {
+                                peer_id: peer_id_clone.clone(),
+                                level: 0.0,
+                            },
+                        );
+                        if let Some(stream) = audio_streams.get(&peer_name) {
+                            let samples = comfort_noise.generate(&noise, channels, frame_len);
+                            *stream.lock().unwrap() =
+                                AudioFrame::new(samples, sample_rate, channels)
+                                    .with_peer_id(&peer_id_clone);
+                        }
+                    }
+                    Message::StreamEnd => {
+                        // The peer's audio ended cleanly; nothing more to mix for them
+                        watchdog.lock().unwrap().forget(&peer_id_clone);
+                        if let Some(mixer) = &mixer {
+                            mixer.lock().unwrap().remove_peer(&peer_id_clone);
+                        }
+                        publish_event(
+                            &audit_log,
+                            &event_tx,
+                            SessionEvent::StreamEnded {
+                                peer_id: peer_id_clone.clone(),
+                                name: peer_name.clone(),
+                            },
+                        );
+                    }
+                    Message::PeerLeft { peer_id } => {
+                        // A peer left the session
+                        if let Some(mixer) = &mixer {
+                            mixer.lock().unwrap().remove_peer(&peer_id);
+                        }
+                        playouts.lock().unwrap().remove(&peer_id);
+                        let mut peers_lock = peers.lock().unwrap();
+                        if recording.lock().unwrap().set_recording(&peer_id, false) {
+                            publish_event(
+                                &audit_log,
+                                &event_tx,
+                                SessionEvent::RecordingChanged {
+                                    peer_id: peer_id.clone(),
+                                    name: display_name(&peers_lock, &aliases, &peer_id),
+                                    recording: false,
+                                },
+                            );
+                        }
+                        peers_lock.remove(&peer_id);
+                        publish_event(
+                            &audit_log,
+                            &event_tx,
+                            SessionEvent::PeerLeft {
+                                peer_id: peer_id.clone(),
+                            },
+                        );
+
+                        // Handle host leaving
+                        let mut new_host_needed = false;
+                        for peer in peers_lock.values() {
+                            if peer.is_host && peer.id == peer_id {
+                                new_host_needed = true;
+                                break;
+                            }
+                        }
+
+                        if new_host_needed {
+                            // Simple host election: oldest peer becomes host
+                            let mut oldest_time = u64::MAX;
+                            let mut oldest_id = String::new();
+
+                            for (id, peer) in peers_lock.iter() {
+                                if peer.joined_at < oldest_time {
+                                    oldest_time = peer.joined_at;
+                                    oldest_id = id.clone();
+                                }
+                            }
+
+                            // If we're the oldest, we become host
+                            if oldest_id == self_id_clone {
+                                if let Some(peer) = peers_lock.get_mut(&self_id_clone) {
+                                    peer.is_host = true;
+                                    publish_event(
+                                        &audit_log,
+                                        &event_tx,
+                                        SessionEvent::HostChanged {
+                                            peer_id: self_id_clone.clone(),
+                                        },
+                                    );
+                                }
+                            }
+                        }
+                    }
+                    Message::Presence { peer_id, status } => {
+                        // A peer became active, idle or away
+                        let name = display_name(&peers.lock().unwrap(), &aliases, &peer_id);
+
+                        publish_event(
+                            &audit_log,
+                            &event_tx,
+                            SessionEvent::PresenceChanged {
+                                peer_id,
+                                name,
+                                status,
+                            },
+                        );
+                    }
+                    // Peers only raise their own hand
+                    Message::HandRaised { peer_id, raised } if peer_id == peer_id_clone => {
+                        let changed = meeting.lock().unwrap().set_hand_raised(&peer_id, raised);
+                        if changed {
+                            publish_event(
+                                &audit_log,
+                                &event_tx,
+                                SessionEvent::HandRaised {
+                                    peer_id,
+                                    name: peer_name.clone(),
+                                    raised,
+                                },
+                            );
+                        }
+                    }
+                    // Peers only announce their own recording
+                    Message::RecordingStateChanged {
+                        peer_id,
+                        recording: active,
+                    } if peer_id == peer_id_clone => {
+                        if is_host && active {
+                            // The peer may be waiting on this to start; answer repeats too
+                            let connection = connection.clone();
+                            let peer_id = peer_id.clone();
+                            topology::spawn(Plane::Control, "recording-ack", async move {
+                                if let Err(e) =
+                                    connection.send_recording_acknowledged(&peer_id).await
+                                {
+                                    log::warn!("Failed to acknowledge recording: {}", e);
+                                }
+                            });
+                        }
+                        if recording.lock().unwrap().set_recording(&peer_id, active) {
+                            publish_event(
+                                &audit_log,
+                                &event_tx,
+                                SessionEvent::RecordingChanged {
+                                    peer_id,
+                                    name: peer_name.clone(),
+                                    recording: active,
+                                },
+                            );
+                        }
+                    }
+                    Message::SyncedPlayout { peer_id, delay_ms } if peer_id == peer_id_clone => {
+                        set_playout(&playouts, peer_id, delay_ms);
+                    }
+                    // Handle other message types as needed
+                    _ => {}
+                }
+
+                Ok(())
+            })
+            .await;
+
+        self.listeners
+            .insert(peer.id.clone(), handler_task.abort_handle());
+        self.shutdown.track(handler_task);
+    }
+
+    /// Estimated clock offset of a connected peer (theirs minus ours), in microseconds
+    pub fn clock_offset(&self, peer_id: &str) -> Option<i64> {
+        self.peer_connections
+            .get(peer_id)
+            .and_then(|connection| connection.clock_offset())
+    }
+
+    /// Packets from a connected peer rejected by the channel's cryptography
+    pub fn crypto_stats(&self, peer_id: &str) -> Option<CryptoStats> {
+        self.peer_connections
+            .get(peer_id)
+            .map(|connection| connection.crypto_stats())
+    }
+
+    /// Handshake, keys, codec, path and recent history of the connection to
+    /// the peer shown as `name`, if we have one
+    pub async fn inspect_peer(&self, name: &str) -> Option<ConnectionDetails> {
+        let peer_id = self.peer_named(name)?;
+        Some(self.peer_connections.get(&peer_id)?.inspect().await)
+    }
+
+    /// Id of the peer shown as `name`
+    fn peer_named(&self, name: &str) -> Option<String> {
+        self.peers
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(_, peer)| self.aliases.name_for(&peer.public_key, &peer.name) == name)
+            .map(|(id, _)| id.clone())
+    }
+
+    /// A participant shown as `name`, tied to the roster's peer of that name if there is one
+    fn participant_for(&self, name: &str) -> Participant {
+        let participant = Participant::new(name);
+        if name == "Me" {
+            return participant.with_id(&self.self_id);
+        }
+        match self.peer_named(name) {
+            Some(peer_id) => participant.with_id(&peer_id),
+            None => participant,
+        }
+    }
+
+    /// A connection to a peer with our network and audio settings
+    fn new_connection(
+        &self,
+        remote: std::net::SocketAddr,
+        session_id: String,
+        remote_key: [u8; 32],
+        peer_id: &str,
+    ) -> ConnectionManager {
+        ConnectionManager::new(remote.ip(), remote.port(), session_id, remote_key)
+            .with_mtu(self.mtu)
+            .with_audio_bundling(self.audio_bundling)
+            .with_silence_suppression(self.silence_suppression)
+            .with_forward_secrecy(self.forward_secrecy)
+            .with_nonce_strategy(self.nonce_strategy)
+            .with_bandwidth(self.bandwidth.clone())
+            .with_codec(self.codec())
+            .with_low_complexity(self.low_complexity)
+            .with_bind_address(self.bind_address.clone())
+            .with_tamper_alert(self.tamper_alert(peer_id))
+    }
+
+    /// Reports a connection to `peer_id` that failed after its handshake
+    fn handshake_failed(&self, peer_id: &str, error: anyhow::Error) -> SessionError {
+        match error.downcast_ref::<NetworkError>() {
+            Some(NetworkError::HandshakeTimedOut(address)) => {
+                publish_event(
+                    &self.audit_log,
+                    &self.event_tx,
+                    SessionEvent::HandshakeTimedOut {
+                        peer_id: peer_id.to_string(),
+                        address: *address,
+                    },
+                );
+                SessionError::HandshakeTimedOut(*address)
+            }
+            _ => SessionError::ConnectionFailed(error.to_string()),
+        }
+    }
+
+    /// Lets the UI follow an attempt to connect to `peer_id`
+    fn publish_attempt(&self, peer_id: &str, name: &str, retry: &Retry) {
+        publish_event(
+            &self.audit_log,
+            &self.event_tx,
+            SessionEvent::ConnectionAttempt {
+                peer_id: peer_id.to_string(),
+                name: name.to_string(),
+                attempt: retry.attempt(),
+                attempts: retry.attempts(),
+            },
+        );
+    }
+
+    /// Callback raising a tampering warning about `peer_id`
+    fn tamper_alert(&self, peer_id: &str) -> impl Fn() + Send + Sync + 'static {
+        let audit_log = self.audit_log.clone();
+        let event_tx = self.event_tx.clone();
+        let peer_id = peer_id.to_string();
+        move || {
+            publish_event(
+                &audit_log,
+                &event_tx,
+                SessionEvent::PossibleTampering {
+                    peer_id: peer_id.clone(),
+                },
+            )
+        }
+    }
+
+    /// Who the host has muted and who is waiting to speak
+    pub fn meeting(&self) -> MeetingMode {
+        self.meeting.lock().unwrap().clone()
+    }
+
+    /// Whether the host lets us be heard; the host always is
+    pub fn may_speak(&self) -> bool {
+        let is_host = self
+            .current_session
+            .as_ref()
+            .is_some_and(|session| session.is_host);
+        is_host || self.meeting.lock().unwrap().may_speak(&self.self_id)
+    }
+
+    /// Whether we've asked the host to let us speak
+    pub fn is_hand_raised(&self) -> bool {
+        self.meeting.lock().unwrap().is_hand_raised(&self.self_id)
+    }
+
+    /// Mutes everyone but us, or lets everyone speak again (host only)
+    ///
+    /// Muted peers stop sending, and everyone drops what they send anyway.
+    pub async fn set_meeting_mode(&mut self, enabled: bool) -> Result<(), SessionError> {
+        match &self.current_session {
+            Some(session) if session.is_host => {}
+            Some(_) => return Err(SessionError::NotHost),
+            None => return Err(SessionError::NoActiveSession),
+        }
+
+        self.meeting.lock().unwrap().set_enabled(enabled);
+        for connection in self.peer_connections.values() {
+            if connection.is_connected().await {
+                connection
+                    .send_meeting_mode(enabled)
+                    .await
+                    .map_err(|e| SessionError::NetworkError(e.to_string()))?;
+            }
+        }
+
+        publish_event(
+            &self.audit_log,
+            &self.event_tx,
+            SessionEvent::MeetingModeChanged { enabled },
+        );
+        self.record_host_action(HostAction::MuteAll { enabled });
+        Ok(())
+    }
+
+    /// Asks the host to let us speak, or takes it back
+    ///
+    /// Returns false if there was nothing to do, as when we aren't muted.
+    pub async fn raise_hand(&self, raised: bool) -> Result<bool, SessionError> {
+        if self.current_session.is_none() {
+            return Err(SessionError::NoActiveSession);
+        }
+        if self.may_speak() && raised {
+            return Ok(false);
+        }
+        if !self
+            .meeting
+            .lock()
+            .unwrap()
+            .set_hand_raised(&self.self_id, raised)
+        {
+            return Ok(false);
+        }
+
         for connection in self.peer_connections.values() {
-            let state = connection.connection_state().await;
-            if state == ConnectionState::Connected {
-                return Some(state);
+            if connection.is_connected().await {
+                connection
+                    .send_hand_raised(&self.self_id, raised)
+                    .await
+                    .map_err(|e| SessionError::NetworkError(e.to_string()))?;
             }
         }
-        None
-    }
 
-    /// Connects to a peer in the session
-    pub async fn connect_to_peer(&mut self, peer_id: &str) -> Result<(), SessionError> {
-        // Get peer info
-        let peer = match self.peers.get(peer_id) {
-            Some(peer) => peer.clone(),
-            None => return Err(SessionError::NetworkError("Peer not found".to_string())),
-        };
+        publish_event(
+            &self.audit_log,
+            &self.event_tx,
+            SessionEvent::HandRaised {
+                peer_id: self.self_id.clone(),
+                name: "Me".to_string(),
+                raised,
+            },
+        );
+        Ok(true)
+    }
 
-        // Don't connect to ourselves
-        if peer.id == self.self_id {
-            return Ok(());
+    /// Lets a muted peer speak (host only)
+    pub async fn grant_speak(&self, peer_id: &str) -> Result<(), SessionError> {
+        match &self.current_session {
+            Some(session) if session.is_host => {}
+            Some(_) => return Err(SessionError::NotHost),
+            None => return Err(SessionError::NoActiveSession),
         }
 
-        // Skip if already connected
-        if self.peer_connections.contains_key(&peer.id) {
-            return Ok(());
+        self.meeting.lock().unwrap().grant(peer_id);
+        for connection in self.peer_connections.values() {
+            if connection.is_connected().await {
+                connection
+                    .send_speak_granted(peer_id)
+                    .await
+                    .map_err(|e| SessionError::NetworkError(e.to_string()))?;
+            }
         }
 
-        let session_id = match &self.current_session {
-            Some(session) => session.id.clone(),
-            None => return Err(SessionError::NoActiveSession),
-        };
-
-        // Create connection manager
-        let connection_manager = ConnectionManager::new(
-            peer.endpoint.ip,
-            peer.endpoint.port,
-            session_id,
-            peer.public_key,
+        let name = display_name(&self.peers.lock().unwrap(), &self.aliases, peer_id);
+        publish_event(
+            &self.audit_log,
+            &self.event_tx,
+            SessionEvent::SpeakGranted {
+                peer_id: peer_id.to_string(),
+                name: name.clone(),
+            },
         );
+        self.record_host_action(HostAction::GrantSpeak {
+            peer_id: peer_id.to_string(),
+            name,
+        });
+        Ok(())
+    }
 
-        // Connect to peer
-        connection_manager
-            .connect()
-            .await
-            .map_err(|e| SessionError::NetworkError(format!("Connection failed: {}", e)))?;
-
-        // Setup message handler
-        let audio_streams = self.audio_streams.clone();
-        let peers = Arc::new(Mutex::new(self.peers.clone()));
-        let self_id_clone = self.self_id.clone();
-        let peer_name = peer.name.clone();
-
-        let handler_task = connection_manager
-            .start_listening(move |message| {
-                match message {
-                    Message::Audio { data, timestamp: _ } => {
-                        // Convert audio data to f32 samples
-                        let samples: Vec<f32> = data.iter().map(|&b| (b as f32) / 255.0).collect();
+    /// Names of the peers recording the room, in the order they started
+    pub fn recorders(&self) -> Vec<String> {
+        self.recording
+            .lock()
+            .unwrap()
+            .recorders()
+            .iter()
+            .map(|peer_id| {
+                if *peer_id == self.self_id {
+                    "Me".to_string()
+                } else {
+                    display_name(&self.peers.lock().unwrap(), &self.aliases, peer_id)
+                }
+            })
+            .collect()
+    }
 
-                        // Store audio stream for this peer
-                        if let Some(stream) = audio_streams.get(&peer_name) {
-                            let mut stream = stream.lock().unwrap();
-                            *stream = samples;
-                        }
-                    }
-                    Message::PeerLeft { peer_id } => {
-                        // A peer left the session
-                        let mut peers_lock = peers.lock().unwrap();
-                        peers_lock.remove(&peer_id);
+    /// Whether we told the room we're recording it
+    pub fn is_recording(&self) -> bool {
+        self.recording.lock().unwrap().is_recording(&self.self_id)
+    }
 
-                        // Handle host leaving
-                        let mut new_host_needed = false;
-                        for peer in peers_lock.values() {
-                            if peer.is_host && peer.id == peer_id {
-                                new_host_needed = true;
-                                break;
-                            }
-                        }
+    /// Tells the room we started or stopped recording it
+    ///
+    /// Where the room asks for consent, a guest's recording only starts once
+    /// the host acknowledges it; if it doesn't in time, the announcement is
+    /// taken back. Returns false if there was nothing to do.
+    pub async fn set_recording(&self, recording: bool) -> Result<bool, SessionError> {
+        let is_host = match &self.current_session {
+            Some(session) => session.is_host,
+            None => return Err(SessionError::NoActiveSession),
+        };
+        if self.is_recording() == recording {
+            return Ok(false);
+        }
 
-                        if new_host_needed {
-                            // Simple host election: oldest peer becomes host
-                            let mut oldest_time = u64::MAX;
-                            let mut oldest_id = String::new();
+        let needs_ack = recording && !is_host && self.recording.lock().unwrap().consent_required();
+        if needs_ack {
+            self.recording.lock().unwrap().await_ack();
+        }
+        self.announce_recording(recording).await?;
+        if needs_ack {
+            let acknowledged = tokio::time::timeout(RECORDING_ACK_TIMEOUT, async {
+                while self.recording.lock().unwrap().awaiting_ack() {
+                    self.recording_ack.notified().await;
+                }
+            })
+            .await
+            .is_ok();
+            // An acknowledgement arriving just as we gave up still counts
+            if !acknowledged && self.recording.lock().unwrap().settle_ack() {
+                self.announce_recording(false).await?;
+                return Err(SessionError::RecordingNotAcknowledged);
+            }
+        }
 
-                            for (id, peer) in peers_lock.iter() {
-                                if peer.joined_at < oldest_time {
-                                    oldest_time = peer.joined_at;
-                                    oldest_id = id.clone();
-                                }
-                            }
+        self.recording
+            .lock()
+            .unwrap()
+            .set_recording(&self.self_id, recording);
+        publish_event(
+            &self.audit_log,
+            &self.event_tx,
+            SessionEvent::RecordingChanged {
+                peer_id: self.self_id.clone(),
+                name: "Me".to_string(),
+                recording,
+            },
+        );
+        Ok(true)
+    }
 
-                            // If we're the oldest, we become host
-                            if oldest_id == self_id_clone {
-                                if let Some(peer) = peers_lock.get_mut(&self_id_clone) {
-                                    peer.is_host = true;
-                                }
-                            }
-                        }
-                    }
-                    // Handle other message types as needed
-                    _ => {}
+    /// Tells each peer how long after capture to play our audio, so the room
+    /// hears what we share together, or that it needn't wait any more
+    ///
+    /// The delay follows the slowest peer's round trip. Only peers that
+    /// haven't heard the current delay are sent it, so this is cheap to call
+    /// every second and reaches peers as they join.
+    pub async fn update_synced_playout(&mut self) {
+        if self.current_session.is_none() {
+            return;
+        }
+        let delay_ms = if self.synced_playout {
+            let round_trips = self
+                .peer_connections
+                .values()
+                .filter_map(|connection| connection.round_trip());
+            synced_delay(round_trips).as_millis() as u32
+        } else {
+            0
+        };
+        for (peer_id, connection) in &self.peer_connections {
+            let sent = self.synced_sent.get(peer_id).copied().unwrap_or(0);
+            if sent == delay_ms || !connection.is_connected().await {
+                continue;
+            }
+            match connection
+                .send_synced_playout(&self.self_id, delay_ms)
+                .await
+            {
+                Ok(()) => {
+                    self.synced_sent.insert(peer_id.clone(), delay_ms);
                 }
+                Err(e) => log::warn!("Failed to send synced playout to {}: {}", peer_id, e),
+            }
+        }
+    }
 
-                Ok(())
+    /// When to play each peer that asked for its audio in sync, by name,
+    /// once its clock offset is known
+    pub fn synced_playouts(&self) -> HashMap<String, SyncedPlayout> {
+        self.playouts
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|(peer_id, delay_ms)| {
+                let name = self.peers.lock().unwrap().get(peer_id)?.name.clone();
+                let sync = SyncedPlayout {
+                    delay: Duration::from_millis(*delay_ms as u64),
+                    clock_offset: self.clock_offset(peer_id)?,
+                };
+                Some((name, sync))
             })
-            .await;
+            .collect()
+    }
 
-        self.background_tasks.push(handler_task);
-        self.peer_connections
-            .insert(peer.id.clone(), connection_manager);
+    async fn announce_recording(&self, recording: bool) -> Result<(), SessionError> {
+        for connection in self.peer_connections.values() {
+            if connection.is_connected().await {
+                connection
+                    .send_recording_state(&self.self_id, recording)
+                    .await
+                    .map_err(|e| SessionError::NetworkError(e.to_string()))?;
+            }
+        }
+        Ok(())
+    }
 
-        // Initialize audio stream for this peer
-        self.audio_streams
-            .insert(peer.name.clone(), Arc::new(Mutex::new(Vec::new())));
+    /// Tells all connected peers about a change in our presence
+    pub async fn broadcast_presence(&self, status: PresenceStatus) -> Result<(), SessionError> {
+        if self.current_session.is_none() {
+            return Err(SessionError::NoActiveSession);
+        }
+
+        for connection in self.peer_connections.values() {
+            if connection.is_connected().await {
+                connection
+                    .send_presence(&self.self_id, status)
+                    .await
+                    .map_err(|e| SessionError::NetworkError(e.to_string()))?;
+            }
+        }
 
         Ok(())
     }
@@ -601,8 +3273,13 @@ impl SessionManager {
             return Ok(());
         }
 
-        // Send peer list to all peers
-        let peers: Vec<Peer> = self.peers.values().cloned().collect();
+        // Send peer list to all peers, leaving out ones that never connected
+        let peers: Vec<Peer> = {
+            let mut peers = self.peers.lock().unwrap();
+            peers.expire_stale();
+            peers.values().cloned().collect()
+        };
+        let recorders = self.recording.lock().unwrap().recorders().to_vec();
 
         for (peer_id, connection) in &self.peer_connections {
             // Skip sending to ourselves
@@ -613,6 +3290,12 @@ impl SessionManager {
             // Skip sending if connection is not active
             if connection.is_connected().await {
                 let _ = connection.send_peer_list(&peers).await;
+                let _ = connection
+                    .send_recording_policy(self.recording_consent)
+                    .await;
+                for recorder in &recorders {
+                    let _ = connection.send_recording_state(recorder, true).await;
+                }
             }
         }
 
@@ -656,7 +3339,7 @@ impl SessionManager {
 
         // If we have a session but no peer connections, return true if we're the host
         if self.peer_connections.is_empty() {
-            return self.current_session.as_ref().map_or(false, |s| s.is_host);
+            return self.current_session.as_ref().is_some_and(|s| s.is_host);
         }
 
         // Otherwise check for active peer connections
@@ -667,18 +3350,90 @@ impl SessionManager {
         }
         false
     }
+
+    /// Holds every connection still while the machine sleeps, or has just woken
+    pub fn suspend_connections(&self) {
+        for connection in self.peer_connections.values() {
+            connection.suspend();
+        }
+    }
+
+    /// Reconnects to everyone after the machine slept for `slept`
+    pub async fn wake_connections(&self, slept: Duration) {
+        if self.peer_connections.is_empty() {
+            return;
+        }
+        log::info!(
+            "Woke after {}s asleep, reconnecting to {} peers",
+            slept.as_secs(),
+            self.peer_connections.len()
+        );
+        for connection in self.peer_connections.values() {
+            connection.wake().await;
+        }
+    }
 }
 
 impl Clone for SessionManager {
     fn clone(&self) -> Self {
+        let (lobby_tx, lobby_rx) = mpsc::unbounded_channel();
         SessionManager {
             current_session: self.current_session.clone(),
             audio_streams: self.audio_streams.clone(),
             peer_connections: self.peer_connections.clone(),
-            background_tasks: Vec::new(), // Don't clone background tasks
+            shutdown: ShutdownCoordinator::new(), // Don't clone background tasks
+            listeners: HashMap::new(),
             host_public_endpoint: self.host_public_endpoint.clone(),
             peers: self.peers.clone(),
             self_id: self.self_id.clone(),
+            audit_log: self.audit_log.clone(),
+            event_tx: self.event_tx.clone(),
+            event_rx: None, // The receiver stays with the original
+            topology: self.topology,
+            mixer: self.mixer.clone(),
+            mix_targets: self.mix_targets.clone(),
+            port_mapping: self.port_mapping,
+            ice_servers: self.ice_servers.clone(),
+            bind_address: self.bind_address.clone(),
+            proxy: self.proxy.clone(),
+            room_proxy: self.room_proxy.clone(),
+            mtu: self.mtu,
+            retry: self.retry.clone(),
+            audio_bundling: self.audio_bundling,
+            silence_suppression: self.silence_suppression,
+            forward_secrecy: self.forward_secrecy,
+            nonce_strategy: self.nonce_strategy,
+            codec: self.codec,
+            room_codec: self.room_codec,
+            frame_duration: self.frame_duration,
+            repacketizer: self.repacketizer.clone(),
+            echo_bot: self.echo_bot.clone(),
+            low_complexity: self.low_complexity,
+            bandwidth: self.bandwidth.clone(),
+            meeting: self.meeting.clone(),
+            recording: self.recording.clone(),
+            recording_ack: self.recording_ack.clone(),
+            recording_consent: self.recording_consent,
+            synced_playout: self.synced_playout,
+            synced_sent: self.synced_sent.clone(),
+            playouts: self.playouts.clone(),
+            audio_watchdog: self.audio_watchdog.clone(),
+            aliases: self.aliases.clone(),
+            dnd: self.dnd.clone(),
+            admission: self.admission.clone(),
+            listener: None, // Peers reaching the host socket go to the original
+            pending_joins: HashMap::new(),
+            lobby_tx,
+            lobby_rx,
+            username: self.username.clone(),
+            join_reply: self.join_reply.clone(),
+            host_actions: self.host_actions.clone(),
+            host_socket: self.host_socket.clone(),
+            port_mapper: None, // The mapping is released by the original
+            nat_report: self.nat_report.clone(),
+            time_limit: self.time_limit,
+            timer: self.timer.clone(),
+            layout: self.layout,
         }
     }
 }
@@ -686,7 +3441,7 @@ impl Clone for SessionManager {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::net::IpAddr;
+    use crate::app::events::EventCoalescer;
 
     #[test]
     fn test_session_creation() {
@@ -697,6 +3452,7 @@ mod tests {
             is_host: true,
             original_host_id: "test-id".to_string(),
             created_at: 0,
+            topology: MixingTopology::FullMesh,
         };
 
         assert_eq!(session.id, "test-id");
@@ -732,7 +3488,7 @@ mod tests {
         assert!(manager.current_session().is_none());
         assert_eq!(manager.audio_streams.len(), 0);
         assert_eq!(manager.peer_connections.len(), 0);
-        assert_eq!(manager.peers.len(), 0);
+        assert_eq!(manager.peers.lock().unwrap().len(), 0);
     }
 
     #[test]
@@ -742,6 +3498,12 @@ mod tests {
 
         let error = SessionError::CreationError("test error".to_string());
         assert!(format!("{}", error).contains("test error"));
+        assert_eq!(error.code(), "CREATE_FAILED");
+        assert_eq!(error.user_hint(), None);
+
+        let error = SessionError::NatDiscovery("no STUN server answered".to_string());
+        assert_eq!(error.code(), "NAT_DISCOVERY");
+        assert!(error.user_hint().is_some());
     }
 
     #[test]
@@ -753,6 +3515,7 @@ mod tests {
             is_host: true,
             original_host_id: "test-id".to_string(),
             created_at: 0,
+            topology: MixingTopology::FullMesh,
         };
 
         let cloned = session.clone();
@@ -760,5 +3523,562 @@ mod tests {
         assert_eq!(session.participants.len(), cloned.participants.len());
     }
 
+    #[test]
+    fn test_apply_layout_moves_participants() {
+        let mut manager = SessionManager::new();
+        manager.current_session = Some(Session {
+            id: "test-id".to_string(),
+            connection_link: "test-link".to_string(),
+            participants: vec![Participant::new("Me"), Participant::new("Alice")],
+            is_host: false,
+            original_host_id: "host".to_string(),
+            created_at: 0,
+            topology: MixingTopology::FullMesh,
+        });
+        for (id, name) in [
+            (manager.self_id.clone(), "Me"),
+            ("alice".to_string(), "Alice"),
+        ] {
+            manager.peers.lock().unwrap().insert(
+                id.clone(),
+                Peer {
+                    id,
+                    name: name.to_string(),
+                    endpoint: Endpoint {
+                        ip: "127.0.0.1".parse().unwrap(),
+                        port: 8080,
+                    },
+                    public_key: [0; 32],
+                    position: (0.0, 0.0, 0.0),
+                    is_host: false,
+                    joined_at: 0,
+                },
+            );
+        }
+
+        let positions = vec![
+            ("alice".to_string(), (0.0, 0.0, -1.5)),
+            (manager.self_id.clone(), (0.0, 0.0, 1.5)),
+            ("unknown".to_string(), (9.0, 9.0, 9.0)),
+        ];
+        manager.apply_layout(SpatialLayout::PresenterFront, &positions);
+
+        let session = manager.current_session().unwrap();
+        assert_eq!(session.participants[0].position, (0.0, 0.0, 1.5));
+        assert_eq!(session.participants[1].position, (0.0, 0.0, -1.5));
+        assert_eq!(manager.spatial_layout(), SpatialLayout::PresenterFront);
+    }
+
+    #[test]
+    fn test_position_bursts_collapse() {
+        let mut manager = SessionManager::new();
+        let mut events = manager.take_event_receiver().unwrap();
+        manager.peers.lock().unwrap().insert(
+            "alice".to_string(),
+            Peer {
+                id: "alice".to_string(),
+                name: "Alice".to_string(),
+                endpoint: Endpoint {
+                    ip: "127.0.0.1".parse().unwrap(),
+                    port: 8080,
+                },
+                public_key: [0; 32],
+                position: (0.0, 0.0, 0.0),
+                is_host: false,
+                joined_at: 0,
+            },
+        );
+
+        // The last seat is taken twice; sitting still isn't a move
+        for z in [-1.0, -1.5, -2.0, -2.0] {
+            manager.apply_layout(SpatialLayout::Grid, &[("alice".to_string(), (0.0, 0.0, z))]);
+        }
+        let mut coalescer = EventCoalescer::new(Duration::ZERO);
+        let mut published = 0;
+        while let Ok(event) = events.try_recv() {
+            coalescer.push(event);
+            published += 1;
+        }
+
+        assert_eq!(published, 3);
+        assert_eq!(
+            coalescer.flush(),
+            vec![SessionEvent::PositionChanged {
+                peer_id: "alice".to_string(),
+                position: (0.0, 0.0, -2.0),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_join_requests_queue_in_order() {
+        let mut manager = SessionManager::new();
+        let mut events = manager.take_event_receiver().unwrap();
+        manager.current_session = Some(Session {
+            id: "test-id".to_string(),
+            connection_link: "test-link".to_string(),
+            participants: vec![Participant::new("Me")],
+            is_host: true,
+            original_host_id: manager.self_id.clone(),
+            created_at: 0,
+            topology: MixingTopology::FullMesh,
+        });
+
+        for peer_id in ["alice", "bob", "alice"] {
+            let request = JoinRequest {
+                peer_id: peer_id.to_string(),
+                name: peer_id.to_uppercase(),
+            };
+            assert_eq!(manager.screen_join_request(request), JoinDecision::Ask);
+        }
+        let waiting: Vec<String> = manager
+            .join_queue()
+            .into_iter()
+            .map(|queued| queued.peer_id)
+            .collect();
+        assert_eq!(waiting, ["alice", "bob"]);
+
+        // Asking twice is announced once, but every change sends the line
+        let mut requested = 0;
+        let mut last_queue = None;
+        while let Ok(event) = events.try_recv() {
+            match event {
+                SessionEvent::JoinRequested { .. } => requested += 1,
+                SessionEvent::JoinQueueUpdated { queue } => last_queue = Some(queue),
+                _ => {}
+            }
+        }
+        assert_eq!(requested, 2);
+        assert_eq!(last_queue.map(|queue| queue.len()), Some(2));
+
+        assert_eq!(manager.deny_all_joins().unwrap(), 2);
+        assert!(manager.join_queue().is_empty());
+        assert!(matches!(
+            events.try_recv(),
+            Ok(SessionEvent::JoinQueueUpdated { queue }) if queue.is_empty()
+        ));
+    }
+
+    /// Joins `link` as `name` from a task of its own on the current `LocalSet`
+    fn ask_to_join(link: &str, name: &str) -> tokio::task::JoinHandle<Result<(), SessionError>> {
+        let (link, name) = (link.to_string(), name.to_string());
+        tokio::task::spawn_local(async move {
+            let mut guest = SessionManager::new();
+            guest.set_username(name);
+            guest.join_p2p_session(&link).await
+        })
+    }
+
+    #[tokio::test]
+    async fn test_join_requests_cue_the_host() {
+        let local = tokio::task::LocalSet::new();
+        local
+            .run_until(async {
+                let mut host = SessionManager::new();
+                let mut events = host.take_event_receiver().unwrap();
+                let link = host_on_loopback(&mut host).await;
+                let joiners = [ask_to_join(&link, "Alice"), ask_to_join(&link, "Bob")];
+                accept_until(&mut host, |host| host.join_queue().len() == 2).await;
+
+                // The frame the UI draws after the requests, as its event loop sees it
+                let mut coalescer = EventCoalescer::new(Duration::ZERO);
+                while let Ok(event) = events.try_recv() {
+                    coalescer.push(event);
+                }
+                let frame = coalescer.flush();
+                let requested: Vec<&str> = frame
+                    .iter()
+                    .filter_map(|event| match event {
+                        SessionEvent::JoinRequested { name, .. } => Some(name.as_str()),
+                        _ => None,
+                    })
+                    .collect();
+                assert_eq!(requested.len(), 2);
+                assert!(requested.contains(&"Alice") && requested.contains(&"Bob"));
+                for joiner in joiners {
+                    joiner.abort();
+                }
+            })
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_join_requests_flash_the_screen() {
+        use crate::ui::accessibility::Accessibility;
+        use crate::ui::TerminalUI;
+
+        let local = tokio::task::LocalSet::new();
+        local
+            .run_until(async {
+                let mut host = SessionManager::new();
+                let mut events = host.take_event_receiver().unwrap();
+                let link = host_on_loopback(&mut host).await;
+                let mut ui = TerminalUI::new();
+                ui.set_accessibility(Accessibility {
+                    flash_join_requests: true,
+                    ..Accessibility::default()
+                });
+
+                let joiner = ask_to_join(&link, "Alice");
+                accept_until(&mut host, |host| host.join_queue().len() == 1).await;
+                assert!(!ui.flashing());
+
+                // The line the host's UI is sent, as its event loop hands it on
+                while let Ok(event) = events.try_recv() {
+                    if let SessionEvent::JoinQueueUpdated { queue } = event {
+                        ui.set_join_queue(queue);
+                    }
+                }
+                assert!(ui.flashing());
+                joiner.abort();
+            })
+            .await;
+    }
+
+    /// Takes in joiners, as the host's main loop would, until `done` holds
+    async fn accept_until(host: &mut SessionManager, done: impl Fn(&SessionManager) -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !done(host) {
+            assert!(
+                Instant::now() < deadline,
+                "Joiners never got where expected"
+            );
+            host.accept_joins().await;
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_undo_denied_joins() {
+        let local = tokio::task::LocalSet::new();
+        local
+            .run_until(async {
+                let mut host = SessionManager::new();
+                let link = host_on_loopback(&mut host).await;
+                let alice = ask_to_join(&link, "Alice");
+                let bob = ask_to_join(&link, "Bob");
+                accept_until(&mut host, |host| host.join_queue().len() == 2).await;
+                let names = |host: &SessionManager| -> Vec<String> {
+                    host.join_queue().into_iter().map(|queued| queued.name).collect()
+                };
+                let line = names(&host);
+                let bob_id = host
+                    .join_queue()
+                    .into_iter()
+                    .find(|queued| queued.name == "Bob")
+                    .unwrap()
+                    .peer_id;
+
+                // Turned away, but not told straight away
+                assert_eq!(host.deny_all_joins().unwrap(), 2);
+                let denied = host.host_actions()[0].id;
+                for _ in 0..5 {
+                    host.accept_joins().await;
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                }
+                assert!(!alice.is_finished() && !bob.is_finished());
+
+                // Undoing puts them back in line, in the same order
+                host.undo_host_action(denied).await.unwrap();
+                assert_eq!(names(&host), line);
+                let actions = host.host_actions();
+                assert!(actions[0].undone);
+                assert_eq!(
+                    actions[1].action.to_string(),
+                    format!("Invited back {}", line.join(", "))
+                );
+
+                // Taking it again turns away whoever is waiting again; once
+                // Bob's connection is gone, undoing brings back only Alice
+                host.replay_host_action(denied).await.unwrap();
+                assert!(host.join_queue().is_empty());
+                let bob_addr = host.pending_join_addr(&bob_id).unwrap();
+                host.pending_joins[&bob_addr].connection.close().await;
+                let denied_again = host.host_actions().last().unwrap().id;
+                host.undo_host_action(denied_again).await.unwrap();
+                assert_eq!(names(&host), ["Alice"]);
+
+                // Once the grace runs out Alice hears she was turned away,
+                // and there's no taking it back
+                host.deny_all_joins().unwrap();
+                let denied_last = host.host_actions().last().unwrap().id;
+                for pending in host.pending_joins.values_mut() {
+                    if pending.deny_at.is_some() {
+                        pending.deny_at = Some(Instant::now());
+                    }
+                }
+                host.accept_joins().await;
+                let result = tokio::time::timeout(Duration::from_secs(2), alice)
+                    .await
+                    .unwrap()
+                    .unwrap();
+                assert!(
+                    matches!(&result, Err(SessionError::JoinDenied(message)) if message == DENIED_MESSAGE),
+                    "{:?}",
+                    result
+                );
+                host.undo_host_action(denied_last).await.unwrap();
+                assert!(host.join_queue().is_empty());
+
+                assert!(matches!(
+                    host.undo_host_action(99).await,
+                    Err(SessionError::UnknownAction(99))
+                ));
+                bob.abort();
+            })
+            .await;
+    }
+
+    /// Hosts a room on a loopback socket, skipping STUN, and returns its link
+    async fn host_on_loopback(manager: &mut SessionManager) -> String {
+        let socket = Arc::new(tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let addr = socket.local_addr().unwrap();
+        let keypair = Keypair::generate();
+        let session_id = uuid::Uuid::new_v4().to_string();
+        let link = generate_connection_link(
+            &Endpoint {
+                ip: addr.ip(),
+                port: addr.port(),
+            },
+            &session_id,
+            &keypair.public.to_bytes(),
+        );
+        manager.current_session = Some(Session {
+            id: session_id.clone(),
+            connection_link: link.clone(),
+            participants: vec![Participant::new("Me").with_id(&manager.self_id)],
+            is_host: true,
+            original_host_id: manager.self_id.clone(),
+            created_at: 0,
+            topology: MixingTopology::FullMesh,
+        });
+        manager.host_socket = Some(socket.clone());
+        manager.listener = Some(HostListener::start(socket, keypair, session_id));
+        link
+    }
+
+    /// Joins `link`, with the host taking in peers meanwhile as its main loop
+    /// would and approving whoever asks
+    async fn join_hosted(
+        guest: &mut SessionManager,
+        host: &mut SessionManager,
+        link: &str,
+    ) -> Result<(), SessionError> {
+        let join = guest.join_p2p_session(link);
+        tokio::pin!(join);
+        loop {
+            tokio::select! {
+                result = &mut join => return result,
+                _ = tokio::time::sleep(Duration::from_millis(20)) => {
+                    host.accept_joins().await;
+                    host.approve_all_joins().await.unwrap();
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stale_peers_show_in_the_stats() {
+        let mut host = SessionManager::new();
+        let link = host_on_loopback(&mut host).await;
+        let mut guest = SessionManager::new();
+        guest.peers = Arc::new(Mutex::new(
+            PeerTable::new().with_handshake_timeout(Duration::from_millis(200)),
+        ));
+        join_hosted(&mut guest, &mut host, &link).await.unwrap();
+
+        // The host announces a peer we never manage to reach
+        let ghost = Peer {
+            id: "ghost".to_string(),
+            name: "Ghost".to_string(),
+            endpoint: Endpoint {
+                ip: "127.0.0.1".parse().unwrap(),
+                port: 9,
+            },
+            public_key: [9; 32],
+            position: (0.0, 0.0, 0.0),
+            is_host: false,
+            joined_at: 0,
+        };
+        host.notify_new_peer(&ghost).await.unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while !guest.other_peers().iter().any(|peer| peer.id == "ghost") {
+            assert!(
+                Instant::now() < deadline,
+                "The announced peer never arrived"
+            );
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(guest.network_stats().await.evictions.expired, 0);
+
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert_eq!(guest.network_stats().await.evictions.expired, 1);
+        assert!(!guest.other_peers().iter().any(|peer| peer.id == "ghost"));
+    }
+
+    #[tokio::test]
+    async fn test_do_not_disturb_turns_joiners_away() {
+        let mut host = SessionManager::new();
+        host.set_dnd_policy("deny:Back at 3pm".parse().unwrap());
+        host.set_do_not_disturb(true);
+        let link = host_on_loopback(&mut host).await;
+
+        let mut guest = SessionManager::new();
+        guest.set_username("Alice".to_string());
+        let result = join_hosted(&mut guest, &mut host, &link).await;
+
+        assert!(
+            matches!(&result, Err(SessionError::JoinDenied(message)) if message == "Back at 3pm"),
+            "{:?}",
+            result
+        );
+        assert!(guest.current_session().is_none());
+        assert!(host.join_queue().is_empty());
+        assert!(host.other_peers().is_empty());
+
+        // The host lets go of the peer once the reply has gone out
+        tokio::time::sleep(DRAIN_GRACE).await;
+        host.accept_joins().await;
+        assert!(host.pending_joins.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_join_requests_reach_the_webhook() {
+        use crate::app::webhook::WebhookEvent;
+
+        let mut host = SessionManager::new();
+        let mut events = host.take_event_receiver().unwrap();
+        let link = host_on_loopback(&mut host).await;
+        let mut guest = SessionManager::new();
+        guest.set_username("Alice".to_string());
+        join_hosted(&mut guest, &mut host, &link).await.unwrap();
+
+        let mut hooks = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            hooks.extend(WebhookEvent::from_session_event(&event));
+        }
+        // Asked first, then let in
+        assert_eq!(
+            hooks,
+            vec![
+                WebhookEvent::JoinRequested {
+                    peer_id: guest.self_id.clone(),
+                    name: "Alice".to_string(),
+                },
+                WebhookEvent::PeerJoined {
+                    peer_id: guest.self_id.clone(),
+                    name: "Alice".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_queued_joiner_is_let_in_once_approved() {
+        let mut host = SessionManager::new();
+        let link = host_on_loopback(&mut host).await;
+        let mut guest = SessionManager::new();
+        guest.set_username("Alice".to_string());
+        let guest_id = guest.self_id.clone();
+
+        let mut queued = Vec::new();
+        let result = {
+            let join = guest.join_p2p_session(&link);
+            tokio::pin!(join);
+            loop {
+                tokio::select! {
+                    result = &mut join => break result,
+                    _ = tokio::time::sleep(Duration::from_millis(20)) => {
+                        host.accept_joins().await;
+                        // The request waits in line until the host decides
+                        if queued.is_empty() {
+                            queued = host.join_queue();
+                            if !queued.is_empty() {
+                                assert!(host.other_peers().is_empty());
+                                host.approve_join(&guest_id).await.unwrap();
+                            }
+                        }
+                    }
+                }
+            }
+        };
+
+        result.unwrap();
+        assert_eq!(queued.len(), 1);
+        assert_eq!(
+            (queued[0].peer_id.as_str(), queued[0].name.as_str()),
+            (guest_id.as_str(), "Alice")
+        );
+        assert!(host.join_queue().is_empty());
+        assert!(host.pending_joins.is_empty());
+        assert!(host.other_peers().iter().any(|peer| peer.id == guest_id));
+        assert!(host.has_active_connection().await);
+        assert!(guest.current_session().is_some());
+    }
+
+    #[test]
+    fn test_kicks_are_audited() {
+        let mut manager = SessionManager::new();
+        manager.set_audit_log(Some(AuditLog::new([3; 32])));
+        manager.current_session = Some(Session {
+            id: "test-id".to_string(),
+            connection_link: "test-link".to_string(),
+            participants: vec![Participant::new("Me"), Participant::new("Alice")],
+            is_host: true,
+            original_host_id: manager.self_id.clone(),
+            created_at: 0,
+            topology: MixingTopology::FullMesh,
+        });
+        manager.peers.lock().unwrap().insert(
+            "alice".to_string(),
+            Peer {
+                id: "alice".to_string(),
+                name: "Alice".to_string(),
+                endpoint: Endpoint {
+                    ip: "127.0.0.1".parse().unwrap(),
+                    port: 8080,
+                },
+                public_key: [0; 32],
+                position: (0.0, 0.0, 0.0),
+                is_host: false,
+                joined_at: 0,
+            },
+        );
+
+        tokio_test::block_on(manager.kick_peer("alice")).unwrap();
+        let session = manager.current_session().unwrap();
+        assert_eq!(session.participants.len(), 1);
+        let log = manager.audit_log().unwrap();
+        let events: Vec<AuditEvent> = log
+            .lock()
+            .unwrap()
+            .entries()
+            .iter()
+            .map(|entry| entry.event.clone())
+            .collect();
+        assert_eq!(
+            events,
+            [
+                AuditEvent::PeerKicked {
+                    peer_id: "alice".to_string(),
+                    by: manager.self_id.clone(),
+                },
+                AuditEvent::PeerLeft {
+                    peer_id: "alice".to_string(),
+                },
+            ]
+        );
+
+        // Gone already, and we can't kick ourselves
+        let self_id = manager.self_id.clone();
+        for peer_id in ["alice", self_id.as_str()] {
+            assert!(matches!(
+                tokio_test::block_on(manager.kick_peer(peer_id)),
+                Err(SessionError::PeerNotFound(_))
+            ));
+        }
+    }
+
     // More complex tests for peer interactions would be done with integration tests
 }
@@ -0,0 +1,113 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// How long before the end of a time-limited session participants are warned
+pub const TIME_WARNINGS: [Duration; 2] = [Duration::from_secs(5 * 60), Duration::from_secs(60)];
+
+/// Tracks how long we've been in a session and when a host-set limit ends it
+///
+/// The host's limit travels in the join link as a wall-clock end time, so
+/// everyone counts down to the same moment however late they joined.
+#[derive(Debug, Clone)]
+pub struct SessionTimer {
+    started: Instant,
+    ends_at: Option<Instant>,
+}
+
+impl SessionTimer {
+    /// Starts timing now, with the session ending at `ends_at` (Unix seconds) if set
+    pub fn start(ends_at: Option<u64>) -> Self {
+        let started = Instant::now();
+        let ends_at = ends_at.map(|ends_at| {
+            let now = unix_now();
+            started + Duration::from_secs(ends_at.saturating_sub(now))
+        });
+        Self { started, ends_at }
+    }
+
+    /// Time since we entered the session
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Time until the session ends, if it has a limit
+    pub fn remaining(&self) -> Option<Duration> {
+        self.ends_at
+            .map(|ends_at| ends_at.saturating_duration_since(Instant::now()))
+    }
+
+    /// When the session ends, if it has a limit
+    pub fn ends_at(&self) -> Option<Instant> {
+        self.ends_at
+    }
+}
+
+/// Seconds since the Unix epoch
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Reads the session end time from a join link's `ends` parameter
+pub fn ends_at_from_link(link: &str) -> Option<u64> {
+    link.split(['?', '&'])
+        .find_map(|param| param.strip_prefix("ends="))
+        .and_then(|value| value.parse().ok())
+}
+
+/// Adds the session end time to a join link
+pub fn append_ends_at(link: &str, ends_at: Option<u64>) -> String {
+    match ends_at {
+        Some(ends_at) => format!("{}&ends={}", link, ends_at),
+        None => link.to_string(),
+    }
+}
+
+/// Formats a duration as `M:SS`, or `H:MM:SS` from an hour up
+pub fn format_clock(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, minutes, seconds) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_end_time_in_link() {
+        let link = "resonance://join?ip=1.2.3.4&port=5&sid=abc&key=xyz";
+
+        assert_eq!(ends_at_from_link(link), None);
+        assert_eq!(append_ends_at(link, None), link);
+
+        let limited = append_ends_at(link, Some(1_700_000_000));
+        assert_eq!(ends_at_from_link(&limited), Some(1_700_000_000));
+    }
+
+    #[test]
+    fn test_timer_counts_down_to_end_time() {
+        assert_eq!(SessionTimer::start(None).remaining(), None);
+
+        let remaining = SessionTimer::start(Some(unix_now() + 600))
+            .remaining()
+            .unwrap();
+        assert!(remaining > Duration::from_secs(590) && remaining <= Duration::from_secs(600));
+
+        // An end time already passed leaves nothing
+        let late = SessionTimer::start(Some(unix_now() - 10));
+        assert_eq!(late.remaining(), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_format_clock() {
+        assert_eq!(format_clock(Duration::from_secs(0)), "0:00");
+        assert_eq!(format_clock(Duration::from_secs(754)), "12:34");
+        assert_eq!(format_clock(Duration::from_secs(3723)), "1:02:03");
+    }
+}
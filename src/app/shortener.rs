@@ -0,0 +1,83 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+
+use crate::network::http_request;
+
+/// Turns long join links into short ones that lead to them
+#[async_trait]
+pub trait LinkShortener: Send + Sync {
+    async fn shorten(&self, link: &str) -> Result<String>;
+}
+
+/// Posts links to a self-hosted shortener and reads back the short link
+///
+/// The link is sent as the plain-text body and the shortener answers with
+/// the short link as its whole body, as most self-hosted shorteners can be
+/// set up to. Only `http://` URLs work, as there's no TLS client.
+#[derive(Debug, Clone)]
+pub struct HttpShortener {
+    url: String,
+}
+
+impl HttpShortener {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl LinkShortener for HttpShortener {
+    async fn shorten(&self, link: &str) -> Result<String> {
+        let body = http_request(&self.url, "POST", &[("Content-Type", "text/plain")], link).await?;
+        let short = body.trim();
+        if short.is_empty() || short.contains(char::is_whitespace) {
+            return Err(anyhow!("{} didn't answer with a link", self.url));
+        }
+        Ok(short.to_string())
+    }
+}
+
+/// `link` shortened, or in full if the shortener fails or makes it no shorter
+pub async fn shorten_or_full(shortener: &dyn LinkShortener, link: &str) -> String {
+    match shortener.shorten(link).await {
+        Ok(short) if short.len() < link.len() => short,
+        Ok(_) => link.to_string(),
+        Err(e) => {
+            log::warn!("Couldn't shorten the room link: {}", e);
+            link.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Prefix(Option<&'static str>);
+
+    #[async_trait]
+    impl LinkShortener for Prefix {
+        async fn shorten(&self, link: &str) -> Result<String> {
+            match self.0 {
+                Some(prefix) => Ok(format!("{}{}", prefix, link.len())),
+                None => Err(anyhow!("shortener down")),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_to_the_full_link() {
+        let link = "resonance://join?ip=1.2.3.4&port=5&sid=abc&key=xyz";
+        assert_eq!(
+            shorten_or_full(&Prefix(Some("http://s/")), link).await,
+            format!("http://s/{}", link.len())
+        );
+        assert_eq!(shorten_or_full(&Prefix(None), link).await, link);
+        assert_eq!(
+            shorten_or_full(&Prefix(Some("http://s/")), "r://j").await,
+            "r://j"
+        );
+    }
+}
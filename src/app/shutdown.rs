@@ -0,0 +1,107 @@
+use std::time::Duration;
+
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// How long a leaving session keeps running so queued audio can go out
+///
+/// Longer than the playback fade, so the fade has finished by the time
+/// tasks are torn down.
+pub const DRAIN_GRACE: Duration = Duration::from_millis(150);
+
+/// Owns a session's background tasks and stops them in order on leave
+///
+/// Draining first tells tasks to wrap up, then gives them a grace period
+/// to flush what they have in flight. Only tasks still running after that
+/// are aborted.
+pub struct ShutdownCoordinator {
+    tasks: Vec<JoinHandle<()>>,
+    draining: watch::Sender<bool>,
+}
+
+impl ShutdownCoordinator {
+    pub fn new() -> Self {
+        let (draining, _) = watch::channel(false);
+        Self {
+            tasks: Vec::new(),
+            draining,
+        }
+    }
+
+    /// Takes ownership of a background task
+    pub fn track(&mut self, task: JoinHandle<()>) {
+        self.tasks.push(task);
+    }
+
+    /// Signal that flips to true when draining starts
+    pub fn subscribe(&self) -> watch::Receiver<bool> {
+        self.draining.subscribe()
+    }
+
+    /// Whether a drain has started
+    pub fn is_draining(&self) -> bool {
+        *self.draining.borrow()
+    }
+
+    /// Number of tasks being tracked
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Signals every task to finish, waits up to `grace`, then aborts the rest
+    ///
+    /// Returns how many tasks had to be aborted. The coordinator can be
+    /// reused for the next session afterwards.
+    pub async fn drain(&mut self, grace: Duration) -> usize {
+        self.draining.send_replace(true);
+
+        let deadline = tokio::time::Instant::now() + grace;
+        let mut aborted = 0;
+        for task in self.tasks.drain(..) {
+            let abort = task.abort_handle();
+            if tokio::time::timeout_at(deadline, task).await.is_err() {
+                abort.abort();
+                aborted += 1;
+            }
+        }
+
+        self.draining.send_replace(false);
+        aborted
+    }
+}
+
+impl Default for ShutdownCoordinator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_drain_lets_tasks_finish_then_aborts_the_rest() {
+        let mut coordinator = ShutdownCoordinator::new();
+
+        // Finishes as soon as it's told to
+        let mut draining = coordinator.subscribe();
+        let (done_tx, done_rx) = tokio::sync::oneshot::channel();
+        coordinator.track(tokio::spawn(async move {
+            let _ = draining.wait_for(|draining| *draining).await;
+            let _ = done_tx.send(());
+        }));
+
+        // Never finishes on its own
+        coordinator.track(tokio::spawn(std::future::pending()));
+
+        assert_eq!(coordinator.drain(Duration::from_millis(50)).await, 1);
+        assert!(done_rx.await.is_ok());
+        assert!(coordinator.is_empty());
+        assert!(!coordinator.is_draining());
+    }
+}
@@ -0,0 +1,80 @@
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often to look for the machine having slept
+pub const SLEEP_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A gap between checks longer than this means the machine slept
+///
+/// Long enough that a busy machine or a stalled terminal isn't mistaken
+/// for sleep, short enough that peers haven't given up on us yet.
+const SLEEP_THRESHOLD: Duration = Duration::from_secs(10);
+
+/// Notices the machine waking from sleep, by the clocks jumping between checks
+///
+/// Nothing runs while a laptop sleeps, so the first check after waking
+/// finds far more time passed than it should have. On Linux and macOS the
+/// monotonic clock stops during sleep and only the wall clock shows the
+/// jump; on Windows both do. The larger of the two gaps is taken.
+#[derive(Debug, Clone)]
+pub struct SleepDetector {
+    last_wall: SystemTime,
+    last_monotonic: Instant,
+}
+
+impl Default for SleepDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SleepDetector {
+    pub fn new() -> Self {
+        Self {
+            last_wall: SystemTime::now(),
+            last_monotonic: Instant::now(),
+        }
+    }
+
+    /// Checks the clocks now, returning how long the machine slept if it just woke
+    pub fn check(&mut self) -> Option<Duration> {
+        self.check_at(SystemTime::now(), Instant::now())
+    }
+
+    fn check_at(&mut self, wall: SystemTime, monotonic: Instant) -> Option<Duration> {
+        // The wall clock may be set back; that's no sleep
+        let wall_gap = wall.duration_since(self.last_wall).unwrap_or_default();
+        let monotonic_gap = monotonic.saturating_duration_since(self.last_monotonic);
+        self.last_wall = wall;
+        self.last_monotonic = monotonic;
+
+        let gap = wall_gap.max(monotonic_gap);
+        (gap > SLEEP_THRESHOLD).then_some(gap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_clock_jumps() {
+        let wall = SystemTime::now();
+        let monotonic = Instant::now();
+        let mut detector = SleepDetector {
+            last_wall: wall,
+            last_monotonic: monotonic,
+        };
+        let second = Duration::from_secs(1);
+        assert_eq!(detector.check_at(wall + second, monotonic + second), None);
+
+        // Only the wall clock moved on while asleep
+        let slept = Duration::from_secs(600);
+        assert_eq!(
+            detector.check_at(wall + second + slept, monotonic + second * 2),
+            Some(slept)
+        );
+
+        // Setting the clock back isn't sleep
+        assert_eq!(detector.check_at(wall, monotonic + second * 3), None);
+    }
+}
@@ -0,0 +1,37 @@
+use std::time::Duration;
+
+/// Least and most time from capture to playout of audio shared in sync
+pub const MIN_SYNCED_DELAY: Duration = Duration::from_millis(120);
+pub const MAX_SYNCED_DELAY: Duration = Duration::from_millis(400);
+
+/// Added to the slowest listener's one-way delay, to ride out its jitter
+const SYNC_HEADROOM: Duration = Duration::from_millis(80);
+
+/// The delay is kept to whole steps of this, so small swings in round
+/// trips don't have everyone resynchronizing
+const SYNC_STEP_MS: u64 = 20;
+
+/// Playout delay that every listener can meet, from each one's round trip to us
+///
+/// Each listener plays our audio this long after we captured it, holding it
+/// back for however much of the delay the network didn't already take.
+pub fn synced_delay(round_trips: impl IntoIterator<Item = Duration>) -> Duration {
+    let slowest = round_trips.into_iter().max().unwrap_or_default();
+    let delay = (slowest / 2 + SYNC_HEADROOM).as_millis() as u64;
+    let stepped = Duration::from_millis(delay.div_ceil(SYNC_STEP_MS) * SYNC_STEP_MS);
+    stepped.clamp(MIN_SYNCED_DELAY, MAX_SYNCED_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay_covers_the_slowest_listener() {
+        assert_eq!(synced_delay([]), MIN_SYNCED_DELAY);
+        let round_trips = [Duration::from_millis(30), Duration::from_millis(170)];
+        // Half of 170 ms plus the headroom, rounded up to a step
+        assert_eq!(synced_delay(round_trips), Duration::from_millis(180));
+        assert_eq!(synced_delay([Duration::from_secs(2)]), MAX_SYNCED_DELAY);
+    }
+}
@@ -1,17 +1,10 @@
+use crate::app::mixing::MixingTopology;
 use crate::app::session::{Session, SessionError};
 use crate::ui::Participant;
 use anyhow::{anyhow, Result};
-use std::fs::File;
-use std::io::{BufReader, Read};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
-use std::time::{Duration, Instant};
-use symphonia::core::audio::SampleBuffer;
-use symphonia::core::formats::{FormatOptions, FormatReader};
-use symphonia::core::io::MediaSourceStream;
-use symphonia::core::meta::MetadataOptions;
-use symphonia::core::probe::Hint;
-use symphonia::default::get_probe;
+use std::time::Duration;
 use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
 
@@ -29,6 +22,12 @@ pub struct TestSessionManager {
     participant_audio: Arc<Mutex<Vec<Vec<f32>>>>,
 }
 
+impl Default for TestSessionManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TestSessionManager {
     /// Creates a new test session manager
     pub fn new() -> Self {
@@ -68,6 +67,7 @@ impl TestSessionManager {
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap_or_default()
                 .as_secs(),
+            topology: MixingTopology::FullMesh,
         };
 
         // Start test audio playback
@@ -127,9 +127,6 @@ impl TestSessionManager {
         let (tx2, rx2) = mpsc::channel::<Vec<f32>>(100);
         let (tx3, rx3) = mpsc::channel::<Vec<f32>>(100);
 
-        // Clone data structures for tasks
-        let participant_audio = Arc::clone(&self.participant_audio);
-
         // Start playback coordinator task
         let handle = tokio::spawn(async move {
             let mut round = 0;
@@ -218,7 +215,7 @@ impl Clone for TestSessionManager {
 /// Loads an MP3 file and sends the audio data through the channel
 async fn load_and_play_mp3(path: &Path, tx: mpsc::Sender<Vec<f32>>) {
     // For now, just use the fallback audio while we resolve Symphonia integration
-    eprintln!("Using test audio for {}", path.display());
+    log::info!("Using test audio for {}", path.display());
     generate_fallback_audio(tx).await;
 }
 
@@ -0,0 +1,333 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::app::events::SessionEvent;
+use crate::app::presence::PresenceStatus;
+use crate::app::session_timer::format_clock;
+
+/// Most entries the timeline keeps; the oldest go first
+pub const TIMELINE_LEN: usize = 500;
+
+/// A change in who is in the room, or how they are
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimelineEvent {
+    /// We entered the room
+    Entered,
+    /// We left the room
+    Left,
+    PeerJoined {
+        name: String,
+    },
+    PeerLeft {
+        name: String,
+    },
+    /// We took over as host
+    BecameHost,
+    PresenceChanged {
+        name: String,
+        status: PresenceStatus,
+    },
+    RecordingChanged {
+        name: String,
+        recording: bool,
+    },
+    /// The host muted everyone else, or let everyone speak again
+    MeetingModeChanged {
+        enabled: bool,
+    },
+}
+
+impl fmt::Display for TimelineEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimelineEvent::Entered => write!(f, "You entered the room"),
+            TimelineEvent::Left => write!(f, "You left the room"),
+            TimelineEvent::PeerJoined { name } => write!(f, "{} joined", name),
+            TimelineEvent::PeerLeft { name } => write!(f, "{} left", name),
+            TimelineEvent::BecameHost => write!(f, "You became the host"),
+            TimelineEvent::PresenceChanged { name, status } => {
+                let status = match status {
+                    PresenceStatus::Active => "back",
+                    PresenceStatus::Idle => "idle",
+                    PresenceStatus::Away => "away",
+                };
+                write!(f, "{} is {}", name, status)
+            }
+            TimelineEvent::RecordingChanged {
+                name,
+                recording: true,
+            } => write!(f, "{} started recording", name),
+            TimelineEvent::RecordingChanged {
+                name,
+                recording: false,
+            } => write!(f, "{} stopped recording", name),
+            TimelineEvent::MeetingModeChanged { enabled: true } => {
+                write!(f, "Everyone but the host was muted")
+            }
+            TimelineEvent::MeetingModeChanged { enabled: false } => {
+                write!(f, "Everyone may speak")
+            }
+        }
+    }
+}
+
+/// An event on the timeline
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimelineEntry {
+    pub at: SystemTime,
+    /// How far into the session it happened
+    pub offset: Duration,
+    pub event: TimelineEvent,
+}
+
+/// Who came and went during the session, and what changed while they were there
+#[derive(Debug, Clone, Default)]
+pub struct SessionTimeline {
+    started: Option<SystemTime>,
+    // Until we leave the room
+    running: bool,
+    entries: VecDeque<TimelineEntry>,
+    // Names of peers seen joining, for events that only carry their id
+    names: HashMap<String, String>,
+}
+
+impl SessionTimeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts the timeline of a new session, forgetting the last one, unless
+    /// one is already under way
+    pub fn start(&mut self) {
+        if !self.running {
+            self.start_at(SystemTime::now());
+        }
+    }
+
+    fn start_at(&mut self, at: SystemTime) {
+        self.entries.clear();
+        self.names.clear();
+        self.started = Some(at);
+        self.running = true;
+        self.push(at, TimelineEvent::Entered);
+    }
+
+    /// Marks us leaving; the timeline is kept until the next session starts
+    pub fn finish(&mut self) {
+        if self.running {
+            self.push(SystemTime::now(), TimelineEvent::Left);
+            self.running = false;
+        }
+    }
+
+    /// Adds a session event, if it's one the timeline shows, starting the
+    /// timeline if the event came before we noticed being in a room
+    pub fn record(&mut self, event: &SessionEvent) {
+        self.record_at(SystemTime::now(), event);
+    }
+
+    fn record_at(&mut self, at: SystemTime, event: &SessionEvent) {
+        let event = match event {
+            SessionEvent::PeerJoined { peer_id, name } => {
+                self.names.insert(peer_id.clone(), name.clone());
+                TimelineEvent::PeerJoined { name: name.clone() }
+            }
+            SessionEvent::PeerLeft { peer_id } => TimelineEvent::PeerLeft {
+                name: self.name(peer_id),
+            },
+            SessionEvent::HostChanged { .. } => TimelineEvent::BecameHost,
+            SessionEvent::PresenceChanged { name, status, .. } => TimelineEvent::PresenceChanged {
+                name: name.clone(),
+                status: *status,
+            },
+            SessionEvent::RecordingChanged {
+                name, recording, ..
+            } => TimelineEvent::RecordingChanged {
+                name: name.clone(),
+                recording: *recording,
+            },
+            SessionEvent::MeetingModeChanged { enabled } => {
+                TimelineEvent::MeetingModeChanged { enabled: *enabled }
+            }
+            _ => return,
+        };
+        if !self.running {
+            self.start_at(at);
+        }
+        self.push(at, event);
+    }
+
+    fn name(&self, peer_id: &str) -> String {
+        self.names
+            .get(peer_id)
+            .cloned()
+            .unwrap_or_else(|| peer_id.chars().take(8).collect())
+    }
+
+    fn push(&mut self, at: SystemTime, event: TimelineEvent) {
+        if self.entries.len() == TIMELINE_LEN {
+            self.entries.pop_front();
+        }
+        let offset = self
+            .started
+            .and_then(|started| at.duration_since(started).ok())
+            .unwrap_or_default();
+        self.entries.push_back(TimelineEntry { at, offset, event });
+    }
+
+    /// Entries kept, oldest first
+    pub fn entries(&self) -> Vec<TimelineEntry> {
+        self.entries.iter().cloned().collect()
+    }
+
+    /// The session report: when it began and ended, then everything in between
+    pub fn report(&self) -> String {
+        let mut report = format!("resonance {} session report\n", env!("CARGO_PKG_VERSION"));
+        if let Some(started) = self.started {
+            report.push_str(&format!("started: {}\n", format_utc(started)));
+        }
+        if let Some(last) = self.entries.back() {
+            if last.event == TimelineEvent::Left {
+                report.push_str(&format!("ended: {}\n", format_utc(last.at)));
+                report.push_str(&format!("length: {}\n", format_clock(last.offset)));
+            }
+        }
+        report.push_str("\ntimeline:\n");
+        for entry in &self.entries {
+            report.push_str(&format!(
+                "  {}  +{:>7}  {}\n",
+                format_utc(entry.at),
+                format_clock(entry.offset),
+                entry.event
+            ));
+        }
+        report
+    }
+
+    /// Writes the session report into `directory`, returning its path
+    pub fn write_report(&self, directory: &Path) -> io::Result<PathBuf> {
+        fs::create_dir_all(directory)?;
+        let stamp = self
+            .started
+            .unwrap_or_else(SystemTime::now)
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = directory.join(format!("resonance-session-{}.txt", stamp));
+        fs::write(&path, self.report())?;
+        Ok(path)
+    }
+}
+
+/// `YYYY-MM-DD HH:MM:SS UTC`
+fn format_utc(at: SystemTime) -> String {
+    let secs = at
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (days, time) = (secs / 86400, secs % 86400);
+
+    // Days since 1970 to a civil date, in 400-year eras from 0000-03-01
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records_who_came_and_went() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_792_159_200);
+        let mut timeline = SessionTimeline::new();
+        timeline.start_at(start);
+        timeline.record_at(
+            start + Duration::from_secs(5),
+            &SessionEvent::PeerJoined {
+                peer_id: "a1b2c3d4e5".to_string(),
+                name: "Alice".to_string(),
+            },
+        );
+        // Levels and positions aren't membership
+        timeline.record_at(
+            start + Duration::from_secs(6),
+            &SessionEvent::AudioLevel {
+                peer_id: "a1b2c3d4e5".to_string(),
+                level: 0.5,
+            },
+        );
+        timeline.record_at(
+            start + Duration::from_secs(65),
+            &SessionEvent::RecordingChanged {
+                peer_id: "a1b2c3d4e5".to_string(),
+                name: "Alice".to_string(),
+                recording: true,
+            },
+        );
+        timeline.record_at(
+            start + Duration::from_secs(90),
+            &SessionEvent::PeerLeft {
+                peer_id: "a1b2c3d4e5".to_string(),
+            },
+        );
+        timeline.record_at(
+            start + Duration::from_secs(91),
+            &SessionEvent::PeerLeft {
+                peer_id: "f6a7b8c9d0e1".to_string(),
+            },
+        );
+
+        let entries = timeline.entries();
+        assert_eq!(entries.len(), 5);
+        assert_eq!(entries[3].event.to_string(), "Alice left");
+        assert_eq!(entries[3].offset, Duration::from_secs(90));
+        // Someone we never saw join is known by their id
+        assert_eq!(entries[4].event.to_string(), "f6a7b8c9 left");
+
+        let report = timeline.report();
+        assert!(report.contains("started: 2026-10-16 14:00:00 UTC"));
+        assert!(report.contains("2026-10-16 14:01:05 UTC  +   1:05  Alice started recording"));
+
+        // After we leave, the next room's first event starts a new timeline
+        timeline.finish();
+        assert_eq!(
+            timeline.entries().last().unwrap().event,
+            TimelineEvent::Left
+        );
+        timeline.record_at(
+            start + Duration::from_secs(600),
+            &SessionEvent::HostChanged {
+                peer_id: "f6a7b8c9d0e1".to_string(),
+            },
+        );
+        let entries = timeline.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].event, TimelineEvent::Entered);
+    }
+}
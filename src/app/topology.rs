@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use tokio::runtime::Handle;
+use tokio::task::JoinHandle;
+
+use crate::audio::AudioRuntime;
+
+/// Where a task runs, from most to least urgent
+///
+/// Media tasks get the audio runtime to themselves, so a busy control plane
+/// (many peers, a slow handshake) can't delay a frame. Control tasks run on
+/// the main runtime with network I/O, and the UI is drawn from the main
+/// thread between them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Plane {
+    /// Capture, mixing and playback, on the audio runtime and device threads
+    Media,
+    /// Connections, signaling and the session, on the main runtime
+    Control,
+    /// The terminal, on the main thread
+    Ui,
+}
+
+impl fmt::Display for Plane {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Plane::Media => write!(f, "media"),
+            Plane::Control => write!(f, "control"),
+            Plane::Ui => write!(f, "ui"),
+        }
+    }
+}
+
+/// A live task as [`runtime_report`] lists it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskReport {
+    pub name: &'static str,
+    pub plane: Plane,
+    /// How long the task has been running
+    pub age: Duration,
+    /// Items waiting in the task's queue, for tasks fed by one
+    pub queue_depth: Option<usize>,
+}
+
+impl fmt::Display for TaskReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:<8} {:<24} {:>6}s",
+            self.plane,
+            self.name,
+            self.age.as_secs()
+        )?;
+        if let Some(depth) = self.queue_depth {
+            write!(f, " queued {}", depth)?;
+        }
+        Ok(())
+    }
+}
+
+type QueueDepth = Box<dyn Fn() -> usize + Send + Sync>;
+
+struct Entry {
+    name: &'static str,
+    plane: Plane,
+    started: Instant,
+    queue: Option<QueueDepth>,
+}
+
+fn tasks() -> &'static Mutex<HashMap<u64, Entry>> {
+    static TASKS: OnceLock<Mutex<HashMap<u64, Entry>>> = OnceLock::new();
+    TASKS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+static CONTROL: OnceLock<Handle> = OnceLock::new();
+
+/// Keeps a task listed in [`runtime_report`] until it's dropped
+pub struct Registration(u64);
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        tasks().lock().unwrap().remove(&self.0);
+    }
+}
+
+/// Lists a task, thread or loop that wasn't started by one of the spawn helpers
+pub fn register(plane: Plane, name: &'static str, queue: Option<QueueDepth>) -> Registration {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    tasks().lock().unwrap().insert(
+        id,
+        Entry {
+            name,
+            plane,
+            started: Instant::now(),
+            queue,
+        },
+    );
+    Registration(id)
+}
+
+/// Makes the calling runtime the control plane's
+///
+/// Called once from `main`. Without it control tasks go to whichever
+/// runtime spawns them, which from a media task is the audio runtime.
+pub fn set_control_runtime(handle: Handle) {
+    if CONTROL.set(handle).is_err() {
+        log::warn!("The control runtime was already set");
+    }
+}
+
+/// Runs a task on its plane's runtime and lists it until it finishes
+pub fn spawn<F>(plane: Plane, name: &'static str, future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    spawn_registered(plane, register(plane, name, None), future)
+}
+
+/// Like [`spawn`], for a task fed by a queue whose depth `queue` reads
+pub fn spawn_queued<F, Q>(
+    plane: Plane,
+    name: &'static str,
+    queue: Q,
+    future: F,
+) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+    Q: Fn() -> usize + Send + Sync + 'static,
+{
+    spawn_registered(plane, register(plane, name, Some(Box::new(queue))), future)
+}
+
+fn spawn_registered<F>(plane: Plane, registration: Registration, future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    // The registration moves into the task, so it's dropped with it even
+    // if the task is aborted before it first runs
+    let future = async move {
+        let _registration = registration;
+        future.await
+    };
+    match plane {
+        Plane::Media => AudioRuntime::shared().spawn(future),
+        Plane::Control | Plane::Ui => match CONTROL.get() {
+            Some(handle) => handle.spawn(future),
+            None => tokio::spawn(future),
+        },
+    }
+}
+
+/// Starts a named thread on a plane, listed until it returns
+///
+/// Device threads that block in their own loop belong here rather than
+/// on a runtime, where they would hold a worker.
+pub fn spawn_thread<F, T>(
+    plane: Plane,
+    name: &'static str,
+    f: F,
+) -> std::io::Result<std::thread::JoinHandle<T>>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let registration = register(plane, name, None);
+    std::thread::Builder::new()
+        .name(format!("resonance-{}", name))
+        .spawn(move || {
+            let _registration = registration;
+            f()
+        })
+}
+
+/// Every live task, media first, with how long it has run and its queue depth
+pub fn runtime_report() -> Vec<TaskReport> {
+    let now = Instant::now();
+    let mut report: Vec<TaskReport> = tasks()
+        .lock()
+        .unwrap()
+        .values()
+        .map(|entry| TaskReport {
+            name: entry.name,
+            plane: entry.plane,
+            age: now.saturating_duration_since(entry.started),
+            queue_depth: entry.queue.as_ref().map(|depth| depth()),
+        })
+        .collect();
+    report.sort_by(|a, b| {
+        (a.plane, a.name)
+            .cmp(&(b.plane, b.name))
+            .then(b.age.cmp(&a.age))
+    });
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn test_report_lists_live_tasks_on_their_planes() {
+        let (tx, _rx) = mpsc::channel::<()>(4);
+        let depth = tx.clone();
+        tx.send(()).await.unwrap();
+        tx.send(()).await.unwrap();
+
+        let (thread_tx, thread_rx) = tokio::sync::oneshot::channel();
+        let media = spawn_queued(
+            Plane::Media,
+            "test-media",
+            move || depth.max_capacity() - depth.capacity(),
+            async move {
+                let _ = thread_tx.send(std::thread::current().name().map(str::to_string));
+                std::future::pending::<()>().await
+            },
+        );
+        assert_eq!(thread_rx.await.unwrap().as_deref(), Some("resonance-audio"));
+
+        let report = runtime_report();
+        let listed = report
+            .iter()
+            .find(|task| task.name == "test-media")
+            .unwrap();
+        assert_eq!(listed.plane, Plane::Media);
+        assert_eq!(listed.queue_depth, Some(2));
+
+        // Aborted tasks leave the report
+        media.abort();
+        let _ = media.await;
+        assert!(!runtime_report()
+            .iter()
+            .any(|task| task.name == "test-media"));
+    }
+}
@@ -0,0 +1,195 @@
+use anyhow::Result;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::app::events::SessionEvent;
+use crate::app::session_timer::unix_now;
+use crate::app::topology::{self, Plane};
+use crate::network::http_request;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Header carrying `sha256=` and the hex HMAC-SHA256 of the body, keyed with the secret
+pub const SIGNATURE_HEADER: &str = "X-Resonance-Signature";
+
+/// A room event posted to the webhook
+///
+/// Join requests are only announced; the operator's system lets people in
+/// with the `approve` control command, or turns them away with `deny_all`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum WebhookEvent {
+    PeerJoined {
+        peer_id: String,
+        name: String,
+    },
+    PeerLeft {
+        peer_id: String,
+    },
+    JoinRequested {
+        peer_id: String,
+        name: String,
+    },
+    /// We left the room, or shut down while in one
+    RoomClosed,
+}
+
+impl WebhookEvent {
+    /// The webhook event for a session event, if it's one webhooks hear about
+    pub fn from_session_event(event: &SessionEvent) -> Option<Self> {
+        match event {
+            SessionEvent::PeerJoined { peer_id, name } => Some(WebhookEvent::PeerJoined {
+                peer_id: peer_id.clone(),
+                name: name.clone(),
+            }),
+            SessionEvent::PeerLeft { peer_id } => Some(WebhookEvent::PeerLeft {
+                peer_id: peer_id.clone(),
+            }),
+            SessionEvent::JoinRequested { peer_id, name } => Some(WebhookEvent::JoinRequested {
+                peer_id: peer_id.clone(),
+                name: name.clone(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// What's posted: the event, plus the room and when it happened
+#[derive(Serialize)]
+struct Payload<'a> {
+    #[serde(flatten)]
+    event: &'a WebhookEvent,
+    session_id: Option<&'a str>,
+    /// Unix time, so receivers can refuse old deliveries replayed to them
+    timestamp: u64,
+}
+
+/// Posts room events as JSON to an operator's URL
+///
+/// Only `http://` URLs work, as there's no TLS client; point it at a
+/// receiver on the same machine or network. With a secret, each body is
+/// signed so the receiver can tell the post came from us.
+#[derive(Debug, Clone)]
+pub struct Webhook {
+    url: String,
+    secret: Option<String>,
+}
+
+impl Webhook {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            secret: None,
+        }
+    }
+
+    pub fn with_secret(mut self, secret: Option<String>) -> Self {
+        self.secret = secret;
+        self
+    }
+
+    /// The JSON body for an event
+    pub fn body(event: &WebhookEvent, session_id: Option<&str>, timestamp: u64) -> String {
+        let payload = Payload {
+            event,
+            session_id,
+            timestamp,
+        };
+        serde_json::to_string(&payload).unwrap_or_default()
+    }
+
+    /// The signature header's value for a body, if there's a secret to sign with
+    pub fn sign(&self, body: &str) -> Option<String> {
+        let secret = self.secret.as_ref()?;
+        let mut mac =
+            HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+        mac.update(body.as_bytes());
+        let hex: String = mac
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        Some(format!("sha256={}", hex))
+    }
+
+    /// Posts one body to the URL
+    pub async fn post(&self, body: &str) -> Result<()> {
+        let signature = self.sign(body);
+        let mut headers = vec![("Content-Type", "application/json")];
+        if let Some(signature) = &signature {
+            headers.push((SIGNATURE_HEADER, signature.as_str()));
+        }
+        http_request(&self.url, "POST", &headers, body).await?;
+        Ok(())
+    }
+
+    /// Starts posting events in the background, one at a time in order
+    ///
+    /// A post that fails is logged and dropped, so a receiver that's down
+    /// doesn't hold up the room.
+    pub fn spawn(self) -> WebhookQueue {
+        let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+        let task = topology::spawn(Plane::Control, "webhook", async move {
+            while let Some(body) = rx.recv().await {
+                if let Err(e) = self.post(&body).await {
+                    log::warn!("Webhook to {} failed: {}", self.url, e);
+                }
+            }
+        });
+        WebhookQueue { tx, task }
+    }
+}
+
+/// Events waiting to be posted to a webhook
+pub struct WebhookQueue {
+    tx: mpsc::UnboundedSender<String>,
+    task: JoinHandle<()>,
+}
+
+impl WebhookQueue {
+    /// Queues an event, stamped with the current time
+    pub fn send(&self, event: &WebhookEvent, session_id: Option<&str>) {
+        let _ = self.tx.send(Webhook::body(event, session_id, unix_now()));
+    }
+
+    /// Waits for the queued events to be posted, such as before exiting
+    pub async fn close(self) {
+        drop(self.tx);
+        let _ = self.task.await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_webhook_body_and_signature() {
+        let event = WebhookEvent::from_session_event(&SessionEvent::JoinRequested {
+            peer_id: "peer-1".to_string(),
+            name: "Alice".to_string(),
+        })
+        .unwrap();
+        let body = Webhook::body(&event, Some("room"), 1_700_000_000);
+        assert_eq!(
+            body,
+            r#"{"event":"join_requested","peer_id":"peer-1","name":"Alice","session_id":"room","timestamp":1700000000}"#
+        );
+        assert_eq!(
+            Webhook::body(&WebhookEvent::RoomClosed, None, 5),
+            r#"{"event":"room_closed","session_id":null,"timestamp":5}"#
+        );
+
+        // HMAC-SHA256 test vector from RFC 4231, case 2
+        let webhook = Webhook::new("http://127.0.0.1:8080/hook").with_secret(Some("Jefe".into()));
+        assert_eq!(
+            webhook.sign("what do ya want for nothing?").as_deref(),
+            Some("sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843")
+        );
+        assert_eq!(Webhook::new("http://127.0.0.1:8080/hook").sign(&body), None);
+    }
+}
@@ -0,0 +1,172 @@
+use super::conversions::{downmix_to_mono, upmix_from_mono};
+use super::{AudioFrame, DeviceCapabilities};
+
+/// Rate used when every device involved supports it
+const PREFERRED_SAMPLE_RATE: u32 = 48000;
+
+/// Sample rate and channel count audio is processed and sent at
+///
+/// Resolved at startup from what the devices support rather than fixed at
+/// compile time. Frames carry their own format, so audio from a peer
+/// running at another rate is conformed to ours when it arrives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioConfig {
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+impl Default for AudioConfig {
+    /// Mono speech at 48 kHz
+    fn default() -> Self {
+        Self {
+            sample_rate: PREFERRED_SAMPLE_RATE,
+            channels: 1,
+        }
+    }
+}
+
+impl AudioConfig {
+    /// Picks a config both the input and output device can run at
+    ///
+    /// 48 kHz if both support it, otherwise the highest rate they share,
+    /// otherwise the input's own rate. Devices that couldn't be probed
+    /// don't narrow the choice.
+    pub fn resolve(
+        input: Option<&DeviceCapabilities>,
+        output: Option<&DeviceCapabilities>,
+    ) -> Self {
+        let supports = |capabilities: Option<&DeviceCapabilities>, rate: u32| {
+            capabilities.is_none_or(|capabilities| {
+                capabilities.sample_rates.is_empty() || capabilities.sample_rates.contains(&rate)
+            })
+        };
+
+        let shared = input
+            .into_iter()
+            .chain(output)
+            .flat_map(|capabilities| capabilities.sample_rates.iter().copied())
+            .filter(|&rate| supports(input, rate) && supports(output, rate))
+            .max();
+
+        let sample_rate =
+            if supports(input, PREFERRED_SAMPLE_RATE) && supports(output, PREFERRED_SAMPLE_RATE) {
+                PREFERRED_SAMPLE_RATE
+            } else {
+                shared
+                    .or_else(|| input.and_then(|capabilities| capabilities.default_sample_rate))
+                    .unwrap_or(PREFERRED_SAMPLE_RATE)
+            };
+
+        Self {
+            sample_rate,
+            ..Self::default()
+        }
+    }
+
+    /// Samples in `millis` of audio, across all channels
+    pub fn frame_len(&self, millis: u32) -> usize {
+        (self.sample_rate as u64 * millis as u64 / 1000) as usize * self.channels as usize
+    }
+
+    /// Converts a frame to this config's rate and channel count
+    pub fn conform(&self, mut frame: AudioFrame) -> AudioFrame {
+        if frame.sample_rate == self.sample_rate && frame.channels == self.channels {
+            return frame;
+        }
+
+        let mono = downmix_to_mono(&frame.samples, frame.channels);
+        let mono = resample(&mono, frame.sample_rate, self.sample_rate);
+
+        frame.samples = upmix_from_mono(&mono, self.channels);
+        frame.sample_rate = self.sample_rate;
+        frame.channels = self.channels;
+        frame
+    }
+}
+
+/// Linear resampling of mono audio
+pub(crate) fn resample(samples: &[f32], from: u32, to: u32) -> Vec<f32> {
+    if from == to || samples.len() < 2 {
+        return samples.to_vec();
+    }
+
+    let len = (samples.len() as u64 * to as u64 / from as u64) as usize;
+    let step = from as f64 / to as f64;
+    (0..len)
+        .map(|i| {
+            let at = i as f64 * step;
+            let index = (at as usize).min(samples.len() - 2);
+            let frac = (at - index as f64) as f32;
+            samples[index] * (1.0 - frac) + samples[index + 1] * frac
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::ExclusiveMode;
+
+    fn device(rates: &[u32], default: u32) -> DeviceCapabilities {
+        DeviceCapabilities {
+            device: crate::audio::capture::AudioDevice {
+                id: "device".to_string(),
+                name: "Device".to_string(),
+                is_input: true,
+            },
+            sample_rates: rates.to_vec(),
+            default_sample_rate: Some(default),
+            min_buffer_frames: None,
+            exclusive: ExclusiveMode::SharedOnly,
+            channel_counts: vec![2],
+            fingerprint: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_resolve_prefers_48k_then_shared_rates() {
+        assert_eq!(AudioConfig::resolve(None, None), AudioConfig::default());
+
+        let both = device(&[44100, 48000], 44100);
+        assert_eq!(
+            AudioConfig::resolve(Some(&both), Some(&both)).sample_rate,
+            48000
+        );
+
+        let input = device(&[16000, 44100], 44100);
+        let output = device(&[44100, 96000], 96000);
+        assert_eq!(
+            AudioConfig::resolve(Some(&input), Some(&output)).sample_rate,
+            44100
+        );
+
+        // Nothing in common: run at the microphone's rate
+        let output = device(&[96000], 96000);
+        assert_eq!(
+            AudioConfig::resolve(Some(&input), Some(&output)).sample_rate,
+            44100
+        );
+    }
+
+    #[test]
+    fn test_conform_downmixes_and_resamples() {
+        let config = AudioConfig::default();
+        let stereo = AudioFrame::new([0.2, 0.4].repeat(441), 44100, 2);
+        let conformed = config.conform(stereo);
+
+        assert_eq!(conformed.sample_rate, 48000);
+        assert_eq!(conformed.channels, 1);
+        assert_eq!(conformed.samples.len(), 480);
+        assert!(conformed.samples.iter().all(|s| (s - 0.3).abs() < 1e-6));
+        assert_eq!(config.frame_len(20), 960);
+    }
+
+    #[test]
+    fn test_resample_keeps_duration() {
+        let samples: Vec<f32> = (0..441).map(|i| i as f32).collect();
+        let resampled = resample(&samples, 44100, 48000);
+        assert_eq!(resampled.len(), 480);
+        assert_eq!(resampled[0], 0.0);
+        assert!(resampled.windows(2).all(|pair| pair[1] >= pair[0]));
+    }
+}
@@ -0,0 +1,321 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// How long the quiet part of the calibration runs
+pub const QUIET_PHASE: Duration = Duration::from_secs(4);
+
+/// How long the speaking part of the calibration runs
+pub const SPEAK_PHASE: Duration = Duration::from_secs(6);
+
+/// Device key used when no input device is configured
+pub const DEFAULT_DEVICE_ID: &str = "default";
+
+/// Speaking level (RMS) calibrated voices are brought to
+pub const TARGET_SPEECH_LEVEL: f32 = 0.1;
+
+/// Lowest gap between speech and room noise the calibration accepts (about 6 dB)
+const MIN_SPEECH_TO_NOISE: f32 = 2.0;
+
+/// Range of input gain the calibration suggests
+const GAIN_RANGE: (f32, f32) = (0.25, 8.0);
+
+/// Length of the blocks levels are measured over, in milliseconds
+const BLOCK_MS: u32 = 20;
+
+/// Input settings measured for one microphone
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Calibration {
+    /// Gain applied to the microphone before anything else
+    pub input_gain: f32,
+    /// Level (RMS, after input gain) below which the microphone is gated
+    pub gate_threshold: f32,
+    /// Speaking level (RMS) automatic gain control aims for
+    pub agc_target: f32,
+}
+
+impl Default for Calibration {
+    /// Leaves the microphone as it is
+    fn default() -> Self {
+        Self {
+            input_gain: 1.0,
+            gate_threshold: 0.0,
+            agc_target: TARGET_SPEECH_LEVEL,
+        }
+    }
+}
+
+impl Calibration {
+    /// Derives settings from the room's noise floor and the speaker's level (both RMS)
+    pub fn from_levels(noise_floor: f32, speech_level: f32) -> Result<Self, CalibrationError> {
+        if speech_level <= f32::EPSILON {
+            return Err(CalibrationError::NoSignal);
+        }
+        if speech_level < noise_floor * MIN_SPEECH_TO_NOISE {
+            return Err(CalibrationError::TooNoisy);
+        }
+
+        let input_gain = (TARGET_SPEECH_LEVEL / speech_level).clamp(GAIN_RANGE.0, GAIN_RANGE.1);
+
+        // Halfway between noise and speech on a log scale, so quiet words still open the gate
+        let gate_threshold = (noise_floor * speech_level).sqrt() * input_gain;
+
+        Ok(Self {
+            input_gain,
+            gate_threshold,
+            agc_target: TARGET_SPEECH_LEVEL,
+        })
+    }
+}
+
+impl fmt::Display for Calibration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{},{},{}",
+            self.input_gain, self.gate_threshold, self.agc_target
+        )
+    }
+}
+
+impl FromStr for Calibration {
+    type Err = String;
+
+    /// Parses `gain,gate,agc_target`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values: Vec<f32> = s
+            .split(',')
+            .map(|value| value.trim().parse::<f32>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("Invalid calibration: {}", s))?;
+
+        match values[..] {
+            [input_gain, gate_threshold, agc_target] => Ok(Self {
+                input_gain,
+                gate_threshold,
+                agc_target,
+            }),
+            _ => Err(format!("Invalid calibration: {}", s)),
+        }
+    }
+}
+
+/// Why a calibration couldn't be used
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum CalibrationError {
+    #[error("no sound reached the microphone")]
+    NoSignal,
+    #[error("speech wasn't clearly louder than the room")]
+    TooNoisy,
+}
+
+/// Calibrations by input device ID
+///
+/// Written as `device=gain,gate,agc_target` entries separated by `;`. Device
+/// names may contain `=`, so each entry is split at its last one.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeviceCalibrations {
+    devices: BTreeMap<String, Calibration>,
+}
+
+impl DeviceCalibrations {
+    pub fn get(&self, device_id: &str) -> Option<&Calibration> {
+        self.devices.get(device_id)
+    }
+
+    pub fn insert(&mut self, device_id: &str, calibration: Calibration) {
+        self.devices.insert(device_id.to_string(), calibration);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.devices.is_empty()
+    }
+}
+
+// Floats don't implement Eq, but calibrations are never NaN
+impl Eq for Calibration {}
+impl Eq for DeviceCalibrations {}
+
+impl fmt::Display for DeviceCalibrations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries: Vec<String> = self
+            .devices
+            .iter()
+            .map(|(device, calibration)| format!("{}={}", device, calibration))
+            .collect();
+        write!(f, "{}", entries.join(";"))
+    }
+}
+
+impl FromStr for DeviceCalibrations {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut calibrations = DeviceCalibrations::default();
+        for entry in s.split(';').filter(|entry| !entry.trim().is_empty()) {
+            let (device, calibration) = entry
+                .rsplit_once('=')
+                .ok_or_else(|| format!("Invalid device calibration: {}", entry))?;
+            calibrations.insert(device.trim(), calibration.parse()?);
+        }
+        Ok(calibrations)
+    }
+}
+
+/// Which part of the guided test is running
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalibrationPhase {
+    /// The user stays quiet while the room's noise is measured
+    Quiet,
+    /// The user speaks normally while their level is measured
+    Speak,
+    /// Enough audio has been measured
+    Done,
+}
+
+/// Measures the noise floor and speaking level over a guided test
+///
+/// Captured audio is fed in as it arrives. The first [`QUIET_PHASE`] is
+/// taken as room noise and the following [`SPEAK_PHASE`] as speech.
+pub struct CalibrationRun {
+    block_len: usize,
+    quiet_blocks: usize,
+    speak_blocks: usize,
+    pending: Vec<f32>,
+    quiet_levels: Vec<f32>,
+    speech_levels: Vec<f32>,
+}
+
+impl CalibrationRun {
+    /// Starts a run for mono audio at `sample_rate`
+    pub fn new(sample_rate: u32) -> Self {
+        let block_len = (sample_rate * BLOCK_MS / 1000).max(1) as usize;
+        let blocks = |phase: Duration| (phase.as_millis() as u32 / BLOCK_MS) as usize;
+        Self {
+            block_len,
+            quiet_blocks: blocks(QUIET_PHASE),
+            speak_blocks: blocks(SPEAK_PHASE),
+            pending: Vec::with_capacity(block_len),
+            quiet_levels: Vec::new(),
+            speech_levels: Vec::new(),
+        }
+    }
+
+    /// Adds captured samples
+    pub fn push(&mut self, samples: &[f32]) {
+        for &sample in samples {
+            if self.phase() == CalibrationPhase::Done {
+                return;
+            }
+
+            self.pending.push(sample);
+            if self.pending.len() == self.block_len {
+                let level = rms(&self.pending);
+                self.pending.clear();
+                if self.quiet_levels.len() < self.quiet_blocks {
+                    self.quiet_levels.push(level);
+                } else {
+                    self.speech_levels.push(level);
+                }
+            }
+        }
+    }
+
+    pub fn phase(&self) -> CalibrationPhase {
+        if self.quiet_levels.len() < self.quiet_blocks {
+            CalibrationPhase::Quiet
+        } else if self.speech_levels.len() < self.speak_blocks {
+            CalibrationPhase::Speak
+        } else {
+            CalibrationPhase::Done
+        }
+    }
+
+    /// Share of the whole test measured so far (0.0 - 1.0)
+    pub fn progress(&self) -> f32 {
+        let measured = self.quiet_levels.len() + self.speech_levels.len();
+        measured as f32 / (self.quiet_blocks + self.speak_blocks).max(1) as f32
+    }
+
+    /// Works out the settings from what was measured
+    ///
+    /// The noise floor is the median quiet block, so a cough doesn't count,
+    /// and the speaking level the 90th percentile, so pauses between words
+    /// don't either.
+    pub fn finish(&self) -> Result<Calibration, CalibrationError> {
+        let noise_floor = percentile(&self.quiet_levels, 0.5);
+        let speech_level = percentile(&self.speech_levels, 0.9);
+        Calibration::from_levels(noise_floor, speech_level)
+    }
+}
+
+fn rms(samples: &[f32]) -> f32 {
+    (samples.iter().map(|sample| sample * sample).sum::<f32>() / samples.len().max(1) as f32).sqrt()
+}
+
+fn percentile(levels: &[f32], at: f32) -> f32 {
+    if levels.is_empty() {
+        return 0.0;
+    }
+    let mut sorted = levels.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    sorted[((sorted.len() - 1) as f32 * at).round() as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(level: f32, len: usize) -> Vec<f32> {
+        // A square wave's RMS is its amplitude
+        (0..len)
+            .map(|i| if i % 2 == 0 { level } else { -level })
+            .collect()
+    }
+
+    #[test]
+    fn test_guided_run_measures_both_phases() {
+        let mut run = CalibrationRun::new(8000);
+        assert_eq!(run.phase(), CalibrationPhase::Quiet);
+
+        run.push(&tone(0.002, 8000 * 4));
+        assert_eq!(run.phase(), CalibrationPhase::Speak);
+        assert!((run.progress() - 0.4).abs() < 0.01);
+
+        run.push(&tone(0.05, 8000 * 7));
+        assert_eq!(run.phase(), CalibrationPhase::Done);
+
+        let calibration = run.finish().unwrap();
+        assert!((calibration.input_gain - 2.0).abs() < 1e-3);
+        assert!((calibration.gate_threshold - 0.02).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_rejects_unusable_runs() {
+        assert_eq!(
+            Calibration::from_levels(0.0, 0.0),
+            Err(CalibrationError::NoSignal)
+        );
+        assert_eq!(
+            Calibration::from_levels(0.05, 0.06),
+            Err(CalibrationError::TooNoisy)
+        );
+
+        // Very quiet microphones get as much gain as is sensible, no more
+        let quiet = Calibration::from_levels(0.0001, 0.001).unwrap();
+        assert_eq!(quiet.input_gain, GAIN_RANGE.1);
+    }
+
+    #[test]
+    fn test_device_calibrations_round_trip() {
+        let mut calibrations = DeviceCalibrations::default();
+        calibrations.insert("default", Calibration::from_levels(0.002, 0.05).unwrap());
+        calibrations.insert("USB Mic=2", Calibration::default());
+
+        let parsed: DeviceCalibrations = calibrations.to_string().parse().unwrap();
+        assert_eq!(parsed.get("USB Mic=2"), Some(&Calibration::default()));
+        assert!((parsed.get("default").unwrap().input_gain - 2.0).abs() < 1e-3);
+        assert!("mic=loud".parse::<DeviceCalibrations>().is_err());
+    }
+}
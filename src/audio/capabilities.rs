@@ -0,0 +1,192 @@
+use cpal::traits::{DeviceTrait, HostTrait};
+use cpal::SupportedBufferSize;
+use std::time::Duration;
+
+use super::capture::{AudioDevice, AudioDeviceManager, AudioError};
+use super::fingerprint::DeviceFingerprint;
+
+/// Sample rates checked against each device's supported ranges
+const COMMON_SAMPLE_RATES: [u32; 8] = [8000, 16000, 22050, 32000, 44100, 48000, 96000, 192000];
+
+/// Whether a device can be opened for exclusive, lowest-latency use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExclusiveMode {
+    /// The app talks to the hardware directly, bypassing the system mixer
+    Available,
+    /// Streams go through the system mixer, shared with other apps
+    SharedOnly,
+}
+
+/// What an audio device can do, for choosing between devices
+#[derive(Debug, Clone)]
+pub struct DeviceCapabilities {
+    pub device: AudioDevice,
+    /// Common sample rates the device supports, lowest first
+    pub sample_rates: Vec<u32>,
+    /// Rate the device runs at unless asked otherwise
+    pub default_sample_rate: Option<u32>,
+    /// Smallest buffer the driver accepts, in frames, if it says
+    pub min_buffer_frames: Option<u32>,
+    pub exclusive: ExclusiveMode,
+    /// Channel counts the device offers, fewest first
+    pub channel_counts: Vec<u16>,
+    /// Recognises the device again after it's renamed
+    pub fingerprint: DeviceFingerprint,
+}
+
+impl DeviceCapabilities {
+    /// Lowest latency one buffer adds, at the default sample rate
+    pub fn min_latency(&self) -> Option<Duration> {
+        let rate = self
+            .default_sample_rate
+            .or_else(|| self.sample_rates.last().copied())?;
+        let frames = self.min_buffer_frames?;
+        Some(Duration::from_secs_f64(frames as f64 / rate as f64))
+    }
+}
+
+/// Probes what `device` supports
+pub fn probe_device(device: &AudioDevice) -> Result<DeviceCapabilities, AudioError> {
+    let host = cpal::default_host();
+    let mut devices = if device.is_input {
+        host.input_devices()
+    } else {
+        host.output_devices()
+    }
+    .map_err(|e| AudioError::new(&format!("Failed to get devices: {}", e)))?;
+
+    let cpal_device = devices
+        .find(|d| d.name().is_ok_and(|name| name == device.id))
+        .ok_or_else(|| AudioError::new(&format!("Device not found: {}", device.id)))?;
+
+    let (ranges, default_config) = if device.is_input {
+        (
+            cpal_device
+                .supported_input_configs()
+                .map(|configs| configs.collect::<Vec<_>>()),
+            cpal_device.default_input_config().ok(),
+        )
+    } else {
+        (
+            cpal_device
+                .supported_output_configs()
+                .map(|configs| configs.collect::<Vec<_>>()),
+            cpal_device.default_output_config().ok(),
+        )
+    };
+    let ranges =
+        ranges.map_err(|e| AudioError::new(&format!("Failed to get device configs: {}", e)))?;
+
+    let rate_ranges: Vec<(u32, u32)> = ranges
+        .iter()
+        .map(|range| (range.min_sample_rate().0, range.max_sample_rate().0))
+        .collect();
+    let min_buffer_frames = ranges
+        .iter()
+        .filter_map(|range| match range.buffer_size() {
+            SupportedBufferSize::Range { min, .. } => Some(*min),
+            SupportedBufferSize::Unknown => None,
+        })
+        .min();
+    let mut channel_counts: Vec<u16> = ranges.iter().map(|range| range.channels()).collect();
+    channel_counts.sort_unstable();
+    channel_counts.dedup();
+    let host_name = host.id().name();
+    let fingerprint =
+        DeviceFingerprint::new(device.is_input, host_name, &device.id, &channel_counts);
+
+    Ok(DeviceCapabilities {
+        device: device.clone(),
+        sample_rates: rates_in_ranges(&rate_ranges),
+        default_sample_rate: default_config.map(|config| config.sample_rate().0),
+        min_buffer_frames,
+        exclusive: exclusive_mode(host_name, &device.id),
+        channel_counts,
+        fingerprint,
+    })
+}
+
+/// Probes every device, leaving out those that can't be queried
+pub fn probe_devices() -> Vec<DeviceCapabilities> {
+    AudioDeviceManager::enumerate_devices()
+        .iter()
+        .filter_map(|device| match probe_device(device) {
+            Ok(capabilities) => Some(capabilities),
+            Err(e) => {
+                log::debug!("Couldn't probe {}: {}", device, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Common rates that fall inside any of the supported `(min, max)` ranges
+fn rates_in_ranges(ranges: &[(u32, u32)]) -> Vec<u32> {
+    COMMON_SAMPLE_RATES
+        .iter()
+        .copied()
+        .filter(|rate| ranges.iter().any(|(min, max)| (min..=max).contains(&rate)))
+        .collect()
+}
+
+/// Whether devices on `host` can be opened exclusively
+///
+/// WASAPI and CoreAudio devices are opened in shared mode, through the
+/// system mixer. ASIO drivers and ALSA `hw:` devices are used directly.
+fn exclusive_mode(host: &str, device_id: &str) -> ExclusiveMode {
+    match host {
+        "ASIO" => ExclusiveMode::Available,
+        "ALSA" if device_id.starts_with("hw:") => ExclusiveMode::Available,
+        _ => ExclusiveMode::SharedOnly,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rates_in_ranges() {
+        assert_eq!(
+            rates_in_ranges(&[(44100, 48000), (96000, 96000)]),
+            vec![44100, 48000, 96000]
+        );
+        assert!(rates_in_ranges(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_exclusive_mode_by_host() {
+        assert_eq!(
+            exclusive_mode("ASIO", "Focusrite USB"),
+            ExclusiveMode::Available
+        );
+        assert_eq!(
+            exclusive_mode("ALSA", "hw:CARD=PCH,DEV=0"),
+            ExclusiveMode::Available
+        );
+        assert_eq!(exclusive_mode("ALSA", "pulse"), ExclusiveMode::SharedOnly);
+        assert_eq!(
+            exclusive_mode("WASAPI", "Speakers"),
+            ExclusiveMode::SharedOnly
+        );
+    }
+
+    #[test]
+    fn test_min_latency_uses_default_rate() {
+        let capabilities = DeviceCapabilities {
+            device: AudioDevice {
+                id: "mic".to_string(),
+                name: "Mic".to_string(),
+                is_input: true,
+            },
+            sample_rates: vec![44100, 48000],
+            default_sample_rate: Some(48000),
+            min_buffer_frames: Some(240),
+            exclusive: ExclusiveMode::SharedOnly,
+            channel_counts: vec![1],
+            fingerprint: DeviceFingerprint::new(true, "ALSA", "mic", &[1]),
+        };
+        let latency = capabilities.min_latency().unwrap();
+        assert!((latency.as_secs_f64() - 0.005).abs() < 1e-6);
+    }
+}
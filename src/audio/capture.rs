@@ -1,15 +1,16 @@
-use cpal::{
-    self,
-    traits::{DeviceTrait, HostTrait, StreamTrait},
-    Sample,
-};
-use ringbuf::{Consumer, HeapRb, Producer};
+use ringbuf::{HeapConsumer, HeapProducer, HeapRb};
 use std::error::Error;
 use std::fmt;
-use std::sync::Arc;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, AtomicU16, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 
+use super::conversions::{i16_to_f32, u16_to_f32};
+use super::host::{default_host, AudioHost, HostStream, InputBuffer};
+use super::{AudioConfig, AudioFrame};
+use crate::app::topology::{self, Plane};
+
 // Define the required types
 #[derive(Debug, Clone)]
 pub struct AudioDevice {
@@ -35,7 +36,7 @@ pub struct AudioError {
 }
 
 impl AudioError {
-    fn new(message: &str) -> Self {
+    pub(super) fn new(message: &str) -> Self {
         Self {
             message: message.to_string(),
         }
@@ -55,6 +56,12 @@ pub struct AudioDeviceManager {
     current_output_device: Option<AudioDevice>,
 }
 
+impl Default for AudioDeviceManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl AudioDeviceManager {
     pub fn new() -> Self {
         Self {
@@ -64,35 +71,28 @@ impl AudioDeviceManager {
     }
 
     pub fn enumerate_devices() -> Vec<AudioDevice> {
-        let mut devices = Vec::new();
+        Self::devices_on(&*default_host())
+    }
 
-        // Use cpal to get actual audio devices
-        let host = cpal::default_host();
+    fn devices_on(host: &dyn AudioHost) -> Vec<AudioDevice> {
+        let mut devices = Vec::new();
 
         // Get input devices
-        if let Ok(input_devices) = host.input_devices() {
-            for device in input_devices {
-                if let Ok(name) = device.name() {
-                    devices.push(AudioDevice {
-                        id: name.clone(),
-                        name,
-                        is_input: true,
-                    });
-                }
-            }
+        for name in host.input_devices().unwrap_or_default() {
+            devices.push(AudioDevice {
+                id: name.clone(),
+                name,
+                is_input: true,
+            });
         }
 
         // Get output devices
-        if let Ok(output_devices) = host.output_devices() {
-            for device in output_devices {
-                if let Ok(name) = device.name() {
-                    devices.push(AudioDevice {
-                        id: name.clone(),
-                        name,
-                        is_input: false,
-                    });
-                }
-            }
+        for name in host.output_devices().unwrap_or_default() {
+            devices.push(AudioDevice {
+                id: name.clone(),
+                name,
+                is_input: false,
+            });
         }
 
         // If no devices were found, return mock devices for testing
@@ -145,25 +145,58 @@ impl AudioDeviceManager {
 
 // Define AudioCapture struct
 pub struct AudioCapture {
+    host: Arc<dyn AudioHost>,
     device: Option<AudioDevice>,
     is_active: bool,
-    data_tx: Option<mpsc::Sender<Vec<f32>>>,
+    data_tx: Option<mpsc::Sender<AudioFrame>>,
     cancel_token: Option<tokio::sync::oneshot::Sender<()>>,
-    #[allow(dead_code)]
-    audio_stream: Option<cpal::Stream>,
+    stream_thread: Option<std::thread::JoinHandle<()>>,
+    stream_stop: Option<Arc<AtomicBool>>,
+    stream_live: Option<Arc<AtomicBool>>,
+    consumer: Option<HeapConsumer<f32>>,
+    format: Arc<StreamFormat>,
+    stall_timeout: Duration,
+    diagnostics_tx: Option<mpsc::UnboundedSender<WatchdogEvent>>,
+}
+
+impl Default for AudioCapture {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl AudioCapture {
     pub fn new() -> Self {
         Self {
+            host: default_host(),
             device: None,
             is_active: false,
             data_tx: None,
             cancel_token: None,
-            audio_stream: None,
+            stream_thread: None,
+            stream_stop: None,
+            stream_live: None,
+            consumer: None,
+            format: Arc::new(StreamFormat::new()),
+            stall_timeout: Duration::from_secs(2),
+            diagnostics_tx: None,
         }
     }
 
+    /// Asks for the device to be opened at `sample_rate` when it supports it
+    ///
+    /// Takes effect the next time the stream is opened.
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.format
+            .requested_rate
+            .store(sample_rate, Ordering::Relaxed);
+    }
+
+    /// Sets how long the stream may go without callbacks before it is rebuilt
+    pub fn set_stall_timeout(&mut self, timeout: Duration) {
+        self.stall_timeout = timeout;
+    }
+
     pub fn set_device(&mut self, device: AudioDevice) -> Result<(), AudioError> {
         if !device.is_input {
             return Err(AudioError::new("Cannot capture from output device"));
@@ -174,37 +207,68 @@ impl AudioCapture {
 
     pub fn set_data_callback<F>(&mut self, callback: F)
     where
-        F: Fn(Vec<f32>) + Send + Sync + 'static,
+        F: Fn(AudioFrame) + Send + Sync + 'static,
     {
         // Create a channel for passing audio data
-        let (tx, mut rx) = mpsc::channel::<Vec<f32>>(100);
+        let (tx, mut rx) = mpsc::channel::<AudioFrame>(100);
+        let depth = tx.downgrade();
         self.data_tx = Some(tx);
 
-        // Spawn a task to listen for data and call the callback
+        // Call the callback from the audio runtime
         let callback = Arc::new(callback);
-        tokio::spawn(async move {
+        let queued = move || {
+            depth
+                .upgrade()
+                .map_or(0, |tx| tx.max_capacity() - tx.capacity())
+        };
+        topology::spawn_queued(Plane::Media, "capture-callback", queued, async move {
             while let Some(data) = rx.recv().await {
                 callback(data);
             }
         });
     }
 
+    /// Sets a callback for watchdog diagnostics (stalls and stream rebuilds)
+    pub fn set_diagnostics_callback<F>(&mut self, callback: F)
+    where
+        F: Fn(WatchdogEvent) + Send + Sync + 'static,
+    {
+        let (tx, mut rx) = mpsc::unbounded_channel::<WatchdogEvent>();
+        self.diagnostics_tx = Some(tx);
+
+        topology::spawn(Plane::Control, "capture-diagnostics", async move {
+            while let Some(event) = rx.recv().await {
+                callback(event);
+            }
+        });
+    }
+
+    /// Opens the capture stream ahead of time but keeps it paused
+    ///
+    /// Opening a device can take hundreds of milliseconds; a later call to
+    /// `start` on a pre-warmed capture only has to resume the stream.
+    pub async fn prewarm(&mut self) -> Result<(), AudioError> {
+        if self.is_active || self.stream_thread.is_some() {
+            return Ok(());
+        }
+
+        self.open_stream(false)
+    }
+
+    /// Returns whether the stream is open but not yet capturing
+    pub fn is_prewarmed(&self) -> bool {
+        !self.is_active && self.stream_thread.is_some()
+    }
+
     pub async fn start(&mut self) -> Result<(), AudioError> {
         if self.is_active {
             return Err(AudioError::new("Audio capture already started"));
         }
 
-        // Ensure we have a device
-        if self.device.is_none() {
-            // If no device is set, use the default
-            let devices = AudioDeviceManager::enumerate_devices();
-            let default_input = devices.iter().find(|d| d.is_input).cloned();
-
-            if let Some(device) = default_input {
-                self.device = Some(device);
-            } else {
-                return Err(AudioError::new("No input device available"));
-            }
+        // Reuse a pre-warmed stream if there is one
+        match &self.stream_live {
+            Some(live) => live.store(true, Ordering::SeqCst),
+            None => self.open_stream(true)?,
         }
 
         // Create a cancel channel
@@ -213,122 +277,18 @@ impl AudioCapture {
 
         // Clone the data tx for the capture task
         let data_tx = self.data_tx.clone();
+        let format = self.format.clone();
 
-        // Set up real microphone capture using cpal
-        let host = cpal::default_host();
-        let device_name = self
-            .device
-            .as_ref()
-            .map(|d| d.id.clone())
-            .unwrap_or_default();
-
-        // Try to find the device by name, or use default input device
-        let device = host
-            .input_devices()
-            .map_err(|e| AudioError::new(&format!("Failed to get input devices: {}", e)))?
-            .find(|d| match d.name() {
-                Ok(name) => name == device_name,
-                Err(_) => false,
-            })
-            .or_else(|| host.default_input_device())
-            .ok_or_else(|| AudioError::new("No input device found"))?;
-
-        // Get supported configs and choose a reasonable one
-        let config = match device.default_input_config() {
-            Ok(config) => config,
-            Err(e) => {
-                return Err(AudioError::new(&format!(
-                    "Default config not supported: {}",
-                    e
-                )))
-            }
-        };
-
-        // Create a ring buffer for audio samples
-        let ring_size = 1024 * 8;
-        let rb = HeapRb::<f32>::new(ring_size);
-        let (mut prod, mut cons) = rb.split();
-
-        // Create stream for audio input
-        let err_fn = move |err| {
-            eprintln!("an error occurred on the audio stream: {}", err);
-        };
-
-        // Set up the actual audio input stream with cpal
-        let stream = match config.sample_format() {
-            cpal::SampleFormat::F32 => {
-                let stream = device
-                    .build_input_stream(
-                        &config.into(),
-                        move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                            // Push the incoming audio data to the ring buffer
-                            for &sample in data {
-                                let _ = prod.push(sample);
-                            }
-                        },
-                        err_fn,
-                        None,
-                    )
-                    .map_err(|e| {
-                        AudioError::new(&format!("Failed to build input stream: {}", e))
-                    })?;
-                stream
-            }
-            cpal::SampleFormat::I16 => {
-                let stream = device
-                    .build_input_stream(
-                        &config.into(),
-                        move |data: &[i16], _: &cpal::InputCallbackInfo| {
-                            // Convert i16 samples to f32 and push to the ring buffer
-                            for &sample in data {
-                                // Normalize i16 to f32 range
-                                let normalized = sample as f32 / i16::MAX as f32;
-                                let _ = prod.push(normalized);
-                            }
-                        },
-                        err_fn,
-                        None,
-                    )
-                    .map_err(|e| {
-                        AudioError::new(&format!("Failed to build input stream: {}", e))
-                    })?;
-                stream
-            }
-            cpal::SampleFormat::U16 => {
-                let stream = device
-                    .build_input_stream(
-                        &config.into(),
-                        move |data: &[u16], _: &cpal::InputCallbackInfo| {
-                            // Convert u16 samples to f32 and push to the ring buffer
-                            for &sample in data {
-                                // Normalize u16 to f32 range, centered around 0
-                                let normalized = (sample as f32 / u16::MAX as f32) * 2.0 - 1.0;
-                                let _ = prod.push(normalized);
-                            }
-                        },
-                        err_fn,
-                        None,
-                    )
-                    .map_err(|e| {
-                        AudioError::new(&format!("Failed to build input stream: {}", e))
-                    })?;
-                stream
-            }
-            _ => return Err(AudioError::new("Unsupported sample format")),
-        };
-
-        // Start the stream
-        stream
-            .play()
-            .map_err(|e| AudioError::new(&format!("Failed to start audio stream: {}", e)))?;
+        let mut cons = self
+            .consumer
+            .take()
+            .ok_or_else(|| AudioError::new("Audio stream is not open"))?;
 
-        // Store the stream to keep it alive
-        self.audio_stream = Some(stream);
-
-        // Start a task to read from the ring buffer and send data to the callback
-        tokio::spawn(async move {
+        // Read from the ring buffer on the audio runtime and send data to the callback
+        topology::spawn(Plane::Media, "capture-reader", async move {
             let mut interval = tokio::time::interval(Duration::from_millis(20));
             let mut buffer = Vec::with_capacity(1024);
+            let mut seq = 0;
 
             loop {
                 tokio::select! {
@@ -345,7 +305,11 @@ impl AudioCapture {
                         // Send audio data if we have enough samples and a channel
                         if !buffer.is_empty() {
                             if let Some(tx) = &data_tx {
-                                let _ = tx.send(buffer.clone()).await;
+                                let (sample_rate, channels) = format.get();
+                                let frame = AudioFrame::new(buffer.clone(), sample_rate, channels)
+                                    .with_seq(seq);
+                                seq += 1;
+                                let _ = tx.send(frame).await;
                             }
                         }
                     }
@@ -361,8 +325,78 @@ impl AudioCapture {
         Ok(())
     }
 
+    /// Spawns the stream thread, capturing immediately if `live` is set
+    fn open_stream(&mut self, live: bool) -> Result<(), AudioError> {
+        // Ensure we have a device
+        if self.device.is_none() {
+            // If no device is set, use the default
+            let devices = AudioDeviceManager::devices_on(&*self.host);
+            let default_input = devices.iter().find(|d| d.is_input).cloned();
+
+            if let Some(device) = default_input {
+                self.device = Some(device);
+            } else {
+                return Err(AudioError::new("No input device available"));
+            }
+        }
+
+        // Resolve the device name now so the stream thread can rebuild it later
+        let device_name = self
+            .device
+            .as_ref()
+            .map(|d| d.id.clone())
+            .unwrap_or_default();
+
+        // Create a ring buffer for audio samples
+        let ring_size = 1024 * 8;
+        let rb = HeapRb::<f32>::new(ring_size);
+        let (prod, cons) = rb.split();
+        let prod = Arc::new(Mutex::new(prod));
+
+        // cpal streams are not Send on every platform, so the stream lives on a
+        // dedicated thread which also runs the watchdog and rebuilds it when stalled
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let live_flag = Arc::new(AtomicBool::new(live));
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+        let thread_stop = stop_flag.clone();
+        let thread_live = live_flag.clone();
+        let stall_timeout = self.stall_timeout;
+        let diagnostics_tx = self.diagnostics_tx.clone();
+        let format = self.format.clone();
+        let host = self.host.clone();
+
+        let stream_thread = topology::spawn_thread(Plane::Media, "capture-stream", move || {
+            let watchdog = StreamWatchdog {
+                prod,
+                format,
+                stall_timeout,
+                stop: thread_stop,
+                live: thread_live,
+                diagnostics_tx,
+                ready_tx,
+            };
+            watchdog.run(&*host, &device_name);
+        })
+        .map_err(|e| AudioError::new(&format!("Failed to start the capture thread: {}", e)))?;
+
+        match ready_rx.recv() {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => {
+                let _ = stream_thread.join();
+                return Err(e);
+            }
+            Err(_) => return Err(AudioError::new("Audio stream thread exited unexpectedly")),
+        }
+
+        self.stream_stop = Some(stop_flag);
+        self.stream_live = Some(live_flag);
+        self.stream_thread = Some(stream_thread);
+        self.consumer = Some(cons);
+        Ok(())
+    }
+
     pub async fn stop(&mut self) -> Result<(), AudioError> {
-        if !self.is_active {
+        if !self.is_active && self.stream_thread.is_none() {
             return Err(AudioError::new("Audio capture not started"));
         }
 
@@ -371,14 +405,245 @@ impl AudioCapture {
             let _ = cancel_token.send(());
         }
 
-        // Stop the audio stream
-        self.audio_stream = None;
+        // Stop the audio stream and wait for its thread to release the device
+        if let Some(stop_flag) = self.stream_stop.take() {
+            stop_flag.store(true, Ordering::SeqCst);
+        }
+        self.stream_live = None;
+        self.consumer = None;
+        if let Some(handle) = self.stream_thread.take() {
+            let _ = tokio::task::spawn_blocking(move || handle.join()).await;
+        }
 
         self.is_active = false;
         Ok(())
     }
 }
 
+/// Diagnostics emitted by the capture stream watchdog
+#[derive(Debug, Clone, PartialEq)]
+pub enum WatchdogEvent {
+    /// No audio callbacks were received for the given duration
+    StreamStalled { silent_for: Duration },
+    /// The stream was rebuilt after stalling
+    StreamRebuilt,
+    /// Rebuilding the stream failed; it will be retried
+    RebuildFailed(String),
+}
+
+/// Sample rate and channel count of the current stream, which may change on rebuild
+struct StreamFormat {
+    sample_rate: AtomicU32,
+    channels: AtomicU16,
+    // Rate to open the device at if it supports it, or 0 for its default
+    requested_rate: AtomicU32,
+}
+
+impl StreamFormat {
+    fn new() -> Self {
+        let config = AudioConfig::default();
+        Self {
+            sample_rate: AtomicU32::new(config.sample_rate),
+            channels: AtomicU16::new(config.channels),
+            requested_rate: AtomicU32::new(0),
+        }
+    }
+
+    fn set(&self, sample_rate: u32, channels: u16) {
+        self.sample_rate.store(sample_rate, Ordering::Relaxed);
+        self.channels.store(channels, Ordering::Relaxed);
+    }
+
+    fn get(&self) -> (u32, u16) {
+        (
+            self.sample_rate.load(Ordering::Relaxed),
+            self.channels.load(Ordering::Relaxed),
+        )
+    }
+}
+
+/// Tracks when the audio callback last ran
+struct CallbackLiveness {
+    origin: Instant,
+    last_callback_ms: AtomicU64,
+}
+
+impl CallbackLiveness {
+    fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+            last_callback_ms: AtomicU64::new(0),
+        }
+    }
+
+    /// Marks the callback as alive now
+    fn touch(&self) {
+        let now = self.origin.elapsed().as_millis() as u64;
+        self.last_callback_ms.store(now, Ordering::Relaxed);
+    }
+
+    /// How long it has been since the callback last ran
+    fn silent_for(&self) -> Duration {
+        let last = Duration::from_millis(self.last_callback_ms.load(Ordering::Relaxed));
+        self.origin.elapsed().saturating_sub(last)
+    }
+}
+
+/// What the capture thread needs to keep a stream running
+struct StreamWatchdog {
+    prod: Arc<Mutex<HeapProducer<f32>>>,
+    format: Arc<StreamFormat>,
+    stall_timeout: Duration,
+    stop: Arc<AtomicBool>,
+    live: Arc<AtomicBool>,
+    diagnostics_tx: Option<mpsc::UnboundedSender<WatchdogEvent>>,
+    ready_tx: std::sync::mpsc::Sender<Result<(), AudioError>>,
+}
+
+impl StreamWatchdog {
+    /// Builds the stream and rebuilds it whenever callbacks stop arriving
+    fn run(self, host: &dyn AudioHost, device_name: &str) {
+        let StreamWatchdog {
+            prod,
+            format,
+            stall_timeout,
+            stop,
+            live,
+            diagnostics_tx,
+            ready_tx,
+        } = self;
+
+        let emit = |event: WatchdogEvent| {
+            if let Some(tx) = &diagnostics_tx {
+                let _ = tx.send(event);
+            }
+        };
+
+        let poll_interval = Duration::from_millis(50);
+        let mut ready_tx = Some(ready_tx);
+
+        while !stop.load(Ordering::SeqCst) {
+            let liveness = Arc::new(CallbackLiveness::new());
+            liveness.touch();
+
+            let stream = match build_input_stream(
+                host,
+                device_name,
+                prod.clone(),
+                &format,
+                liveness.clone(),
+            ) {
+                Ok(stream) => stream,
+                Err(e) => {
+                    // The first build failure is reported back to start()
+                    if let Some(tx) = ready_tx.take() {
+                        let _ = tx.send(Err(e));
+                        return;
+                    }
+                    emit(WatchdogEvent::RebuildFailed(e.to_string()));
+                    std::thread::sleep(stall_timeout);
+                    continue;
+                }
+            };
+
+            match ready_tx.take() {
+                Some(tx) => {
+                    let _ = tx.send(Ok(()));
+                }
+                None => emit(WatchdogEvent::StreamRebuilt),
+            }
+
+            // A pre-warmed stream stays paused until capture starts
+            let mut playing = true;
+
+            // Watch the callback until we are stopped or it stalls
+            while !stop.load(Ordering::SeqCst) {
+                let want_playing = live.load(Ordering::SeqCst);
+                if want_playing != playing {
+                    let result = if want_playing {
+                        stream.play().map_err(|e| e.to_string())
+                    } else {
+                        stream.pause().map_err(|e| e.to_string())
+                    };
+                    if let Err(e) = result {
+                        log::warn!("Failed to change audio stream state: {}", e);
+                    }
+                    playing = want_playing;
+                    liveness.touch();
+                }
+
+                std::thread::sleep(poll_interval);
+
+                // A paused stream has no callbacks, so it cannot stall
+                let silent_for = liveness.silent_for();
+                if playing && silent_for >= stall_timeout {
+                    emit(WatchdogEvent::StreamStalled { silent_for });
+                    break;
+                }
+            }
+
+            drop(stream);
+        }
+    }
+}
+
+/// Opens the named input device (or the default) and starts capturing into `prod`
+fn build_input_stream(
+    host: &dyn AudioHost,
+    device_name: &str,
+    prod: Arc<Mutex<HeapProducer<f32>>>,
+    format: &StreamFormat,
+    liveness: Arc<CallbackLiveness>,
+) -> Result<Box<dyn HostStream>, AudioError> {
+    // Try to find the device by name, or use default input device
+    let device = host
+        .input_devices()
+        .map_err(|e| AudioError::new(&format!("Failed to get input devices: {}", e)))?
+        .into_iter()
+        .find(|name| name == device_name)
+        .or_else(|| host.default_input_device())
+        .ok_or_else(|| AudioError::new("No input device found"))?;
+
+    // Get supported configs and choose a reasonable one
+    let default = host
+        .default_format(&device, true)
+        .map_err(|e| AudioError::new(&format!("Default config not supported: {}", e)))?;
+
+    // Prefer the rate audio is processed at, so it needn't be resampled
+    let requested = format.requested_rate.load(Ordering::Relaxed);
+    let config = host
+        .input_formats(&device)
+        .unwrap_or_default()
+        .iter()
+        .find_map(|range| range.at(default, requested))
+        .unwrap_or(default);
+
+    format.set(config.sample_rate, config.channels);
+
+    // Pushes normalized samples to the ring buffer; skipped if the buffer is
+    // briefly held by a stream being torn down
+    let push = move |samples: &mut dyn Iterator<Item = f32>| {
+        liveness.touch();
+        if let Ok(mut prod) = prod.try_lock() {
+            for sample in samples {
+                let _ = prod.push(sample);
+            }
+        }
+    };
+
+    // Set up the actual audio input stream, which starts once built
+    host.build_input_stream(
+        &device,
+        config,
+        Box::new(move |data| match data {
+            InputBuffer::F32(data) => push(&mut data.iter().copied()),
+            InputBuffer::I16(data) => push(&mut data.iter().map(|&s| i16_to_f32(s))),
+            InputBuffer::U16(data) => push(&mut data.iter().map(|&s| u16_to_f32(s))),
+        }),
+    )
+    .map_err(|e| AudioError::new(&format!("Failed to build input stream: {}", e)))
+}
+
 // Helper function to generate test audio data
 pub fn generate_test_audio() -> Vec<f32> {
     // Generate 1024 samples of a simple sine wave
@@ -390,10 +655,12 @@ pub fn generate_test_audio() -> Vec<f32> {
     data
 }
 
+#[cfg(test)]
 pub fn generate_test_mono_audio() -> Vec<f32> {
     generate_test_audio()
 }
 
+#[cfg(test)]
 pub fn generate_test_audio_with_echo() -> Vec<f32> {
     let original = generate_test_audio();
     let mut with_echo = Vec::with_capacity(original.len());
@@ -410,10 +677,12 @@ pub fn generate_test_audio_with_echo() -> Vec<f32> {
     with_echo
 }
 
+#[cfg(test)]
 pub fn generate_test_silence() -> Vec<f32> {
     vec![0.0; 1024]
 }
 
+#[cfg(test)]
 pub fn generate_test_speech() -> Vec<f32> {
     // For test purposes, we'll just create a higher amplitude signal
     let mut speech = generate_test_audio();
@@ -423,6 +692,7 @@ pub fn generate_test_speech() -> Vec<f32> {
     speech
 }
 
+#[cfg(test)]
 pub fn measure_echo_level(audio: &[f32]) -> f32 {
     // Simple metric for echo: standard deviation of samples
     let mean = audio.iter().sum::<f32>() / audio.len() as f32;
@@ -434,6 +704,32 @@ pub fn measure_echo_level(audio: &[f32]) -> f32 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::audio::virtual_host::{VirtualDevice, VirtualHost};
+    use cpal::SampleFormat;
+
+    fn microphone() -> VirtualDevice {
+        VirtualDevice::input("Microphone", 48000, 1, SampleFormat::F32)
+    }
+
+    fn virtual_capture(host: &VirtualHost, device: &str) -> AudioCapture {
+        let mut capture = AudioCapture::new();
+        capture.host = Arc::new(host.clone());
+        capture
+            .set_device(AudioDevice {
+                id: device.to_string(),
+                name: device.to_string(),
+                is_input: true,
+            })
+            .unwrap();
+        capture
+    }
+
+    async fn next<T>(rx: &mut mpsc::UnboundedReceiver<T>) -> T {
+        tokio::time::timeout(Duration::from_secs(2), rx.recv())
+            .await
+            .expect("timed out")
+            .unwrap()
+    }
 
     #[test]
     fn test_audio_devices_enumeration() {
@@ -453,16 +749,31 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_callback_liveness() {
+        let liveness = CallbackLiveness::new();
+        liveness.touch();
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(liveness.silent_for() >= Duration::from_millis(30));
+
+        liveness.touch();
+        assert!(liveness.silent_for() < Duration::from_millis(30));
+    }
+
     #[tokio::test]
     async fn test_audio_capture_start_stop() {
-        let mut capture = AudioCapture::new();
+        let host = VirtualHost::default().with_device(microphone());
+        let mut capture = virtual_capture(&host, "Microphone");
         assert!(capture.start().await.is_ok());
+        assert!(host.stream_format("Microphone").is_some());
         assert!(capture.stop().await.is_ok());
+        assert!(host.stream_format("Microphone").is_none());
     }
 
     #[tokio::test]
     async fn test_audio_data_received() {
-        let mut capture = AudioCapture::new();
+        let host = VirtualHost::default().with_device(microphone());
+        let mut capture = virtual_capture(&host, "Microphone");
         let (tx, mut rx) = tokio::sync::mpsc::channel(100);
 
         capture.set_data_callback(move |data| {
@@ -470,6 +781,10 @@ mod tests {
         });
 
         capture.start().await.unwrap();
+        assert_eq!(
+            host.feed("Microphone", InputBuffer::F32(&generate_test_audio())),
+            1
+        );
 
         // Should receive audio data within 1 second
         tokio::select! {
@@ -484,4 +799,70 @@ mod tests {
 
         capture.stop().await.unwrap();
     }
+
+    #[tokio::test]
+    async fn test_format_fallback() {
+        let host = VirtualHost::default()
+            .with_device(VirtualDevice::input("Headset", 44100, 2, SampleFormat::I16))
+            .with_device(
+                VirtualDevice::input("Interface", 44100, 1, SampleFormat::F32)
+                    .with_rates(8000, 96000),
+            );
+
+        // The headset only streams at its default rate, in 16-bit samples
+        let mut capture = virtual_capture(&host, "Headset");
+        capture.set_sample_rate(48000);
+        let (tx, mut frames) = mpsc::unbounded_channel();
+        capture.set_data_callback(move |frame| {
+            let _ = tx.send(frame);
+        });
+        capture.start().await.unwrap();
+        assert_eq!(host.stream_format("Headset").unwrap().sample_rate, 44100);
+        host.feed("Headset", InputBuffer::I16(&[i16::MAX, 0]));
+        let frame = next(&mut frames).await;
+        assert_eq!((frame.sample_rate, frame.channels), (44100, 2));
+        assert_eq!(frame.samples, vec![1.0, 0.0]);
+        capture.stop().await.unwrap();
+
+        // The interface can be asked for the rate audio is processed at
+        let mut capture = virtual_capture(&host, "Interface");
+        capture.set_sample_rate(48000);
+        capture.start().await.unwrap();
+        assert_eq!(host.stream_format("Interface").unwrap().sample_rate, 48000);
+        capture.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rebuilds_on_the_default_device_when_unplugged() {
+        let host = VirtualHost::default()
+            .with_device(microphone())
+            .with_device(VirtualDevice::input("USB Mic", 16000, 1, SampleFormat::F32));
+        let mut capture = virtual_capture(&host, "USB Mic");
+        capture.set_stall_timeout(Duration::from_millis(300));
+        let (events_tx, mut events) = mpsc::unbounded_channel();
+        capture.set_diagnostics_callback(move |event| {
+            let _ = events_tx.send(event);
+        });
+        let (frames_tx, mut frames) = mpsc::unbounded_channel();
+        capture.set_data_callback(move |frame| {
+            let _ = frames_tx.send(frame);
+        });
+        capture.start().await.unwrap();
+
+        host.feed("USB Mic", InputBuffer::F32(&[0.5; 160]));
+        assert_eq!(next(&mut frames).await.sample_rate, 16000);
+
+        // Its callbacks stop with it, so the watchdog rebuilds on the default microphone
+        host.pull_out("USB Mic");
+        assert!(matches!(
+            next(&mut events).await,
+            WatchdogEvent::StreamStalled { .. }
+        ));
+        assert_eq!(next(&mut events).await, WatchdogEvent::StreamRebuilt);
+        assert_eq!(host.feed("Microphone", InputBuffer::F32(&[0.25; 480])), 1);
+        let frame = next(&mut frames).await;
+        assert_eq!((frame.sample_rate, frame.samples[0]), (48000, 0.25));
+
+        capture.stop().await.unwrap();
+    }
 }
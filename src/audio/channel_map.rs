@@ -0,0 +1,108 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// How the stereo mix is laid onto an output device's channels
+///
+/// Serialized as `stereo` when left as it is, or as any of `mono`, `swap`
+/// and the pair of device channels to play on, such as `3+4`, separated
+/// by commas.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChannelMap {
+    /// Play the same mono downmix on both sides, for one-eared headsets
+    pub mono: bool,
+    /// Swap left and right, for headphones worn or wired the wrong way round
+    pub swap: bool,
+    /// Device channels left and right go to, counting from 1; the first
+    /// two if none, or if the device has fewer
+    pub outputs: Option<(u16, u16)>,
+}
+
+impl ChannelMap {
+    /// Applies the downmix and swap to one stereo frame
+    pub fn apply(&self, left: f32, right: f32) -> (f32, f32) {
+        if self.mono {
+            let mono = (left + right) * 0.5;
+            (mono, mono)
+        } else if self.swap {
+            (right, left)
+        } else {
+            (left, right)
+        }
+    }
+
+    /// Indices of the channels left and right go to on a device with `channels`
+    pub fn output_indices(&self, channels: usize) -> (usize, usize) {
+        match self.outputs {
+            Some((left, right)) if (left as usize) <= channels && (right as usize) <= channels => {
+                (left as usize - 1, right as usize - 1)
+            }
+            _ => (0, 1),
+        }
+    }
+}
+
+impl fmt::Display for ChannelMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if self.mono {
+            parts.push("mono".to_string());
+        }
+        if self.swap {
+            parts.push("swap".to_string());
+        }
+        if let Some((left, right)) = self.outputs {
+            parts.push(format!("{}+{}", left, right));
+        }
+        if parts.is_empty() {
+            write!(f, "stereo")
+        } else {
+            write!(f, "{}", parts.join(","))
+        }
+    }
+}
+
+impl FromStr for ChannelMap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut map = ChannelMap::default();
+        for part in s.split(',').map(str::trim) {
+            match part {
+                "stereo" => {}
+                "mono" => map.mono = true,
+                "swap" => map.swap = true,
+                _ => {
+                    let channel = |n: &str| n.trim().parse::<u16>().ok().filter(|&n| n >= 1);
+                    let (left, right) = part
+                        .split_once('+')
+                        .and_then(|(left, right)| Some((channel(left)?, channel(right)?)))
+                        .ok_or_else(|| format!("Invalid output channels: {}", part))?;
+                    map.outputs = Some((left, right));
+                }
+            }
+        }
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_map_round_trip_and_apply() {
+        let map: ChannelMap = "swap, 3+4".parse().unwrap();
+        assert_eq!(map.to_string(), "swap,3+4");
+        assert_eq!(map.apply(0.25, -0.5), (-0.5, 0.25));
+        assert_eq!(map.output_indices(8), (2, 3));
+        // A device with too few channels gets its first two
+        assert_eq!(map.output_indices(2), (0, 1));
+
+        let mono: ChannelMap = "mono".parse().unwrap();
+        assert_eq!(mono.apply(0.5, 0.0), (0.25, 0.25));
+        assert_eq!(ChannelMap::default().to_string(), "stereo");
+        assert_eq!("stereo".parse(), Ok(ChannelMap::default()));
+        assert!("0+1".parse::<ChannelMap>().is_err());
+        assert!("left".parse::<ChannelMap>().is_err());
+    }
+}
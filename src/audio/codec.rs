@@ -0,0 +1,562 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use super::conversions::{f32_to_i16, i16_to_f32};
+use super::AudioFrame;
+
+/// Opus bitrate used unless configured otherwise, plenty for speech
+pub const DEFAULT_BITRATE_KBPS: u32 = 32;
+
+/// Opus encoder complexity normally, and when the CPU can't keep up (0-10)
+#[cfg(feature = "opus")]
+const FULL_COMPLEXITY: u8 = 10;
+#[cfg(feature = "opus")]
+const LOW_COMPLEXITY: u8 = 2;
+
+/// Bitrates Opus accepts, in kilobits per second
+const BITRATE_RANGE_KBPS: std::ops::RangeInclusive<u32> = 6..=510;
+
+/// Codecs tried when the preferred one isn't shared, least bandwidth first
+const FALLBACK_ORDER: [AudioCodec; 3] = [AudioCodec::Opus, AudioCodec::Adpcm, AudioCodec::Pcm];
+
+/// Step sizes of IMA ADPCM
+const ADPCM_STEPS: [i32; 89] = [
+    7, 8, 9, 10, 11, 12, 13, 14, 16, 17, 19, 21, 23, 25, 28, 31, 34, 37, 41, 45, 50, 55, 60, 66,
+    73, 80, 88, 97, 107, 118, 130, 143, 157, 173, 190, 209, 230, 253, 279, 307, 337, 371, 408, 449,
+    494, 544, 598, 658, 724, 796, 876, 963, 1060, 1166, 1282, 1411, 1552, 1707, 1878, 2066, 2272,
+    2499, 2749, 3024, 3327, 3660, 4026, 4428, 4871, 5358, 5894, 6484, 7132, 7845, 8630, 9493,
+    10442, 11487, 12635, 13899, 15289, 16818, 18500, 20350, 22385, 24623, 27086, 29794, 32767,
+];
+
+/// How far each ADPCM code moves the step size
+const ADPCM_INDEX_STEPS: [i32; 8] = [-1, -1, -1, -1, 2, 4, 6, 8];
+
+/// How audio samples are encoded on the wire
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AudioCodec {
+    /// 8-bit samples, which every peer understands; sent until codecs are negotiated
+    Pcm8,
+    /// Uncompressed 16-bit samples: no encoding delay or CPU cost, for when bandwidth is free
+    Pcm,
+    /// IMA ADPCM, 4 bits a sample; cheap enough for very weak CPUs
+    Adpcm,
+    /// Opus at the configured bitrate; needs the `opus` feature
+    Opus,
+}
+
+impl AudioCodec {
+    pub fn code(&self) -> &'static str {
+        match self {
+            AudioCodec::Pcm8 => "pcm8",
+            AudioCodec::Pcm => "pcm",
+            AudioCodec::Adpcm => "adpcm",
+            AudioCodec::Opus => "opus",
+        }
+    }
+
+    fn bit(&self) -> u8 {
+        match self {
+            AudioCodec::Pcm8 => 0,
+            AudioCodec::Pcm => 1 << 0,
+            AudioCodec::Adpcm => 1 << 1,
+            AudioCodec::Opus => 1 << 2,
+        }
+    }
+}
+
+impl fmt::Display for AudioCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+impl FromStr for AudioCodec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "pcm8" => Ok(AudioCodec::Pcm8),
+            "pcm" => Ok(AudioCodec::Pcm),
+            "adpcm" => Ok(AudioCodec::Adpcm),
+            "opus" => Ok(AudioCodec::Opus),
+            other => Err(format!("Invalid audio codec: {}", other)),
+        }
+    }
+}
+
+/// Codecs a peer can decode, exchanged with its capabilities
+///
+/// 8-bit PCM is always understood, so it isn't listed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CodecSet(u8);
+
+impl CodecSet {
+    /// Codecs this build can encode and decode
+    pub fn available() -> Self {
+        let set = Self::default()
+            .with(AudioCodec::Pcm)
+            .with(AudioCodec::Adpcm);
+        if cfg!(feature = "opus") {
+            set.with(AudioCodec::Opus)
+        } else {
+            set
+        }
+    }
+
+    pub fn with(self, codec: AudioCodec) -> Self {
+        Self(self.0 | codec.bit())
+    }
+
+    pub fn contains(&self, codec: AudioCodec) -> bool {
+        self.0 & codec.bit() == codec.bit()
+    }
+
+    /// Codecs in both sets
+    pub fn common(&self, other: &Self) -> Self {
+        Self(self.0 & other.0)
+    }
+}
+
+/// The codec to send to a peer: ours if it can decode it, else the best we share
+pub fn negotiate(preferred: AudioCodec, shared: CodecSet) -> AudioCodec {
+    if shared.contains(preferred) {
+        return preferred;
+    }
+    FALLBACK_ORDER
+        .into_iter()
+        .find(|codec| shared.contains(*codec))
+        .unwrap_or(AudioCodec::Pcm8)
+}
+
+/// A codec and, for Opus, its bitrate
+///
+/// Written as the codec alone, or `opus:<kbps>` for another bitrate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodecSettings {
+    pub codec: AudioCodec,
+    pub bitrate_kbps: u32,
+}
+
+impl Default for CodecSettings {
+    fn default() -> Self {
+        Self {
+            codec: AudioCodec::Opus,
+            bitrate_kbps: DEFAULT_BITRATE_KBPS,
+        }
+    }
+}
+
+impl fmt::Display for CodecSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.codec == AudioCodec::Opus && self.bitrate_kbps != DEFAULT_BITRATE_KBPS {
+            write!(f, "{}:{}", self.codec, self.bitrate_kbps)
+        } else {
+            write!(f, "{}", self.codec)
+        }
+    }
+}
+
+impl FromStr for CodecSettings {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (codec, bitrate) = match s.split_once(':') {
+            Some((codec, bitrate)) => {
+                let bitrate = bitrate
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|kbps| BITRATE_RANGE_KBPS.contains(kbps))
+                    .ok_or_else(|| format!("Invalid codec bitrate: {}", bitrate))?;
+                (codec, bitrate)
+            }
+            None => (s, DEFAULT_BITRATE_KBPS),
+        };
+        Ok(Self {
+            codec: codec.parse()?,
+            bitrate_kbps: bitrate,
+        })
+    }
+}
+
+/// Reads a room's codec override from a join link's `codec` parameter
+pub fn codec_from_link(link: &str) -> Option<CodecSettings> {
+    link.split(['?', '&'])
+        .find_map(|param| param.strip_prefix("codec="))
+        .and_then(|value| value.parse().ok())
+}
+
+/// Adds a room's codec override to a join link
+pub fn append_codec(link: &str, codec: Option<CodecSettings>) -> String {
+    match codec {
+        Some(codec) => format!("{}&codec={}", link, codec),
+        None => link.to_string(),
+    }
+}
+
+/// Encodes frames for one peer
+///
+/// ADPCM and Opus carry state from frame to frame, so each stream needs
+/// its own encoder. ADPCM frames still decode on their own: each starts
+/// with the state it was encoded from.
+#[derive(Debug, Default)]
+pub struct AudioEncoder {
+    bitrate_kbps: u32,
+    low_complexity: bool,
+    // Predictor and step index per channel
+    adpcm: Vec<(i32, i32)>,
+    #[cfg(feature = "opus")]
+    opus: Option<((u32, u16), audiopus::coder::Encoder)>,
+}
+
+impl AudioEncoder {
+    pub fn new(bitrate_kbps: u32) -> Self {
+        Self {
+            bitrate_kbps,
+            ..Self::default()
+        }
+    }
+
+    pub fn set_bitrate(&mut self, bitrate_kbps: u32) {
+        if bitrate_kbps != self.bitrate_kbps {
+            self.bitrate_kbps = bitrate_kbps;
+            #[cfg(feature = "opus")]
+            {
+                self.opus = None;
+            }
+        }
+    }
+
+    /// Has Opus spend less CPU per frame, at some cost in quality
+    ///
+    /// ADPCM and PCM cost next to nothing either way.
+    pub fn set_low_complexity(&mut self, low: bool) {
+        if low != self.low_complexity {
+            self.low_complexity = low;
+            #[cfg(feature = "opus")]
+            {
+                self.opus = None;
+            }
+        }
+    }
+
+    /// Encodes a frame, returning the codec actually used and the data
+    ///
+    /// Opus only takes frames of 2.5 to 60 ms at its own sample rates;
+    /// other frames are sent as 16-bit PCM.
+    pub fn encode(&mut self, codec: AudioCodec, frame: &AudioFrame) -> (AudioCodec, Vec<u8>) {
+        match codec {
+            AudioCodec::Pcm8 => (codec, encode_pcm8(&frame.samples)),
+            AudioCodec::Pcm => (codec, encode_pcm(&frame.samples)),
+            AudioCodec::Adpcm => (codec, self.encode_adpcm(frame)),
+            AudioCodec::Opus => match self.encode_opus(frame) {
+                Some(data) => (codec, data),
+                None => (AudioCodec::Pcm, encode_pcm(&frame.samples)),
+            },
+        }
+    }
+
+    fn encode_adpcm(&mut self, frame: &AudioFrame) -> Vec<u8> {
+        let channels = frame.channels.max(1) as usize;
+        if self.adpcm.len() != channels {
+            self.adpcm = vec![(0, 0); channels];
+        }
+
+        let mut data = Vec::with_capacity(4 + channels * 3 + frame.samples.len().div_ceil(2));
+        data.extend_from_slice(&(frame.samples.len() as u32).to_le_bytes());
+        for &(predictor, index) in &self.adpcm {
+            data.extend_from_slice(&(predictor as i16).to_le_bytes());
+            data.push(index as u8);
+        }
+
+        let codes: Vec<u8> = frame
+            .samples
+            .iter()
+            .enumerate()
+            .map(|(i, &sample)| {
+                let (predictor, index) = &mut self.adpcm[i % channels];
+                adpcm_encode(f32_to_i16(sample) as i32, predictor, index)
+            })
+            .collect();
+        data.extend(
+            codes
+                .chunks(2)
+                .map(|pair| pair[0] | pair.get(1).map_or(0, |hi| hi << 4)),
+        );
+        data
+    }
+
+    #[cfg(feature = "opus")]
+    fn encode_opus(&mut self, frame: &AudioFrame) -> Option<Vec<u8>> {
+        use audiopus::{coder::Encoder, Application, Bitrate};
+
+        // Opus frames last 2.5, 5, 10, 20, 40 or 60 ms
+        let frames = frame.frames() * 400;
+        let units = frames / frame.sample_rate.max(1) as usize;
+        if frames % frame.sample_rate.max(1) as usize != 0 || ![1, 2, 4, 8, 16, 24].contains(&units)
+        {
+            return None;
+        }
+
+        let format = (frame.sample_rate, frame.channels);
+        if self.opus.as_ref().map(|(current, _)| *current) != Some(format) {
+            let (sample_rate, channels) = opus_format(format)?;
+            let mut encoder = Encoder::new(sample_rate, channels, Application::Voip).ok()?;
+            encoder
+                .set_bitrate(Bitrate::BitsPerSecond(self.bitrate_kbps as i32 * 1000))
+                .ok()?;
+            let complexity = if self.low_complexity {
+                LOW_COMPLEXITY
+            } else {
+                FULL_COMPLEXITY
+            };
+            encoder.set_complexity(complexity).ok()?;
+            self.opus = Some((format, encoder));
+        }
+
+        let (_, encoder) = self.opus.as_ref()?;
+        let mut packet = vec![0; MAX_OPUS_PACKET];
+        let len = encoder.encode_float(&frame.samples, &mut packet).ok()?;
+        packet.truncate(len);
+        Some(packet)
+    }
+
+    #[cfg(not(feature = "opus"))]
+    fn encode_opus(&mut self, _frame: &AudioFrame) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+/// Decodes frames from one peer
+#[derive(Debug, Default)]
+pub struct AudioDecoder {
+    #[cfg(feature = "opus")]
+    opus: Option<((u32, u16), audiopus::coder::Decoder)>,
+}
+
+impl AudioDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decodes a frame's samples, or `None` if the data is malformed
+    pub fn decode(
+        &mut self,
+        codec: AudioCodec,
+        data: &[u8],
+        sample_rate: u32,
+        channels: u16,
+    ) -> Option<Vec<f32>> {
+        match codec {
+            AudioCodec::Pcm8 => Some(data.iter().map(|&b| (b as f32) / 255.0).collect()),
+            AudioCodec::Pcm => Some(
+                data.chunks_exact(2)
+                    .map(|pair| i16_to_f32(i16::from_le_bytes([pair[0], pair[1]])))
+                    .collect(),
+            ),
+            AudioCodec::Adpcm => decode_adpcm(data, channels),
+            AudioCodec::Opus => self.decode_opus(data, sample_rate, channels),
+        }
+    }
+
+    #[cfg(feature = "opus")]
+    fn decode_opus(&mut self, data: &[u8], sample_rate: u32, channels: u16) -> Option<Vec<f32>> {
+        use audiopus::{coder::Decoder, packet::Packet, MutSignals};
+
+        let format = (sample_rate, channels);
+        if self.opus.as_ref().map(|(current, _)| *current) != Some(format) {
+            let (sample_rate, channels) = opus_format(format)?;
+            self.opus = Some((format, Decoder::new(sample_rate, channels).ok()?));
+        }
+
+        let (_, decoder) = self.opus.as_mut()?;
+        // Room for the longest packet Opus produces, 120 ms
+        let mut samples = vec![0.0; sample_rate as usize * 120 / 1000 * channels as usize];
+        let frames = decoder
+            .decode_float(
+                Some(Packet::try_from(data).ok()?),
+                MutSignals::try_from(&mut samples[..]).ok()?,
+                false,
+            )
+            .ok()?;
+        samples.truncate(frames * channels as usize);
+        Some(samples)
+    }
+
+    #[cfg(not(feature = "opus"))]
+    fn decode_opus(&mut self, _data: &[u8], _sample_rate: u32, _channels: u16) -> Option<Vec<f32>> {
+        None
+    }
+}
+
+/// Largest Opus packet we produce
+#[cfg(feature = "opus")]
+const MAX_OPUS_PACKET: usize = 4000;
+
+#[cfg(feature = "opus")]
+fn opus_format(
+    (sample_rate, channels): (u32, u16),
+) -> Option<(audiopus::SampleRate, audiopus::Channels)> {
+    let sample_rate = audiopus::SampleRate::try_from(sample_rate as i32).ok()?;
+    let channels = match channels {
+        1 => audiopus::Channels::Mono,
+        2 => audiopus::Channels::Stereo,
+        _ => return None,
+    };
+    Some((sample_rate, channels))
+}
+
+fn encode_pcm8(samples: &[f32]) -> Vec<u8> {
+    samples
+        .iter()
+        .map(|&sample| (sample * 255.0) as u8)
+        .collect()
+}
+
+fn encode_pcm(samples: &[f32]) -> Vec<u8> {
+    samples
+        .iter()
+        .flat_map(|&sample| f32_to_i16(sample).to_le_bytes())
+        .collect()
+}
+
+/// Encodes one sample as a 4-bit code, moving the channel's state along
+fn adpcm_encode(sample: i32, predictor: &mut i32, index: &mut i32) -> u8 {
+    let mut step = ADPCM_STEPS[*index as usize];
+    let mut diff = sample - *predictor;
+    let mut code = 0;
+    if diff < 0 {
+        code = 8;
+        diff = -diff;
+    }
+    for bit in [4, 2, 1] {
+        if diff >= step {
+            code |= bit;
+            diff -= step;
+        }
+        step >>= 1;
+    }
+    adpcm_step(code, predictor, index);
+    code
+}
+
+/// Applies a 4-bit code to a channel's state, as both ends do
+fn adpcm_step(code: u8, predictor: &mut i32, index: &mut i32) {
+    let step = ADPCM_STEPS[*index as usize];
+    let mut delta = step >> 3;
+    if code & 4 != 0 {
+        delta += step;
+    }
+    if code & 2 != 0 {
+        delta += step >> 1;
+    }
+    if code & 1 != 0 {
+        delta += step >> 2;
+    }
+    if code & 8 != 0 {
+        delta = -delta;
+    }
+    *predictor = (*predictor + delta).clamp(i16::MIN as i32, i16::MAX as i32);
+    *index = (*index + ADPCM_INDEX_STEPS[(code & 7) as usize]).clamp(0, 88);
+}
+
+fn decode_adpcm(data: &[u8], channels: u16) -> Option<Vec<f32>> {
+    let channels = channels.max(1) as usize;
+    let count = u32::from_le_bytes(data.get(..4)?.try_into().ok()?) as usize;
+    let header = data.get(4..4 + channels * 3)?;
+    let mut state: Vec<(i32, i32)> = header
+        .chunks_exact(3)
+        .map(|entry| {
+            let predictor = i16::from_le_bytes([entry[0], entry[1]]) as i32;
+            (predictor, (entry[2] as i32).min(88))
+        })
+        .collect();
+
+    let codes = data.get(4 + channels * 3..)?;
+    if codes.len() * 2 < count {
+        return None;
+    }
+    let samples = (0..count)
+        .map(|i| {
+            let code = (codes[i / 2] >> (4 * (i % 2))) & 0x0f;
+            let (predictor, index) = &mut state[i % channels];
+            adpcm_step(code, predictor, index);
+            i16_to_f32(*predictor as i16)
+        })
+        .collect();
+    Some(samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codecs_round_trip() {
+        let samples: Vec<f32> = (0..1920).map(|i| 0.5 * (i as f32 * 0.05).sin()).collect();
+        let frames: Vec<AudioFrame> = samples
+            .chunks(960)
+            .map(|chunk| AudioFrame::new(chunk.to_vec(), 48000, 2))
+            .collect();
+
+        for (codec, tolerance) in [(AudioCodec::Pcm, 1e-4), (AudioCodec::Adpcm, 0.05)] {
+            let mut encoder = AudioEncoder::new(DEFAULT_BITRATE_KBPS);
+            let encoded: Vec<_> = frames
+                .iter()
+                .map(|frame| encoder.encode(codec, frame))
+                .collect();
+
+            // The second frame decodes on its own, once ADPCM has adapted
+            let (used, data) = &encoded[1];
+            assert_eq!(*used, codec);
+            let decoded = AudioDecoder::new().decode(*used, data, 48000, 2).unwrap();
+            assert_eq!(decoded.len(), 960);
+            let worst = decoded
+                .iter()
+                .zip(&frames[1].samples)
+                .map(|(a, b)| (a - b).abs())
+                .fold(0.0, f32::max);
+            assert!(worst < tolerance, "{} off by {}", codec, worst);
+        }
+
+        let (_, adpcm) = AudioEncoder::default().encode(AudioCodec::Adpcm, &frames[0]);
+        assert_eq!(adpcm.len(), 4 + 2 * 3 + 480);
+        let mut decoder = AudioDecoder::new();
+        assert!(decoder
+            .decode(AudioCodec::Adpcm, &adpcm[..20], 48000, 2)
+            .is_none());
+    }
+
+    #[test]
+    fn test_negotiation_and_settings() {
+        let ours = CodecSet::default()
+            .with(AudioCodec::Pcm)
+            .with(AudioCodec::Adpcm);
+        let theirs = CodecSet::default()
+            .with(AudioCodec::Adpcm)
+            .with(AudioCodec::Opus);
+        let shared = ours.common(&theirs);
+        assert_eq!(negotiate(AudioCodec::Adpcm, shared), AudioCodec::Adpcm);
+        assert_eq!(negotiate(AudioCodec::Opus, shared), AudioCodec::Adpcm);
+        assert_eq!(negotiate(AudioCodec::Pcm, shared), AudioCodec::Adpcm);
+        assert_eq!(
+            negotiate(AudioCodec::Pcm, CodecSet::default()),
+            AudioCodec::Pcm8
+        );
+
+        for settings in ["opus", "opus:64", "pcm", "adpcm"] {
+            let parsed: CodecSettings = settings.parse().unwrap();
+            assert_eq!(parsed.to_string(), settings);
+        }
+        assert!("opus:1000".parse::<CodecSettings>().is_err());
+        assert!("mp3".parse::<CodecSettings>().is_err());
+
+        let link = append_codec("resonance://join?session=abc", "pcm".parse().ok());
+        assert_eq!(
+            codec_from_link(&link).map(|c| c.codec),
+            Some(AudioCodec::Pcm)
+        );
+        assert_eq!(codec_from_link("resonance://join?session=abc"), None);
+    }
+}
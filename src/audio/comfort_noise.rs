@@ -0,0 +1,384 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use super::VoiceProcessor;
+
+/// Order of the filter describing the noise spectrum
+pub const LPC_ORDER: usize = 4;
+
+/// Frame energy under which a frame counts as silence
+///
+/// Well below quiet speech, so only background noise is suppressed.
+const SILENCE_ENERGY: f32 = 1e-4;
+
+/// Silent frames still sent after speech, so word endings aren't clipped
+pub const HANGOVER_FRAMES: usize = 10;
+
+/// How often a fresh descriptor is sent while silent
+pub const DESCRIPTOR_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Loudest comfort noise generated, whatever a descriptor says
+const MAX_NOISE_LEVEL: f32 = 0.02;
+
+/// Share of the way the estimate moves towards each new silent frame
+const NOISE_SMOOTHING: f32 = 0.2;
+
+/// Longest frame of comfort noise a peer may ask for
+const MAX_NOISE_FRAME_MS: u64 = 120;
+
+/// Sample rates and channel counts a peer's comfort noise may be in
+const NOISE_SAMPLE_RATES: std::ops::RangeInclusive<u32> = 8000..=192_000;
+const MAX_NOISE_CHANNELS: u16 = 8;
+
+/// Background noise as a level and the spectral envelope it has
+///
+/// Like RFC 3389 comfort noise, the spectrum is given as linear prediction
+/// coefficients: white noise through the all-pole filter they describe
+/// sounds like the original noise.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct NoiseDescriptor {
+    /// RMS level of the noise
+    pub level: f32,
+    /// Prediction coefficients `a1..aN`, so that `x[n] ≈ -Σ a_k x[n-k]`
+    pub coefficients: [f32; LPC_ORDER],
+}
+
+impl NoiseDescriptor {
+    /// The descriptor with any level or coefficient that isn't a finite number zeroed
+    fn sanitized(&self) -> Self {
+        let finite = |value: f32| if value.is_finite() { value } else { 0.0 };
+        Self {
+            level: finite(self.level),
+            coefficients: self.coefficients.map(finite),
+        }
+    }
+}
+
+/// Samples in a comfort noise frame a peer described, if it's one we'll make
+///
+/// The frame length comes from the peer and sizes what we allocate, so
+/// frames over [`MAX_NOISE_FRAME_MS`], or in an implausible format, are
+/// refused.
+pub fn noise_frame_len(sample_rate: u32, channels: u16, frame_len: u32) -> Option<usize> {
+    if !NOISE_SAMPLE_RATES.contains(&sample_rate) || !(1..=MAX_NOISE_CHANNELS).contains(&channels) {
+        return None;
+    }
+    let longest = sample_rate as u64 * channels as u64 * MAX_NOISE_FRAME_MS / 1000;
+    (frame_len as u64 <= longest).then_some(frame_len as usize)
+}
+
+/// Prediction coefficients for an autocorrelation, by Levinson-Durbin recursion
+fn levinson(autocorrelation: &[f32; LPC_ORDER + 1]) -> [f32; LPC_ORDER] {
+    let mut coefficients = [0.0; LPC_ORDER];
+    let mut error = autocorrelation[0];
+    if error <= f32::EPSILON {
+        return coefficients;
+    }
+
+    for i in 0..LPC_ORDER {
+        let mut acc = autocorrelation[i + 1];
+        for j in 0..i {
+            acc += coefficients[j] * autocorrelation[i - j];
+        }
+        let reflection = -acc / error;
+        let previous = coefficients;
+        for j in 0..i {
+            coefficients[j] = previous[j] + reflection * previous[i - 1 - j];
+        }
+        coefficients[i] = reflection;
+        error *= 1.0 - reflection * reflection;
+        if error <= f32::EPSILON {
+            break;
+        }
+    }
+    coefficients
+}
+
+fn rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    (samples.iter().map(|sample| sample * sample).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+/// Follows the background noise across silent frames
+///
+/// Only the first channel of interleaved audio is analysed.
+#[derive(Debug, Clone, Default)]
+pub struct NoiseEstimator {
+    // Smoothed autocorrelation per sample, lag 0 first
+    autocorrelation: Option<[f32; LPC_ORDER + 1]>,
+}
+
+impl NoiseEstimator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Folds a silent frame into the estimate
+    pub fn update(&mut self, samples: &[f32], channels: u16) {
+        let first_channel: Vec<f32> = samples
+            .iter()
+            .copied()
+            .step_by(channels.max(1) as usize)
+            .collect();
+        if first_channel.len() <= LPC_ORDER {
+            return;
+        }
+
+        let mut frame = [0.0; LPC_ORDER + 1];
+        for (lag, value) in frame.iter_mut().enumerate() {
+            *value = first_channel
+                .iter()
+                .zip(&first_channel[lag..])
+                .map(|(a, b)| a * b)
+                .sum::<f32>()
+                / first_channel.len() as f32;
+        }
+
+        let estimate = self.autocorrelation.get_or_insert(frame);
+        for (value, new) in estimate.iter_mut().zip(frame) {
+            *value += (new - *value) * NOISE_SMOOTHING;
+        }
+    }
+
+    /// The noise heard so far, or silence if there hasn't been any
+    pub fn descriptor(&self) -> NoiseDescriptor {
+        let Some(autocorrelation) = self.autocorrelation else {
+            return NoiseDescriptor::default();
+        };
+        NoiseDescriptor {
+            level: autocorrelation[0].max(0.0).sqrt(),
+            coefficients: levinson(&autocorrelation),
+        }
+    }
+}
+
+/// Makes low-level noise shaped like a descriptor, so a silent peer doesn't sound cut off
+#[derive(Debug)]
+pub struct ComfortNoiseGenerator {
+    rng: StdRng,
+    // Last outputs of the shaping filter, newest first
+    history: [f32; LPC_ORDER],
+}
+
+impl ComfortNoiseGenerator {
+    pub fn new() -> Self {
+        Self {
+            rng: StdRng::from_entropy(),
+            history: [0.0; LPC_ORDER],
+        }
+    }
+
+    /// Generates `frame_len` interleaved samples of noise matching `noise`
+    pub fn generate(
+        &mut self,
+        noise: &NoiseDescriptor,
+        channels: u16,
+        frame_len: usize,
+    ) -> Vec<f32> {
+        let noise = noise.sanitized();
+        let channels = channels.max(1) as usize;
+        let shaped: Vec<f32> = (0..frame_len / channels)
+            .map(|_| {
+                let excitation: f32 = self.rng.gen_range(-1.0..1.0);
+                let predicted: f32 = noise
+                    .coefficients
+                    .iter()
+                    .zip(&self.history)
+                    .map(|(a, y)| a * y)
+                    .sum();
+                let sample = (excitation - predicted).clamp(-1e3, 1e3);
+                self.history.rotate_right(1);
+                self.history[0] = sample;
+                sample
+            })
+            .collect();
+
+        // Set the level from the descriptor, but never more than comfortable
+        let gain = noise.level.min(MAX_NOISE_LEVEL) / rms(&shaped).max(f32::EPSILON);
+        let mut samples: Vec<f32> = shaped
+            .into_iter()
+            .flat_map(|sample| std::iter::repeat_n(sample * gain, channels))
+            .collect();
+        samples.resize(frame_len, 0.0);
+        samples
+    }
+}
+
+impl Default for ComfortNoiseGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What to send for a captured frame when silence is suppressed
+#[derive(Debug, Clone, PartialEq)]
+pub enum Transmission {
+    /// Send the frame as usual
+    Frame,
+    /// Send this description of the background noise instead
+    Descriptor(NoiseDescriptor),
+    /// Send nothing; the peer keeps playing the last noise described
+    Nothing,
+}
+
+/// Stops sending silent frames, describing the background noise instead
+///
+/// Frames keep going out for [`HANGOVER_FRAMES`] after speech stops, then
+/// a noise descriptor is sent every [`DESCRIPTOR_INTERVAL`] until speech
+/// resumes.
+#[derive(Clone)]
+pub struct SilenceSuppressor {
+    vad: VoiceProcessor,
+    estimator: NoiseEstimator,
+    silent_frames: usize,
+    last_descriptor: Option<Instant>,
+}
+
+impl SilenceSuppressor {
+    pub fn new() -> Self {
+        Self {
+            vad: VoiceProcessor::new().with_vad_threshold(SILENCE_ENERGY),
+            estimator: NoiseEstimator::new(),
+            silent_frames: 0,
+            last_descriptor: None,
+        }
+    }
+
+    /// Decides what to send for a captured frame
+    pub fn push(&mut self, samples: &[f32], channels: u16, now: Instant) -> Transmission {
+        if samples.is_empty() || self.vad.detect_voice_activity(samples) {
+            self.silent_frames = 0;
+            self.last_descriptor = None;
+            return Transmission::Frame;
+        }
+
+        self.estimator.update(samples, channels);
+        self.silent_frames += 1;
+        if self.silent_frames <= HANGOVER_FRAMES {
+            return Transmission::Frame;
+        }
+
+        let due = self
+            .last_descriptor
+            .is_none_or(|at| now.duration_since(at) >= DESCRIPTOR_INTERVAL);
+        if !due {
+            return Transmission::Nothing;
+        }
+        self.last_descriptor = Some(now);
+        Transmission::Descriptor(self.estimator.descriptor())
+    }
+}
+
+impl Default for SilenceSuppressor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Quiet noise with most of its energy below 500 Hz, like a fan hum
+    fn rumble(len: usize) -> Vec<f32> {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut state = 0.0;
+        (0..len)
+            .map(|_| {
+                state += (rng.gen_range(-1.0..1.0) - state) * 0.05;
+                state * 0.02
+            })
+            .collect()
+    }
+
+    // How alike neighbouring samples are; near 1 for low-pitched noise, near 0 for white
+    fn lag_one_correlation(samples: &[f32]) -> f32 {
+        let lagged: f32 = samples.windows(2).map(|pair| pair[0] * pair[1]).sum();
+        lagged / samples.iter().map(|sample| sample * sample).sum::<f32>()
+    }
+
+    #[test]
+    fn test_generated_noise_matches_descriptor() {
+        let mut estimator = NoiseEstimator::new();
+        let recorded = rumble(48000);
+        for chunk in recorded.chunks(960) {
+            estimator.update(chunk, 1);
+        }
+        let noise = estimator.descriptor();
+        assert!((noise.level - rms(&recorded)).abs() < rms(&recorded) * 0.2);
+
+        let mut generator = ComfortNoiseGenerator::new();
+        let generated: Vec<f32> = (0..50)
+            .flat_map(|_| generator.generate(&noise, 2, 1920))
+            .collect();
+        assert_eq!(generated.len(), 50 * 1920);
+        assert_eq!(generated[0], generated[1]);
+
+        let first_channel: Vec<f32> = generated.iter().copied().step_by(2).collect();
+        assert!((rms(&first_channel) - noise.level).abs() < noise.level * 0.2);
+        let (heard, wanted) = (
+            lag_one_correlation(&first_channel),
+            lag_one_correlation(&recorded),
+        );
+        assert!((heard - wanted).abs() < 0.05, "{} vs {}", heard, wanted);
+
+        // Loud "noise" is turned down to a comfortable level
+        let loud = NoiseDescriptor {
+            level: 0.5,
+            coefficients: [0.0; LPC_ORDER],
+        };
+        let samples = generator.generate(&loud, 1, 960);
+        assert!((rms(&samples) - MAX_NOISE_LEVEL).abs() < 1e-4);
+
+        // A peer can't make us allocate more than a short frame, nor feed NaN in
+        assert_eq!(noise_frame_len(48000, 2, 1920), Some(1920));
+        assert_eq!(noise_frame_len(48000, 2, 11520), Some(11520));
+        assert_eq!(noise_frame_len(48000, 2, 11521), None);
+        assert_eq!(noise_frame_len(48000, 2, u32::MAX), None);
+        assert_eq!(noise_frame_len(u32::MAX, 2, 1920), None);
+        assert_eq!(noise_frame_len(48000, 0, 1920), None);
+        let broken = NoiseDescriptor {
+            level: f32::NAN,
+            coefficients: [f32::INFINITY, 0.5, f32::NAN, 0.0],
+        };
+        assert!(generator
+            .generate(&broken, 1, 960)
+            .iter()
+            .all(|sample| sample.is_finite()));
+    }
+
+    #[test]
+    fn test_suppresses_silence_after_hangover() {
+        let mut suppressor = SilenceSuppressor::new();
+        let start = Instant::now();
+        let speech = vec![0.5; 960];
+        let silence = rumble(960);
+
+        assert_eq!(suppressor.push(&speech, 1, start), Transmission::Frame);
+        for _ in 0..HANGOVER_FRAMES {
+            assert_eq!(suppressor.push(&silence, 1, start), Transmission::Frame);
+        }
+        assert!(matches!(
+            suppressor.push(&silence, 1, start),
+            Transmission::Descriptor(_)
+        ));
+        assert_eq!(
+            suppressor.push(&silence, 1, start + DESCRIPTOR_INTERVAL / 2),
+            Transmission::Nothing
+        );
+        assert!(matches!(
+            suppressor.push(&silence, 1, start + DESCRIPTOR_INTERVAL),
+            Transmission::Descriptor(_)
+        ));
+
+        // Speech goes straight out again
+        assert_eq!(
+            suppressor.push(&speech, 1, start + DESCRIPTOR_INTERVAL),
+            Transmission::Frame
+        );
+    }
+}
@@ -0,0 +1,214 @@
+/// Scales a 16-bit sample to -1.0..=1.0
+pub fn i16_to_f32(sample: i16) -> f32 {
+    sample as f32 / i16::MAX as f32
+}
+
+/// Scales an unsigned 16-bit sample, centred on 32768, to -1.0..=1.0
+pub fn u16_to_f32(sample: u16) -> f32 {
+    (sample as f32 / u16::MAX as f32) * 2.0 - 1.0
+}
+
+/// Converts to 16 bits by truncation, clipping anything out of range
+///
+/// Bit-exact and cheap, which codecs need; [`Ditherer`] sounds better for
+/// quiet audio sent to a device.
+pub fn f32_to_i16(sample: f32) -> i16 {
+    (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16
+}
+
+/// Converts to unsigned 16 bits, centred on 32768, clipping anything out of range
+pub fn f32_to_u16(sample: f32) -> u16 {
+    ((sample.clamp(-1.0, 1.0) * 0.5 + 0.5) * u16::MAX as f32) as u16
+}
+
+/// Converts to 16 bits with triangular (TPDF) dither
+///
+/// Truncating quiet audio to 16 bits leaves distortion that follows the
+/// signal; a little noise of about one bit turns it into a steady hiss that
+/// isn't heard. Keeps its own random state, so it's safe on the audio
+/// thread: no locks and no allocation.
+#[derive(Debug, Clone)]
+pub struct Ditherer {
+    state: u32,
+}
+
+impl Default for Ditherer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Ditherer {
+    pub fn new() -> Self {
+        Self { state: 0x9e37_79b9 }
+    }
+
+    /// Uniform noise in -0.5..0.5 of one 16-bit step
+    fn next_noise(&mut self) -> f32 {
+        // xorshift32
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        (self.state as f32 / u32::MAX as f32) - 0.5
+    }
+
+    pub fn to_i16(&mut self, sample: f32) -> i16 {
+        // Two uniform noises add up to a triangular one
+        let noise = self.next_noise() + self.next_noise();
+        let scaled = sample.clamp(-1.0, 1.0) * i16::MAX as f32 + noise;
+        scaled.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16
+    }
+
+    pub fn convert(&mut self, samples: &[f32]) -> Vec<i16> {
+        samples.iter().map(|&sample| self.to_i16(sample)).collect()
+    }
+}
+
+/// Splits interleaved audio into one buffer per channel
+pub fn deinterleave(samples: &[f32], channels: u16) -> Vec<Vec<f32>> {
+    let channels = channels.max(1) as usize;
+    let mut planes = vec![Vec::with_capacity(samples.len() / channels); channels];
+    for frame in samples.chunks_exact(channels) {
+        for (plane, &sample) in planes.iter_mut().zip(frame) {
+            plane.push(sample);
+        }
+    }
+    planes
+}
+
+/// Interleaves per-channel buffers, stopping at the shortest
+pub fn interleave(planes: &[Vec<f32>]) -> Vec<f32> {
+    let len = planes.iter().map(Vec::len).min().unwrap_or(0);
+    let mut samples = Vec::with_capacity(len * planes.len());
+    for index in 0..len {
+        samples.extend(planes.iter().map(|plane| plane[index]));
+    }
+    samples
+}
+
+/// Averages interleaved audio of any channel count down to mono
+pub fn downmix_to_mono(samples: &[f32], channels: u16) -> Vec<f32> {
+    let channels = channels.max(1) as usize;
+    if channels == 1 {
+        return samples.to_vec();
+    }
+    samples
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect()
+}
+
+/// Copies mono audio to every one of `channels` channels, interleaved
+pub fn upmix_from_mono(samples: &[f32], channels: u16) -> Vec<f32> {
+    let channels = channels.max(1) as usize;
+    samples
+        .iter()
+        .flat_map(|&sample| std::iter::repeat_n(sample, channels))
+        .collect()
+}
+
+/// Converts interleaved audio between channel counts, through mono if they differ
+pub fn remix(samples: &[f32], from: u16, to: u16) -> Vec<f32> {
+    if from.max(1) == to.max(1) {
+        return samples.to_vec();
+    }
+    upmix_from_mono(&downmix_to_mono(samples, from), to)
+}
+
+/// Regroups a stream of samples into blocks of one fixed length
+///
+/// Devices deliver audio in whatever block sizes suit them; DSP, codecs and
+/// FFTs want a fixed one. Samples are held until a whole block is ready.
+#[derive(Debug, Clone)]
+pub struct BlockAdapter {
+    block_len: usize,
+    pending: Vec<f32>,
+}
+
+impl BlockAdapter {
+    pub fn new(block_len: usize) -> Self {
+        Self {
+            block_len: block_len.max(1),
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn block_len(&self) -> usize {
+        self.block_len
+    }
+
+    /// Changes the block length, dropping any partial block
+    pub fn set_block_len(&mut self, block_len: usize) {
+        self.block_len = block_len.max(1);
+        self.pending.clear();
+    }
+
+    /// Samples waiting for the rest of their block
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Adds samples, returning every block now complete
+    pub fn push(&mut self, samples: &[f32]) -> Vec<Vec<f32>> {
+        self.pending.extend_from_slice(samples);
+        let mut blocks = Vec::with_capacity(self.pending.len() / self.block_len);
+        while self.pending.len() >= self.block_len {
+            blocks.push(self.pending.drain(..self.block_len).collect());
+        }
+        blocks
+    }
+
+    /// Drops any partial block
+    pub fn reset(&mut self) {
+        self.pending.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_formats() {
+        for sample in [i16::MIN + 1, -1, 0, 1, i16::MAX] {
+            assert_eq!(f32_to_i16(i16_to_f32(sample)), sample);
+        }
+        assert_eq!(f32_to_i16(2.0), i16::MAX);
+        assert_eq!(u16_to_f32(0), -1.0);
+        assert_eq!(f32_to_u16(1.0), u16::MAX);
+
+        // Dither stays within a step or so and averages out to the signal
+        let mut ditherer = Ditherer::new();
+        let quiet = 0.3 / i16::MAX as f32;
+        let dithered = ditherer.convert(&vec![quiet; 10_000]);
+        assert!(dithered.iter().all(|sample| (-1..=2).contains(sample)));
+        let mean = dithered.iter().map(|&s| s as f32).sum::<f32>() / dithered.len() as f32;
+        assert!((mean - 0.3).abs() < 0.05, "mean {}", mean);
+        assert_eq!(ditherer.to_i16(5.0), i16::MAX);
+    }
+
+    #[test]
+    fn test_channel_layouts() {
+        let stereo = [0.1, 0.3, 0.5, 0.7, 0.9];
+        let planes = deinterleave(&stereo, 2);
+        assert_eq!(planes, vec![vec![0.1, 0.5], vec![0.3, 0.7]]);
+        assert_eq!(interleave(&planes), vec![0.1, 0.3, 0.5, 0.7]);
+
+        let mono = downmix_to_mono(&[0.2, 0.4, -1.0, 1.0], 2);
+        assert!((mono[0] - 0.3).abs() < 1e-6 && mono[1] == 0.0);
+        assert_eq!(upmix_from_mono(&[0.5, -0.5], 2), vec![0.5, 0.5, -0.5, -0.5]);
+        assert_eq!(remix(&[0.5, 0.5], 2, 2), vec![0.5, 0.5]);
+        assert_eq!(remix(&[0.5, 0.5], 2, 1), vec![0.5]);
+    }
+
+    #[test]
+    fn test_block_adapter() {
+        let mut adapter = BlockAdapter::new(4);
+        assert!(adapter.push(&[1.0; 3]).is_empty());
+        let blocks = adapter.push(&[2.0; 6]);
+        assert_eq!(blocks, vec![vec![1.0, 1.0, 1.0, 2.0], vec![2.0; 4]]);
+        assert_eq!(adapter.pending(), 1);
+        adapter.set_block_len(2);
+        assert_eq!(adapter.pending(), 0);
+    }
+}
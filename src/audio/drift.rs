@@ -0,0 +1,193 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Most a peer's audio is sped up or slowed down to follow their clock: 0.5%
+pub const MAX_DRIFT_CORRECTION: f64 = 0.005;
+
+/// How far back the queue's trend is fitted over
+const DRIFT_WINDOW: Duration = Duration::from_secs(60);
+
+/// Least history the drift is estimated from; over less, jitter swamps it
+const MIN_DRIFT_SPAN: Duration = Duration::from_secs(10);
+
+/// Arrivals this far apart start the history afresh, as the queue drained
+/// in between for reasons that aren't drift
+const DRIFT_GAP: Duration = Duration::from_millis(500);
+
+/// Follows the drift between a peer's sound card clock and ours, and
+/// resamples their audio to cancel it
+///
+/// A peer whose card runs fast sends a little more audio than ours plays,
+/// so their queue slowly grows; a slow one's shrinks until it runs dry. The
+/// queue's trend, less whatever was padded, skipped or resampled away on
+/// purpose, is the drift. Stretching the audio by as much holds the queue
+/// steady over long calls without the jitter buffer having to step in.
+#[derive(Debug, Clone)]
+pub struct DriftCompensator {
+    origin: Option<Instant>,
+    last_arrival: Option<Instant>,
+    // Seconds since the origin, and the queue then less the audio added on purpose
+    fills: VecDeque<(f64, f64)>,
+    // Seconds of audio added to the queue on purpose, less those removed
+    adjusted: f64,
+    drift: f64,
+    // Where the next output sample is read, counting the last frame of the
+    // previous block as 0, and that frame
+    position: f64,
+    last: (f32, f32),
+}
+
+impl Default for DriftCompensator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DriftCompensator {
+    pub fn new() -> Self {
+        Self {
+            origin: None,
+            last_arrival: None,
+            fills: VecDeque::new(),
+            adjusted: 0.0,
+            drift: 0.0,
+            position: 0.0,
+            last: (0.0, 0.0),
+        }
+    }
+
+    /// Estimated rate the peer's audio arrives at beyond ours, as a share;
+    /// positive if their clock runs fast
+    pub fn drift(&self) -> f64 {
+        self.drift
+    }
+
+    /// Records a frame arriving with `queued` audio still waiting to be played
+    pub fn observe(&mut self, at: Instant, queued: Duration) {
+        let resumed = self
+            .last_arrival
+            .is_none_or(|last| at.saturating_duration_since(last) > DRIFT_GAP);
+        self.last_arrival = Some(at);
+        if resumed {
+            // The estimate so far still holds; clocks drift slowly
+            self.origin = Some(at);
+            self.fills.clear();
+            self.adjusted = 0.0;
+        }
+        // A queue that ran dry says how late the frame was, not how fast it came
+        if queued.is_zero() {
+            return;
+        }
+
+        let now = at
+            .saturating_duration_since(self.origin.unwrap_or(at))
+            .as_secs_f64();
+        self.fills
+            .push_back((now, queued.as_secs_f64() - self.adjusted));
+        while self
+            .fills
+            .front()
+            .is_some_and(|(time, _)| now - time > DRIFT_WINDOW.as_secs_f64())
+        {
+            self.fills.pop_front();
+        }
+        if let Some(drift) = self.fit() {
+            let previous = self.drift;
+            self.drift = drift.clamp(-MAX_DRIFT_CORRECTION, MAX_DRIFT_CORRECTION);
+            if (self.drift - previous).abs() > 0.000_1 {
+                log::debug!("Clock drift now {:.0} ppm", self.drift * 1e6);
+            }
+        }
+    }
+
+    /// Records audio added to the queue, such as padding, or removed if negative
+    pub fn adjust(&mut self, seconds: f64) {
+        self.adjusted += seconds;
+    }
+
+    /// The queue's slope over the history, once it's long enough
+    fn fit(&self) -> Option<f64> {
+        let (first, last) = (self.fills.front()?, self.fills.back()?);
+        if last.0 - first.0 < MIN_DRIFT_SPAN.as_secs_f64() {
+            return None;
+        }
+        let count = self.fills.len() as f64;
+        let (mean_time, mean_fill) = self
+            .fills
+            .iter()
+            .fold((0.0, 0.0), |(time, fill), (t, f)| (time + t, fill + f));
+        let (mean_time, mean_fill) = (mean_time / count, mean_fill / count);
+        let (covariance, variance) =
+            self.fills
+                .iter()
+                .fold((0.0, 0.0), |(covariance, variance), (time, fill)| {
+                    let dt = time - mean_time;
+                    (covariance + dt * (fill - mean_fill), variance + dt * dt)
+                });
+        (variance > 0.0).then(|| covariance / variance)
+    }
+
+    /// Resamples interleaved stereo audio to cancel the drift, carrying the
+    /// read position over from one block to the next so there are no seams
+    pub fn process(&mut self, stereo: &[f32], sample_rate: u32) -> Vec<f32> {
+        let frames = stereo.len() / 2;
+        if frames == 0 {
+            return Vec::new();
+        }
+        let step = 1.0 + self.drift;
+        let frame = |index: usize| match index {
+            0 => self.last,
+            _ => (stereo[index * 2 - 2], stereo[index * 2 - 1]),
+        };
+
+        let mut output = Vec::with_capacity(stereo.len() + 4);
+        let mut position = self.position;
+        while (position as usize) < frames {
+            let index = position as usize;
+            let fraction = (position - index as f64) as f32;
+            let (a, b) = (frame(index), frame(index + 1));
+            output.push(a.0 + (b.0 - a.0) * fraction);
+            output.push(a.1 + (b.1 - a.1) * fraction);
+            position += step;
+        }
+        self.position = position - frames as f64;
+        self.last = frame(frames);
+
+        let added = output.len() as f64 / 2.0 - frames as f64;
+        self.adjust(added / sample_rate as f64);
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_holds_a_fast_peer_queue_steady() {
+        let mut compensator = DriftCompensator::new();
+        let start = Instant::now();
+        let rate = 48000;
+        // 961 frames every 20 ms is a clock running about 1042 ppm fast
+        let block = vec![0.1; 961 * 2];
+        let mut queued = 0.06;
+
+        for n in 0..4500u32 {
+            let at = start + Duration::from_millis(20 * n as u64);
+            // A few milliseconds of jitter in how much is queued when it's measured
+            let jitter = ((n * 7919) % 11) as f64 / 1000.0 - 0.005;
+            compensator.observe(at, Duration::from_secs_f64(queued + jitter));
+            let played = compensator.process(&block, rate);
+            queued += played.len() as f64 / 2.0 / rate as f64 - 0.02;
+        }
+
+        assert!((compensator.drift() - 961.0 / 960.0 + 1.0).abs() < 0.000_05);
+        // Only the first seconds, before there was an estimate, went uncorrected
+        assert!(queued < 0.06 + 0.015, "queue grew to {}", queued);
+
+        // Resampling leaves the level of the audio alone
+        let played = compensator.process(&block, rate);
+        assert!(played.iter().all(|sample| (sample - 0.1).abs() < 1e-6));
+        assert!(played.len() < block.len());
+    }
+}
@@ -0,0 +1,193 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::capabilities::DeviceCapabilities;
+
+/// Identifies an audio device across replugs and restarts
+///
+/// Systems number devices that share a name, so the same interface can be
+/// "USB Audio" one day and "USB Audio (2)" the next. A fingerprint keeps the
+/// audio API and name the device was chosen under, plus a hash of the
+/// channel layouts it offers, so it can still be recognised when renamed.
+///
+/// Written as `in/<host>/<channels>/<name>` or `out/...`, the channel hash
+/// in hex.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct DeviceFingerprint {
+    pub is_input: bool,
+    /// Audio API the device was found through, such as `ALSA` or `WASAPI`
+    pub host: String,
+    pub name: String,
+    /// Hash of the channel counts the device supports
+    pub channels: u32,
+}
+
+/// How closely a device matches a fingerprint, best last
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DeviceMatch {
+    /// Same name, but the device now offers different channel layouts
+    Reconfigured,
+    /// Same device under a renumbered name, like "USB Audio (2)"
+    Renamed,
+    Exact,
+}
+
+impl DeviceFingerprint {
+    pub fn new(is_input: bool, host: &str, name: &str, channel_counts: &[u16]) -> Self {
+        let mut counts = channel_counts.to_vec();
+        counts.sort_unstable();
+        counts.dedup();
+
+        // FNV-1a, which stays the same across Rust versions unlike the std hasher
+        let mut hash: u32 = 0x811c_9dc5;
+        for count in counts {
+            for byte in count.to_le_bytes() {
+                hash ^= byte as u32;
+                hash = hash.wrapping_mul(0x0100_0193);
+            }
+        }
+
+        Self {
+            is_input,
+            host: host.to_string(),
+            name: name.to_string(),
+            channels: hash,
+        }
+    }
+
+    /// How well `other` matches this fingerprint, if it could be the same device
+    pub fn matches(&self, other: &DeviceFingerprint) -> Option<DeviceMatch> {
+        if self.is_input != other.is_input || self.host != other.host {
+            return None;
+        }
+        if self.name == other.name && self.channels == other.channels {
+            Some(DeviceMatch::Exact)
+        } else if base_name(&self.name) != base_name(&other.name) {
+            None
+        } else if self.channels == other.channels {
+            Some(DeviceMatch::Renamed)
+        } else {
+            Some(DeviceMatch::Reconfigured)
+        }
+    }
+
+    /// The device in `devices` most likely to be this one, if any could be
+    pub fn find<'a>(&self, devices: &'a [DeviceCapabilities]) -> Option<&'a DeviceCapabilities> {
+        // The first of equally good matches, as systems list the original first
+        let mut best: Option<(DeviceMatch, &DeviceCapabilities)> = None;
+        for device in devices {
+            if let Some(quality) = self.matches(&device.fingerprint) {
+                if best.is_none_or(|(best, _)| quality > best) {
+                    best = Some((quality, device));
+                }
+            }
+        }
+        best.map(|(_, device)| device)
+    }
+}
+
+/// A device name without the numbering systems add to tell duplicates apart
+///
+/// Handles PulseAudio and CoreAudio style suffixes ("USB Audio (2)") and
+/// Windows style prefixes ("2- USB Audio"), ignoring case.
+fn base_name(name: &str) -> String {
+    let mut name = name.trim();
+    if let Some(open) = name.rfind(" (") {
+        let suffix = &name[open + 2..];
+        if suffix
+            .strip_suffix(')')
+            .is_some_and(|number| number.parse::<u32>().is_ok())
+        {
+            name = &name[..open];
+        }
+    }
+    if let Some((number, rest)) = name.split_once("- ") {
+        if number.parse::<u32>().is_ok() {
+            name = rest;
+        }
+    }
+    name.trim().to_lowercase()
+}
+
+impl fmt::Display for DeviceFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{}/{:08x}/{}",
+            if self.is_input { "in" } else { "out" },
+            self.host,
+            self.channels,
+            self.name
+        )
+    }
+}
+
+impl FromStr for DeviceFingerprint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid device fingerprint: {}", s);
+        let mut parts = s.trim().splitn(4, '/');
+        let is_input = match parts.next() {
+            Some("in") => true,
+            Some("out") => false,
+            _ => return Err(invalid()),
+        };
+        let host = parts
+            .next()
+            .filter(|host| !host.is_empty())
+            .ok_or_else(invalid)?;
+        let channels = parts
+            .next()
+            .and_then(|hash| u32::from_str_radix(hash, 16).ok())
+            .ok_or_else(invalid)?;
+        let name = parts
+            .next()
+            .filter(|name| !name.is_empty())
+            .ok_or_else(invalid)?;
+        Ok(Self {
+            is_input,
+            host: host.to_string(),
+            name: name.to_string(),
+            channels,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_survives_renumbering() {
+        let usb = DeviceFingerprint::new(true, "ALSA", "USB Audio", &[2, 1, 2]);
+        assert_eq!(
+            usb.to_string().parse::<DeviceFingerprint>(),
+            Ok(usb.clone())
+        );
+        assert!("in/ALSA/zz/USB Audio".parse::<DeviceFingerprint>().is_err());
+        assert!("sideways/ALSA/00000000/USB"
+            .parse::<DeviceFingerprint>()
+            .is_err());
+
+        let renumbered = DeviceFingerprint::new(true, "ALSA", "USB Audio (2)", &[1, 2]);
+        let windows = DeviceFingerprint::new(true, "ALSA", "3- USB Audio", &[1, 2]);
+        let mono_only = DeviceFingerprint::new(true, "ALSA", "usb audio", &[1]);
+        assert_eq!(usb.matches(&usb), Some(DeviceMatch::Exact));
+        assert_eq!(usb.matches(&renumbered), Some(DeviceMatch::Renamed));
+        assert_eq!(usb.matches(&windows), Some(DeviceMatch::Renamed));
+        assert_eq!(usb.matches(&mono_only), Some(DeviceMatch::Reconfigured));
+
+        // Other devices, other directions and other audio APIs never match
+        let other = DeviceFingerprint::new(true, "ALSA", "Webcam Mic", &[1, 2]);
+        let output = DeviceFingerprint::new(false, "ALSA", "USB Audio", &[1, 2]);
+        let jack = DeviceFingerprint::new(true, "JACK", "USB Audio", &[1, 2]);
+        for fingerprint in [other, output, jack] {
+            assert_eq!(usb.matches(&fingerprint), None);
+        }
+        assert_eq!(
+            base_name("Speakers (Realtek(R) Audio)"),
+            "speakers (realtek(r) audio)"
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use crate::network::now_micros;
+
+/// A block of audio samples and where and when they came from
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioFrame {
+    /// Interleaved samples
+    pub samples: Vec<f32>,
+    /// Capture time in microseconds since the Unix epoch, on the capturing peer's clock
+    pub capture_ts: u64,
+    /// Position of this frame in its source's stream
+    pub seq: u64,
+    /// Source peer, or `None` for local capture
+    pub peer_id: Option<String>,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+impl Default for AudioFrame {
+    /// An empty mono frame
+    fn default() -> Self {
+        Self {
+            samples: Vec::new(),
+            capture_ts: 0,
+            seq: 0,
+            peer_id: None,
+            sample_rate: 48000,
+            channels: 1,
+        }
+    }
+}
+
+impl AudioFrame {
+    /// Creates a local frame captured now
+    pub fn new(samples: Vec<f32>, sample_rate: u32, channels: u16) -> Self {
+        Self {
+            samples,
+            capture_ts: now_micros(),
+            seq: 0,
+            peer_id: None,
+            sample_rate,
+            channels,
+        }
+    }
+
+    pub fn with_seq(mut self, seq: u64) -> Self {
+        self.seq = seq;
+        self
+    }
+
+    pub fn with_peer_id(mut self, peer_id: &str) -> Self {
+        self.peer_id = Some(peer_id.to_string());
+        self
+    }
+
+    pub fn with_capture_ts(mut self, capture_ts: u64) -> Self {
+        self.capture_ts = capture_ts;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Number of samples per channel
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels.max(1) as usize
+    }
+
+    /// Playback length of the frame
+    pub fn duration(&self) -> Duration {
+        if self.sample_rate == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(self.frames() as f64 / self.sample_rate as f64)
+    }
+
+    /// Loudest sample in the frame, from 0.0 to 1.0
+    pub fn level(&self) -> f32 {
+        self.samples
+            .iter()
+            .fold(0.0f32, |loudest, sample| loudest.max(sample.abs()))
+            .min(1.0)
+    }
+
+    /// Time from capture to `now_micros`, both on the same clock
+    pub fn latency(&self, now_micros: u64) -> Duration {
+        Duration::from_micros(now_micros.saturating_sub(self.capture_ts))
+    }
+
+    /// Number of frames missing between `previous` and this one
+    pub fn frames_lost_since(&self, previous: &AudioFrame) -> u64 {
+        self.seq.saturating_sub(previous.seq).saturating_sub(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_timing() {
+        let frame = AudioFrame::new(vec![0.0; 960], 48000, 2).with_capture_ts(1_000);
+
+        assert_eq!(frame.frames(), 480);
+        assert_eq!(frame.duration(), Duration::from_millis(10));
+        assert_eq!(frame.latency(26_000), Duration::from_millis(25));
+        assert_eq!(frame.latency(0), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_level_is_the_loudest_sample() {
+        assert_eq!(AudioFrame::default().level(), 0.0);
+        assert_eq!(AudioFrame::new(vec![0.1, -0.4, 0.2], 48000, 1).level(), 0.4);
+        assert_eq!(AudioFrame::new(vec![1.5], 48000, 1).level(), 1.0);
+    }
+
+    #[test]
+    fn test_frames_lost() {
+        let first = AudioFrame::new(vec![0.0; 4], 48000, 1).with_seq(7);
+
+        assert_eq!(first.clone().with_seq(8).frames_lost_since(&first), 0);
+        assert_eq!(first.clone().with_seq(11).frames_lost_since(&first), 3);
+        assert_eq!(first.clone().with_seq(7).frames_lost_since(&first), 0);
+    }
+}
@@ -0,0 +1,219 @@
+use anyhow::{anyhow, Context, Result};
+use std::f32::consts::PI;
+use std::fmt;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use symphonia::default::{get_codecs, get_probe};
+
+use super::audio_config::resample;
+use super::conversions::downmix_to_mono;
+
+/// Longest clip kept for hold audio; anything after this is cut off
+const MAX_CLIP_SECS: u32 = 30;
+
+/// Level of the hold tone, well below speech so it isn't intrusive
+const TONE_VOLUME: f32 = 0.1;
+
+/// What is sent instead of the microphone while we're away
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum HoldSource {
+    /// Silence, like a muted microphone
+    #[default]
+    None,
+    /// A soft repeating chime
+    Tone,
+    /// A short audio file played on a loop
+    Clip(PathBuf),
+}
+
+impl fmt::Display for HoldSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HoldSource::None => write!(f, "none"),
+            HoldSource::Tone => write!(f, "tone"),
+            HoldSource::Clip(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+impl FromStr for HoldSource {
+    type Err = String;
+
+    /// Parses `none`, `tone` or the path of a clip
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "" => Err("Missing hold audio".to_string()),
+            "none" => Ok(HoldSource::None),
+            "tone" => Ok(HoldSource::Tone),
+            path => Ok(HoldSource::Clip(PathBuf::from(path))),
+        }
+    }
+}
+
+/// Mono audio looped in place of the microphone
+#[derive(Debug, Clone)]
+pub struct HoldAudio {
+    samples: Vec<f32>,
+    position: usize,
+}
+
+impl HoldAudio {
+    /// Plays nothing
+    pub fn silence() -> Self {
+        Self {
+            samples: Vec::new(),
+            position: 0,
+        }
+    }
+
+    /// A two-note chime followed by a pause, repeating every two seconds
+    pub fn tone(sample_rate: u32) -> Self {
+        let note_len = sample_rate as usize / 4;
+        let fade = (sample_rate as usize / 200).max(1);
+        let mut samples = vec![0.0; sample_rate as usize * 2];
+
+        for (note, frequency) in [523.0f32, 392.0].iter().enumerate() {
+            for i in 0..note_len {
+                let edge = i.min(note_len - 1 - i);
+                let envelope = (edge as f32 / fade as f32).min(1.0);
+                let t = i as f32 / sample_rate as f32;
+                samples[note * note_len + i] =
+                    (2.0 * PI * frequency * t).sin() * envelope * TONE_VOLUME;
+            }
+        }
+
+        Self {
+            samples,
+            position: 0,
+        }
+    }
+
+    /// Decodes a clip, mixed down to mono and resampled to `sample_rate`
+    pub fn load(path: &Path, sample_rate: u32) -> Result<Self> {
+        let file =
+            File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        let stream = MediaSourceStream::new(Box::new(file), Default::default());
+
+        let mut hint = Hint::new();
+        if let Some(extension) = path.extension().and_then(|ext| ext.to_str()) {
+            hint.with_extension(extension);
+        }
+
+        let probed = get_probe()
+            .format(
+                &hint,
+                stream,
+                &FormatOptions::default(),
+                &MetadataOptions::default(),
+            )
+            .with_context(|| format!("Unsupported audio file {}", path.display()))?;
+        let mut format = probed.format;
+
+        let track = format
+            .default_track()
+            .ok_or_else(|| anyhow!("No audio in {}", path.display()))?;
+        let track_id = track.id;
+        let clip_rate = track
+            .codec_params
+            .sample_rate
+            .ok_or_else(|| anyhow!("Unknown sample rate in {}", path.display()))?;
+        let mut decoder = get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+
+        let max_len = (clip_rate * MAX_CLIP_SECS) as usize;
+        let mut mono = Vec::new();
+        while mono.len() < max_len {
+            let packet = match format.next_packet() {
+                Ok(packet) => packet,
+                // The reader reports the end of the file as an error
+                Err(_) => break,
+            };
+            if packet.track_id() != track_id {
+                continue;
+            }
+
+            let decoded = match decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                Err(e) => {
+                    log::debug!("Skipping undecodable packet in hold clip: {}", e);
+                    continue;
+                }
+            };
+            let channels = decoded.spec().channels.count() as u16;
+            let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, *decoded.spec());
+            buffer.copy_interleaved_ref(decoded);
+
+            mono.extend(downmix_to_mono(buffer.samples(), channels));
+        }
+        mono.truncate(max_len);
+
+        if mono.is_empty() {
+            return Err(anyhow!("No audio in {}", path.display()));
+        }
+
+        Ok(Self {
+            samples: resample(&mono, clip_rate, sample_rate),
+            position: 0,
+        })
+    }
+
+    /// Builds the hold audio for `source`
+    pub fn from_source(source: &HoldSource, sample_rate: u32) -> Result<Self> {
+        match source {
+            HoldSource::None => Ok(Self::silence()),
+            HoldSource::Tone => Ok(Self::tone(sample_rate)),
+            HoldSource::Clip(path) => Self::load(path, sample_rate),
+        }
+    }
+
+    /// Overwrites interleaved `samples` with the next stretch of the loop
+    pub fn fill(&mut self, samples: &mut [f32], channels: u16) {
+        if self.samples.is_empty() {
+            samples.fill(0.0);
+            return;
+        }
+
+        for frame in samples.chunks_mut(channels.max(1) as usize) {
+            frame.fill(self.samples[self.position]);
+            self.position = (self.position + 1) % self.samples.len();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_round_trip() {
+        for text in ["none", "tone", "/home/me/hold.mp3"] {
+            let source: HoldSource = text.parse().unwrap();
+            assert_eq!(source.to_string(), text);
+        }
+        assert_eq!("tone".parse(), Ok(HoldSource::Tone));
+        assert!("".parse::<HoldSource>().is_err());
+    }
+
+    #[test]
+    fn test_fill_loops_across_channels() {
+        let mut hold = HoldAudio::tone(8000);
+        let loop_len = hold.samples.len();
+
+        // A whole loop of stereo, then the start again
+        let mut stereo = vec![1.0; loop_len * 2 + 4];
+        hold.fill(&mut stereo, 2);
+        assert!(stereo.iter().all(|sample| sample.abs() <= TONE_VOLUME));
+        assert_eq!(stereo[..4], stereo[loop_len * 2..]);
+        assert!(stereo.chunks(2).all(|frame| frame[0] == frame[1]));
+
+        let mut silent = vec![1.0; 16];
+        HoldAudio::silence().fill(&mut silent, 1);
+        assert!(silent.iter().all(|sample| *sample == 0.0));
+    }
+}
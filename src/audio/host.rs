@@ -0,0 +1,265 @@
+use anyhow::{anyhow, Result};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::SampleFormat;
+use std::sync::Arc;
+
+/// The format a device streams in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviceFormat {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub sample_format: SampleFormat,
+}
+
+/// Formats a device also supports, over a range of sample rates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatRange {
+    pub channels: u16,
+    pub sample_format: SampleFormat,
+    pub min_rate: u32,
+    pub max_rate: u32,
+}
+
+impl FormatRange {
+    /// `format` at `sample_rate`, if this range has it
+    pub fn at(&self, format: DeviceFormat, sample_rate: u32) -> Option<DeviceFormat> {
+        (self.channels == format.channels
+            && self.sample_format == format.sample_format
+            && (self.min_rate..=self.max_rate).contains(&sample_rate))
+        .then_some(DeviceFormat {
+            sample_rate,
+            ..format
+        })
+    }
+}
+
+/// Samples a device captured, in its own sample format
+pub enum InputBuffer<'a> {
+    F32(&'a [f32]),
+    I16(&'a [i16]),
+    U16(&'a [u16]),
+}
+
+/// A buffer a device wants filled, in its own sample format
+pub enum OutputBuffer<'a> {
+    F32(&'a mut [f32]),
+    I16(&'a mut [i16]),
+    U16(&'a mut [u16]),
+}
+
+pub type InputCallback = Box<dyn FnMut(InputBuffer) + Send>;
+pub type OutputCallback = Box<dyn FnMut(OutputBuffer) + Send>;
+
+/// An open stream, which stops when dropped
+pub trait HostStream {
+    fn play(&self) -> Result<()>;
+    fn pause(&self) -> Result<()>;
+}
+
+/// Where devices and their streams come from
+///
+/// Devices are known by name. Capture and playback only reach the system's
+/// audio through this, so tests can swap in a host of their own.
+pub trait AudioHost: Send + Sync {
+    fn input_devices(&self) -> Result<Vec<String>>;
+    fn output_devices(&self) -> Result<Vec<String>>;
+    fn default_input_device(&self) -> Option<String>;
+    fn default_output_device(&self) -> Option<String>;
+
+    /// The format the device streams in unless asked otherwise
+    fn default_format(&self, device: &str, is_input: bool) -> Result<DeviceFormat>;
+
+    /// Every format the input device supports
+    fn input_formats(&self, device: &str) -> Result<Vec<FormatRange>>;
+
+    /// Opens an input stream, which is playing once built
+    fn build_input_stream(
+        &self,
+        device: &str,
+        format: DeviceFormat,
+        callback: InputCallback,
+    ) -> Result<Box<dyn HostStream>>;
+
+    /// Opens an output stream, which is playing once built
+    fn build_output_stream(
+        &self,
+        device: &str,
+        format: DeviceFormat,
+        callback: OutputCallback,
+    ) -> Result<Box<dyn HostStream>>;
+}
+
+/// The system's audio, through cpal's default host
+pub struct CpalHost;
+
+/// The host capture and playback use unless given another
+pub fn default_host() -> Arc<dyn AudioHost> {
+    Arc::new(CpalHost)
+}
+
+impl CpalHost {
+    fn device(&self, name: &str, is_input: bool) -> Result<cpal::Device> {
+        let host = cpal::default_host();
+        let named = |device: &cpal::Device| device.name().is_ok_and(|n| n == name);
+        let default = if is_input {
+            host.default_input_device()
+        } else {
+            host.default_output_device()
+        };
+        if let Some(device) = default.filter(named) {
+            return Ok(device);
+        }
+        let mut devices = if is_input {
+            host.input_devices()?
+        } else {
+            host.output_devices()?
+        };
+        devices
+            .find(named)
+            .ok_or_else(|| anyhow!("Audio device not found: {}", name))
+    }
+}
+
+fn names(devices: impl Iterator<Item = cpal::Device>) -> Vec<String> {
+    devices.filter_map(|device| device.name().ok()).collect()
+}
+
+fn stream_config(format: DeviceFormat) -> cpal::StreamConfig {
+    cpal::StreamConfig {
+        channels: format.channels,
+        sample_rate: cpal::SampleRate(format.sample_rate),
+        buffer_size: cpal::BufferSize::Default,
+    }
+}
+
+impl HostStream for cpal::Stream {
+    fn play(&self) -> Result<()> {
+        Ok(StreamTrait::play(self)?)
+    }
+
+    fn pause(&self) -> Result<()> {
+        Ok(StreamTrait::pause(self)?)
+    }
+}
+
+impl AudioHost for CpalHost {
+    fn input_devices(&self) -> Result<Vec<String>> {
+        Ok(names(cpal::default_host().input_devices()?))
+    }
+
+    fn output_devices(&self) -> Result<Vec<String>> {
+        Ok(names(cpal::default_host().output_devices()?))
+    }
+
+    fn default_input_device(&self) -> Option<String> {
+        cpal::default_host().default_input_device()?.name().ok()
+    }
+
+    fn default_output_device(&self) -> Option<String> {
+        cpal::default_host().default_output_device()?.name().ok()
+    }
+
+    fn default_format(&self, device: &str, is_input: bool) -> Result<DeviceFormat> {
+        let device = self.device(device, is_input)?;
+        let config = if is_input {
+            device.default_input_config()?
+        } else {
+            device.default_output_config()?
+        };
+        Ok(DeviceFormat {
+            sample_rate: config.sample_rate().0,
+            channels: config.channels(),
+            sample_format: config.sample_format(),
+        })
+    }
+
+    fn input_formats(&self, device: &str) -> Result<Vec<FormatRange>> {
+        let ranges = self.device(device, true)?.supported_input_configs()?;
+        Ok(ranges
+            .map(|range| FormatRange {
+                channels: range.channels(),
+                sample_format: range.sample_format(),
+                min_rate: range.min_sample_rate().0,
+                max_rate: range.max_sample_rate().0,
+            })
+            .collect())
+    }
+
+    fn build_input_stream(
+        &self,
+        device: &str,
+        format: DeviceFormat,
+        mut callback: InputCallback,
+    ) -> Result<Box<dyn HostStream>> {
+        let device = self.device(device, true)?;
+        let config = stream_config(format);
+        let err_fn = move |err| {
+            log::error!("an error occurred on the audio stream: {}", err);
+        };
+        let stream = match format.sample_format {
+            SampleFormat::F32 => device.build_input_stream(
+                &config,
+                move |data: &[f32], _: &cpal::InputCallbackInfo| callback(InputBuffer::F32(data)),
+                err_fn,
+                None,
+            ),
+            SampleFormat::I16 => device.build_input_stream(
+                &config,
+                move |data: &[i16], _: &cpal::InputCallbackInfo| callback(InputBuffer::I16(data)),
+                err_fn,
+                None,
+            ),
+            SampleFormat::U16 => device.build_input_stream(
+                &config,
+                move |data: &[u16], _: &cpal::InputCallbackInfo| callback(InputBuffer::U16(data)),
+                err_fn,
+                None,
+            ),
+            _ => return Err(anyhow!("Unsupported sample format")),
+        }?;
+        StreamTrait::play(&stream)?;
+        Ok(Box::new(stream))
+    }
+
+    fn build_output_stream(
+        &self,
+        device: &str,
+        format: DeviceFormat,
+        mut callback: OutputCallback,
+    ) -> Result<Box<dyn HostStream>> {
+        let device = self.device(device, false)?;
+        let config = stream_config(format);
+        let err_fn = move |err| {
+            log::error!("an error occurred on the playback stream: {}", err);
+        };
+        let stream = match format.sample_format {
+            SampleFormat::F32 => device.build_output_stream(
+                &config,
+                move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                    callback(OutputBuffer::F32(data))
+                },
+                err_fn,
+                None,
+            ),
+            SampleFormat::I16 => device.build_output_stream(
+                &config,
+                move |data: &mut [i16], _: &cpal::OutputCallbackInfo| {
+                    callback(OutputBuffer::I16(data))
+                },
+                err_fn,
+                None,
+            ),
+            SampleFormat::U16 => device.build_output_stream(
+                &config,
+                move |data: &mut [u16], _: &cpal::OutputCallbackInfo| {
+                    callback(OutputBuffer::U16(data))
+                },
+                err_fn,
+                None,
+            ),
+            _ => return Err(anyhow!("Unsupported sample format")),
+        }?;
+        StreamTrait::play(&stream)?;
+        Ok(Box::new(stream))
+    }
+}
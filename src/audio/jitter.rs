@@ -0,0 +1,339 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Arrivals the jitter percentile is taken over, about five seconds of 20 ms frames
+const JITTER_WINDOW: usize = 250;
+
+/// Share of arrivals the target delay must cover
+const JITTER_PERCENTILE: f64 = 0.95;
+
+/// Least and most audio held back for one peer
+pub const MIN_TARGET_DELAY: Duration = Duration::from_millis(20);
+pub const MAX_TARGET_DELAY: Duration = Duration::from_millis(400);
+
+/// Share of frames arriving after the buffer ran dry above which the target
+/// grows, whatever the percentile says
+const LATE_LOSS_LIMIT: f64 = 0.02;
+
+/// How far the target grows each time too many frames come late
+const LATE_STEP: Duration = Duration::from_millis(10);
+
+/// How much lower a new target must be, and for how long, before the target
+/// comes down to it
+///
+/// Growing is immediate, since late audio is heard as a gap; shrinking waits
+/// so a target riding the percentile doesn't wobble up and down.
+const SHRINK_MARGIN: Duration = Duration::from_millis(10);
+const SHRINK_HOLD: Duration = Duration::from_secs(2);
+
+/// A gap this long is the peer pausing, such as for silence, not jitter
+const PAUSE: Duration = Duration::from_secs(1);
+
+/// How far synchronized playout may be from its deadline before it's corrected
+const SYNC_TOLERANCE: Duration = Duration::from_millis(3);
+
+/// What to do with a frame to bring the audio queued for its peer to the target
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayoutAdjustment {
+    /// Play the frame as it is
+    Play,
+    /// Play this much silence first; the buffer ran dry and is built back up
+    Pad(Duration),
+    /// Skip this much of the start of the frame to catch up
+    Skip(Duration),
+}
+
+/// A peer's jitter buffer as it stands
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JitterStats {
+    /// Delay the buffer is aiming for
+    pub target: Duration,
+    /// Audio queued after the latest frame, if a device is playing the peer
+    pub achieved: Option<Duration>,
+    /// Inter-arrival jitter at the percentile the target covers
+    pub jitter: Duration,
+    /// Share of recent frames that arrived after the buffer ran dry
+    pub late_rate: f64,
+    /// The peer's clock drift against ours, as a share, where it's compensated
+    pub drift: f64,
+}
+
+/// When a peer's audio plays, for playback kept in step across the room
+///
+/// The peer stamps each frame with its capture time, and every listener
+/// plays it `delay` later, reading that time on their own clock through
+/// the estimated offset between the two. Listeners nearer the peer hold
+/// the audio back longer, so everyone hears it together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncedPlayout {
+    /// Time from capture to playout, the same for everyone in the room
+    pub delay: Duration,
+    /// The peer's clock minus ours, in microseconds
+    pub clock_offset: i64,
+}
+
+impl SyncedPlayout {
+    /// How long from `now_micros` on our clock until audio captured at
+    /// `capture_ts` on the peer's is due to play; zero if it's late
+    pub fn due_in(&self, capture_ts: u64, now_micros: u64) -> Duration {
+        let due = capture_ts as i64 - self.clock_offset + self.delay.as_micros() as i64;
+        Duration::from_micros((due - now_micros as i64).max(0) as u64)
+    }
+}
+
+/// Tunes how much of one peer's audio is held back before it's played
+///
+/// Each arrival's deviation from the frame's own duration is kept over a
+/// sliding window, and the target covers the 95th percentile of it plus a
+/// frame. Too many frames arriving after the buffer ran dry grows it further.
+#[derive(Debug, Clone)]
+pub struct JitterTuner {
+    last_arrival: Option<Instant>,
+    // Each arrival's jitter, and whether it came after the buffer ran dry
+    arrivals: VecDeque<(Duration, bool)>,
+    target: Duration,
+    // When a lower target first looked enough, while it still does
+    shrink_since: Option<Instant>,
+    achieved: Option<Duration>,
+}
+
+impl Default for JitterTuner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JitterTuner {
+    pub fn new() -> Self {
+        Self {
+            last_arrival: None,
+            arrivals: VecDeque::with_capacity(JITTER_WINDOW),
+            target: MIN_TARGET_DELAY,
+            shrink_since: None,
+            achieved: None,
+        }
+    }
+
+    /// Records a frame of `frame` length arriving with `queued` audio still
+    /// waiting to be played for the peer, or `None` if nothing plays it, and
+    /// says how to play the frame
+    pub fn arrive(
+        &mut self,
+        at: Instant,
+        frame: Duration,
+        queued: Option<Duration>,
+    ) -> PlayoutAdjustment {
+        let ran_dry = self.record(at, frame, queued);
+        let Some(queued) = queued else {
+            self.achieved = None;
+            return PlayoutAdjustment::Play;
+        };
+        let (adjustment, achieved) = if ran_dry {
+            let pad = self.target.saturating_sub(frame);
+            (PlayoutAdjustment::Pad(pad), pad + frame)
+        } else if queued > self.target + frame {
+            // At most half the frame, so catching up isn't heard as a jump
+            let skip = (queued - self.target).min(frame / 2);
+            (PlayoutAdjustment::Skip(skip), queued + frame - skip)
+        } else {
+            (PlayoutAdjustment::Play, queued + frame)
+        };
+        if adjustment != PlayoutAdjustment::Play {
+            log::trace!("Jitter buffer {:?} towards {:?}", adjustment, self.target);
+        }
+        self.achieved = Some(achieved);
+        adjustment
+    }
+
+    /// Like [`JitterTuner::arrive`], but brings the queued audio to `due`,
+    /// how long until the frame must start playing, rather than the target
+    pub fn arrive_synced(
+        &mut self,
+        at: Instant,
+        frame: Duration,
+        queued: Option<Duration>,
+        due: Duration,
+    ) -> PlayoutAdjustment {
+        self.record(at, frame, queued);
+        let Some(queued) = queued else {
+            self.achieved = None;
+            return PlayoutAdjustment::Play;
+        };
+        let (adjustment, achieved) = if queued + SYNC_TOLERANCE < due {
+            (PlayoutAdjustment::Pad(due - queued), due + frame)
+        } else if queued > due + SYNC_TOLERANCE {
+            let skip = (queued - due).min(frame / 2);
+            (PlayoutAdjustment::Skip(skip), queued + frame - skip)
+        } else {
+            (PlayoutAdjustment::Play, queued + frame)
+        };
+        self.achieved = Some(achieved);
+        adjustment
+    }
+
+    /// Records an arrival and retunes the target, returning whether the
+    /// buffer had run dry
+    fn record(&mut self, at: Instant, frame: Duration, queued: Option<Duration>) -> bool {
+        let ran_dry = queued.is_some_and(|queued| queued.is_zero());
+        match self.last_arrival {
+            Some(last) if at.saturating_duration_since(last) < PAUSE => {
+                let gap = at.saturating_duration_since(last);
+                let jitter = gap.abs_diff(frame);
+                if self.arrivals.len() == JITTER_WINDOW {
+                    self.arrivals.pop_front();
+                }
+                self.arrivals.push_back((jitter, ran_dry));
+            }
+            _ => {}
+        }
+        self.last_arrival = Some(at);
+        self.retune(at, frame);
+        ran_dry
+    }
+
+    pub fn stats(&self) -> JitterStats {
+        JitterStats {
+            target: self.target,
+            achieved: self.achieved,
+            jitter: self.jitter(),
+            late_rate: self.late_rate(),
+            drift: 0.0,
+        }
+    }
+
+    fn jitter(&self) -> Duration {
+        if self.arrivals.is_empty() {
+            return Duration::ZERO;
+        }
+        let mut jitters: Vec<Duration> = self.arrivals.iter().map(|(jitter, _)| *jitter).collect();
+        jitters.sort();
+        let index = ((jitters.len() - 1) as f64 * JITTER_PERCENTILE).round() as usize;
+        jitters[index]
+    }
+
+    fn late_rate(&self) -> f64 {
+        if self.arrivals.is_empty() {
+            return 0.0;
+        }
+        let late = self.arrivals.iter().filter(|(_, late)| *late).count();
+        late as f64 / self.arrivals.len() as f64
+    }
+
+    fn retune(&mut self, now: Instant, frame: Duration) {
+        let mut wanted = (self.jitter() + frame).clamp(MIN_TARGET_DELAY, MAX_TARGET_DELAY);
+        if self.late_rate() > LATE_LOSS_LIMIT {
+            wanted = wanted.max(self.target + LATE_STEP).min(MAX_TARGET_DELAY);
+            // The late frames are answered; judge the new target afresh
+            for (_, late) in self.arrivals.iter_mut() {
+                *late = false;
+            }
+        }
+
+        if wanted >= self.target {
+            self.target = wanted;
+            self.shrink_since = None;
+        } else if self.target - wanted < SHRINK_MARGIN {
+            self.shrink_since = None;
+        } else {
+            let since = *self.shrink_since.get_or_insert(now);
+            if now.saturating_duration_since(since) >= SHRINK_HOLD {
+                self.target = wanted;
+                self.shrink_since = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_follows_jitter_with_hysteresis() {
+        let frame = Duration::from_millis(20);
+        let mut tuner = JitterTuner::new();
+        let mut at = Instant::now();
+        let queued = Some(Duration::from_millis(20));
+
+        // Arrivals alternately 30 ms late and 19 ms early
+        for i in 0..100 {
+            at += if i % 2 == 0 {
+                Duration::from_millis(50)
+            } else {
+                Duration::from_millis(1)
+            };
+            tuner.arrive(at, frame, queued);
+        }
+        let jittery = tuner.stats().target;
+        assert!(jittery >= Duration::from_millis(49), "{:?}", jittery);
+
+        // Steady again, the target waits before coming down
+        let steady_from = at;
+        while at.duration_since(steady_from) < SHRINK_HOLD {
+            at += frame;
+            tuner.arrive(at, frame, queued);
+        }
+        assert_eq!(tuner.stats().target, jittery);
+        for _ in 0..JITTER_WINDOW * 2 {
+            at += frame;
+            tuner.arrive(at, frame, queued);
+        }
+        assert_eq!(tuner.stats().target, MIN_TARGET_DELAY);
+
+        // Running dry builds the buffer back up to the target, and too
+        // often grows it
+        let before = tuner.stats().target;
+        for _ in 0..10 {
+            at += frame;
+            assert_eq!(
+                tuner.arrive(at, frame, Some(Duration::ZERO)),
+                PlayoutAdjustment::Pad(tuner.stats().target.saturating_sub(frame))
+            );
+        }
+        assert!(tuner.stats().target > before);
+
+        // Far ahead of the target, part of the frame is skipped
+        at += frame;
+        assert_eq!(
+            tuner.arrive(at, frame, Some(Duration::from_secs(1))),
+            PlayoutAdjustment::Skip(frame / 2)
+        );
+        assert_eq!(
+            tuner.arrive(at + frame, frame, None),
+            PlayoutAdjustment::Play
+        );
+        assert_eq!(tuner.stats().achieved, None);
+    }
+
+    #[test]
+    fn test_synced_playout_meets_its_deadline() {
+        // The peer's clock is 5 ms ahead, and everyone plays 200 ms after capture
+        let sync = SyncedPlayout {
+            delay: Duration::from_millis(200),
+            clock_offset: 5_000,
+        };
+        assert_eq!(
+            sync.due_in(1_005_000, 1_050_000),
+            Duration::from_millis(150)
+        );
+        assert_eq!(sync.due_in(1_005_000, 1_300_000), Duration::ZERO);
+
+        let frame = Duration::from_millis(20);
+        let due = Duration::from_millis(150);
+        let mut tuner = JitterTuner::new();
+        let at = Instant::now();
+        assert_eq!(
+            tuner.arrive_synced(at, frame, Some(Duration::from_millis(40)), due),
+            PlayoutAdjustment::Pad(Duration::from_millis(110))
+        );
+        assert_eq!(tuner.stats().achieved, Some(due + frame));
+        assert_eq!(
+            tuner.arrive_synced(at, frame, Some(Duration::from_millis(152)), due),
+            PlayoutAdjustment::Play
+        );
+        assert_eq!(
+            tuner.arrive_synced(at, frame, Some(Duration::from_millis(158)), due),
+            PlayoutAdjustment::Skip(Duration::from_millis(8))
+        );
+    }
+}
@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+
+/// Radius of the circle layout, in meters
+const CIRCLE_RADIUS: f32 = 2.0;
+
+/// Distance between neighbouring seats in the grid and classroom layouts, in meters
+const SEAT_SPACING: f32 = 1.5;
+
+/// Seats per row facing the presenter
+const CLASSROOM_ROW: usize = 4;
+
+/// How participants are placed in virtual space
+///
+/// Positions are handed out in seating order, the first seat going to the
+/// host. In the presenter layout that's the seat at the front.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SpatialLayout {
+    /// Everyone around a circle, facing its center
+    #[default]
+    Circle,
+    /// Rows and columns of equal size
+    Grid,
+    /// The presenter at the front, everyone else in rows facing them
+    PresenterFront,
+}
+
+impl SpatialLayout {
+    /// All layouts, in the order they're cycled through
+    pub const ALL: [SpatialLayout; 3] = [
+        SpatialLayout::Circle,
+        SpatialLayout::Grid,
+        SpatialLayout::PresenterFront,
+    ];
+
+    /// Name used in settings
+    pub fn code(&self) -> &'static str {
+        match self {
+            SpatialLayout::Circle => "circle",
+            SpatialLayout::Grid => "grid",
+            SpatialLayout::PresenterFront => "presenter",
+        }
+    }
+
+    /// The layout after this one
+    pub fn next(&self) -> SpatialLayout {
+        let index = Self::ALL
+            .iter()
+            .position(|layout| layout == self)
+            .unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Positions (x, y, z) for `count` participants in seating order
+    pub fn positions(&self, count: usize) -> Vec<(f32, f32, f32)> {
+        match self {
+            SpatialLayout::Circle => (0..count)
+                .map(|i| {
+                    // y is up, so the circle lies in x and z
+                    let angle = 2.0 * PI * i as f32 / count as f32;
+                    (
+                        CIRCLE_RADIUS * angle.cos(),
+                        0.0,
+                        CIRCLE_RADIUS * angle.sin(),
+                    )
+                })
+                .collect(),
+            SpatialLayout::Grid => {
+                let columns = (count as f32).sqrt().ceil().max(1.0) as usize;
+                (0..count)
+                    .map(|i| seat(i % columns, i / columns, columns, 0.0))
+                    .collect()
+            }
+            SpatialLayout::PresenterFront => {
+                let mut positions = Vec::with_capacity(count);
+                if count > 0 {
+                    positions.push((0.0, 0.0, -SEAT_SPACING));
+                }
+                let audience = count.saturating_sub(1);
+                let columns = audience.clamp(1, CLASSROOM_ROW);
+                positions.extend(
+                    (0..audience).map(|i| seat(i % columns, i / columns, columns, SEAT_SPACING)),
+                );
+                positions
+            }
+        }
+    }
+
+    /// Which way (yaw, in radians) the participant in `seat` of `count` faces
+    pub fn facing(&self, seat: usize, count: usize) -> f32 {
+        match self {
+            // Inward, which is the opposite of the seat's angle
+            SpatialLayout::Circle if count > 0 => -2.0 * PI * seat as f32 / count as f32,
+            // The presenter faces the audience
+            SpatialLayout::PresenterFront if seat == 0 => PI,
+            _ => 0.0,
+        }
+    }
+}
+
+/// A seat in a row of `columns` centered on x = 0, rows going back from `front`
+fn seat(column: usize, row: usize, columns: usize, front: f32) -> (f32, f32, f32) {
+    let x = (column as f32 - (columns - 1) as f32 / 2.0) * SEAT_SPACING;
+    (x, 0.0, front + row as f32 * SEAT_SPACING)
+}
+
+impl fmt::Display for SpatialLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+impl FromStr for SpatialLayout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .find(|layout| layout.code() == s)
+            .copied()
+            .ok_or_else(|| format!("Unknown spatial layout: {}", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn distinct(positions: &[(f32, f32, f32)]) -> bool {
+        positions.iter().enumerate().all(|(i, a)| {
+            positions[i + 1..]
+                .iter()
+                .all(|b| (a.0 - b.0).abs() + (a.2 - b.2).abs() > 0.1)
+        })
+    }
+
+    #[test]
+    fn test_every_layout_seats_everyone_apart() {
+        for layout in SpatialLayout::ALL {
+            for count in [0, 1, 2, 5, 9, 13] {
+                let positions = layout.positions(count);
+                assert_eq!(positions.len(), count, "{} with {}", layout, count);
+                assert!(distinct(&positions), "{} with {}", layout, count);
+            }
+            assert_eq!(layout.code().parse(), Ok(layout));
+        }
+        assert!("theatre".parse::<SpatialLayout>().is_err());
+    }
+
+    #[test]
+    fn test_presenter_sits_in_front_of_the_rows() {
+        let positions = SpatialLayout::PresenterFront.positions(6);
+        assert_eq!(positions[0], (0.0, 0.0, -SEAT_SPACING));
+        assert!(positions[1..].iter().all(|(_, _, z)| *z > 0.0));
+
+        // A full front row, the fifth listener behind it
+        assert!(positions[1..5].iter().all(|(_, _, z)| *z == SEAT_SPACING));
+        assert_eq!(positions[5].2, 2.0 * SEAT_SPACING);
+    }
+
+    #[test]
+    fn test_grid_is_centered() {
+        let positions = SpatialLayout::Grid.positions(4);
+        let mean_x: f32 = positions.iter().map(|(x, _, _)| x).sum::<f32>() / 4.0;
+        assert!(mean_x.abs() < 1e-6);
+        assert_eq!(SpatialLayout::Grid.next(), SpatialLayout::PresenterFront);
+        assert_eq!(SpatialLayout::PresenterFront.next(), SpatialLayout::Circle);
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Span audio processing time is averaged over
+pub const LOAD_WINDOW: Duration = Duration::from_secs(1);
+
+/// How long load must stay over the limit before quality is lowered a step
+pub const SUSTAIN: Duration = Duration::from_secs(3);
+
+/// Share of one core audio processing may use before quality is lowered, unless configured
+pub const DEFAULT_LOAD_LIMIT: f64 = 0.8;
+
+/// A processing stage given up to keep up with the audio, in the order they go
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Degradation {
+    /// Plain stereo panning instead of ear filtering, time differences and reverb
+    SimplePanning,
+    /// The codec is told to spend less CPU on encoding, at some cost in quality
+    LowCodecComplexity,
+}
+
+impl Degradation {
+    /// All steps, cheapest to give up first
+    pub const ORDER: [Degradation; 2] =
+        [Degradation::SimplePanning, Degradation::LowCodecComplexity];
+}
+
+/// Tracks how much of a core audio processing takes and lowers quality when it's too much
+///
+/// Each step is taken once load has stayed over the limit for [`SUSTAIN`],
+/// so a brief spike doesn't cost quality. Steps aren't undone on their own:
+/// load drops because of them, and undoing them would bring it back.
+#[derive(Debug, Clone)]
+pub struct LoadMonitor {
+    limit: Option<f64>,
+    samples: VecDeque<(Instant, Duration)>,
+    busy: Duration,
+    over_since: Option<Instant>,
+    applied: Vec<Degradation>,
+    // Steps taken that nobody has been told about yet
+    unreported: Vec<Degradation>,
+}
+
+impl Default for LoadMonitor {
+    fn default() -> Self {
+        Self::new(Some(DEFAULT_LOAD_LIMIT))
+    }
+}
+
+impl LoadMonitor {
+    /// Creates a monitor lowering quality above `limit`, a share of one core; `None` never does
+    pub fn new(limit: Option<f64>) -> Self {
+        Self {
+            limit,
+            samples: VecDeque::new(),
+            busy: Duration::ZERO,
+            over_since: None,
+            applied: Vec::new(),
+            unreported: Vec::new(),
+        }
+    }
+
+    pub fn set_limit(&mut self, limit: Option<f64>) {
+        self.limit = limit;
+        self.over_since = None;
+    }
+
+    /// Records time spent processing audio, returning a step to take if one is due
+    pub fn record(&mut self, busy: Duration, now: Instant) -> Option<Degradation> {
+        self.prune(now);
+        self.samples.push_back((now, busy));
+        self.busy += busy;
+
+        let limit = self.limit?;
+        if self.utilization(now) <= limit {
+            self.over_since = None;
+            return None;
+        }
+        let since = *self.over_since.get_or_insert(now);
+        if now.saturating_duration_since(since) < SUSTAIN {
+            return None;
+        }
+
+        // The next step needs another sustained stretch over the limit
+        self.over_since = None;
+        let step = Degradation::ORDER
+            .into_iter()
+            .find(|step| !self.applied.contains(step))?;
+        self.applied.push(step);
+        self.unreported.push(step);
+        Some(step)
+    }
+
+    /// Share of one core spent processing audio over the last [`LOAD_WINDOW`]
+    pub fn utilization(&mut self, now: Instant) -> f64 {
+        self.prune(now);
+        self.busy.as_secs_f64() / LOAD_WINDOW.as_secs_f64()
+    }
+
+    pub fn is_applied(&self, step: Degradation) -> bool {
+        self.applied.contains(&step)
+    }
+
+    /// Steps taken since the last call, oldest first
+    pub fn take_unreported(&mut self) -> Vec<Degradation> {
+        std::mem::take(&mut self.unreported)
+    }
+
+    fn prune(&mut self, now: Instant) {
+        while let Some(&(at, busy)) = self.samples.front() {
+            if now.saturating_duration_since(at) < LOAD_WINDOW {
+                break;
+            }
+            self.samples.pop_front();
+            self.busy -= busy;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_steps_down_on_sustained_load() {
+        let start = Instant::now();
+        let mut monitor = LoadMonitor::new(Some(0.5));
+        let tick = Duration::from_millis(20);
+        let mut steps = Vec::new();
+
+        // 18 ms of work per 20 ms frame, for ten seconds
+        for i in 0..500 {
+            let now = start + tick * i;
+            steps.extend(monitor.record(Duration::from_millis(18), now));
+        }
+        assert_eq!(steps, Degradation::ORDER);
+        assert!(monitor.is_applied(Degradation::SimplePanning));
+        assert_eq!(monitor.take_unreported(), Degradation::ORDER);
+        assert!(monitor.take_unreported().is_empty());
+
+        // A spike shorter than the sustain time costs nothing
+        let mut monitor = LoadMonitor::new(Some(0.5));
+        for i in 0..100 {
+            let busy = if i < 50 { 18 } else { 2 };
+            let step = monitor.record(Duration::from_millis(busy), start + tick * i);
+            assert_eq!(step, None);
+        }
+        assert!(monitor.utilization(start + tick * 100) < 0.5);
+
+        // Without a limit quality is never lowered
+        let mut monitor = LoadMonitor::new(None);
+        for i in 0..500 {
+            assert_eq!(monitor.record(tick, start + tick * i), None);
+        }
+    }
+}
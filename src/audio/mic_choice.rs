@@ -0,0 +1,138 @@
+use super::capabilities::DeviceCapabilities;
+
+/// Name fragments of virtual cables, loopbacks and other inputs that aren't microphones
+const NOT_MICROPHONES: [&str; 11] = [
+    "cable",
+    "virtual",
+    "loopback",
+    "monitor of",
+    "stereo mix",
+    "what u hear",
+    "voicemeeter",
+    "blackhole",
+    "soundflower",
+    "vb-audio",
+    "null",
+];
+
+/// Name fragments of devices made for calls, like headsets
+const CALL_DEVICES: [&str; 4] = ["headset", "communication", "hands-free", "handsfree"];
+
+/// How sensible a microphone an input device looks, and why
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MicScore {
+    pub score: i32,
+    pub reasons: Vec<&'static str>,
+}
+
+/// Scores an input device as a microphone for calls, higher being better
+///
+/// Only names, channel counts and sample rates are known, so this is a
+/// guess: devices made for calls and offering 48 kHz mono or stereo come
+/// first, and virtual cables and loopbacks, which carry other apps' audio
+/// rather than a voice, come last.
+pub fn score_microphone(capabilities: &DeviceCapabilities) -> MicScore {
+    let name = capabilities.device.id.to_lowercase();
+    let mut score = MicScore {
+        score: 0,
+        reasons: Vec::new(),
+    };
+    let mut add = |points: i32, reason: &'static str| {
+        score.score += points;
+        score.reasons.push(reason);
+    };
+
+    if NOT_MICROPHONES
+        .iter()
+        .any(|fragment| name.contains(fragment))
+    {
+        add(-100, "looks like a virtual cable or loopback");
+    }
+    if CALL_DEVICES.iter().any(|fragment| name.contains(fragment)) {
+        add(30, "made for calls");
+    }
+    if name.contains("mic") {
+        add(10, "named as a microphone");
+    }
+
+    if capabilities.sample_rates.contains(&48000) {
+        add(20, "supports 48 kHz");
+    } else if capabilities.sample_rates.contains(&16000) {
+        add(10, "supports 16 kHz");
+    } else if capabilities.sample_rates.is_empty() {
+        add(-50, "supports no common rate");
+    }
+
+    let channels = &capabilities.channel_counts;
+    if channels.iter().any(|&count| count == 1 || count == 2) {
+        add(10, "mono or stereo");
+    } else if channels.iter().all(|&count| count > 8) && !channels.is_empty() {
+        add(-10, "only many channels");
+    }
+    score
+}
+
+/// The input device most likely to be a sensible microphone, if there are any
+///
+/// The first of equally good devices wins, as systems list their own first.
+pub fn choose_microphone(devices: &[DeviceCapabilities]) -> Option<&DeviceCapabilities> {
+    let mut best: Option<(i32, &DeviceCapabilities)> = None;
+    for device in devices.iter().filter(|device| device.device.is_input) {
+        let score = score_microphone(device);
+        log::debug!(
+            "Microphone {} scores {} ({})",
+            device.device.id,
+            score.score,
+            score.reasons.join(", ")
+        );
+        if best.is_none_or(|(best, _)| score.score > best) {
+            best = Some((score.score, device));
+        }
+    }
+    if let Some((score, device)) = best {
+        log::info!(
+            "Chose {} as the microphone, scoring {}",
+            device.device.id,
+            score
+        );
+    }
+    best.map(|(_, device)| device)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::{AudioDevice, ExclusiveMode};
+
+    fn input(id: &str, rates: &[u32], channels: &[u16]) -> DeviceCapabilities {
+        DeviceCapabilities {
+            device: AudioDevice {
+                id: id.to_string(),
+                name: id.to_string(),
+                is_input: true,
+            },
+            sample_rates: rates.to_vec(),
+            default_sample_rate: rates.last().copied(),
+            min_buffer_frames: None,
+            exclusive: ExclusiveMode::SharedOnly,
+            channel_counts: channels.to_vec(),
+            fingerprint: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_prefers_call_devices_over_cables() {
+        let cable = input("CABLE Output (VB-Audio Virtual Cable)", &[48000], &[2]);
+        let built_in = input("Built-in Microphone", &[44100, 48000], &[1]);
+        let headset = input("Jabra Headset", &[16000], &[1]);
+        let interface = input("MADIface", &[48000], &[64]);
+
+        let devices = [cable.clone(), interface, built_in, headset];
+        assert_eq!(
+            choose_microphone(&devices).map(|device| device.device.id.as_str()),
+            Some("Jabra Headset")
+        );
+        assert!(score_microphone(&cable).score < 0);
+        assert_eq!(choose_microphone(&[]).map(|device| &device.device.id), None);
+    }
+}
@@ -1,10 +1,81 @@
+mod audio_config;
+mod calibration;
+mod capabilities;
 mod capture;
+mod channel_map;
+mod codec;
+mod comfort_noise;
+pub mod conversions;
+mod drift;
+mod fingerprint;
+mod frame;
+mod hold_audio;
+mod host;
+mod jitter;
+mod layout;
+mod load;
+mod mic_choice;
+mod motion;
+mod network_monitor;
+mod playback;
+mod repacketizer;
+mod runtime;
+mod sanitize;
+mod smoothing;
 mod spatial;
+mod spatial_preview;
+mod stream_role;
 pub mod streams;
+mod system_audio;
+mod system_mute;
+pub mod transcription;
+mod ui_sounds;
+#[cfg(test)]
+mod virtual_host;
 mod voice;
 
+pub use audio_config::AudioConfig;
+pub use calibration::{
+    Calibration, CalibrationError, CalibrationPhase, CalibrationRun, DeviceCalibrations,
+    DEFAULT_DEVICE_ID, QUIET_PHASE, SPEAK_PHASE,
+};
+pub use capabilities::{probe_device, probe_devices, DeviceCapabilities, ExclusiveMode};
 pub use capture::generate_test_audio;
 pub use capture::AudioCapture;
+pub use capture::AudioDevice;
+pub use capture::AudioDeviceManager;
+pub use capture::WatchdogEvent;
+pub use channel_map::ChannelMap;
+pub use codec::{
+    append_codec, codec_from_link, negotiate, AudioCodec, AudioDecoder, AudioEncoder, CodecSet,
+    CodecSettings, DEFAULT_BITRATE_KBPS,
+};
+pub use comfort_noise::{
+    noise_frame_len, ComfortNoiseGenerator, NoiseDescriptor, NoiseEstimator, SilenceSuppressor,
+    Transmission, DESCRIPTOR_INTERVAL, HANGOVER_FRAMES, LPC_ORDER,
+};
+pub use drift::DriftCompensator;
+pub use fingerprint::{DeviceFingerprint, DeviceMatch};
+pub use frame::AudioFrame;
+pub use hold_audio::{HoldAudio, HoldSource};
+pub use jitter::{JitterStats, JitterTuner, PlayoutAdjustment, SyncedPlayout};
+pub use layout::SpatialLayout;
+pub use load::{Degradation, LoadMonitor, DEFAULT_LOAD_LIMIT, LOAD_WINDOW, SUSTAIN};
+pub use mic_choice::choose_microphone;
+pub use motion::{doppler_factor, MotionEffect, MotionQuality, MotionSettings};
+pub use network_monitor::NetworkMonitor;
+pub use playback::{AudioPlayback, OutputRouting, OutputSource, HANGUP_FADE};
+pub use repacketizer::{
+    append_frame_duration, frame_duration_from_link, FrameDuration, Repacketizer,
+};
+pub use runtime::AudioRuntime;
+pub use sanitize::{clean_sample, sanitize, total_faults, SampleFaults};
+pub use smoothing::{PositionSmoother, POSITION_GLIDE};
 pub use spatial::SpatialAudioProcessor;
+pub use spatial_preview::{PreviewDirection, SpatialPreview, PREVIEW_BLOCK};
+pub use stream_role::{set_stream_role, StreamRole};
 pub use streams::AudioStreamManager;
+pub use system_audio::SystemAudio;
+pub use system_mute::{detect_system_mute, PulseAudioMute, SystemMute, SystemMuteWatcher};
+pub use ui_sounds::{UiSound, UiSoundSettings};
 pub use voice::VoiceProcessor;
@@ -0,0 +1,218 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Speed of sound in air, in meters per second
+pub const SPEED_OF_SOUND: f32 = 343.0;
+
+/// Furthest Doppler shift applied, as a playback rate either side of 1.0
+const MAX_SHIFT: f32 = 0.1;
+
+/// Loudest and quietest a moving source gets from its motion alone
+const MOTION_GAIN_RANGE: (f32, f32) = (0.7, 1.4);
+
+/// How carefully motion effects are rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MotionQuality {
+    /// One shift per frame with linear interpolation
+    #[default]
+    Low,
+    /// Shift eased across each frame with cubic interpolation; several times the CPU
+    High,
+}
+
+impl MotionQuality {
+    /// Name used in settings
+    pub fn code(&self) -> &'static str {
+        match self {
+            MotionQuality::Low => "low",
+            MotionQuality::High => "high",
+        }
+    }
+}
+
+impl fmt::Display for MotionQuality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+impl FromStr for MotionQuality {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "low" => Ok(MotionQuality::Low),
+            "high" => Ok(MotionQuality::High),
+            _ => Err(format!("Unknown motion effects quality: {}", s)),
+        }
+    }
+}
+
+/// Whether moving participants get Doppler and motion effects, and at what quality
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MotionSettings {
+    pub enabled: bool,
+    pub quality: MotionQuality,
+}
+
+/// Playback rate for a source heard by a listener, from their positions and velocities
+///
+/// Above 1.0 the source is closing in and sounds higher; below, it's
+/// moving away. The shift is limited so fast jumps don't warble.
+pub fn doppler_factor(
+    listener: ((f32, f32, f32), (f32, f32, f32)),
+    source: ((f32, f32, f32), (f32, f32, f32)),
+) -> f32 {
+    let ((lp, lv), (sp, sv)) = (listener, source);
+    let to_listener = (lp.0 - sp.0, lp.1 - sp.1, lp.2 - sp.2);
+    let distance = (to_listener.0 * to_listener.0
+        + to_listener.1 * to_listener.1
+        + to_listener.2 * to_listener.2)
+        .sqrt();
+    if distance < f32::EPSILON {
+        return 1.0;
+    }
+
+    // Speeds along the line from source to listener
+    let along = |v: (f32, f32, f32)| {
+        (v.0 * to_listener.0 + v.1 * to_listener.1 + v.2 * to_listener.2) / distance
+    };
+    let listener_speed = along(lv);
+    let source_speed = along(sv).min(SPEED_OF_SOUND * 0.5);
+
+    let factor = (SPEED_OF_SOUND - listener_speed) / (SPEED_OF_SOUND - source_speed);
+    factor.clamp(1.0 - MAX_SHIFT, 1.0 + MAX_SHIFT)
+}
+
+/// Doppler shift and motion loudness for one moving source
+///
+/// Each frame is resampled at the Doppler rate into a frame of the same
+/// length, reading on from where the last frame stopped. Sources closing in
+/// are a little louder, and sources moving away a little quieter.
+#[derive(Debug, Clone)]
+pub struct MotionEffect {
+    quality: MotionQuality,
+    factor: f32,
+    // Read position carried into the next frame, relative to its first sample
+    phase: f32,
+    // End of the last frame, for interpolating across the frame boundary
+    history: [f32; 3],
+}
+
+impl MotionEffect {
+    pub fn new(quality: MotionQuality) -> Self {
+        Self {
+            quality,
+            factor: 1.0,
+            phase: 0.0,
+            history: [0.0; 3],
+        }
+    }
+
+    /// Renders a frame moving at playback rate `factor`
+    pub fn process(&mut self, samples: &[f32], factor: f32) -> Vec<f32> {
+        if samples.is_empty() {
+            return Vec::new();
+        }
+
+        // The previous frame's tail comes first, so reads can look back across the boundary
+        let mut input = Vec::with_capacity(samples.len() + self.history.len());
+        input.extend_from_slice(&self.history);
+        input.extend_from_slice(samples);
+        let start = self.history.len() as f32;
+
+        let from = self.factor;
+        let len = samples.len();
+        let mut position = start + self.phase;
+        let mut output = Vec::with_capacity(len);
+        for i in 0..len {
+            let rate = match self.quality {
+                MotionQuality::Low => factor,
+                MotionQuality::High => from + (factor - from) * i as f32 / len as f32,
+            };
+            let gain = (rate * rate).clamp(MOTION_GAIN_RANGE.0, MOTION_GAIN_RANGE.1);
+            let sample = match self.quality {
+                MotionQuality::Low => linear(&input, position),
+                MotionQuality::High => cubic(&input, position),
+            };
+            output.push(sample * gain);
+            position += rate;
+        }
+
+        // Reading runs slightly ahead of or behind the input; keep that small so
+        // latency doesn't drift, and carry the rest into the next frame
+        self.phase = (position - start - len as f32).clamp(-1.0, 1.0);
+        let tail = input.len() - self.history.len();
+        self.history.copy_from_slice(&input[tail..]);
+        self.factor = factor;
+        output
+    }
+}
+
+/// Reads between samples along a straight line
+fn linear(input: &[f32], position: f32) -> f32 {
+    let position = position.clamp(0.0, (input.len() - 1) as f32);
+    let index = position as usize;
+    let next = (index + 1).min(input.len() - 1);
+    let frac = position - index as f32;
+    input[index] + (input[next] - input[index]) * frac
+}
+
+/// Reads between samples along a Catmull-Rom curve
+fn cubic(input: &[f32], position: f32) -> f32 {
+    let last = input.len() as isize - 1;
+    let position = position.clamp(0.0, last as f32);
+    let index = position as isize;
+    let t = position - index as f32;
+    let at = |i: isize| input[i.clamp(0, last) as usize];
+    let (p0, p1, p2, p3) = (at(index - 1), at(index), at(index + 1), at(index + 2));
+
+    p1 + 0.5
+        * t
+        * (p2 - p0 + t * (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3 + t * (3.0 * (p1 - p2) + p3 - p0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_doppler_follows_motion_towards_listener() {
+        let listener = ((0.0, 0.0, 0.0), (0.0, 0.0, 0.0));
+
+        let approaching = doppler_factor(listener, ((0.0, 0.0, -2.0), (0.0, 0.0, 10.0)));
+        let receding = doppler_factor(listener, ((0.0, 0.0, -2.0), (0.0, 0.0, -10.0)));
+        let passing = doppler_factor(listener, ((0.0, 0.0, -2.0), (10.0, 0.0, 0.0)));
+
+        assert!(approaching > 1.0 && approaching <= 1.0 + MAX_SHIFT);
+        assert!(receding < 1.0);
+        assert!((passing - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_still_sources_pass_through() {
+        let input: Vec<f32> = (0..480).map(|i| (i as f32 * 0.05).sin()).collect();
+        for quality in [MotionQuality::Low, MotionQuality::High] {
+            let mut effect = MotionEffect::new(quality);
+            let first = effect.process(&input, 1.0);
+            let second = effect.process(&input, 1.0);
+
+            assert_eq!(first.len(), input.len());
+            for output in [first, second] {
+                assert!(output.iter().zip(&input).all(|(a, b)| (a - b).abs() < 1e-5));
+            }
+        }
+    }
+
+    #[test]
+    fn test_approaching_sources_are_louder() {
+        let input = vec![0.5; 480];
+        let mut effect = MotionEffect::new(MotionQuality::Low);
+        effect.process(&input, 1.0);
+        let output = effect.process(&input, 1.05);
+        assert!(output[100] > 0.5);
+
+        assert_eq!("high".parse(), Ok(MotionQuality::High));
+        assert!("ultra".parse::<MotionQuality>().is_err());
+    }
+}
@@ -0,0 +1,79 @@
+use super::codec::{AudioDecoder, AudioEncoder, CodecSettings};
+use super::conversions::remix;
+use super::frame::AudioFrame;
+
+/// Level we hear ourselves at, well under the room so it isn't taken for someone else
+pub const MONITOR_GAIN: f32 = 0.3;
+
+/// Lets us hear what peers hear of us: our processed audio, through the
+/// codec and decoded again
+///
+/// Noise suppression, the gate and the codec all change how we sound, and
+/// none of it is heard from our side of the call otherwise.
+#[derive(Debug)]
+pub struct NetworkMonitor {
+    codec: CodecSettings,
+    encoder: AudioEncoder,
+    decoder: AudioDecoder,
+}
+
+impl NetworkMonitor {
+    pub fn new(codec: CodecSettings) -> Self {
+        Self {
+            codec,
+            encoder: AudioEncoder::new(codec.bitrate_kbps),
+            decoder: AudioDecoder::new(),
+        }
+    }
+
+    pub fn codec(&self) -> CodecSettings {
+        self.codec
+    }
+
+    /// A processed frame as peers hear it, in interleaved stereo at [`MONITOR_GAIN`]
+    ///
+    /// Silence if the codec can't decode what it made.
+    pub fn process(&mut self, frame: &AudioFrame) -> Vec<f32> {
+        let (codec, data) = self.encoder.encode(self.codec.codec, frame);
+        let decoded = self
+            .decoder
+            .decode(codec, &data, frame.sample_rate, frame.channels)
+            .unwrap_or_else(|| vec![0.0; frame.samples.len()]);
+        remix(&decoded, frame.channels, 2)
+            .into_iter()
+            .map(|sample| sample * MONITOR_GAIN)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::AudioCodec;
+
+    #[test]
+    fn test_monitor_hears_the_codec() {
+        let samples: Vec<f32> = (0..480)
+            .map(|i| (i as f32 / 480.0 * std::f32::consts::TAU * 10.0).sin() * 0.5)
+            .collect();
+        let frame = AudioFrame::new(samples.clone(), 48000, 1);
+
+        let mut monitor = NetworkMonitor::new(CodecSettings {
+            codec: AudioCodec::Adpcm,
+            bitrate_kbps: 64,
+        });
+        assert_eq!(monitor.process(&frame).len(), samples.len() * 2);
+
+        // The second frame, once ADPCM has adapted, is close to what went
+        // in, quieter, and not bit-exact: ADPCM is lossy
+        let heard = monitor.process(&frame);
+        let mut differs = false;
+        for (i, &sample) in samples.iter().enumerate() {
+            let left = heard[i * 2] / MONITOR_GAIN;
+            assert_eq!(heard[i * 2], heard[i * 2 + 1]);
+            assert!((left - sample).abs() < 0.1, "{} vs {}", left, sample);
+            differs |= left != sample;
+        }
+        assert!(differs);
+    }
+}
@@ -0,0 +1,511 @@
+use anyhow::{anyhow, Result};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use super::channel_map::ChannelMap;
+use super::conversions::{f32_to_u16, Ditherer};
+use super::host::{default_host, AudioHost, DeviceFormat, HostStream, OutputBuffer};
+use super::sanitize::{clean_sample, SampleFaults};
+use crate::app::topology::{self, Plane};
+
+/// How long playback takes to fade out when hanging up
+pub const HANGUP_FADE: Duration = Duration::from_millis(100);
+
+/// Audio that can be sent to an output device of its own
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum OutputSource {
+    /// Everyone in the room
+    Mix,
+    /// A single participant, by name
+    Peer(String),
+}
+
+impl fmt::Display for OutputSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputSource::Mix => write!(f, "mix"),
+            OutputSource::Peer(name) => write!(f, "peer:{}", name),
+        }
+    }
+}
+
+impl FromStr for OutputSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mix" => Ok(OutputSource::Mix),
+            _ => match s.strip_prefix("peer:") {
+                Some(name) if !name.is_empty() => Ok(OutputSource::Peer(name.to_string())),
+                _ => Err(format!("Unknown output source: {}", s)),
+            },
+        }
+    }
+}
+
+/// Which output device each routed source plays on
+///
+/// Serialized as `source=device` pairs separated by `;`, for example
+/// `mix=CABLE Input;peer:Alice=Headphones`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OutputRouting {
+    routes: Vec<(OutputSource, String)>,
+}
+
+impl OutputRouting {
+    /// Plays `source` on `device`, replacing any earlier route for it
+    pub fn with_route(mut self, source: OutputSource, device: &str) -> Self {
+        self.route(source, device);
+        self
+    }
+
+    /// Plays `source` on `device`, replacing any earlier route for it
+    pub fn route(&mut self, source: OutputSource, device: &str) {
+        self.unroute(&source);
+        self.routes.push((source, device.to_string()));
+    }
+
+    /// Stops routing `source` to a device of its own
+    pub fn unroute(&mut self, source: &OutputSource) {
+        self.routes.retain(|(routed, _)| routed != source);
+    }
+
+    /// Device `source` is routed to, if any
+    pub fn device_for(&self, source: &OutputSource) -> Option<&str> {
+        self.routes
+            .iter()
+            .find(|(routed, _)| routed == source)
+            .map(|(_, device)| device.as_str())
+    }
+
+    /// Every device used by a route, once each
+    pub fn devices(&self) -> Vec<&str> {
+        let mut devices: Vec<&str> = Vec::new();
+        for (_, device) in &self.routes {
+            if !devices.contains(&device.as_str()) {
+                devices.push(device);
+            }
+        }
+        devices
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+}
+
+impl fmt::Display for OutputRouting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let routes: Vec<String> = self
+            .routes
+            .iter()
+            .map(|(source, device)| format!("{}={}", source, device))
+            .collect();
+        write!(f, "{}", routes.join(";"))
+    }
+}
+
+impl FromStr for OutputRouting {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut routing = OutputRouting::default();
+        for entry in s.split(';').filter(|entry| !entry.trim().is_empty()) {
+            let (source, device) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid output route: {}", entry))?;
+            routing.route(source.trim().parse()?, device.trim());
+        }
+        Ok(routing)
+    }
+}
+
+/// Sums interleaved stereo audio from several sources for one device
+///
+/// Each source writes from its own cursor, so frames that arrive from
+/// different peers at the same time are mixed rather than queued one after
+/// the other.
+struct MixBus {
+    samples: VecDeque<f32>,
+    cursors: HashMap<String, usize>,
+    capacity: usize,
+    // Frames left in a fade-out and its length; silent once it reaches zero
+    fade: Option<(usize, usize)>,
+    // Times the device asked for audio after the queue ran dry, and whether it's dry now
+    underruns: u64,
+    starved: bool,
+    channel_map: ChannelMap,
+}
+
+impl MixBus {
+    fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            cursors: HashMap::new(),
+            capacity,
+            fade: None,
+            underruns: 0,
+            starved: false,
+            channel_map: ChannelMap::default(),
+        }
+    }
+
+    fn add(&mut self, source: &str, stereo: &[f32]) {
+        // One bad source mustn't poison everyone else's audio in the mix
+        let mut faults = SampleFaults::default();
+        let cursor = self.cursors.entry(source.to_string()).or_insert(0);
+        for (i, sample) in stereo.iter().enumerate() {
+            let sample = clean_sample(*sample, &mut faults);
+            match self.samples.get_mut(*cursor + i) {
+                Some(mixed) => *mixed += sample,
+                None => self.samples.push_back(sample),
+            }
+        }
+        *cursor += stereo.len();
+        if faults.non_finite > 0 {
+            log::debug!("Replaced {} from {}", faults, source);
+        }
+        faults.report();
+
+        // Drop the oldest audio if sources are running ahead of the device
+        let excess = self.samples.len().saturating_sub(self.capacity);
+        self.consume(excess + excess % 2);
+    }
+
+    /// Takes the next stereo frame, or silence if nothing is queued
+    fn next_frame(&mut self) -> (f32, f32) {
+        if self.samples.len() < 2 {
+            // Only a source that stopped short counts, not a quiet device
+            if !self.starved && !self.cursors.is_empty() {
+                self.underruns += 1;
+            }
+            self.starved = true;
+            return (0.0, 0.0);
+        }
+        self.starved = false;
+        let frame = (self.samples[0], self.samples[1]);
+        self.consume(2);
+
+        let Some((remaining, length)) = self.fade.as_mut() else {
+            return frame;
+        };
+        let gain = *remaining as f32 / (*length).max(1) as f32;
+        *remaining = remaining.saturating_sub(1);
+
+        // Once silent, whatever was still queued is dropped and the bus starts afresh
+        if *remaining == 0 {
+            self.fade = None;
+            self.samples.clear();
+            self.cursors.clear();
+        }
+        (frame.0 * gain, frame.1 * gain)
+    }
+
+    /// Ramps everything queued down to silence over `frames`
+    fn fade_out(&mut self, frames: usize) {
+        self.fade = Some((frames, frames));
+    }
+
+    fn remove_source(&mut self, source: &str) {
+        self.cursors.remove(source);
+    }
+
+    fn consume(&mut self, count: usize) {
+        let count = count.min(self.samples.len());
+        self.samples.drain(..count);
+        for cursor in self.cursors.values_mut() {
+            *cursor = cursor.saturating_sub(count);
+        }
+    }
+}
+
+/// A playback stream on one output device
+///
+/// Like capture, the cpal stream lives on a thread of its own because streams
+/// are not `Send` on every platform.
+pub struct AudioPlayback {
+    device: String,
+    sample_rate: u32,
+    bus: Arc<Mutex<MixBus>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl AudioPlayback {
+    /// Opens the named output device (`default` for the system default)
+    pub fn open(device: &str, sample_rate: u32) -> Result<Self> {
+        Self::open_on(default_host(), device, sample_rate)
+    }
+
+    fn open_on(host: Arc<dyn AudioHost>, device: &str, sample_rate: u32) -> Result<Self> {
+        // Half a second of stereo audio
+        let bus = Arc::new(Mutex::new(MixBus::new(sample_rate as usize)));
+        let stop = Arc::new(AtomicBool::new(false));
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+
+        let thread_device = device.to_string();
+        let thread_bus = bus.clone();
+        let thread_stop = stop.clone();
+        let thread = topology::spawn_thread(Plane::Media, "playback", move || {
+            let stream = match build_output_stream(&*host, &thread_device, sample_rate, thread_bus)
+            {
+                Ok(stream) => {
+                    let _ = ready_tx.send(Ok(()));
+                    stream
+                }
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+
+            while !thread_stop.load(Ordering::SeqCst) {
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            drop(stream);
+        })?;
+
+        match ready_rx.recv() {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => {
+                let _ = thread.join();
+                return Err(e);
+            }
+            Err(_) => return Err(anyhow!("Playback thread exited unexpectedly")),
+        }
+
+        Ok(Self {
+            device: device.to_string(),
+            sample_rate,
+            bus,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// Name of the device this stream plays on
+    pub fn device(&self) -> &str {
+        &self.device
+    }
+
+    /// Queues interleaved stereo audio from `source`, mixed with other sources
+    pub fn push(&self, source: &str, stereo: &[f32]) {
+        self.bus.lock().unwrap().add(source, stereo);
+    }
+
+    /// Forgets a source that has stopped sending audio
+    pub fn remove_source(&self, source: &str) {
+        self.bus.lock().unwrap().remove_source(source);
+    }
+
+    /// Audio from `source` still waiting to be played
+    pub fn queued(&self, source: &str) -> Duration {
+        let samples = self
+            .bus
+            .lock()
+            .unwrap()
+            .cursors
+            .get(source)
+            .copied()
+            .unwrap_or(0);
+        Duration::from_secs_f64(samples as f64 / 2.0 / self.sample_rate as f64)
+    }
+
+    /// Times the device ran out of queued audio since the stream was opened
+    pub fn underruns(&self) -> u64 {
+        self.bus.lock().unwrap().underruns
+    }
+
+    /// Lays the mix onto the device's channels as `map` says, from the next buffer on
+    pub fn set_channel_map(&self, map: ChannelMap) {
+        self.bus.lock().unwrap().channel_map = map;
+    }
+
+    /// Ramps playback down to silence over `duration`, instead of cutting it off
+    pub fn fade_out(&self, duration: Duration) {
+        let frames = (self.sample_rate as f64 * duration.as_secs_f64()) as usize;
+        self.bus.lock().unwrap().fade_out(frames);
+    }
+}
+
+impl Drop for AudioPlayback {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Opens an output device and starts playing from `bus`
+fn build_output_stream(
+    host: &dyn AudioHost,
+    device_name: &str,
+    sample_rate: u32,
+    bus: Arc<Mutex<MixBus>>,
+) -> Result<Box<dyn HostStream>> {
+    // A route to a missing device shouldn't fall back to another one
+    let device = if device_name == "default" {
+        host.default_output_device()
+    } else {
+        host.output_devices()?
+            .into_iter()
+            .find(|name| name == device_name)
+    }
+    .ok_or_else(|| anyhow!("Output device not found: {}", device_name))?;
+
+    let supported = host.default_format(&device, false)?;
+    let channels = supported.channels as usize;
+    let format = DeviceFormat {
+        sample_rate,
+        ..supported
+    };
+
+    let mut ditherer = Ditherer::new();
+    host.build_output_stream(
+        &device,
+        format,
+        Box::new(move |data| match data {
+            OutputBuffer::F32(data) => render(&bus, channels, data, |s| s),
+            OutputBuffer::I16(data) => render(&bus, channels, data, |s| ditherer.to_i16(s)),
+            OutputBuffer::U16(data) => render(&bus, channels, data, f32_to_u16),
+        }),
+    )
+}
+
+/// Fills a device buffer from the bus, laying stereo onto the device's channels
+fn render<T: Copy>(
+    bus: &Mutex<MixBus>,
+    channels: usize,
+    data: &mut [T],
+    mut convert: impl FnMut(f32) -> T,
+) {
+    // Play silence rather than block the audio thread
+    let mut bus = bus.try_lock().ok();
+    let map = bus.as_ref().map(|bus| bus.channel_map).unwrap_or_default();
+    let (left_index, right_index) = map.output_indices(channels.max(1));
+
+    for frame in data.chunks_mut(channels.max(1)) {
+        let (left, right) = bus
+            .as_mut()
+            .map(|bus| bus.next_frame())
+            .unwrap_or((0.0, 0.0));
+        let (left, right) = map.apply(left.clamp(-1.0, 1.0), right.clamp(-1.0, 1.0));
+
+        if frame.len() == 1 {
+            frame[0] = convert((left + right) * 0.5);
+            continue;
+        }
+        for sample in frame.iter_mut() {
+            *sample = convert(0.0);
+        }
+        frame[left_index] = convert(left);
+        frame[right_index] = convert(right);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::virtual_host::{VirtualDevice, VirtualHost};
+
+    #[test]
+    fn test_routing_round_trip() {
+        let routing = OutputRouting::default()
+            .with_route(OutputSource::Mix, "CABLE Input")
+            .with_route(OutputSource::Peer("Alice".to_string()), "Headphones")
+            .with_route(OutputSource::Peer("Bob".to_string()), "CABLE Input");
+
+        assert_eq!(
+            routing.to_string(),
+            "mix=CABLE Input;peer:Alice=Headphones;peer:Bob=CABLE Input"
+        );
+        assert_eq!(routing.to_string().parse(), Ok(routing.clone()));
+        assert_eq!(routing.devices(), vec!["CABLE Input", "Headphones"]);
+        assert_eq!(
+            routing.device_for(&OutputSource::Peer("Alice".to_string())),
+            Some("Headphones")
+        );
+        assert!("speakers".parse::<OutputRouting>().is_err());
+    }
+
+    #[test]
+    fn test_mix_bus_sums_sources() {
+        let mut bus = MixBus::new(64);
+        bus.add("alice", &[0.25, 0.25, 0.25, 0.25]);
+        bus.add("bob", &[0.5, -0.5]);
+
+        // Bob's first frame lands on top of Alice's, not after it
+        assert_eq!(bus.next_frame(), (0.75, -0.25));
+        assert_eq!(bus.next_frame(), (0.25, 0.25));
+        assert_eq!(bus.next_frame(), (0.0, 0.0));
+        assert_eq!(bus.next_frame(), (0.0, 0.0));
+
+        // Cursors follow the read position
+        bus.add("bob", &[0.1, 0.1]);
+        assert_eq!(bus.next_frame(), (0.1, 0.1));
+
+        // A source sending NaN doesn't silence the others
+        bus.add("alice", &[0.25, 0.25]);
+        bus.add("bob", &[f32::NAN, f32::INFINITY]);
+        assert_eq!(bus.next_frame(), (0.25, 0.25));
+
+        // Running dry counts once per gap, and never before any audio came
+        assert_eq!(bus.underruns, 1);
+        let mut idle = MixBus::new(64);
+        idle.next_frame();
+        assert_eq!(idle.underruns, 0);
+    }
+
+    #[test]
+    fn test_mix_bus_fades_out_then_starts_afresh() {
+        let mut bus = MixBus::new(64);
+        bus.add("alice", &[1.0; 12]);
+        bus.fade_out(4);
+
+        let levels: Vec<f32> = (0..4).map(|_| bus.next_frame().0).collect();
+        assert_eq!(levels, vec![1.0, 0.75, 0.5, 0.25]);
+
+        // The rest of Alice's audio went with the fade
+        assert_eq!(bus.next_frame(), (0.0, 0.0));
+        bus.add("alice", &[0.5, 0.5]);
+        assert_eq!(bus.next_frame(), (0.5, 0.5));
+    }
+
+    #[test]
+    fn test_mix_bus_drops_oldest_when_full() {
+        let mut bus = MixBus::new(4);
+        bus.add("alice", &[0.1, 0.1, 0.2, 0.2, 0.3, 0.3]);
+
+        assert_eq!(bus.next_frame(), (0.2, 0.2));
+        assert_eq!(bus.next_frame(), (0.3, 0.3));
+    }
+
+    #[test]
+    fn test_plays_on_a_virtual_device() {
+        let host = VirtualHost::default().with_device(VirtualDevice::output(
+            "Speakers",
+            1,
+            cpal::SampleFormat::F32,
+        ));
+        assert!(AudioPlayback::open_on(Arc::new(host.clone()), "Headphones", 48000).is_err());
+
+        let playback = AudioPlayback::open_on(Arc::new(host.clone()), "default", 24000).unwrap();
+        assert_eq!(host.stream_format("Speakers").unwrap().sample_rate, 24000);
+        playback.push("alice", &[0.5, 0.25, 1.0, 1.0]);
+
+        // A mono device hears both sides of the mix
+        let mut buffer = [1.0f32; 3];
+        assert!(host.render("Speakers", OutputBuffer::F32(&mut buffer)));
+        assert_eq!(buffer, [0.375, 1.0, 0.0]);
+        assert_eq!(playback.underruns(), 1);
+
+        drop(playback);
+        assert!(!host.render("Speakers", OutputBuffer::F32(&mut buffer)));
+    }
+}
@@ -0,0 +1,186 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::conversions::BlockAdapter;
+use super::AudioFrame;
+
+/// How much audio each frame sent to the room carries
+///
+/// Shorter frames arrive sooner but cost a packet header, encryption tag and
+/// codec overhead each, so they use more bandwidth; longer ones save
+/// bandwidth and CPU at the cost of latency. The host picks one for the
+/// room and everyone who joins with the link uses it, so codecs and jitter
+/// buffers see the same frame size from every peer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FrameDuration {
+    /// Lowest latency, most packets
+    Ms10,
+    /// The usual trade-off, and what peers assume without a link setting
+    #[default]
+    Ms20,
+    /// Least bandwidth, 20 ms more latency than the default
+    Ms40,
+}
+
+impl FrameDuration {
+    pub fn millis(&self) -> u32 {
+        match self {
+            FrameDuration::Ms10 => 10,
+            FrameDuration::Ms20 => 20,
+            FrameDuration::Ms40 => 40,
+        }
+    }
+}
+
+impl fmt::Display for FrameDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.millis())
+    }
+}
+
+impl FromStr for FrameDuration {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().trim_end_matches("ms") {
+            "10" => Ok(FrameDuration::Ms10),
+            "20" => Ok(FrameDuration::Ms20),
+            "40" => Ok(FrameDuration::Ms40),
+            other => Err(format!(
+                "Unsupported frame duration: {} (use 10, 20 or 40)",
+                other
+            )),
+        }
+    }
+}
+
+/// The room's frame duration from a join link, if the host set one
+pub fn frame_duration_from_link(link: &str) -> Option<FrameDuration> {
+    link.split(['?', '&'])
+        .find_map(|param| param.strip_prefix("frame="))
+        .and_then(|value| value.parse().ok())
+}
+
+/// Adds a room's frame duration to a join link, unless it's the default peers assume anyway
+pub fn append_frame_duration(link: &str, duration: FrameDuration) -> String {
+    if duration == FrameDuration::default() {
+        link.to_string()
+    } else {
+        format!("{}&frame={}", link, duration)
+    }
+}
+
+/// Cuts captured audio into frames of one fixed duration
+///
+/// Devices deliver audio in whatever blocks suit them, often not a whole
+/// number of milliseconds. Samples are held until a full frame is ready, so
+/// every frame sent is exactly the room's duration, numbered in order and
+/// stamped with the capture time of its first sample.
+#[derive(Debug, Clone)]
+pub struct Repacketizer {
+    duration: FrameDuration,
+    pending: BlockAdapter,
+    // Format of the pending samples
+    sample_rate: u32,
+    channels: u16,
+    // Capture time of the first pending sample
+    capture_ts: u64,
+    next_seq: u64,
+}
+
+impl Repacketizer {
+    pub fn new(duration: FrameDuration) -> Self {
+        Self {
+            duration,
+            pending: BlockAdapter::new(1),
+            sample_rate: 0,
+            channels: 0,
+            capture_ts: 0,
+            next_seq: 0,
+        }
+    }
+
+    /// Changes the frame duration, dropping any partial frame
+    pub fn set_duration(&mut self, duration: FrameDuration) {
+        if duration != self.duration {
+            self.duration = duration;
+            self.pending
+                .set_block_len(self.frame_len(self.sample_rate, self.channels));
+        }
+    }
+
+    /// Samples in one frame of the given format, across all channels
+    fn frame_len(&self, sample_rate: u32, channels: u16) -> usize {
+        let per_channel = sample_rate as u64 * self.duration.millis() as u64 / 1000;
+        (per_channel as usize * channels.max(1) as usize).max(1)
+    }
+
+    /// Adds captured audio, returning every frame now complete
+    ///
+    /// A change of sample rate or channel count drops the partial frame in
+    /// the old format rather than mixing formats in one frame.
+    pub fn push(&mut self, frame: &AudioFrame) -> Vec<AudioFrame> {
+        if frame.sample_rate != self.sample_rate || frame.channels != self.channels {
+            self.sample_rate = frame.sample_rate;
+            self.channels = frame.channels;
+            self.pending
+                .set_block_len(self.frame_len(self.sample_rate, self.channels));
+        }
+        if self.pending.pending() == 0 {
+            self.capture_ts = frame.capture_ts;
+        }
+
+        let mut frames = Vec::new();
+        for samples in self.pending.push(&frame.samples) {
+            let mut out = AudioFrame::new(samples, self.sample_rate, self.channels)
+                .with_seq(self.next_seq)
+                .with_capture_ts(self.capture_ts);
+            out.peer_id = frame.peer_id.clone();
+            frames.push(out);
+
+            self.next_seq += 1;
+            self.capture_ts += self.duration.millis() as u64 * 1000;
+        }
+        frames
+    }
+
+    /// Drops any partial frame, such as when the stream stops
+    pub fn reset(&mut self) {
+        self.pending.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames_have_fixed_duration() {
+        for text in ["10", "20", "40"] {
+            assert_eq!(text.parse::<FrameDuration>().unwrap().to_string(), text);
+        }
+        assert_eq!("40ms".parse(), Ok(FrameDuration::Ms40));
+        assert!("30".parse::<FrameDuration>().is_err());
+
+        // 10 ms at 48 kHz is 480 samples; feed blocks that don't line up with it
+        let mut repacketizer = Repacketizer::new(FrameDuration::Ms10);
+        let block = |len: usize| AudioFrame::new(vec![0.5; len], 48000, 1);
+        assert!(repacketizer.push(&block(300)).is_empty());
+        let frames = repacketizer.push(&block(700));
+        assert_eq!(frames.len(), 2);
+        assert!(frames.iter().all(|frame| frame.samples.len() == 480));
+        assert_eq!((frames[0].seq, frames[1].seq), (0, 1));
+        assert_eq!(frames[1].capture_ts - frames[0].capture_ts, 10_000);
+
+        // 40 samples are left over; a new format starts over
+        let stereo = AudioFrame::new(vec![0.5; 1920], 48000, 2);
+        let frames = repacketizer.push(&stereo);
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].samples.len(), 960);
+
+        let link = append_frame_duration("resonance://1.2.3.4:5000/abc?key=k", FrameDuration::Ms40);
+        assert_eq!(frame_duration_from_link(&link), Some(FrameDuration::Ms40));
+        let link = append_frame_duration("resonance://1.2.3.4:5000/abc?key=k", FrameDuration::Ms20);
+        assert_eq!(frame_duration_from_link(&link), None);
+    }
+}
@@ -0,0 +1,67 @@
+use std::future::Future;
+use std::sync::OnceLock;
+use tokio::runtime::{Builder, Handle, Runtime};
+use tokio::task::JoinHandle;
+
+/// Worker threads for audio tasks; each task only wakes every few milliseconds
+const AUDIO_WORKER_THREADS: usize = 1;
+
+/// A tokio runtime kept apart from network I/O and the UI
+///
+/// Capture forwarding and local mixing run here so a busy main runtime
+/// (many peers, a slow terminal) doesn't delay them. Tasks talk to the main
+/// runtime over tokio channels, which work across runtimes.
+pub struct AudioRuntime {
+    runtime: Runtime,
+}
+
+impl AudioRuntime {
+    fn new() -> std::io::Result<Self> {
+        let runtime = Builder::new_multi_thread()
+            .worker_threads(AUDIO_WORKER_THREADS)
+            .thread_name("resonance-audio")
+            .enable_time()
+            .build()?;
+        Ok(Self { runtime })
+    }
+
+    /// The process-wide audio runtime, started on first use
+    ///
+    /// It lives for the rest of the process, since a runtime can't be shut
+    /// down from inside another one.
+    pub fn shared() -> &'static AudioRuntime {
+        static SHARED: OnceLock<AudioRuntime> = OnceLock::new();
+        SHARED.get_or_init(|| AudioRuntime::new().expect("Failed to start the audio runtime"))
+    }
+
+    /// Runs a task on the audio runtime
+    pub fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.runtime.spawn(future)
+    }
+
+    pub fn handle(&self) -> &Handle {
+        self.runtime.handle()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn test_tasks_run_on_audio_threads() {
+        let (tx, mut rx) = mpsc::channel(1);
+        AudioRuntime::shared().spawn(async move {
+            let name = std::thread::current().name().map(str::to_string);
+            tx.send(name).await.unwrap();
+        });
+
+        // The result comes back to this runtime over the channel
+        assert_eq!(rx.recv().await.unwrap().as_deref(), Some("resonance-audio"));
+    }
+}
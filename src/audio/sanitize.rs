@@ -0,0 +1,107 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// Samples fixed since the process started, for diagnostics
+static NON_FINITE: AtomicU64 = AtomicU64::new(0);
+static DENORMAL: AtomicU64 = AtomicU64::new(0);
+
+/// Bad samples found in audio and replaced before it was mixed
+///
+/// NaN and infinite samples come from broken DSP or from peers sending
+/// garbage, and would otherwise poison the whole mix or drive the output to
+/// full scale. Denormals are harmless to hear but can cost some CPUs a
+/// hundred times more per operation, so they're flushed to zero too.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SampleFaults {
+    /// NaN or infinite samples replaced with silence
+    pub non_finite: u64,
+    /// Denormal samples flushed to zero
+    pub denormal: u64,
+}
+
+impl SampleFaults {
+    pub fn total(&self) -> u64 {
+        self.non_finite + self.denormal
+    }
+
+    /// Adds these to the process-wide counts reported by [`total_faults`]
+    pub fn report(&self) {
+        if self.non_finite > 0 {
+            NON_FINITE.fetch_add(self.non_finite, Ordering::Relaxed);
+        }
+        if self.denormal > 0 {
+            DENORMAL.fetch_add(self.denormal, Ordering::Relaxed);
+        }
+    }
+}
+
+impl fmt::Display for SampleFaults {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} NaN/Inf, {} denormal", self.non_finite, self.denormal)
+    }
+}
+
+/// Returns `sample`, or silence if it's NaN, infinite or denormal, counting what was replaced
+pub fn clean_sample(sample: f32, faults: &mut SampleFaults) -> f32 {
+    if !sample.is_finite() {
+        faults.non_finite += 1;
+        0.0
+    } else if sample.is_subnormal() {
+        faults.denormal += 1;
+        0.0
+    } else {
+        sample
+    }
+}
+
+/// Cleans a buffer of samples in place, adding what was replaced to the process-wide counts
+pub fn sanitize(samples: &mut [f32]) -> SampleFaults {
+    let mut faults = SampleFaults::default();
+    for sample in samples.iter_mut() {
+        *sample = clean_sample(*sample, &mut faults);
+    }
+    faults.report();
+    faults
+}
+
+/// Bad samples replaced since the process started
+pub fn total_faults() -> SampleFaults {
+    SampleFaults {
+        non_finite: NON_FINITE.load(Ordering::Relaxed),
+        denormal: DENORMAL.load(Ordering::Relaxed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_replaces_bad_samples() {
+        let before = total_faults();
+        let mut samples = [
+            0.5,
+            f32::NAN,
+            f32::INFINITY,
+            -f32::INFINITY,
+            f32::MIN_POSITIVE / 2.0,
+            -0.25,
+            0.0,
+        ];
+
+        let faults = sanitize(&mut samples);
+        assert_eq!(
+            faults,
+            SampleFaults {
+                non_finite: 3,
+                denormal: 1
+            }
+        );
+        assert_eq!(samples, [0.5, 0.0, 0.0, 0.0, 0.0, -0.25, 0.0]);
+
+        // Other tests may scrub samples at the same time, so only a lower bound holds
+        let after = total_faults();
+        assert!(after.non_finite >= before.non_finite + 3);
+        assert!(after.denormal > before.denormal);
+    }
+}
@@ -0,0 +1,125 @@
+use std::time::{Duration, Instant};
+
+/// How long a participant takes to settle at a new position
+pub const POSITION_GLIDE: Duration = Duration::from_millis(200);
+
+/// Spring stiffness times settle time for the spring to cover 95% of a move
+const SETTLE_FACTOR: f32 = 4.75;
+
+/// Glides a position towards its target with a critically damped spring
+///
+/// Position updates arrive in steps; jumping straight to each one is heard
+/// as a click in the spatial image. The spring follows without overshoot
+/// and keeps its velocity when the target moves again mid-glide.
+#[derive(Debug, Clone)]
+pub struct PositionSmoother {
+    current: [f32; 3],
+    velocity: [f32; 3],
+    target: [f32; 3],
+    // Spring stiffness, in 1/s
+    omega: f32,
+    last_update: Instant,
+}
+
+impl PositionSmoother {
+    /// Starts at rest at `position`
+    pub fn new(position: (f32, f32, f32)) -> Self {
+        let position = [position.0, position.1, position.2];
+        Self {
+            current: position,
+            velocity: [0.0; 3],
+            target: position,
+            omega: SETTLE_FACTOR / POSITION_GLIDE.as_secs_f32(),
+            last_update: Instant::now(),
+        }
+    }
+
+    /// Sets how long a move takes to settle
+    pub fn with_glide_time(mut self, glide: Duration) -> Self {
+        self.omega = SETTLE_FACTOR / glide.as_secs_f32().max(f32::EPSILON);
+        self
+    }
+
+    /// Sets where the position glides to
+    pub fn set_target(&mut self, target: (f32, f32, f32)) {
+        self.target = [target.0, target.1, target.2];
+    }
+
+    /// Where the position is gliding to
+    pub fn target(&self) -> (f32, f32, f32) {
+        (self.target[0], self.target[1], self.target[2])
+    }
+
+    /// How fast the position is moving, in meters per second
+    pub fn velocity(&self) -> (f32, f32, f32) {
+        (self.velocity[0], self.velocity[1], self.velocity[2])
+    }
+
+    /// Moves along the glide to `now` and returns the position there
+    pub fn advance(&mut self, now: Instant) -> (f32, f32, f32) {
+        let dt = now
+            .saturating_duration_since(self.last_update)
+            .as_secs_f32();
+        self.last_update = self.last_update.max(now);
+        self.advance_by(dt)
+    }
+
+    /// Moves `dt` seconds along the glide and returns the position there
+    pub fn advance_by(&mut self, dt: f32) -> (f32, f32, f32) {
+        // Exact step of x'' = -2ωx' - ω²x, so any frame rate gives the same path
+        let decay = (-self.omega * dt).exp();
+        for axis in 0..3 {
+            let offset = self.current[axis] - self.target[axis];
+            let c = self.velocity[axis] + self.omega * offset;
+            self.current[axis] = self.target[axis] + (offset + c * dt) * decay;
+            self.velocity[axis] = (self.velocity[axis] - self.omega * c * dt) * decay;
+        }
+        (self.current[0], self.current[1], self.current[2])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glides_without_overshoot() {
+        let mut smoother = PositionSmoother::new((0.0, 0.0, 0.0));
+        smoother.set_target((1.0, 0.0, -2.0));
+
+        // Moves a little each 10 ms frame, never past the target
+        let mut last = 0.0;
+        for _ in 0..20 {
+            let (x, _, z) = smoother.advance_by(0.01);
+            assert!(x > last && x <= 1.0);
+            assert!((z + 2.0 * x).abs() < 1e-5);
+            last = x;
+        }
+
+        // Settled to within 5% after the glide time
+        assert!(last > 0.95);
+    }
+
+    #[test]
+    fn test_path_does_not_depend_on_frame_rate() {
+        let mut coarse = PositionSmoother::new((0.0, 0.0, 0.0));
+        let mut fine = coarse.clone();
+        coarse.set_target((3.0, 0.0, 0.0));
+        fine.set_target((3.0, 0.0, 0.0));
+
+        let a = coarse.advance_by(0.1);
+        let mut b = (0.0, 0.0, 0.0);
+        for _ in 0..10 {
+            b = fine.advance_by(0.01);
+        }
+        assert!((a.0 - b.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_a_new_position_starts_at_rest() {
+        let mut smoother =
+            PositionSmoother::new((2.0, 0.0, 0.0)).with_glide_time(Duration::from_millis(50));
+        assert_eq!(smoother.advance_by(1.0), (2.0, 0.0, 0.0));
+        assert_eq!(smoother.target(), (2.0, 0.0, 0.0));
+    }
+}
@@ -1,5 +1,4 @@
-use super::capture::generate_test_mono_audio;
-use std::sync::{Arc, Mutex};
+use super::{AudioConfig, SpatialLayout};
 
 #[derive(Clone)]
 pub struct SpatialAudioProcessor {
@@ -8,11 +7,18 @@ pub struct SpatialAudioProcessor {
     listener_orientation: (f32, f32, f32), // (yaw, pitch, roll) in radians
     room_size: (f32, f32, f32),            // Width, height, depth
     reverb_amount: f32,                    // 0.0-1.0
+    simple_panning: bool,                  // Skip ear filtering, ITD and reverb to save CPU
 
     // Audio processing parameters
     sample_rate: u32,
 }
 
+impl Default for SpatialAudioProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SpatialAudioProcessor {
     pub fn new() -> Self {
         Self {
@@ -21,7 +27,8 @@ impl SpatialAudioProcessor {
             listener_orientation: (0.0, 0.0, 0.0),
             room_size: (10.0, 3.0, 10.0), // Default room size in meters
             reverb_amount: 0.3,
-            sample_rate: 48000, // Default 48kHz
+            simple_panning: false,
+            sample_rate: AudioConfig::default().sample_rate,
         }
     }
 
@@ -38,36 +45,29 @@ impl SpatialAudioProcessor {
         participant_count: usize,
         current_user_index: usize,
     ) -> Vec<(f32, f32, f32)> {
-        // Use a reasonable default radius for the circle (in meters)
-        let radius = 2.0;
-
-        // Create positions for all participants
-        let mut positions = Vec::with_capacity(participant_count);
-
-        for i in 0..participant_count {
-            // Calculate angle in radians for this participant
-            let angle = 2.0 * std::f32::consts::PI * (i as f32) / (participant_count as f32);
-
-            // Calculate position on the circle (y is up, so we use x and z for the circle)
-            let x = radius * angle.cos();
-            let z = radius * angle.sin();
+        self.arrange_participants(SpatialLayout::Circle, participant_count, current_user_index)
+    }
 
-            positions.push((x, 0.0, z));
-        }
+    /// Arrange participants using a layout preset
+    ///
+    /// Returns the positions in seating order, and places and turns the
+    /// listener at the current user's seat.
+    pub fn arrange_participants(
+        &mut self,
+        layout: SpatialLayout,
+        participant_count: usize,
+        current_user_index: usize,
+    ) -> Vec<(f32, f32, f32)> {
+        let positions = layout.positions(participant_count);
 
-        // Update listener position and orientation for the current user
-        if participant_count > 0 && current_user_index < participant_count {
-            // Set listener position to the current user's position in the circle
+        if current_user_index < participant_count {
             self.listener_position = positions[current_user_index];
-
-            // Calculate the angle to face the center of the circle
-            // This is the opposite of the position angle
-            let angle = 2.0 * std::f32::consts::PI * (current_user_index as f32)
-                / (participant_count as f32);
-
-            // Set orientation to face the center (negative to face inward)
-            // Only setting yaw (rotation around y-axis), keeping pitch and roll at 0
-            self.listener_orientation = (-angle, 0.0, 0.0);
+            // Only yaw (rotation around the y axis) changes; pitch and roll stay level
+            self.listener_orientation = (
+                layout.facing(current_user_index, participant_count),
+                0.0,
+                0.0,
+            );
         }
 
         positions
@@ -93,6 +93,11 @@ impl SpatialAudioProcessor {
         self.reverb_amount = amount.clamp(0.0, 1.0);
     }
 
+    /// Places sources with plain equal-power panning, for machines that can't keep up
+    pub fn set_simple_panning(&mut self, enabled: bool) {
+        self.simple_panning = enabled;
+    }
+
     /// Process mono audio into spatial stereo audio
     ///
     /// This implementation uses a simplified HRTF-like approach with:
@@ -131,6 +136,10 @@ impl SpatialAudioProcessor {
         // azimuth = 0 is in front, π is behind, π/2 is to the right, -π/2 is to the left
         let pan = (azimuth / std::f32::consts::PI).clamp(-1.0, 1.0);
 
+        if self.simple_panning {
+            return self.apply_simple_panning(mono_input, pan, distance_attenuation);
+        }
+
         // Apply binaural processing
         let stereo_output = self.apply_binaural_processing(mono_input, pan, distance);
 
@@ -142,6 +151,16 @@ impl SpatialAudioProcessor {
         }
     }
 
+    // Equal-power panning and distance attenuation only, interleaved
+    fn apply_simple_panning(&self, mono_input: &[f32], pan: f32, attenuation: f32) -> Vec<f32> {
+        let angle = (pan + 1.0) * std::f32::consts::PI / 4.0; // 0 to π/2
+        let (left_gain, right_gain) = (angle.cos() * attenuation, angle.sin() * attenuation);
+        mono_input
+            .iter()
+            .flat_map(|&sample| [sample * left_gain, sample * right_gain])
+            .collect()
+    }
+
     // Apply more realistic binaural processing
     fn apply_binaural_processing(&self, mono_input: &[f32], pan: f32, distance: f32) -> Vec<f32> {
         let mut stereo_output = Vec::with_capacity(mono_input.len() * 2);
@@ -193,10 +212,10 @@ impl SpatialAudioProcessor {
         // Process left channel
         if left_delay > 0 {
             // If we need to delay the left channel, start with zeros
-            stereo_output.extend(left_delay_buffer.iter().map(|&s| s));
-            stereo_output.extend(left_filtered.iter().map(|&s| s));
+            stereo_output.extend(left_delay_buffer.iter().copied());
+            stereo_output.extend(left_filtered.iter().copied());
         } else {
-            stereo_output.extend(left_filtered.iter().map(|&s| s));
+            stereo_output.extend(left_filtered.iter().copied());
             // Pad with zeros at the end to maintain proper length
             stereo_output.extend(vec![0.0; right_delay]);
         }
@@ -204,10 +223,10 @@ impl SpatialAudioProcessor {
         // Process right channel
         if right_delay > 0 {
             // If we need to delay the right channel, start with zeros
-            stereo_output.extend(right_delay_buffer.iter().map(|&s| s));
-            stereo_output.extend(right_filtered.iter().map(|&s| s));
+            stereo_output.extend(right_delay_buffer.iter().copied());
+            stereo_output.extend(right_filtered.iter().copied());
         } else {
-            stereo_output.extend(right_filtered.iter().map(|&s| s));
+            stereo_output.extend(right_filtered.iter().copied());
             // Pad with zeros at the end to maintain proper length
             stereo_output.extend(vec![0.0; left_delay]);
         }
@@ -221,10 +240,9 @@ impl SpatialAudioProcessor {
         let alpha = 0.5 + 0.45 * strength;
         let mut prev = 0.0;
 
-        for i in 0..samples.len() {
-            let curr = samples[i];
-            samples[i] = alpha * prev + (1.0 - alpha) * curr;
-            prev = samples[i];
+        for sample in samples.iter_mut() {
+            *sample = alpha * prev + (1.0 - alpha) * *sample;
+            prev = *sample;
         }
     }
 
@@ -285,6 +303,7 @@ impl SpatialAudioProcessor {
 }
 
 // Helper function to measure stereo channel levels
+#[cfg(test)]
 pub fn measure_stereo_levels(stereo_audio: &[f32]) -> (f32, f32) {
     if stereo_audio.len() < 2 {
         return (0.0, 0.0);
@@ -307,6 +326,7 @@ pub fn measure_stereo_levels(stereo_audio: &[f32]) -> (f32, f32) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::audio::capture::generate_test_mono_audio;
 
     #[test]
     fn test_spatial_positioning() {
@@ -329,6 +349,21 @@ mod tests {
         assert!(left_level_when_left > right_level_when_left);
     }
 
+    #[test]
+    fn test_simple_panning() {
+        let mut processor = SpatialAudioProcessor::new();
+        processor.set_simple_panning(true);
+        let mono_input = generate_test_mono_audio();
+
+        // Azimuth is measured from +x, so +z is to the right
+        processor.set_source_position(0.0, 0.0, 1.0);
+        let right_biased = processor.process(&mono_input);
+        assert_eq!(right_biased.len(), mono_input.len() * 2);
+
+        let (left_level, right_level) = measure_stereo_levels(&right_biased);
+        assert!(right_level > left_level);
+    }
+
     #[test]
     fn test_circular_positioning() {
         let mut processor = SpatialAudioProcessor::new();
@@ -366,19 +401,13 @@ mod tests {
         // Get positions after arrangement from user 0's perspective
         let pos_0 = processor.listener_position;
 
-        // Calculate vectors from user 0 to other users
+        // Calculate vector from user 0 to user 1
         let vec_0_to_1 = (
             positions[1].0 - pos_0.0,
             positions[1].1 - pos_0.1,
             positions[1].2 - pos_0.2,
         );
 
-        let vec_0_to_2 = (
-            positions[2].0 - pos_0.0,
-            positions[2].1 - pos_0.1,
-            positions[2].2 - pos_0.2,
-        );
-
         // Now arrange from user 1's perspective
         let positions_from_1 = processor.arrange_participants_in_circle(3, 1);
         let pos_1 = processor.listener_position;
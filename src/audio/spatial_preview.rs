@@ -0,0 +1,216 @@
+use std::f32::consts::PI;
+use std::time::Duration;
+
+use super::SpatialAudioProcessor;
+
+/// Length of each block the preview is rendered and played in
+pub const PREVIEW_BLOCK: Duration = Duration::from_millis(20);
+
+/// How long the voice takes to walk a quarter of the way round
+const WALK: Duration = Duration::from_millis(1500);
+
+/// How long the voice stays put once it gets there, to be listened for
+const HOLD: Duration = Duration::from_millis(2500);
+
+/// Distance of the voice from the listener, in meters
+const ORBIT_RADIUS: f32 = 1.5;
+
+/// Syllables spoken per second
+const SYLLABLE_RATE: f32 = 4.0;
+
+/// First two formants, in Hz, of the vowels the voice cycles through
+const VOWELS: [(f32, f32); 5] = [
+    (730.0, 1090.0),
+    (270.0, 2290.0),
+    (530.0, 1840.0),
+    (300.0, 870.0),
+    (570.0, 840.0),
+];
+
+/// Where the preview voice stops on its way round the listener
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreviewDirection {
+    Front,
+    Right,
+    Back,
+    Left,
+}
+
+impl PreviewDirection {
+    /// The stops, in the order the voice reaches them walking clockwise
+    pub const ALL: [PreviewDirection; 4] = [
+        PreviewDirection::Front,
+        PreviewDirection::Right,
+        PreviewDirection::Back,
+        PreviewDirection::Left,
+    ];
+
+    /// Angle clockwise from straight ahead, seen from above
+    fn angle(&self) -> f32 {
+        match self {
+            PreviewDirection::Front => 0.0,
+            PreviewDirection::Right => PI / 2.0,
+            PreviewDirection::Back => PI,
+            PreviewDirection::Left => 3.0 * PI / 2.0,
+        }
+    }
+}
+
+/// Position on the orbit at `angle`, in the spatial processor's frame:
+/// azimuth is measured from +x, so +x is ahead and +z to the right
+fn orbit_position(angle: f32) -> (f32, f32, f32) {
+    (ORBIT_RADIUS * angle.cos(), 0.0, ORBIT_RADIUS * angle.sin())
+}
+
+/// Renders a synthesized voice walking round the listener, to check
+/// headphones and spatial settings
+///
+/// The voice goes through a copy of the mixer's spatial processor, so it's
+/// placed exactly as a participant standing there would be.
+pub struct SpatialPreview {
+    processor: SpatialAudioProcessor,
+    voice: Voice,
+    block_len: usize,
+}
+
+impl SpatialPreview {
+    pub fn new(mut processor: SpatialAudioProcessor, sample_rate: u32) -> Self {
+        processor.set_sample_rate(sample_rate);
+        processor.set_listener_position(0.0, 0.0, 0.0);
+        processor.set_listener_orientation(0.0, 0.0, 0.0);
+        Self {
+            processor,
+            voice: Voice::new(sample_rate),
+            block_len: (sample_rate as u64 * PREVIEW_BLOCK.as_millis() as u64 / 1000) as usize,
+        }
+    }
+
+    /// The voice walking a quarter circle clockwise to `to`, then waiting
+    /// there, as blocks of interleaved stereo to play one [`PREVIEW_BLOCK`] apart
+    pub fn walk_to(&mut self, to: PreviewDirection) -> Vec<Vec<f32>> {
+        let walk_blocks = (WALK.as_millis() / PREVIEW_BLOCK.as_millis()) as usize;
+        let hold_blocks = (HOLD.as_millis() / PREVIEW_BLOCK.as_millis()) as usize;
+        let end = to.angle();
+
+        (0..walk_blocks + hold_blocks)
+            .map(|block| {
+                let progress = (block as f32 / walk_blocks as f32).min(1.0);
+                let (x, y, z) = orbit_position(end - PI / 2.0 * (1.0 - progress));
+                self.processor.set_source_position(x, y, z);
+                self.processor.process(&self.voice.next(self.block_len))
+            })
+            .collect()
+    }
+}
+
+/// Two-pole resonator, shaping the voice's source into a formant
+#[derive(Debug, Clone, Copy, Default)]
+struct Resonator {
+    a1: f32,
+    a2: f32,
+    gain: f32,
+    y1: f32,
+    y2: f32,
+}
+
+impl Resonator {
+    fn tune(&mut self, frequency: f32, bandwidth: f32, sample_rate: f32) {
+        let r = (-PI * bandwidth / sample_rate).exp();
+        self.a1 = 2.0 * r * (2.0 * PI * frequency / sample_rate).cos();
+        self.a2 = -r * r;
+        self.gain = 1.0 - r;
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let output = self.gain * input + self.a1 * self.y1 + self.a2 * self.y2;
+        self.y2 = self.y1;
+        self.y1 = output;
+        output
+    }
+}
+
+/// A buzzing source through vowel formants, in syllables, that's heard as speech
+/// without being any words
+struct Voice {
+    sample_rate: f32,
+    // Samples generated so far
+    position: u64,
+    // Of the glottal pulse, 0.0 - 1.0
+    phase: f32,
+    formants: [Resonator; 2],
+}
+
+impl Voice {
+    fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate: sample_rate.max(1) as f32,
+            position: 0,
+            phase: 0.0,
+            formants: [Resonator::default(); 2],
+        }
+    }
+
+    fn next(&mut self, len: usize) -> Vec<f32> {
+        let syllable_len = (self.sample_rate / SYLLABLE_RATE) as u64;
+        (0..len)
+            .map(|_| {
+                let within = self.position % syllable_len;
+                if within == 0 {
+                    let (f1, f2) = VOWELS[(self.position / syllable_len) as usize % VOWELS.len()];
+                    self.formants[0].tune(f1, 90.0, self.sample_rate);
+                    self.formants[1].tune(f2, 120.0, self.sample_rate);
+                }
+                let t = self.position as f32 / self.sample_rate;
+                self.position += 1;
+
+                // Pitch drifts like intonation; a sawtooth stands in for the vocal folds
+                let pitch = 120.0 + 15.0 * (2.0 * PI * 0.7 * t).sin();
+                self.phase = (self.phase + pitch / self.sample_rate).fract();
+                let source = 1.0 - 2.0 * self.phase;
+                let shaped =
+                    self.formants[0].process(source) + 0.5 * self.formants[1].process(source);
+
+                // Each syllable swells and fades, with a short gap before the next
+                let syllable = within as f32 / syllable_len as f32;
+                let envelope = if syllable < 0.8 {
+                    (PI * syllable / 0.8).sin()
+                } else {
+                    0.0
+                };
+                (shaped * envelope * 0.15).clamp(-1.0, 1.0)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::spatial::measure_stereo_levels;
+
+    #[test]
+    fn test_voice_walks_round_listener() {
+        let (x, _, z) = orbit_position(PreviewDirection::Right.angle());
+        assert!(x.abs() < 1e-6 && (z - ORBIT_RADIUS).abs() < 1e-6);
+        let (x, _, _) = orbit_position(PreviewDirection::Back.angle());
+        assert!((x + ORBIT_RADIUS).abs() < 1e-6);
+
+        let mut processor = SpatialAudioProcessor::new();
+        processor.set_simple_panning(true);
+        let mut preview = SpatialPreview::new(processor, 48000);
+        let blocks = preview.walk_to(PreviewDirection::Right);
+        assert_eq!(blocks.len(), 200);
+        assert!(blocks.iter().all(|block| block.len() == 1920));
+
+        // Heard mostly in the ear on its side, but a little in the other
+        let held: Vec<f32> = blocks[100..].concat();
+        assert!(held.iter().all(|sample| sample.abs() <= 1.0));
+        let (left, right) = measure_stereo_levels(&held);
+        assert!(right > left * 2.0, "left {} right {}", left, right);
+        assert!(left > 0.0);
+
+        let held: Vec<f32> = preview.walk_to(PreviewDirection::Left)[100..].concat();
+        let (left, right) = measure_stereo_levels(&held);
+        assert!(left > right * 2.0, "left {} right {}", left, right);
+    }
+}
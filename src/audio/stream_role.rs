@@ -0,0 +1,109 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Environment variable PulseAudio, and PipeWire's PulseAudio layer, read client properties from
+const PULSE_PROPERTIES: &str = "PULSE_PROP";
+
+/// What our audio streams are, for the system's mixer policy
+///
+/// Systems treat call audio differently from media: they lower other sounds
+/// while a call plays, route it to a headset's hands-free profile, and may
+/// run echo cancellation on it. They can only do that if they know which
+/// streams carry a call.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StreamRole {
+    /// Voice call audio, ducking other sounds where the system does that
+    #[default]
+    Communication,
+    /// Ordinary media playback, left alone by ducking
+    Media,
+}
+
+impl StreamRole {
+    /// `media.role` as PulseAudio and PipeWire name it
+    fn media_role(&self) -> &'static str {
+        match self {
+            StreamRole::Communication => "phone",
+            StreamRole::Media => "music",
+        }
+    }
+}
+
+impl fmt::Display for StreamRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamRole::Communication => write!(f, "communication"),
+            StreamRole::Media => write!(f, "media"),
+        }
+    }
+}
+
+impl FromStr for StreamRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "communication" => Ok(StreamRole::Communication),
+            "media" => Ok(StreamRole::Media),
+            other => Err(format!("Unknown stream role: {}", other)),
+        }
+    }
+}
+
+/// Tags the streams we open from now on with `role`, where the audio system allows
+///
+/// cpal has no way to set a stream's category, so on Linux the role goes to
+/// PulseAudio or PipeWire as a client property read when the first stream
+/// connects; call this before opening any device. A role the user already
+/// set in `PULSE_PROP` is left alone.
+///
+/// `communications_device` asks for the Windows "communications device"
+/// instead of the default one, which is what makes Windows duck other apps.
+/// cpal only opens the default console device, so it's logged and ignored.
+pub fn set_stream_role(role: StreamRole, communications_device: bool) {
+    if cfg!(target_os = "linux") {
+        let properties = std::env::var(PULSE_PROPERTIES).unwrap_or_default();
+        if !properties.contains("media.role") {
+            std::env::set_var(PULSE_PROPERTIES, with_media_role(&properties, role));
+        }
+    }
+    if communications_device {
+        if cfg!(target_os = "windows") {
+            log::warn!("The communications device can't be opened yet; using the default device");
+        } else {
+            log::debug!("The communications device setting only applies on Windows");
+        }
+    }
+}
+
+/// Adds a `media.role` to a PulseAudio property list
+fn with_media_role(properties: &str, role: StreamRole) -> String {
+    let role = format!("media.role={}", role.media_role());
+    if properties.trim().is_empty() {
+        role
+    } else {
+        format!("{} {}", properties.trim(), role)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_media_role_property() {
+        for text in ["communication", "media"] {
+            assert_eq!(text.parse::<StreamRole>().unwrap().to_string(), text);
+        }
+        assert!("alarm".parse::<StreamRole>().is_err());
+
+        assert_eq!(
+            with_media_role("", StreamRole::Communication),
+            "media.role=phone"
+        );
+        assert_eq!(
+            with_media_role("application.name=resonance", StreamRole::Media),
+            "application.name=resonance media.role=music"
+        );
+    }
+}
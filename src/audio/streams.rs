@@ -1,17 +1,45 @@
 use anyhow::{anyhow, Result};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tokio::sync::mpsc;
 use tokio::sync::oneshot;
 
-use crate::audio::{AudioCapture, SpatialAudioProcessor, VoiceProcessor};
-use crate::network::WebRtcManager;
+use crate::app::topology::{self, Plane};
+use crate::audio::transcription::TranscriptionHook;
+use crate::audio::{
+    doppler_factor, AudioCapture, AudioConfig, AudioDevice, AudioFrame, AudioPlayback, Calibration,
+    ChannelMap, CodecSettings, Degradation, DriftCompensator, HoldAudio, JitterStats, JitterTuner,
+    LoadMonitor, MotionEffect, MotionSettings, NetworkMonitor, OutputRouting, OutputSource,
+    PlayoutAdjustment, PositionSmoother, SpatialAudioProcessor, SpatialPreview, SyncedPlayout,
+    SystemAudio, UiSound, UiSoundSettings, VoiceProcessor, WatchdogEvent,
+};
+use crate::network::{now_micros, WebRtcManager};
 use crate::ui::Participant;
 
+/// Playback source name for UI sounds, which are only ever heard locally
+const UI_SOUND_SOURCE: &str = "ui-sounds";
+
+/// Playback source name for the spatial preview, also heard only locally
+const SPATIAL_PREVIEW_SOURCE: &str = "spatial-preview";
+
+/// Playback source name for hearing ourselves as peers do
+const NETWORK_MONITOR_SOURCE: &str = "network-monitor";
+
+/// How long the microphone may send nothing before its stream is reopened, unless set
+const CAPTURE_STALL_TIMEOUT: Duration = Duration::from_secs(2);
+
 /// Manages audio streams for participants in a session
 pub struct AudioStreamManager {
     webrtc: WebRtcManager,
     capture: Option<AudioCapture>,
+    // Capture opened ahead of time so joining a session starts audio immediately
+    prewarmed_capture: Option<AudioCapture>,
+    // Microphone to capture from, or the first listed if unset
+    input_device: Option<AudioDevice>,
+    // Speakers our own audio plays on when the mix isn't routed, or the default if unset
+    output_device: Option<String>,
     voice_processor: Arc<Mutex<VoiceProcessor>>,
     spatial_processor: Arc<Mutex<SpatialAudioProcessor>>,
 
@@ -19,17 +47,69 @@ pub struct AudioStreamManager {
     input_streams: HashMap<String, mpsc::Sender<Vec<f32>>>,
     output_streams: HashMap<String, Arc<Mutex<Vec<f32>>>>,
 
-    // Mapping of participant positions for spatial audio
-    participant_positions: Arc<Mutex<HashMap<String, (f32, f32, f32)>>>,
+    // Participant positions for spatial audio, gliding to each new update
+    participant_positions: Arc<Mutex<HashMap<String, PositionSmoother>>>,
 
     // Store the raw capture data for monitoring
     raw_capture_data: Arc<Mutex<Vec<f32>>>,
 
+    // When voice was last detected in the local capture
+    last_voice_activity: Arc<Mutex<Option<std::time::Instant>>>,
+
+    // Whether the local capture is muted
+    muted: Arc<AtomicBool>,
+
+    // Audio sent in place of the microphone while we're away
+    hold: Arc<Mutex<Option<HoldAudio>>>,
+
     // Track whether streams are active
     active: bool,
 
-    // Sample rate for audio processing
-    sample_rate: u32,
+    // Rate and channels audio is processed at
+    config: AudioConfig,
+
+    // Optional speech-to-text for remote participants
+    transcription: Option<TranscriptionHook>,
+
+    // Secondary output devices for the room mix or single participants
+    routing: OutputRouting,
+    playbacks: HashMap<String, AudioPlayback>,
+
+    // Doppler and motion effects for moving participants, with each one's state
+    motion: MotionSettings,
+    motion_effects: HashMap<String, MotionEffect>,
+
+    // How much of each participant's audio is held back to ride out jitter
+    jitter: HashMap<String, JitterTuner>,
+    // Each participant's clock drift against ours, resampled away
+    drift: HashMap<String, DriftCompensator>,
+    // Participants whose audio plays in step with the rest of the room
+    synced: HashMap<String, SyncedPlayout>,
+
+    // The only participant heard locally while soloing
+    solo: Option<String>,
+
+    // Which UI sounds play, and the device they play on when the mix isn't routed
+    ui_sounds: UiSoundSettings,
+    ui_playback: Option<AudioPlayback>,
+
+    // Time spent processing audio, and the stages given up to keep up
+    load: Arc<Mutex<LoadMonitor>>,
+
+    // Plays our processed audio back through the codec, and where it's heard, if on
+    network_monitor: Arc<Mutex<Option<(NetworkMonitor, AudioPlayback)>>>,
+
+    // How every playback stream lays the mix onto its device's channels
+    channel_map: ChannelMap,
+
+    // Other apps' audio mixed into what we send, while shared
+    system_audio: Arc<Mutex<Option<SystemAudio>>>,
+
+    // How long the microphone may send nothing before its stream is reopened
+    capture_stall_timeout: Duration,
+    // Stalls and recoveries of the microphone, until the receiver is taken to show them
+    capture_events: mpsc::UnboundedSender<WatchdogEvent>,
+    capture_events_rx: Option<mpsc::UnboundedReceiver<WatchdogEvent>>,
 }
 
 /// Represents an active audio stream
@@ -37,7 +117,8 @@ pub struct AudioStream {
     session_id: String,
     stream_id: String,
     active: bool,
-    shutdown_tx: Option<oneshot::Sender<()>>,
+    // Dropping the stream drops this, which ends its processing task
+    _shutdown_tx: Option<oneshot::Sender<()>>,
 }
 
 impl AudioStream {
@@ -47,7 +128,7 @@ impl AudioStream {
             session_id,
             stream_id,
             active: true,
-            shutdown_tx: None,
+            _shutdown_tx: None,
         }
     }
 
@@ -67,12 +148,22 @@ impl AudioStream {
     }
 }
 
+impl Default for AudioStreamManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl AudioStreamManager {
     /// Creates a new audio stream manager
     pub fn new() -> Self {
+        let (capture_events, capture_events_rx) = mpsc::unbounded_channel();
         Self {
             webrtc: WebRtcManager::new(),
             capture: None,
+            prewarmed_capture: None,
+            input_device: None,
+            output_device: None,
             voice_processor: Arc::new(Mutex::new(
                 VoiceProcessor::new()
                     .with_vad_threshold(0.05)
@@ -83,8 +174,29 @@ impl AudioStreamManager {
             output_streams: HashMap::new(),
             participant_positions: Arc::new(Mutex::new(HashMap::new())),
             raw_capture_data: Arc::new(Mutex::new(Vec::new())),
+            last_voice_activity: Arc::new(Mutex::new(None)),
+            muted: Arc::new(AtomicBool::new(false)),
+            hold: Arc::new(Mutex::new(None)),
             active: false,
-            sample_rate: 48000,
+            config: AudioConfig::default(),
+            transcription: None,
+            routing: OutputRouting::default(),
+            playbacks: HashMap::new(),
+            motion: MotionSettings::default(),
+            motion_effects: HashMap::new(),
+            jitter: HashMap::new(),
+            drift: HashMap::new(),
+            synced: HashMap::new(),
+            solo: None,
+            ui_sounds: UiSoundSettings::default(),
+            ui_playback: None,
+            load: Arc::new(Mutex::new(LoadMonitor::default())),
+            network_monitor: Arc::new(Mutex::new(None)),
+            channel_map: ChannelMap::default(),
+            system_audio: Arc::new(Mutex::new(None)),
+            capture_stall_timeout: CAPTURE_STALL_TIMEOUT,
+            capture_events,
+            capture_events_rx: Some(capture_events_rx),
         }
     }
 
@@ -95,13 +207,92 @@ impl AudioStreamManager {
         // Initialize spatial processor with sample rate
         {
             let mut spatial = self.spatial_processor.lock().unwrap();
-            spatial.set_sample_rate(self.sample_rate);
+            spatial.set_sample_rate(self.config.sample_rate);
         }
 
         self.active = true;
         Ok(())
     }
 
+    /// Chooses the microphone streams capture from, from the next one opened
+    pub fn set_input_device(&mut self, device: Option<AudioDevice>) {
+        self.input_device = device;
+    }
+
+    /// Chooses the speakers our own audio plays on when the mix isn't routed,
+    /// from the next playback opened
+    pub fn set_output_device(&mut self, device: Option<String>) {
+        self.output_device = device;
+    }
+
+    /// Sets how long the microphone may send nothing before its stream is
+    /// reopened, from the next capture opened
+    pub fn set_capture_stall_timeout(&mut self, timeout: Duration) {
+        self.capture_stall_timeout = timeout;
+    }
+
+    /// Takes the receiver for the capture watchdog's reports of stalls and recoveries
+    ///
+    /// Only one receiver exists per manager; later calls return `None`.
+    pub fn take_capture_events(&mut self) -> Option<mpsc::UnboundedReceiver<WatchdogEvent>> {
+        self.capture_events_rx.take()
+    }
+
+    /// A capture at our rate, reporting to the capture event receiver
+    fn new_capture(&self) -> AudioCapture {
+        let mut capture = AudioCapture::new();
+        capture.set_sample_rate(self.config.sample_rate);
+        capture.set_stall_timeout(self.capture_stall_timeout);
+        let events = self.capture_events.clone();
+        capture.set_diagnostics_callback(move |event| {
+            match &event {
+                WatchdogEvent::StreamStalled { silent_for } => {
+                    log::warn!("Microphone sent nothing for {:?}; reopening it", silent_for)
+                }
+                WatchdogEvent::StreamRebuilt => log::info!("Microphone reopened"),
+                WatchdogEvent::RebuildFailed(e) => {
+                    log::warn!("Couldn't reopen the microphone: {}", e)
+                }
+            }
+            let _ = events.send(event);
+        });
+        capture
+    }
+
+    /// Opens the microphone and speakers ahead of time so `create_stream`
+    /// and the first sound played don't wait on them
+    ///
+    /// Routed devices are opened with their routes; this opens the chosen
+    /// speakers when the mix isn't routed. Both are tried even if one fails.
+    pub async fn prewarm(&mut self) -> Result<()> {
+        let capture = self.prewarm_capture().await;
+        let playback = self.prewarm_playback();
+        capture.and(playback)
+    }
+
+    async fn prewarm_capture(&mut self) -> Result<()> {
+        if self.capture.is_some() || self.prewarmed_capture.is_some() {
+            return Ok(());
+        }
+
+        let mut capture = self.new_capture();
+        if let Some(device) = &self.input_device {
+            capture.set_device(device.clone())?;
+        }
+        capture.prewarm().await?;
+        self.prewarmed_capture = Some(capture);
+        Ok(())
+    }
+
+    fn prewarm_playback(&mut self) -> Result<()> {
+        if self.ui_playback.is_some() || self.routing.device_for(&OutputSource::Mix).is_some() {
+            return Ok(());
+        }
+
+        self.ui_playback = Some(self.open_playback(self.local_device())?);
+        Ok(())
+    }
+
     /// Creates a new audio stream for a session
     pub async fn create_stream(&mut self, session_id: String) -> Result<AudioStream> {
         if !self.active {
@@ -113,7 +304,14 @@ impl AudioStreamManager {
 
         // Initialize audio capture if not already set up
         if self.capture.is_none() {
-            let mut capture = AudioCapture::new();
+            let mut capture = self
+                .prewarmed_capture
+                .take()
+                .unwrap_or_else(|| self.new_capture());
+            capture.set_sample_rate(self.config.sample_rate);
+            if let Some(device) = &self.input_device {
+                capture.set_device(device.clone())?;
+            }
 
             // Set up the processing pipeline
             let voice_processor = Arc::clone(&self.voice_processor);
@@ -121,22 +319,39 @@ impl AudioStreamManager {
             let output_streams = Arc::new(Mutex::new(self.output_streams.clone()));
             let participant_positions = Arc::clone(&self.participant_positions);
             let raw_capture_data = Arc::clone(&self.raw_capture_data);
+            let last_voice_activity = Arc::clone(&self.last_voice_activity);
+            let muted = Arc::clone(&self.muted);
+            let hold = Arc::clone(&self.hold);
+            let load = Arc::clone(&self.load);
+            let network_monitor = Arc::clone(&self.network_monitor);
+            let system_audio = Arc::clone(&self.system_audio);
 
             // Create a channel for shutdown signaling
             let (shutdown_tx, mut shutdown_rx) = oneshot::channel::<()>();
 
             // Create channel for audio data
-            let (tx, mut rx) = mpsc::channel::<Vec<f32>>(100);
+            let (tx, mut rx) = mpsc::channel::<AudioFrame>(100);
 
             // Set up the callback for audio data
-            capture.set_data_callback(move |data| {
+            let config = self.config;
+            capture.set_data_callback(move |frame| {
+                // The device may not run at our rate, or in mono
+                let mut frame = config.conform(frame);
+
+                // Away, the hold audio replaces the microphone; muted, it captures silence
+                if let Some(hold) = hold.lock().unwrap().as_mut() {
+                    hold.fill(&mut frame.samples, frame.channels);
+                } else if muted.load(Ordering::Relaxed) {
+                    frame.samples.fill(0.0);
+                }
+
                 // Store raw capture data for visualization
                 {
                     let mut raw_data = raw_capture_data.lock().unwrap();
-                    *raw_data = data.clone();
+                    *raw_data = frame.samples.clone();
                 }
 
-                let _ = tx.try_send(data);
+                let _ = tx.try_send(frame);
             });
 
             // Start the audio capture
@@ -145,9 +360,9 @@ impl AudioStreamManager {
             // Create a processing task
             let webrtc = self.webrtc.clone();
             let session_id_clone = session_id.clone();
-            let sample_rate = self.sample_rate;
+            let _sample_rate = self.config.sample_rate;
 
-            tokio::spawn(async move {
+            topology::spawn(Plane::Media, "mixer", async move {
                 // Buffer to store captured audio data from all participants
                 let mut participant_buffers: HashMap<String, Vec<f32>> = HashMap::new();
 
@@ -162,21 +377,39 @@ impl AudioStreamManager {
                         }
 
                         // Process incoming audio data
-                        Some(audio_data) = rx.recv() => {
+                        Some(frame) = rx.recv() => {
+                            let started = std::time::Instant::now();
+                            let (sample_rate, channels) = (frame.sample_rate, frame.channels);
+
                             // Apply voice processing
-                            let processed = {
+                            let mut processed = {
                                 let voice_processor = voice_processor.lock().unwrap();
-                                voice_processor.process(audio_data)
+                                voice_processor.process(frame.samples)
                             };
 
+                            // Shared system audio joins after voice processing, which would take it for noise
+                            let sharing = system_audio
+                                .lock()
+                                .unwrap()
+                                .as_ref()
+                                .is_some_and(|shared| shared.mix_into(&mut processed, channels));
+
+                            // Hear ourselves as peers do, if monitoring
+                            if let Some((monitor, playback)) = network_monitor.lock().unwrap().as_mut() {
+                                let sent = AudioFrame::new(processed.clone(), sample_rate, channels);
+                                playback.push(NETWORK_MONITOR_SOURCE, &monitor.process(&sent));
+                            }
+
                             // Check for voice activity
-                            let has_voice = {
+                            let has_voice = sharing || {
                                 let voice_processor = voice_processor.lock().unwrap();
                                 voice_processor.detect_voice_activity(&processed)
                             };
 
                             // If voice detected, send to peers
                             if has_voice {
+                                *last_voice_activity.lock().unwrap() = Some(std::time::Instant::now());
+
                                 // Send the processed audio to connected peers
                                 if let Ok(connections) = webrtc.get_connections() {
                                     for conn in connections {
@@ -195,7 +428,12 @@ impl AudioStreamManager {
                                 // and mix the result for output
                                 let mut spatial_processor_guard = spatial_processor.lock().unwrap();
                                 let mut streams_guard = output_streams.lock().unwrap();
-                                let positions_guard = participant_positions.lock().unwrap();
+                                let mut positions_guard = participant_positions.lock().unwrap();
+                                if let Some(listener) = glide(&mut positions_guard, "Me") {
+                                    spatial_processor_guard.set_listener_position(
+                                        listener.0, listener.1, listener.2
+                                    );
+                                }
 
                                 // For each participant, position their audio correctly and mix
                                 for (name, buffer) in &participant_buffers {
@@ -208,7 +446,7 @@ impl AudioStreamManager {
 
                                     if is_fresh {
                                         // Get position for this participant
-                                        if let Some(position) = positions_guard.get(name) {
+                                        if let Some(position) = glide(&mut positions_guard, name) {
                                             // Set source position for spatial processing
                                             spatial_processor_guard.set_source_position(
                                                 position.0, position.1, position.2
@@ -241,6 +479,8 @@ impl AudioStreamManager {
                                     participant_buffers.remove(&name);
                                 }
                             }
+
+                            record_load(&load, &spatial_processor, started.elapsed());
                         }
                     }
                 }
@@ -253,7 +493,7 @@ impl AudioStreamManager {
                 session_id,
                 stream_id,
                 active: true,
-                shutdown_tx: Some(shutdown_tx),
+                _shutdown_tx: Some(shutdown_tx),
             };
 
             return Ok(stream);
@@ -264,42 +504,84 @@ impl AudioStreamManager {
         Ok(stream)
     }
 
+    /// Mutes or unmutes the local capture
+    pub fn set_muted(&self, muted: bool) {
+        self.muted.store(muted, Ordering::Relaxed);
+    }
+
+    /// Sample rate audio is processed at
+    pub fn sample_rate(&self) -> u32 {
+        self.config.sample_rate
+    }
+
+    /// Whether the local capture is muted
+    pub fn is_muted(&self) -> bool {
+        self.muted.load(Ordering::Relaxed)
+    }
+
+    /// Sends `hold` in place of the microphone while we're away, or the microphone again with `None`
+    pub fn set_hold_audio(&self, hold: Option<HoldAudio>) {
+        *self.hold.lock().unwrap() = hold;
+    }
+
+    /// Whether hold audio is replacing the microphone
+    pub fn is_on_hold(&self) -> bool {
+        self.hold.lock().unwrap().is_some()
+    }
+
+    /// Gets when voice was last detected in the local capture
+    pub fn last_voice_activity(&self) -> Option<std::time::Instant> {
+        *self.last_voice_activity.lock().unwrap()
+    }
+
     /// Stops and cleans up all audio streams
     pub async fn stop_all_streams(&mut self) -> Result<()> {
         if let Some(mut capture) = self.capture.take() {
             capture.stop().await?;
         }
+        if let Some(mut capture) = self.prewarmed_capture.take() {
+            capture.stop().await?;
+        }
 
         self.input_streams.clear();
         self.output_streams.clear();
+        self.playbacks.clear();
+
+        Ok(())
+    }
+
+    /// Closes the audio devices and opens them again, for the stream of `session_id`
+    ///
+    /// Devices can come back from the machine sleeping stale, or gone from
+    /// under their streams; this picks up whatever is there now.
+    pub async fn reopen_devices(&mut self, session_id: String) -> Result<()> {
+        if let Some(mut capture) = self.capture.take() {
+            capture.stop().await?;
+        }
+        if let Some(mut capture) = self.prewarmed_capture.take() {
+            capture.stop().await?;
+        }
+        self.ui_playback = None;
 
+        self.playbacks.clear();
+        let routing = std::mem::take(&mut self.routing);
+        if let Err(e) = self.set_output_routing(routing) {
+            log::warn!("{}", e);
+        }
+
+        self.create_stream(session_id).await?;
         Ok(())
     }
 
     /// Updates participant positions for spatial audio processing
     pub fn update_positions(&mut self, participants: &[Participant]) -> Result<()> {
-        // Update the current user's position in the spatial processor
+        // Everyone, including the listener ("Me"), glides to their new position
+        let mut positions = self.participant_positions.lock().unwrap();
         for participant in participants {
-            if participant.name == "Me" {
-                // Update listener position
-                let mut spatial_processor = self.spatial_processor.lock().unwrap();
-                spatial_processor.set_listener_position(
-                    participant.position.0,
-                    participant.position.1,
-                    participant.position.2,
-                );
-            }
-
-            // Update participant position map
-            let mut positions = self.participant_positions.lock().unwrap();
-            positions.insert(
-                participant.name.clone(),
-                (
-                    participant.position.0,
-                    participant.position.1,
-                    participant.position.2,
-                ),
-            );
+            positions
+                .entry(participant.name.clone())
+                .and_modify(|smoother| smoother.set_target(participant.position))
+                .or_insert_with(|| PositionSmoother::new(participant.position));
         }
 
         Ok(())
@@ -317,48 +599,424 @@ impl AudioStreamManager {
         Ok(())
     }
 
+    /// Fades out the room's audio, so hanging up doesn't cut it off mid-word
+    pub fn fade_out(&self, duration: std::time::Duration) {
+        for playback in self.playbacks.values() {
+            playback.fade_out(duration);
+        }
+    }
+
     /// Removes a participant's output stream
     pub fn remove_participant_stream(&mut self, name: &str) -> Result<()> {
         self.output_streams.remove(name);
+        self.motion_effects.remove(name);
+        self.jitter.remove(name);
+        self.drift.remove(name);
+        for playback in self.playbacks.values() {
+            playback.remove_source(name);
+        }
+        Ok(())
+    }
+
+    /// Sends the room mix or single participants to output devices of their own
+    ///
+    /// Opens a playback stream per routed device and closes streams for devices
+    /// no longer in use. A participant with a route of their own is also heard
+    /// in the routed room mix, unless both go to the same device.
+    pub fn set_output_routing(&mut self, routing: OutputRouting) -> Result<()> {
+        let devices = routing.devices();
+        self.playbacks
+            .retain(|device, _| devices.contains(&device.as_str()));
+
+        let mut failed = Vec::new();
+        for device in devices {
+            if self.playbacks.contains_key(device) {
+                continue;
+            }
+            match self.open_playback(device) {
+                Ok(playback) => {
+                    self.playbacks.insert(device.to_string(), playback);
+                }
+                Err(e) => failed.push(format!("{}: {}", device, e)),
+            }
+        }
+
+        self.routing = routing;
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "Failed to open output devices: {}",
+                failed.join(", ")
+            ))
+        }
+    }
+
+    /// Current output routing
+    pub fn output_routing(&self) -> &OutputRouting {
+        &self.routing
+    }
+
+    /// Devices a participant's audio is routed to
+    fn routed_devices(&self, participant_name: &str) -> Vec<&AudioPlayback> {
+        let peer = OutputSource::Peer(participant_name.to_string());
+        let peer_device = self.routing.device_for(&peer);
+        let mix_device = self
+            .routing
+            .device_for(&OutputSource::Mix)
+            .filter(|device| Some(*device) != peer_device);
+
+        [peer_device, mix_device]
+            .into_iter()
+            .flatten()
+            .filter_map(|device| self.playbacks.get(device))
+            .collect()
+    }
+
+    /// Plays a participant's processed audio on the devices it is routed to
+    fn play_routed(&self, participant_name: &str, audio: &[f32]) {
+        for playback in self.routed_devices(participant_name) {
+            playback.push(participant_name, audio);
+        }
+    }
+
+    /// Pads or trims a participant's frame to keep their queued audio at the
+    /// jitter buffer's target, or to play it when the room does if it's synced
+    fn buffer_jitter(
+        &mut self,
+        participant_name: &str,
+        capture_ts: u64,
+        mut audio: Vec<f32>,
+    ) -> Vec<f32> {
+        let rate = self.config.sample_rate as f64;
+        let frame = std::time::Duration::from_secs_f64(audio.len() as f64 / 2.0 / rate);
+        let queued = self
+            .routed_devices(participant_name)
+            .first()
+            .map(|playback| playback.queued(participant_name));
+        let synced = self.synced.get(participant_name).copied();
+        let tuner = self.jitter.entry(participant_name.to_string()).or_default();
+        let at = std::time::Instant::now();
+        let adjustment = match synced {
+            Some(sync) => {
+                tuner.arrive_synced(at, frame, queued, sync.due_in(capture_ts, now_micros()))
+            }
+            None => tuner.arrive(at, frame, queued),
+        };
+
+        // Follow the participant's clock, so their queue neither creeps up nor runs dry
+        let drift = self.drift.entry(participant_name.to_string()).or_default();
+        if let Some(queued) = queued {
+            drift.observe(at, queued);
+            audio = drift.process(&audio, self.config.sample_rate);
+        }
+
+        // In whole stereo frames
+        let samples = |duration: std::time::Duration| (duration.as_secs_f64() * rate) as usize * 2;
+        match adjustment {
+            PlayoutAdjustment::Play => audio,
+            PlayoutAdjustment::Pad(pad) => {
+                let mut padded = vec![0.0; samples(pad)];
+                drift.adjust(padded.len() as f64 / 2.0 / rate);
+                padded.append(&mut audio);
+                padded
+            }
+            PlayoutAdjustment::Skip(skip) => {
+                let skipped = samples(skip).min(audio.len());
+                drift.adjust(-(skipped as f64) / 2.0 / rate);
+                audio.split_off(skipped)
+            }
+        }
+    }
+
+    /// Plays each of these participants' audio at the same time as the rest
+    /// of the room, and everyone else's as soon as jitter allows
+    pub fn set_synced_playouts(&mut self, synced: HashMap<String, SyncedPlayout>) {
+        self.synced = synced;
+    }
+
+    /// A participant's jitter buffer: its target delay and what it achieves,
+    /// and how far their clock drifts from ours
+    pub fn jitter_stats(&self, participant_name: &str) -> Option<JitterStats> {
+        self.jitter.get(participant_name).map(|tuner| JitterStats {
+            drift: self
+                .drift
+                .get(participant_name)
+                .map_or(0.0, DriftCompensator::drift),
+            ..tuner.stats()
+        })
+    }
+
+    /// Applies a microphone calibration to the local capture, or none to leave it as it is
+    pub fn set_calibration(&self, calibration: Option<Calibration>) {
+        self.voice_processor
+            .lock()
+            .unwrap()
+            .set_calibration(calibration);
+    }
+
+    /// Turns Doppler and motion effects on or off, or changes their quality
+    pub fn set_motion_effects(&mut self, settings: MotionSettings) {
+        self.motion = settings;
+        self.motion_effects.clear();
+    }
+
+    /// Hears only `participant`, or everyone again with `None`
+    ///
+    /// Soloing only silences others locally; nothing changes for them or in
+    /// what we send, and clearing it brings back the mix as it was.
+    pub fn set_solo(&mut self, participant: Option<String>) {
+        self.solo = participant;
+    }
+
+    /// The participant being soloed, if any
+    pub fn solo(&self) -> Option<&str> {
+        self.solo.as_deref()
+    }
+
+    /// Whether a participant is heard locally
+    pub fn is_audible(&self, participant_name: &str) -> bool {
+        self.solo
+            .as_deref()
+            .is_none_or(|solo| solo == participant_name)
+    }
+
+    /// Chooses which UI sounds play and how loud
+    pub fn set_ui_sounds(&mut self, settings: UiSoundSettings) {
+        self.ui_sounds = settings;
+    }
+
+    /// Plays a UI sound where the room is heard, if it is enabled
+    ///
+    /// The sound goes to the routed mix device, or the default output device
+    /// otherwise. It is never sent to peers.
+    pub fn play_ui_sound(&mut self, sound: UiSound) {
+        if !self.ui_sounds.allows(sound) {
+            return;
+        }
+        let stereo = sound.render(self.config.sample_rate, self.ui_sounds.gain());
+        self.play_local(UI_SOUND_SOURCE, &stereo);
+    }
+
+    /// A preview of the voice walking round us, placed by the mixer's current spatial settings
+    pub fn spatial_preview(&self) -> SpatialPreview {
+        let processor = self.spatial_processor.lock().unwrap().clone();
+        SpatialPreview::new(processor, self.config.sample_rate)
+    }
+
+    /// Plays one block of a [`SpatialPreview`]
+    pub fn play_spatial_preview(&mut self, stereo: &[f32]) {
+        self.play_local(SPATIAL_PREVIEW_SOURCE, stereo);
+    }
+
+    /// Plays our processed audio back to us through `codec`, quietly, as
+    /// peers hear it, or stops with `None`
+    ///
+    /// Heard where the room mix goes, or on the chosen speakers.
+    pub fn set_network_monitor(&mut self, codec: Option<CodecSettings>) -> Result<()> {
+        let mut network_monitor = self.network_monitor.lock().unwrap();
+        let Some(codec) = codec else {
+            *network_monitor = None;
+            return Ok(());
+        };
+        if let Some((monitor, _)) = network_monitor.as_ref() {
+            if monitor.codec() == codec {
+                return Ok(());
+            }
+        }
+
+        let device = self
+            .routing
+            .device_for(&OutputSource::Mix)
+            .unwrap_or(self.local_device());
+        let playback = self.open_playback(device)?;
+        *network_monitor = Some((NetworkMonitor::new(codec), playback));
         Ok(())
     }
 
+    /// Shares other apps' audio with the room at `percent` of its level, or
+    /// stops sharing it with `None`
+    pub fn set_system_audio(&mut self, percent: Option<u32>) -> Result<()> {
+        let mut system_audio = self.system_audio.lock().unwrap();
+        let Some(percent) = percent else {
+            *system_audio = None;
+            return Ok(());
+        };
+        let level = percent as f32 / 100.0;
+        match system_audio.as_ref() {
+            Some(shared) => shared.set_level(level),
+            None => *system_audio = Some(SystemAudio::start(self.config.sample_rate, level)?),
+        }
+        Ok(())
+    }
+
+    /// Downmixes, swaps or moves the mix to other channels on every output device
+    pub fn set_channel_map(&mut self, map: ChannelMap) {
+        self.channel_map = map;
+        for playback in self.playbacks.values().chain(&self.ui_playback) {
+            playback.set_channel_map(map);
+        }
+        if let Some((_, playback)) = self.network_monitor.lock().unwrap().as_ref() {
+            playback.set_channel_map(map);
+        }
+    }
+
+    fn open_playback(&self, device: &str) -> Result<AudioPlayback> {
+        let playback = AudioPlayback::open(device, self.config.sample_rate)?;
+        playback.set_channel_map(self.channel_map);
+        Ok(playback)
+    }
+
+    /// Speakers our own audio plays on when the mix isn't routed
+    fn local_device(&self) -> &str {
+        self.output_device.as_deref().unwrap_or("default")
+    }
+
+    /// Plays audio of our own, not a peer's, where the mix goes, or on the
+    /// chosen speakers outside a room
+    fn play_local(&mut self, source: &str, stereo: &[f32]) {
+        if let Some(device) = self.routing.device_for(&OutputSource::Mix) {
+            if let Some(playback) = self.playbacks.get(device) {
+                playback.push(source, stereo);
+                return;
+            }
+        }
+
+        if self.ui_playback.is_none() {
+            match self.open_playback(self.local_device()) {
+                Ok(playback) => self.ui_playback = Some(playback),
+                Err(e) => {
+                    log::debug!("No device for {}: {}", source, e);
+                    return;
+                }
+            }
+        }
+        if let Some(playback) = &self.ui_playback {
+            playback.push(source, stereo);
+        }
+    }
+
     /// Process received audio from another participant
     pub async fn process_remote_audio(
         &mut self,
         participant_name: &str,
-        audio_data: &[f32],
+        frame: &AudioFrame,
     ) -> Result<()> {
+        let started = std::time::Instant::now();
+
         // Create the participant stream if it doesn't exist
         if !self.output_streams.contains_key(participant_name) {
             self.add_participant_stream(participant_name)?;
         }
 
-        // Get position for this participant
-        let position = {
-            let positions = self.participant_positions.lock().unwrap();
-            positions
-                .get(participant_name)
-                .cloned()
-                .unwrap_or((0.0, 0.0, 0.0))
+        // Transcribe the unprocessed audio
+        if let Some(hook) = self.transcription.as_mut() {
+            hook.push_audio(participant_name, &frame.samples, frame.sample_rate);
+        }
+
+        // Peers may send at another rate; play everyone at ours
+        let frame = &self.config.conform(frame.clone());
+
+        // Get where we and this participant are along any glide, and how fast we're moving
+        let (listener, position, doppler) = {
+            let mut positions = self.participant_positions.lock().unwrap();
+            let listener = glide(&mut positions, "Me");
+            let position = glide(&mut positions, participant_name).unwrap_or((0.0, 0.0, 0.0));
+            let velocity = |name: &str| {
+                positions
+                    .get(name)
+                    .map_or((0.0, 0.0, 0.0), |smoother| smoother.velocity())
+            };
+            let doppler = doppler_factor(
+                (listener.unwrap_or_default(), velocity("Me")),
+                (position, velocity(participant_name)),
+            );
+            (listener, position, doppler)
+        };
+
+        // Shift moving participants' pitch and level before placing them
+        let samples = if self.motion.enabled {
+            let quality = self.motion.quality;
+            self.motion_effects
+                .entry(participant_name.to_string())
+                .or_insert_with(|| MotionEffect::new(quality))
+                .process(&frame.samples, doppler)
+        } else {
+            frame.samples.clone()
         };
 
         // Apply spatial processing
-        let spatial_audio = {
+        let mut spatial_audio = {
             let mut spatial = self.spatial_processor.lock().unwrap();
+            if let Some(listener) = listener {
+                spatial.set_listener_position(listener.0, listener.1, listener.2);
+            }
             spatial.set_source_position(position.0, position.1, position.2);
-            spatial.process(audio_data)
+            spatial.process(&samples)
         };
 
+        // Others are silent while someone is soloed
+        if !self.is_audible(participant_name) {
+            spatial_audio.fill(0.0);
+        }
+
+        // Hold back enough to ride out the participant's jitter
+        let spatial_audio = self.buffer_jitter(participant_name, frame.capture_ts, spatial_audio);
+
+        self.play_routed(participant_name, &spatial_audio);
+
         // Store the processed audio
         if let Some(output) = self.output_streams.get(participant_name) {
             let mut out = output.lock().unwrap();
             *out = spatial_audio;
         }
 
+        record_load(&self.load, &self.spatial_processor, started.elapsed());
         Ok(())
     }
 
+    /// Places participants with plain panning, or with ear filtering again
+    ///
+    /// Stays on while the CPU load called for it.
+    pub fn set_simple_panning(&self, enabled: bool) {
+        let degraded = self
+            .load
+            .lock()
+            .unwrap()
+            .is_applied(Degradation::SimplePanning);
+        self.spatial_processor
+            .lock()
+            .unwrap()
+            .set_simple_panning(enabled || degraded);
+    }
+
+    /// Sets the share of one core audio processing may use before quality is
+    /// lowered, or `None` to never lower it
+    pub fn set_load_limit(&self, limit: Option<f64>) {
+        self.load.lock().unwrap().set_limit(limit);
+    }
+
+    /// Share of one core spent processing audio lately
+    pub fn cpu_load(&self) -> f64 {
+        self.load
+            .lock()
+            .unwrap()
+            .utilization(std::time::Instant::now())
+    }
+
+    /// Stages given up since the last call, to tell the user about
+    ///
+    /// Spatial processing falls back to plain panning on its own; codec
+    /// complexity is up to whoever owns the encoders.
+    pub fn take_degradations(&self) -> Vec<Degradation> {
+        self.load.lock().unwrap().take_unreported()
+    }
+
+    /// Sends remote participants' speech to a transcription hook
+    pub fn set_transcription_hook(&mut self, hook: Option<TranscriptionHook>) {
+        self.transcription = hook;
+    }
+
     /// Get the raw capture data for visualization
     pub fn get_raw_capture_data(&self) -> Vec<f32> {
         let data = self.raw_capture_data.lock().unwrap();
@@ -367,21 +1025,46 @@ impl AudioStreamManager {
 
     /// Set the sample rate for all audio processing
     pub fn set_sample_rate(&mut self, sample_rate: u32) -> Result<()> {
-        self.sample_rate = sample_rate;
+        self.set_audio_config(AudioConfig {
+            sample_rate,
+            ..self.config
+        })
+    }
+
+    /// Sets the rate and channels audio is captured, processed and played at
+    pub fn set_audio_config(&mut self, config: AudioConfig) -> Result<()> {
+        self.config = config;
 
         // Update spatial processor
         if let Ok(mut spatial) = self.spatial_processor.lock() {
-            spatial.set_sample_rate(sample_rate);
+            spatial.set_sample_rate(config.sample_rate);
         }
 
         Ok(())
     }
+
+    /// Rate and channels audio is processed at
+    pub fn audio_config(&self) -> AudioConfig {
+        self.config
+    }
+}
+
+/// Records time spent processing audio, switching to plain panning if the load calls for it
+fn record_load(
+    load: &Mutex<LoadMonitor>,
+    spatial_processor: &Mutex<SpatialAudioProcessor>,
+    busy: std::time::Duration,
+) {
+    let step = load.lock().unwrap().record(busy, std::time::Instant::now());
+    if step == Some(Degradation::SimplePanning) {
+        spatial_processor.lock().unwrap().set_simple_panning(true);
+    }
 }
 
 impl Drop for AudioStreamManager {
     fn drop(&mut self) {
         // Clean up any resources when the manager is dropped
-        if let Some(mut capture) = self.capture.take() {
+        if let Some(capture) = self.capture.take() {
             // We can't use async/await in drop, so just send a stop signal
             // This will eventually be processed by the capture's internal task
             std::mem::forget(capture);
@@ -389,6 +1072,13 @@ impl Drop for AudioStreamManager {
     }
 }
 
+/// Advances a participant's position glide to now
+fn glide(positions: &mut HashMap<String, PositionSmoother>, name: &str) -> Option<(f32, f32, f32)> {
+    positions
+        .get_mut(name)
+        .map(|smoother| smoother.advance(std::time::Instant::now()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -410,6 +1100,20 @@ mod tests {
         audio
     }
 
+    #[tokio::test]
+    async fn test_capture_events_reach_the_receiver() {
+        let mut manager = AudioStreamManager::new();
+        let mut events = manager.take_capture_events().unwrap();
+        assert!(manager.take_capture_events().is_none());
+
+        // Captures opened later report through the same receiver
+        manager
+            .capture_events
+            .send(WatchdogEvent::StreamRebuilt)
+            .unwrap();
+        assert_eq!(events.try_recv().unwrap(), WatchdogEvent::StreamRebuilt);
+    }
+
     #[tokio::test]
     async fn test_audio_stream_creation() {
         let mut manager = AudioStreamManager::new();
@@ -445,20 +1149,6 @@ mod tests {
         audio_data: Vec<f32>,
     }
 
-    impl MockParticipant {
-        async fn send_audio(&self, audio: &[f32]) -> Result<()> {
-            // In a real implementation, this would send the audio over WebRTC
-            // For testing, we'll just return Ok
-            Ok(())
-        }
-
-        async fn receive_audio(&mut self) -> Result<Vec<f32>> {
-            // In a real implementation, this would receive audio from WebRTC
-            // For testing, we'll return a copy of our test audio
-            Ok(self.audio_data.clone())
-        }
-    }
-
     #[tokio::test]
     async fn test_audio_data_transmission() {
         let mut manager = AudioStreamManager::new();
@@ -481,17 +1171,17 @@ mod tests {
         manager.update_positions(&participants).unwrap();
 
         // Process some mock audio from Alice
+        let frame = AudioFrame::new(alice.audio_data.clone(), 48000, 1).with_peer_id(&alice.name);
         manager
-            .process_remote_audio(&alice.name, &alice.audio_data)
+            .process_remote_audio(&alice.name, &frame)
             .await
             .unwrap();
 
         // Verify we can retrieve the processed audio
         let alice_output = manager.get_participant_audio(&alice.name).unwrap();
-        let alice_audio = alice_output.lock().unwrap();
 
         // Check that we have spatialized stereo audio (2 channels)
-        assert!(!alice_audio.is_empty());
+        assert!(!alice_output.lock().unwrap().is_empty());
 
         // Clean up
         manager.stop_all_streams().await.unwrap();
@@ -0,0 +1,273 @@
+use anyhow::{anyhow, Result};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use crate::app::topology::{self, Plane};
+
+/// Most system audio held for the mix, in seconds; older audio is dropped
+/// so a stalled mixer doesn't leave the share lagging behind
+const MAX_QUEUED_SECS: f32 = 0.2;
+
+/// Mono system audio waiting to be mixed into what we send
+#[derive(Debug)]
+struct LoopbackBuffer {
+    samples: VecDeque<f32>,
+    capacity: usize,
+    level: f32,
+}
+
+impl LoopbackBuffer {
+    fn new(sample_rate: u32, level: f32) -> Self {
+        let capacity = (sample_rate as f32 * MAX_QUEUED_SECS) as usize;
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+            level,
+        }
+    }
+
+    fn push(&mut self, mono: impl IntoIterator<Item = f32>) {
+        self.samples.extend(mono);
+        let excess = self.samples.len().saturating_sub(self.capacity);
+        self.samples.drain(..excess);
+    }
+
+    /// Adds queued audio to interleaved `samples`, returning whether there was any
+    fn mix_into(&mut self, samples: &mut [f32], channels: u16) -> bool {
+        if self.samples.is_empty() {
+            return false;
+        }
+        for frame in samples.chunks_mut(channels.max(1) as usize) {
+            let Some(shared) = self.samples.pop_front() else {
+                break;
+            };
+            for sample in frame {
+                *sample = (*sample + shared * self.level).clamp(-1.0, 1.0);
+            }
+        }
+        true
+    }
+}
+
+/// Other apps' audio, like music or a video, shared into the room
+///
+/// Captured from the PulseAudio or PipeWire monitor of the default output
+/// through `parec` on Linux, and by WASAPI loopback on Windows. The audio
+/// is mixed into our outgoing stream after voice processing, which would
+/// otherwise take it for noise.
+pub struct SystemAudio {
+    buffer: Arc<Mutex<LoopbackBuffer>>,
+    backend: Backend,
+}
+
+impl SystemAudio {
+    /// Starts capturing system audio at `sample_rate`, to be mixed in at
+    /// `level` from 0 to 1
+    pub fn start(sample_rate: u32, level: f32) -> Result<Self> {
+        let buffer = Arc::new(Mutex::new(LoopbackBuffer::new(sample_rate, level)));
+        let backend = Backend::start(sample_rate, buffer.clone())?;
+        Ok(Self { buffer, backend })
+    }
+
+    pub fn set_level(&self, level: f32) {
+        self.buffer.lock().unwrap().level = level;
+    }
+
+    /// Adds the system audio captured since the last call to interleaved
+    /// `samples`, returning whether there was any
+    pub fn mix_into(&self, samples: &mut [f32], channels: u16) -> bool {
+        self.buffer.lock().unwrap().mix_into(samples, channels)
+    }
+}
+
+impl Drop for SystemAudio {
+    fn drop(&mut self) {
+        self.backend.stop();
+    }
+}
+
+/// `parec` recording the default output's monitor, read on a thread of its own
+#[cfg(target_os = "linux")]
+struct Backend {
+    child: std::process::Child,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+#[cfg(target_os = "linux")]
+impl Backend {
+    fn start(sample_rate: u32, buffer: Arc<Mutex<LoopbackBuffer>>) -> Result<Self> {
+        use std::io::Read;
+        use std::process::{Command, Stdio};
+
+        let mut child = Command::new("parec")
+            .args([
+                "--device=@DEFAULT_MONITOR@",
+                "--format=float32le",
+                "--channels=1",
+                "--latency-msec=20",
+                &format!("--rate={}", sample_rate),
+                "--raw",
+            ])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| anyhow!("Couldn't run parec to capture system audio: {}", e))?;
+        let mut stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow!("parec has no output"))?;
+
+        // Ends once parec is killed and its output closes
+        let reader = topology::spawn_thread(Plane::Media, "system-audio", move || {
+            let mut bytes = [0u8; 4096];
+            let mut filled = 0;
+            while let Ok(read) = stdout.read(&mut bytes[filled..]) {
+                if read == 0 {
+                    break;
+                }
+                filled += read;
+                let whole = filled - filled % 4;
+                buffer.lock().unwrap().push(
+                    bytes[..whole]
+                        .chunks_exact(4)
+                        .map(|sample| f32::from_le_bytes(sample.try_into().unwrap())),
+                );
+                bytes.copy_within(whole..filled, 0);
+                filled -= whole;
+            }
+        });
+        let thread = match reader {
+            Ok(thread) => thread,
+            Err(e) => {
+                let _ = child.kill();
+                return Err(e.into());
+            }
+        };
+        Ok(Self {
+            child,
+            thread: Some(thread),
+        })
+    }
+
+    fn stop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// WASAPI loopback of the default output device, held on a thread of its own
+/// because cpal streams aren't `Send`
+#[cfg(target_os = "windows")]
+struct Backend {
+    stop: Arc<std::sync::atomic::AtomicBool>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+#[cfg(target_os = "windows")]
+impl Backend {
+    fn start(sample_rate: u32, buffer: Arc<Mutex<LoopbackBuffer>>) -> Result<Self> {
+        use super::audio_config::resample;
+        use super::conversions::downmix_to_mono;
+        use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::time::Duration;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+        let thread_stop = stop.clone();
+        let thread = topology::spawn_thread(Plane::Media, "system-audio", move || {
+            // An input stream on an output device captures what it plays
+            let open = || -> Result<cpal::Stream> {
+                let device = cpal::default_host()
+                    .default_output_device()
+                    .ok_or_else(|| anyhow!("No output device to capture"))?;
+                let config = device.default_output_config()?;
+                if config.sample_format() != cpal::SampleFormat::F32 {
+                    return Err(anyhow!("Unsupported loopback format"));
+                }
+                let (rate, channels) = (config.sample_rate().0, config.channels());
+                let stream = device.build_input_stream(
+                    &config.into(),
+                    move |data: &[f32], _: &cpal::InputCallbackInfo| {
+                        let mono = resample(&downmix_to_mono(data, channels), rate, sample_rate);
+                        buffer.lock().unwrap().push(mono);
+                    },
+                    |e| log::warn!("System audio capture error: {}", e),
+                    None,
+                )?;
+                stream.play()?;
+                Ok(stream)
+            };
+            let stream = match open() {
+                Ok(stream) => {
+                    let _ = ready_tx.send(Ok(()));
+                    stream
+                }
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+            while !thread_stop.load(Ordering::SeqCst) {
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            drop(stream);
+        })?;
+
+        match ready_rx.recv() {
+            Ok(Ok(())) => Ok(Self {
+                stop,
+                thread: Some(thread),
+            }),
+            Ok(Err(e)) => {
+                let _ = thread.join();
+                Err(e)
+            }
+            Err(_) => Err(anyhow!("System audio thread exited unexpectedly")),
+        }
+    }
+
+    fn stop(&mut self) {
+        self.stop.store(true, std::sync::atomic::Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+struct Backend;
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+impl Backend {
+    fn start(_sample_rate: u32, _buffer: Arc<Mutex<LoopbackBuffer>>) -> Result<Self> {
+        Err(anyhow!(
+            "Sharing system audio isn't supported on this platform"
+        ))
+    }
+
+    fn stop(&mut self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loopback_mixes_at_level_and_drops_backlog() {
+        let mut buffer = LoopbackBuffer::new(100, 0.5);
+        assert!(!buffer.mix_into(&mut [0.0; 4], 2));
+
+        // Only the newest 200 ms, 20 samples at 100 Hz, are kept
+        buffer.push((0..30).map(|i| i as f32 / 64.0));
+        assert_eq!(buffer.samples.len(), 20);
+
+        let mut stereo = [0.25, 0.25, 0.96875, 0.96875];
+        assert!(buffer.mix_into(&mut stereo, 2));
+        assert_eq!(stereo, [0.328125, 0.328125, 1.0, 1.0]);
+        assert_eq!(buffer.samples.len(), 18);
+    }
+}
@@ -0,0 +1,192 @@
+use anyhow::{anyhow, Result};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// The operating system's mute switch for the default microphone
+///
+/// Hardware mic-mute keys and desktop volume controls flip this switch, so
+/// following it keeps the app's capture mute in step with them.
+pub trait SystemMute: Send {
+    /// Whether the default microphone is muted
+    fn is_muted(&mut self) -> Result<bool>;
+
+    /// Mutes or unmutes the default microphone
+    fn set_muted(&mut self, muted: bool) -> Result<()>;
+}
+
+/// Default source mute through `pactl`, which PipeWire also answers
+pub struct PulseAudioMute;
+
+impl PulseAudioMute {
+    /// Returns a backend if `pactl` can read the default source
+    pub fn detect() -> Option<Self> {
+        let mut backend = PulseAudioMute;
+        backend.is_muted().ok().map(|_| backend)
+    }
+}
+
+impl SystemMute for PulseAudioMute {
+    fn is_muted(&mut self) -> Result<bool> {
+        let output = Command::new("pactl")
+            .args(["get-source-mute", "@DEFAULT_SOURCE@"])
+            .output()?;
+        if !output.status.success() {
+            return Err(anyhow!("pactl exited with {}", output.status));
+        }
+
+        parse_pactl_mute(&String::from_utf8_lossy(&output.stdout))
+            .ok_or_else(|| anyhow!("Unexpected pactl output"))
+    }
+
+    fn set_muted(&mut self, muted: bool) -> Result<()> {
+        let status = Command::new("pactl")
+            .args([
+                "set-source-mute",
+                "@DEFAULT_SOURCE@",
+                if muted { "1" } else { "0" },
+            ])
+            .status()?;
+        if !status.success() {
+            return Err(anyhow!("pactl exited with {}", status));
+        }
+        Ok(())
+    }
+}
+
+/// Parses `Mute: yes` / `Mute: no` from `pactl get-source-mute`
+fn parse_pactl_mute(output: &str) -> Option<bool> {
+    match output.trim().strip_prefix("Mute:")?.trim() {
+        "yes" => Some(true),
+        "no" => Some(false),
+        _ => None,
+    }
+}
+
+/// Finds a way to follow the system mic mute on this platform, if there is one
+pub fn detect_system_mute() -> Option<Box<dyn SystemMute>> {
+    if cfg!(target_os = "linux") {
+        PulseAudioMute::detect().map(|backend| Box::new(backend) as Box<dyn SystemMute>)
+    } else {
+        None
+    }
+}
+
+/// Polls the system mute switch and reports when it changes
+pub struct SystemMuteWatcher {
+    backend: Arc<Mutex<Box<dyn SystemMute>>>,
+    // Last state seen or set, so our own changes aren't reported back
+    known: Arc<Mutex<Option<bool>>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SystemMuteWatcher {
+    /// Starts polling; the current state is reported first
+    pub fn spawn(
+        backend: Box<dyn SystemMute>,
+        interval: Duration,
+    ) -> (Self, mpsc::UnboundedReceiver<bool>) {
+        let backend = Arc::new(Mutex::new(backend));
+        let known = Arc::new(Mutex::new(None));
+        let stop = Arc::new(AtomicBool::new(false));
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        let thread_backend = backend.clone();
+        let thread_known = known.clone();
+        let thread_stop = stop.clone();
+        let thread = std::thread::spawn(move || {
+            while !thread_stop.load(Ordering::SeqCst) {
+                // Hold the known state while reading so a concurrent
+                // `set_muted` can't be reported back as stale
+                let mut known = thread_known.lock().unwrap();
+                let state = thread_backend.lock().unwrap().is_muted();
+                match state {
+                    Ok(muted) => {
+                        if *known != Some(muted) {
+                            *known = Some(muted);
+                            if tx.send(muted).is_err() {
+                                break;
+                            }
+                        }
+                    }
+                    Err(e) => log::debug!("Failed to read system mute: {}", e),
+                }
+                drop(known);
+                std::thread::sleep(interval);
+            }
+        });
+
+        let watcher = Self {
+            backend,
+            known,
+            stop,
+            thread: Some(thread),
+        };
+        (watcher, rx)
+    }
+
+    /// Sets the system mute to match a change made in the app
+    pub fn set_muted(&self, muted: bool) -> Result<()> {
+        let mut known = self.known.lock().unwrap();
+        self.backend.lock().unwrap().set_muted(muted)?;
+        *known = Some(muted);
+        Ok(())
+    }
+}
+
+impl Drop for SystemMuteWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A mute switch the test can flip from outside
+    struct FakeMute(Arc<AtomicBool>);
+
+    impl SystemMute for FakeMute {
+        fn is_muted(&mut self) -> Result<bool> {
+            Ok(self.0.load(Ordering::SeqCst))
+        }
+
+        fn set_muted(&mut self, muted: bool) -> Result<()> {
+            self.0.store(muted, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_parse_pactl_mute() {
+        assert_eq!(parse_pactl_mute("Mute: yes\n"), Some(true));
+        assert_eq!(parse_pactl_mute("Mute: no"), Some(false));
+        assert_eq!(parse_pactl_mute("Failure: No such entity"), None);
+    }
+
+    #[tokio::test]
+    async fn test_watcher_reports_external_changes_only() {
+        let switch = Arc::new(AtomicBool::new(false));
+        let (watcher, mut changes) =
+            SystemMuteWatcher::spawn(Box::new(FakeMute(switch.clone())), Duration::from_millis(5));
+        assert_eq!(changes.recv().await, Some(false));
+
+        // Someone presses the mic-mute key
+        switch.store(true, Ordering::SeqCst);
+        assert_eq!(changes.recv().await, Some(true));
+
+        // Our own change reaches the system but isn't echoed back
+        watcher.set_muted(false).unwrap();
+        assert!(!switch.load(Ordering::SeqCst));
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert!(changes.try_recv().is_err());
+    }
+}
@@ -0,0 +1,311 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::mpsc as std_mpsc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+
+use super::VoiceProcessor;
+
+/// A run of speech from one peer, bounded by silence
+#[derive(Debug, Clone)]
+pub struct SpeechSegment {
+    pub peer_id: String,
+    /// Unix time in milliseconds when the segment started
+    pub started_at: u64,
+    pub sample_rate: u32,
+    pub samples: Vec<f32>,
+}
+
+/// Transcribed text for one segment
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranscriptEntry {
+    pub peer_id: String,
+    /// Unix time in milliseconds when the speech started
+    pub started_at: u64,
+    pub text: String,
+}
+
+impl TranscriptEntry {
+    /// Appends the entry to a transcript file as `<started_at> <peer_id>: <text>`
+    pub fn append_to(&self, path: &Path) -> Result<()> {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{} {}: {}", self.started_at, self.peer_id, self.text)?;
+        Ok(())
+    }
+}
+
+/// A speech-to-text engine
+///
+/// Implementations run on a dedicated thread, so they may block.
+pub trait Transcriber: Send {
+    /// Returns the text spoken in `segment`, or `None` if nothing was recognised
+    fn transcribe(&mut self, segment: &SpeechSegment) -> Result<Option<String>>;
+}
+
+/// Runs an external program per segment
+///
+/// The program receives the sample rate as its last argument and the samples
+/// as little-endian f32 on stdin, and writes the recognised text to stdout.
+pub struct ExternalProcessTranscriber {
+    program: String,
+    args: Vec<String>,
+}
+
+impl ExternalProcessTranscriber {
+    pub fn new(program: &str, args: &[&str]) -> Self {
+        Self {
+            program: program.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        }
+    }
+}
+
+impl Transcriber for ExternalProcessTranscriber {
+    fn transcribe(&mut self, segment: &SpeechSegment) -> Result<Option<String>> {
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .arg(segment.sample_rate.to_string())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let bytes: Vec<u8> = segment
+            .samples
+            .iter()
+            .flat_map(|sample| sample.to_le_bytes())
+            .collect();
+        child
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow!("Transcriber stdin unavailable"))?
+            .write_all(&bytes)?;
+
+        let mut text = String::new();
+        if let Some(mut stdout) = child.stdout.take() {
+            stdout.read_to_string(&mut text)?;
+        }
+
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(anyhow!("Transcriber exited with {}", status));
+        }
+
+        let text = text.trim();
+        Ok(if text.is_empty() {
+            None
+        } else {
+            Some(text.to_string())
+        })
+    }
+}
+
+/// Splits one peer's audio into speech segments using voice activity
+struct SpeechSegmenter {
+    current: Option<SpeechSegment>,
+    silent_samples: usize,
+}
+
+impl SpeechSegmenter {
+    fn new() -> Self {
+        Self {
+            current: None,
+            silent_samples: 0,
+        }
+    }
+
+    /// Adds a block of audio, returning a segment once it is closed by silence or length
+    fn push(
+        &mut self,
+        peer_id: &str,
+        samples: &[f32],
+        sample_rate: u32,
+        has_voice: bool,
+        settings: &SegmentSettings,
+    ) -> Option<SpeechSegment> {
+        if has_voice {
+            self.silent_samples = 0;
+            let segment = self.current.get_or_insert_with(|| SpeechSegment {
+                peer_id: peer_id.to_string(),
+                started_at: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis() as u64,
+                sample_rate,
+                samples: Vec::new(),
+            });
+            segment.samples.extend_from_slice(samples);
+        } else if let Some(segment) = self.current.as_mut() {
+            // Keep trailing silence so words aren't clipped
+            segment.samples.extend_from_slice(samples);
+            self.silent_samples += samples.len();
+        }
+
+        let segment = self.current.as_ref()?;
+        let max_samples = duration_samples(settings.max_length, segment.sample_rate);
+        let hangover = duration_samples(settings.hangover, segment.sample_rate);
+
+        if self.silent_samples >= hangover || segment.samples.len() >= max_samples {
+            self.silent_samples = 0;
+            return self.current.take();
+        }
+
+        None
+    }
+}
+
+fn duration_samples(duration: Duration, sample_rate: u32) -> usize {
+    (duration.as_secs_f32() * sample_rate as f32) as usize
+}
+
+/// How audio is split into segments for transcription
+#[derive(Debug, Clone)]
+struct SegmentSettings {
+    hangover: Duration,
+    max_length: Duration,
+}
+
+/// Feeds per-peer speech segments to a `Transcriber` and delivers the text
+pub struct TranscriptionHook {
+    vad: VoiceProcessor,
+    settings: SegmentSettings,
+    segmenters: HashMap<String, SpeechSegmenter>,
+    segment_tx: std_mpsc::Sender<SpeechSegment>,
+}
+
+impl TranscriptionHook {
+    /// Starts a worker thread for `transcriber`, returning the hook and the transcript stream
+    pub fn new(
+        mut transcriber: Box<dyn Transcriber>,
+    ) -> (Self, mpsc::UnboundedReceiver<TranscriptEntry>) {
+        let (segment_tx, segment_rx) = std_mpsc::channel::<SpeechSegment>();
+        let (entry_tx, entry_rx) = mpsc::unbounded_channel();
+
+        // Engines can be slow, so keep them off the audio path
+        std::thread::spawn(move || {
+            for segment in segment_rx {
+                match transcriber.transcribe(&segment) {
+                    Ok(Some(text)) => {
+                        let entry = TranscriptEntry {
+                            peer_id: segment.peer_id,
+                            started_at: segment.started_at,
+                            text,
+                        };
+                        if entry_tx.send(entry).is_err() {
+                            break;
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => log::warn!("Transcription failed: {}", e),
+                }
+            }
+        });
+
+        let hook = Self {
+            vad: VoiceProcessor::new(),
+            settings: SegmentSettings {
+                hangover: Duration::from_millis(600),
+                max_length: Duration::from_secs(15),
+            },
+            segmenters: HashMap::new(),
+            segment_tx,
+        };
+
+        (hook, entry_rx)
+    }
+
+    /// Sets how much silence ends a segment
+    pub fn with_hangover(mut self, hangover: Duration) -> Self {
+        self.settings.hangover = hangover;
+        self
+    }
+
+    /// Sets the longest segment sent to the engine
+    pub fn with_max_segment_length(mut self, max_length: Duration) -> Self {
+        self.settings.max_length = max_length;
+        self
+    }
+
+    /// Sets the voice activity detector used for segmenting
+    pub fn with_voice_processor(mut self, vad: VoiceProcessor) -> Self {
+        self.vad = vad;
+        self
+    }
+
+    /// Adds a block of a peer's audio
+    pub fn push_audio(&mut self, peer_id: &str, samples: &[f32], sample_rate: u32) {
+        if samples.is_empty() {
+            return;
+        }
+
+        let has_voice = self.vad.detect_voice_activity(samples);
+        let segmenter = self
+            .segmenters
+            .entry(peer_id.to_string())
+            .or_insert_with(SpeechSegmenter::new);
+
+        if let Some(segment) =
+            segmenter.push(peer_id, samples, sample_rate, has_voice, &self.settings)
+        {
+            let _ = self.segment_tx.send(segment);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct LengthTranscriber;
+
+    impl Transcriber for LengthTranscriber {
+        fn transcribe(&mut self, segment: &SpeechSegment) -> Result<Option<String>> {
+            Ok(Some(format!("{} samples", segment.samples.len())))
+        }
+    }
+
+    #[test]
+    fn test_segmenter_closes_on_silence() {
+        let settings = SegmentSettings {
+            hangover: Duration::from_millis(20),
+            max_length: Duration::from_secs(10),
+        };
+        let mut segmenter = SpeechSegmenter::new();
+        let block = vec![0.0; 10]; // 10ms at 1kHz
+
+        // Silence before speech is ignored
+        assert!(segmenter
+            .push("a", &block, 1000, false, &settings)
+            .is_none());
+        assert!(segmenter.push("a", &block, 1000, true, &settings).is_none());
+        assert!(segmenter
+            .push("a", &block, 1000, false, &settings)
+            .is_none());
+
+        let segment = segmenter.push("a", &block, 1000, false, &settings).unwrap();
+        assert_eq!(segment.samples.len(), 30);
+        assert!(segmenter.current.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_hook_delivers_transcripts() {
+        let (hook, mut transcripts) = TranscriptionHook::new(Box::new(LengthTranscriber));
+        let mut hook = hook.with_hangover(Duration::from_millis(10));
+
+        let speech = vec![0.5; 100];
+        let silence = vec![0.0; 100];
+        hook.push_audio("peer", &speech, 1000);
+        hook.push_audio("peer", &silence, 1000);
+
+        let entry = tokio::time::timeout(Duration::from_secs(1), transcripts.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.peer_id, "peer");
+        assert_eq!(entry.text, "200 samples");
+    }
+}
@@ -0,0 +1,174 @@
+use std::f32::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+
+/// Length of the fade at each end of a tone, so notes start and stop without clicks
+const FADE_MS: u32 = 5;
+
+/// A short sound played locally when something happens in the session
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UiSound {
+    /// Someone is asking to join a room we host
+    JoinRequest,
+    /// A participant joined
+    PeerJoined,
+    /// A participant left
+    PeerLeft,
+    /// We muted the microphone
+    Muted,
+    /// We unmuted the microphone
+    Unmuted,
+}
+
+impl UiSound {
+    /// Notes of the sound as (frequency in Hz, length in milliseconds)
+    fn notes(&self) -> &'static [(f32, u32)] {
+        match self {
+            UiSound::JoinRequest => &[(880.0, 90), (0.0, 60), (880.0, 90)],
+            UiSound::PeerJoined => &[(660.0, 80), (880.0, 120)],
+            UiSound::PeerLeft => &[(880.0, 80), (660.0, 120)],
+            UiSound::Muted => &[(440.0, 70)],
+            UiSound::Unmuted => &[(587.0, 70)],
+        }
+    }
+
+    /// Renders the sound as interleaved stereo at `volume` (0.0 - 1.0)
+    pub fn render(&self, sample_rate: u32, volume: f32) -> Vec<f32> {
+        let mut stereo = Vec::new();
+        let fade = (sample_rate * FADE_MS / 1000).max(1) as f32;
+
+        for &(frequency, millis) in self.notes() {
+            let len = sample_rate * millis / 1000;
+            for i in 0..len {
+                let edge = i.min(len - 1 - i) as f32;
+                let envelope = (edge / fade).min(1.0);
+                let t = i as f32 / sample_rate as f32;
+                let sample = (2.0 * PI * frequency * t).sin() * envelope * volume;
+                stereo.push(sample);
+                stereo.push(sample);
+            }
+        }
+
+        stereo
+    }
+}
+
+/// Which UI sounds are enabled, and how loud they are
+///
+/// The enabled groups are written as a comma separated list, for example
+/// `join_requests,peers,mute`, or `none`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UiSoundSettings {
+    /// Someone asks to join a room we host
+    pub join_requests: bool,
+    /// Participants join or leave
+    pub peers: bool,
+    /// The microphone is muted or unmuted from the app
+    pub mute: bool,
+    /// Volume in percent
+    pub volume: u8,
+}
+
+impl Default for UiSoundSettings {
+    fn default() -> Self {
+        Self {
+            join_requests: false,
+            peers: false,
+            mute: false,
+            volume: 50,
+        }
+    }
+}
+
+impl UiSoundSettings {
+    /// Whether `sound` should be played
+    pub fn allows(&self, sound: UiSound) -> bool {
+        match sound {
+            UiSound::JoinRequest => self.join_requests,
+            UiSound::PeerJoined | UiSound::PeerLeft => self.peers,
+            UiSound::Muted | UiSound::Unmuted => self.mute,
+        }
+    }
+
+    /// Volume as a gain (0.0 - 1.0)
+    pub fn gain(&self) -> f32 {
+        self.volume.min(100) as f32 / 100.0
+    }
+}
+
+impl fmt::Display for UiSoundSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let groups: Vec<&str> = [
+            (self.join_requests, "join_requests"),
+            (self.peers, "peers"),
+            (self.mute, "mute"),
+        ]
+        .iter()
+        .filter(|(enabled, _)| *enabled)
+        .map(|(_, name)| *name)
+        .collect();
+
+        if groups.is_empty() {
+            write!(f, "none")
+        } else {
+            write!(f, "{}", groups.join(","))
+        }
+    }
+}
+
+impl FromStr for UiSoundSettings {
+    type Err = String;
+
+    /// Parses the enabled groups; the volume keeps its default
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut settings = UiSoundSettings::default();
+        if s.trim() == "none" {
+            return Ok(settings);
+        }
+
+        for group in s
+            .split(',')
+            .map(str::trim)
+            .filter(|group| !group.is_empty())
+        {
+            match group {
+                "join_requests" => settings.join_requests = true,
+                "peers" => settings.peers = true,
+                "mute" => settings.mute = true,
+                _ => return Err(format!("Unknown UI sound: {}", group)),
+            }
+        }
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_round_trip() {
+        let settings: UiSoundSettings = "peers, mute".parse().unwrap();
+        assert!(settings.allows(UiSound::PeerLeft));
+        assert!(settings.allows(UiSound::Unmuted));
+        assert!(!settings.allows(UiSound::JoinRequest));
+        assert_eq!(settings.to_string(), "peers,mute");
+
+        assert_eq!("none".parse(), Ok(UiSoundSettings::default()));
+        assert_eq!(UiSoundSettings::default().to_string(), "none");
+        assert!("fanfare".parse::<UiSoundSettings>().is_err());
+    }
+
+    #[test]
+    fn test_render_is_short_quiet_and_click_free() {
+        let stereo = UiSound::PeerJoined.render(48000, 0.5);
+
+        // Two notes of 80 and 120 ms, two channels
+        assert_eq!(stereo.len(), 48 * 200 * 2);
+        assert!(stereo.iter().all(|sample| sample.abs() <= 0.5));
+
+        // Fades in from and out to silence
+        assert_eq!(stereo[0], 0.0);
+        assert!(stereo.last().unwrap().abs() < 1e-3);
+    }
+}
@@ -0,0 +1,275 @@
+use anyhow::{anyhow, Result};
+use cpal::SampleFormat;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use super::host::{
+    AudioHost, DeviceFormat, FormatRange, HostStream, InputBuffer, InputCallback, OutputBuffer,
+    OutputCallback,
+};
+
+/// A device on the virtual host
+#[derive(Debug, Clone)]
+pub struct VirtualDevice {
+    pub name: String,
+    pub is_input: bool,
+    pub format: DeviceFormat,
+    /// Formats an input also supports, besides its default
+    pub ranges: Vec<FormatRange>,
+}
+
+impl VirtualDevice {
+    pub fn input(name: &str, sample_rate: u32, channels: u16, sample_format: SampleFormat) -> Self {
+        Self {
+            name: name.to_string(),
+            is_input: true,
+            format: DeviceFormat {
+                sample_rate,
+                channels,
+                sample_format,
+            },
+            ranges: Vec::new(),
+        }
+    }
+
+    pub fn output(name: &str, channels: u16, sample_format: SampleFormat) -> Self {
+        Self {
+            is_input: false,
+            ..Self::input(name, 48000, channels, sample_format)
+        }
+    }
+
+    /// Also supports its default format at any rate from `min_rate` to `max_rate`
+    pub fn with_rates(mut self, min_rate: u32, max_rate: u32) -> Self {
+        self.ranges.push(FormatRange {
+            channels: self.format.channels,
+            sample_format: self.format.sample_format,
+            min_rate,
+            max_rate,
+        });
+        self
+    }
+}
+
+enum Callback {
+    Input(InputCallback),
+    Output(OutputCallback),
+}
+
+/// A stream the virtual host opened, for as long as its handle lives
+struct Opened {
+    device: String,
+    format: DeviceFormat,
+    playing: Arc<AtomicBool>,
+    open: Arc<AtomicBool>,
+    callback: Arc<Mutex<Callback>>,
+}
+
+/// Handle to a virtual stream, closing it when dropped
+struct VirtualStream {
+    playing: Arc<AtomicBool>,
+    open: Arc<AtomicBool>,
+}
+
+impl HostStream for VirtualStream {
+    fn play(&self) -> Result<()> {
+        self.playing.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn pause(&self) -> Result<()> {
+        self.playing.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+impl Drop for VirtualStream {
+    fn drop(&mut self) {
+        self.open.store(false, Ordering::SeqCst);
+    }
+}
+
+#[derive(Default)]
+struct State {
+    devices: Vec<VirtualDevice>,
+    streams: Vec<Opened>,
+}
+
+/// Audio devices that exist only in memory, whose callbacks tests run by hand
+///
+/// Devices can be plugged in and pulled out while streams are open, and
+/// pulling one out closes its streams as a real host would.
+#[derive(Clone, Default)]
+pub struct VirtualHost {
+    state: Arc<Mutex<State>>,
+}
+
+impl VirtualHost {
+    pub fn with_device(self, device: VirtualDevice) -> Self {
+        self.plug_in(device);
+        self
+    }
+
+    pub fn plug_in(&self, device: VirtualDevice) {
+        self.state.lock().unwrap().devices.push(device);
+    }
+
+    pub fn pull_out(&self, name: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.devices.retain(|device| device.name != name);
+        for stream in state.streams.iter().filter(|stream| stream.device == name) {
+            stream.open.store(false, Ordering::SeqCst);
+        }
+    }
+
+    /// Format of the stream open on `device`, if there is one
+    pub fn stream_format(&self, device: &str) -> Option<DeviceFormat> {
+        self.live_streams(device, false)
+            .first()
+            .map(|(format, _)| *format)
+    }
+
+    /// Runs the callbacks of streams playing on `device`, returning how many ran
+    pub fn feed(&self, device: &str, samples: InputBuffer) -> usize {
+        let streams = self.live_streams(device, true);
+        for (_, callback) in &streams {
+            if let Callback::Input(callback) = &mut *callback.lock().unwrap() {
+                let samples = match &samples {
+                    InputBuffer::F32(data) => InputBuffer::F32(data),
+                    InputBuffer::I16(data) => InputBuffer::I16(data),
+                    InputBuffer::U16(data) => InputBuffer::U16(data),
+                };
+                callback(samples);
+            }
+        }
+        streams.len()
+    }
+
+    /// Asks the stream playing on `device` to fill `buffer`, returning whether one did
+    pub fn render(&self, device: &str, buffer: OutputBuffer) -> bool {
+        let Some((_, callback)) = self.live_streams(device, true).into_iter().next() else {
+            return false;
+        };
+        if let Callback::Output(callback) = &mut *callback.lock().unwrap() {
+            callback(buffer);
+        }
+        true
+    }
+
+    fn live_streams(
+        &self,
+        device: &str,
+        playing_only: bool,
+    ) -> Vec<(DeviceFormat, Arc<Mutex<Callback>>)> {
+        let mut state = self.state.lock().unwrap();
+        state
+            .streams
+            .retain(|stream| stream.open.load(Ordering::SeqCst));
+        state
+            .streams
+            .iter()
+            .filter(|stream| stream.device == device)
+            .filter(|stream| !playing_only || stream.playing.load(Ordering::SeqCst))
+            .map(|stream| (stream.format, stream.callback.clone()))
+            .collect()
+    }
+
+    fn find(&self, name: &str, is_input: bool) -> Result<VirtualDevice> {
+        self.state
+            .lock()
+            .unwrap()
+            .devices
+            .iter()
+            .find(|device| device.name == name && device.is_input == is_input)
+            .cloned()
+            .ok_or_else(|| anyhow!("Audio device not found: {}", name))
+    }
+
+    fn names(&self, is_input: bool) -> Vec<String> {
+        self.state
+            .lock()
+            .unwrap()
+            .devices
+            .iter()
+            .filter(|device| device.is_input == is_input)
+            .map(|device| device.name.clone())
+            .collect()
+    }
+
+    fn open(
+        &self,
+        device: &str,
+        is_input: bool,
+        format: DeviceFormat,
+        callback: Callback,
+    ) -> Result<Box<dyn HostStream>> {
+        let found = self.find(device, is_input)?;
+        let supported = format == found.format
+            || found
+                .ranges
+                .iter()
+                .any(|range| range.at(found.format, format.sample_rate) == Some(format));
+        // Outputs play at any rate, as most do
+        if is_input && !supported {
+            return Err(anyhow!("{} doesn't support {:?}", device, format));
+        }
+
+        let stream = VirtualStream {
+            playing: Arc::new(AtomicBool::new(true)),
+            open: Arc::new(AtomicBool::new(true)),
+        };
+        self.state.lock().unwrap().streams.push(Opened {
+            device: device.to_string(),
+            format,
+            playing: stream.playing.clone(),
+            open: stream.open.clone(),
+            callback: Arc::new(Mutex::new(callback)),
+        });
+        Ok(Box::new(stream))
+    }
+}
+
+impl AudioHost for VirtualHost {
+    fn input_devices(&self) -> Result<Vec<String>> {
+        Ok(self.names(true))
+    }
+
+    fn output_devices(&self) -> Result<Vec<String>> {
+        Ok(self.names(false))
+    }
+
+    // The first device of each kind is the default
+    fn default_input_device(&self) -> Option<String> {
+        self.names(true).into_iter().next()
+    }
+
+    fn default_output_device(&self) -> Option<String> {
+        self.names(false).into_iter().next()
+    }
+
+    fn default_format(&self, device: &str, is_input: bool) -> Result<DeviceFormat> {
+        Ok(self.find(device, is_input)?.format)
+    }
+
+    fn input_formats(&self, device: &str) -> Result<Vec<FormatRange>> {
+        Ok(self.find(device, true)?.ranges)
+    }
+
+    fn build_input_stream(
+        &self,
+        device: &str,
+        format: DeviceFormat,
+        callback: InputCallback,
+    ) -> Result<Box<dyn HostStream>> {
+        self.open(device, true, format, Callback::Input(callback))
+    }
+
+    fn build_output_stream(
+        &self,
+        device: &str,
+        format: DeviceFormat,
+        callback: OutputCallback,
+    ) -> Result<Box<dyn HostStream>> {
+        self.open(device, false, format, Callback::Output(callback))
+    }
+}
@@ -1,8 +1,12 @@
-use super::capture::{
-    generate_test_audio_with_echo, generate_test_silence, generate_test_speech, measure_echo_level,
-};
+use super::Calibration;
 use std::sync::{Arc, Mutex};
 
+/// Range automatic gain control may move the level within
+const AGC_RANGE: (f32, f32) = (0.25, 4.0);
+
+/// Share of the way to the wanted gain AGC moves each block, so it doesn't pump
+const AGC_STEP: f32 = 0.05;
+
 // Voice processor for handling microphone audio
 #[derive(Clone)]
 pub struct VoiceProcessor {
@@ -11,6 +15,16 @@ pub struct VoiceProcessor {
     muted: bool,
     // We'll store the far end buffer for echo cancellation
     far_end_buffer: Arc<Mutex<Vec<f32>>>,
+    // Input gain, noise gate and AGC target for the microphone, if calibrated
+    calibration: Option<Calibration>,
+    // Gain AGC has settled on so far
+    agc_gain: Arc<Mutex<f32>>,
+}
+
+impl Default for VoiceProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl VoiceProcessor {
@@ -20,6 +34,8 @@ impl VoiceProcessor {
             echo_cancellation_enabled: true,
             muted: false,
             far_end_buffer: Arc::new(Mutex::new(Vec::new())),
+            calibration: None,
+            agc_gain: Arc::new(Mutex::new(1.0)),
         }
     }
 
@@ -38,6 +54,16 @@ impl VoiceProcessor {
         self
     }
 
+    /// Applies a microphone calibration, or none to leave the input as it is
+    pub fn set_calibration(&mut self, calibration: Option<Calibration>) {
+        self.calibration = calibration;
+        *self.agc_gain.lock().unwrap() = 1.0;
+    }
+
+    pub fn calibration(&self) -> Option<&Calibration> {
+        self.calibration.as_ref()
+    }
+
     pub fn is_muted(&self) -> bool {
         self.muted
     }
@@ -70,9 +96,39 @@ impl VoiceProcessor {
             }
         }
 
+        if let Some(calibration) = &self.calibration {
+            self.apply_calibration(&mut output, calibration);
+        }
+
         output
     }
 
+    // Input gain, then the noise gate, then AGC towards the target level
+    fn apply_calibration(&self, output: &mut [f32], calibration: &Calibration) {
+        if output.is_empty() {
+            return;
+        }
+
+        output
+            .iter_mut()
+            .for_each(|sample| *sample *= calibration.input_gain);
+
+        let level =
+            (output.iter().map(|sample| sample * sample).sum::<f32>() / output.len() as f32).sqrt();
+        if level < calibration.gate_threshold {
+            output.fill(0.0);
+            return;
+        }
+
+        // Only speech moves the AGC; silence would otherwise wind the gain up
+        let mut agc_gain = self.agc_gain.lock().unwrap();
+        if level > f32::EPSILON {
+            let wanted = (calibration.agc_target / level).clamp(AGC_RANGE.0, AGC_RANGE.1);
+            *agc_gain += (wanted - *agc_gain) * AGC_STEP;
+        }
+        output.iter_mut().for_each(|sample| *sample *= *agc_gain);
+    }
+
     // Set the far-end audio (what's coming from the speakers)
     pub fn set_far_end_audio(&mut self, audio: &[f32]) {
         let mut far_end = self.far_end_buffer.lock().unwrap();
@@ -99,9 +155,9 @@ impl VoiceProcessor {
         let alpha = 0.95; // Filter coefficient
         let mut prev = 0.0;
 
-        for i in 0..output.len() {
-            output[i] = alpha * (output[i] - prev) + prev;
-            prev = output[i];
+        for sample in output.iter_mut() {
+            *sample = alpha * (*sample - prev) + prev;
+            prev = *sample;
         }
 
         // Simple echo cancellation using scaled subtraction
@@ -125,6 +181,10 @@ impl VoiceProcessor {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::audio::capture::{
+        generate_test_audio_with_echo, generate_test_silence, generate_test_speech,
+        measure_echo_level,
+    };
 
     #[test]
     fn test_echo_cancellation() {
@@ -147,6 +207,27 @@ mod tests {
         assert!(processor.detect_voice_activity(&speech));
     }
 
+    #[test]
+    fn test_calibration_gates_noise_and_levels_speech() {
+        let mut processor = VoiceProcessor::new().with_echo_cancellation(false);
+        processor.set_calibration(Some(Calibration {
+            input_gain: 2.0,
+            gate_threshold: 0.02,
+            agc_target: 0.1,
+        }));
+
+        // Room noise stays below the gate
+        let noise = processor.process(vec![0.005; 480]);
+        assert!(noise.iter().all(|sample| *sample == 0.0));
+
+        // Quiet speech is raised towards the target over successive blocks
+        let mut level = 0.0;
+        for _ in 0..100 {
+            level = processor.process(vec![0.025; 480])[0];
+        }
+        assert!(level > 0.05 && level <= 0.1 + 1e-3);
+    }
+
     #[test]
     fn test_mute_functionality() {
         // Test initial state
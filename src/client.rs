@@ -0,0 +1,189 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Result};
+use tokio::sync::mpsc;
+
+use crate::app::config::Config;
+use crate::app::events::SessionEvent;
+use crate::app::App;
+use crate::audio::{AudioStreamManager, HoldAudio};
+
+/// One handle for embedding Resonance in another application
+///
+/// Sets up the session manager and audio from a [`Config`], the way the
+/// terminal app does, so an embedding app only has to create or join a room
+/// and react to events:
+///
+/// ```no_run
+/// # async fn embed() -> anyhow::Result<()> {
+/// use resonance::app::config::Config;
+/// use resonance::RoomClient;
+///
+/// let mut client = RoomClient::new(Config::default()).await?;
+/// let mut events = client.events().expect("events are taken once");
+/// let link = client.create_room().await?;
+/// println!("Invite others with {}", link);
+///
+/// client.start_audio().await?;
+/// client.audio_controls().set_muted(true);
+///
+/// while let Some(event) = events.recv().await {
+///     println!("{:?}", event);
+/// }
+/// client.shutdown().await
+/// # }
+/// ```
+pub struct RoomClient {
+    app: App,
+    audio: Arc<Mutex<AudioStreamManager>>,
+    events: Option<mpsc::UnboundedReceiver<SessionEvent>>,
+}
+
+impl RoomClient {
+    /// Creates a client with the session and audio settings in `config`
+    ///
+    /// No devices are opened until [`RoomClient::start_audio`].
+    pub async fn new(config: Config) -> Result<Self> {
+        let mut audio = AudioStreamManager::new();
+        audio.set_ui_sounds(config.ui_sounds.clone());
+        audio.set_motion_effects(config.motion_effects);
+        audio.set_load_limit(
+            config
+                .cpu_load_limit_percent
+                .map(|percent| percent as f64 / 100.0),
+        );
+
+        let mut app = App::with_config(config);
+        app.initialize().await.map_err(|e| anyhow!(e))?;
+        let events = app
+            .session_manager
+            .as_mut()
+            .and_then(|manager| manager.take_event_receiver());
+
+        Ok(Self {
+            app,
+            audio: Arc::new(Mutex::new(audio)),
+            events,
+        })
+    }
+
+    /// Hosts a new room, returning the link others join it with
+    pub async fn create_room(&mut self) -> Result<String> {
+        let session = self.app.create_p2p_session().await?;
+        Ok(session.connection_link)
+    }
+
+    /// Joins a room from a link someone shared
+    pub async fn join(&mut self, link: &str) -> Result<()> {
+        self.app.join_p2p_session(link).await?;
+        Ok(())
+    }
+
+    /// Leaves the current room
+    pub async fn leave(&mut self) -> Result<()> {
+        self.app.leave_session().await.map_err(|e| anyhow!(e))
+    }
+
+    /// Everything that happens in the room, in order
+    ///
+    /// There's a single stream of events, so this returns `None` after the
+    /// first call.
+    pub fn events(&mut self) -> Option<mpsc::UnboundedReceiver<SessionEvent>> {
+        self.events.take()
+    }
+
+    /// Opens the microphone and speakers
+    pub async fn start_audio(&mut self) -> Result<()> {
+        // The manager can't stay locked across an await, so it's taken out meanwhile
+        let mut audio = self.take_audio();
+        let result = async {
+            audio.initialize()?;
+            audio.create_stream("default-session".to_string()).await
+        }
+        .await;
+        *self.audio.lock().unwrap() = audio;
+        result.map(|_stream| ())
+    }
+
+    fn take_audio(&self) -> AudioStreamManager {
+        std::mem::take(&mut *self.audio.lock().unwrap())
+    }
+
+    /// Mute, hold and solo for the local audio
+    pub fn audio_controls(&self) -> AudioControls {
+        AudioControls {
+            audio: self.audio.clone(),
+        }
+    }
+
+    /// The application underneath, for anything the client doesn't cover
+    pub fn app(&mut self) -> &mut App {
+        &mut self.app
+    }
+
+    /// Leaves any room and releases audio devices
+    pub async fn shutdown(mut self) -> Result<()> {
+        let mut audio = self.take_audio();
+        audio.stop_all_streams().await?;
+        self.app.shutdown().await.map_err(|e| anyhow!(e))
+    }
+}
+
+/// Controls for local audio, cheap to clone and share with UI code
+#[derive(Clone)]
+pub struct AudioControls {
+    audio: Arc<Mutex<AudioStreamManager>>,
+}
+
+impl AudioControls {
+    /// Stops or resumes sending the microphone
+    pub fn set_muted(&self, muted: bool) {
+        self.audio.lock().unwrap().set_muted(muted);
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.audio.lock().unwrap().is_muted()
+    }
+
+    /// Flips mute, returning whether we're now muted
+    pub fn toggle_mute(&self) -> bool {
+        let audio = self.audio.lock().unwrap();
+        let muted = !audio.is_muted();
+        audio.set_muted(muted);
+        muted
+    }
+
+    /// Sends `hold` in place of the microphone, or the microphone again with `None`
+    pub fn set_hold_audio(&self, hold: Option<HoldAudio>) {
+        self.audio.lock().unwrap().set_hold_audio(hold);
+    }
+
+    /// Hears only `participant`, or everyone again with `None`
+    pub fn set_solo(&self, participant: Option<String>) {
+        self.audio.lock().unwrap().set_solo(participant);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_client_without_room() {
+        let mut client = RoomClient::new(Config::default()).await.unwrap();
+        assert!(client.events().is_some());
+        assert!(client.events().is_none());
+
+        // Controls share one audio manager
+        let controls = client.audio_controls();
+        assert!(!controls.is_muted());
+        assert!(client.audio_controls().toggle_mute());
+        assert!(controls.is_muted());
+        controls.set_muted(false);
+        assert!(!client.audio_controls().is_muted());
+
+        assert!(client.leave().await.is_err());
+        assert!(client.join("not a link").await.is_err());
+        client.shutdown().await.unwrap();
+    }
+}
@@ -3,6 +3,7 @@
 
 pub mod app;
 pub mod audio;
+pub mod client;
 pub mod network;
 pub mod ui;
 
@@ -10,6 +11,7 @@ pub mod ui;
 pub use app::session::{Peer, Session, SessionError, SessionManager};
 pub use app::App;
 pub use audio::{SpatialAudioProcessor, VoiceProcessor};
+pub use client::{AudioControls, RoomClient};
 pub use network::connection_manager::ConnectionManager;
 pub use network::p2p::{ConnectionState, Endpoint};
 pub use ui::Participant;
@@ -1,19 +1,38 @@
-mod app;
-mod audio;
-mod network;
-mod ui;
-
+use resonance::{app, audio, network, ui};
+
+use app::control::{ControlCommand, ControlReply, ControlStatus, Versioned};
+use app::events::{ConnectionIssue, EventCoalescer, SessionEvent};
+use app::handoff::HandoffKey;
+use app::instance::{Claim, TakeoverRequest};
+use app::keep_awake::KeepAwake;
+use app::low_bandwidth;
+use app::presence::PresenceTracker;
+use app::session::{SessionError, JOIN_CHECK_INTERVAL};
+use app::session_timer::format_clock;
+use app::shortener::{shorten_or_full, HttpShortener};
+use app::sleep::{SleepDetector, SLEEP_CHECK_INTERVAL};
+use app::timeline::SessionTimeline;
+use app::topology::{self, Plane};
+use app::webhook::{Webhook, WebhookEvent};
 use app::App;
-use audio::{AudioCapture, AudioStreamManager, SpatialAudioProcessor, VoiceProcessor};
+use audio::transcription::{ExternalProcessTranscriber, TranscriptionHook};
+use audio::{
+    choose_microphone, detect_system_mute, probe_devices, AudioCapture, AudioConfig, AudioFrame,
+    AudioStreamManager, Calibration, CalibrationError, CalibrationPhase, CalibrationRun,
+    Degradation, DeviceCapabilities, DeviceFingerprint, ExclusiveMode, HoldAudio, SpatialLayout,
+    SystemMuteWatcher, UiSound, WatchdogEvent, DEFAULT_DEVICE_ID, HANGUP_FADE, QUIET_PHASE,
+    SPEAK_PHASE,
+};
 use std::env;
 use std::io::{self, Write};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tokio::sync::mpsc;
-use ui::{qr_code::display_connection_options, run_tui, Participant};
+use ui::i18n::{t, t_args};
+use ui::widgets::SettingsForm;
+use ui::Participant;
 
 // Default sample rate for all audio processing
-const DEFAULT_SAMPLE_RATE: u32 = 48000;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -24,16 +43,241 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     if args.len() > 2 && args[1] == "join" {
         join_link = Some(args[2].clone());
     }
+    let skip_checks = args.iter().any(|arg| arg == "--skip-checks");
+
+    // Control tasks stay on this runtime, wherever they're spawned from
+    topology::set_control_runtime(tokio::runtime::Handle::current());
 
-    // Initialize application
+    // Initialize application, with the settings saved last time
     let mut app = App::new();
+    let config_path = app::config::default_path();
+    if let Some(directory) = config_path.parent() {
+        app.set_secret_store(app::secrets::platform_store(directory));
+    }
+    let mut config_error = None;
+    if config_path.exists() {
+        if let Err(e) = app.load_config(&config_path) {
+            eprintln!("Ignoring {}: {}", config_path.display(), e);
+            config_error = Some(e);
+        }
+    }
     app.initialize().await?;
 
-    // Initialize the audio stream manager with a specific sample rate
+    // Log to a rotating file; the terminal belongs to the TUI
+    let log_settings = app::logging::LogSettings::from_config(app.config());
+    let log_dir = log_settings.directory.clone();
+    let log_file = match app::logging::init(log_settings) {
+        Ok(path) => Some(path),
+        Err(e) => {
+            eprintln!("Failed to set up logging: {}", e);
+            None
+        }
+    };
+
+    // Tag our streams as call audio before any device is opened
+    audio::set_stream_role(app.config().stream_role, app.config().communications_device);
+    network::set_socket_buffers(network::SocketBuffers::from_kib(
+        app.config().udp_receive_buffer_kb,
+        app.config().udp_send_buffer_kb,
+    ));
+
+    // Generate a diagnostic bundle on request, then exit
+    if args.len() > 1 && args[1] == "diagnostics" {
+        let directory = args
+            .get(2)
+            .map(std::path::PathBuf::from)
+            .unwrap_or_else(|| log_dir.clone());
+        let path =
+            app::diagnostics::generate_bundle(app.config(), log_file.as_deref(), &directory)?;
+        println!("Diagnostic bundle written to {}", path.display());
+        return Ok(());
+    }
+
+    // Loop audio through the pipeline and report on it, then exit
+    if args.len() > 1 && args[1] == "audio-check" {
+        let duration = args
+            .get(2)
+            .and_then(|seconds| seconds.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(app::audio_check::DEFAULT_CHECK_DURATION);
+        println!(
+            "Checking audio for {} seconds; speak into the microphone...",
+            duration.as_secs()
+        );
+        let report = app::audio_check::run(app.config(), duration).await?;
+        println!("{}", report);
+        return Ok(());
+    }
+
+    // Run without a terminal UI, driven over stdin/stdout
+    if args.len() > 1 && args[1] == "headless" {
+        run_headless(app).await?;
+        return Ok(());
+    }
+
+    // Crash bundles are opt-in
+    if app.config().crash_reports {
+        app::diagnostics::install_panic_hook(app.config().clone(), log_file.clone(), log_dir);
+    }
+
+    // Show UI strings in the configured language
+    ui::i18n::set_locale(app.config().locale);
+
+    // One instance at a time uses the devices and config; a second can take
+    // over the first's call
+    let config_dir = config_path.parent().unwrap_or(std::path::Path::new("."));
+    let mut takeover = None;
+    let mut instance_lock = match app::instance::claim(config_dir).await {
+        Ok(Claim::Acquired(lock)) => Some(lock),
+        Ok(Claim::Running(running)) => {
+            print!(
+                "Resonance is already running (pid {}). Take over its call? [y/N] ",
+                running.pid
+            );
+            io::stdout().flush()?;
+            let mut answer = String::new();
+            io::stdin().read_line(&mut answer)?;
+            if !answer.trim().eq_ignore_ascii_case("y") {
+                eprintln!("Leaving the running instance be; close it to start another");
+                return Ok(());
+            }
+            takeover = running.take_over().await?;
+            Some(app::instance::claim_after_exit(config_dir).await?)
+        }
+        Err(e) => {
+            log::warn!("Couldn't check for another instance: {}", e);
+            None
+        }
+    };
+    let takeovers = instance_lock.as_mut().map(|lock| lock.listen());
+
+    // Follow the chosen devices if the system renamed them since last time
+    let devices = probe_devices();
+    let mut config = app.config().clone();
+    let missing_devices = config.match_devices(&devices);
+    if config != *app.config() {
+        app.update_config(config);
+        // A file we couldn't read is left for the user to fix, not replaced with defaults
+        if config_error.is_none() {
+            if let Err(e) = app.save_config(&config_path) {
+                log::warn!("Failed to save the renamed devices: {}", e);
+            }
+        }
+    }
+
+    // Check what calls depend on, and show the checklist if anything's amiss
+    let mut health_checks = None;
+    if !skip_checks {
+        let checks = app::health::run_checks(app.config(), config_error.as_deref(), &devices).await;
+        if app::health::needs_attention(&checks) {
+            health_checks = Some(checks);
+        }
+    }
+
+    // Stand the likeliest microphone in for a missing one, for this run only
+    let mut input_device = app.config().input_device.clone();
+    let mut replaced_microphone = None;
+    if let Some(id) = &input_device {
+        let present = devices
+            .iter()
+            .any(|capabilities| capabilities.device.is_input && capabilities.device.id == *id);
+        if !present && app.config().auto_microphone {
+            if let Some(stand_in) = choose_microphone(&devices) {
+                log::info!("{} is missing; using {} instead", id, stand_in.device.id);
+                replaced_microphone = Some((id.clone(), stand_in.device.id.clone()));
+                input_device = Some(stand_in.device.id.clone());
+            }
+        }
+    }
+
+    // Run audio at a rate both the chosen microphone and speakers support
+    let chosen = |is_input: bool, id: &Option<String>| {
+        devices.iter().find(|capabilities| {
+            capabilities.device.is_input == is_input
+                && id.as_ref().is_none_or(|id| *id == capabilities.device.id)
+        })
+    };
+    let audio_config = AudioConfig::resolve(
+        chosen(true, &input_device),
+        chosen(false, &app.config().output_device),
+    );
+
     let mut audio_manager = AudioStreamManager::new();
-    audio_manager.set_sample_rate(DEFAULT_SAMPLE_RATE)?;
+    audio_manager.set_audio_config(audio_config)?;
+    audio_manager.set_input_device(
+        input_device
+            .as_ref()
+            .and(chosen(true, &input_device))
+            .map(|capabilities| capabilities.device.clone()),
+    );
+    audio_manager.set_output_device(app.config().output_device.clone());
     audio_manager.initialize()?;
 
+    // Send the room mix or chosen participants to secondary output devices
+    if !app.config().output_routes.is_empty() {
+        if let Err(e) = audio_manager.set_output_routing(app.config().output_routes.clone()) {
+            eprintln!("Failed to set up output routing: {}", e);
+        }
+    }
+    audio_manager.set_ui_sounds(app.config().ui_sounds.clone());
+    audio_manager.set_motion_effects(app.config().motion_effects);
+    audio_manager.set_channel_map(app.config().output_channels);
+    audio_manager.set_simple_panning(app.config().low_bandwidth);
+    if let Err(e) = audio_manager.set_network_monitor(
+        app.config()
+            .network_monitor
+            .then(|| low_bandwidth::send_codec(app.config())),
+    ) {
+        eprintln!("Failed to start the network monitor: {}", e);
+    }
+    if let Err(e) = audio_manager.set_system_audio(app.config().system_audio_percent) {
+        eprintln!("Failed to share system audio: {}", e);
+    }
+    audio_manager.set_load_limit(
+        app.config()
+            .cpu_load_limit_percent
+            .map(|percent| percent as f64 / 100.0),
+    );
+    audio_manager.set_calibration(
+        app.config()
+            .calibrations
+            .get(calibration_device(app.config()))
+            .copied(),
+    );
+    audio_manager.set_capture_stall_timeout(Duration::from_millis(
+        app.config().capture_stall_timeout_ms as u64,
+    ));
+
+    // Open the microphone and speakers now if configured, so joining doesn't wait on them
+    if app.config().prewarm_audio {
+        if let Err(e) = audio_manager.prewarm().await {
+            eprintln!("Failed to pre-warm audio: {}", e);
+        }
+    }
+
+    // Transcribe remote participants through an external speech-to-text command
+    if let Some(command) = app.config().transcription_command.clone() {
+        let mut parts = command.split_whitespace();
+        if let Some(program) = parts.next() {
+            let args: Vec<&str> = parts.collect();
+            let transcriber = ExternalProcessTranscriber::new(program, &args);
+            let (hook, mut transcripts) = TranscriptionHook::new(Box::new(transcriber));
+            audio_manager.set_transcription_hook(Some(hook));
+
+            let transcript_file = app.config().transcript_file.clone();
+            topology::spawn(Plane::Control, "transcripts", async move {
+                while let Some(entry) = transcripts.recv().await {
+                    log::info!("[{}] {}: {}", entry.started_at, entry.peer_id, entry.text);
+                    if let Some(path) = &transcript_file {
+                        if let Err(e) = entry.append_to(std::path::Path::new(path)) {
+                            log::warn!("Failed to save transcript: {}", e);
+                        }
+                    }
+                }
+            });
+        }
+    }
+
     // Create participant for ourselves with initial position at the center (0,0,0)
     let current_user = Participant::new("Me").with_position(0.0, 0.0, 0.0);
     let participants = Arc::new(Mutex::new(vec![current_user.clone()]));
@@ -44,9 +288,6 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         audio_manager.update_positions(&participants_guard)?;
     }
 
-    // Create a reference to app.session_manager that will be moved to the background task
-    let session_manager_ref = app.session_manager.clone();
-
     // Create a separate reference to app that implements has_active_connection()
     let mut app_check_connection = App::new();
     app_check_connection.initialize().await?;
@@ -54,21 +295,32 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Important - use the same session_manager so we get accurate connection state
     app_check_connection.session_manager = app.session_manager.clone();
 
-    let app_check_connection = Arc::new(app_check_connection);
-
     // Create shared audio manager for TUI
-    let audio_manager = Arc::new(Mutex::new(audio_manager));
+    let audio_manager = Arc::new(tokio::sync::Mutex::new(audio_manager));
     let participants_clone = Arc::clone(&participants);
 
     // If we have a join link from command line, try to join immediately
     if let Some(link) = join_link {
         if let Err(e) = app.join_p2p_session(&link).await {
-            eprintln!("Failed to join session: {}", e);
+            eprintln!("{}", with_hint(t_args("error.join_failed", &[&e]), &e));
+        }
+    }
+
+    // Carry on the call the instance we took over from was in
+    if let Some((sealed, key)) = takeover {
+        if let Err(e) = app.import_handoff(&sealed, &key).await {
+            eprintln!("Failed to take over the call: {}", e);
         }
     }
 
+    // Session events are coalesced and applied by the TUI loop
+    let session_events = app
+        .session_manager
+        .as_mut()
+        .and_then(|manager| manager.take_event_receiver());
+
     // Create a sharable app instance for the TUI
-    let shared_app = Arc::new(Mutex::new(app));
+    let shared_app = Arc::new(tokio::sync::Mutex::new(app));
 
     // Run the TUI, passing both the app and the audio manager for audio visualization
     if let Err(e) = run_tui_with_audio(
@@ -76,6 +328,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         audio_manager.clone(),
         app_check_connection,
         participants_clone,
+        session_events,
+        StartupReport {
+            log_file,
+            missing_devices,
+            replaced_microphone,
+            health_checks,
+        },
+        takeovers,
     )
     .await
     {
@@ -84,7 +344,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Cleanup before exit
     let mut app_for_cleanup = match Arc::try_unwrap(shared_app) {
-        Ok(mutex) => mutex.into_inner()?,
+        Ok(mutex) => mutex.into_inner(),
         Err(_) => {
             eprintln!("Could not get exclusive ownership of App for cleanup");
             // Create a dummy app for cleanup
@@ -95,7 +355,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     };
 
     let mut audio_manager_for_cleanup = match Arc::try_unwrap(audio_manager) {
-        Ok(mutex) => mutex.into_inner()?,
+        Ok(mutex) => mutex.into_inner(),
         Err(_) => {
             eprintln!("Could not get exclusive ownership of AudioStreamManager for cleanup");
             // Create a dummy audio manager for cleanup
@@ -111,36 +371,600 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Runs without the TUI or audio devices, for scripted and end-to-end tests
+///
+/// Commands are read one per line from stdin and each produces one reply line
+/// on stdout; session events are written as they arrive. Outgoing audio is a
+/// generated test tone rather than the microphone.
+async fn run_headless(mut app: App) -> Result<(), Box<dyn std::error::Error>> {
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    let mut events = app
+        .session_manager
+        .as_mut()
+        .and_then(|manager| manager.take_event_receiver());
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+
+    // Room events go to the operator's webhook too, if there is one
+    let webhook = app.config().webhook_url.as_deref().map(|url| {
+        Webhook::new(url)
+            .with_secret(app.config().webhook_secret.clone())
+            .spawn()
+    });
+    let session_id = |app: &App| app.current_session().map(|session| session.id);
+
+    println!("ready");
+
+    // Replies follow the format of the last command: plain text or JSON
+    let mut json = false;
+    let print = |json: bool, reply: ControlReply| {
+        if json {
+            println!("{}", Versioned::new(reply).to_json());
+        } else {
+            println!("{}", reply);
+        }
+    };
+
+    // Reconnect to everyone if the machine sleeps, and keep it awake while in a room
+    let mut sleep_detector = SleepDetector::new();
+    let mut sleep_check = tokio::time::interval(SLEEP_CHECK_INTERVAL);
+    let mut keep_awake = KeepAwake::new();
+
+    // Take in peers joining a room we host
+    let mut join_check = tokio::time::interval(JOIN_CHECK_INTERVAL);
+
+    loop {
+        let line = tokio::select! {
+            line = lines.next_line() => line?,
+            _ = join_check.tick() => {
+                if let Some(manager) = app.session_manager.as_mut() {
+                    manager.accept_joins().await;
+                }
+                continue;
+            }
+            _ = sleep_check.tick() => {
+                if let (Some(slept), Some(manager)) =
+                    (sleep_detector.check(), app.session_manager.as_ref())
+                {
+                    manager.suspend_connections();
+                    manager.wake_connections(slept).await;
+                }
+                keep_awake.set_active(app.config().keep_awake && app.has_active_connection().await);
+                continue;
+            }
+            Some(event) = async {
+                match events.as_mut() {
+                    Some(events) => events.recv().await,
+                    None => std::future::pending().await,
+                }
+            } => {
+                if let (Some(webhook), Some(hook_event)) =
+                    (&webhook, WebhookEvent::from_session_event(&event))
+                {
+                    webhook.send(&hook_event, session_id(&app).as_deref());
+                }
+                print(json, ControlReply::Event { event });
+                continue;
+            }
+        };
+
+        // stdin closed
+        let Some(line) = line else { break };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        json = line.trim_start().starts_with('{');
+        let command = if json {
+            Versioned::<ControlCommand>::from_json(&line)
+        } else {
+            line.parse::<ControlCommand>()
+        };
+        let command = match command {
+            Ok(command) => command,
+            Err(e) => {
+                print(json, ControlReply::error(e));
+                continue;
+            }
+        };
+
+        let reply = match command {
+            ControlCommand::Create => match app.create_p2p_session().await {
+                Ok(session) => ControlReply::ok_with(session.connection_link),
+                Err(e) => ControlReply::session_error(&e),
+            },
+            ControlCommand::Join { link } => match app.join_p2p_session(&link).await {
+                Ok(()) => ControlReply::ok(),
+                Err(e) => ControlReply::session_error(&e),
+            },
+            ControlCommand::Approve { peer_id } => match app.session_manager.as_mut() {
+                Some(manager) => match manager.approve_join(&peer_id).await {
+                    Ok(()) => ControlReply::ok(),
+                    Err(e) => ControlReply::session_error(&e),
+                },
+                None => ControlReply::error("No session manager"),
+            },
+            ControlCommand::ApproveAll => match app.session_manager.as_mut() {
+                Some(manager) => match manager.approve_all_joins().await {
+                    Ok(approved) => ControlReply::ok_with(approved),
+                    Err(e) => ControlReply::session_error(&e),
+                },
+                None => ControlReply::error("No session manager"),
+            },
+            ControlCommand::DenyAll => match app.session_manager.as_mut() {
+                Some(manager) => match manager.deny_all_joins() {
+                    Ok(denied) => ControlReply::ok_with(denied),
+                    Err(e) => ControlReply::session_error(&e),
+                },
+                None => ControlReply::error("No session manager"),
+            },
+            ControlCommand::MuteAll { enabled } => match app.session_manager.as_mut() {
+                Some(manager) => match manager.set_meeting_mode(enabled).await {
+                    Ok(()) => ControlReply::ok(),
+                    Err(e) => ControlReply::session_error(&e),
+                },
+                None => ControlReply::error("No session manager"),
+            },
+            ControlCommand::EchoBot { enabled } => match app.session_manager.as_mut() {
+                Some(manager) => match if enabled {
+                    manager.spawn_echo_bot()
+                } else {
+                    manager.dismiss_echo_bot()
+                } {
+                    Ok(()) => ControlReply::ok(),
+                    Err(e) => ControlReply::session_error(&e),
+                },
+                None => ControlReply::error("No session manager"),
+            },
+            ControlCommand::Hand { raised } => match app.session_manager.as_ref() {
+                Some(manager) => match manager.raise_hand(raised).await {
+                    Ok(changed) => ControlReply::ok_with(changed),
+                    Err(e) => ControlReply::session_error(&e),
+                },
+                None => ControlReply::error("No session manager"),
+            },
+            ControlCommand::Grant { peer_id } => match app.session_manager.as_ref() {
+                Some(manager) => match manager.grant_speak(&peer_id).await {
+                    Ok(()) => ControlReply::ok(),
+                    Err(e) => ControlReply::session_error(&e),
+                },
+                None => ControlReply::error("No session manager"),
+            },
+            ControlCommand::Kick { peer_id } => match app.session_manager.as_mut() {
+                Some(manager) => match manager.kick_peer(&peer_id).await {
+                    Ok(()) => ControlReply::ok(),
+                    Err(e) => ControlReply::session_error(&e),
+                },
+                None => ControlReply::error("No session manager"),
+            },
+            ControlCommand::Actions => match app.session_manager.as_ref() {
+                Some(manager) => {
+                    let actions: Vec<String> = manager
+                        .host_actions()
+                        .iter()
+                        .map(|recorded| {
+                            let undone = if recorded.undone { " (undone)" } else { "" };
+                            format!("{}:{}{}", recorded.id, recorded.action, undone)
+                        })
+                        .collect();
+                    ControlReply::ok_with(actions.join(","))
+                }
+                None => ControlReply::error("No session manager"),
+            },
+            ControlCommand::Replay { id } => match app.session_manager.as_mut() {
+                Some(manager) => match manager.replay_host_action(id).await {
+                    Ok(()) => ControlReply::ok(),
+                    Err(e) => ControlReply::session_error(&e),
+                },
+                None => ControlReply::error("No session manager"),
+            },
+            ControlCommand::Undo { id } => match app.session_manager.as_mut() {
+                Some(manager) => match manager.undo_host_action(id).await {
+                    Ok(()) => ControlReply::ok(),
+                    Err(e) => ControlReply::session_error(&e),
+                },
+                None => ControlReply::error("No session manager"),
+            },
+            ControlCommand::Alias { peer_id, alias } => {
+                match app.set_peer_alias(&peer_id, &alias) {
+                    Ok(()) => ControlReply::ok(),
+                    Err(e) => ControlReply::error(e),
+                }
+            }
+            ControlCommand::Contact { peer_id } => match app.save_contact(&peer_id) {
+                Ok(name) => ControlReply::ok_with(name),
+                Err(e) => ControlReply::error(e),
+            },
+            ControlCommand::Contacts => {
+                let contacts: Vec<String> = app
+                    .contacts()
+                    .into_iter()
+                    .map(|(contact, reachability)| format!("{}={}", contact.name, reachability))
+                    .collect();
+                ControlReply::ok_with(contacts.join(","))
+            }
+            ControlCommand::Call { contact } => match app.call_contact(&contact).await {
+                Ok(link) => ControlReply::ok_with(link),
+                Err(e) => ControlReply::error(e),
+            },
+            ControlCommand::Dnd { enabled } => {
+                app.set_do_not_disturb(enabled);
+                ControlReply::ok()
+            }
+            ControlCommand::AuditExport { path } => match app.export_audit_log(&path) {
+                Ok(entries) => ControlReply::ok_with(entries),
+                Err(e) => ControlReply::error(e),
+            },
+            ControlCommand::Codec { codec } => match codec.map(|codec| codec.parse()).transpose() {
+                Ok(settings) => match app.set_room_codec(settings) {
+                    Ok(()) => ControlReply::ok(),
+                    Err(e) => ControlReply::error(e),
+                },
+                Err(e) => ControlReply::error(e),
+            },
+            ControlCommand::Proxy { proxy } => match proxy.map(|proxy| proxy.parse()).transpose() {
+                Ok(proxy) => match app.set_room_proxy(proxy) {
+                    Ok(()) => ControlReply::ok(),
+                    Err(e) => ControlReply::error(e),
+                },
+                Err(e) => ControlReply::error(e),
+            },
+            ControlCommand::Impair { impairment } => {
+                match impairment.parse::<network::Impairment>() {
+                    Ok(impairment) => {
+                        network::set_impairment(impairment);
+                        ControlReply::ok_with(impairment.to_string())
+                    }
+                    Err(e) => ControlReply::error(e),
+                }
+            }
+            ControlCommand::Tone { millis } => match send_test_tone(&app, millis).await {
+                Ok(frames) => ControlReply::ok_with(frames),
+                Err(e) => ControlReply::error(e),
+            },
+            ControlCommand::Handoff => match app.export_handoff().await {
+                Ok((handoff, key)) => ControlReply::ok_with(format!("{} {}", key, handoff)),
+                Err(e) => ControlReply::session_error(&e),
+            },
+            ControlCommand::Resume { key, handoff } => match key.parse::<HandoffKey>() {
+                Ok(key) => match app.import_handoff(&handoff, &key).await {
+                    Ok(session) => ControlReply::ok_with(session.id),
+                    Err(e) => ControlReply::session_error(&e),
+                },
+                Err(e) => ControlReply::error(e),
+            },
+            ControlCommand::Status => ControlReply::Status(ControlStatus {
+                connected: app.has_active_connection().await,
+                participants: app
+                    .current_session()
+                    .map(|session| session.participants.len())
+                    .unwrap_or(0),
+                receiving: app
+                    .session_manager
+                    .as_ref()
+                    .map(|manager| manager.receiving_streams())
+                    .unwrap_or_default(),
+            }),
+            ControlCommand::Leave => {
+                let left = session_id(&app);
+                match app.leave_session().await {
+                    Ok(()) => {
+                        if let (Some(webhook), Some(left)) = (&webhook, left) {
+                            webhook.send(&WebhookEvent::RoomClosed, Some(&left));
+                        }
+                        ControlReply::ok()
+                    }
+                    Err(e) => ControlReply::error(e),
+                }
+            }
+            ControlCommand::Quit => {
+                print(json, ControlReply::ok());
+                break;
+            }
+        };
+
+        print(json, reply);
+    }
+
+    if let Some(webhook) = webhook {
+        if let Some(session_id) = session_id(&app) {
+            webhook.send(&WebhookEvent::RoomClosed, Some(&session_id));
+        }
+        webhook.close().await;
+    }
+    app.shutdown().await?;
+    Ok(())
+}
+
+/// Sends a 440 Hz tone to the session in 20 ms frames, returning the frame count
+async fn send_test_tone(app: &App, millis: u64) -> Result<usize, String> {
+    let manager = app
+        .session_manager
+        .as_ref()
+        .ok_or_else(|| "No session manager".to_string())?;
+
+    let config = AudioConfig::default();
+    let frame_len = config.frame_len(20);
+    let frame_count = (millis / 20).max(1) as usize;
+    let mut interval = tokio::time::interval(Duration::from_millis(20));
+
+    for seq in 0..frame_count {
+        interval.tick().await;
+        let samples = (0..frame_len)
+            .map(|i| {
+                let t = (seq * frame_len + i) as f32 / config.sample_rate as f32;
+                (t * 440.0 * 2.0 * std::f32::consts::PI).sin() * 0.25
+            })
+            .collect();
+        let frame =
+            AudioFrame::new(samples, config.sample_rate, config.channels).with_seq(seq as u64);
+        manager
+            .send_audio_data(&frame)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(frame_count)
+}
+
+/// Localized name of a spatial layout
+fn layout_name(layout: SpatialLayout) -> &'static str {
+    match layout {
+        SpatialLayout::Circle => t("layout.circle"),
+        SpatialLayout::Grid => t("layout.grid"),
+        SpatialLayout::PresenterFront => t("layout.presenter"),
+    }
+}
+
+/// One line of the device picker: rates, lowest latency and exclusive mode
+fn describe_capabilities(capabilities: &DeviceCapabilities) -> String {
+    let rates = capabilities
+        .sample_rates
+        .iter()
+        .map(|rate| format!("{}", *rate as f32 / 1000.0))
+        .collect::<Vec<_>>()
+        .join("/");
+    let latency = match capabilities.min_latency() {
+        Some(latency) => format!("{:.1} ms", latency.as_secs_f64() * 1000.0),
+        None => t("settings.latency_unknown").to_string(),
+    };
+    let exclusive = match capabilities.exclusive {
+        ExclusiveMode::Available => t("settings.exclusive"),
+        ExclusiveMode::SharedOnly => t("settings.shared_only"),
+    };
+    t_args(
+        "settings.device_line",
+        &[&capabilities.device, &rates, &latency, &exclusive],
+    )
+}
+
+/// Applies settings changed on the settings screen to what's already running
+///
+/// Devices, logging and the like are only read at startup, so those take
+/// effect the next time the app starts.
+async fn apply_settings(
+    app: &mut App,
+    config: app::config::Config,
+    audio_manager: &Arc<tokio::sync::Mutex<AudioStreamManager>>,
+    terminal_ui: &mut ui::TerminalUI,
+) {
+    let relabel = config.locale != app.config().locale;
+    ui::i18n::set_locale(config.locale);
+    {
+        let mut manager = audio_manager.lock().await;
+        manager.set_load_limit(
+            config
+                .cpu_load_limit_percent
+                .map(|percent| percent as f64 / 100.0),
+        );
+        manager.set_channel_map(config.output_channels);
+        manager.set_simple_panning(config.low_bandwidth);
+        if let Err(e) = manager.set_network_monitor(
+            config
+                .network_monitor
+                .then(|| low_bandwidth::send_codec(&config)),
+        ) {
+            terminal_ui.show_warning(
+                t_args("error.network_monitor", &[&e]),
+                Duration::from_secs(4),
+            );
+        }
+        if let Err(e) = manager.set_system_audio(config.system_audio_percent) {
+            terminal_ui.show_warning(t_args("error.system_audio", &[&e]), Duration::from_secs(4));
+        }
+    }
+    terminal_ui.set_do_not_disturb(config.do_not_disturb);
+    terminal_ui.set_low_bandwidth(config.low_bandwidth);
+    terminal_ui.set_accessibility(ui::accessibility::Accessibility::from_config(&config));
+    // Sockets bound from now on; those already open keep their buffers
+    network::set_socket_buffers(network::SocketBuffers::from_kib(
+        config.udp_receive_buffer_kb,
+        config.udp_send_buffer_kb,
+    ));
+    if let Err(e) = config.bind_address.resolve() {
+        terminal_ui.show_warning(t_args("error.bind_address", &[&e]), Duration::from_secs(4));
+    }
+    terminal_ui.set_link_privacy(if config.share_lan_addresses {
+        network::LinkPrivacy::LOCAL
+    } else {
+        network::LinkPrivacy::default()
+    });
+    app.apply_settings(config).await;
+
+    // Menu labels are fixed when the menu is built
+    if relabel {
+        let connected = app.has_active_connection().await;
+        terminal_ui.update_menu_items(connected);
+    }
+}
+
+/// An error message as shown to the user, with what to try on the line below
+fn with_hint(message: String, error: &SessionError) -> String {
+    match error.user_hint() {
+        Some(hint) => format!("{}\n{}", message, hint),
+        None => message,
+    }
+}
+
+/// Interface names, then addresses, offered for binding sockets to
+fn bind_choices() -> Vec<String> {
+    let interfaces = network::list_interfaces().unwrap_or_default();
+    let mut choices: Vec<String> = Vec::new();
+    for interface in &interfaces {
+        if !choices.contains(&interface.name) {
+            choices.push(interface.name.clone());
+        }
+    }
+    choices.extend(
+        interfaces
+            .iter()
+            .map(|interface| interface.address.to_string()),
+    );
+    choices
+}
+
+/// Device ID calibrations for the current input are stored under
+fn calibration_device(config: &app::config::Config) -> &str {
+    config.input_device.as_deref().unwrap_or(DEFAULT_DEVICE_ID)
+}
+
+/// Progress of a calibration running in the background
+enum CalibrationUpdate {
+    Phase(CalibrationPhase),
+    Finished(Result<Calibration, String>),
+}
+
+/// Runs the guided calibration on its own capture of the microphone
+///
+/// Phase changes are reported as they happen so the user knows when to
+/// speak, followed by the result.
+async fn run_calibration(updates: mpsc::UnboundedSender<CalibrationUpdate>) {
+    let (frames_tx, mut frames) = mpsc::unbounded_channel::<AudioFrame>();
+    let mut capture = AudioCapture::new();
+    capture.set_data_callback(move |frame| {
+        let _ = frames_tx.send(frame);
+    });
+    if let Err(e) = capture.start().await {
+        let _ = updates.send(CalibrationUpdate::Finished(Err(e.to_string())));
+        return;
+    }
+
+    let mut run: Option<CalibrationRun> = None;
+    let mut phase = CalibrationPhase::Quiet;
+    let _ = updates.send(CalibrationUpdate::Phase(phase));
+    let result = loop {
+        // A microphone that stops delivering audio can't be calibrated
+        let frame = match tokio::time::timeout(Duration::from_secs(2), frames.recv()).await {
+            Ok(Some(frame)) => frame,
+            _ => break Err(CalibrationError::NoSignal.to_string()),
+        };
+        let run = run.get_or_insert_with(|| CalibrationRun::new(frame.sample_rate));
+
+        let mono = audio::conversions::downmix_to_mono(&frame.samples, frame.channels);
+        run.push(&mono);
+
+        if run.phase() != phase {
+            phase = run.phase();
+            if phase == CalibrationPhase::Done {
+                break run.finish().map_err(|e| e.to_string());
+            }
+            let _ = updates.send(CalibrationUpdate::Phase(phase));
+        }
+    };
+
+    if let Err(e) = capture.stop().await {
+        log::warn!("Failed to stop calibration capture: {}", e);
+    }
+    let _ = updates.send(CalibrationUpdate::Finished(result));
+}
+
+/// What startup found that the screen shows once it opens
+struct StartupReport {
+    log_file: Option<std::path::PathBuf>,
+    missing_devices: Vec<DeviceFingerprint>,
+    replaced_microphone: Option<(String, String)>,
+    health_checks: Option<Vec<app::health::HealthCheck>>,
+}
+
 // Modified version of run_tui that uses shared audio data
 async fn run_tui_with_audio(
-    app: Arc<Mutex<App>>,
-    audio_manager: Arc<Mutex<AudioStreamManager>>,
-    app_connection: Arc<App>,
+    app: Arc<tokio::sync::Mutex<App>>,
+    audio_manager: Arc<tokio::sync::Mutex<AudioStreamManager>>,
+    app_connection: App,
     participants: Arc<Mutex<Vec<Participant>>>,
+    mut session_events: Option<mpsc::UnboundedReceiver<SessionEvent>>,
+    startup: StartupReport,
+    mut takeovers: Option<mpsc::UnboundedReceiver<TakeoverRequest>>,
 ) -> io::Result<()> {
-    // Initialize terminal
+    let StartupReport {
+        log_file,
+        missing_devices,
+        replaced_microphone,
+        health_checks,
+    } = startup;
+
+    // Initialize terminal, drawn from this thread until we quit
+    let _ui = topology::register(Plane::Ui, "terminal", None);
     let mut terminal_ui = ui::terminal_ui::TerminalUI::new();
     terminal_ui.initialize()?;
 
+    // Another device stands in until the chosen one is plugged back in
+    let mut lines: Vec<String> = missing_devices
+        .iter()
+        .filter(|missing| !(missing.is_input && replaced_microphone.is_some()))
+        .map(|missing| {
+            let key = if missing.is_input {
+                "notify.input_missing"
+            } else {
+                "notify.output_missing"
+            };
+            t_args(key, &[&missing.name])
+        })
+        .collect();
+    if let Some((missing, stand_in)) = &replaced_microphone {
+        lines.insert(0, t_args("notify.input_replaced", &[missing, stand_in]));
+    }
+    if !lines.is_empty() {
+        terminal_ui.show_warning(lines.join("\n"), Duration::from_secs(8));
+    }
+    // Session reports are written beside the log
+    let report_dir = log_file
+        .as_deref()
+        .and_then(std::path::Path::parent)
+        .map(std::path::Path::to_path_buf)
+        .unwrap_or_else(app::logging::default_log_dir);
+    terminal_ui.set_log_file(log_file);
+    if app.lock().await.config().share_lan_addresses {
+        terminal_ui.set_link_privacy(network::LinkPrivacy::LOCAL);
+    }
+    terminal_ui.set_do_not_disturb(app.lock().await.config().do_not_disturb);
+    terminal_ui.set_low_bandwidth(app.lock().await.config().low_bandwidth);
+    terminal_ui.set_accessibility(ui::accessibility::Accessibility::from_config(
+        app.lock().await.config(),
+    ));
+    if let Some(checks) = health_checks {
+        terminal_ui.show_health_checks(checks);
+    }
+
     // Check if we're already in a session and set menu items accordingly
     let has_connection = {
-        let app_lock = app.lock().unwrap();
+        let app_lock = app.lock().await;
         app_lock.has_active_connection().await
     };
     terminal_ui.update_menu_items(has_connection);
 
     // Create an audio stream
-    if let Ok(mut audio_manager_guard) = audio_manager.lock() {
-        match audio_manager_guard
-            .create_stream("default-session".to_string())
-            .await
-        {
-            Ok(_stream) => {
-                // Successfully created stream
-            }
-            Err(e) => {
-                eprintln!("Error creating audio stream: {}", e);
-            }
+    match audio_manager
+        .lock()
+        .await
+        .create_stream("default-session".to_string())
+        .await
+    {
+        Ok(_stream) => {
+            // Successfully created stream
+        }
+        Err(e) => {
+            eprintln!("Error creating audio stream: {}", e);
         }
     }
 
@@ -149,6 +973,49 @@ async fn run_tui_with_audio(
     let audio_update_rate = Duration::from_millis(200); // Update participant positions every 200ms
     let mut last_tick = std::time::Instant::now();
     let mut last_audio_update = std::time::Instant::now();
+    let mut last_stats_sample = std::time::Instant::now();
+
+    // Merge bursts of session events so they cause at most one update per frame
+    let mut event_coalescer = EventCoalescer::new(tick_rate);
+
+    // Our own presence, derived from voice activity and key presses
+    let mut presence = PresenceTracker::default();
+
+    // Notices the machine waking from sleep, so the call can be picked up again
+    let mut sleep_detector = SleepDetector::new();
+
+    // Holds off sleep and screen dimming while we're in a room
+    let mut keep_awake = KeepAwake::new();
+
+    // Who came and went in the room, for the timeline pane and session report
+    let mut timeline = SessionTimeline::new();
+    let mut was_connected = false;
+
+    // Follow the system mic mute, so mic-mute keys mute the room too
+    let follow_system_mute = app.lock().await.config().follow_system_mute;
+    let (mute_watcher, mut system_mute_changes) = match detect_system_mute() {
+        Some(backend) if follow_system_mute => {
+            let (watcher, changes) = SystemMuteWatcher::spawn(backend, Duration::from_millis(500));
+            (Some(watcher), Some(changes))
+        }
+        _ => (None, None),
+    };
+
+    // The microphone's stalls and recoveries, shown as they happen
+    let mut capture_events = audio_manager.lock().await.take_capture_events();
+
+    // Updates from a microphone calibration, while one is running
+    let mut calibration_updates: Option<mpsc::UnboundedReceiver<CalibrationUpdate>> = None;
+
+    // Results of testing ICE servers from the settings screen, while tests are running
+    let mut ice_results: Option<
+        mpsc::UnboundedReceiver<(network::IceServer, network::IceTestResult)>,
+    > = None;
+    // Plays the spatial preview voice's current walk, one block at a time
+    let mut spatial_preview: Option<tokio::task::JoinHandle<()>> = None;
+    // Shareable links as shortened, and the shortener and link last sent to it
+    let (short_links_tx, mut short_links) = mpsc::unbounded_channel::<(String, String)>();
+    let mut shortening: Option<(String, String)> = None;
 
     // For throttling error messages
     let mut last_error_time = std::time::Instant::now();
@@ -161,272 +1028,843 @@ async fn run_tui_with_audio(
             .unwrap_or_else(|| Duration::from_secs(0));
 
         // Poll for events
-        if let Some(event) = terminal_ui.poll_events(timeout)? {
-            match event {
-                crossterm::event::Event::Key(key_event) => {
-                    // Handle Ctrl+C for exit
-                    if key_event
-                        .modifiers
-                        .contains(crossterm::event::KeyModifiers::CONTROL)
-                        && key_event.code == crossterm::event::KeyCode::Char('c')
-                    {
-                        break;
-                    }
-
-                    if let Some(action) = terminal_ui.handle_key_event(key_event.code) {
-                        // First handle internal actions like copy
-                        if terminal_ui.handle_menu_action(action.clone()) {
-                            continue;
+        if let Some(crossterm::event::Event::Key(key_event)) = terminal_ui.poll_events(timeout)? {
+            presence.record_input();
+
+            // Handle Ctrl+C for exit
+            if key_event
+                .modifiers
+                .contains(crossterm::event::KeyModifiers::CONTROL)
+                && key_event.code == crossterm::event::KeyCode::Char('c')
+            {
+                break;
+            }
+
+            if let Some(action) = terminal_ui.handle_key_event(key_event.code) {
+                // First handle internal actions like copy
+                if terminal_ui.handle_menu_action(action.clone()) {
+                    continue;
+                }
+
+                let mut app_lock = app.lock().await;
+                match action {
+                    ui::MenuAction::Create => {
+                        // Create a new session
+                        match app_lock.create_p2p_session().await {
+                            Ok(session) => {
+                                terminal_ui
+                                    .set_connection_link(Some(session.connection_link.clone()));
+                                terminal_ui.update_participants(session.participants.clone());
+                                // Update menu for active connection
+                                terminal_ui.update_menu_items(true);
+                            }
+                            Err(e) => terminal_ui.show_warning(
+                                with_hint(t_args("error.create_failed", &[&e]), &e),
+                                Duration::from_secs(6),
+                            ),
                         }
+                    }
+                    ui::MenuAction::Join => {
+                        // Show input prompt for session link
+                        terminal_ui.show_text_input_popup(t("prompt.join_link"));
+
+                        // Release the lock during input to avoid deadlock
+                        drop(app_lock);
+
+                        // Process events until we get input or cancel
+                        loop {
+                            // Use a shorter tick rate for responsive input
+                            let input_timeout = Duration::from_millis(16); // ~60FPS for responsive input
 
-                        let mut app_lock = app.lock().unwrap();
-                        match action {
-                            ui::MenuAction::Create => {
-                                // Create a new session
-                                if let Ok(session) = app_lock.create_p2p_session().await {
-                                    terminal_ui
-                                        .set_connection_link(Some(session.connection_link.clone()));
-                                    terminal_ui.update_participants(session.participants.clone());
-                                    // Update menu for active connection
-                                    terminal_ui.update_menu_items(true);
+                            if let Some(crossterm::event::Event::Key(key_event)) =
+                                terminal_ui.poll_events(input_timeout)?
+                            {
+                                // Handle Ctrl+C for exit during input
+                                if key_event
+                                    .modifiers
+                                    .contains(crossterm::event::KeyModifiers::CONTROL)
+                                    && key_event.code == crossterm::event::KeyCode::Char('c')
+                                {
+                                    terminal_ui.close_text_input();
+                                    break;
                                 }
+
+                                // Let the terminal_ui handle the input
+                                terminal_ui.handle_key_event(key_event.code);
                             }
-                            ui::MenuAction::Join => {
-                                // Show input prompt for session link
-                                terminal_ui.show_text_input_popup("Enter session link to join:");
-
-                                // Release the lock during input to avoid deadlock
-                                drop(app_lock);
-
-                                // Process events until we get input or cancel
-                                loop {
-                                    // Use a shorter tick rate for responsive input
-                                    let input_timeout = Duration::from_millis(16); // ~60FPS for responsive input
-
-                                    if let Some(event) = terminal_ui.poll_events(input_timeout)? {
-                                        if let crossterm::event::Event::Key(key_event) = event {
-                                            // Handle Ctrl+C for exit during input
-                                            if key_event
-                                                .modifiers
-                                                .contains(crossterm::event::KeyModifiers::CONTROL)
-                                                && key_event.code
-                                                    == crossterm::event::KeyCode::Char('c')
-                                            {
-                                                terminal_ui.close_text_input();
-                                                break;
-                                            }
 
-                                            // Let the terminal_ui handle the input
-                                            terminal_ui.handle_key_event(key_event.code);
-                                        }
-                                    }
+                            // Render UI during input
+                            let audio_data = audio_manager.lock().await.get_raw_capture_data();
 
-                                    // Render UI during input
-                                    let audio_data = {
-                                        if let Ok(audio_manager) = audio_manager.lock() {
-                                            audio_manager.get_raw_capture_data()
-                                        } else {
-                                            Vec::new()
-                                        }
-                                    };
-
-                                    if !audio_data.is_empty() {
-                                        terminal_ui.update_audio_data(&audio_data);
-                                    }
+                            if !audio_data.is_empty() {
+                                terminal_ui.update_audio_data(&audio_data);
+                            }
 
-                                    terminal_ui.render(&app.lock().unwrap())?;
-
-                                    // Check if text input is still active
-                                    if let Some(text_input) = terminal_ui.get_input_text() {
-                                        if !terminal_ui.is_text_input_active() {
-                                            // Input finished, close the input
-                                            terminal_ui.close_text_input();
-
-                                            // If we have a link, try to join the session
-                                            if !text_input.trim().is_empty() {
-                                                let mut app_lock = app.lock().unwrap();
-                                                match app_lock.join_p2p_session(&text_input).await {
-                                                    Ok(()) => {
-                                                        if let Some(session) =
-                                                            app_lock.current_session()
-                                                        {
-                                                            terminal_ui.set_connection_link(Some(
-                                                                session.connection_link.clone(),
-                                                            ));
-                                                            terminal_ui.update_participants(
-                                                                session.participants.clone(),
-                                                            );
-                                                            // Update menu for active connection
-                                                            terminal_ui.update_menu_items(true);
-                                                            terminal_ui.show_notification(
-                                                                "Successfully joined session"
-                                                                    .to_string(),
-                                                                Duration::from_secs(2),
-                                                            );
-                                                        }
-                                                    }
-                                                    Err(e) => {
-                                                        // Show error notification
-                                                        terminal_ui.show_notification(
-                                                            format!(
-                                                                "Failed to join session: {}",
-                                                                e
-                                                            ),
-                                                            Duration::from_secs(3),
-                                                        );
-                                                    }
+                            terminal_ui.render(&*app.lock().await)?;
+
+                            // Check if text input is still active
+                            if let Some(text_input) = terminal_ui.get_input_text() {
+                                if !terminal_ui.is_text_input_active() {
+                                    // Input finished, close the input
+                                    terminal_ui.close_text_input();
+
+                                    // If we have a link, try to join the session
+                                    if !text_input.trim().is_empty() {
+                                        let mut app_lock = app.lock().await;
+                                        match app_lock.join_p2p_session(&text_input).await {
+                                            Ok(()) => {
+                                                if let Some(session) = app_lock.current_session() {
+                                                    terminal_ui.set_connection_link(Some(
+                                                        session.connection_link.clone(),
+                                                    ));
+                                                    terminal_ui.update_participants(
+                                                        session.participants.clone(),
+                                                    );
+                                                    // Update menu for active connection
+                                                    terminal_ui.update_menu_items(true);
+                                                    terminal_ui.show_notification(
+                                                        t("notify.joined").to_string(),
+                                                        Duration::from_secs(2),
+                                                    );
                                                 }
                                             }
-                                            break;
+                                            Err(e) => {
+                                                // Show error notification
+                                                terminal_ui.show_warning(
+                                                    with_hint(
+                                                        t_args("error.join_failed", &[&e]),
+                                                        &e,
+                                                    ),
+                                                    Duration::from_secs(6),
+                                                );
+                                            }
                                         }
-                                    } else {
-                                        // Input was closed
-                                        break;
                                     }
+                                    break;
                                 }
+                            } else {
+                                // Input was closed
+                                break;
                             }
-                            ui::MenuAction::Leave => {
-                                if app_lock.has_active_connection().await {
-                                    // Show a notification that we're leaving
+                        }
+                    }
+                    ui::MenuAction::Leave => {
+                        if app_lock.has_active_connection().await {
+                            // Show a notification that we're leaving
+                            terminal_ui.show_notification(
+                                t("notify.leaving").to_string(),
+                                Duration::from_secs(1),
+                            );
+
+                            // Fade out what we're hearing while the session drains
+                            audio_manager.lock().await.fade_out(HANGUP_FADE);
+
+                            // Actually leave the session
+                            match app_lock.leave_session().await {
+                                Ok(_) => {
+                                    // Clear UI state
+                                    terminal_ui.set_connection_link(None);
+                                    terminal_ui.update_participants(vec![]);
+                                    terminal_ui.set_recorders(Vec::new());
+                                    // Update menu for no active connection
+                                    terminal_ui.update_menu_items(false);
                                     terminal_ui.show_notification(
-                                        "Leaving session...".to_string(),
-                                        Duration::from_secs(1),
+                                        t("notify.left").to_string(),
+                                        Duration::from_secs(2),
                                     );
+                                }
+                                Err(e) => {
+                                    terminal_ui.show_notification(
+                                        t_args("error.leave_failed", &[&e]),
+                                        Duration::from_secs(3),
+                                    );
+                                }
+                            }
+                        } else {
+                            // Already not in a session
+                            terminal_ui.show_notification(
+                                t("notify.not_in_session").to_string(),
+                                Duration::from_secs(2),
+                            );
+                            // Make sure UI is in the correct state
+                            terminal_ui.set_connection_link(None);
+                            terminal_ui.update_participants(vec![]);
+                            terminal_ui.update_menu_items(false);
+                        }
+                    }
+                    ui::MenuAction::CopyLink
+                    | ui::MenuAction::CopyLocalLink
+                    | ui::MenuAction::Inspect
+                    | ui::MenuAction::HostActions
+                    | ui::MenuAction::Timeline => {
+                        // Already handled in handle_menu_action
+                    }
+                    ui::MenuAction::ExportTimeline => {
+                        let message = match timeline.write_report(&report_dir) {
+                            Ok(path) => t_args("notify.report_written", &[&path.display()]),
+                            Err(e) => t_args("error.session_report", &[&e]),
+                        };
+                        terminal_ui.show_notification(message, Duration::from_secs(4));
+                    }
+                    ui::MenuAction::ReplayHostAction(id) | ui::MenuAction::UndoHostAction(id) => {
+                        let Some(manager) = app_lock.session_manager.as_mut() else {
+                            continue;
+                        };
+                        let undo = matches!(action, ui::MenuAction::UndoHostAction(_));
+                        let result = if undo {
+                            manager.undo_host_action(id).await
+                        } else {
+                            manager.replay_host_action(id).await
+                        };
+                        let message = match result {
+                            Ok(()) if undo => t("notify.action_undone").to_string(),
+                            Ok(()) => t("notify.action_replayed").to_string(),
+                            Err(e) => e.to_string(),
+                        };
+                        terminal_ui.set_host_actions(manager.host_actions());
+                        terminal_ui.show_notification(message, Duration::from_secs(2));
+                    }
+                    ui::MenuAction::ToggleMute => {
+                        let muted = {
+                            let manager = audio_manager.lock().await;
+                            let muted = !manager.is_muted();
+                            manager.set_muted(muted);
+                            muted
+                        };
+                        if let Some(watcher) = &mute_watcher {
+                            if let Err(e) = watcher.set_muted(muted) {
+                                log::warn!("Failed to set system mute: {}", e);
+                            }
+                        }
+                        audio_manager.lock().await.play_ui_sound(if muted {
+                            UiSound::Muted
+                        } else {
+                            UiSound::Unmuted
+                        });
+                        terminal_ui.set_muted(muted);
+                        terminal_ui.show_notification(
+                            t(if muted {
+                                "notify.muted"
+                            } else {
+                                "notify.unmuted"
+                            })
+                            .to_string(),
+                            Duration::from_secs(1),
+                        );
+                    }
+                    ui::MenuAction::BeRightBack => {
+                        let away = !presence.is_away();
+                        let hold = if away {
+                            let source = app.lock().await.config().brb_audio.clone();
+                            let sample_rate = audio_manager.lock().await.sample_rate();
+                            // A clip that won't load shouldn't keep us from stepping away
+                            Some(
+                                HoldAudio::from_source(&source, sample_rate).unwrap_or_else(|e| {
+                                    log::warn!("Failed to load hold audio: {}", e);
+                                    terminal_ui.show_notification(
+                                        t_args("notify.hold_audio_failed", &[&e]),
+                                        Duration::from_secs(3),
+                                    );
+                                    HoldAudio::silence()
+                                }),
+                            )
+                        } else {
+                            None
+                        };
+                        audio_manager.lock().await.set_hold_audio(hold);
+
+                        // Peers see us as away on the next presence update
+                        presence.set_away(away);
+                        terminal_ui.set_away(away);
+                        terminal_ui.show_notification(
+                            t(if away { "notify.away" } else { "notify.back" }).to_string(),
+                            Duration::from_secs(2),
+                        );
+                    }
+                    ui::MenuAction::CycleLayout => {
+                        let is_host = app_lock
+                            .current_session()
+                            .is_some_and(|session| session.is_host);
+                        if !is_host {
+                            terminal_ui.show_notification(
+                                t("notify.layout_host_only").to_string(),
+                                Duration::from_secs(2),
+                            );
+                            continue;
+                        }
 
-                                    // Actually leave the session
-                                    match app_lock.leave_session().await {
-                                        Ok(_) => {
-                                            // Clear UI state
-                                            terminal_ui.set_connection_link(None);
-                                            terminal_ui.update_participants(vec![]);
-                                            // Update menu for no active connection
-                                            terminal_ui.update_menu_items(false);
+                        let layout = app_lock
+                            .session_manager
+                            .as_ref()
+                            .map(|manager| manager.spatial_layout().next())
+                            .unwrap_or_default();
+                        match app_lock.arrange_participants(layout).await {
+                            Ok(()) => terminal_ui.show_notification(
+                                t_args("notify.layout", &[&layout_name(layout)]),
+                                Duration::from_secs(2),
+                            ),
+                            Err(e) => terminal_ui.show_notification(e, Duration::from_secs(2)),
+                        }
+                    }
+                    ui::MenuAction::MuteAll => {
+                        let Some(manager) = app_lock.session_manager.as_mut() else {
+                            continue;
+                        };
+                        let enabled = !manager.meeting().is_enabled();
+                        // The event for it updates the roster
+                        match manager.set_meeting_mode(enabled).await {
+                            Ok(()) => {}
+                            Err(SessionError::NotHost) => terminal_ui.show_notification(
+                                t("notify.meeting_host_only").to_string(),
+                                Duration::from_secs(2),
+                            ),
+                            Err(e) => {
+                                terminal_ui.show_notification(e.to_string(), Duration::from_secs(2))
+                            }
+                        }
+                    }
+                    ui::MenuAction::EchoBot => {
+                        let Some(manager) = app_lock.session_manager.as_mut() else {
+                            continue;
+                        };
+                        let enabled = !manager.has_echo_bot();
+                        let result = if enabled {
+                            manager.spawn_echo_bot()
+                        } else {
+                            manager.dismiss_echo_bot()
+                        };
+                        let message = match result {
+                            Ok(()) if enabled => t("notify.echo_bot_on").to_string(),
+                            Ok(()) => t("notify.echo_bot_off").to_string(),
+                            Err(SessionError::NotHost) => {
+                                t("notify.echo_bot_host_only").to_string()
+                            }
+                            Err(e) => e.to_string(),
+                        };
+                        terminal_ui.show_notification(message, Duration::from_secs(2));
+                    }
+                    ui::MenuAction::CycleImpairment => {
+                        // Hidden debug menu: steps through the impairment presets
+                        let current = network::impairment();
+                        let next = network::IMPAIRMENT_PRESETS
+                            .iter()
+                            .position(|(_, preset)| *preset == current)
+                            .map_or(0, |index| (index + 1) % network::IMPAIRMENT_PRESETS.len());
+                        let (name, impairment) = network::IMPAIRMENT_PRESETS[next];
+                        network::set_impairment(impairment);
+                        terminal_ui.show_notification(
+                            t_args("notify.impairment", &[&name, &impairment]),
+                            Duration::from_secs(3),
+                        );
+                    }
+                    ui::MenuAction::DoNotDisturb => {
+                        let enabled = !app_lock.config().do_not_disturb;
+                        app_lock.set_do_not_disturb(enabled);
+                        terminal_ui.set_do_not_disturb(enabled);
+                        terminal_ui.show_notification(
+                            t(if enabled {
+                                "notify.dnd_on"
+                            } else {
+                                "notify.dnd_off"
+                            })
+                            .to_string(),
+                            Duration::from_secs(2),
+                        );
+                    }
+                    ui::MenuAction::LowBandwidth => {
+                        let enabled = !app_lock.config().low_bandwidth;
+                        app_lock.set_low_bandwidth(enabled).await;
+                        audio_manager.lock().await.set_simple_panning(enabled);
+                        terminal_ui.set_low_bandwidth(enabled);
+                        terminal_ui.show_notification(
+                            t(if enabled {
+                                "notify.low_bandwidth_on"
+                            } else {
+                                "notify.low_bandwidth_off"
+                            })
+                            .to_string(),
+                            Duration::from_secs(2),
+                        );
+                    }
+                    ui::MenuAction::RaiseHand => {
+                        let Some(manager) = app_lock.session_manager.as_ref() else {
+                            continue;
+                        };
+                        match manager.raise_hand(!manager.is_hand_raised()).await {
+                            Ok(true) => {}
+                            Ok(false) => terminal_ui.show_notification(
+                                t("notify.hand_not_needed").to_string(),
+                                Duration::from_secs(2),
+                            ),
+                            Err(e) => {
+                                terminal_ui.show_notification(e.to_string(), Duration::from_secs(2))
+                            }
+                        }
+                    }
+                    ui::MenuAction::GrantSpeak => {
+                        let Some(manager) = app_lock.session_manager.as_ref() else {
+                            continue;
+                        };
+                        let Some(peer_id) = manager.meeting().raised_hands().first().cloned()
+                        else {
+                            terminal_ui.show_notification(
+                                t("notify.no_hands").to_string(),
+                                Duration::from_secs(2),
+                            );
+                            continue;
+                        };
+                        match manager.grant_speak(&peer_id).await {
+                            Ok(()) => {}
+                            Err(SessionError::NotHost) => terminal_ui.show_notification(
+                                t("notify.meeting_host_only").to_string(),
+                                Duration::from_secs(2),
+                            ),
+                            Err(e) => {
+                                terminal_ui.show_notification(e.to_string(), Duration::from_secs(2))
+                            }
+                        }
+                    }
+                    ui::MenuAction::Record => {
+                        let Some(manager) = app_lock.session_manager.as_ref() else {
+                            continue;
+                        };
+                        // The event for it updates the indicator
+                        match manager.set_recording(!manager.is_recording()).await {
+                            Ok(true) if manager.is_recording() => terminal_ui.show_notification(
+                                t("notify.recording_self").to_string(),
+                                Duration::from_secs(3),
+                            ),
+                            Ok(_) => {}
+                            Err(e) => terminal_ui.show_notification(
+                                with_hint(e.to_string(), &e),
+                                Duration::from_secs(4),
+                            ),
+                        }
+                    }
+                    ui::MenuAction::ApproveAll => {
+                        let Some(manager) = app_lock.session_manager.as_mut() else {
+                            continue;
+                        };
+                        match manager.approve_all_joins().await {
+                            Ok(approved) => terminal_ui.show_notification(
+                                t_args("notify.approved_all", &[&approved]),
+                                Duration::from_secs(2),
+                            ),
+                            Err(e) => {
+                                terminal_ui.show_notification(e.to_string(), Duration::from_secs(2))
+                            }
+                        }
+                    }
+                    ui::MenuAction::DenyAll => {
+                        let Some(manager) = app_lock.session_manager.as_mut() else {
+                            continue;
+                        };
+                        match manager.deny_all_joins() {
+                            Ok(denied) => terminal_ui.show_notification(
+                                t_args("notify.denied_all", &[&denied]),
+                                Duration::from_secs(2),
+                            ),
+                            Err(e) => {
+                                terminal_ui.show_notification(e.to_string(), Duration::from_secs(2))
+                            }
+                        }
+                    }
+                    ui::MenuAction::Calibrate => {
+                        if calibration_updates.is_some() {
+                            continue;
+                        }
+                        let (tx, rx) = mpsc::unbounded_channel();
+                        topology::spawn(Plane::Control, "calibration", run_calibration(tx));
+                        calibration_updates = Some(rx);
+                    }
+                    ui::MenuAction::Solo(participant) => {
+                        audio_manager.lock().await.set_solo(participant.clone());
+                        let message = match &participant {
+                            Some(name) => t_args("notify.solo", &[name]),
+                            None => t("notify.solo_off").to_string(),
+                        };
+                        terminal_ui.set_solo(participant);
+                        terminal_ui.show_notification(message, Duration::from_secs(1));
+                    }
+                    ui::MenuAction::Settings => {
+                        // Show settings menu
+                        terminal_ui.show_text_input_popup(t("menu.settings"));
+
+                        // Release the lock during input to avoid deadlock
+                        drop(app_lock);
+
+                        // Show a sub-menu with settings options
+                        let settings_options = [
+                            t("settings.test_session"),
+                            t("settings.devices"),
+                            t("settings.preferences"),
+                            t("settings.cancel"),
+                        ];
+
+                        // Display settings options
+                        terminal_ui
+                            .show_notification(settings_options.join("\n"), Duration::from_secs(5));
+
+                        // Process input for settings
+                        loop {
+                            let input_timeout = Duration::from_millis(16);
+
+                            if let Some(crossterm::event::Event::Key(key_event)) =
+                                terminal_ui.poll_events(input_timeout)?
+                            {
+                                // Handle key events for settings
+                                match key_event.code {
+                                    crossterm::event::KeyCode::Char('1') => {
+                                        // Create test session
+                                        terminal_ui.close_text_input();
+
+                                        let mut app_lock = app.lock().await;
+                                        if let Ok(session) = app_lock.create_test_session().await {
+                                            terminal_ui
+                                                .update_participants(session.participants.clone());
+                                            terminal_ui.set_connection_link(Some(
+                                                "Test Session".to_string(),
+                                            ));
+                                            terminal_ui.update_menu_items(true);
                                             terminal_ui.show_notification(
-                                                "Session left successfully".to_string(),
+                                                t("notify.test_session_created").to_string(),
                                                 Duration::from_secs(2),
                                             );
-                                        }
-                                        Err(e) => {
+                                        } else {
                                             terminal_ui.show_notification(
-                                                format!("Error leaving session: {}", e),
-                                                Duration::from_secs(3),
+                                                t("error.test_session_failed").to_string(),
+                                                Duration::from_secs(2),
                                             );
                                         }
+                                        break;
                                     }
-                                } else {
-                                    // Already not in a session
-                                    terminal_ui.show_notification(
-                                        "Not in a session".to_string(),
-                                        Duration::from_secs(2),
-                                    );
-                                    // Make sure UI is in the correct state
-                                    terminal_ui.set_connection_link(None);
-                                    terminal_ui.update_participants(vec![]);
-                                    terminal_ui.update_menu_items(false);
-                                }
-                            }
-                            ui::MenuAction::CopyLink => {
-                                // Already handled in handle_menu_action
-                            }
-                            ui::MenuAction::Settings => {
-                                // Show settings menu
-                                terminal_ui.show_text_input_popup("Settings");
-
-                                // Release the lock during input to avoid deadlock
-                                drop(app_lock);
-
-                                // Show a sub-menu with settings options
-                                let settings_options = vec!["1. Create Test Session", "2. Cancel"];
-
-                                // Display settings options
-                                terminal_ui.show_notification(
-                                    settings_options.join("\n"),
-                                    Duration::from_secs(5),
-                                );
-
-                                // Process input for settings
-                                loop {
-                                    let input_timeout = Duration::from_millis(16);
-
-                                    if let Some(event) = terminal_ui.poll_events(input_timeout)? {
-                                        if let crossterm::event::Event::Key(key_event) = event {
-                                            // Handle key events for settings
-                                            match key_event.code {
-                                                crossterm::event::KeyCode::Char('1') => {
-                                                    // Create test session
-                                                    terminal_ui.close_text_input();
-
-                                                    let mut app_lock = app.lock().unwrap();
-                                                    if let Ok(session) =
-                                                        app_lock.create_test_session().await
-                                                    {
-                                                        terminal_ui.update_participants(
-                                                            session.participants.clone(),
-                                                        );
-                                                        terminal_ui.set_connection_link(Some(
-                                                            "Test Session".to_string(),
-                                                        ));
-                                                        terminal_ui.update_menu_items(true);
-                                                        terminal_ui.show_notification(
-                                                            "Test session created with 3 simulated participants".to_string(),
-                                                            Duration::from_secs(2),
-                                                        );
-                                                    } else {
-                                                        terminal_ui.show_notification(
-                                                            "Failed to create test session"
-                                                                .to_string(),
-                                                            Duration::from_secs(2),
-                                                        );
-                                                    }
-                                                    break;
-                                                }
-                                                crossterm::event::KeyCode::Char('2')
-                                                | crossterm::event::KeyCode::Esc => {
-                                                    // Cancel
-                                                    terminal_ui.close_text_input();
-                                                    break;
-                                                }
-                                                _ => {}
-                                            }
-                                        }
+                                    crossterm::event::KeyCode::Char('2') => {
+                                        // Show what each device can do
+                                        terminal_ui.close_text_input();
+                                        let lines: Vec<String> = probe_devices()
+                                            .iter()
+                                            .map(describe_capabilities)
+                                            .collect();
+                                        terminal_ui.show_notification(
+                                            if lines.is_empty() {
+                                                t("settings.no_devices").to_string()
+                                            } else {
+                                                lines.join("\n")
+                                            },
+                                            Duration::from_secs(10),
+                                        );
+                                        break;
                                     }
-
-                                    // Render UI during input
-                                    terminal_ui.render(&app.lock().unwrap())?;
-
-                                    // Check if text input is still active
-                                    if !terminal_ui.is_text_input_active() {
+                                    crossterm::event::KeyCode::Char('3') => {
+                                        // Edit settings on their own screen
+                                        terminal_ui.close_text_input();
+                                        let names = |is_input: bool| {
+                                            probe_devices()
+                                                .into_iter()
+                                                .filter(|capabilities| {
+                                                    capabilities.device.is_input == is_input
+                                                })
+                                                .map(|capabilities| capabilities.device.id)
+                                                .collect()
+                                        };
+                                        let config = app.lock().await.config().clone();
+                                        terminal_ui.open_settings(SettingsForm::new(
+                                            config,
+                                            names(true),
+                                            names(false),
+                                            bind_choices(),
+                                        ));
                                         break;
                                     }
+                                    crossterm::event::KeyCode::Char('4')
+                                    | crossterm::event::KeyCode::Esc => {
+                                        // Cancel
+                                        terminal_ui.close_text_input();
+                                        break;
+                                    }
+                                    _ => {}
                                 }
                             }
-                            ui::MenuAction::TestSession => {
-                                // Directly create a test session
-                                if let Ok(session) = app_lock.create_test_session().await {
-                                    terminal_ui.update_participants(session.participants.clone());
-                                    terminal_ui
-                                        .set_connection_link(Some("Test Session".to_string()));
-                                    terminal_ui.update_menu_items(true);
-                                    terminal_ui.show_notification(
-                                        "Test session created with 3 simulated participants"
-                                            .to_string(),
-                                        Duration::from_secs(2),
-                                    );
-                                }
+
+                            // Render UI during input
+                            terminal_ui.render(&*app.lock().await)?;
+
+                            // Check if text input is still active
+                            if !terminal_ui.is_text_input_active() {
+                                break;
                             }
-                            ui::MenuAction::Quit => break,
                         }
                     }
+                    ui::MenuAction::ApplySettings(config) => {
+                        apply_settings(&mut app_lock, *config, &audio_manager, &mut terminal_ui)
+                            .await;
+                    }
+                    ui::MenuAction::CloseSettings(config) => {
+                        // Saved with what's needed to find the devices again if renamed
+                        let mut config = *config;
+                        config.remember_devices(&probe_devices());
+                        apply_settings(&mut app_lock, config, &audio_manager, &mut terminal_ui)
+                            .await;
+                        let path = app::config::default_path();
+                        match app_lock.save_config(&path) {
+                            Ok(()) => terminal_ui.show_notification(
+                                t_args("notify.settings_saved", &[&path.display()]),
+                                Duration::from_secs(2),
+                            ),
+                            Err(e) => terminal_ui.show_warning(
+                                t_args("error.settings_save_failed", &[&e]),
+                                Duration::from_secs(4),
+                            ),
+                        }
+                    }
+                    ui::MenuAction::TestIceServers(servers) => {
+                        let (tx, rx) = mpsc::unbounded_channel();
+                        for server in servers.iter().cloned() {
+                            let tx = tx.clone();
+                            topology::spawn(Plane::Control, "ice-server-test", async move {
+                                let result = network::test_ice_server(&server).await;
+                                let _ = tx.send((server, result));
+                            });
+                        }
+                        ice_results = Some(rx);
+                    }
+                    ui::MenuAction::SpatialPreview(direction) => {
+                        if let Some(task) = spatial_preview.take() {
+                            task.abort();
+                        }
+                        let blocks = match direction {
+                            Some(direction) => Some(
+                                audio_manager
+                                    .lock()
+                                    .await
+                                    .spatial_preview()
+                                    .walk_to(direction),
+                            ),
+                            None => None,
+                        };
+                        if let Some(blocks) = blocks {
+                            let audio_manager = Arc::clone(&audio_manager);
+                            spatial_preview = Some(topology::spawn(
+                                Plane::Media,
+                                "spatial-preview",
+                                async move {
+                                    let mut interval = tokio::time::interval(audio::PREVIEW_BLOCK);
+                                    for block in blocks {
+                                        interval.tick().await;
+                                        audio_manager.lock().await.play_spatial_preview(&block);
+                                    }
+                                },
+                            ));
+                        }
+                    }
+                    ui::MenuAction::TestSession => {
+                        // Directly create a test session
+                        if let Ok(session) = app_lock.create_test_session().await {
+                            terminal_ui.update_participants(session.participants.clone());
+                            terminal_ui.set_connection_link(Some("Test Session".to_string()));
+                            terminal_ui.update_menu_items(true);
+                            terminal_ui.show_notification(
+                                t("notify.test_session_created").to_string(),
+                                Duration::from_secs(2),
+                            );
+                        }
+                    }
+                    ui::MenuAction::OpenLog => {
+                        // Already handled in handle_menu_action
+                    }
+                    ui::MenuAction::Quit => break,
+                }
+            }
+        }
+
+        // Hand our call to an instance started after us, then make way for it
+        if let Some(request) = takeovers
+            .as_mut()
+            .and_then(|requests| requests.try_recv().ok())
+        {
+            let call = match app.lock().await.export_handoff().await {
+                Ok(call) => Some(call),
+                Err(SessionError::NoActiveSession) => None,
+                Err(e) => {
+                    log::warn!("Couldn't hand over the call: {}", e);
+                    None
+                }
+            };
+            if let Err(e) = request.answer(call).await {
+                log::warn!("Couldn't answer the takeover: {}", e);
+            }
+            break;
+        }
+
+        // Mirror mute changes made outside the app
+        if let Some(changes) = system_mute_changes.as_mut() {
+            while let Ok(muted) = changes.try_recv() {
+                audio_manager.lock().await.set_muted(muted);
+                terminal_ui.set_muted(muted);
+            }
+        }
+
+        // Tell the user when the microphone stalls and whether it comes back
+        if let Some(events) = capture_events.as_mut() {
+            while let Ok(event) = events.try_recv() {
+                match event {
+                    WatchdogEvent::StreamStalled { .. } => terminal_ui.show_warning(
+                        t("notify.capture_stalled").to_string(),
+                        Duration::from_secs(5),
+                    ),
+                    WatchdogEvent::StreamRebuilt => terminal_ui.show_notification(
+                        t("notify.capture_recovered").to_string(),
+                        Duration::from_secs(3),
+                    ),
+                    WatchdogEvent::RebuildFailed(e) => terminal_ui.show_warning(
+                        t_args("notify.capture_failed", &[&e]),
+                        Duration::from_secs(5),
+                    ),
                 }
-                _ => {}
             }
         }
 
+        // Guide the user through a running calibration and apply its result
+        if let Some(updates) = calibration_updates.as_mut() {
+            while let Ok(update) = updates.try_recv() {
+                match update {
+                    CalibrationUpdate::Phase(CalibrationPhase::Quiet) => terminal_ui
+                        .show_notification(t("notify.calibrate_quiet").to_string(), QUIET_PHASE),
+                    CalibrationUpdate::Phase(_) => terminal_ui
+                        .show_notification(t("notify.calibrate_speak").to_string(), SPEAK_PHASE),
+                    CalibrationUpdate::Finished(Ok(calibration)) => {
+                        let mut app_lock = app.lock().await;
+                        let mut config = app_lock.config().clone();
+                        let device = calibration_device(&config).to_string();
+                        config.calibrations.insert(&device, calibration);
+                        app_lock.update_config(config);
+                        audio_manager
+                            .lock()
+                            .await
+                            .set_calibration(Some(calibration));
+                        terminal_ui.show_notification(
+                            t_args(
+                                "notify.calibrated",
+                                &[&format!("{:.1}", calibration.input_gain)],
+                            ),
+                            Duration::from_secs(3),
+                        );
+                    }
+                    CalibrationUpdate::Finished(Err(e)) => terminal_ui.show_notification(
+                        t_args("notify.calibration_failed", &[&e]),
+                        Duration::from_secs(3),
+                    ),
+                }
+            }
+            if updates.is_closed() && updates.is_empty() {
+                calibration_updates = None;
+            }
+        }
+
+        if let Some(results) = ice_results.as_mut() {
+            while let Ok((server, result)) = results.try_recv() {
+                terminal_ui.set_ice_result(server, result);
+            }
+            if results.is_closed() && results.is_empty() {
+                ice_results = None;
+            }
+        }
+
+        // Shorten the shareable link whenever it or the shortener changes
+        let shortener_url = app.lock().await.config().link_shortener_url.clone();
+        if let (Some(url), Some(link)) = (shortener_url, terminal_ui.shareable_link()) {
+            let wanted = (url, link);
+            if shortening.as_ref() != Some(&wanted) {
+                let (url, link) = wanted.clone();
+                let tx = short_links_tx.clone();
+                topology::spawn(Plane::Control, "link-shortener", async move {
+                    let short = shorten_or_full(&HttpShortener::new(&url), &link).await;
+                    let _ = tx.send((link, short));
+                });
+                shortening = Some(wanted);
+            }
+        }
+        while let Ok((link, short)) = short_links.try_recv() {
+            terminal_ui.set_short_link(link, short);
+        }
+
         // Update audio-related data every 200ms
         if last_audio_update.elapsed() >= audio_update_rate {
+            // After the machine slept, hold the connections while the audio
+            // devices are reopened, then reconnect to everyone
+            if let Some(slept) = sleep_detector.check() {
+                if let Some(session_manager) = &app.lock().await.session_manager {
+                    session_manager.suspend_connections();
+                }
+                let reopened = audio_manager
+                    .lock()
+                    .await
+                    .reopen_devices("default-session".to_string())
+                    .await;
+                if let Err(e) = reopened {
+                    log::warn!("Couldn't reopen audio devices after sleep: {}", e);
+                }
+                let app_lock = app.lock().await;
+                if let Some(session_manager) = &app_lock.session_manager {
+                    session_manager.wake_connections(slept).await;
+                }
+                terminal_ui.show_notification(
+                    t_args("notify.woke", &[&format_clock(slept)]),
+                    Duration::from_secs(3),
+                );
+            }
+
+            // Update our presence and tell peers when it changes
+            let last_voice_activity = audio_manager.lock().await.last_voice_activity();
+            if let Some(at) = last_voice_activity {
+                presence.record_voice_activity(at);
+            }
+            if let Some(status) = presence.poll() {
+                terminal_ui.set_presence("Me", status);
+                let app_lock = app.lock().await;
+                if let Some(session_manager) = &app_lock.session_manager {
+                    // Not being in a session is fine; peers get our status when we next change
+                    let _ = session_manager.broadcast_presence(status).await;
+                }
+            }
+
+            if terminal_ui.showing_host_actions() {
+                let app_lock = app.lock().await;
+                if let Some(session_manager) = &app_lock.session_manager {
+                    terminal_ui.set_host_actions(session_manager.host_actions());
+                }
+            }
+
+            // Keep the inspector's view of its peer's connection current
+            if let Some(peer) = terminal_ui.inspected_peer() {
+                let app_lock = app.lock().await;
+                let details = match &app_lock.session_manager {
+                    Some(session_manager) => session_manager.inspect_peer(&peer).await,
+                    None => None,
+                };
+                let jitter = audio_manager.lock().await.jitter_stats(&peer);
+                terminal_ui.set_inspector_details(details, jitter);
+            }
+
             // Check if we have an active connection
             let has_connection = app_connection.has_active_connection().await;
 
+            // Keep the machine awake for as long as we're in a room
+            keep_awake.set_active(has_connection && app.lock().await.config().keep_awake);
+
+            // The timeline runs from entering the room to leaving it
+            if has_connection != was_connected {
+                if has_connection {
+                    timeline.start();
+                } else {
+                    timeline.finish();
+                }
+                was_connected = has_connection;
+            }
+            if terminal_ui.showing_timeline() {
+                terminal_ui.set_timeline(timeline.entries());
+            }
+
             if has_connection {
                 // Get current session participants from the app instance
                 let current_session_participants =
@@ -438,83 +1876,97 @@ async fn run_tui_with_audio(
 
                 if !current_session_participants.is_empty() {
                     // Update participants list with session participants
-                    let mut participants_guard = participants.lock().unwrap();
-
-                    // Start with the current user
-                    let mut updated_participants = vec![participants_guard[0].clone()];
+                    let updated_participants = {
+                        let mut participants_guard = participants.lock().unwrap();
+
+                        // Start with the current user, known by the peer id the room knows us by
+                        let mut me = participants_guard[0].clone();
+                        if let Some(session_me) = current_session_participants
+                            .iter()
+                            .find(|participant| participant.name == "Me")
+                        {
+                            me.id = session_me.id.clone();
+                        }
+                        let mut updated_participants = vec![me];
 
-                    // Add other participants from the session
-                    for participant in &current_session_participants {
-                        if participant.name != "Me" {
-                            updated_participants.push(participant.clone());
+                        // Add other participants from the session
+                        for participant in &current_session_participants {
+                            if participant.name != "Me" {
+                                updated_participants.push(participant.clone());
+                            }
                         }
-                    }
 
-                    // Update the shared participants list
-                    *participants_guard = updated_participants.clone();
+                        // Update the shared participants list
+                        *participants_guard = updated_participants.clone();
+                        updated_participants
+                    };
 
                     // Let the audio manager know about updated participant positions
-                    drop(participants_guard); // Release lock before taking another one
-                    if let Ok(mut audio_manager_guard) = audio_manager.lock() {
-                        if let Err(e) = audio_manager_guard.update_positions(&updated_participants)
-                        {
-                            let now = std::time::Instant::now();
-                            if now.duration_since(last_error_time) > error_throttle_duration {
-                                eprintln!("Error updating participant positions: {}", e);
-                                last_error_time = now;
-                            }
+                    let mut audio_manager_guard = audio_manager.lock().await;
+                    if let Err(e) = audio_manager_guard.update_positions(&updated_participants) {
+                        let now = std::time::Instant::now();
+                        if now.duration_since(last_error_time) > error_throttle_duration {
+                            eprintln!("Error updating participant positions: {}", e);
+                            last_error_time = now;
                         }
+                    }
 
-                        // Ensure each participant has an audio stream
-                        for participant in &updated_participants {
-                            if participant.name != "Me" {
-                                if let Err(e) =
-                                    audio_manager_guard.add_participant_stream(&participant.name)
-                                {
-                                    // Ignore errors if the stream already exists
-                                    if !e.to_string().contains("already exists") {
-                                        let now = std::time::Instant::now();
-                                        if now.duration_since(last_error_time)
-                                            > error_throttle_duration
-                                        {
-                                            eprintln!("Error adding participant stream: {}", e);
-                                            last_error_time = now;
-                                        }
+                    // Ensure each participant has an audio stream
+                    for participant in &updated_participants {
+                        if participant.name != "Me" {
+                            if let Err(e) =
+                                audio_manager_guard.add_participant_stream(&participant.name)
+                            {
+                                // Ignore errors if the stream already exists
+                                if !e.to_string().contains("already exists") {
+                                    let now = std::time::Instant::now();
+                                    if now.duration_since(last_error_time) > error_throttle_duration
+                                    {
+                                        eprintln!("Error adding participant stream: {}", e);
+                                        last_error_time = now;
                                     }
                                 }
                             }
                         }
+                    }
 
-                        // Check if we're in a test session and get test audio data
-                        let app_lock = app.lock().unwrap();
-                        let is_test_session = app_lock
-                            .current_session()
-                            .map(|s| s.connection_link == "test-session")
-                            .unwrap_or(false);
-
-                        if is_test_session {
-                            // Process audio for each test participant
-                            for (i, participant) in updated_participants.iter().enumerate().skip(1)
-                            {
-                                if i <= 3 {
-                                    // We have 3 test participants
-                                    // Get the test audio data for this participant
-                                    let test_audio = app_lock.get_test_participant_audio(i - 1);
-
-                                    if !test_audio.is_empty() {
-                                        // Process the audio data and add it to the participant's stream
-                                        if let Err(e) = audio_manager_guard
-                                            .process_remote_audio(&participant.name, &test_audio)
-                                            .await
+                    // Check if we're in a test session and get test audio data
+                    let app_lock = app.lock().await;
+                    let is_test_session = app_lock
+                        .current_session()
+                        .map(|s| s.connection_link == "test-session")
+                        .unwrap_or(false);
+
+                    if is_test_session {
+                        // Process audio for each test participant
+                        for (i, participant) in updated_participants.iter().enumerate().skip(1) {
+                            if i <= 3 {
+                                // We have 3 test participants
+                                // Get the test audio data for this participant
+                                let test_audio = app_lock.get_test_participant_audio(i - 1);
+
+                                if !test_audio.is_empty() {
+                                    // Test audio is generated as mono at the default rate
+                                    let test_config = AudioConfig::default();
+                                    let frame = AudioFrame::new(
+                                        test_audio,
+                                        test_config.sample_rate,
+                                        test_config.channels,
+                                    )
+                                    .with_peer_id(&participant.name);
+
+                                    // Process the audio data and add it to the participant's stream
+                                    if let Err(e) = audio_manager_guard
+                                        .process_remote_audio(&participant.name, &frame)
+                                        .await
+                                    {
+                                        // Log errors but don't interrupt
+                                        let now = std::time::Instant::now();
+                                        if now.duration_since(last_error_time)
+                                            > error_throttle_duration
                                         {
-                                            // Log errors but don't interrupt
-                                            let now = std::time::Instant::now();
-                                            if now.duration_since(last_error_time)
-                                                > error_throttle_duration
-                                            {
-                                                eprintln!("Error processing test audio: {}", e);
-                                                last_error_time = now;
-                                            }
+                                            eprintln!("Error processing test audio: {}", e);
+                                            last_error_time = now;
                                         }
                                     }
                                 }
@@ -527,25 +1979,278 @@ async fn run_tui_with_audio(
             last_audio_update = std::time::Instant::now();
         }
 
+        // Queue any session events that arrived since the last iteration
+        if let Some(events) = session_events.as_mut() {
+            while let Ok(event) = events.try_recv() {
+                timeline.record(&event);
+                event_coalescer.push(event);
+            }
+        }
+
         // Update UI if it's time for a frame
         if last_tick.elapsed() >= tick_rate {
+            // Hosts reseat the room when people come and go
+            let mut roster_changed = false;
+            let mut join_requested = false;
+            let mut positions_changed = false;
+            for event in event_coalescer.flush() {
+                match event {
+                    SessionEvent::PeerJoined { name, .. } => {
+                        roster_changed = true;
+                        if !terminal_ui.do_not_disturb() {
+                            audio_manager
+                                .lock()
+                                .await
+                                .play_ui_sound(UiSound::PeerJoined);
+                        }
+                        terminal_ui.show_notification(
+                            t_args("notify.peer_joined", &[&name]),
+                            Duration::from_secs(2),
+                        )
+                    }
+                    SessionEvent::PeerLeft { .. } => {
+                        roster_changed = true;
+                        if !terminal_ui.do_not_disturb() {
+                            audio_manager.lock().await.play_ui_sound(UiSound::PeerLeft);
+                        }
+                        terminal_ui.show_notification(
+                            t("notify.peer_left").to_string(),
+                            Duration::from_secs(2),
+                        )
+                    }
+                    SessionEvent::StreamEnded { name, .. } => {
+                        // Their audio has played out; drop what we kept for them
+                        let _ = audio_manager.lock().await.remove_participant_stream(&name);
+                    }
+                    SessionEvent::HostChanged { .. } => terminal_ui.show_notification(
+                        t("notify.now_host").to_string(),
+                        Duration::from_secs(2),
+                    ),
+                    SessionEvent::PresenceChanged { name, status, .. } => {
+                        terminal_ui.set_presence(&name, status)
+                    }
+                    SessionEvent::LayoutChanged { layout, positions } => {
+                        if let Some(manager) = app.lock().await.session_manager.as_mut() {
+                            manager.apply_layout(layout, &positions);
+                        }
+                        terminal_ui.show_notification(
+                            t_args("notify.layout", &[&layout_name(layout)]),
+                            Duration::from_secs(2),
+                        );
+                    }
+                    SessionEvent::TimeWarning { remaining_secs } => terminal_ui.show_notification(
+                        t_args("notify.time_warning", &[&(remaining_secs / 60).to_string()]),
+                        Duration::from_secs(5),
+                    ),
+                    SessionEvent::TimeLimitReached => {
+                        let mut app_lock = app.lock().await;
+                        if let Err(e) = app_lock.leave_session().await {
+                            log::warn!("Failed to leave session at its time limit: {}", e);
+                        }
+                        terminal_ui.set_connection_link(None);
+                        terminal_ui.update_participants(vec![]);
+                        terminal_ui.update_menu_items(false);
+                        terminal_ui.show_notification(
+                            t("notify.time_limit").to_string(),
+                            Duration::from_secs(3),
+                        );
+                    }
+                    SessionEvent::PossibleTampering { .. } => terminal_ui
+                        .show_warning(t("notify.tampering").to_string(), Duration::from_secs(10)),
+                    SessionEvent::ConnectionIssue { name, issue, .. } => match issue {
+                        ConnectionIssue::OneWayAudio => terminal_ui.show_warning(
+                            t_args("notify.one_way_audio", &[&name]),
+                            Duration::from_secs(15),
+                        ),
+                    },
+                    SessionEvent::HandshakeTimedOut { address, .. } => terminal_ui.show_warning(
+                        t_args("notify.handshake_timeout", &[&address]),
+                        Duration::from_secs(8),
+                    ),
+                    // The first attempt needs no mention; only retries do
+                    SessionEvent::ConnectionAttempt {
+                        name,
+                        attempt,
+                        attempts,
+                        ..
+                    } if attempt > 1 => terminal_ui.show_notification(
+                        t_args("notify.connection_retry", &[&name, &attempt, &attempts]),
+                        Duration::from_secs(3),
+                    ),
+                    SessionEvent::MeetingModeChanged { enabled } => {
+                        let is_host = app
+                            .lock()
+                            .await
+                            .current_session()
+                            .is_some_and(|session| session.is_host);
+                        let host_name = if is_host {
+                            "Me".to_string()
+                        } else {
+                            app.lock()
+                                .await
+                                .session_manager
+                                .as_ref()
+                                .and_then(|manager| manager.host_name())
+                                .unwrap_or_else(|| "Host".to_string())
+                        };
+                        terminal_ui.set_meeting_mode(enabled, &host_name);
+                        let message = match (enabled, is_host) {
+                            (false, _) => "notify.meeting_off",
+                            (true, true) => "notify.meeting_on_host",
+                            (true, false) => "notify.meeting_on",
+                        };
+                        terminal_ui
+                            .show_notification(t(message).to_string(), Duration::from_secs(3));
+                    }
+                    SessionEvent::HandRaised { name, raised, .. } => {
+                        terminal_ui.set_hand_raised(&name, raised);
+                        if raised && name != "Me" {
+                            terminal_ui.show_notification(
+                                t_args("notify.hand_raised", &[&name]),
+                                Duration::from_secs(3),
+                            );
+                        }
+                    }
+                    SessionEvent::SpeakGranted { name, .. } => {
+                        terminal_ui.grant_speaker(&name);
+                        let message = if name == "Me" {
+                            t("notify.granted_self").to_string()
+                        } else {
+                            t_args("notify.granted", &[&name])
+                        };
+                        terminal_ui.show_notification(message, Duration::from_secs(2));
+                    }
+                    SessionEvent::RecordingChanged {
+                        name, recording, ..
+                    } => {
+                        let recorders = app
+                            .lock()
+                            .await
+                            .session_manager
+                            .as_ref()
+                            .map(|manager| manager.recorders())
+                            .unwrap_or_default();
+                        terminal_ui.set_recorders(recorders);
+                        if name != "Me" {
+                            let message = if recording {
+                                "notify.recording_started"
+                            } else {
+                                "notify.recording_stopped"
+                            };
+                            terminal_ui.show_notification(
+                                t_args(message, &[&name]),
+                                Duration::from_secs(3),
+                            );
+                        }
+                    }
+                    // Announced once for a burst of requests, with the line they joined
+                    SessionEvent::JoinRequested { .. } => join_requested = true,
+                    SessionEvent::JoinQueueUpdated { queue } => terminal_ui.set_join_queue(queue),
+                    SessionEvent::AudioLevel { peer_id, level } => {
+                        terminal_ui.set_audio_level(&peer_id, level)
+                    }
+                    // Heard from the new seat at once, not on the next participant refresh
+                    SessionEvent::PositionChanged { .. } => positions_changed = true,
+                    SessionEvent::ConnectionAttempt { .. } => {}
+                }
+            }
+            if join_requested && !terminal_ui.do_not_disturb() {
+                audio_manager
+                    .lock()
+                    .await
+                    .play_ui_sound(UiSound::JoinRequest);
+            }
+            if positions_changed {
+                let session = app.lock().await.current_session();
+                if let Some(session) = session {
+                    let mut manager = audio_manager.lock().await;
+                    if let Err(e) = manager.update_positions(&session.participants) {
+                        log::debug!("Couldn't move participants to their seats: {}", e);
+                    }
+                }
+            }
+            if roster_changed {
+                if let Some(manager) = app.lock().await.session_manager.as_mut() {
+                    if let Err(e) = manager.arrange_participants().await {
+                        log::debug!("Couldn't reseat the room: {}", e);
+                    }
+                }
+            }
+
+            // Take in peers joining a room we host, and answer their requests
+            if let Some(manager) = app.lock().await.session_manager.as_mut() {
+                manager.accept_joins().await;
+            }
+
             // Update app state
             {
-                let app_lock = app.lock().unwrap();
+                let mut app_lock = app.lock().await;
 
                 // Update participants if in a session
-                if let Some(session) = app_lock.current_session() {
+                let session = app_lock.current_session();
+                if let Some(session) = &session {
                     terminal_ui.update_participants(session.participants.clone());
                 }
 
-                // Get the latest audio data for visualization from the audio manager
-                let audio_data = {
-                    if let Ok(audio_manager) = audio_manager.lock() {
-                        audio_manager.get_raw_capture_data()
-                    } else {
-                        Vec::new()
+                // A soloed participant who left can't keep everyone else silent
+                {
+                    let mut manager = audio_manager.lock().await;
+                    let present = |name: &str| {
+                        session.as_ref().is_some_and(|session| {
+                            session.participants.iter().any(|p| p.name == name)
+                        })
+                    };
+                    if manager.solo().is_some_and(|name| !present(name)) {
+                        manager.set_solo(None);
+                        terminal_ui.set_solo(None);
                     }
-                };
+                }
+
+                terminal_ui.set_session_timer(
+                    app_lock
+                        .session_manager
+                        .as_ref()
+                        .and_then(|manager| manager.session_timer())
+                        .cloned(),
+                );
+                if let Some(manager) = app_lock.session_manager.as_ref() {
+                    terminal_ui.set_bandwidth(manager.bandwidth_usage());
+                }
+                if last_stats_sample.elapsed() >= network::STATS_INTERVAL {
+                    last_stats_sample = std::time::Instant::now();
+                    let stats = match app_lock.session_manager.as_ref() {
+                        Some(manager) if session.is_some() => Some(manager.network_stats().await),
+                        _ => None,
+                    };
+                    terminal_ui.set_network_stats(stats);
+
+                    // Keep what's shared in sync with the rest of the room
+                    if let Some(manager) = app_lock.session_manager.as_mut() {
+                        manager.update_synced_playout().await;
+                        audio_manager
+                            .lock()
+                            .await
+                            .set_synced_playouts(manager.synced_playouts());
+                    }
+                }
+
+                // Stages given up because the machine can't keep up with the audio
+                let degradations = audio_manager.lock().await.take_degradations();
+                for step in degradations {
+                    let message = match step {
+                        Degradation::SimplePanning => t("notify.simple_panning"),
+                        Degradation::LowCodecComplexity => {
+                            if let Some(manager) = app_lock.session_manager.as_mut() {
+                                manager.set_low_complexity(true);
+                            }
+                            t("notify.low_complexity")
+                        }
+                    };
+                    terminal_ui.show_warning(message.to_string(), Duration::from_secs(5));
+                }
+
+                // Get the latest audio data for visualization from the audio manager
+                let audio_data = audio_manager.lock().await.get_raw_capture_data();
 
                 // Only update if we have data
                 if !audio_data.is_empty() {
@@ -554,7 +2259,7 @@ async fn run_tui_with_audio(
             }
 
             // Render UI
-            terminal_ui.render(&app.lock().unwrap())?;
+            terminal_ui.render(&*app.lock().await)?;
 
             last_tick = std::time::Instant::now();
         }
@@ -0,0 +1,319 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::audio::{AudioConfig, AudioFrame};
+
+/// Span usage is averaged over
+pub const METER_WINDOW: Duration = Duration::from_secs(2);
+
+/// Share of a cap above which the link counts as saturated
+pub const SATURATION: f64 = 0.9;
+
+/// Share of a cap under which audio quality is raised again
+const HEADROOM: f64 = 0.6;
+
+/// Shortest time between quality changes, so a burst doesn't cause flapping
+const STEP_HOLD: Duration = Duration::from_secs(2);
+
+/// Sample rate divisors tried, best quality first
+const RATE_DIVISORS: [u32; 4] = [1, 2, 3, 6];
+
+/// Lowest sample rate audio is reduced to, still fine for speech
+const MIN_SAMPLE_RATE: u32 = 8000;
+
+/// Upload and download limits for a room, in kilobits per second
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BandwidthCaps {
+    pub upload_kbps: Option<u32>,
+    pub download_kbps: Option<u32>,
+}
+
+/// Converts kilobits per second to bytes per second
+pub fn kbps_to_bytes(kbps: u32) -> usize {
+    kbps as usize * 1000 / 8
+}
+
+/// Bytes moved over the last [`METER_WINDOW`]
+#[derive(Debug, Clone, Default)]
+pub struct BandwidthMeter {
+    samples: VecDeque<(Instant, usize)>,
+    total: usize,
+}
+
+impl BandwidthMeter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, bytes: usize, now: Instant) {
+        self.prune(now);
+        self.samples.push_back((now, bytes));
+        self.total += bytes;
+    }
+
+    /// Average rate over the window, in bits per second
+    pub fn bits_per_second(&mut self, now: Instant) -> u64 {
+        self.prune(now);
+        (self.total as u64 * 8 * 1000) / METER_WINDOW.as_millis() as u64
+    }
+
+    fn prune(&mut self, now: Instant) {
+        while let Some(&(at, bytes)) = self.samples.front() {
+            if now.saturating_duration_since(at) < METER_WINDOW {
+                break;
+            }
+            self.samples.pop_front();
+            self.total -= bytes;
+        }
+    }
+}
+
+/// Lowers the audio sample rate while sending uses too much of a cap
+///
+/// Peers conform incoming audio to their own rate, so frames can be sent
+/// at any rate. Quality drops a step as soon as usage nears the cap, and
+/// rises again only once there's clear headroom.
+#[derive(Debug, Clone, Default)]
+pub struct BitrateController {
+    step: usize,
+    last_change: Option<Instant>,
+}
+
+impl BitrateController {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Steps quality up or down given usage and the cap, both in bits per second
+    pub fn adapt(&mut self, usage_bps: u64, cap_bps: Option<u64>, now: Instant) {
+        let held = self
+            .last_change
+            .is_some_and(|at| now.saturating_duration_since(at) < STEP_HOLD);
+        let Some(cap) = cap_bps else {
+            self.step = 0;
+            return;
+        };
+        if held {
+            return;
+        }
+
+        let usage = usage_bps as f64;
+        if usage > cap as f64 * SATURATION && self.step + 1 < RATE_DIVISORS.len() {
+            self.step += 1;
+            self.last_change = Some(now);
+        } else if usage < cap as f64 * HEADROOM && self.step > 0 {
+            self.step -= 1;
+            self.last_change = Some(now);
+        }
+    }
+
+    /// Sample rate divisor currently applied
+    pub fn divisor(&self) -> u32 {
+        RATE_DIVISORS[self.step]
+    }
+
+    /// Reduces a frame to the current quality, downmixed to mono below full quality
+    pub fn shape(&self, frame: &AudioFrame) -> AudioFrame {
+        self.shape_with(self.divisor(), frame)
+    }
+
+    fn shape_with(&self, divisor: u32, frame: &AudioFrame) -> AudioFrame {
+        if divisor <= 1 {
+            return frame.clone();
+        }
+        let target = AudioConfig {
+            sample_rate: (frame.sample_rate / divisor).max(MIN_SAMPLE_RATE.min(frame.sample_rate)),
+            channels: 1,
+        };
+        target.conform(frame.clone())
+    }
+}
+
+/// Upload and download usage for a room, against its caps
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BandwidthUsage {
+    pub upload_bps: u64,
+    pub download_bps: u64,
+    pub caps: BandwidthCaps,
+}
+
+impl BandwidthUsage {
+    pub fn upload_saturated(&self) -> bool {
+        saturated(self.upload_bps, self.caps.upload_kbps)
+    }
+
+    pub fn download_saturated(&self) -> bool {
+        saturated(self.download_bps, self.caps.download_kbps)
+    }
+}
+
+fn saturated(usage_bps: u64, cap_kbps: Option<u32>) -> bool {
+    cap_kbps.is_some_and(|cap| usage_bps as f64 >= cap as f64 * 1000.0 * SATURATION)
+}
+
+/// What one peer lets us send it, and how much we have
+#[derive(Debug, Clone, Default)]
+pub struct PeerBandwidth {
+    limit_kbps: Option<u32>,
+    sent: BandwidthMeter,
+    controller: BitrateController,
+}
+
+impl PeerBandwidth {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the most the peer wants to receive; `None` or 0 for no limit
+    pub fn set_limit(&mut self, kbps: Option<u32>) {
+        self.limit_kbps = kbps.filter(|&kbps| kbps > 0);
+    }
+
+    pub fn limit(&self) -> Option<u32> {
+        self.limit_kbps
+    }
+
+    pub fn record_sent(&mut self, bytes: usize, now: Instant) {
+        self.sent.record(bytes, now);
+    }
+
+    /// Sample rate divisor that keeps what we send the peer under its limit
+    pub fn divisor(&mut self, now: Instant) -> u32 {
+        let usage = self.sent.bits_per_second(now);
+        let cap = self.limit_kbps.map(|kbps| kbps as u64 * 1000);
+        self.controller.adapt(usage, cap, now);
+        self.controller.divisor()
+    }
+}
+
+#[derive(Debug, Default)]
+struct MonitorState {
+    caps: BandwidthCaps,
+    upload: BandwidthMeter,
+    download: BandwidthMeter,
+    controller: BitrateController,
+}
+
+/// Meters a room's traffic across all its connections and keeps upload under the cap
+///
+/// Cloning gives another handle to the same monitor.
+#[derive(Debug, Clone, Default)]
+pub struct BandwidthMonitor {
+    state: Arc<Mutex<MonitorState>>,
+}
+
+impl BandwidthMonitor {
+    pub fn new(caps: BandwidthCaps) -> Self {
+        let monitor = Self::default();
+        monitor.set_caps(caps);
+        monitor
+    }
+
+    pub fn set_caps(&self, caps: BandwidthCaps) {
+        self.state.lock().unwrap().caps = caps;
+    }
+
+    pub fn caps(&self) -> BandwidthCaps {
+        self.state.lock().unwrap().caps
+    }
+
+    pub fn record_sent(&self, bytes: usize) {
+        self.state
+            .lock()
+            .unwrap()
+            .upload
+            .record(bytes, Instant::now());
+    }
+
+    pub fn record_received(&self, bytes: usize) {
+        self.state
+            .lock()
+            .unwrap()
+            .download
+            .record(bytes, Instant::now());
+    }
+
+    pub fn usage(&self) -> BandwidthUsage {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        BandwidthUsage {
+            upload_bps: state.upload.bits_per_second(now),
+            download_bps: state.download.bits_per_second(now),
+            caps: state.caps,
+        }
+    }
+
+    /// Reduces a frame as far as the room's upload cap requires
+    ///
+    /// `peer_divisor` is the reduction a connection needs on its own, for a
+    /// peer with a download cap; the stronger of the two applies.
+    pub fn shape_audio(&self, frame: &AudioFrame, peer_divisor: u32) -> AudioFrame {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        let usage = state.upload.bits_per_second(now);
+        let cap = state.caps.upload_kbps.map(|kbps| kbps as u64 * 1000);
+        state.controller.adapt(usage, cap, now);
+        let divisor = state.controller.divisor().max(peer_divisor);
+        state.controller.shape_with(divisor, frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_meter_window() {
+        let start = Instant::now();
+        let mut meter = BandwidthMeter::new();
+        meter.record(1000, start);
+        meter.record(1000, start + Duration::from_secs(1));
+        assert_eq!(meter.bits_per_second(start + Duration::from_secs(1)), 8000);
+        // The first second has left the window
+        assert_eq!(meter.bits_per_second(start + METER_WINDOW), 4000);
+    }
+
+    #[test]
+    fn test_controller_steps_down_and_back_up() {
+        let start = Instant::now();
+        let mut controller = BitrateController::new();
+        let frame = AudioFrame::new(vec![0.1; 1920], 48000, 2);
+        assert_eq!(controller.shape(&frame).samples.len(), 1920);
+
+        controller.adapt(95_000, Some(100_000), start);
+        assert_eq!(controller.divisor(), 2);
+        let shaped = controller.shape(&frame);
+        assert_eq!((shaped.sample_rate, shaped.channels), (24000, 1));
+        assert_eq!(shaped.samples.len(), 480);
+
+        // Held, then stepped down again while still over
+        controller.adapt(95_000, Some(100_000), start + Duration::from_secs(1));
+        assert_eq!(controller.divisor(), 2);
+        controller.adapt(95_000, Some(100_000), start + STEP_HOLD);
+        assert_eq!(controller.divisor(), 3);
+
+        // Back up only with clear headroom
+        controller.adapt(70_000, Some(100_000), start + STEP_HOLD * 2);
+        assert_eq!(controller.divisor(), 3);
+        controller.adapt(50_000, Some(100_000), start + STEP_HOLD * 3);
+        assert_eq!(controller.divisor(), 2);
+
+        controller.adapt(50_000, None, start + STEP_HOLD * 3);
+        assert_eq!(controller.divisor(), 1);
+    }
+
+    #[test]
+    fn test_saturation() {
+        let usage = BandwidthUsage {
+            upload_bps: 95_000,
+            download_bps: 95_000,
+            caps: BandwidthCaps {
+                upload_kbps: Some(100),
+                download_kbps: None,
+            },
+        };
+        assert!(usage.upload_saturated());
+        assert!(!usage.download_saturated());
+    }
+}
@@ -0,0 +1,176 @@
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use super::secure_channel::{Message, PACKET_OVERHEAD};
+use crate::audio::{AudioCodec, AudioFrame};
+
+/// Most audio frames packed into one datagram
+pub const MAX_FRAMES_PER_BUNDLE: usize = 4;
+
+/// Bytes a bundle adds around its frames when serialized
+const BUNDLE_OVERHEAD: usize = 4 + 4 + 2 + 4 + 8;
+
+/// Bytes each frame in a bundle adds around its samples when serialized
+const FRAME_OVERHEAD: usize = 8 + 8 + 8;
+
+/// One frame's audio inside a bundle, with its own capture time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundledFrame {
+    pub data: Bytes,
+    pub timestamp: u64,
+    pub seq: u64,
+}
+
+/// Packs consecutive audio frames into one message to cut the packet rate
+///
+/// Each frame waits until the bundle is full, so bundling `n` frames adds
+/// up to `n - 1` frames of latency. A bundle is sent early rather than
+/// outgrow the datagram size, and frames are never bundled across a
+/// format change.
+#[derive(Debug)]
+pub struct AudioBundler {
+    frames_per_bundle: usize,
+    pending: Vec<Message>,
+    pending_bytes: usize,
+}
+
+impl AudioBundler {
+    pub fn new(frames_per_bundle: usize) -> Self {
+        Self {
+            frames_per_bundle: frames_per_bundle.clamp(1, MAX_FRAMES_PER_BUNDLE),
+            pending: Vec::new(),
+            pending_bytes: 0,
+        }
+    }
+
+    pub fn frames_per_bundle(&self) -> usize {
+        self.frames_per_bundle
+    }
+
+    /// Changes how many frames go in each bundle, from the next frame on
+    pub fn set_frames_per_bundle(&mut self, frames_per_bundle: usize) {
+        self.frames_per_bundle = frames_per_bundle.clamp(1, MAX_FRAMES_PER_BUNDLE);
+    }
+
+    /// Adds a frame, returning any messages ready to send
+    ///
+    /// `max_datagram` is the largest datagram the path carries.
+    pub fn push(&mut self, frame: &AudioFrame, max_datagram: usize) -> Vec<Message> {
+        self.push_encoded(Message::from_audio_frame(frame), max_datagram)
+    }
+
+    /// Adds a frame already encoded as an audio message
+    pub fn push_encoded(&mut self, message: Message, max_datagram: usize) -> Vec<Message> {
+        let mut ready = Vec::new();
+        let size = match &message {
+            Message::Audio { data, .. } => data.len() + FRAME_OVERHEAD,
+            _ => 0,
+        };
+
+        let same_format = self.pending.first().is_none_or(|first| {
+            matches!(
+                (first, &message),
+                (
+                    Message::Audio { sample_rate: a, channels: c, codec: e, .. },
+                    Message::Audio { sample_rate: b, channels: d, codec: f, .. },
+                ) if a == b && c == d && e == f
+            )
+        });
+        let fits = PACKET_OVERHEAD + BUNDLE_OVERHEAD + self.pending_bytes + size <= max_datagram;
+        if !(self.pending.is_empty() || (same_format && fits)) {
+            ready.extend(self.flush());
+        }
+
+        self.pending.push(message);
+        self.pending_bytes += size;
+        if self.pending.len() >= self.frames_per_bundle {
+            ready.extend(self.flush());
+        }
+        ready
+    }
+
+    /// Takes whatever frames are waiting, such as when the stream ends
+    pub fn flush(&mut self) -> Option<Message> {
+        self.pending_bytes = 0;
+        if self.pending.len() <= 1 {
+            return self.pending.pop();
+        }
+
+        let mut format = (0, 0, AudioCodec::Pcm8);
+        let frames = self
+            .pending
+            .drain(..)
+            .filter_map(|message| match message {
+                Message::Audio {
+                    data,
+                    timestamp,
+                    seq,
+                    sample_rate,
+                    channels,
+                    codec,
+                } => {
+                    format = (sample_rate, channels, codec);
+                    Some(BundledFrame {
+                        data,
+                        timestamp,
+                        seq,
+                    })
+                }
+                _ => None,
+            })
+            .collect();
+
+        Some(Message::AudioBundle {
+            sample_rate: format.0,
+            channels: format.1,
+            codec: format.2,
+            frames,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(seq: u64, len: usize) -> AudioFrame {
+        AudioFrame::new(vec![0.5; len], 48000, 1)
+            .with_seq(seq)
+            .with_capture_ts(seq * 10_000)
+    }
+
+    #[test]
+    fn test_bundles_frames_with_their_timestamps() {
+        let mut bundler = AudioBundler::new(3);
+        assert!(bundler.push(&frame(0, 240), 1400).is_empty());
+        assert!(bundler.push(&frame(1, 240), 1400).is_empty());
+
+        let ready = bundler.push(&frame(2, 240), 1400);
+        assert_eq!(ready.len(), 1);
+        let frames: Vec<Message> = ready.into_iter().flat_map(Message::unbundle).collect();
+        assert_eq!(frames.len(), 3);
+        let restored = frames[2].to_audio_frame("peer").unwrap();
+        assert_eq!((restored.seq, restored.capture_ts), (2, 20_000));
+        assert_eq!(restored.samples.len(), 240);
+    }
+
+    #[test]
+    fn test_sends_early_rather_than_outgrow_datagram() {
+        let mut bundler = AudioBundler::new(4);
+        assert!(bundler.push(&frame(0, 600), 1200).is_empty());
+
+        // A second 600 byte frame would overflow 1200 bytes
+        let ready = bundler.push(&frame(1, 600), 1200);
+        assert!(matches!(ready[..], [Message::Audio { seq: 0, .. }]));
+
+        assert!(matches!(
+            bundler.flush(),
+            Some(Message::Audio { seq: 1, .. })
+        ));
+        assert!(bundler.flush().is_none());
+
+        // Without bundling frames go straight out
+        let mut bundler = AudioBundler::new(1);
+        assert_eq!(bundler.push(&frame(0, 480), 1200).len(), 1);
+    }
+}
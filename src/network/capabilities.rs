@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+
+use crate::audio::CodecSet;
+
+/// Optional features a peer supports, exchanged once the channel is up
+///
+/// Until the peer's arrive it is assumed to support none of them, so
+/// older peers keep getting plain audio.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerCapabilities {
+    /// Plays comfort noise from descriptors sent in place of silent frames
+    pub comfort_noise: bool,
+    /// Audio codecs it decodes, besides 8-bit PCM
+    pub codecs: CodecSet,
+    /// Ratchets message keys and agrees fresh ones with [`Message::Rekey`]
+    ///
+    /// [`Message::Rekey`]: super::secure_channel::Message::Rekey
+    pub forward_secrecy: bool,
+}
+
+impl PeerCapabilities {
+    /// Everything this build supports
+    pub fn local() -> Self {
+        Self {
+            comfort_noise: true,
+            codecs: CodecSet::available(),
+            forward_secrecy: true,
+        }
+    }
+
+    /// Features both sides support
+    pub fn common(&self, other: &Self) -> Self {
+        Self {
+            comfort_noise: self.comfort_noise && other.comfort_noise,
+            codecs: self.codecs.common(&other.codecs),
+            forward_secrecy: self.forward_secrecy && other.forward_secrecy,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_common_capabilities() {
+        let local = PeerCapabilities::local();
+        assert!(local.common(&local).comfort_noise);
+        assert!(!local.common(&PeerCapabilities::default()).comfort_noise);
+        assert!(!local.common(&PeerCapabilities::default()).forward_secrecy);
+        assert_eq!(
+            local.common(&PeerCapabilities::default()).codecs,
+            CodecSet::default()
+        );
+    }
+}
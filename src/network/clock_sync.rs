@@ -0,0 +1,114 @@
+use std::collections::VecDeque;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Number of recent exchanges considered when estimating the offset
+const SAMPLE_WINDOW: usize = 8;
+
+/// Current wall-clock time in microseconds since the Unix epoch
+pub fn now_micros() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_micros() as u64
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ClockSample {
+    offset: i64,
+    round_trip: u64,
+}
+
+/// NTP-style estimate of a peer's clock offset from Ping/Pong timestamps
+#[derive(Debug, Default)]
+pub struct ClockSync {
+    samples: VecDeque<ClockSample>,
+}
+
+impl ClockSync {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records one exchange
+    ///
+    /// `ping_sent` and `pong_received` are local times; `ping_received` and
+    /// `pong_sent` are the peer's times, all in microseconds.
+    pub fn record(
+        &mut self,
+        ping_sent: u64,
+        ping_received: u64,
+        pong_sent: u64,
+        pong_received: u64,
+    ) {
+        let (t0, t1, t2, t3) = (
+            ping_sent as i64,
+            ping_received as i64,
+            pong_sent as i64,
+            pong_received as i64,
+        );
+
+        // Time spent in flight, excluding the peer's processing time
+        let round_trip = ((t3 - t0) - (t2 - t1)).max(0) as u64;
+        let offset = ((t1 - t0) + (t2 - t3)) / 2;
+
+        if self.samples.len() == SAMPLE_WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(ClockSample { offset, round_trip });
+    }
+
+    /// Estimated peer clock minus local clock, in microseconds
+    ///
+    /// Uses the exchange with the shortest round trip, as it has the least
+    /// room for asymmetric delay.
+    pub fn offset(&self) -> Option<i64> {
+        self.best_sample().map(|sample| sample.offset)
+    }
+
+    /// Round trip time of the sample the offset is based on, in microseconds
+    pub fn round_trip(&self) -> Option<u64> {
+        self.best_sample().map(|sample| sample.round_trip)
+    }
+
+    /// Converts a timestamp from the peer's clock to the local clock
+    pub fn to_local(&self, remote_micros: u64) -> Option<u64> {
+        self.offset()
+            .map(|offset| (remote_micros as i64 - offset).max(0) as u64)
+    }
+
+    fn best_sample(&self) -> Option<ClockSample> {
+        self.samples
+            .iter()
+            .min_by_key(|sample| sample.round_trip)
+            .copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offset_estimation() {
+        let mut clock = ClockSync::new();
+        assert_eq!(clock.offset(), None);
+
+        // Peer is 1000us ahead, 50us each way, 10us processing
+        clock.record(0, 1050, 1060, 110);
+        assert_eq!(clock.offset(), Some(1000));
+        assert_eq!(clock.round_trip(), Some(100));
+        assert_eq!(clock.to_local(5000), Some(4000));
+    }
+
+    #[test]
+    fn test_prefers_shortest_round_trip() {
+        let mut clock = ClockSync::new();
+
+        // A slow, asymmetric exchange skews the offset
+        clock.record(0, 1400, 1400, 500);
+        // A fast one is closer to the truth
+        clock.record(1000, 2010, 2010, 1020);
+
+        assert_eq!(clock.offset(), Some(1000));
+    }
+}
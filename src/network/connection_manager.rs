@@ -1,15 +1,57 @@
 use anyhow::{anyhow, Result};
+use bytes::BytesMut;
+use serde::{Deserialize, Serialize};
 use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
 use tokio::sync::{
     mpsc::{self, Receiver, Sender},
-    Mutex,
+    watch, Mutex,
 };
 use tokio::task::JoinHandle;
 
-use super::p2p::{establish_direct_udp_connection, ConnectionState};
-use super::secure_channel::{Message, SecureChannel};
+use super::bandwidth::{kbps_to_bytes, BandwidthMonitor, PeerBandwidth};
+use super::bundle::AudioBundler;
+use super::capabilities::PeerCapabilities;
+use super::clock_sync::{now_micros, ClockSync};
+use super::connectivity::{
+    CandidateChecks, CHECK_INTERVAL, CHECK_ROUNDS, CHECK_TIMEOUT, FAILOVER_SILENCE,
+};
+use super::inspect::{ConnectionDetails, ConnectionHistory};
+use super::interfaces::BindAddress;
+use super::keepalive::{is_keepalive, probe_binding_lifetime, AdaptiveKeepalive, KeepaliveConfig};
+use super::listener::Inbox;
+use super::migration::{path_probe, PathValidator, ADDRESS_CHECK_INTERVAL};
+use super::mtu::{mtu_probe, MtuConfig, PathMtu, PROBE_ATTEMPTS, PROBE_TIMEOUT};
+use super::p2p::{establish_udp_connection_from, local_ipv4, ConnectionState};
+use super::pacing::PacingConfig;
+use super::racing::{race_candidates, DEFAULT_STAGGER};
+use super::secure_channel::{
+    ChannelState, Keypair, Message, NonceStrategy, SecureChannel, PACKET_OVERHEAD,
+};
+use super::socket_buffers::GrantedBuffers;
+use super::stats::PacketCounts;
+use super::tamper::{CryptoFailure, CryptoStats, TamperMonitor};
+use super::NetworkError;
+use crate::audio::{
+    negotiate, AudioCodec, AudioEncoder, AudioFrame, CodecSettings, SilenceSuppressor, Transmission,
+};
+
+/// How often application pings are sent; NAT keepalives are sent separately
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Longest the listener waits on one socket, so it notices when the channel is rebound
+const RECEIVE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A connection taken off one machine to be carried on from another
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ConnectionHandoff {
+    pub channel: ChannelState,
+    /// What the peer told us it supports; it won't say again
+    pub capabilities: PeerCapabilities,
+}
 
 /// Manages connections to remote peers
 #[derive(Clone)]
@@ -29,6 +71,70 @@ pub struct ConnectionManager {
     /// Heartbeat timer
     last_heartbeat: Arc<Mutex<Instant>>,
 
+    /// NAT binding refresh cadence, and when the peer was last heard from
+    keepalive: Arc<std::sync::Mutex<AdaptiveKeepalive>>,
+    last_received: Arc<std::sync::Mutex<Instant>>,
+
+    /// Offset estimate for the remote peer's clock
+    clock: Arc<std::sync::Mutex<ClockSync>>,
+
+    /// Send pacing for the channel, if enabled
+    pacing: Option<PacingConfig>,
+
+    /// Interface or address our sockets are bound to
+    bind: BindAddress,
+
+    /// Largest datagram the path to the peer carries, as far as discovered
+    mtu: Arc<std::sync::Mutex<PathMtu>>,
+
+    /// Audio frames waiting to be sent together
+    bundler: Arc<std::sync::Mutex<AudioBundler>>,
+
+    /// Replaces silent frames with comfort noise descriptors, if enabled
+    suppressor: Arc<std::sync::Mutex<Option<SilenceSuppressor>>>,
+
+    /// Codec we'd like to send with, and the encoder for the stream
+    codec: Arc<std::sync::Mutex<CodecSettings>>,
+    encoder: Arc<std::sync::Mutex<AudioEncoder>>,
+
+    /// Features both we and the peer support, once the peer has said
+    peer_capabilities: Arc<std::sync::Mutex<PeerCapabilities>>,
+
+    /// Whether we offer to ratchet message keys
+    forward_secrecy: bool,
+
+    /// How our channels make nonces; the peer needn't make them the same way
+    nonce_strategy: NonceStrategy,
+
+    /// The room's traffic and caps, shared by all its connections
+    bandwidth: Option<BandwidthMonitor>,
+
+    /// What the peer lets us send it, and how much we have
+    peer_bandwidth: Arc<std::sync::Mutex<PeerBandwidth>>,
+
+    /// Connectivity checks across the peer's addresses, with working spares
+    checks: Arc<std::sync::Mutex<CandidateChecks>>,
+
+    /// Checks path probes from a peer whose address changed
+    path_validator: Arc<std::sync::Mutex<PathValidator>>,
+
+    /// Packets from the peer rejected by the channel's cryptography
+    tamper: Arc<std::sync::Mutex<TamperMonitor>>,
+    tamper_alert: Option<Arc<dyn Fn() + Send + Sync>>,
+
+    /// Recent handshakes, path changes and the like, for the inspector
+    history: ConnectionHistory,
+
+    /// Set while the machine sleeps: nothing is sent, and silence isn't held against the path
+    suspended: Arc<AtomicBool>,
+
+    /// Set once anything authenticated arrives from the peer
+    answered: Arc<watch::Sender<bool>>,
+
+    /// Packets routed to us from a socket shared with other peers, for a
+    /// connection the peer opened to us rather than we to it
+    inbox: Option<Arc<Mutex<Inbox>>>,
+
     /// Message queue for outgoing messages
     message_tx: Sender<Message>,
     message_rx: Arc<Mutex<Option<Receiver<Message>>>>,
@@ -55,31 +161,460 @@ impl ConnectionManager {
             channel: Arc::new(Mutex::new(None)),
             state: Arc::new(Mutex::new(ConnectionState::Disconnected)),
             last_heartbeat: Arc::new(Mutex::new(Instant::now())),
+            keepalive: Arc::new(std::sync::Mutex::new(AdaptiveKeepalive::for_peer(
+                SocketAddr::new(remote_ip, remote_port),
+                KeepaliveConfig::default(),
+            ))),
+            last_received: Arc::new(std::sync::Mutex::new(Instant::now())),
+            clock: Arc::new(std::sync::Mutex::new(ClockSync::new())),
+            pacing: Some(PacingConfig::default()),
+            bind: BindAddress::Auto,
+            mtu: Arc::new(std::sync::Mutex::new(PathMtu::new(MtuConfig::default()))),
+            bundler: Arc::new(std::sync::Mutex::new(AudioBundler::new(1))),
+            suppressor: Arc::new(std::sync::Mutex::new(None)),
+            codec: Arc::new(std::sync::Mutex::new(CodecSettings::default())),
+            encoder: Arc::new(std::sync::Mutex::new(AudioEncoder::new(
+                CodecSettings::default().bitrate_kbps,
+            ))),
+            peer_capabilities: Arc::new(std::sync::Mutex::new(PeerCapabilities::default())),
+            forward_secrecy: true,
+            nonce_strategy: NonceStrategy::default(),
+            bandwidth: None,
+            peer_bandwidth: Arc::new(std::sync::Mutex::new(PeerBandwidth::new())),
+            checks: Arc::new(std::sync::Mutex::new(CandidateChecks::new(&[
+                SocketAddr::new(remote_ip, remote_port),
+            ]))),
+            path_validator: Arc::new(std::sync::Mutex::new(PathValidator::new())),
+            tamper: Arc::new(std::sync::Mutex::new(TamperMonitor::new())),
+            tamper_alert: None,
+            history: ConnectionHistory::new(),
+            suspended: Arc::new(AtomicBool::new(false)),
+            answered: Arc::new(watch::channel(false).0),
+            inbox: None,
             message_tx: tx,
             message_rx: Arc::new(Mutex::new(Some(rx))),
             tasks: Arc::new(Mutex::new(Vec::new())),
         }
     }
 
+    /// Sets send pacing for the channel, or `None` to send immediately
+    ///
+    /// Takes effect on the next connection.
+    pub fn with_pacing(mut self, pacing: Option<PacingConfig>) -> Self {
+        self.pacing = pacing;
+        self
+    }
+
+    /// Binds the connection's sockets to an interface or address instead of any
+    ///
+    /// Takes effect on the next connection.
+    pub fn with_bind_address(mut self, bind: BindAddress) -> Self {
+        self.bind = bind;
+        self
+    }
+
+    /// Sets how the largest datagram sent to the peer is chosen
+    ///
+    /// Takes effect on the next connection.
+    pub fn with_mtu(self, config: MtuConfig) -> Self {
+        *self.mtu.lock().unwrap() = PathMtu::new(config);
+        self
+    }
+
+    /// Packs up to `frames` audio frames into each datagram
+    ///
+    /// Fewer packets for up to `frames - 1` frames of added latency; 1 sends
+    /// every frame on its own.
+    pub fn with_audio_bundling(self, frames: usize) -> Self {
+        *self.bundler.lock().unwrap() = AudioBundler::new(frames);
+        self
+    }
+
+    /// Changes how many audio frames are packed into each datagram, keeping
+    /// any waiting to go
+    pub fn set_audio_bundling(&self, frames: usize) {
+        self.bundler.lock().unwrap().set_frames_per_bundle(frames);
+    }
+
+    /// Sends comfort noise descriptors instead of silent frames, if the peer supports them
+    pub fn with_silence_suppression(self, enabled: bool) -> Self {
+        *self.suppressor.lock().unwrap() = enabled.then(SilenceSuppressor::new);
+        self
+    }
+
+    /// Ratchets message keys, agreeing fresh ones every few minutes, if the peer supports it
+    ///
+    /// Takes effect on the next connection.
+    pub fn with_forward_secrecy(mut self, enabled: bool) -> Self {
+        self.forward_secrecy = enabled;
+        self
+    }
+
+    /// Sets how nonces are made for the messages we send
+    ///
+    /// Takes effect on the next connection.
+    pub fn with_nonce_strategy(mut self, strategy: NonceStrategy) -> Self {
+        self.nonce_strategy = strategy;
+        self
+    }
+
+    /// What we tell the peer we support
+    fn local_capabilities(&self) -> PeerCapabilities {
+        PeerCapabilities {
+            forward_secrecy: self.forward_secrecy,
+            ..PeerCapabilities::local()
+        }
+    }
+
+    /// Sets the codec we'd like to send audio with
+    pub fn with_codec(self, settings: CodecSettings) -> Self {
+        self.set_codec(settings);
+        self
+    }
+
+    /// Changes the codec we'd like to send audio with, from the next frame
+    ///
+    /// The peer gets it only if it can decode it; otherwise the best codec
+    /// both sides support is used.
+    pub fn set_codec(&self, settings: CodecSettings) {
+        *self.codec.lock().unwrap() = settings;
+        self.encoder
+            .lock()
+            .unwrap()
+            .set_bitrate(settings.bitrate_kbps);
+    }
+
+    /// Has the codec spend less CPU per frame, for machines that can't keep up
+    pub fn with_low_complexity(self, low: bool) -> Self {
+        self.set_low_complexity(low);
+        self
+    }
+
+    /// Changes how much CPU the codec spends per frame, from the next frame
+    pub fn set_low_complexity(&self, low: bool) {
+        self.encoder.lock().unwrap().set_low_complexity(low);
+    }
+
+    /// Codec audio is sent to the peer with, as negotiated so far
+    pub fn audio_codec(&self) -> AudioCodec {
+        let preferred = self.codec.lock().unwrap().codec;
+        negotiate(preferred, self.peer_capabilities().codecs)
+    }
+
+    /// Meters the connection as part of a room and keeps it within the room's caps
+    ///
+    /// Pacing never sends faster than the upload cap, and audio is sent at a
+    /// lower sample rate while the room nears it. Takes effect on the next
+    /// connection.
+    pub fn with_bandwidth(mut self, monitor: BandwidthMonitor) -> Self {
+        if let (Some(pacing), Some(cap)) = (self.pacing.as_mut(), monitor.caps().upload_kbps) {
+            let rate = kbps_to_bytes(cap).max(1);
+            pacing.rate = pacing.rate.min(rate);
+            pacing.burst = pacing.burst.min(rate);
+        }
+        self.bandwidth = Some(monitor);
+        self
+    }
+
+    /// Most the peer wants to receive from us, in kilobits per second
+    pub fn peer_bandwidth_limit(&self) -> Option<u32> {
+        self.peer_bandwidth.lock().unwrap().limit()
+    }
+
+    /// Features both we and the peer support; none until the peer says
+    pub fn peer_capabilities(&self) -> PeerCapabilities {
+        *self.peer_capabilities.lock().unwrap()
+    }
+
+    /// Largest datagram sent to the peer; larger messages are fragmented
+    pub fn max_datagram(&self) -> usize {
+        self.mtu.lock().unwrap().current()
+    }
+
+    /// Calls `alert` when packets from the peer look tampered with
+    ///
+    /// See [`TamperMonitor::record`] for when that is.
+    pub fn with_tamper_alert(mut self, alert: impl Fn() + Send + Sync + 'static) -> Self {
+        self.tamper_alert = Some(Arc::new(alert));
+        self
+    }
+
+    /// Counts of packets from the peer that failed decryption, authentication or replay checks
+    pub fn crypto_stats(&self) -> CryptoStats {
+        self.tamper.lock().unwrap().stats()
+    }
+
+    /// Packets the peer has sent on the current channel, and how many arrived
+    pub async fn packet_counts(&self) -> PacketCounts {
+        self.channel
+            .lock()
+            .await
+            .as_ref()
+            .map(SecureChannel::packet_counts)
+            .unwrap_or_default()
+    }
+
+    /// Round trip of the clock probe the offset estimate is based on
+    pub fn round_trip(&self) -> Option<Duration> {
+        self.clock
+            .lock()
+            .unwrap()
+            .round_trip()
+            .map(Duration::from_micros)
+    }
+
+    /// Address of the remote peer
+    pub fn remote_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.remote_ip, self.remote_port)
+    }
+
+    /// Handshake, keys, codec and path of the connection, with its recent history
+    pub async fn inspect(&self) -> ConnectionDetails {
+        let state = self.state.lock().await.clone();
+        let channel_guard = self.channel.lock().await;
+        let channel = channel_guard.as_ref();
+        ConnectionDetails {
+            state,
+            cipher: channel.and_then(SecureChannel::cipher),
+            nonce_strategy: channel
+                .map(SecureChannel::nonce_strategy)
+                .unwrap_or_default(),
+            forward_secrecy: channel.is_some_and(SecureChannel::forward_secrecy),
+            key_age: channel.and_then(SecureChannel::key_age),
+            codec: self.audio_codec(),
+            bitrate_kbps: self.codec.lock().unwrap().bitrate_kbps,
+            local_addr: channel.and_then(|channel| channel.clone_socket().local_addr().ok()),
+            remote_addr: channel.map_or(self.remote_addr(), SecureChannel::remote_addr),
+            max_datagram: self.max_datagram(),
+            socket_buffers: channel
+                .and_then(|channel| GrantedBuffers::of(&channel.clone_socket()).ok()),
+            packets: channel
+                .map(SecureChannel::packet_counts)
+                .unwrap_or_default(),
+            crypto: self.crypto_stats(),
+            history: self.history.events(),
+        }
+    }
+
+    /// Opens a secure channel to `remote_addr` and completes the handshake
+    async fn open_channel(
+        bind: &BindAddress,
+        remote_addr: SocketAddr,
+        session_id: String,
+        remote_key: [u8; 32],
+        nonce_strategy: NonceStrategy,
+    ) -> Result<SecureChannel> {
+        // Establish UDP connection
+        let socket =
+            establish_udp_connection_from(bind, remote_addr.ip(), remote_addr.port()).await?;
+
+        // Create secure channel
+        let mut channel = SecureChannel::new(socket, remote_addr).await;
+        channel.set_nonce_strategy(nonce_strategy);
+
+        // Set session ID
+        channel.session_id = session_id;
+
+        // Perform key exchange
+        channel.perform_key_exchange(Some(remote_key)).await?;
+
+        Ok(channel)
+    }
+
     /// Connect to the remote peer
     pub async fn connect(&self) -> Result<()> {
         // Update state
         let mut state = self.state.lock().await;
         *state = ConnectionState::Connecting;
         drop(state);
+        self.history
+            .record(format!("Handshaking with {}", self.remote_addr()));
+
+        let channel = Self::open_channel(
+            &self.bind,
+            self.remote_addr(),
+            self.session_id.clone(),
+            self.remote_key,
+            self.nonce_strategy,
+        )
+        .await?;
+        self.install_channel(channel).await;
 
-        // Establish UDP connection
-        let socket = establish_direct_udp_connection(self.remote_ip, self.remote_port).await?;
+        Ok(())
+    }
 
-        // Create secure channel
-        let remote_addr = SocketAddr::new(self.remote_ip, self.remote_port);
-        let mut channel = SecureChannel::new(socket, remote_addr).await;
+    /// Connects over whichever candidate address completes a handshake first
+    ///
+    /// Candidates are tried in staggered order and the remaining attempts are
+    /// cancelled once one succeeds. The winner becomes the peer's address,
+    /// including for reconnection.
+    pub async fn connect_racing(&mut self, candidates: &[SocketAddr]) -> Result<()> {
+        let mut state = self.state.lock().await;
+        *state = ConnectionState::Connecting;
+        drop(state);
+        self.history
+            .record(format!("Racing {} candidates", candidates.len()));
 
-        // Set session ID
-        channel.session_id = self.session_id.clone();
+        let session_id = self.session_id.clone();
+        let remote_key = self.remote_key;
+        let bind = self.bind.clone();
+        let nonce_strategy = self.nonce_strategy;
+        let (winner, channel) =
+            race_candidates(candidates, DEFAULT_STAGGER, |addr| {
+                let bind = bind.clone();
+                let session_id = session_id.clone();
+                async move {
+                    Self::open_channel(&bind, addr, session_id, remote_key, nonce_strategy).await
+                }
+            })
+            .await?;
+
+        log::info!("Connected to peer at {}", winner);
+        self.history.record(format!("Won the race: {}", winner));
+        self.remote_ip = winner.ip();
+        self.remote_port = winner.port();
+        *self.keepalive.lock().unwrap() =
+            AdaptiveKeepalive::for_peer(winner, KeepaliveConfig::default());
+        *self.checks.lock().unwrap() = CandidateChecks::new(candidates);
+        self.install_channel(channel).await;
 
-        // Perform key exchange
-        channel.perform_key_exchange(Some(self.remote_key)).await?;
+        Ok(())
+    }
+
+    /// Waits up to `timeout` for the peer to send anything after the handshake
+    ///
+    /// Keys are agreed from the link without a reply, so a peer that's gone,
+    /// or a firewall dropping our packets, only shows as silence. Once
+    /// listening, a peer that's there answers with its capabilities. On
+    /// timeout the connection is torn down.
+    pub async fn await_answer(&self, timeout: Duration) -> Result<()> {
+        let mut answered = self.answered.subscribe();
+        if tokio::time::timeout(timeout, answered.wait_for(|answered| *answered))
+            .await
+            .is_ok()
+        {
+            return Ok(());
+        }
+
+        let address = self.remote_addr();
+        log::warn!("No answer from {} in {:?}", address, timeout);
+        self.history.record(format!("No answer from {}", address));
+        self.close().await;
+        Err(NetworkError::HandshakeTimedOut(address).into())
+    }
+
+    /// Takes on a peer whose handshake reached us on a shared socket, such as the host's
+    ///
+    /// The channel is keyed from the handshake, and the peer's packets come
+    /// through `inbox` rather than a socket of our own. The connection
+    /// neither re-handshakes nor moves to another socket; the peer
+    /// reconnects to us if it has to.
+    pub async fn accept(
+        &mut self,
+        socket: Arc<UdpSocket>,
+        keypair: Keypair,
+        inbox: Inbox,
+    ) -> Result<()> {
+        self.history
+            .record(format!("Accepted a handshake from {}", self.remote_addr()));
+        let channel = SecureChannel::accept(
+            socket,
+            self.remote_addr(),
+            keypair,
+            self.session_id.clone(),
+            self.remote_key,
+            self.nonce_strategy,
+        )?;
+        self.inbox = Some(Arc::new(Mutex::new(inbox)));
+        self.install_channel(channel).await;
+        Ok(())
+    }
+
+    /// Drops the channel and stops the background tasks, without telling the peer
+    pub async fn close(&self) {
+        self.channel.lock().await.take();
+        for task in self.tasks.lock().await.drain(..) {
+            task.abort();
+        }
+        *self.state.lock().await = ConnectionState::Disconnected;
+    }
+
+    /// Whether the peer has been silent for longer than its keepalives allow
+    pub fn gone_quiet(&self) -> bool {
+        let silent_for = self.last_received.lock().unwrap().elapsed();
+        silent_for > self.keepalive.lock().unwrap().silence_threshold()
+    }
+
+    /// Pings each candidate over the channel and moves it to the fastest
+    ///
+    /// Run once the listener is up, as replies arrive through it. The
+    /// other candidates that answered are kept as hot spares, used if the
+    /// peer goes quiet on the chosen address for [`FAILOVER_SILENCE`].
+    pub async fn check_candidates(&self, candidates: &[SocketAddr]) -> Result<SocketAddr> {
+        *self.checks.lock().unwrap() = CandidateChecks::new(candidates);
+
+        for _ in 0..CHECK_ROUNDS {
+            {
+                let channel_guard = self.channel.lock().await;
+                let channel = channel_guard
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("Not connected"))?;
+                for &addr in candidates {
+                    let sent_at = self.checks.lock().unwrap().start_check(addr, now_micros());
+                    if let Err(e) = channel.send_to(addr, &Message::Ping { sent_at }).await {
+                        log::debug!("Connectivity check to {} failed: {}", addr, e);
+                    }
+                }
+            }
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+        tokio::time::sleep(CHECK_TIMEOUT).await;
+
+        let best = {
+            let mut checks = self.checks.lock().unwrap();
+            checks.clear_pending();
+            checks.best()
+        }
+        .ok_or_else(|| anyhow!("No candidate answered connectivity checks"))?;
+
+        let mut channel_guard = self.channel.lock().await;
+        if let Some(channel) = channel_guard.as_mut() {
+            if channel.remote_addr() != best {
+                log::info!("Using {} for the control channel", best);
+                self.history
+                    .record(format!("Connectivity checks picked {}", best));
+                channel.set_remote_addr(best);
+                *self.keepalive.lock().unwrap() =
+                    AdaptiveKeepalive::for_peer(best, KeepaliveConfig::default());
+            }
+        }
+        Ok(best)
+    }
+
+    /// Addresses kept as spares for the control channel, fastest first
+    pub fn spare_paths(&self) -> Vec<SocketAddr> {
+        self.checks.lock().unwrap().spares()
+    }
+
+    /// Stores a connected channel and starts the background tasks
+    async fn install_channel(&self, mut channel: SecureChannel) {
+        // Pace everything sent after the handshake
+        if let Some(pacing) = &self.pacing {
+            channel.enable_pacing(pacing.clone());
+        }
+        channel.set_bandwidth_monitor(self.bandwidth.clone());
+        channel.set_max_datagram(self.max_datagram());
+
+        // Tell the peer what we support; until it does the same we assume nothing
+        let capabilities = Message::Capabilities {
+            capabilities: self.local_capabilities(),
+        };
+        if let Err(e) = channel.send(&capabilities).await {
+            log::debug!("Failed to send capabilities: {}", e);
+        }
+
+        self.history
+            .record(format!("Keys agreed with {}", channel.remote_addr()));
 
         // Store channel
         let mut channel_guard = self.channel.lock().await;
@@ -93,42 +628,266 @@ impl ConnectionManager {
 
         // Start background tasks
         self.start_background_tasks();
-
-        Ok(())
     }
 
     /// Start background tasks for heartbeats and reconnection
+    ///
+    /// An accepted connection shares its socket, so it can't reconnect or
+    /// migrate to one of its own.
     fn start_background_tasks(&self) {
         let heartbeat_task = self.start_heartbeat_task();
-        let reconnect_task = self.start_reconnect_task();
+        let own_socket = self.inbox.is_none();
+        let reconnect_task = own_socket.then(|| self.start_reconnect_task());
         let message_task = self.start_message_task();
+        let probe_task = self.start_binding_probe_task();
+        let migration_task = own_socket.then(|| self.start_migration_task());
+        let mtu_task = self.start_mtu_task();
 
         let tasks_clone = self.tasks.clone();
         tokio::spawn(async move {
             let mut tasks = tasks_clone.lock().await;
             tasks.push(heartbeat_task);
-            tasks.push(reconnect_task);
+            tasks.extend(reconnect_task);
             tasks.push(message_task);
+            tasks.extend(migration_task);
+            tasks.push(mtu_task);
+            tasks.extend(probe_task);
         });
     }
 
+    /// Measures the NAT binding lifetime in the background, for peers behind a NAT
+    fn start_binding_probe_task(&self) -> Option<JoinHandle<()>> {
+        if !self.keepalive.lock().unwrap().behind_nat() {
+            return None;
+        }
+
+        let keepalive = self.keepalive.clone();
+        Some(tokio::spawn(async move {
+            let idle_periods = [15, 30, 60].map(Duration::from_secs);
+            if let Some(lifetime) = probe_binding_lifetime(&idle_periods).await {
+                log::debug!("NAT binding lifetime is at least {:?}", lifetime);
+                keepalive.lock().unwrap().set_binding_lifetime(lifetime);
+            }
+        }))
+    }
+
+    /// Probes the path for the largest datagram it carries, then sizes fragments to it
+    ///
+    /// Probes go out with the don't-fragment bit where the OS sets it by
+    /// default, so oversized ones are dropped or refused rather than split.
+    fn start_mtu_task(&self) -> JoinHandle<()> {
+        let channel = self.channel.clone();
+        let mtu = self.mtu.clone();
+        let history = self.history.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let Some(size) = mtu.lock().unwrap().next_probe() else {
+                    break;
+                };
+
+                if let Some(ref channel) = *channel.lock().await {
+                    let probe = mtu_probe(size);
+                    for _ in 0..PROBE_ATTEMPTS {
+                        if let Err(e) = channel.send(&probe).await {
+                            log::debug!("MTU probe of {} bytes not sent: {}", size, e);
+                        }
+                    }
+                }
+                tokio::time::sleep(PROBE_TIMEOUT).await;
+
+                // Does nothing if the probe was acknowledged meanwhile
+                mtu.lock().unwrap().lost(size);
+            }
+
+            let size = mtu.lock().unwrap().current();
+            log::debug!("Path carries datagrams of up to {} bytes", size);
+            history.record(format!("Path MTU: {} bytes", size));
+            if let Some(ref channel) = *channel.lock().await {
+                channel.set_max_datagram(size);
+            }
+        })
+    }
+
+    /// Watches the local address and migrates the connection when it changes
+    fn start_migration_task(&self) -> JoinHandle<()> {
+        let channel = self.channel.clone();
+        let remote_addr = self.remote_addr();
+        let bind = self.bind.clone();
+        let history = self.history.clone();
+
+        tokio::spawn(async move {
+            let mut address = local_ipv4().await.ok();
+            loop {
+                tokio::time::sleep(ADDRESS_CHECK_INTERVAL).await;
+
+                let current = local_ipv4().await.ok();
+                if current.is_some() && current != address {
+                    log::info!(
+                        "Local address changed, migrating connection to {}",
+                        remote_addr
+                    );
+                    match Self::rebind_channel(&channel, &bind, remote_addr).await {
+                        Ok(()) => history.record("Local address changed; migrated"),
+                        Err(e) => {
+                            log::warn!("Connection migration failed: {}", e);
+                            history.record(format!("Migration failed: {}", e));
+                        }
+                    }
+                }
+                address = current;
+            }
+        })
+    }
+
+    /// Moves the channel to a fresh socket and tells the peer with a path probe
+    async fn rebind_channel(
+        channel: &Mutex<Option<SecureChannel>>,
+        bind: &BindAddress,
+        remote_addr: SocketAddr,
+    ) -> Result<()> {
+        let socket =
+            establish_udp_connection_from(bind, remote_addr.ip(), remote_addr.port()).await?;
+
+        let mut channel_guard = channel.lock().await;
+        let channel = channel_guard
+            .as_mut()
+            .ok_or_else(|| anyhow!("Not connected"))?;
+        channel.rebind(socket);
+        channel.send(&path_probe()).await
+    }
+
+    /// Moves the connection to our current local address without a new handshake
+    ///
+    /// Called automatically when the local address changes, such as when
+    /// switching from Wi-Fi to ethernet. The peer learns the new address
+    /// from a probe authenticated with the existing session keys.
+    pub async fn migrate(&self) -> Result<()> {
+        Self::rebind_channel(&self.channel, &self.bind, self.remote_addr()).await
+    }
+
+    /// Stops the connection here and returns what's needed to carry it on elsewhere
+    ///
+    /// Nothing more is sent or received here, so the channel's keys and
+    /// sequence numbers stay in step for [`ConnectionManager::resume`].
+    pub async fn hand_off(&self) -> Result<ConnectionHandoff> {
+        let channel = self
+            .channel
+            .lock()
+            .await
+            .take()
+            .ok_or_else(|| anyhow!("Not connected"))?;
+        for task in self.tasks.lock().await.drain(..) {
+            task.abort();
+        }
+        *self.state.lock().await = ConnectionState::Disconnected;
+        self.history.record("Handed off");
+
+        Ok(ConnectionHandoff {
+            channel: channel.export_state()?,
+            capabilities: self.peer_capabilities(),
+        })
+    }
+
+    /// Carries on a connection handed off from another machine
+    ///
+    /// The channel keeps its keys and moves to a socket here; a path probe
+    /// tells the peer, which switches to us as it would for a changed
+    /// address, so there's no handshake and no rejoining.
+    pub async fn resume(&self, handoff: ConnectionHandoff) -> Result<()> {
+        let remote = handoff.channel.remote;
+        let socket = establish_udp_connection_from(&self.bind, remote.ip(), remote.port()).await?;
+        let channel = SecureChannel::resume(socket, handoff.channel);
+        *self.peer_capabilities.lock().unwrap() = handoff.capabilities;
+
+        // Anything else sent before the peer accepts the probe is dropped
+        channel.send(&path_probe()).await?;
+        self.history.record("Resumed from a hand-off");
+        self.install_channel(channel).await;
+        Ok(())
+    }
+
     /// Start heartbeat task
     fn start_heartbeat_task(&self) -> JoinHandle<()> {
         let state_clone = self.state.clone();
         let channel_clone = self.channel.clone();
         let last_heartbeat_clone = self.last_heartbeat.clone();
+        let keepalive = self.keepalive.clone();
+        let last_received = self.last_received.clone();
+        let checks = self.checks.clone();
+        let history = self.history.clone();
+        let suspended = self.suspended.clone();
 
         tokio::spawn(async move {
+            let mut last_keepalive = Instant::now();
+
             loop {
                 // Sleep for a short time
                 tokio::time::sleep(Duration::from_secs(1)).await;
+                if suspended.load(Ordering::Relaxed) {
+                    continue;
+                }
+
+                // Refresh the NAT binding at the peer's adaptive cadence
+                let now = Instant::now();
+                let keepalive_due = {
+                    let mut keepalive = keepalive.lock().unwrap();
+                    let due = now.duration_since(last_keepalive) >= keepalive.interval();
+                    if due {
+                        let silent_for = now.duration_since(*last_received.lock().unwrap());
+                        if silent_for > keepalive.silence_threshold() {
+                            keepalive.record_silence();
+                        } else {
+                            keepalive.record_healthy();
+                        }
+                    }
+                    due
+                };
+
+                // Move to a hot spare if the peer has gone quiet on this path
+                let silent_for = now.duration_since(*last_received.lock().unwrap());
+                if silent_for > FAILOVER_SILENCE {
+                    let mut channel_guard = channel_clone.lock().await;
+                    if let Some(channel) = channel_guard.as_mut() {
+                        let current = channel.remote_addr();
+                        let spare = checks.lock().unwrap().fail_over(current);
+                        if let Some(spare) = spare {
+                            log::info!("No reply from {}, switching to {}", current, spare);
+                            history.record(format!(
+                                "No reply from {}, failed over to {}",
+                                current, spare
+                            ));
+                            channel.set_remote_addr(spare);
+                            *keepalive.lock().unwrap() =
+                                AdaptiveKeepalive::for_peer(spare, KeepaliveConfig::default());
+                            *last_received.lock().unwrap() = now;
+                        }
+                    }
+                }
+
+                if keepalive_due {
+                    last_keepalive = now;
+                    if let Some(ref channel) = *channel_clone.lock().await {
+                        if let Err(e) = channel.send_keepalive().await {
+                            log::debug!("Keepalive failed: {}", e);
+                        }
+                    }
+                }
+
+                // Agree fresh ratchet keys when due
+                if let Some(ref channel) = *channel_clone.lock().await {
+                    if let Some(rekey) = channel.rekey_request() {
+                        if let Err(e) = channel.send(&rekey).await {
+                            log::debug!("Failed to send rekey: {}", e);
+                        }
+                    }
+                }
 
                 // Check if we need to send a heartbeat
                 let mut needs_heartbeat = false;
                 {
-                    let now = Instant::now();
                     let mut last = last_heartbeat_clone.lock().await;
-                    if now.duration_since(*last) > Duration::from_secs(5) {
+                    if now.duration_since(*last) > PING_INTERVAL {
                         needs_heartbeat = true;
                         *last = now;
                     }
@@ -140,14 +899,15 @@ impl ConnectionManager {
                     drop(state_guard);
 
                     if current_state == ConnectionState::Connected {
-                        // Try to send heartbeat
+                        // Try to send heartbeat; pings also keep the clock estimate fresh
                         let channel_guard = channel_clone.lock().await;
                         if let Some(ref channel) = *channel_guard {
-                            let result = channel.send_heartbeat().await;
+                            let result = channel.send_ping().await;
                             drop(channel_guard);
 
                             if let Err(e) = result {
-                                eprintln!("Heartbeat failed: {}", e);
+                                log::warn!("Heartbeat failed: {}", e);
+                                history.record(format!("Heartbeat failed: {}", e));
                                 let mut state = state_clone.lock().await;
                                 *state = ConnectionState::Connecting;
                             }
@@ -166,11 +926,22 @@ impl ConnectionManager {
         let remote_port = self.remote_port;
         let session_id = self.session_id.clone();
         let remote_key = self.remote_key;
+        let bind = self.bind.clone();
+        let pacing = self.pacing.clone();
+        let bandwidth = self.bandwidth.clone();
+        let mtu = self.mtu.clone();
+        let history = self.history.clone();
+        let suspended = self.suspended.clone();
+        let peer_capabilities = self.peer_capabilities.clone();
+        let nonce_strategy = self.nonce_strategy;
 
         tokio::spawn(async move {
             loop {
                 // Sleep for a short time
                 tokio::time::sleep(Duration::from_secs(1)).await;
+                if suspended.load(Ordering::Relaxed) {
+                    continue;
+                }
 
                 // Check if we need to reconnect
                 let state_guard = state_clone.lock().await;
@@ -179,17 +950,27 @@ impl ConnectionManager {
 
                 if current_state == ConnectionState::Connecting {
                     // Try to reconnect
-                    match establish_direct_udp_connection(remote_ip, remote_port).await {
+                    match establish_udp_connection_from(&bind, remote_ip, remote_port).await {
                         Ok(socket) => {
                             // Create new secure channel
                             let remote_addr = SocketAddr::new(remote_ip, remote_port);
                             let mut new_channel = SecureChannel::new(socket, remote_addr).await;
                             new_channel.session_id = session_id.clone();
+                            new_channel.set_nonce_strategy(nonce_strategy);
 
                             // Try to perform key exchange
                             match new_channel.perform_key_exchange(Some(remote_key)).await {
                                 Ok(_) => {
                                     // Reconnection successful
+                                    if let Some(pacing) = &pacing {
+                                        new_channel.enable_pacing(pacing.clone());
+                                    }
+                                    new_channel.set_bandwidth_monitor(bandwidth.clone());
+                                    new_channel.set_max_datagram(mtu.lock().unwrap().current());
+                                    new_channel.set_forward_secrecy(
+                                        peer_capabilities.lock().unwrap().forward_secrecy,
+                                    );
+
                                     {
                                         let mut channel = channel_clone.lock().await;
                                         *channel = Some(new_channel);
@@ -200,15 +981,17 @@ impl ConnectionManager {
                                         *state = ConnectionState::Connected;
                                     }
 
-                                    println!("Reconnected to peer");
+                                    log::info!("Reconnected to peer");
+                                    history.record("Reconnected; new keys agreed");
                                 }
                                 Err(e) => {
-                                    eprintln!("Key exchange failed during reconnection: {}", e);
+                                    log::warn!("Key exchange failed during reconnection: {}", e);
+                                    history.record(format!("Reconnection handshake failed: {}", e));
                                 }
                             }
                         }
                         Err(e) => {
-                            eprintln!("Reconnection attempt failed: {}", e);
+                            log::warn!("Reconnection attempt failed: {}", e);
                         }
                     }
                 }
@@ -262,12 +1045,12 @@ impl ConnectionManager {
                             match result {
                                 Ok(_) => break, // Message sent successfully
                                 Err(e) => {
-                                    eprintln!("Failed to send message: {}", e);
+                                    log::warn!("Failed to send message: {}", e);
                                     retry_count += 1;
 
                                     if retry_count >= max_retries {
                                         // Give up after max retries
-                                        eprintln!("Giving up after {} retries", max_retries);
+                                        log::error!("Giving up after {} retries", max_retries);
                                         break;
                                     }
 
@@ -307,14 +1090,63 @@ impl ConnectionManager {
             .map_err(|_| anyhow!("Failed to queue message"))
     }
 
-    /// Send audio data reliably
-    pub async fn send_audio(&self, audio_data: &[u8], timestamp: u64) -> Result<()> {
-        let message = Message::Audio {
-            data: audio_data.to_vec(),
-            timestamp,
+    /// Send an audio frame reliably, bundled with the next ones if bundling is on
+    ///
+    /// With silence suppression on and a peer that supports it, silent
+    /// frames are replaced by occasional comfort noise descriptors.
+    pub async fn send_audio(&self, frame: &AudioFrame) -> Result<()> {
+        let transmission = match self.suppressor.lock().unwrap().as_mut() {
+            Some(suppressor) if self.peer_capabilities().comfort_noise => {
+                suppressor.push(&frame.samples, frame.channels, Instant::now())
+            }
+            _ => Transmission::Frame,
+        };
+        match transmission {
+            Transmission::Frame => {}
+            Transmission::Nothing => return Ok(()),
+            Transmission::Descriptor(noise) => {
+                // Frames still waiting go out before the line goes quiet
+                let unsent = self.bundler.lock().unwrap().flush();
+                if let Some(message) = unsent {
+                    self.send_reliable(message).await?;
+                }
+                let message = Message::ComfortNoise {
+                    sample_rate: frame.sample_rate,
+                    channels: frame.channels,
+                    frame_len: frame.samples.len() as u32,
+                    noise,
+                };
+                return self.send_reliable(message).await;
+            }
+        }
+
+        // Send less while the room or the peer is short of bandwidth
+        let shaped;
+        let frame = match &self.bandwidth {
+            Some(monitor) => {
+                let peer_divisor = self.peer_bandwidth.lock().unwrap().divisor(Instant::now());
+                shaped = monitor.shape_audio(frame, peer_divisor);
+                &shaped
+            }
+            None => frame,
         };
 
-        self.send_reliable(message).await
+        let message =
+            Message::encode_audio(frame, self.audio_codec(), &mut self.encoder.lock().unwrap());
+        let ready = self
+            .bundler
+            .lock()
+            .unwrap()
+            .push_encoded(message, self.max_datagram());
+        for message in ready {
+            let size = PACKET_OVERHEAD + bincode::serialized_size(&message).unwrap_or(0) as usize;
+            self.peer_bandwidth
+                .lock()
+                .unwrap()
+                .record_sent(size, Instant::now());
+            self.send_reliable(message).await?;
+        }
+        Ok(())
     }
 
     /// Listen for incoming messages
@@ -323,33 +1155,236 @@ impl ConnectionManager {
         F: FnMut(Message) -> Result<()> + Send + 'static,
     {
         let channel_clone = self.channel.clone();
+        let clock = self.clock.clone();
+        let last_received = self.last_received.clone();
+        let keepalive = self.keepalive.clone();
+        let path_validator = self.path_validator.clone();
+        let checks = self.checks.clone();
+        let tamper = self.tamper.clone();
+        let tamper_alert = self.tamper_alert.clone();
+        let mtu = self.mtu.clone();
+        let peer_capabilities = self.peer_capabilities.clone();
+        let local_capabilities = self.local_capabilities();
+        let bandwidth = self.bandwidth.clone();
+        let peer_bandwidth = self.peer_bandwidth.clone();
+        let codec = self.codec.clone();
+        let history = self.history.clone();
+        let answered = self.answered.clone();
+        let inbox = self.inbox.clone();
 
         tokio::spawn(async move {
+            // Packets are split off the front, and the space is reused once
+            // the messages decoded from them are dropped
+            let mut buf = BytesMut::new();
             loop {
                 // Check if we have a channel
                 let channel_guard = channel_clone.lock().await;
-                let current_socket = match &*channel_guard {
-                    Some(ch) => Some(ch.clone_socket()),
-                    None => None,
-                };
+                let current_socket = (*channel_guard).as_ref().map(|ch| ch.clone_socket());
                 drop(channel_guard);
 
                 if let Some(socket) = current_socket {
                     // We have a channel, try to receive
-                    let mut buf = [0u8; 65536];
-                    match socket.recv_from(&mut buf).await {
-                        Ok((size, addr)) => {
+                    let received = match &inbox {
+                        // The shared socket's listener routes the peer's packets to us
+                        Some(inbox) => {
+                            match tokio::time::timeout(RECEIVE_TIMEOUT, inbox.lock().await.recv())
+                                .await
+                            {
+                                Ok(Some(routed)) => Ok(routed),
+                                Ok(None) => break,
+                                Err(_) => continue,
+                            }
+                        }
+                        None => {
+                            buf.clear();
+                            buf.reserve(65536);
+                            match tokio::time::timeout(
+                                RECEIVE_TIMEOUT,
+                                socket.recv_buf_from(&mut buf),
+                            )
+                            .await
+                            {
+                                Ok(received) => received.map(|(_, addr)| (buf.split(), addr)),
+                                Err(_) => continue,
+                            }
+                        }
+                    };
+                    match received {
+                        Ok((packet, addr)) => {
+                            let size = packet.len();
                             // Process the packet
-                            let channel_guard = channel_clone.lock().await;
-                            if let Some(ref ch) = *channel_guard {
-                                if addr == ch.remote_addr() {
-                                    // TODO: Process the packet
+                            let received_at = now_micros();
+                            if let Some(monitor) = &bandwidth {
+                                monitor.record_received(size);
+                            }
+                            let mut channel_guard = channel_clone.lock().await;
+                            let message = match channel_guard.as_mut() {
+                                Some(ch)
+                                    if addr == ch.remote_addr()
+                                        || checks.lock().unwrap().contains(addr) =>
+                                {
+                                    *last_received.lock().unwrap() = Instant::now();
+                                    if is_keepalive(&packet) {
+                                        continue;
+                                    }
+                                    ch.decode_packet(packet)
+                                }
+                                Some(ch) => {
+                                    // Another address only counts if it sends a valid path probe
+                                    if is_keepalive(&packet) {
+                                        continue;
+                                    }
+                                    if let Ok(Message::PathProbe { nonce, sent_at }) =
+                                        ch.decode_packet(packet)
+                                    {
+                                        if path_validator.lock().unwrap().accept(
+                                            nonce,
+                                            sent_at,
+                                            received_at,
+                                        ) {
+                                            log::info!(
+                                                "Peer moved from {} to {}",
+                                                ch.remote_addr(),
+                                                addr
+                                            );
+                                            history.record(format!(
+                                                "Peer moved from {} to {}",
+                                                ch.remote_addr(),
+                                                addr
+                                            ));
+                                            ch.set_remote_addr(addr);
+                                            *last_received.lock().unwrap() = Instant::now();
+                                            *keepalive.lock().unwrap() =
+                                                AdaptiveKeepalive::for_peer(
+                                                    addr,
+                                                    KeepaliveConfig::default(),
+                                                );
+                                            if let Err(e) =
+                                                ch.send(&Message::PathConfirm { nonce }).await
+                                            {
+                                                log::warn!("Failed to confirm new path: {}", e);
+                                            }
+                                        }
+                                    }
+                                    continue;
+                                }
+                                None => continue,
+                            };
+                            if message.is_ok() {
+                                answered.send_if_modified(|answered| {
+                                    !std::mem::replace(answered, true)
+                                });
+                            }
+
+                            match message {
+                                Ok(Message::Heartbeat) => {}
+                                Ok(Message::Ping { sent_at }) => {
+                                    // Answer clock probes so the peer can estimate its offset
+                                    if let Some(ref ch) = *channel_guard {
+                                        let pong = Message::Pong {
+                                            ping_sent_at: sent_at,
+                                            received_at,
+                                            sent_at: now_micros(),
+                                        };
+                                        if let Err(e) = ch.send(&pong).await {
+                                            log::warn!("Failed to answer ping: {}", e);
+                                        }
+                                    }
+                                }
+                                Ok(Message::Pong {
+                                    ping_sent_at,
+                                    received_at: peer_received_at,
+                                    sent_at,
+                                }) => {
+                                    // Replies to connectivity checks also feed the clock estimate
+                                    checks
+                                        .lock()
+                                        .unwrap()
+                                        .record_reply(ping_sent_at, received_at);
+                                    clock.lock().unwrap().record(
+                                        ping_sent_at,
+                                        peer_received_at,
+                                        sent_at,
+                                        received_at,
+                                    );
+                                }
+                                Ok(Message::MtuProbe { .. }) => {
+                                    if let Some(ref ch) = *channel_guard {
+                                        let ack = Message::MtuAck { size: size as u32 };
+                                        if let Err(e) = ch.send(&ack).await {
+                                            log::debug!("Failed to acknowledge MTU probe: {}", e);
+                                        }
+                                    }
+                                }
+                                Ok(Message::MtuAck { size }) => {
+                                    mtu.lock().unwrap().confirm(size as usize);
+                                }
+                                Ok(Message::Rekey { epoch, public_key }) => {
+                                    if let Some(ref ch) = *channel_guard {
+                                        if let Some(answer) = ch.accept_rekey(epoch, public_key) {
+                                            if let Err(e) = ch.send(&answer).await {
+                                                log::debug!("Failed to answer rekey: {}", e);
+                                            }
+                                        }
+                                    }
+                                }
+                                Ok(Message::Capabilities { capabilities }) => {
+                                    let common = local_capabilities.common(&capabilities);
+                                    *peer_capabilities.lock().unwrap() = common;
+                                    if let Some(ch) = channel_guard.as_mut() {
+                                        if common.forward_secrecy && !ch.forward_secrecy() {
+                                            ch.set_forward_secrecy(true);
+                                            history
+                                                .record("Forward secrecy on; message keys ratchet");
+                                        }
+                                    }
+                                    let preferred = codec.lock().unwrap().codec;
+                                    history.record(format!(
+                                        "Codec negotiated: {}",
+                                        negotiate(preferred, common.codecs)
+                                    ));
+                                }
+                                Ok(Message::BandwidthLimit { kbps }) => {
+                                    peer_bandwidth.lock().unwrap().set_limit(Some(kbps));
+                                }
+                                // Still waiting for the message's other fragments
+                                Ok(Message::Fragment { .. }) => {}
+                                Ok(Message::PathProbe { .. }) => {}
+                                Ok(Message::PathConfirm { .. }) => {
+                                    log::info!("Peer confirmed our new address");
+                                    history.record("Peer confirmed our new address");
+                                }
+                                Ok(message) => {
+                                    drop(channel_guard);
+                                    // Bundled frames are handled one at a time, as if sent separately
+                                    for message in message.unbundle() {
+                                        if let Err(e) = handler(message) {
+                                            log::warn!("Message handler failed: {}", e);
+                                        }
+                                    }
+                                }
+                                Err(e) => {
+                                    log::debug!("Dropped packet: {}", e);
+                                    if let Some(&failure) = e.downcast_ref::<CryptoFailure>() {
+                                        if tamper.lock().unwrap().record(failure, Instant::now()) {
+                                            log::warn!(
+                                                "Packets from the peer keep failing checks ({}); the connection may be tampered with",
+                                                failure
+                                            );
+                                            history.record(format!(
+                                                "Packets keep failing checks ({})",
+                                                failure
+                                            ));
+                                            if let Some(alert) = &tamper_alert {
+                                                alert();
+                                            }
+                                        }
+                                    }
                                 }
                             }
-                            drop(channel_guard);
                         }
                         Err(e) => {
-                            eprintln!("Error receiving: {}", e);
+                            log::warn!("Error receiving: {}", e);
                         }
                     }
                 } else {
@@ -366,6 +1401,40 @@ impl ConnectionManager {
         state.clone()
     }
 
+    /// Estimated remote clock minus local clock, in microseconds
+    pub fn clock_offset(&self) -> Option<i64> {
+        self.clock.lock().unwrap().offset()
+    }
+
+    /// Stops keepalives, heartbeats, failover and reconnection until [`wake`](Self::wake)
+    ///
+    /// For when the machine sleeps: the peer stops hearing from us either
+    /// way, and nothing we'd conclude from its silence meanwhile holds.
+    pub fn suspend(&self) {
+        if !self.suspended.swap(true, Ordering::Relaxed) {
+            self.history.record("Suspended for sleep");
+        }
+    }
+
+    /// Picks the connection up again after the machine slept
+    ///
+    /// The peer's NAT binding, and likely the peer's patience, ran out while
+    /// we slept, so a live connection is handed to the reconnect task to
+    /// agree keys afresh rather than trusted as it was.
+    pub async fn wake(&self) {
+        let now = Instant::now();
+        *self.last_received.lock().unwrap() = now;
+        *self.last_heartbeat.lock().await = now;
+        {
+            let mut state = self.state.lock().await;
+            if *state == ConnectionState::Connected {
+                *state = ConnectionState::Connecting;
+            }
+        }
+        self.suspended.store(false, Ordering::Relaxed);
+        self.history.record("Woke from sleep, reconnecting");
+    }
+
     /// Check if the connection is currently active
     pub async fn is_connected(&self) -> bool {
         let state = self.state.lock().await;
@@ -380,6 +1449,101 @@ impl ConnectionManager {
         self.send_reliable(message).await
     }
 
+    /// Send a marker that our audio has ended, after any frames still queued
+    pub async fn send_stream_end(&self) -> Result<()> {
+        let unsent = self.bundler.lock().unwrap().flush();
+        if let Some(message) = unsent {
+            self.send_reliable(message).await?;
+        }
+        self.send_reliable(Message::StreamEnd).await
+    }
+
+    /// Send a presence update for a peer
+    pub async fn send_presence(
+        &self,
+        peer_id: &str,
+        status: crate::app::presence::PresenceStatus,
+    ) -> Result<()> {
+        let message = Message::Presence {
+            peer_id: peer_id.to_string(),
+            status,
+        };
+        self.send_reliable(message).await
+    }
+
+    /// Send the seats of a spatial layout
+    pub async fn send_layout(
+        &self,
+        layout: crate::audio::SpatialLayout,
+        positions: &[(String, (f32, f32, f32))],
+    ) -> Result<()> {
+        let message = Message::Layout {
+            layout,
+            positions: positions.to_vec(),
+        };
+        self.send_reliable(message).await
+    }
+
+    /// Send whether everyone but the host is muted
+    pub async fn send_meeting_mode(&self, enabled: bool) -> Result<()> {
+        self.send_reliable(Message::MeetingMode { enabled }).await
+    }
+
+    /// Send that a peer raised or lowered their hand
+    pub async fn send_hand_raised(&self, peer_id: &str, raised: bool) -> Result<()> {
+        let message = Message::HandRaised {
+            peer_id: peer_id.to_string(),
+            raised,
+        };
+        self.send_reliable(message).await
+    }
+
+    /// Send that the host let a muted peer speak
+    pub async fn send_speak_granted(&self, peer_id: &str) -> Result<()> {
+        let message = Message::SpeakGranted {
+            peer_id: peer_id.to_string(),
+        };
+        self.send_reliable(message).await
+    }
+
+    /// Send that a peer started or stopped recording
+    pub async fn send_recording_state(&self, peer_id: &str, recording: bool) -> Result<()> {
+        let message = Message::RecordingStateChanged {
+            peer_id: peer_id.to_string(),
+            recording,
+        };
+        self.send_reliable(message).await
+    }
+
+    /// Send that the host heard the peer started recording
+    pub async fn send_recording_acknowledged(&self, peer_id: &str) -> Result<()> {
+        let message = Message::RecordingAcknowledged {
+            peer_id: peer_id.to_string(),
+        };
+        self.send_reliable(message).await
+    }
+
+    /// Send whether recordings in the room wait for the host to acknowledge them
+    pub async fn send_recording_policy(&self, consent: bool) -> Result<()> {
+        self.send_reliable(Message::RecordingPolicy { consent })
+            .await
+    }
+
+    /// Send how long after capture a peer's audio is to be played; 0 for as
+    /// soon as it arrives
+    pub async fn send_synced_playout(&self, peer_id: &str, delay_ms: u32) -> Result<()> {
+        let message = Message::SyncedPlayout {
+            peer_id: peer_id.to_string(),
+            delay_ms,
+        };
+        self.send_reliable(message).await
+    }
+
+    /// Tells the peer the most we want to receive from it; 0 for no limit
+    pub async fn send_bandwidth_limit(&self, kbps: u32) -> Result<()> {
+        self.send_reliable(Message::BandwidthLimit { kbps }).await
+    }
+
     /// Send a list of peers to a newly connected peer
     pub async fn send_peer_list(&self, peers: &[crate::app::session::Peer]) -> Result<()> {
         let message = Message::PeerList {
@@ -394,21 +1558,18 @@ impl ConnectionManager {
         self.send_reliable(message).await
     }
 
-    /// Sends audio data to the remote peer
-    pub async fn send_audio_data(&self, audio_data: &[f32]) -> Result<()> {
-        // Convert f32 samples to bytes for transmission (simple conversion for testing)
-        let bytes: Vec<u8> = audio_data
-            .iter()
-            .map(|&sample| (sample * 255.0) as u8)
-            .collect();
-
-        // Generate timestamp
-        let timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_millis() as u64;
+    /// Asks the host to let us into the room
+    pub async fn send_join_request(&self, peer_id: &str, name: &str) -> Result<()> {
+        let message = Message::JoinRequest {
+            peer_id: peer_id.to_string(),
+            name: name.to_string(),
+        };
+        self.send_reliable(message).await
+    }
 
-        self.send_audio(&bytes, timestamp).await
+    /// Answers a peer's request to join the room we host
+    pub async fn send_join_reply(&self, reply: crate::app::admission::JoinReply) -> Result<()> {
+        self.send_reliable(Message::JoinReply { reply }).await
     }
 }
 
@@ -430,4 +1591,34 @@ mod tests {
             ConnectionState::Disconnected
         );
     }
+
+    #[tokio::test]
+    async fn test_silent_peer_times_out() {
+        // Nothing listens on the other end, so nothing ever answers
+        let silent = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = silent.local_addr().unwrap();
+        drop(silent);
+
+        let manager = ConnectionManager::new(
+            address.ip(),
+            address.port(),
+            "test-session".to_string(),
+            [9u8; 32],
+        );
+        manager.connect().await.unwrap();
+        let _listener = manager.start_listening(|_| Ok(())).await;
+
+        let error = manager
+            .await_answer(Duration::from_millis(100))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<NetworkError>(),
+            Some(NetworkError::HandshakeTimedOut(timed_out)) if *timed_out == address
+        ));
+        assert_eq!(
+            manager.connection_state().await,
+            ConnectionState::Disconnected
+        );
+    }
 }
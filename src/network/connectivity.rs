@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+/// Pings sent to each candidate while choosing a path
+pub const CHECK_ROUNDS: usize = 3;
+
+/// Time between rounds of checks
+pub const CHECK_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How long after the last round replies are still counted
+pub const CHECK_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long the peer may go silent before traffic moves to a spare path
+pub const FAILOVER_SILENCE: Duration = Duration::from_secs(10);
+
+/// A peer address and what checking it has shown
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub addr: SocketAddr,
+    /// Best round trip seen, if the address has answered at all
+    pub rtt: Option<Duration>,
+    /// Whether traffic already moved away from this address after it went quiet
+    pub failed: bool,
+}
+
+/// Connectivity checks across a peer's candidate addresses
+///
+/// Each candidate is sent authenticated pings, and the one answering
+/// fastest carries the control channel. The others that answered are kept
+/// as hot spares to fall back on without a new handshake.
+#[derive(Debug, Default)]
+pub struct CandidateChecks {
+    candidates: Vec<Candidate>,
+    // Candidate each outstanding ping went to, by the ping's send time
+    pending: HashMap<u64, SocketAddr>,
+}
+
+impl CandidateChecks {
+    pub fn new(addrs: &[SocketAddr]) -> Self {
+        let mut checks = Self::default();
+        for &addr in addrs {
+            if !checks.contains(addr) {
+                checks.candidates.push(Candidate {
+                    addr,
+                    rtt: None,
+                    failed: false,
+                });
+            }
+        }
+        checks
+    }
+
+    pub fn candidates(&self) -> &[Candidate] {
+        &self.candidates
+    }
+
+    /// Whether `addr` is one of the peer's candidates
+    pub fn contains(&self, addr: SocketAddr) -> bool {
+        self.candidates
+            .iter()
+            .any(|candidate| candidate.addr == addr)
+    }
+
+    /// Records a ping to `addr` sent at `now`, returning the send time to stamp it with
+    ///
+    /// Send times identify the reply, so one already in use is bumped.
+    pub fn start_check(&mut self, addr: SocketAddr, now: u64) -> u64 {
+        let mut sent_at = now;
+        while self.pending.contains_key(&sent_at) {
+            sent_at += 1;
+        }
+        self.pending.insert(sent_at, addr);
+        sent_at
+    }
+
+    /// Records the reply to a check, returning the candidate it was for
+    pub fn record_reply(&mut self, ping_sent_at: u64, now: u64) -> Option<SocketAddr> {
+        let addr = self.pending.remove(&ping_sent_at)?;
+        let rtt = Duration::from_micros(now.saturating_sub(ping_sent_at));
+        let candidate = self
+            .candidates
+            .iter_mut()
+            .find(|candidate| candidate.addr == addr)?;
+        candidate.rtt = Some(candidate.rtt.map_or(rtt, |best| best.min(rtt)));
+        candidate.failed = false;
+        Some(addr)
+    }
+
+    /// Forgets checks that were never answered
+    pub fn clear_pending(&mut self) {
+        self.pending.clear();
+    }
+
+    /// The working candidate with the lowest round trip
+    pub fn best(&self) -> Option<SocketAddr> {
+        self.ranked().first().copied()
+    }
+
+    /// Working candidates other than the best, fastest first
+    pub fn spares(&self) -> Vec<SocketAddr> {
+        self.ranked().into_iter().skip(1).collect()
+    }
+
+    /// Marks `addr` as failed and returns the best spare to move to
+    ///
+    /// Without a spare the address is left alone, as there's nowhere to go.
+    pub fn fail_over(&mut self, addr: SocketAddr) -> Option<SocketAddr> {
+        let spare = self.ranked().into_iter().find(|&spare| spare != addr)?;
+        if let Some(candidate) = self
+            .candidates
+            .iter_mut()
+            .find(|candidate| candidate.addr == addr)
+        {
+            candidate.failed = true;
+        }
+        Some(spare)
+    }
+
+    fn ranked(&self) -> Vec<SocketAddr> {
+        let mut working: Vec<&Candidate> = self
+            .candidates
+            .iter()
+            .filter(|candidate| candidate.rtt.is_some() && !candidate.failed)
+            .collect();
+        working.sort_by_key(|candidate| candidate.rtt);
+        working
+            .into_iter()
+            .map(|candidate| candidate.addr)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> SocketAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_selects_fastest_and_keeps_spares() {
+        let lan = addr("192.168.1.5:4000");
+        let public = addr("203.0.113.1:4000");
+        let dead = addr("[2001:db8::1]:4000");
+        let mut checks = CandidateChecks::new(&[public, lan, dead, lan]);
+        assert_eq!(checks.candidates().len(), 3);
+
+        let to_public = checks.start_check(public, 1_000);
+        let to_lan = checks.start_check(lan, 1_000);
+        let to_dead = checks.start_check(dead, 1_000);
+        assert_ne!(to_public, to_lan);
+
+        assert_eq!(
+            checks.record_reply(to_public, to_public + 40_000),
+            Some(public)
+        );
+        assert_eq!(checks.record_reply(to_lan, to_lan + 2_000), Some(lan));
+        assert_eq!(checks.record_reply(to_lan, to_lan + 2_000), None);
+        checks.clear_pending();
+        assert_eq!(checks.record_reply(to_dead, to_dead + 1_000), None);
+
+        assert_eq!(checks.best(), Some(lan));
+        assert_eq!(checks.spares(), vec![public]);
+
+        // Failing over promotes the spare until the old path answers again
+        assert_eq!(checks.fail_over(lan), Some(public));
+        assert!(checks.spares().is_empty());
+        assert_eq!(checks.fail_over(public), None);
+        let to_lan = checks.start_check(lan, 50_000);
+        checks.record_reply(to_lan, 53_000);
+        assert_eq!(checks.best(), Some(lan));
+    }
+}
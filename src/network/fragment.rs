@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Most fragments one message may be split into
+pub const MAX_FRAGMENTS: usize = 64;
+
+/// How long a partly received message is kept waiting for its other fragments
+const FRAGMENT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Most messages reassembled at once, so a flood of first fragments can't use up memory
+const MAX_PENDING: usize = 16;
+
+/// A message still missing some of its fragments
+#[derive(Debug)]
+struct Partial {
+    parts: Vec<Option<Vec<u8>>>,
+    missing: usize,
+    started: Instant,
+}
+
+/// Puts messages split across several datagrams back together
+///
+/// Fragments may arrive in any order. Messages not completed within a
+/// couple of seconds are dropped, as audio that late is no use anyway.
+#[derive(Debug, Default)]
+pub struct Reassembler {
+    pending: HashMap<u32, Partial>,
+}
+
+impl Reassembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds fragment `index` of `count` for message `id`, returning the message once complete
+    pub fn push(
+        &mut self,
+        id: u32,
+        index: u16,
+        count: u16,
+        data: &[u8],
+        now: Instant,
+    ) -> Option<Vec<u8>> {
+        let (index, count) = (index as usize, count as usize);
+        if count == 0 || count > MAX_FRAGMENTS || index >= count {
+            return None;
+        }
+
+        self.pending
+            .retain(|_, partial| now.duration_since(partial.started) < FRAGMENT_TIMEOUT);
+        if !self.pending.contains_key(&id) && self.pending.len() >= MAX_PENDING {
+            return None;
+        }
+
+        let partial = self.pending.entry(id).or_insert_with(|| Partial {
+            parts: vec![None; count],
+            missing: count,
+            started: now,
+        });
+        if partial.parts.len() != count {
+            return None;
+        }
+        if partial.parts[index].is_none() {
+            partial.parts[index] = Some(data.to_vec());
+            partial.missing -= 1;
+        }
+
+        if partial.missing > 0 {
+            return None;
+        }
+        self.pending
+            .remove(&id)
+            .map(|partial| partial.parts.into_iter().flatten().flatten().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reassembles_out_of_order() {
+        let mut reassembler = Reassembler::new();
+        let now = Instant::now();
+        let message: Vec<u8> = (0..=255).collect();
+        let chunks: Vec<&[u8]> = message.chunks(100).collect();
+
+        assert_eq!(reassembler.push(1, 2, 3, chunks[2], now), None);
+        assert_eq!(reassembler.push(1, 0, 3, chunks[0], now), None);
+        assert_eq!(reassembler.push(1, 0, 3, chunks[0], now), None);
+        assert_eq!(reassembler.push(1, 1, 3, chunks[1], now), Some(message));
+        assert_eq!(reassembler.pending.len(), 0);
+    }
+
+    #[test]
+    fn test_drops_stale_and_invalid_fragments() {
+        let mut reassembler = Reassembler::new();
+        let now = Instant::now();
+
+        assert_eq!(reassembler.push(1, 0, 2, b"a", now), None);
+        assert_eq!(
+            reassembler.push(1, 1, 2, b"b", now + FRAGMENT_TIMEOUT),
+            None
+        );
+        assert_eq!(reassembler.pending.len(), 1);
+
+        assert_eq!(reassembler.push(2, 2, 2, b"c", now), None);
+        assert_eq!(
+            reassembler.push(3, 0, MAX_FRAGMENTS as u16 + 1, b"d", now),
+            None
+        );
+        assert_eq!(reassembler.pending.len(), 1);
+    }
+}
@@ -0,0 +1,568 @@
+use anyhow::{anyhow, Result};
+use hmac::{Hmac, Mac};
+use md5::{Digest, Md5};
+use rand::{thread_rng, RngCore};
+use sha1::Sha1;
+use std::fmt;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::time::Instant;
+
+use super::stun::{mapped_address, StunConfig};
+
+/// Requests sent to a server under test before it counts as unreachable
+const TEST_ATTEMPTS: u32 = 3;
+
+/// Wait for the first request of a test; doubled after each retry
+const TEST_TIMEOUT: Duration = Duration::from_millis(500);
+
+const MAGIC_COOKIE: [u8; 4] = [0x21, 0x12, 0xA4, 0x42];
+const ALLOCATE_REQUEST: u16 = 0x0003;
+const ALLOCATE_SUCCESS: u16 = 0x0103;
+const ALLOCATE_ERROR: u16 = 0x0113;
+const REFRESH_REQUEST: u16 = 0x0004;
+const USERNAME: u16 = 0x0006;
+const MESSAGE_INTEGRITY: u16 = 0x0008;
+const ERROR_CODE: u16 = 0x0009;
+const LIFETIME: u16 = 0x000D;
+const REALM: u16 = 0x0014;
+const NONCE: u16 = 0x0015;
+const XOR_RELAYED_ADDRESS: u16 = 0x0016;
+const REQUESTED_TRANSPORT: u16 = 0x0019;
+
+/// UDP, as the protocol number REQUESTED-TRANSPORT carries
+const TRANSPORT_UDP: u8 = 17;
+
+/// A STUN or TURN server used to find our public address or relay audio
+///
+/// Written as `stun:host:port` or `turn:user:password@host:port`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum IceServer {
+    Stun {
+        address: String,
+    },
+    Turn {
+        address: String,
+        username: String,
+        credential: String,
+    },
+}
+
+impl IceServer {
+    /// Server as `host:port`
+    pub fn address(&self) -> &str {
+        match self {
+            IceServer::Stun { address } | IceServer::Turn { address, .. } => address,
+        }
+    }
+
+    /// URL for the WebRTC stack, e.g. `stun:host:port`
+    pub fn url(&self) -> String {
+        match self {
+            IceServer::Stun { address } => format!("stun:{}", address),
+            IceServer::Turn { address, .. } => format!("turn:{}", address),
+        }
+    }
+}
+
+impl fmt::Display for IceServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IceServer::Stun { address } => write!(f, "stun:{}", address),
+            IceServer::Turn {
+                address,
+                username,
+                credential,
+            } => write!(f, "turn:{}:{}@{}", username, credential, address),
+        }
+    }
+}
+
+impl FromStr for IceServer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid ICE server: {}", s);
+        let valid_address = |address: &str| {
+            address
+                .rsplit_once(':')
+                .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok())
+        };
+
+        // The list is kept in one config line, separated by ';'
+        if s.contains(';') || s.chars().any(char::is_whitespace) {
+            return Err(invalid());
+        }
+        match s.split_once(':') {
+            Some(("stun", address)) if valid_address(address) => Ok(IceServer::Stun {
+                address: address.to_string(),
+            }),
+            Some(("turn", rest)) => {
+                // Passwords may contain '@', so the address is after the last one
+                let (credentials, address) = rest.rsplit_once('@').ok_or_else(invalid)?;
+                let (username, credential) = credentials.split_once(':').ok_or_else(invalid)?;
+                if username.is_empty() || !valid_address(address) {
+                    return Err(invalid());
+                }
+                Ok(IceServer::Turn {
+                    address: address.to_string(),
+                    username: username.to_string(),
+                    credential: credential.to_string(),
+                })
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// STUN and TURN servers, in the order they're tried
+///
+/// An empty list means the built-in public STUN servers.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IceServerList {
+    servers: Vec<IceServer>,
+}
+
+impl IceServerList {
+    /// Adds a server at the lowest priority, unless it's already listed
+    pub fn add(&mut self, server: IceServer) {
+        if !self.servers.contains(&server) {
+            self.servers.push(server);
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<IceServer> {
+        (index < self.servers.len()).then(|| self.servers.remove(index))
+    }
+
+    /// Moves a server one place up, to be tried sooner; returns its new index
+    pub fn move_up(&mut self, index: usize) -> usize {
+        if index > 0 && index < self.servers.len() {
+            self.servers.swap(index, index - 1);
+            index - 1
+        } else {
+            index
+        }
+    }
+
+    /// Moves a server one place down, to be tried later; returns its new index
+    pub fn move_down(&mut self, index: usize) -> usize {
+        if index + 1 < self.servers.len() {
+            self.servers.swap(index, index + 1);
+            index + 1
+        } else {
+            index
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<&IceServer> {
+        self.servers.get(index)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &IceServer> {
+        self.servers.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.servers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.servers.is_empty()
+    }
+
+    /// STUN settings for NAT discovery, using our STUN servers if there are any
+    ///
+    /// TURN servers answer Binding requests too, so they're asked as well.
+    pub fn stun_config(&self) -> StunConfig {
+        if self.servers.is_empty() {
+            return StunConfig::default();
+        }
+        StunConfig::default().with_servers(
+            self.servers
+                .iter()
+                .map(|server| server.address().to_string())
+                .collect(),
+        )
+    }
+}
+
+impl fmt::Display for IceServerList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries: Vec<String> = self.servers.iter().map(|s| s.to_string()).collect();
+        write!(f, "{}", entries.join(";"))
+    }
+}
+
+impl FromStr for IceServerList {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut list = IceServerList::default();
+        for entry in s.split(';').filter(|entry| !entry.trim().is_empty()) {
+            list.add(entry.trim().parse()?);
+        }
+        Ok(list)
+    }
+}
+
+/// Outcome of testing a server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IceTestResult {
+    /// Answered; for STUN the Binding round trip, for TURN the time to get an allocation
+    Passed(Duration),
+    Failed(String),
+}
+
+/// Checks that a server works: a Binding round trip for STUN, an allocation for TURN
+///
+/// A TURN allocation that succeeds is released again straight away.
+pub async fn test_ice_server(server: &IceServer) -> IceTestResult {
+    let result = match server {
+        IceServer::Stun { address } => test_stun(address, TEST_TIMEOUT).await,
+        IceServer::Turn {
+            address,
+            username,
+            credential,
+        } => test_turn(address, username, credential, TEST_TIMEOUT).await,
+    };
+    match result {
+        Ok(latency) => IceTestResult::Passed(latency),
+        Err(e) => IceTestResult::Failed(e.to_string()),
+    }
+}
+
+/// Resolves a server and binds a socket of the same family to reach it
+async fn connect(address: &str) -> Result<(UdpSocket, SocketAddr)> {
+    let server = tokio::net::lookup_host(address)
+        .await?
+        .next()
+        .ok_or_else(|| anyhow!("No address for {}", address))?;
+    let local = if server.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    };
+    Ok((UdpSocket::bind(local).await?, server))
+}
+
+async fn test_stun(address: &str, timeout: Duration) -> Result<Duration> {
+    // Resolved first, so the lookup isn't counted in the round trip
+    let (socket, server) = connect(address).await?;
+    let config = StunConfig::default()
+        .with_servers(vec![server.to_string()])
+        .with_retries(TEST_ATTEMPTS, timeout);
+
+    let started = Instant::now();
+    mapped_address(&socket, &config).await?;
+    Ok(started.elapsed())
+}
+
+async fn test_turn(
+    address: &str,
+    username: &str,
+    credential: &str,
+    timeout: Duration,
+) -> Result<Duration> {
+    let (socket, server) = connect(address).await?;
+    let started = Instant::now();
+
+    // The first request is expected to be refused with the realm and nonce to sign with
+    let transport = vec![TRANSPORT_UDP, 0, 0, 0];
+    let request = StunMessage::new(ALLOCATE_REQUEST).with(REQUESTED_TRANSPORT, transport.clone());
+    let challenge = transact(&socket, server, &request, None, timeout).await?;
+    if challenge.kind == ALLOCATE_SUCCESS {
+        release(&socket, server, None, timeout).await;
+        return Ok(started.elapsed());
+    }
+    let realm = challenge
+        .attribute(REALM)
+        .ok_or_else(|| anyhow!("Server refused the allocation: {}", challenge.error()))?
+        .to_vec();
+    let nonce = challenge
+        .attribute(NONCE)
+        .ok_or_else(|| anyhow!("Server sent no nonce"))?
+        .to_vec();
+
+    let key = long_term_key(username, &String::from_utf8_lossy(&realm), credential);
+    let auth = Auth {
+        username: username.as_bytes().to_vec(),
+        realm,
+        nonce,
+        key,
+    };
+    let request =
+        auth.sign(StunMessage::new(ALLOCATE_REQUEST).with(REQUESTED_TRANSPORT, transport));
+    let response = transact(&socket, server, &request, Some(&auth.key), timeout).await?;
+    if response.kind != ALLOCATE_SUCCESS {
+        return Err(anyhow!(
+            "Server refused the allocation: {}",
+            response.error()
+        ));
+    }
+    if response.attribute(XOR_RELAYED_ADDRESS).is_none() {
+        return Err(anyhow!("Server sent no relayed address"));
+    }
+    let latency = started.elapsed();
+
+    release(&socket, server, Some(&auth), timeout).await;
+    Ok(latency)
+}
+
+/// Gives an allocation back by refreshing it with a zero lifetime, ignoring failures
+async fn release(socket: &UdpSocket, server: SocketAddr, auth: Option<&Auth>, timeout: Duration) {
+    let refresh = StunMessage::new(REFRESH_REQUEST).with(LIFETIME, 0u32.to_be_bytes().to_vec());
+    let (refresh, key) = match auth {
+        Some(auth) => (auth.sign(refresh), Some(auth.key.as_slice())),
+        None => (refresh, None),
+    };
+    if let Err(e) = transact(socket, server, &refresh, key, timeout).await {
+        log::debug!("Failed to release TURN allocation on {}: {}", server, e);
+    }
+}
+
+/// Long-term credentials for a TURN server (RFC 8489 §9.2)
+struct Auth {
+    username: Vec<u8>,
+    realm: Vec<u8>,
+    nonce: Vec<u8>,
+    key: Vec<u8>,
+}
+
+impl Auth {
+    fn sign(&self, message: StunMessage) -> StunMessage {
+        message
+            .with(USERNAME, self.username.clone())
+            .with(REALM, self.realm.clone())
+            .with(NONCE, self.nonce.clone())
+    }
+}
+
+/// Key for MESSAGE-INTEGRITY: MD5 of `username:realm:password`
+fn long_term_key(username: &str, realm: &str, credential: &str) -> Vec<u8> {
+    Md5::digest(format!("{}:{}:{}", username, realm, credential).as_bytes()).to_vec()
+}
+
+/// Sends a request and waits for the response with its transaction ID, resending with a doubling timeout
+async fn transact(
+    socket: &UdpSocket,
+    server: SocketAddr,
+    request: &StunMessage,
+    key: Option<&[u8]>,
+    timeout: Duration,
+) -> Result<StunMessage> {
+    let data = request.encode(key);
+    let mut timeout = timeout;
+    let mut buf = [0u8; 1500];
+
+    for _ in 0..TEST_ATTEMPTS {
+        socket.send_to(&data, server).await?;
+        let deadline = Instant::now() + timeout;
+        loop {
+            let size = match tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await {
+                Ok(Ok((size, from))) if from == server => size,
+                Ok(Ok(_)) => continue,
+                Ok(Err(e)) => return Err(e.into()),
+                Err(_) => break,
+            };
+            match StunMessage::decode(&buf[..size]) {
+                Some(response) if response.transaction_id == request.transaction_id => {
+                    return Ok(response)
+                }
+                _ => continue,
+            }
+        }
+        timeout *= 2;
+    }
+    Err(anyhow!("No answer from {}", server))
+}
+
+/// A STUN message, as used for TURN
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct StunMessage {
+    kind: u16,
+    transaction_id: [u8; 12],
+    attributes: Vec<(u16, Vec<u8>)>,
+}
+
+impl StunMessage {
+    fn new(kind: u16) -> Self {
+        let mut transaction_id = [0u8; 12];
+        thread_rng().fill_bytes(&mut transaction_id);
+        Self {
+            kind,
+            transaction_id,
+            attributes: Vec::new(),
+        }
+    }
+
+    fn with(mut self, attribute: u16, value: Vec<u8>) -> Self {
+        self.attributes.push((attribute, value));
+        self
+    }
+
+    fn attribute(&self, attribute: u16) -> Option<&[u8]> {
+        self.attributes
+            .iter()
+            .find(|(kind, _)| *kind == attribute)
+            .map(|(_, value)| value.as_slice())
+    }
+
+    /// Error code and reason of an error response, for messages
+    fn error(&self) -> String {
+        match self.attribute(ERROR_CODE) {
+            Some(value) if value.len() >= 4 => format!(
+                "{} {}",
+                (value[2] & 0x07) as u16 * 100 + value[3] as u16,
+                String::from_utf8_lossy(&value[4..])
+            ),
+            _ if self.kind == ALLOCATE_ERROR => "unknown error".to_string(),
+            _ => format!("unexpected response {:#06x}", self.kind),
+        }
+    }
+
+    /// Encodes the message, ending with MESSAGE-INTEGRITY if given a key
+    fn encode(&self, key: Option<&[u8]>) -> Vec<u8> {
+        let mut data = self.kind.to_be_bytes().to_vec();
+        data.extend_from_slice(&[0, 0]);
+        data.extend_from_slice(&MAGIC_COOKIE);
+        data.extend_from_slice(&self.transaction_id);
+        for (attribute, value) in &self.attributes {
+            if *attribute == MESSAGE_INTEGRITY {
+                continue;
+            }
+            data.extend_from_slice(&attribute.to_be_bytes());
+            data.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            data.extend_from_slice(value);
+            // Attributes are padded to 4 bytes
+            data.resize((data.len() + 3) & !3, 0);
+        }
+
+        if let Some(key) = key {
+            // The length covers the integrity attribute when the HMAC is taken
+            let length = (data.len() - 20 + 24) as u16;
+            data[2..4].copy_from_slice(&length.to_be_bytes());
+            let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC takes any key length");
+            mac.update(&data);
+            data.extend_from_slice(&MESSAGE_INTEGRITY.to_be_bytes());
+            data.extend_from_slice(&20u16.to_be_bytes());
+            data.extend_from_slice(&mac.finalize().into_bytes());
+        }
+
+        let length = (data.len() - 20) as u16;
+        data[2..4].copy_from_slice(&length.to_be_bytes());
+        data
+    }
+
+    fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() < 20 || data[4..8] != MAGIC_COOKIE {
+            return None;
+        }
+        let length = u16::from_be_bytes([data[2], data[3]]) as usize;
+        if data.len() < 20 + length {
+            return None;
+        }
+
+        let mut attributes = Vec::new();
+        let mut pos = 20;
+        while pos + 4 <= 20 + length {
+            let attribute = u16::from_be_bytes([data[pos], data[pos + 1]]);
+            let size = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+            pos += 4;
+            let value = data.get(pos..pos + size)?;
+            attributes.push((attribute, value.to_vec()));
+            pos += (size + 3) & !3;
+        }
+
+        Some(Self {
+            kind: u16::from_be_bytes([data[0], data[1]]),
+            transaction_id: data[8..20].try_into().ok()?,
+            attributes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_list_round_trip_and_order() {
+        let mut list: IceServerList =
+            "stun:stun.example.org:3478;turn:alice:p@ss:word@turn.example.org:3478"
+                .parse()
+                .unwrap();
+        assert_eq!(list.len(), 2);
+        assert_eq!(
+            list.get(1),
+            Some(&IceServer::Turn {
+                address: "turn.example.org:3478".to_string(),
+                username: "alice".to_string(),
+                credential: "p@ss:word".to_string(),
+            })
+        );
+        assert_eq!(list.to_string().parse(), Ok(list.clone()));
+
+        assert_eq!(list.move_up(1), 0);
+        assert_eq!(
+            list.get(0).map(|s| s.url()),
+            Some("turn:turn.example.org:3478".to_string())
+        );
+        assert_eq!(list.move_down(1), 1);
+        list.add("stun:stun.example.org:3478".parse().unwrap());
+        assert_eq!(list.len(), 2);
+        assert!(list.remove(0).is_some());
+        assert_eq!(
+            list.stun_config().servers,
+            vec!["stun.example.org:3478".to_string()]
+        );
+
+        for invalid in [
+            "stun:example.org",
+            "turn:example.org:3478",
+            "udp:a:1",
+            "stun:a b:1",
+        ] {
+            assert!(invalid.parse::<IceServer>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_turn_allocation_with_credentials() {
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = server.local_addr().unwrap().to_string();
+        let key = long_term_key("alice", "example.org", "secret");
+
+        // Challenges unsigned requests, allocates for correctly signed ones
+        tokio::spawn(async move {
+            let mut buf = [0u8; 1500];
+            loop {
+                let (size, from) = server.recv_from(&mut buf).await.unwrap();
+                let request = StunMessage::decode(&buf[..size]).unwrap();
+                let mut reply = StunMessage::new(ALLOCATE_ERROR);
+                reply.transaction_id = request.transaction_id;
+                let reply = if request.attribute(MESSAGE_INTEGRITY).is_none() {
+                    reply
+                        .with(ERROR_CODE, vec![0, 0, 4, 1])
+                        .with(REALM, b"example.org".to_vec())
+                        .with(NONCE, b"n0nce".to_vec())
+                } else if request.encode(Some(&key)) == buf[..size] {
+                    reply.kind = request.kind | 0x0100;
+                    reply.with(XOR_RELAYED_ADDRESS, vec![0, 1, 0, 0, 0, 0, 0, 0])
+                } else {
+                    reply.with(ERROR_CODE, vec![0, 0, 4, 1])
+                };
+                server.send_to(&reply.encode(None), from).await.unwrap();
+            }
+        });
+
+        let timeout = Duration::from_millis(200);
+        assert!(test_turn(&address, "alice", "secret", timeout)
+            .await
+            .is_ok());
+        let refused = test_turn(&address, "alice", "wrong", timeout).await;
+        assert!(refused.unwrap_err().to_string().contains("refused"));
+    }
+}
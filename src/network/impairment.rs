@@ -0,0 +1,275 @@
+use rand::Rng;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Longest a datagram may wait behind a bandwidth cap before it's dropped instead
+pub const QUEUE_LIMIT: Duration = Duration::from_millis(500);
+
+/// Faults injected into what we send, to try out loss concealment, FEC and
+/// jitter buffers on a real call
+///
+/// Written as `none`, or comma-separated settings such as
+/// `drop=5,latency=80,jitter=40,dup=1,rate=128`: percent dropped, added
+/// latency and random jitter in milliseconds, percent sent twice, and an
+/// upload cap in kbps.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Impairment {
+    /// Percent of datagrams dropped
+    pub drop_percent: f32,
+    /// Added to every datagram
+    pub latency: Duration,
+    /// Up to this much more, at random, so datagrams arrive out of order
+    pub jitter: Duration,
+    /// Percent of datagrams sent twice
+    pub duplicate_percent: f32,
+    /// Upload cap; datagrams queue behind it, and are dropped past [`QUEUE_LIMIT`]
+    pub bandwidth_kbps: Option<u32>,
+}
+
+/// Impairments the debug menu steps through, by name
+pub const IMPAIRMENT_PRESETS: [(&str, Impairment); 5] = [
+    ("off", Impairment::NONE),
+    (
+        "lossy",
+        Impairment {
+            drop_percent: 10.0,
+            ..Impairment::NONE
+        },
+    ),
+    (
+        "jittery",
+        Impairment {
+            latency: Duration::from_millis(60),
+            jitter: Duration::from_millis(80),
+            ..Impairment::NONE
+        },
+    ),
+    (
+        "congested",
+        Impairment {
+            bandwidth_kbps: Some(48),
+            ..Impairment::NONE
+        },
+    ),
+    (
+        "terrible",
+        Impairment {
+            drop_percent: 15.0,
+            latency: Duration::from_millis(150),
+            jitter: Duration::from_millis(100),
+            duplicate_percent: 5.0,
+            bandwidth_kbps: Some(64),
+        },
+    ),
+];
+
+impl Impairment {
+    pub const NONE: Impairment = Impairment {
+        drop_percent: 0.0,
+        latency: Duration::ZERO,
+        jitter: Duration::ZERO,
+        duplicate_percent: 0.0,
+        bandwidth_kbps: None,
+    };
+
+    pub fn is_none(&self) -> bool {
+        *self == Impairment::NONE
+    }
+}
+
+impl fmt::Display for Impairment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_none() {
+            return write!(f, "none");
+        }
+        let mut settings = Vec::new();
+        if self.drop_percent > 0.0 {
+            settings.push(format!("drop={}", self.drop_percent));
+        }
+        if !self.latency.is_zero() {
+            settings.push(format!("latency={}", self.latency.as_millis()));
+        }
+        if !self.jitter.is_zero() {
+            settings.push(format!("jitter={}", self.jitter.as_millis()));
+        }
+        if self.duplicate_percent > 0.0 {
+            settings.push(format!("dup={}", self.duplicate_percent));
+        }
+        if let Some(kbps) = self.bandwidth_kbps {
+            settings.push(format!("rate={}", kbps));
+        }
+        write!(f, "{}", settings.join(","))
+    }
+}
+
+impl FromStr for Impairment {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s == "none" || s == "off" {
+            return Ok(Impairment::NONE);
+        }
+        if let Some((_, preset)) = IMPAIRMENT_PRESETS.iter().find(|(name, _)| *name == s) {
+            return Ok(*preset);
+        }
+
+        let mut impairment = Impairment::NONE;
+        for setting in s.split(',') {
+            let (name, value) = setting
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid impairment: {}", setting))?;
+            let invalid = || format!("Invalid value for {}: {}", name, value);
+            let percent = || {
+                value
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|percent| (0.0..=100.0).contains(percent))
+                    .ok_or_else(invalid)
+            };
+            let millis = || {
+                value
+                    .parse()
+                    .map(Duration::from_millis)
+                    .map_err(|_| invalid())
+            };
+            match name.trim() {
+                "drop" => impairment.drop_percent = percent()?,
+                "latency" => impairment.latency = millis()?,
+                "jitter" => impairment.jitter = millis()?,
+                "dup" => impairment.duplicate_percent = percent()?,
+                "rate" => {
+                    impairment.bandwidth_kbps = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|kbps| *kbps > 0)
+                            .ok_or_else(invalid)?,
+                    )
+                }
+                other => return Err(format!("Unknown impairment: {}", other)),
+            }
+        }
+        Ok(impairment)
+    }
+}
+
+/// Decides the fate of each datagram under an impairment
+struct Impairer {
+    settings: Impairment,
+    /// When the datagrams queued behind the bandwidth cap will have gone
+    queue_until: Instant,
+}
+
+impl Impairer {
+    /// How long from now each copy of a `len`-byte datagram should go out; none to drop it
+    fn plan(&mut self, len: usize, now: Instant, rng: &mut impl Rng) -> Vec<Duration> {
+        let settings = self.settings;
+        if rng.gen::<f32>() * 100.0 < settings.drop_percent {
+            return Vec::new();
+        }
+
+        let mut queued = Duration::ZERO;
+        if let Some(kbps) = settings.bandwidth_kbps {
+            let start = self.queue_until.max(now);
+            if start - now > QUEUE_LIMIT {
+                return Vec::new();
+            }
+            self.queue_until =
+                start + Duration::from_secs_f64(len as f64 * 8.0 / (kbps as f64 * 1000.0));
+            queued = self.queue_until - now;
+        }
+
+        let copies = if rng.gen::<f32>() * 100.0 < settings.duplicate_percent {
+            2
+        } else {
+            1
+        };
+        (0..copies)
+            .map(|_| queued + settings.latency + settings.jitter.mul_f64(rng.gen()))
+            .collect()
+    }
+}
+
+/// Set while an impairment is in effect, so unimpaired sends skip the lock
+static IMPAIRED: AtomicBool = AtomicBool::new(false);
+
+fn impairer() -> &'static Mutex<Impairer> {
+    static IMPAIRER: OnceLock<Mutex<Impairer>> = OnceLock::new();
+    IMPAIRER.get_or_init(|| {
+        Mutex::new(Impairer {
+            settings: Impairment::NONE,
+            queue_until: Instant::now(),
+        })
+    })
+}
+
+/// Impairs everything sent on every connection from now on, or stops with [`Impairment::NONE`]
+///
+/// For testing only: it makes calls worse on purpose.
+pub fn set_impairment(impairment: Impairment) {
+    let mut impairer = impairer().lock().unwrap();
+    impairer.settings = impairment;
+    impairer.queue_until = Instant::now();
+    IMPAIRED.store(!impairment.is_none(), Ordering::Relaxed);
+    if !impairment.is_none() {
+        log::warn!("Impairing the network: {}", impairment);
+    }
+}
+
+/// The impairment in effect
+pub fn impairment() -> Impairment {
+    impairer().lock().unwrap().settings
+}
+
+/// How long from now to send each copy of a `len`-byte datagram, or `None` if unimpaired
+pub(crate) fn impair(len: usize) -> Option<Vec<Duration>> {
+    if !IMPAIRED.load(Ordering::Relaxed) {
+        return None;
+    }
+    let mut impairer = impairer().lock().unwrap();
+    Some(impairer.plan(len, Instant::now(), &mut rand::thread_rng()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_impairment_plans() {
+        let spec = "drop=5,latency=80,jitter=40,dup=1,rate=128";
+        assert_eq!(spec.parse::<Impairment>().unwrap().to_string(), spec);
+        assert_eq!("off".parse(), Ok(Impairment::NONE));
+        assert_eq!("lossy".parse::<Impairment>().unwrap().drop_percent, 10.0);
+        assert!("drop=150".parse::<Impairment>().is_err());
+        assert!("loud=3".parse::<Impairment>().is_err());
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let now = Instant::now();
+        let mut impairer = Impairer {
+            settings: "drop=100".parse().unwrap(),
+            queue_until: now,
+        };
+        assert!(impairer.plan(100, now, &mut rng).is_empty());
+
+        impairer.settings = "dup=100,latency=50,jitter=20".parse().unwrap();
+        let plan = impairer.plan(100, now, &mut rng);
+        assert_eq!(plan.len(), 2);
+        assert!(plan
+            .iter()
+            .all(|delay| (50..=70).contains(&delay.as_millis())));
+
+        // 1250 bytes at 100 kbps take 100 ms each; past half a second queued they're dropped
+        impairer.settings = "rate=100".parse().unwrap();
+        let delays: Vec<Vec<Duration>> =
+            (0..7).map(|_| impairer.plan(1250, now, &mut rng)).collect();
+        assert_eq!(delays[0], vec![Duration::from_millis(100)]);
+        assert_eq!(delays[5], vec![Duration::from_millis(600)]);
+        assert!(delays[6].is_empty());
+    }
+}
@@ -0,0 +1,99 @@
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::p2p::ConnectionState;
+use super::secure_channel::NonceStrategy;
+use super::socket_buffers::GrantedBuffers;
+use super::stats::PacketCounts;
+use super::tamper::CryptoStats;
+use crate::audio::AudioCodec;
+
+/// Most events a connection's history keeps
+pub const HISTORY_LEN: usize = 32;
+
+/// Something that happened on a connection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionEvent {
+    pub at: Instant,
+    pub description: String,
+}
+
+/// The most recent events on a connection, shared by its background tasks
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionHistory {
+    events: Arc<Mutex<VecDeque<ConnectionEvent>>>,
+}
+
+impl ConnectionHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an event, forgetting the oldest past [`HISTORY_LEN`]
+    pub fn record(&self, description: impl Into<String>) {
+        let mut events = self.events.lock().unwrap();
+        if events.len() == HISTORY_LEN {
+            events.pop_front();
+        }
+        events.push_back(ConnectionEvent {
+            at: Instant::now(),
+            description: description.into(),
+        });
+    }
+
+    /// Events kept, oldest first
+    pub fn events(&self) -> Vec<ConnectionEvent> {
+        self.events.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+/// How one connection is set up right now, for the developer inspector
+#[derive(Debug, Clone)]
+pub struct ConnectionDetails {
+    pub state: ConnectionState,
+    /// AEAD the channel encrypts with; none before the handshake
+    pub cipher: Option<&'static str>,
+    pub nonce_strategy: NonceStrategy,
+    /// Whether outgoing messages use ratcheted keys
+    pub forward_secrecy: bool,
+    /// Time since the keys were agreed, or since the channel was handed to us
+    pub key_age: Option<Duration>,
+    /// Codec audio is sent with, as negotiated with the peer
+    pub codec: AudioCodec,
+    /// Bitrate asked of the encoder; only Opus follows it
+    pub bitrate_kbps: u32,
+    /// The candidate pair in use: our socket's address and the peer's
+    pub local_addr: Option<SocketAddr>,
+    pub remote_addr: SocketAddr,
+    /// Largest datagram sent on the path
+    pub max_datagram: usize,
+    /// Buffer sizes the OS gave our socket
+    pub socket_buffers: Option<GrantedBuffers>,
+    /// Packets the peer sent and how many arrived, going by sequence gaps
+    pub packets: PacketCounts,
+    pub crypto: CryptoStats,
+    pub history: Vec<ConnectionEvent>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_keeps_most_recent() {
+        let history = ConnectionHistory::new();
+        let shared = history.clone();
+        for i in 0..HISTORY_LEN + 3 {
+            shared.record(format!("event {}", i));
+        }
+        let events = history.events();
+        assert_eq!(events.len(), HISTORY_LEN);
+        assert_eq!(events[0].description, "event 3");
+        assert_eq!(
+            events.last().unwrap().description,
+            format!("event {}", HISTORY_LEN + 2)
+        );
+    }
+}
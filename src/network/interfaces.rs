@@ -0,0 +1,149 @@
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::str::FromStr;
+use tokio::net::UdpSocket;
+
+use super::socket_buffers::apply_socket_buffers;
+
+/// An address assigned to one of this machine's network interfaces
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkInterface {
+    /// Name the system gives the interface, such as `eth0` or `wg0`
+    pub name: String,
+    pub address: IpAddr,
+}
+
+impl fmt::Display for NetworkInterface {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.name, self.address)
+    }
+}
+
+/// Lists the addresses of every network interface, IPv4 first
+pub fn list_interfaces() -> Result<Vec<NetworkInterface>> {
+    let mut interfaces: Vec<NetworkInterface> = if_addrs::get_if_addrs()?
+        .into_iter()
+        .map(|interface| NetworkInterface {
+            address: interface.ip(),
+            name: interface.name,
+        })
+        .collect();
+    interfaces.sort_by_key(|interface| (interface.address.is_ipv6(), interface.name.clone()));
+    Ok(interfaces)
+}
+
+/// Where our UDP sockets are bound, and so which interface traffic leaves from
+///
+/// Written as `auto`, an interface name or an address. Binding to a VPN's
+/// interface keeps calls inside the tunnel; binding to the LAN keeps them
+/// out of it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum BindAddress {
+    /// Any interface, leaving the choice to the system's routing
+    #[default]
+    Auto,
+    /// The first IPv4 address of the named interface, looked up at bind time
+    Interface(String),
+    Address(IpAddr),
+}
+
+impl BindAddress {
+    /// Address to bind, checking it belongs to this machine
+    pub fn resolve(&self) -> Result<IpAddr> {
+        match self {
+            BindAddress::Auto => Ok(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+            BindAddress::Address(address) => {
+                let local = address.is_loopback()
+                    || list_interfaces()?
+                        .iter()
+                        .any(|interface| interface.address == *address);
+                if !local {
+                    return Err(anyhow!("{} is not an address of this machine", address));
+                }
+                Ok(*address)
+            }
+            BindAddress::Interface(name) => {
+                let addresses: Vec<IpAddr> = list_interfaces()?
+                    .into_iter()
+                    .filter(|interface| interface.name == *name)
+                    .map(|interface| interface.address)
+                    .collect();
+                addresses
+                    .iter()
+                    .find(|address| address.is_ipv4())
+                    .or_else(|| addresses.first())
+                    .copied()
+                    .ok_or_else(|| anyhow!("No network interface named {}", name))
+            }
+        }
+    }
+}
+
+impl fmt::Display for BindAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BindAddress::Auto => write!(f, "auto"),
+            BindAddress::Interface(name) => write!(f, "{}", name),
+            BindAddress::Address(address) => write!(f, "{}", address),
+        }
+    }
+}
+
+impl FromStr for BindAddress {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s == "auto" {
+            return Ok(BindAddress::Auto);
+        }
+        if let Ok(address) = s.parse::<IpAddr>() {
+            return Ok(BindAddress::Address(address));
+        }
+        let valid_name = !s.is_empty()
+            && s.chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_.:".contains(c));
+        if valid_name {
+            Ok(BindAddress::Interface(s.to_string()))
+        } else {
+            Err(format!("Invalid bind address: {}", s))
+        }
+    }
+}
+
+/// Binds a UDP socket on any free port of the chosen interface, with the
+/// configured buffer sizes
+pub async fn bind_udp(bind: &BindAddress) -> Result<UdpSocket> {
+    let address = bind.resolve()?;
+    let socket = UdpSocket::bind(SocketAddr::new(address, 0)).await?;
+    apply_socket_buffers(&socket);
+    Ok(socket)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_bind_address_is_checked_against_local_interfaces() {
+        for text in ["auto", "wg0", "192.168.1.20", "fe80::1"] {
+            let bind: BindAddress = text.parse().unwrap();
+            assert_eq!(bind.to_string(), text);
+        }
+        assert!("".parse::<BindAddress>().is_err());
+        assert!("eth 0".parse::<BindAddress>().is_err());
+
+        let loopback = BindAddress::Address(Ipv4Addr::LOCALHOST.into());
+        let socket = bind_udp(&loopback).await.unwrap();
+        assert!(socket.local_addr().unwrap().ip().is_loopback());
+        assert!(bind_udp(&BindAddress::Auto).await.is_ok());
+
+        // TEST-NET-1 is never assigned to a real interface
+        let foreign = BindAddress::Address("192.0.2.1".parse().unwrap());
+        assert!(foreign.resolve().is_err());
+        assert!(BindAddress::Interface("no-such-if0".into())
+            .resolve()
+            .is_err());
+    }
+}
@@ -0,0 +1,196 @@
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+
+use super::p2p::stun_mapped_address;
+
+/// Datagram sent only to refresh NAT bindings; receivers ignore it
+pub const KEEPALIVE_DATAGRAM: [u8; 1] = [0];
+
+/// Returns whether a received datagram is a bare keepalive
+pub fn is_keepalive(packet: &[u8]) -> bool {
+    packet == KEEPALIVE_DATAGRAM
+}
+
+/// Bounds for adaptive keepalive
+#[derive(Debug, Clone)]
+pub struct KeepaliveConfig {
+    /// Shortest interval, used after bindings are seen to expire
+    pub min_interval: Duration,
+    /// Longest interval, used when no NAT is in the way
+    pub max_interval: Duration,
+    /// Starting interval for peers behind a NAT, before the binding lifetime is known
+    pub initial_interval: Duration,
+}
+
+impl Default for KeepaliveConfig {
+    fn default() -> Self {
+        Self {
+            min_interval: Duration::from_secs(5),
+            max_interval: Duration::from_secs(25),
+            initial_interval: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Picks how often to refresh a peer's NAT binding
+///
+/// The interval starts from the network type, is capped by the measured
+/// binding lifetime, shrinks when the peer goes quiet, and grows back slowly
+/// while traffic keeps flowing.
+#[derive(Debug, Clone)]
+pub struct AdaptiveKeepalive {
+    config: KeepaliveConfig,
+    interval: Duration,
+    ceiling: Duration,
+    healthy_rounds: u32,
+    behind_nat: bool,
+}
+
+impl AdaptiveKeepalive {
+    /// Creates the keepalive for a peer at `remote`
+    pub fn for_peer(remote: SocketAddr, config: KeepaliveConfig) -> Self {
+        let behind_nat = !is_local(remote.ip());
+        let interval = if behind_nat {
+            config.initial_interval
+        } else {
+            config.max_interval
+        };
+
+        Self {
+            ceiling: config.max_interval,
+            interval,
+            healthy_rounds: 0,
+            behind_nat,
+            config,
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Whether traffic to the peer likely crosses a NAT
+    pub fn behind_nat(&self) -> bool {
+        self.behind_nat
+    }
+
+    /// Silence after which the peer's binding is assumed to have expired
+    ///
+    /// Peers send at least every `max_interval`, so anything longer is suspect.
+    pub fn silence_threshold(&self) -> Duration {
+        self.config.max_interval + self.config.min_interval
+    }
+
+    /// Limits the interval to half the measured NAT binding lifetime
+    pub fn set_binding_lifetime(&mut self, lifetime: Duration) {
+        self.ceiling = (lifetime / 2).clamp(self.config.min_interval, self.config.max_interval);
+        self.interval = self.interval.min(self.ceiling);
+    }
+
+    /// Records that the peer was heard from within the last interval
+    pub fn record_healthy(&mut self) {
+        self.healthy_rounds += 1;
+
+        // Back off by a fifth after ten quiet-free intervals
+        if self.healthy_rounds >= 10 {
+            self.healthy_rounds = 0;
+            self.interval = (self.interval + self.interval / 5).min(self.ceiling);
+        }
+    }
+
+    /// Records that the peer went silent, which suggests the binding expired
+    pub fn record_silence(&mut self) {
+        self.healthy_rounds = 0;
+        self.interval = (self.interval / 2).max(self.config.min_interval);
+    }
+}
+
+/// LAN and loopback peers have no NAT binding to keep open
+fn is_local(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => ip.is_private() || ip.is_loopback() || ip.is_link_local(),
+        IpAddr::V6(ip) => ip.is_loopback() || (ip.segments()[0] & 0xfe00) == 0xfc00,
+    }
+}
+
+/// Estimates how long the NAT keeps an idle UDP binding open
+///
+/// Repeats a STUN query after increasing idle periods; a changed mapped
+/// address means the binding expired while idle. Returns the longest idle
+/// period the binding survived, or half the first period if none was survived.
+/// Takes as long as the sum of `idle_periods` when bindings last.
+pub async fn probe_binding_lifetime(idle_periods: &[Duration]) -> Option<Duration> {
+    let socket = UdpSocket::bind("0.0.0.0:0").await.ok()?;
+    let mut mapped = stun_mapped_address(&socket).await.ok()?;
+    let mut survived = None;
+
+    for &idle in idle_periods {
+        tokio::time::sleep(idle).await;
+
+        let current = stun_mapped_address(&socket).await.ok()?;
+        if current.ip != mapped.ip || current.port != mapped.port {
+            return Some(survived.unwrap_or(idle / 2));
+        }
+
+        survived = Some(idle);
+        mapped = current;
+    }
+
+    survived
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn public_peer() -> SocketAddr {
+        "203.0.113.9:4000".parse().unwrap()
+    }
+
+    #[test]
+    fn test_initial_interval_by_network() {
+        let config = KeepaliveConfig::default();
+
+        let lan = AdaptiveKeepalive::for_peer("192.168.1.7:4000".parse().unwrap(), config.clone());
+        assert_eq!(lan.interval(), config.max_interval);
+
+        let remote = AdaptiveKeepalive::for_peer(public_peer(), config.clone());
+        assert_eq!(remote.interval(), config.initial_interval);
+    }
+
+    #[test]
+    fn test_binding_lifetime_caps_interval() {
+        let mut keepalive = AdaptiveKeepalive::for_peer(public_peer(), KeepaliveConfig::default());
+
+        keepalive.set_binding_lifetime(Duration::from_secs(16));
+        assert_eq!(keepalive.interval(), Duration::from_secs(8));
+
+        // Growth stops at the ceiling
+        for _ in 0..100 {
+            keepalive.record_healthy();
+        }
+        assert_eq!(keepalive.interval(), Duration::from_secs(8));
+    }
+
+    #[test]
+    fn test_silence_shortens_and_health_restores() {
+        let mut keepalive = AdaptiveKeepalive::for_peer(public_peer(), KeepaliveConfig::default());
+
+        keepalive.record_silence();
+        assert_eq!(keepalive.interval(), Duration::from_secs(5));
+        keepalive.record_silence();
+        assert_eq!(keepalive.interval(), Duration::from_secs(5));
+
+        for _ in 0..10 {
+            keepalive.record_healthy();
+        }
+        assert_eq!(keepalive.interval(), Duration::from_secs(6));
+    }
+
+    #[test]
+    fn test_keepalive_datagram() {
+        assert!(is_keepalive(&KEEPALIVE_DATAGRAM));
+        assert!(!is_keepalive(&[0, 0]));
+    }
+}
@@ -0,0 +1,185 @@
+use bytes::BytesMut;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use super::secure_channel::{Keypair, Message};
+
+/// Packets from one peer, with the address each came from
+pub type Inbox = mpsc::UnboundedReceiver<(BytesMut, SocketAddr)>;
+
+type Routes = Arc<Mutex<HashMap<SocketAddr, mpsc::UnboundedSender<(BytesMut, SocketAddr)>>>>;
+
+/// A peer whose handshake for the room reached the host socket
+pub struct IncomingPeer {
+    pub addr: SocketAddr,
+    pub public_key: [u8; 32],
+    /// Everything the peer sends after its handshake, starting straight away
+    pub inbox: Inbox,
+}
+
+/// Shares the host socket among the peers that reach it
+///
+/// Everyone joining a room sends to the address in its link, so one socket
+/// carries them all. Packets are routed to each peer's connection by the
+/// address they came from; a handshake for the room from an address we
+/// haven't seen is a new peer.
+pub struct HostListener {
+    socket: Arc<UdpSocket>,
+    keypair: Keypair,
+    routes: Routes,
+    incoming: mpsc::UnboundedReceiver<IncomingPeer>,
+    task: JoinHandle<()>,
+}
+
+impl HostListener {
+    /// Starts routing what arrives on `socket` for the room `session_id`,
+    /// whose link carries the public half of `keypair`
+    pub fn start(socket: Arc<UdpSocket>, keypair: Keypair, session_id: String) -> Self {
+        let routes = Routes::default();
+        let (incoming_tx, incoming) = mpsc::unbounded_channel();
+        let task = tokio::spawn(route_packets(
+            socket.clone(),
+            session_id,
+            routes.clone(),
+            incoming_tx,
+        ));
+
+        Self {
+            socket,
+            keypair,
+            routes,
+            incoming,
+            task,
+        }
+    }
+
+    pub fn socket(&self) -> Arc<UdpSocket> {
+        self.socket.clone()
+    }
+
+    /// The key pair peers agree keys with
+    pub fn keypair(&self) -> Keypair {
+        self.keypair.clone()
+    }
+
+    /// The next peer to reach us since we last looked, if any has
+    pub fn try_accept(&mut self) -> Option<IncomingPeer> {
+        self.incoming.try_recv().ok()
+    }
+
+    /// Stops routing a peer's packets; a new handshake from it counts as a new peer
+    pub fn forget(&self, addr: SocketAddr) {
+        self.routes.lock().unwrap().remove(&addr);
+    }
+}
+
+impl Drop for HostListener {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn route_packets(
+    socket: Arc<UdpSocket>,
+    session_id: String,
+    routes: Routes,
+    incoming: mpsc::UnboundedSender<IncomingPeer>,
+) {
+    let mut buf = BytesMut::new();
+    loop {
+        buf.clear();
+        buf.reserve(65536);
+        let addr = match socket.recv_buf_from(&mut buf).await {
+            Ok((_, addr)) => addr,
+            Err(e) => {
+                log::debug!("Host socket receive failed: {}", e);
+                continue;
+            }
+        };
+        let packet = buf.split();
+
+        let mut routes = routes.lock().unwrap();
+        if let Some(route) = routes.get(&addr) {
+            if route.send((packet, addr)).is_err() {
+                // Nothing reads the peer's packets any more
+                routes.remove(&addr);
+            }
+            continue;
+        }
+
+        match Message::deserialize(&packet) {
+            Ok(Message::Handshake {
+                public_key,
+                session_id: room,
+            }) if room == session_id => {
+                log::info!("Handshake from {}", addr);
+                let (route, inbox) = mpsc::unbounded_channel();
+                routes.insert(addr, route);
+                let peer = IncomingPeer {
+                    addr,
+                    public_key,
+                    inbox,
+                };
+                if incoming.send(peer).is_err() {
+                    break;
+                }
+            }
+            _ => log::debug!("Ignoring a packet from {}", addr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    async fn handshake(socket: &UdpSocket, to: SocketAddr, session_id: &str) {
+        let handshake = Message::Handshake {
+            public_key: [7; 32],
+            session_id: session_id.to_string(),
+        };
+        let data = bincode::serialize(&handshake).unwrap();
+        socket.send_to(&data, to).await.unwrap();
+    }
+
+    async fn next_peer(listener: &mut HostListener) -> IncomingPeer {
+        for _ in 0..100 {
+            if let Some(peer) = listener.try_accept() {
+                return peer;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("No peer arrived");
+    }
+
+    #[tokio::test]
+    async fn test_routes_peers_by_address() {
+        let host = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let host_addr = host.local_addr().unwrap();
+        let mut listener = HostListener::start(host, Keypair::generate(), "room".to_string());
+
+        // Handshakes for another room are ignored
+        let stranger = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        handshake(&stranger, host_addr, "elsewhere").await;
+
+        let peer_socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        handshake(&peer_socket, host_addr, "room").await;
+        peer_socket.send_to(b"after", host_addr).await.unwrap();
+
+        let mut peer = next_peer(&mut listener).await;
+        assert_eq!(peer.addr, peer_socket.local_addr().unwrap());
+        assert_eq!(peer.public_key, [7; 32]);
+        let (packet, from) = tokio::time::timeout(Duration::from_secs(1), peer.inbox.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(&packet[..], b"after");
+        assert_eq!(from, peer.addr);
+        assert!(listener.try_accept().is_none());
+    }
+}
@@ -0,0 +1,74 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use super::clock_sync::now_micros;
+use super::secure_channel::Message;
+
+/// How often the local address is checked for changes
+pub const ADDRESS_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Furthest a probe's send time may be from ours, in microseconds
+///
+/// Generous, since the peers' clocks aren't synchronized.
+const MAX_PROBE_SKEW: u64 = 60_000_000;
+
+/// Recent probe nonces remembered, to turn away replays
+const REMEMBERED_PROBES: usize = 32;
+
+/// Builds a probe announcing that we now send from a new address
+pub fn path_probe() -> Message {
+    Message::PathProbe {
+        nonce: rand::random(),
+        sent_at: now_micros(),
+    }
+}
+
+/// Decides whether a path probe from a new address should be trusted
+///
+/// Probes are encrypted with the session keys, so only the peer can make
+/// one. A captured probe could still be replayed from somewhere else to
+/// hijack the connection, so probes must also be recent and unseen.
+#[derive(Debug, Default)]
+pub struct PathValidator {
+    seen: VecDeque<u64>,
+}
+
+impl PathValidator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks an authenticated probe received at `now`, remembering its nonce
+    pub fn accept(&mut self, nonce: u64, sent_at: u64, now: u64) -> bool {
+        if now.abs_diff(sent_at) > MAX_PROBE_SKEW || self.seen.contains(&nonce) {
+            return false;
+        }
+
+        self.seen.push_back(nonce);
+        if self.seen.len() > REMEMBERED_PROBES {
+            self.seen.pop_front();
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejects_stale_and_replayed_probes() {
+        let mut validator = PathValidator::new();
+        let now = 1_000_000_000;
+
+        assert!(validator.accept(7, now - 1_000, now));
+        assert!(!validator.accept(7, now - 1_000, now));
+        assert!(!validator.accept(8, now - MAX_PROBE_SKEW - 1, now));
+
+        // Only recent nonces are kept
+        for nonce in 100..100 + REMEMBERED_PROBES as u64 {
+            assert!(validator.accept(nonce, now, now));
+        }
+        assert!(validator.accept(7, now, now));
+    }
+}
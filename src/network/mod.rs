@@ -1,20 +1,82 @@
 // Export all necessary modules
+mod bandwidth;
+mod bundle;
+mod capabilities;
+mod clock_sync;
 pub mod connection_manager;
+mod connectivity;
+mod fragment;
+mod http;
+mod ice;
+mod impairment;
+mod inspect;
+mod interfaces;
+mod keepalive;
+mod listener;
+mod migration;
+mod mtu;
 pub mod p2p;
+mod pacing;
+mod peer_table;
+mod port_mapping;
+#[cfg(test)]
+mod protocol_schema;
+mod proxy;
+mod racing;
+mod ratchet;
+mod retry;
 mod secure_channel;
 mod security;
 mod signaling;
+mod socket_buffers;
+mod stats;
+mod stun;
+mod tamper;
 mod webrtc;
 
 // Re-export necessary components
-pub use connection_manager::ConnectionManager;
+pub use bandwidth::{
+    kbps_to_bytes, BandwidthCaps, BandwidthMeter, BandwidthMonitor, BandwidthUsage,
+    BitrateController, PeerBandwidth,
+};
+pub use bundle::{AudioBundler, BundledFrame, MAX_FRAMES_PER_BUNDLE};
+pub use capabilities::PeerCapabilities;
+pub use clock_sync::{now_micros, ClockSync};
+pub use connection_manager::{ConnectionHandoff, ConnectionManager};
+pub use connectivity::{Candidate, CandidateChecks, FAILOVER_SILENCE};
+pub use http::http_request;
+pub use ice::{test_ice_server, IceServer, IceServerList, IceTestResult};
+pub use impairment::{impairment, set_impairment, Impairment, IMPAIRMENT_PRESETS};
+pub use inspect::ConnectionDetails;
+pub use interfaces::{bind_udp, list_interfaces, BindAddress, NetworkInterface};
+pub use keepalive::{AdaptiveKeepalive, KeepaliveConfig};
+pub use listener::{HostListener, IncomingPeer};
+pub use migration::{path_probe, PathValidator, ADDRESS_CHECK_INTERVAL};
+pub use mtu::{mtu_probe, MtuConfig, PathMtu, DEFAULT_MAX_DATAGRAM_SIZE, MIN_DATAGRAM_SIZE};
 pub use p2p::{
-    discover_public_endpoint, establish_direct_udp_connection, generate_connection_link,
-    is_blocked_ip, parse_connection_link, ConnectionState, Endpoint,
+    add_link_candidates, discover_public_endpoint, establish_direct_udp_connection,
+    establish_udp_connection_from, filter_link_candidates, generate_connection_link, is_blocked_ip,
+    local_ipv4, parse_connection_link, parse_link_candidates, stun_mapped_address, CandidateClass,
+    ConnectionState, Endpoint, LinkPrivacy,
+};
+pub use pacing::{PacingConfig, SendPacer, SendPriority};
+pub use peer_table::{
+    EvictionStats, PeerState, PeerTable, DEFAULT_PEER_CAPACITY, HANDSHAKE_TIMEOUT,
+};
+pub use port_mapping::{MappingProtocol, PortMapper, PortMapping};
+pub use proxy::Proxy;
+pub use retry::{Retry, RetryOperation, RetrySettings};
+pub use secure_channel::{
+    ChannelState, CryptoProvider, Keypair, Message, NonceStrategy, SecureChannel,
 };
-pub use secure_channel::{Keypair, Message, SecureChannel};
 pub use security::SecurityModule;
 pub use signaling::{Peer, SessionInfo, SignalingInterface, SignalingService};
+pub use socket_buffers::{set_socket_buffers, socket_buffers, GrantedBuffers, SocketBuffers};
+pub use stats::{NetworkStats, PacketCounts, STATS_INTERVAL};
+pub use stun::{
+    detect_nat, ConnectionStrategy, NatMapping, NatReport, StunConfig, DEFAULT_STUN_SERVERS,
+};
+pub use tamper::{CryptoFailure, CryptoStats, TamperMonitor, TAMPER_THRESHOLD, TAMPER_WINDOW};
 pub use webrtc::{PeerConnection, WebRtcManager};
 
 // Networking errors
@@ -34,6 +96,9 @@ pub enum NetworkError {
     #[error("Connection lost: {0}")]
     ConnectionLost(String),
 
+    #[error("No answer from {0} to our handshake")]
+    HandshakeTimedOut(std::net::SocketAddr),
+
     #[error("Other error: {0}")]
     Other(#[from] anyhow::Error),
 }
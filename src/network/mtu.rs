@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use super::secure_channel::{Message, STATIC_PACKET_OVERHEAD};
+
+/// Datagram size every path is assumed to carry, as in QUIC
+pub const MIN_DATAGRAM_SIZE: usize = 1200;
+
+/// Largest datagram tried by default: a 1500 byte Ethernet MTU less the IPv4 and UDP headers
+pub const DEFAULT_MAX_DATAGRAM_SIZE: usize = 1472;
+
+/// How long to wait for a probe's acknowledgement before taking it as lost
+pub const PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Copies sent of each probe, so one lost packet doesn't shrink the path
+pub const PROBE_ATTEMPTS: usize = 2;
+
+/// Discovery stops once the largest working and smallest failing sizes are this close
+const SEARCH_PRECISION: usize = 16;
+
+/// Bytes an MTU probe message adds around its padding when serialized
+const PROBE_MESSAGE_OVERHEAD: usize = 4 + 8;
+
+/// How the largest datagram sent to each peer is chosen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MtuConfig {
+    /// Probe each peer's path for the largest datagram that gets through
+    pub discovery: bool,
+    /// Upper bound for discovery, or the size used as is when discovery is off
+    pub max_datagram: usize,
+}
+
+impl Default for MtuConfig {
+    fn default() -> Self {
+        Self {
+            discovery: true,
+            max_datagram: DEFAULT_MAX_DATAGRAM_SIZE,
+        }
+    }
+}
+
+/// Searches a peer's path for the largest datagram it carries
+///
+/// Starts from the size every path should carry and bisects towards the
+/// configured maximum with padded, authenticated probes the peer
+/// acknowledges.
+#[derive(Debug, Clone)]
+pub struct PathMtu {
+    // Largest size known to get through
+    confirmed: usize,
+    // Smallest size known not to, or one past the maximum
+    ceiling: usize,
+}
+
+impl PathMtu {
+    pub fn new(config: MtuConfig) -> Self {
+        let confirmed = if config.discovery {
+            MIN_DATAGRAM_SIZE.min(config.max_datagram)
+        } else {
+            config.max_datagram
+        };
+        Self {
+            confirmed,
+            ceiling: config.max_datagram + 1,
+        }
+    }
+
+    /// Largest datagram known to reach the peer
+    pub fn current(&self) -> usize {
+        self.confirmed
+    }
+
+    /// Size to probe next, or `None` once the search is done
+    pub fn next_probe(&self) -> Option<usize> {
+        (self.ceiling - self.confirmed > SEARCH_PRECISION)
+            .then(|| (self.confirmed + self.ceiling) / 2)
+    }
+
+    /// Records that the peer received a datagram of `size` bytes
+    pub fn confirm(&mut self, size: usize) {
+        if size > self.confirmed && size < self.ceiling {
+            self.confirmed = size;
+        }
+    }
+
+    /// Records that a probe of `size` bytes went unacknowledged
+    pub fn lost(&mut self, size: usize) {
+        if size > self.confirmed && size < self.ceiling {
+            self.ceiling = size;
+        }
+    }
+}
+
+/// Builds a probe padded so its encrypted datagram is `size` bytes
+pub fn mtu_probe(size: usize) -> Message {
+    let padding = size.saturating_sub(STATIC_PACKET_OVERHEAD + PROBE_MESSAGE_OVERHEAD);
+    Message::MtuProbe {
+        padding: vec![0; padding],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_settles_below_path_limit() {
+        let mut mtu = PathMtu::new(MtuConfig::default());
+        assert_eq!(mtu.current(), MIN_DATAGRAM_SIZE);
+
+        // A path that drops anything over 1400 bytes
+        while let Some(size) = mtu.next_probe() {
+            if size <= 1400 {
+                mtu.confirm(size);
+            } else {
+                mtu.lost(size);
+            }
+        }
+        assert!(mtu.current() <= 1400);
+        assert!(mtu.current() > 1400 - SEARCH_PRECISION);
+    }
+
+    #[test]
+    fn test_fixed_size_without_discovery() {
+        let mtu = PathMtu::new(MtuConfig {
+            discovery: false,
+            max_datagram: 1300,
+        });
+        assert_eq!(mtu.current(), 1300);
+        assert_eq!(mtu.next_probe(), None);
+    }
+}
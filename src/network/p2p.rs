@@ -1,11 +1,11 @@
 use anyhow::{anyhow, Result};
-use rand::{thread_rng, RngCore};
 use std::collections::HashMap;
-use std::net::{IpAddr, SocketAddr};
-use std::sync::{Arc, Mutex};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::time::{Duration, Instant};
 use tokio::net::UdpSocket;
-use uuid::Uuid;
+
+use super::interfaces::{bind_udp, BindAddress};
+use super::stun::{self, StunConfig};
 
 /// Public endpoint information
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -37,134 +37,41 @@ pub async fn discover_public_endpoint() -> Result<Endpoint> {
     // Create UDP socket
     let socket = UdpSocket::bind("0.0.0.0:0").await?;
 
-    // Use multiple STUN servers for reliability
-    let stun_servers = [
-        "stun1.l.google.com:19302",
-        "stun2.l.google.com:19302",
-        "stun.stunprotocol.org:3478",
-    ];
-
-    for server in stun_servers {
-        // Create STUN binding request
-        let request = create_stun_binding_request();
-
-        // Send to STUN server
-        socket.send_to(&request, server).await?;
-
-        // Receive response with timeout
-        let mut buf = [0u8; 512];
-        match tokio::time::timeout(Duration::from_secs(2), socket.recv_from(&mut buf)).await {
-            Ok(Ok((size, _))) => {
-                if let Some((ip, port)) = parse_stun_response(&buf[..size]) {
-                    return Ok(Endpoint { ip, port });
-                }
-            }
-            _ => continue,
-        }
-    }
-
-    Err(anyhow!("Failed to discover public endpoint"))
+    stun_mapped_address(&socket).await
 }
 
-/// Create a STUN binding request packet
-fn create_stun_binding_request() -> Vec<u8> {
-    let mut request = vec![
-        0x00, 0x01, // Message Type: Binding Request
-        0x00, 0x00, // Message Length: 0 (no attributes)
-        0x21, 0x12, 0xA4, 0x42, // Magic Cookie
-    ];
-
-    // Transaction ID (12 bytes)
-    let mut transaction_id = [0u8; 12];
-    thread_rng().fill_bytes(&mut transaction_id);
-    request.extend_from_slice(&transaction_id);
-
-    request
+/// Asks STUN servers which public address `socket` is currently mapped to
+pub async fn stun_mapped_address(socket: &UdpSocket) -> Result<Endpoint> {
+    stun::mapped_address(socket, &StunConfig::default()).await
 }
 
-/// Parse a STUN response to extract mapped address
-fn parse_stun_response(data: &[u8]) -> Option<(IpAddr, u16)> {
-    // Minimum packet size check
-    if data.len() < 20 {
-        return None;
-    }
-
-    // Check if it's a STUN response
-    if data[0] != 0x01 || data[1] != 0x01 {
-        return None;
-    }
-
-    // Extract length
-    let length = ((data[2] as usize) << 8) | (data[3] as usize);
-
-    // Make sure we have enough data
-    if data.len() < 20 + length {
-        return None;
-    }
-
-    // STUN attributes start after 20-byte header
-    let mut pos = 20;
-    while pos + 4 <= data.len() {
-        let attr_type = ((data[pos] as u16) << 8) | (data[pos + 1] as u16);
-        let attr_length = ((data[pos + 2] as usize) << 8) | (data[pos + 3] as usize);
-
-        pos += 4;
-
-        // Check if we have the XOR-MAPPED-ADDRESS attribute (0x0020)
-        if attr_type == 0x0020 && pos + attr_length <= data.len() {
-            // Skip first byte (reserved) and get family
-            let family = data[pos + 1];
-
-            // Get port (XOR with magic cookie first 2 bytes)
-            let xor_port = ((data[pos + 2] as u16) << 8) | (data[pos + 3] as u16);
-            let port = xor_port ^ 0x2112;
-
-            if family == 0x01 {
-                // IPv4
-                let xor_ip = [
-                    data[pos + 4] ^ 0x21,
-                    data[pos + 5] ^ 0x12,
-                    data[pos + 6] ^ 0xA4,
-                    data[pos + 7] ^ 0x42,
-                ];
-
-                return Some((IpAddr::from(xor_ip), port));
-            } else if family == 0x02 && pos + 8 + 16 <= data.len() {
-                // IPv6 (XOR with magic cookie + transaction ID)
-                let mut xor_ip = [0u8; 16];
-
-                // XOR with magic cookie + transaction ID
-                xor_ip[0] = data[pos + 4] ^ 0x21;
-                xor_ip[1] = data[pos + 5] ^ 0x12;
-                xor_ip[2] = data[pos + 6] ^ 0xA4;
-                xor_ip[3] = data[pos + 7] ^ 0x42;
-
-                // XOR the rest with transaction ID
-                for i in 4..16 {
-                    xor_ip[i] = data[pos + 4 + i] ^ data[4 + i];
-                }
-
-                return Some((IpAddr::from(xor_ip), port));
-            }
-        }
-
-        pos += attr_length;
-        // Attributes are padded to 4 bytes
-        if attr_length % 4 != 0 {
-            pos += 4 - (attr_length % 4);
-        }
+/// Local IPv4 address used for outgoing traffic
+pub async fn local_ipv4() -> Result<Ipv4Addr> {
+    // Connecting a UDP socket selects a route without sending anything
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    socket.connect("8.8.8.8:80").await?;
+    match socket.local_addr()?.ip() {
+        IpAddr::V4(ip) if !ip.is_unspecified() => Ok(ip),
+        _ => Err(anyhow!("No local IPv4 address")),
     }
-
-    None
 }
 
 /// Establish a direct UDP connection to a remote peer
 pub async fn establish_direct_udp_connection(
     remote_ip: IpAddr,
     remote_port: u16,
+) -> Result<UdpSocket> {
+    establish_udp_connection_from(&BindAddress::Auto, remote_ip, remote_port).await
+}
+
+/// Establish a direct UDP connection to a remote peer from the chosen interface
+pub async fn establish_udp_connection_from(
+    bind: &BindAddress,
+    remote_ip: IpAddr,
+    remote_port: u16,
 ) -> Result<UdpSocket> {
     // Bind local UDP socket to random port
-    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    let socket = bind_udp(bind).await?;
 
     // Send initial packet for NAT hole punching
     let hello_packet = [1, 2, 3, 4]; // Simple packet pattern
@@ -250,6 +157,139 @@ pub fn parse_connection_link(link: &str) -> Result<(IpAddr, u16, String, [u8; 32
     Ok((ip, port, session_id, public_key))
 }
 
+/// Adds extra addresses the host can be reached on to a connection link
+pub fn add_link_candidates(link: &str, candidates: &[SocketAddr]) -> String {
+    if candidates.is_empty() {
+        return link.to_string();
+    }
+
+    let candidates: Vec<String> = candidates.iter().map(|addr| addr.to_string()).collect();
+    format!("{}&cand={}", link, candidates.join(","))
+}
+
+/// All addresses in a connection link, the primary one first
+pub fn parse_link_candidates(link: &str) -> Result<Vec<SocketAddr>> {
+    let (ip, port, _, _) = parse_connection_link(link)?;
+    let mut candidates = vec![SocketAddr::new(ip, port)];
+
+    let extra = link
+        .split(['?', '&'])
+        .find_map(|param| param.strip_prefix("cand="))
+        .unwrap_or_default();
+    for candidate in extra.split(',').filter(|c| !c.is_empty()) {
+        let addr: SocketAddr = candidate.parse()?;
+        if !candidates.contains(&addr) {
+            candidates.push(addr);
+        }
+    }
+
+    Ok(candidates)
+}
+
+/// Kinds of address a connection link can carry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CandidateClass {
+    /// Private, loopback and link-local addresses
+    Lan,
+    /// Addresses reachable from the internet
+    Public,
+}
+
+impl CandidateClass {
+    /// Classifies an address
+    pub fn of(ip: &IpAddr) -> Self {
+        let local = match ip {
+            IpAddr::V4(ipv4) => {
+                ipv4.is_private()
+                    || ipv4.is_loopback()
+                    || ipv4.is_link_local()
+                    || ipv4.is_unspecified()
+            }
+            IpAddr::V6(ipv6) => {
+                let first = ipv6.segments()[0];
+                ipv6.is_loopback()
+                    || ipv6.is_unspecified()
+                    || first & 0xfe00 == 0xfc00 // Unique local
+                    || first & 0xffc0 == 0xfe80 // Link local
+            }
+        };
+
+        if local {
+            CandidateClass::Lan
+        } else {
+            CandidateClass::Public
+        }
+    }
+}
+
+/// Which kinds of address a shared link may reveal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinkPrivacy {
+    pub lan: bool,
+    pub public: bool,
+}
+
+impl LinkPrivacy {
+    /// Public addresses only, for links shared beyond the local network
+    pub const EXTERNAL: Self = Self {
+        lan: false,
+        public: true,
+    };
+
+    /// Every address, for links shared with people on the same network
+    pub const LOCAL: Self = Self {
+        lan: true,
+        public: true,
+    };
+
+    /// Whether a link shared with this setting may contain `addr`
+    pub fn allows(&self, addr: &SocketAddr) -> bool {
+        match CandidateClass::of(&addr.ip()) {
+            CandidateClass::Lan => self.lan,
+            CandidateClass::Public => self.public,
+        }
+    }
+}
+
+impl Default for LinkPrivacy {
+    fn default() -> Self {
+        Self::EXTERNAL
+    }
+}
+
+/// Rewrites a connection link to carry only the addresses `privacy` allows
+///
+/// The first allowed address becomes the link's primary address. Fails if no
+/// address in the link is allowed.
+pub fn filter_link_candidates(link: &str, privacy: LinkPrivacy) -> Result<String> {
+    let allowed: Vec<SocketAddr> = parse_link_candidates(link)?
+        .into_iter()
+        .filter(|addr| privacy.allows(addr))
+        .collect();
+    let (primary, rest) = allowed
+        .split_first()
+        .ok_or_else(|| anyhow!("No address in the link may be shared"))?;
+
+    let params: Vec<String> = link["resonance://join?".len()..]
+        .split('&')
+        .filter(|param| !param.starts_with("cand="))
+        .map(|param| {
+            if param.starts_with("ip=") {
+                format!("ip={}", primary.ip())
+            } else if param.starts_with("port=") {
+                format!("port={}", primary.port())
+            } else {
+                param.to_string()
+            }
+        })
+        .collect();
+
+    Ok(add_link_candidates(
+        &format!("resonance://join?{}", params.join("&")),
+        rest,
+    ))
+}
+
 /// Validates if an IP address should be allowed
 pub fn is_blocked_ip(ip: &IpAddr) -> bool {
     match ip {
@@ -311,6 +351,27 @@ mod tests {
         assert_eq!(key, original_key);
     }
 
+    #[test]
+    fn test_link_candidates() {
+        let endpoint = Endpoint {
+            ip: "203.0.113.5".parse().unwrap(),
+            port: 4000,
+        };
+        let link = generate_connection_link(&endpoint, "sid", &[7u8; 32]);
+        assert_eq!(parse_link_candidates(&link).unwrap().len(), 1);
+
+        let lan: SocketAddr = "192.168.1.20:4000".parse().unwrap();
+        let v6: SocketAddr = "[2001:db8::5]:4000".parse().unwrap();
+        let link = add_link_candidates(&link, &[lan, v6]);
+
+        // Extra candidates don't change the primary address
+        assert_eq!(parse_connection_link(&link).unwrap().1, 4000);
+        assert_eq!(
+            parse_link_candidates(&link).unwrap(),
+            vec!["203.0.113.5:4000".parse().unwrap(), lan, v6]
+        );
+    }
+
     #[test]
     fn test_blocked_ip_detection() {
         // Private IPs should be blocked
@@ -327,20 +388,44 @@ mod tests {
     }
 
     #[test]
-    fn test_stun_binding_request_format() {
-        let request = create_stun_binding_request();
-
-        // Verify STUN message header
-        assert_eq!(request[0], 0x00); // Message Type: Binding Request
-        assert_eq!(request[1], 0x01);
-        assert_eq!(request[2], 0x00); // Message Length: 0
-        assert_eq!(request[3], 0x00);
-        assert_eq!(request[4], 0x21); // Magic Cookie
-        assert_eq!(request[5], 0x12);
-        assert_eq!(request[6], 0xA4);
-        assert_eq!(request[7], 0x42);
-
-        // Check that we have a transaction ID (12 bytes)
-        assert_eq!(request.len(), 20);
+    fn test_filter_link_candidates() {
+        let endpoint = Endpoint {
+            ip: "203.0.113.5".parse().unwrap(),
+            port: 4000,
+        };
+        let link = add_link_candidates(
+            &format!(
+                "{}&mix=host",
+                generate_connection_link(&endpoint, "sid", &[7u8; 32])
+            ),
+            &["192.168.1.20:4000".parse().unwrap()],
+        );
+
+        // External links drop the LAN address but keep everything else
+        let external = filter_link_candidates(&link, LinkPrivacy::EXTERNAL).unwrap();
+        assert!(!external.contains("192.168."));
+        assert!(external.contains("&mix=host"));
+        assert_eq!(parse_link_candidates(&external).unwrap().len(), 1);
+        assert_eq!(
+            parse_connection_link(&external).unwrap(),
+            parse_connection_link(&link).unwrap()
+        );
+
+        // A LAN-only link promotes the LAN address to primary
+        let lan_only = LinkPrivacy {
+            lan: true,
+            public: false,
+        };
+        let local = filter_link_candidates(&link, lan_only).unwrap();
+        assert_eq!(
+            parse_link_candidates(&local).unwrap(),
+            vec!["192.168.1.20:4000".parse::<SocketAddr>().unwrap()]
+        );
+
+        let nothing = LinkPrivacy {
+            lan: false,
+            public: false,
+        };
+        assert!(filter_link_candidates(&link, nothing).is_err());
     }
 }
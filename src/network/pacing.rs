@@ -0,0 +1,294 @@
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc;
+
+/// Priority of an outgoing datagram; earlier variants are sent first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SendPriority {
+    /// Handshakes, heartbeats and clock sync
+    Control = 0,
+    /// Real-time audio
+    Audio = 1,
+    /// Peer lists, positions and other state updates
+    Bulk = 2,
+}
+
+/// Limits for paced sending to a single destination
+#[derive(Debug, Clone)]
+pub struct PacingConfig {
+    /// Sustained send rate in bytes per second
+    pub rate: usize,
+    /// Bytes that may be sent at once after an idle period
+    pub burst: usize,
+    /// Most datagrams sent to a destination per wake-up
+    pub max_batch: usize,
+    /// Datagrams queued per priority before the oldest is dropped
+    pub queue_limit: usize,
+}
+
+impl Default for PacingConfig {
+    fn default() -> Self {
+        Self {
+            rate: 256 * 1024,
+            burst: 16 * 1024,
+            max_batch: 8,
+            queue_limit: 64,
+        }
+    }
+}
+
+/// Byte-based token bucket
+struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    rate: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: usize, capacity: usize) -> Self {
+        Self {
+            capacity: capacity as f64,
+            tokens: capacity as f64,
+            rate: rate as f64,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// Cost of a datagram; ones larger than the bucket go out once it is full
+    fn cost(&self, bytes: usize) -> f64 {
+        (bytes as f64).min(self.capacity)
+    }
+
+    fn try_take(&mut self, bytes: usize, now: Instant) -> bool {
+        self.refill(now);
+        let cost = self.cost(bytes);
+        if self.tokens >= cost {
+            self.tokens -= cost;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Time until `bytes` can be taken
+    fn wait_time(&mut self, bytes: usize, now: Instant) -> Duration {
+        self.refill(now);
+        let missing = self.cost(bytes) - self.tokens;
+        if missing <= 0.0 || self.rate <= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(missing / self.rate)
+        }
+    }
+}
+
+/// Queued datagrams and send budget for one destination
+struct DestinationQueue {
+    bucket: TokenBucket,
+    queues: [VecDeque<Bytes>; 3],
+}
+
+impl DestinationQueue {
+    fn new(config: &PacingConfig) -> Self {
+        Self {
+            bucket: TokenBucket::new(config.rate, config.burst),
+            queues: Default::default(),
+        }
+    }
+
+    /// Queues a datagram, dropping the oldest of the same priority when full
+    fn push(&mut self, priority: SendPriority, packet: Bytes, limit: usize) {
+        let queue = &mut self.queues[priority as usize];
+        if queue.len() >= limit.max(1) {
+            queue.pop_front();
+            log::debug!("Pacing queue full, dropped a {:?} datagram", priority);
+        }
+        queue.push_back(packet);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.queues.iter().all(|queue| queue.is_empty())
+    }
+
+    /// Whether nothing is queued and the full burst is available again
+    fn is_idle(&mut self, now: Instant) -> bool {
+        self.bucket.refill(now);
+        self.is_empty() && self.bucket.tokens >= self.bucket.capacity
+    }
+
+    /// Takes the datagrams that may be sent now, highest priority first
+    fn next_batch(&mut self, max_batch: usize, now: Instant) -> Vec<Bytes> {
+        let mut batch = Vec::new();
+
+        for queue in &mut self.queues {
+            while batch.len() < max_batch {
+                let Some(packet) = queue.front() else { break };
+                if !self.bucket.try_take(packet.len(), now) {
+                    return batch;
+                }
+                batch.extend(queue.pop_front());
+            }
+        }
+
+        batch
+    }
+
+    /// Time until the next queued datagram may be sent
+    fn next_ready(&mut self, now: Instant) -> Option<Duration> {
+        let packet = self.queues.iter().find_map(|queue| queue.front())?;
+        let len = packet.len();
+        Some(self.bucket.wait_time(len, now))
+    }
+}
+
+struct Outgoing {
+    destination: SocketAddr,
+    priority: SendPriority,
+    packet: Bytes,
+}
+
+/// Paces datagrams sent from a socket, per destination and by priority
+///
+/// Cloning gives another handle to the same pacer.
+#[derive(Clone)]
+pub struct SendPacer {
+    tx: mpsc::UnboundedSender<Outgoing>,
+}
+
+impl SendPacer {
+    /// Starts pacing sends on `socket`
+    pub fn new(socket: Arc<UdpSocket>, config: PacingConfig) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(run_pacer(socket, config, rx));
+        Self { tx }
+    }
+
+    /// Queues a datagram for `destination`
+    pub fn send(
+        &self,
+        destination: SocketAddr,
+        priority: SendPriority,
+        packet: Bytes,
+    ) -> Result<()> {
+        self.tx
+            .send(Outgoing {
+                destination,
+                priority,
+                packet,
+            })
+            .map_err(|_| anyhow!("Send pacer has stopped"))
+    }
+}
+
+async fn run_pacer(
+    socket: Arc<UdpSocket>,
+    config: PacingConfig,
+    mut rx: mpsc::UnboundedReceiver<Outgoing>,
+) {
+    let mut destinations: HashMap<SocketAddr, DestinationQueue> = HashMap::new();
+
+    loop {
+        let now = Instant::now();
+        let wait = destinations
+            .values_mut()
+            .filter_map(|queue| queue.next_ready(now))
+            .min();
+
+        tokio::select! {
+            outgoing = rx.recv() => {
+                let Some(outgoing) = outgoing else { break };
+                let mut enqueue = |outgoing: Outgoing| {
+                    destinations
+                        .entry(outgoing.destination)
+                        .or_insert_with(|| DestinationQueue::new(&config))
+                        .push(outgoing.priority, outgoing.packet, config.queue_limit);
+                };
+
+                // Take everything already waiting so it can be prioritized together
+                enqueue(outgoing);
+                while let Ok(outgoing) = rx.try_recv() {
+                    enqueue(outgoing);
+                }
+            }
+            _ = tokio::time::sleep(wait.unwrap_or_default()), if wait.is_some() => {}
+        }
+
+        let now = Instant::now();
+        for (destination, queue) in destinations.iter_mut() {
+            for packet in queue.next_batch(config.max_batch, now) {
+                if let Err(e) = socket.send_to(&packet, destination).await {
+                    log::warn!("Failed to send to {}: {}", destination, e);
+                }
+            }
+        }
+        destinations.retain(|_, queue| !queue.is_idle(now));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(rate: usize, burst: usize) -> PacingConfig {
+        PacingConfig {
+            rate,
+            burst,
+            max_batch: 8,
+            queue_limit: 2,
+        }
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(1000, 100);
+
+        assert!(bucket.try_take(100, start));
+        assert!(!bucket.try_take(50, start));
+        assert_eq!(bucket.wait_time(50, start), Duration::from_millis(50));
+        assert!(bucket.try_take(50, start + Duration::from_millis(50)));
+    }
+
+    #[test]
+    fn test_queue_sends_by_priority() {
+        let now = Instant::now();
+        let mut queue = DestinationQueue::new(&config(1000, 1000));
+        queue.push(SendPriority::Bulk, vec![3].into(), 2);
+        queue.push(SendPriority::Audio, vec![2].into(), 2);
+        queue.push(SendPriority::Control, vec![1].into(), 2);
+
+        assert_eq!(queue.next_batch(8, now), vec![vec![1], vec![2], vec![3]]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_queue_paces_and_drops_oldest() {
+        let now = Instant::now();
+        let mut queue = DestinationQueue::new(&config(1000, 10));
+        queue.push(SendPriority::Audio, vec![0; 10].into(), 2);
+        queue.push(SendPriority::Audio, vec![1; 10].into(), 2);
+        queue.push(SendPriority::Audio, vec![2; 10].into(), 2);
+
+        // The first datagram was dropped, and only one fits in the burst
+        assert_eq!(queue.next_batch(8, now), vec![vec![1; 10]]);
+        assert_eq!(queue.next_ready(now), Some(Duration::from_millis(10)));
+
+        let later = now + Duration::from_millis(10);
+        assert_eq!(queue.next_batch(8, later), vec![vec![2; 10]]);
+        assert_eq!(queue.next_ready(later), None);
+    }
+}
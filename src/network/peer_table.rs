@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// How long a peer may stay half-open before it is dropped
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Most peers a table holds before the least recently used are evicted
+pub const DEFAULT_PEER_CAPACITY: usize = 256;
+
+/// Where a peer is in its connection lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeerState {
+    /// We know about the peer but haven't completed a handshake with it
+    HalfOpen,
+    /// We've completed a handshake with the peer, or it's us
+    Established,
+}
+
+/// Counts of peers removed from a table without being asked to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EvictionStats {
+    /// Half-open peers whose handshake didn't complete in time
+    pub expired: u64,
+    /// Peers dropped to stay within capacity
+    pub evicted: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Entry<V> {
+    value: V,
+    state: PeerState,
+    since: Instant,
+    // Tick of the last use; a counter so uses in the same instant still order
+    last_used: u64,
+}
+
+/// Peers in a session, keyed by peer ID, with bounded size
+///
+/// Peers announced to us start half-open and are dropped after
+/// [`HANDSHAKE_TIMEOUT`] unless marked established. When the table is full,
+/// the least recently used half-open peer makes room, or the least recently
+/// used established one if none are half-open.
+#[derive(Debug, Clone)]
+pub struct PeerTable<V> {
+    entries: HashMap<String, Entry<V>>,
+    capacity: usize,
+    handshake_timeout: Duration,
+    stats: EvictionStats,
+    tick: u64,
+}
+
+impl<V> Default for PeerTable<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> PeerTable<V> {
+    pub fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+            capacity: DEFAULT_PEER_CAPACITY,
+            handshake_timeout: HANDSHAKE_TIMEOUT,
+            stats: EvictionStats::default(),
+            tick: 0,
+        }
+    }
+
+    /// Limits how many peers the table holds
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Sets how long half-open peers are kept
+    pub fn with_handshake_timeout(mut self, timeout: Duration) -> Self {
+        self.handshake_timeout = timeout;
+        self
+    }
+
+    /// Adds or replaces an established peer
+    pub fn insert(&mut self, id: String, value: V) {
+        self.insert_with_state(id, value, PeerState::Established);
+    }
+
+    /// Adds a peer we haven't completed a handshake with yet
+    ///
+    /// A peer that is already established keeps that state.
+    pub fn insert_half_open(&mut self, id: String, value: V) {
+        let state = self.state(&id).unwrap_or(PeerState::HalfOpen);
+        self.insert_with_state(id, value, state);
+    }
+
+    fn insert_with_state(&mut self, id: String, value: V, state: PeerState) {
+        self.expire_stale();
+
+        let now = Instant::now();
+        let tick = self.next_tick();
+        if let Some(entry) = self.entries.get_mut(&id) {
+            if entry.state != state {
+                entry.since = now;
+            }
+            entry.value = value;
+            entry.state = state;
+            entry.last_used = tick;
+            return;
+        }
+
+        while self.entries.len() >= self.capacity {
+            self.evict_one();
+        }
+        self.entries.insert(
+            id,
+            Entry {
+                value,
+                state,
+                since: now,
+                last_used: tick,
+            },
+        );
+    }
+
+    /// Records a completed handshake; returns false for unknown peers
+    pub fn mark_established(&mut self, id: &str) -> bool {
+        let tick = self.next_tick();
+        match self.entries.get_mut(id) {
+            Some(entry) => {
+                if entry.state == PeerState::HalfOpen {
+                    entry.state = PeerState::Established;
+                    entry.since = Instant::now();
+                }
+                entry.last_used = tick;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Lifecycle state of a peer
+    pub fn state(&self, id: &str) -> Option<PeerState> {
+        self.entries.get(id).map(|entry| entry.state)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&V> {
+        self.entries.get(id).map(|entry| &entry.value)
+    }
+
+    /// Mutable access, which also counts as a use for eviction
+    pub fn get_mut(&mut self, id: &str) -> Option<&mut V> {
+        let tick = self.next_tick();
+        self.entries.get_mut(id).map(|entry| {
+            entry.last_used = tick;
+            &mut entry.value
+        })
+    }
+
+    pub fn contains_key(&self, id: &str) -> bool {
+        self.entries.contains_key(id)
+    }
+
+    pub fn remove(&mut self, id: &str) -> Option<V> {
+        self.entries.remove(id).map(|entry| entry.value)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &V)> {
+        self.entries.iter().map(|(id, entry)| (id, &entry.value))
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.entries.values().map(|entry| &entry.value)
+    }
+
+    /// Drops half-open peers whose handshake has timed out, returning their IDs
+    pub fn expire_stale(&mut self) -> Vec<String> {
+        let timeout = self.handshake_timeout;
+        let stale: Vec<String> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| {
+                entry.state == PeerState::HalfOpen && entry.since.elapsed() >= timeout
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        for id in &stale {
+            self.entries.remove(id);
+            self.stats.expired += 1;
+            log::debug!("Dropped peer {} after its handshake timed out", id);
+        }
+        stale
+    }
+
+    /// Peers removed by expiry or eviction so far
+    pub fn eviction_stats(&self) -> EvictionStats {
+        self.stats
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    fn evict_one(&mut self) {
+        let victim = self
+            .entries
+            .iter()
+            .min_by_key(|(_, entry)| (entry.state == PeerState::Established, entry.last_used))
+            .map(|(id, _)| id.clone());
+
+        if let Some(id) = victim {
+            self.entries.remove(&id);
+            self.stats.evicted += 1;
+            log::debug!("Evicted peer {} to stay within capacity", id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_half_open_peers_expire() {
+        let mut table = PeerTable::new().with_handshake_timeout(Duration::from_millis(20));
+        table.insert("me".to_string(), 0);
+        table.insert_half_open("announced".to_string(), 1);
+        table.insert_half_open("connected".to_string(), 2);
+        assert!(table.mark_established("connected"));
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(table.expire_stale(), vec!["announced".to_string()]);
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.state("connected"), Some(PeerState::Established));
+        assert_eq!(table.eviction_stats().expired, 1);
+
+        // Announcing an established peer again doesn't demote it
+        table.insert_half_open("connected".to_string(), 3);
+        assert_eq!(table.state("connected"), Some(PeerState::Established));
+        assert_eq!(table.get("connected"), Some(&3));
+    }
+
+    #[test]
+    fn test_capacity_evicts_half_open_first() {
+        let mut table = PeerTable::new().with_capacity(3);
+        table.insert("me".to_string(), 0);
+        table.insert_half_open("a".to_string(), 1);
+        table.insert_half_open("b".to_string(), 2);
+
+        // "a" is the least recently used half-open peer
+        table.insert_half_open("c".to_string(), 3);
+        assert!(!table.contains_key("a"));
+        assert!(table.contains_key("me"));
+        assert_eq!(table.len(), 3);
+
+        // Touching "b" makes "c" the next to go
+        table.get_mut("b");
+        table.insert("d".to_string(), 4);
+        assert!(!table.contains_key("c"));
+        assert!(table.contains_key("b"));
+
+        // With only established peers left, the oldest of those goes
+        table.mark_established("b");
+        table.insert("e".to_string(), 5);
+        assert!(!table.contains_key("me"));
+        assert_eq!(table.eviction_stats().evicted, 3);
+        assert_eq!(table.eviction_stats().expired, 0);
+    }
+}
@@ -0,0 +1,484 @@
+use anyhow::{anyhow, Result};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::task::JoinHandle;
+
+use super::http::{http_request, split_url};
+use super::p2p::{local_ipv4, Endpoint};
+
+const NATPMP_PORT: u16 = 5351;
+const SSDP_ADDR: &str = "239.255.255.250:1900";
+const UPNP_SERVICES: [&str; 2] = [
+    "urn:schemas-upnp-org:service:WANIPConnection:1",
+    "urn:schemas-upnp-org:service:WANPPPConnection:1",
+];
+
+/// Protocol used to create a port mapping
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MappingProtocol {
+    NatPmp,
+    Upnp,
+}
+
+/// A UDP port forwarded by the home router
+#[derive(Debug, Clone)]
+pub struct PortMapping {
+    pub protocol: MappingProtocol,
+    pub internal_port: u16,
+    /// Address other peers can reach us on
+    pub external: Endpoint,
+    /// Lease granted by the router
+    pub lifetime: Duration,
+}
+
+/// Router that created a mapping, kept for renewal and removal
+#[derive(Debug, Clone)]
+enum Gateway {
+    NatPmp(SocketAddr),
+    Upnp {
+        control_url: String,
+        service_type: String,
+        local_ip: Ipv4Addr,
+    },
+}
+
+/// Keeps a port mapped on the router, renewing the lease until released
+pub struct PortMapper {
+    mapping: PortMapping,
+    gateway: Gateway,
+    renew_task: JoinHandle<()>,
+}
+
+impl PortMapper {
+    /// Maps `internal_port` on the router, trying NAT-PMP and then UPnP
+    pub async fn map(internal_port: u16, lifetime: Duration) -> Result<Self> {
+        let local_ip = local_ipv4().await?;
+
+        let (gateway, mapping) = match map_natpmp(local_ip, internal_port, lifetime).await {
+            Ok(result) => result,
+            Err(natpmp_error) => {
+                map_upnp(local_ip, internal_port, lifetime)
+                    .await
+                    .map_err(|upnp_error| {
+                        anyhow!(
+                            "NAT-PMP failed ({}); UPnP failed ({})",
+                            natpmp_error,
+                            upnp_error
+                        )
+                    })?
+            }
+        };
+
+        log::info!(
+            "Mapped UDP port {} to {}:{} via {:?}",
+            internal_port,
+            mapping.external.ip,
+            mapping.external.port,
+            mapping.protocol
+        );
+
+        let renew_task = tokio::spawn(renew_lease(gateway.clone(), mapping.clone()));
+
+        Ok(Self {
+            mapping,
+            gateway,
+            renew_task,
+        })
+    }
+
+    pub fn mapping(&self) -> &PortMapping {
+        &self.mapping
+    }
+
+    /// Address to advertise in join links
+    pub fn external_endpoint(&self) -> &Endpoint {
+        &self.mapping.external
+    }
+
+    /// Stops renewing and removes the mapping from the router
+    pub async fn release(self) -> Result<()> {
+        self.renew_task.abort();
+        let port = self.mapping.internal_port;
+
+        match &self.gateway {
+            Gateway::NatPmp(gateway) => {
+                // A zero lifetime deletes the mapping
+                natpmp_request(*gateway, &natpmp_map_request(port, 0, 0), 129).await?;
+            }
+            Gateway::Upnp {
+                control_url,
+                service_type,
+                ..
+            } => {
+                let args = [
+                    ("NewRemoteHost", String::new()),
+                    ("NewExternalPort", self.mapping.external.port.to_string()),
+                    ("NewProtocol", "UDP".to_string()),
+                ];
+                soap_request(control_url, service_type, "DeletePortMapping", &args).await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for PortMapper {
+    fn drop(&mut self) {
+        self.renew_task.abort();
+    }
+}
+
+/// Renews the mapping at half its lifetime, retrying sooner on failure
+async fn renew_lease(gateway: Gateway, mut mapping: PortMapping) {
+    loop {
+        tokio::time::sleep(mapping.lifetime / 2).await;
+
+        let renewed = match &gateway {
+            Gateway::NatPmp(gateway) => natpmp_map(
+                *gateway,
+                mapping.internal_port,
+                mapping.external.port,
+                mapping.lifetime,
+            )
+            .await
+            .map(|(external_port, lifetime)| {
+                mapping.external.port = external_port;
+                mapping.lifetime = lifetime;
+            }),
+            Gateway::Upnp {
+                control_url,
+                service_type,
+                local_ip,
+            } => {
+                upnp_add_mapping(
+                    control_url,
+                    service_type,
+                    *local_ip,
+                    mapping.internal_port,
+                    mapping.lifetime,
+                )
+                .await
+            }
+        };
+
+        if let Err(e) = renewed {
+            log::warn!("Failed to renew port mapping: {}", e);
+            mapping.lifetime = mapping.lifetime.min(Duration::from_secs(60));
+        }
+    }
+}
+
+/// Most home routers are the first address on the local /24
+fn guess_gateway(local_ip: Ipv4Addr) -> Ipv4Addr {
+    let [a, b, c, _] = local_ip.octets();
+    Ipv4Addr::new(a, b, c, 1)
+}
+
+async fn map_natpmp(
+    local_ip: Ipv4Addr,
+    internal_port: u16,
+    lifetime: Duration,
+) -> Result<(Gateway, PortMapping)> {
+    let gateway = SocketAddr::new(IpAddr::V4(guess_gateway(local_ip)), NATPMP_PORT);
+
+    let response = natpmp_request(gateway, &[0, 0], 128).await?;
+    let external_ip = parse_natpmp_address_response(&response)?;
+    let (external_port, lifetime) =
+        natpmp_map(gateway, internal_port, internal_port, lifetime).await?;
+
+    let mapping = PortMapping {
+        protocol: MappingProtocol::NatPmp,
+        internal_port,
+        external: Endpoint {
+            ip: IpAddr::V4(external_ip),
+            port: external_port,
+        },
+        lifetime,
+    };
+    Ok((Gateway::NatPmp(gateway), mapping))
+}
+
+async fn natpmp_map(
+    gateway: SocketAddr,
+    internal_port: u16,
+    external_port: u16,
+    lifetime: Duration,
+) -> Result<(u16, Duration)> {
+    let request = natpmp_map_request(internal_port, external_port, lifetime.as_secs() as u32);
+    let response = natpmp_request(gateway, &request, 129).await?;
+    let (external_port, lifetime) = parse_natpmp_map_response(&response)?;
+    Ok((external_port, Duration::from_secs(lifetime as u64)))
+}
+
+/// Sends a NAT-PMP request, retrying with backoff as RFC 6886 suggests
+async fn natpmp_request(gateway: SocketAddr, request: &[u8], opcode: u8) -> Result<Vec<u8>> {
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    let mut timeout = Duration::from_millis(250);
+
+    for _ in 0..3 {
+        socket.send_to(request, gateway).await?;
+
+        let mut buf = [0u8; 16];
+        if let Ok(Ok((size, from))) =
+            tokio::time::timeout(timeout, socket.recv_from(&mut buf)).await
+        {
+            if from.ip() == gateway.ip() && size >= 4 && buf[0] == 0 && buf[1] == opcode {
+                let result = u16::from_be_bytes([buf[2], buf[3]]);
+                if result != 0 {
+                    return Err(anyhow!("NAT-PMP request refused with code {}", result));
+                }
+                return Ok(buf[..size].to_vec());
+            }
+        }
+        timeout *= 2;
+    }
+
+    Err(anyhow!("No NAT-PMP response from {}", gateway))
+}
+
+fn natpmp_map_request(internal_port: u16, external_port: u16, lifetime: u32) -> [u8; 12] {
+    let mut request = [0u8; 12];
+    request[1] = 1; // Map UDP
+    request[4..6].copy_from_slice(&internal_port.to_be_bytes());
+    request[6..8].copy_from_slice(&external_port.to_be_bytes());
+    request[8..12].copy_from_slice(&lifetime.to_be_bytes());
+    request
+}
+
+fn parse_natpmp_address_response(response: &[u8]) -> Result<Ipv4Addr> {
+    if response.len() < 12 {
+        return Err(anyhow!("Short NAT-PMP address response"));
+    }
+    Ok(Ipv4Addr::new(
+        response[8],
+        response[9],
+        response[10],
+        response[11],
+    ))
+}
+
+fn parse_natpmp_map_response(response: &[u8]) -> Result<(u16, u32)> {
+    if response.len() < 16 {
+        return Err(anyhow!("Short NAT-PMP mapping response"));
+    }
+    let external_port = u16::from_be_bytes([response[10], response[11]]);
+    let lifetime = u32::from_be_bytes([response[12], response[13], response[14], response[15]]);
+    Ok((external_port, lifetime))
+}
+
+async fn map_upnp(
+    local_ip: Ipv4Addr,
+    internal_port: u16,
+    lifetime: Duration,
+) -> Result<(Gateway, PortMapping)> {
+    let location = ssdp_discover().await?;
+    let description = http_request(&location, "GET", &[], "").await?;
+    let (service_type, control_url) = find_control_url(&description, &location)
+        .ok_or_else(|| anyhow!("Router has no WAN connection service"))?;
+
+    upnp_add_mapping(
+        &control_url,
+        &service_type,
+        local_ip,
+        internal_port,
+        lifetime,
+    )
+    .await?;
+
+    let response = soap_request(&control_url, &service_type, "GetExternalIPAddress", &[]).await?;
+    let external_ip: IpAddr = xml_value(&response, "NewExternalIPAddress")
+        .ok_or_else(|| anyhow!("Router did not report its external address"))?
+        .parse()?;
+
+    let mapping = PortMapping {
+        protocol: MappingProtocol::Upnp,
+        internal_port,
+        external: Endpoint {
+            ip: external_ip,
+            port: internal_port,
+        },
+        lifetime,
+    };
+    let gateway = Gateway::Upnp {
+        control_url,
+        service_type,
+        local_ip,
+    };
+    Ok((gateway, mapping))
+}
+
+async fn upnp_add_mapping(
+    control_url: &str,
+    service_type: &str,
+    local_ip: Ipv4Addr,
+    port: u16,
+    lifetime: Duration,
+) -> Result<()> {
+    let args = [
+        ("NewRemoteHost", String::new()),
+        ("NewExternalPort", port.to_string()),
+        ("NewProtocol", "UDP".to_string()),
+        ("NewInternalPort", port.to_string()),
+        ("NewInternalClient", local_ip.to_string()),
+        ("NewEnabled", "1".to_string()),
+        ("NewPortMappingDescription", "Resonance".to_string()),
+        ("NewLeaseDuration", lifetime.as_secs().to_string()),
+    ];
+    soap_request(control_url, service_type, "AddPortMapping", &args).await?;
+    Ok(())
+}
+
+/// Finds an internet gateway device with SSDP, returning its description URL
+async fn ssdp_discover() -> Result<String> {
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    let request = "M-SEARCH * HTTP/1.1\r\n\
+                   HOST: 239.255.255.250:1900\r\n\
+                   MAN: \"ssdp:discover\"\r\n\
+                   MX: 2\r\n\
+                   ST: urn:schemas-upnp-org:device:InternetGatewayDevice:1\r\n\r\n";
+    socket.send_to(request.as_bytes(), SSDP_ADDR).await?;
+
+    let mut buf = [0u8; 2048];
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(3);
+    loop {
+        let (size, _) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf))
+            .await
+            .map_err(|_| anyhow!("No UPnP gateway found"))??;
+
+        let response = String::from_utf8_lossy(&buf[..size]);
+        if let Some(location) = header_value(&response, "location") {
+            return Ok(location);
+        }
+    }
+}
+
+/// Value of an HTTP header, matched case-insensitively
+fn header_value(response: &str, name: &str) -> Option<String> {
+    response.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then(|| value.trim().to_string())
+    })
+}
+
+/// Text of the first `<tag>` element in `xml`, ignoring namespaces
+fn xml_value(xml: &str, tag: &str) -> Option<String> {
+    let open = format!("<{}>", tag);
+    let start = xml.find(&open)? + open.len();
+    let end = start + xml[start..].find("</")?;
+    Some(xml[start..end].trim().to_string())
+}
+
+/// Finds the WAN connection service in a device description
+///
+/// Returns the service type and its absolute control URL.
+fn find_control_url(description: &str, location: &str) -> Option<(String, String)> {
+    for service_type in UPNP_SERVICES {
+        let Some(service_start) = description.find(service_type) else {
+            continue;
+        };
+        let control_url = xml_value(&description[service_start..], "controlURL")?;
+
+        let absolute = if control_url.starts_with("http://") {
+            control_url
+        } else {
+            let (host, _) = split_url(location)?;
+            let path = control_url.trim_start_matches('/');
+            format!("http://{}/{}", host, path)
+        };
+        return Some((service_type.to_string(), absolute));
+    }
+    None
+}
+
+async fn soap_request(
+    control_url: &str,
+    service_type: &str,
+    action: &str,
+    args: &[(&str, String)],
+) -> Result<String> {
+    let args: String = args
+        .iter()
+        .map(|(name, value)| format!("<{0}>{1}</{0}>", name, value))
+        .collect();
+    let body = format!(
+        "<?xml version=\"1.0\"?>\
+         <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+         s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\
+         <s:Body><u:{0} xmlns:u=\"{1}\">{2}</u:{0}></s:Body></s:Envelope>",
+        action, service_type, args
+    );
+    let soap_action = format!("\"{}#{}\"", service_type, action);
+    let headers = [
+        ("Content-Type", "text/xml; charset=\"utf-8\""),
+        ("SOAPAction", soap_action.as_str()),
+    ];
+
+    http_request(control_url, "POST", &headers, &body).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_natpmp_messages() {
+        let request = natpmp_map_request(4000, 4001, 3600);
+        assert_eq!(
+            request,
+            [0, 1, 0, 0, 0x0f, 0xa0, 0x0f, 0xa1, 0, 0, 0x0e, 0x10]
+        );
+
+        let address = [0, 128, 0, 0, 0, 0, 0, 1, 203, 0, 113, 7];
+        assert_eq!(
+            parse_natpmp_address_response(&address).unwrap(),
+            Ipv4Addr::new(203, 0, 113, 7)
+        );
+
+        let mapped = [
+            0, 129, 0, 0, 0, 0, 0, 1, 0x0f, 0xa0, 0x13, 0x88, 0, 0, 0x0e, 0x10,
+        ];
+        assert_eq!(parse_natpmp_map_response(&mapped).unwrap(), (5000, 3600));
+        assert!(parse_natpmp_map_response(&mapped[..12]).is_err());
+    }
+
+    #[test]
+    fn test_find_control_url() {
+        let description = "<root><device><serviceList>\
+            <service><serviceType>urn:schemas-upnp-org:service:Layer3Forwarding:1</serviceType>\
+            <controlURL>/l3f</controlURL></service>\
+            <service><serviceType>urn:schemas-upnp-org:service:WANIPConnection:1</serviceType>\
+            <controlURL>/ctl/IPConn</controlURL></service>\
+            </serviceList></device></root>";
+
+        let (service_type, control_url) =
+            find_control_url(description, "http://192.168.1.1:5000/rootDesc.xml").unwrap();
+        assert_eq!(service_type, UPNP_SERVICES[0]);
+        assert_eq!(control_url, "http://192.168.1.1:5000/ctl/IPConn");
+
+        assert!(find_control_url("<root></root>", "http://192.168.1.1/").is_none());
+    }
+
+    #[test]
+    fn test_response_parsing() {
+        let ssdp = "HTTP/1.1 200 OK\r\nCACHE-CONTROL: max-age=120\r\nLocation: http://10.0.0.1:1900/igd.xml\r\n\r\n";
+        assert_eq!(
+            header_value(ssdp, "location").as_deref(),
+            Some("http://10.0.0.1:1900/igd.xml")
+        );
+
+        let soap = "<u:GetExternalIPAddressResponse><NewExternalIPAddress>198.51.100.2</NewExternalIPAddress></u:GetExternalIPAddressResponse>";
+        assert_eq!(
+            xml_value(soap, "NewExternalIPAddress").as_deref(),
+            Some("198.51.100.2")
+        );
+
+        assert_eq!(
+            guess_gateway(Ipv4Addr::new(192, 168, 4, 23)),
+            Ipv4Addr::new(192, 168, 4, 1)
+        );
+    }
+}
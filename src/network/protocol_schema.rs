@@ -0,0 +1,625 @@
+use bytes::Bytes;
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::ser::{self, Serialize, Serializer};
+use serde_json::{json, Value};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use super::secure_channel::{Message, HEADER_LEN, NONCE_LEN, RATCHET_FLAG, RATCHET_LEN};
+use super::{BundledFrame, Endpoint, PeerCapabilities, SendPriority};
+use crate::app::admission::JoinReply;
+use crate::app::presence::PresenceStatus;
+use crate::app::session::Peer;
+use crate::audio::{AudioCodec, NoiseDescriptor, SpatialLayout, LPC_ORDER};
+
+/// Where the generated schema is kept, for those writing their own clients
+const SCHEMA_PATH: &str = "docs/protocol.json";
+
+/// Set to write the schema out rather than check it
+const UPDATE_VAR: &str = "UPDATE_PROTOCOL_SCHEMA";
+
+/// One of every message, with each field filled so its type can be traced
+fn examples() -> Vec<Message> {
+    let peer = Peer {
+        id: "peer".to_string(),
+        name: "Alice".to_string(),
+        endpoint: Endpoint {
+            ip: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            port: 4000,
+        },
+        public_key: [0; 32],
+        position: (0.0, 0.0, 0.0),
+        is_host: false,
+        joined_at: 0,
+    };
+    let id = || "peer".to_string();
+    vec![
+        Message::Handshake {
+            public_key: [0; 32],
+            session_id: id(),
+        },
+        Message::Join {
+            name: id(),
+            public_key: [0; 32],
+        },
+        Message::Audio {
+            data: Bytes::from_static(&[0]),
+            timestamp: 0,
+            seq: 0,
+            sample_rate: 48000,
+            channels: 1,
+            codec: AudioCodec::Pcm8,
+        },
+        Message::Position {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        },
+        Message::Heartbeat,
+        Message::Error {
+            code: 0,
+            message: String::new(),
+        },
+        Message::PeerList {
+            peers: vec![peer.clone()],
+        },
+        Message::NewPeer { peer },
+        Message::PeerLeft { peer_id: id() },
+        Message::Presence {
+            peer_id: id(),
+            status: PresenceStatus::default(),
+        },
+        Message::Layout {
+            layout: SpatialLayout::default(),
+            positions: vec![(id(), (0.0, 0.0, 0.0))],
+        },
+        Message::StreamEnd,
+        Message::Ping { sent_at: 0 },
+        Message::Pong {
+            ping_sent_at: 0,
+            received_at: 0,
+            sent_at: 0,
+        },
+        Message::PathProbe {
+            nonce: 0,
+            sent_at: 0,
+        },
+        Message::PathConfirm { nonce: 0 },
+        Message::Fragment {
+            id: 0,
+            index: 0,
+            count: 1,
+            data: vec![0],
+        },
+        Message::MtuProbe { padding: vec![0] },
+        Message::MtuAck { size: 0 },
+        Message::AudioBundle {
+            sample_rate: 48000,
+            channels: 1,
+            codec: AudioCodec::Pcm8,
+            frames: vec![BundledFrame {
+                data: Bytes::from_static(&[0]),
+                timestamp: 0,
+                seq: 0,
+            }],
+        },
+        Message::Capabilities {
+            capabilities: PeerCapabilities::default(),
+        },
+        Message::ComfortNoise {
+            sample_rate: 48000,
+            channels: 1,
+            frame_len: 960,
+            noise: NoiseDescriptor {
+                level: 0.0,
+                coefficients: [0.0; LPC_ORDER],
+            },
+        },
+        Message::MeetingMode { enabled: false },
+        Message::HandRaised {
+            peer_id: id(),
+            raised: false,
+        },
+        Message::SpeakGranted { peer_id: id() },
+        Message::BandwidthLimit { kbps: 0 },
+        Message::RecordingStateChanged {
+            peer_id: id(),
+            recording: false,
+        },
+        Message::RecordingAcknowledged { peer_id: id() },
+        Message::RecordingPolicy { consent: false },
+        Message::SyncedPlayout {
+            peer_id: id(),
+            delay_ms: 0,
+        },
+        Message::Rekey {
+            epoch: 0,
+            public_key: [0; 32],
+        },
+        Message::JoinRequest {
+            peer_id: id(),
+            name: id(),
+        },
+        Message::JoinReply {
+            reply: JoinReply::Denied(id()),
+        },
+    ]
+}
+
+/// What a peer must have announced in its capabilities before it's sent the message
+fn requirement(message: &Message) -> Option<&'static str> {
+    match message {
+        Message::ComfortNoise { .. } => Some("capabilities.comfort_noise"),
+        Message::Rekey { .. } => Some("capabilities.forward_secrecy"),
+        Message::Audio { .. } | Message::AudioBundle { .. } => {
+            Some("capabilities.codecs, for any codec but pcm8")
+        }
+        _ => None,
+    }
+}
+
+fn priority_name(priority: SendPriority) -> &'static str {
+    match priority {
+        SendPriority::Control => "control",
+        SendPriority::Audio => "audio",
+        SendPriority::Bulk => "bulk",
+    }
+}
+
+/// Every enum a message can hold, with its variants in wire order
+fn enum_definitions() -> Value {
+    json!({
+        "AudioCodec": variants::<AudioCodec>(),
+        // Addresses are written as their octets
+        "IpAddr": {
+            "V4": trace(&Ipv4Addr::LOCALHOST.octets()),
+            "V6": trace(&Ipv6Addr::LOCALHOST.octets()),
+        },
+        // Only a denial carries anything: the host's message
+        "JoinReply": {
+            "variants": variants::<JoinReply>(),
+            "Denied": trace(""),
+        },
+        "PresenceStatus": variants::<PresenceStatus>(),
+        "SpatialLayout": variants::<SpatialLayout>(),
+    })
+}
+
+/// The wire protocol, as a client would need to speak it
+fn protocol_schema() -> Value {
+    let messages: Vec<Value> = examples()
+        .iter()
+        .map(|message| {
+            let shape = trace(message);
+            json!({
+                "name": shape["variant"],
+                "kind": message.kind(),
+                "variant_index": shape["index"],
+                "priority": priority_name(message.priority()),
+                "requires": requirement(message),
+                "fields": shape.get("fields").cloned().unwrap_or_else(|| json!([])),
+            })
+        })
+        .collect();
+    json!({
+        "encoding": {
+            "header": format!(
+                "{} bytes in the clear: the message kind, with {:#04x} set when the packet's key is ratcheted, then a big-endian u64 sequence number; a ratcheted packet follows it with {} more, the big-endian u32 epoch and index of its key",
+                HEADER_LEN, RATCHET_FLAG, RATCHET_LEN
+            ),
+            "body": format!(
+                "a {}-byte nonce, then the message encrypted with XChaCha20-Poly1305, authenticating the header and the sender's public key; a Handshake is sent alone and unencrypted",
+                NONCE_LEN
+            ),
+            "message": "bincode 1 with its default options: little-endian, fixed-width integers",
+            "variant": "a u32 variant index, then the fields in order",
+            "sequences": "a u64 length, then the elements",
+            "bytes": "a u64 length, then the bytes, as a sequence of u8 is",
+            "options": "a u8 tag, 0 for none and 1 followed by the value for some",
+        },
+        "messages": messages,
+        "enums": enum_definitions(),
+    })
+}
+
+/// The shape of a value, as its type serializes
+fn trace<T: ?Sized + Serialize>(value: &T) -> Value {
+    value.serialize(Tracer).expect("every value can be traced")
+}
+
+/// Variant names of an enum, in the order it numbers them
+fn variants<'de, T: Deserialize<'de>>() -> Vec<&'static str> {
+    let mut names = None;
+    let _ = T::deserialize(VariantLister(&mut names));
+    names.expect("the type is an enum").to_vec()
+}
+
+#[derive(Debug)]
+struct TraceError(String);
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for TraceError {}
+
+impl ser::Error for TraceError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+impl de::Error for TraceError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+/// Records the variants an enum asks to be deserialized from, then gives up
+struct VariantLister<'a>(&'a mut Option<&'static [&'static str]>);
+
+impl<'de, 'a> Deserializer<'de> for VariantLister<'a> {
+    type Error = TraceError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, TraceError> {
+        Err(de::Error::custom("not an enum"))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        _: V,
+    ) -> Result<V::Value, TraceError> {
+        *self.0 = Some(variants);
+        Err(de::Error::custom("listed"))
+    }
+
+    // Like bincode, so types that read differently for people read as they do on the wire
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes byte_buf option
+        unit unit_struct newtype_struct seq tuple tuple_struct map struct identifier
+        ignored_any
+    }
+}
+
+/// Serializes a value into a description of its type
+struct Tracer;
+
+/// Collects the shapes of a sequence, tuple or struct's parts
+struct Parts {
+    kind: PartsKind,
+    parts: Vec<Value>,
+}
+
+enum PartsKind {
+    Seq,
+    Tuple,
+    Map,
+    Struct(&'static str),
+    Variant {
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+    },
+}
+
+impl Parts {
+    fn new(kind: PartsKind) -> Self {
+        Self {
+            kind,
+            parts: Vec::new(),
+        }
+    }
+
+    fn finish(self) -> Value {
+        match self.kind {
+            PartsKind::Seq => json!({ "seq": self.parts.first() }),
+            // Arrays are tuples to serde, and written the same way; long ones read better as arrays
+            PartsKind::Tuple
+                if self.parts.len() > 3 && self.parts.windows(2).all(|w| w[0] == w[1]) =>
+            {
+                json!({ "array": self.parts[0], "len": self.parts.len() })
+            }
+            PartsKind::Tuple => json!({ "tuple": self.parts }),
+            PartsKind::Map => json!({ "map": self.parts.get(..2) }),
+            PartsKind::Struct(name) => json!({ "struct": name, "fields": self.parts }),
+            PartsKind::Variant {
+                name,
+                index,
+                variant,
+            } => json!({ "enum": name, "variant": variant, "index": index, "fields": self.parts }),
+        }
+    }
+}
+
+impl Serializer for Tracer {
+    type Ok = Value;
+    type Error = TraceError;
+    type SerializeSeq = Parts;
+    type SerializeTuple = Parts;
+    type SerializeTupleStruct = Parts;
+    type SerializeTupleVariant = Parts;
+    type SerializeMap = Parts;
+    type SerializeStruct = Parts;
+    type SerializeStructVariant = Parts;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn serialize_bool(self, _: bool) -> Result<Value, TraceError> {
+        Ok(json!("bool"))
+    }
+    fn serialize_i8(self, _: i8) -> Result<Value, TraceError> {
+        Ok(json!("i8"))
+    }
+    fn serialize_i16(self, _: i16) -> Result<Value, TraceError> {
+        Ok(json!("i16"))
+    }
+    fn serialize_i32(self, _: i32) -> Result<Value, TraceError> {
+        Ok(json!("i32"))
+    }
+    fn serialize_i64(self, _: i64) -> Result<Value, TraceError> {
+        Ok(json!("i64"))
+    }
+    fn serialize_u8(self, _: u8) -> Result<Value, TraceError> {
+        Ok(json!("u8"))
+    }
+    fn serialize_u16(self, _: u16) -> Result<Value, TraceError> {
+        Ok(json!("u16"))
+    }
+    fn serialize_u32(self, _: u32) -> Result<Value, TraceError> {
+        Ok(json!("u32"))
+    }
+    fn serialize_u64(self, _: u64) -> Result<Value, TraceError> {
+        Ok(json!("u64"))
+    }
+    fn serialize_f32(self, _: f32) -> Result<Value, TraceError> {
+        Ok(json!("f32"))
+    }
+    fn serialize_f64(self, _: f64) -> Result<Value, TraceError> {
+        Ok(json!("f64"))
+    }
+    fn serialize_char(self, _: char) -> Result<Value, TraceError> {
+        Ok(json!("char"))
+    }
+    fn serialize_str(self, _: &str) -> Result<Value, TraceError> {
+        Ok(json!("string"))
+    }
+    fn serialize_bytes(self, _: &[u8]) -> Result<Value, TraceError> {
+        Ok(json!("bytes"))
+    }
+    fn serialize_none(self) -> Result<Value, TraceError> {
+        Err(ser::Error::custom("examples must fill every option"))
+    }
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Value, TraceError> {
+        Ok(json!({ "option": trace(value) }))
+    }
+    fn serialize_unit(self) -> Result<Value, TraceError> {
+        Ok(json!("unit"))
+    }
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Value, TraceError> {
+        Ok(json!({ "struct": name, "fields": [] }))
+    }
+    fn serialize_unit_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+    ) -> Result<Value, TraceError> {
+        Ok(json!({ "enum": name, "variant": variant, "index": index }))
+    }
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Value, TraceError> {
+        Ok(json!({ "newtype": name, "of": trace(value) }))
+    }
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Value, TraceError> {
+        // Which variant is only known at run time; the definitions list them all
+        Ok(json!({ "enum": name }))
+    }
+    fn serialize_seq(self, _: Option<usize>) -> Result<Parts, TraceError> {
+        Ok(Parts::new(PartsKind::Seq))
+    }
+    fn serialize_tuple(self, _: usize) -> Result<Parts, TraceError> {
+        Ok(Parts::new(PartsKind::Tuple))
+    }
+    fn serialize_tuple_struct(self, name: &'static str, _: usize) -> Result<Parts, TraceError> {
+        Ok(Parts::new(PartsKind::Struct(name)))
+    }
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<Parts, TraceError> {
+        Ok(Parts::new(PartsKind::Variant {
+            name,
+            index,
+            variant,
+        }))
+    }
+    fn serialize_map(self, _: Option<usize>) -> Result<Parts, TraceError> {
+        Ok(Parts::new(PartsKind::Map))
+    }
+    fn serialize_struct(self, name: &'static str, _: usize) -> Result<Parts, TraceError> {
+        Ok(Parts::new(PartsKind::Struct(name)))
+    }
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<Parts, TraceError> {
+        Ok(Parts::new(PartsKind::Variant {
+            name,
+            index,
+            variant,
+        }))
+    }
+}
+
+impl Parts {
+    fn push<T: ?Sized + Serialize>(&mut self, value: &T) {
+        self.parts.push(trace(value));
+    }
+
+    fn push_field<T: ?Sized + Serialize>(&mut self, name: &'static str, value: &T) {
+        self.parts
+            .push(json!({ "name": name, "type": trace(value) }));
+    }
+}
+
+impl ser::SerializeSeq for Parts {
+    type Ok = Value;
+    type Error = TraceError;
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), TraceError> {
+        self.push(value);
+        Ok(())
+    }
+    fn end(self) -> Result<Value, TraceError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTuple for Parts {
+    type Ok = Value;
+    type Error = TraceError;
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), TraceError> {
+        self.push(value);
+        Ok(())
+    }
+    fn end(self) -> Result<Value, TraceError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleStruct for Parts {
+    type Ok = Value;
+    type Error = TraceError;
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), TraceError> {
+        let name = ["0", "1", "2", "3"]
+            .get(self.parts.len())
+            .copied()
+            .unwrap_or("_");
+        self.push_field(name, value);
+        Ok(())
+    }
+    fn end(self) -> Result<Value, TraceError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleVariant for Parts {
+    type Ok = Value;
+    type Error = TraceError;
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), TraceError> {
+        self.push(value);
+        Ok(())
+    }
+    fn end(self) -> Result<Value, TraceError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeMap for Parts {
+    type Ok = Value;
+    type Error = TraceError;
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), TraceError> {
+        self.push(key);
+        Ok(())
+    }
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), TraceError> {
+        self.push(value);
+        Ok(())
+    }
+    fn end(self) -> Result<Value, TraceError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeStruct for Parts {
+    type Ok = Value;
+    type Error = TraceError;
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<(), TraceError> {
+        self.push_field(name, value);
+        Ok(())
+    }
+    fn end(self) -> Result<Value, TraceError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeStructVariant for Parts {
+    type Ok = Value;
+    type Error = TraceError;
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<(), TraceError> {
+        self.push_field(name, value);
+        Ok(())
+    }
+    fn end(self) -> Result<Value, TraceError> {
+        Ok(self.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_covers_every_message() {
+        let names: Vec<String> = examples()
+            .iter()
+            .map(|message| trace(message)["variant"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(names, variants::<Message>());
+
+        // Header tags aren't variant indexes, but no two messages share one
+        let mut kinds: Vec<u8> = examples().iter().map(Message::kind).collect();
+        kinds.sort_unstable();
+        kinds.dedup();
+        assert_eq!(kinds.len(), examples().len());
+    }
+
+    #[test]
+    fn test_schema_matches_the_committed_copy() {
+        let schema = serde_json::to_string_pretty(&protocol_schema()).unwrap() + "\n";
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(SCHEMA_PATH);
+        if std::env::var_os(UPDATE_VAR).is_some() {
+            std::fs::write(&path, schema).unwrap();
+            return;
+        }
+        let committed = std::fs::read_to_string(&path).unwrap_or_default();
+        assert!(
+            committed == schema,
+            "{} is out of date; run the tests with {}=1 to regenerate it",
+            SCHEMA_PATH,
+            UPDATE_VAR
+        );
+    }
+}
@@ -0,0 +1,270 @@
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// Longest we wait for the proxy to connect us before giving up
+const PROXY_TIMEOUT: Duration = Duration::from_secs(10);
+
+const SOCKS_VERSION: u8 = 5;
+const NO_AUTH: u8 = 0x00;
+const USERNAME_PASSWORD: u8 = 0x02;
+const NO_ACCEPTABLE_METHOD: u8 = 0xFF;
+const CONNECT: u8 = 0x01;
+const ADDRESS_IPV4: u8 = 0x01;
+const ADDRESS_DOMAIN: u8 = 0x03;
+const ADDRESS_IPV6: u8 = 0x04;
+
+/// How TCP connections for signaling reach the other side
+///
+/// Written as `direct` or `socks5://[user:password@]host:port`. Host names
+/// are passed to the proxy unresolved, so with Tor the lookup happens inside
+/// the network too.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Proxy {
+    #[default]
+    Direct,
+    Socks5 {
+        /// Proxy as `host:port`
+        address: String,
+        /// Username and password, if the proxy asks for them
+        credentials: Option<(String, String)>,
+    },
+}
+
+impl Proxy {
+    pub fn is_direct(&self) -> bool {
+        *self == Proxy::Direct
+    }
+
+    /// Opens a TCP connection to `target` (`host:port`), through the proxy if there is one
+    pub async fn connect(&self, target: &str) -> Result<TcpStream> {
+        match self {
+            Proxy::Direct => Ok(TcpStream::connect(target).await?),
+            Proxy::Socks5 {
+                address,
+                credentials,
+            } => tokio::time::timeout(
+                PROXY_TIMEOUT,
+                socks5_connect(address, credentials.as_ref(), target),
+            )
+            .await
+            .map_err(|_| anyhow!("SOCKS5 proxy {} timed out", address))?,
+        }
+    }
+}
+
+impl fmt::Display for Proxy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Proxy::Direct => write!(f, "direct"),
+            Proxy::Socks5 {
+                address,
+                credentials: None,
+            } => write!(f, "socks5://{}", address),
+            Proxy::Socks5 {
+                address,
+                credentials: Some((username, password)),
+            } => write!(f, "socks5://{}:{}@{}", username, password, address),
+        }
+    }
+}
+
+impl FromStr for Proxy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s == "direct" {
+            return Ok(Proxy::Direct);
+        }
+        let rest = s
+            .strip_prefix("socks5://")
+            .ok_or_else(|| format!("Invalid proxy (expected direct or socks5://...): {}", s))?;
+        let (credentials, address) = match rest.rsplit_once('@') {
+            Some((credentials, address)) => {
+                let (username, password) = credentials
+                    .split_once(':')
+                    .ok_or_else(|| format!("Invalid proxy credentials: {}", s))?;
+                if username.is_empty() || username.len() > 255 || password.len() > 255 {
+                    return Err(format!("Invalid proxy credentials: {}", s));
+                }
+                (Some((username.to_string(), password.to_string())), address)
+            }
+            None => (None, rest),
+        };
+        split_host_port(address).ok_or_else(|| format!("Invalid proxy address: {}", address))?;
+        Ok(Proxy::Socks5 {
+            address: address.to_string(),
+            credentials,
+        })
+    }
+}
+
+/// Splits `host:port` or `[v6]:port`
+fn split_host_port(target: &str) -> Option<(&str, u16)> {
+    let (host, port) = target.rsplit_once(':')?;
+    let host = host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host);
+    if host.is_empty() || host.len() > 255 || host.contains(char::is_whitespace) {
+        return None;
+    }
+    Some((host, port.parse().ok()?))
+}
+
+/// Connects to `target` through a SOCKS5 proxy (RFC 1928), logging in if needed (RFC 1929)
+async fn socks5_connect(
+    proxy: &str,
+    credentials: Option<&(String, String)>,
+    target: &str,
+) -> Result<TcpStream> {
+    let (host, port) =
+        split_host_port(target).ok_or_else(|| anyhow!("Invalid address: {}", target))?;
+    let mut stream = TcpStream::connect(proxy).await?;
+
+    let method = if credentials.is_some() {
+        USERNAME_PASSWORD
+    } else {
+        NO_AUTH
+    };
+    stream.write_all(&[SOCKS_VERSION, 1, method]).await?;
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).await?;
+    if reply[0] != SOCKS_VERSION {
+        return Err(anyhow!("{} is not a SOCKS5 proxy", proxy));
+    }
+    match (reply[1], credentials) {
+        (NO_AUTH, _) => {}
+        (USERNAME_PASSWORD, Some((username, password))) => {
+            let mut login = vec![1, username.len() as u8];
+            login.extend_from_slice(username.as_bytes());
+            login.push(password.len() as u8);
+            login.extend_from_slice(password.as_bytes());
+            stream.write_all(&login).await?;
+            stream.read_exact(&mut reply).await?;
+            if reply[1] != 0 {
+                return Err(anyhow!("SOCKS5 proxy rejected our username and password"));
+            }
+        }
+        (NO_ACCEPTABLE_METHOD, None) | (USERNAME_PASSWORD, None) => {
+            return Err(anyhow!("SOCKS5 proxy requires a username and password"));
+        }
+        (other, _) => return Err(anyhow!("SOCKS5 proxy chose unsupported method {}", other)),
+    }
+
+    let mut request = vec![SOCKS_VERSION, CONNECT, 0];
+    match host.parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => {
+            request.push(ADDRESS_IPV4);
+            request.extend_from_slice(&ip.octets());
+        }
+        Ok(IpAddr::V6(ip)) => {
+            request.push(ADDRESS_IPV6);
+            request.extend_from_slice(&ip.octets());
+        }
+        Err(_) => {
+            request.push(ADDRESS_DOMAIN);
+            request.push(host.len() as u8);
+            request.extend_from_slice(host.as_bytes());
+        }
+    }
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut header = [0u8; 4];
+    stream.read_exact(&mut header).await?;
+    if header[1] != 0 {
+        return Err(anyhow!(
+            "SOCKS5 proxy couldn't connect to {}: {}",
+            target,
+            reply_message(header[1])
+        ));
+    }
+    // The address the proxy connected from isn't needed, but has to be read past
+    let bound_len = match header[3] {
+        ADDRESS_IPV4 => 4,
+        ADDRESS_IPV6 => 16,
+        ADDRESS_DOMAIN => stream.read_u8().await? as usize,
+        other => return Err(anyhow!("SOCKS5 proxy sent unknown address type {}", other)),
+    };
+    let mut bound = vec![0u8; bound_len + 2];
+    stream.read_exact(&mut bound).await?;
+
+    Ok(stream)
+}
+
+/// Meaning of a SOCKS5 reply code
+fn reply_message(code: u8) -> &'static str {
+    match code {
+        1 => "general failure",
+        2 => "not allowed by the proxy's rules",
+        3 => "network unreachable",
+        4 => "host unreachable",
+        5 => "connection refused",
+        6 => "TTL expired",
+        7 => "command not supported",
+        8 => "address type not supported",
+        _ => "unknown error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_socks5_connect_with_login() {
+        for text in [
+            "direct",
+            "socks5://127.0.0.1:9050",
+            "socks5://bob:pw@proxy.example:1080",
+        ] {
+            let proxy: Proxy = text.parse().unwrap();
+            assert_eq!(proxy.to_string(), text);
+        }
+        for invalid in ["socks4://a:1", "socks5://a", "socks5://:pw@a:1"] {
+            assert!(invalid.parse::<Proxy>().is_err(), "{}", invalid);
+        }
+
+        // A proxy that wants a login and relays one connection back to us
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut greeting = [0u8; 3];
+            stream.read_exact(&mut greeting).await.unwrap();
+            assert_eq!(greeting, [5, 1, USERNAME_PASSWORD]);
+            stream.write_all(&[5, USERNAME_PASSWORD]).await.unwrap();
+
+            let mut login = [0u8; 8];
+            stream.read_exact(&mut login).await.unwrap();
+            assert_eq!(&login, b"\x01\x03bob\x02pw");
+            stream.write_all(&[1, 0]).await.unwrap();
+
+            let mut request = [0u8; 5];
+            stream.read_exact(&mut request).await.unwrap();
+            assert_eq!(request[3], ADDRESS_DOMAIN);
+            let mut host = vec![0u8; request[4] as usize + 2];
+            stream.read_exact(&mut host).await.unwrap();
+            assert_eq!(&host[..host.len() - 2], b"signal.example");
+            stream
+                .write_all(&[5, 0, 0, ADDRESS_IPV4, 10, 0, 0, 1, 0x1F, 0x90])
+                .await
+                .unwrap();
+            stream.write_all(b"hello").await.unwrap();
+        });
+
+        let proxy: Proxy = format!("socks5://bob:pw@{}", address).parse().unwrap();
+        let mut stream = proxy.connect("signal.example:443").await.unwrap();
+        let mut greeting = [0u8; 5];
+        stream.read_exact(&mut greeting).await.unwrap();
+        assert_eq!(&greeting, b"hello");
+        server.await.unwrap();
+    }
+}
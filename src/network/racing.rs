@@ -0,0 +1,177 @@
+use anyhow::{anyhow, Result};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::task::JoinSet;
+
+/// Delay before starting the next candidate while earlier ones are still trying
+pub const DEFAULT_STAGGER: Duration = Duration::from_millis(250);
+
+/// Orders candidates by alternating address families, keeping each family's order
+///
+/// Like Happy Eyeballs (RFC 8305), this stops a broken family from delaying
+/// every attempt in the other.
+pub fn order_candidates(candidates: &[SocketAddr]) -> Vec<SocketAddr> {
+    let mut unique = Vec::new();
+    for candidate in candidates {
+        if !unique.contains(candidate) {
+            unique.push(*candidate);
+        }
+    }
+
+    // Start with whichever family the link listed first
+    let first_is_v6 = unique.first().is_some_and(|addr| addr.is_ipv6());
+    let (mut first, mut second): (Vec<_>, Vec<_>) = unique
+        .into_iter()
+        .partition(|addr| addr.is_ipv6() == first_is_v6);
+    first.reverse();
+    second.reverse();
+
+    let mut ordered = Vec::new();
+    while let Some(addr) = first.pop() {
+        ordered.push(addr);
+        ordered.extend(second.pop());
+    }
+    ordered.extend(second.into_iter().rev());
+    ordered
+}
+
+/// Tries each candidate, starting the next one after `stagger` or as soon as an
+/// attempt fails, and returns the first success
+///
+/// Attempts still running when one succeeds are cancelled.
+pub async fn race_candidates<T, F, Fut>(
+    candidates: &[SocketAddr],
+    stagger: Duration,
+    attempt: F,
+) -> Result<(SocketAddr, T)>
+where
+    T: Send + 'static,
+    F: Fn(SocketAddr) -> Fut,
+    Fut: Future<Output = Result<T>> + Send + 'static,
+{
+    let mut pending = order_candidates(candidates).into_iter();
+    let mut attempts = JoinSet::new();
+    let mut errors = Vec::new();
+
+    loop {
+        if attempts.is_empty() {
+            match pending.next() {
+                Some(addr) => {
+                    let future = attempt(addr);
+                    attempts.spawn(async move { (addr, future.await) });
+                }
+                None => break,
+            }
+        }
+
+        tokio::select! {
+            finished = attempts.join_next() => {
+                match finished {
+                    Some(Ok((addr, Ok(value)))) => {
+                        // Dropping the set cancels the other attempts
+                        attempts.abort_all();
+                        return Ok((addr, value));
+                    }
+                    Some(Ok((addr, Err(e)))) => {
+                        log::debug!("Connection attempt to {} failed: {}", addr, e);
+                        errors.push(format!("{}: {}", addr, e));
+                        // Start the next candidate right away
+                        if let Some(addr) = pending.next() {
+                            let future = attempt(addr);
+                            attempts.spawn(async move { (addr, future.await) });
+                        }
+                    }
+                    Some(Err(e)) => errors.push(e.to_string()),
+                    None => {}
+                }
+            }
+            _ = tokio::time::sleep(stagger) => {
+                if let Some(addr) = pending.next() {
+                    let future = attempt(addr);
+                    attempts.spawn(async move { (addr, future.await) });
+                }
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Err(anyhow!("No addresses to connect to"))
+    } else {
+        Err(anyhow!(
+            "All connection attempts failed: {}",
+            errors.join("; ")
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> SocketAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_order_candidates() {
+        let candidates = [
+            addr("192.168.1.5:4000"),
+            addr("203.0.113.1:4000"),
+            addr("[2001:db8::1]:4000"),
+            addr("192.168.1.5:4000"),
+            addr("10.0.0.2:4000"),
+        ];
+
+        assert_eq!(
+            order_candidates(&candidates),
+            vec![
+                addr("192.168.1.5:4000"),
+                addr("[2001:db8::1]:4000"),
+                addr("203.0.113.1:4000"),
+                addr("10.0.0.2:4000"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_race_prefers_first_success() {
+        let slow = addr("10.0.0.1:1");
+        let failing = addr("10.0.0.2:1");
+        let fast = addr("10.0.0.3:1");
+
+        let (winner, value) = race_candidates(
+            &[slow, failing, fast],
+            Duration::from_millis(20),
+            move |candidate| async move {
+                if candidate == slow {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    Ok(1)
+                } else if candidate == failing {
+                    Err(anyhow!("unreachable"))
+                } else {
+                    Ok(3)
+                }
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(winner, fast);
+        assert_eq!(value, 3);
+    }
+
+    #[tokio::test]
+    async fn test_race_reports_all_failures() {
+        let result = race_candidates(
+            &[addr("10.0.0.1:1"), addr("10.0.0.2:1")],
+            Duration::from_millis(20),
+            |_| async { Err::<(), _>(anyhow!("refused")) },
+        )
+        .await;
+
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("10.0.0.1:1: refused"));
+        assert!(error.contains("10.0.0.2:1: refused"));
+    }
+}
@@ -0,0 +1,176 @@
+use anyhow::{anyhow, Result};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Maximum number of message keys that may be skipped in one step, which
+/// bounds the memory spent on lost or reordered packets
+pub(crate) const MAX_SKIP: u64 = 256;
+
+/// Derive a 32-byte key from `key` and a context label
+pub fn derive_key(key: &[u8; 32], label: &[u8]) -> [u8; 32] {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(label);
+
+    let mut out = [0u8; 32];
+    out.copy_from_slice(&mac.finalize().into_bytes());
+    out
+}
+
+/// A symmetric hash ratchet producing one key per message
+///
+/// Every message key is derived from the current chain key, which is then
+/// replaced by a one-way hash of itself. Once a key has been used it cannot
+/// be recomputed from the ratchet state, so compromising the current state
+/// does not expose earlier traffic.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HashRatchet {
+    /// Chain key for the message at `index`
+    chain_key: [u8; 32],
+    /// Index of the next message key the chain will produce
+    index: u64,
+    /// Keys for messages skipped over but not yet received
+    skipped: HashMap<u64, [u8; 32]>,
+}
+
+impl HashRatchet {
+    /// Create a ratchet from a root secret and a direction label
+    pub fn new(root: &[u8; 32], label: &[u8]) -> Self {
+        Self {
+            chain_key: derive_key(root, label),
+            index: 0,
+            skipped: HashMap::new(),
+        }
+    }
+
+    /// Advance the chain by one step, returning the message key
+    fn step(&mut self) -> [u8; 32] {
+        let message_key = derive_key(&self.chain_key, &[0x01]);
+        self.chain_key = derive_key(&self.chain_key, &[0x02]);
+        self.index += 1;
+        message_key
+    }
+
+    /// Take the next message key in order, with its index, as a sender does
+    pub fn next_key(&mut self) -> (u64, [u8; 32]) {
+        let index = self.index;
+        (index, self.step())
+    }
+
+    /// Get the message key for `index` without consuming it
+    ///
+    /// A receiver checks a packet with this before committing to its index
+    /// with [`key_for`](Self::key_for), so a forged packet changes nothing.
+    pub fn peek(&self, index: u64) -> Result<[u8; 32]> {
+        if index < self.index {
+            return self
+                .skipped
+                .get(&index)
+                .copied()
+                .ok_or_else(|| anyhow!("Message key {} already used or expired", index));
+        }
+
+        if index - self.index > MAX_SKIP {
+            return Err(anyhow!("Message index {} too far ahead", index));
+        }
+
+        let mut chain_key = self.chain_key;
+        for _ in self.index..index {
+            chain_key = derive_key(&chain_key, &[0x02]);
+        }
+        Ok(derive_key(&chain_key, &[0x01]))
+    }
+
+    /// Get the message key for `index`, consuming it
+    ///
+    /// Keys for indices skipped along the way are retained so late packets
+    /// can still be decrypted; each key is handed out at most once.
+    pub fn key_for(&mut self, index: u64) -> Result<[u8; 32]> {
+        let key = self.peek(index)?;
+        if index < self.index {
+            self.skipped.remove(&index);
+            return Ok(key);
+        }
+
+        while self.index < index {
+            let skipped_index = self.index;
+            let key = self.step();
+            self.skipped.insert(skipped_index, key);
+        }
+
+        // Bound the cache to the most recent window
+        let oldest = self.index.saturating_sub(MAX_SKIP);
+        self.skipped.retain(|&i, _| i >= oldest);
+
+        self.step();
+        Ok(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_both_ends_derive_same_keys() {
+        let mut sender = HashRatchet::new(&[5u8; 32], b"a->b");
+        let mut receiver = HashRatchet::new(&[5u8; 32], b"a->b");
+
+        for i in 0..4 {
+            assert_eq!(sender.next_key(), (i, receiver.key_for(i).unwrap()));
+        }
+
+        // Keys differ per message and per direction
+        let mut other = HashRatchet::new(&[5u8; 32], b"b->a");
+        assert_ne!(sender.key_for(4).unwrap(), other.key_for(0).unwrap());
+    }
+
+    #[test]
+    fn test_out_of_order_and_reuse() {
+        let mut sender = HashRatchet::new(&[1u8; 32], b"dir");
+        let mut receiver = HashRatchet::new(&[1u8; 32], b"dir");
+
+        let k0 = sender.key_for(0).unwrap();
+        let k1 = sender.key_for(1).unwrap();
+        let k2 = sender.key_for(2).unwrap();
+
+        // Message 2 arrives first, then the earlier ones
+        assert_eq!(receiver.key_for(2).unwrap(), k2);
+        assert_eq!(receiver.key_for(0).unwrap(), k0);
+        assert_eq!(receiver.key_for(1).unwrap(), k1);
+
+        // A key is never handed out twice
+        assert!(receiver.key_for(1).is_err());
+    }
+
+    #[test]
+    fn test_peek_leaves_the_chain_alone() {
+        let mut sender = HashRatchet::new(&[3u8; 32], b"dir");
+        let mut receiver = HashRatchet::new(&[3u8; 32], b"dir");
+
+        let k0 = sender.key_for(0).unwrap();
+        let k1 = sender.key_for(1).unwrap();
+        let k2 = sender.key_for(2).unwrap();
+
+        assert_eq!(receiver.peek(2).unwrap(), k2);
+        assert_eq!(receiver.peek(2).unwrap(), k2);
+        assert_eq!(receiver.key_for(2).unwrap(), k2);
+
+        // Skipped keys can be looked at until they are used
+        assert_eq!(receiver.peek(0).unwrap(), k0);
+        assert_eq!(receiver.key_for(0).unwrap(), k0);
+        assert!(receiver.peek(0).is_err());
+        assert_eq!(receiver.key_for(1).unwrap(), k1);
+        assert!(receiver.peek(MAX_SKIP + 4).is_err());
+    }
+
+    #[test]
+    fn test_skip_limit() {
+        let mut ratchet = HashRatchet::new(&[2u8; 32], b"dir");
+        assert!(ratchet.key_for(MAX_SKIP + 1).is_err());
+        assert!(ratchet.key_for(MAX_SKIP).is_ok());
+    }
+}
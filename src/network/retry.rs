@@ -0,0 +1,255 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use rand::Rng;
+
+/// How often, and how patiently, a failed connection attempt is repeated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Tries in all, the first included
+    pub attempts: u32,
+    /// Wait before the first retry, doubled for each one after
+    pub backoff: Duration,
+    /// Longest wait between two tries
+    pub max_backoff: Duration,
+    /// Percent of each wait randomly added or taken off, so peers that
+    /// failed together don't all retry together
+    pub jitter_percent: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(5),
+            jitter_percent: 20,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Wait before retry number `retry`, counting from 1, with `spread`
+    /// between -1 and 1 picking where in the jitter it falls
+    pub fn delay(&self, retry: u32, spread: f64) -> Duration {
+        let doublings = retry.saturating_sub(1).min(16);
+        let wait = self
+            .backoff
+            .saturating_mul(1 << doublings)
+            .min(self.max_backoff);
+        let jitter = self.jitter_percent as f64 / 100.0;
+        wait.mul_f64((1.0 + jitter * spread.clamp(-1.0, 1.0)).max(0.0))
+    }
+}
+
+/// Written as `attempts,backoff_ms,max_backoff_ms,jitter_percent`
+impl fmt::Display for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{},{},{},{}",
+            self.attempts,
+            self.backoff.as_millis(),
+            self.max_backoff.as_millis(),
+            self.jitter_percent
+        )
+    }
+}
+
+impl FromStr for RetryPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid retry policy: {}", s);
+        let parts: Vec<&str> = s.split(',').map(str::trim).collect();
+        let [attempts, backoff, max_backoff, jitter] = parts[..] else {
+            return Err(invalid());
+        };
+        let attempts: u32 = attempts.parse().map_err(|_| invalid())?;
+        let backoff: u64 = backoff.parse().map_err(|_| invalid())?;
+        let max_backoff: u64 = max_backoff.parse().map_err(|_| invalid())?;
+        let jitter_percent: u32 = jitter.parse().map_err(|_| invalid())?;
+        if attempts == 0 || max_backoff < backoff || jitter_percent > 100 {
+            return Err(invalid());
+        }
+        Ok(Self {
+            attempts,
+            backoff: Duration::from_millis(backoff),
+            max_backoff: Duration::from_millis(max_backoff),
+            jitter_percent,
+        })
+    }
+}
+
+/// A connection attempt that can be given a policy of its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryOperation {
+    /// Reaching the host of a room we join
+    Join,
+    /// Reaching another peer in the room
+    Peer,
+}
+
+impl fmt::Display for RetryOperation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RetryOperation::Join => write!(f, "join"),
+            RetryOperation::Peer => write!(f, "peer"),
+        }
+    }
+}
+
+impl FromStr for RetryOperation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "join" => Ok(RetryOperation::Join),
+            "peer" => Ok(RetryOperation::Peer),
+            _ => Err(format!("Unknown connection operation: {}", s)),
+        }
+    }
+}
+
+/// Policies for operations that don't use the default one
+///
+/// Written as `join:5,1000,8000,20;peer:...`, or `none`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RetryOverrides(pub Vec<(RetryOperation, RetryPolicy)>);
+
+impl fmt::Display for RetryOverrides {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "none");
+        }
+        let overrides: Vec<String> = self
+            .0
+            .iter()
+            .map(|(operation, policy)| format!("{}:{}", operation, policy))
+            .collect();
+        write!(f, "{}", overrides.join(";"))
+    }
+}
+
+impl FromStr for RetryOverrides {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim() == "none" {
+            return Ok(Self::default());
+        }
+        s.split(';')
+            .map(|entry| {
+                let (operation, policy) = entry
+                    .split_once(':')
+                    .ok_or_else(|| format!("Invalid retry override: {}", entry))?;
+                Ok((operation.parse()?, policy.parse()?))
+            })
+            .collect::<Result<_, String>>()
+            .map(Self)
+    }
+}
+
+/// The default retry policy and any overrides of it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RetrySettings {
+    pub default: RetryPolicy,
+    pub overrides: RetryOverrides,
+}
+
+impl RetrySettings {
+    /// The policy `operation` uses
+    pub fn policy(&self, operation: RetryOperation) -> RetryPolicy {
+        self.overrides
+            .0
+            .iter()
+            .find(|(overridden, _)| *overridden == operation)
+            .map_or(self.default, |(_, policy)| *policy)
+    }
+}
+
+/// Counts the tries of one operation and waits out the backoff between them
+///
+/// Driven from the caller's own loop rather than taking a closure, so each
+/// attempt can borrow what it connects mutably.
+#[derive(Debug, Clone)]
+pub struct Retry {
+    policy: RetryPolicy,
+    attempt: u32,
+}
+
+impl Retry {
+    pub fn new(policy: RetryPolicy) -> Self {
+        Self { policy, attempt: 1 }
+    }
+
+    /// The try under way, counting from 1
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    pub fn attempts(&self) -> u32 {
+        self.policy.attempts
+    }
+
+    /// Waits before the next try after `error`, or gives it back if that
+    /// was the last
+    pub async fn after<E: fmt::Display>(&mut self, error: E) -> Result<(), E> {
+        if self.attempt >= self.policy.attempts {
+            return Err(error);
+        }
+        let wait = self
+            .policy
+            .delay(self.attempt, rand::thread_rng().gen_range(-1.0..=1.0));
+        log::warn!(
+            "Attempt {} of {} failed, retrying in {:?}: {}",
+            self.attempt,
+            self.policy.attempts,
+            wait,
+            error
+        );
+        tokio::time::sleep(wait).await;
+        self.attempt += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_retries_back_off_until_the_last_attempt() {
+        let policy: RetryPolicy = "3,10,15,0".parse().unwrap();
+        assert_eq!(policy.delay(1, 1.0), Duration::from_millis(10));
+        assert_eq!(policy.delay(2, 1.0), Duration::from_millis(15));
+        assert_eq!(policy.to_string(), "3,10,15,0");
+
+        let jittery = RetryPolicy::default();
+        assert_eq!(jittery.delay(1, -1.0), Duration::from_millis(400));
+        assert_eq!(jittery.delay(1, 1.0), Duration::from_millis(600));
+
+        let started = std::time::Instant::now();
+        let mut retry = Retry::new(policy);
+        let mut tries = Vec::new();
+        let result: Result<(), &str> = loop {
+            tries.push(retry.attempt());
+            if let Err(e) = retry.after("refused").await {
+                break Err(e);
+            }
+        };
+        assert_eq!(result, Err("refused"));
+        assert_eq!(tries, vec![1, 2, 3]);
+        assert!(started.elapsed() >= Duration::from_millis(25));
+
+        let overrides: RetryOverrides = "join:5,1000,8000,20".parse().unwrap();
+        let settings = RetrySettings {
+            default: policy,
+            overrides,
+        };
+        assert_eq!(settings.policy(RetryOperation::Join).attempts, 5);
+        assert_eq!(settings.policy(RetryOperation::Peer), policy);
+        assert!("0,10,15,0".parse::<RetryPolicy>().is_err());
+    }
+}
@@ -1,19 +1,37 @@
 use anyhow::{anyhow, Result};
+use bytes::{BufMut, Bytes, BytesMut};
 use chacha20poly1305::{
-    aead::{Aead, KeyInit},
-    XChaCha20Poly1305,
+    aead::{Aead, AeadInPlace, KeyInit, Payload},
+    XChaCha20Poly1305, XNonce,
 };
 use rand::{thread_rng, RngCore};
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tokio::net::UdpSocket;
 use x25519_dalek::{PublicKey, StaticSecret};
 
+use super::bandwidth::BandwidthMonitor;
+use super::bundle::BundledFrame;
+use super::capabilities::PeerCapabilities;
+use super::clock_sync::now_micros;
+use super::fragment::{Reassembler, MAX_FRAGMENTS};
+use super::impairment::impair;
+use super::keepalive::KEEPALIVE_DATAGRAM;
+use super::mtu::MIN_DATAGRAM_SIZE;
 use super::p2p::ConnectionState;
+use super::pacing::{PacingConfig, SendPacer, SendPriority};
+use super::ratchet::{derive_key, HashRatchet};
+use super::stats::PacketCounts;
+use super::tamper::{CryptoFailure, ReplayWindow};
+use crate::audio::{AudioCodec, AudioDecoder, AudioEncoder, AudioFrame, NoiseDescriptor};
 
 /// A key pair for asymmetric encryption
+#[derive(Clone)]
 pub struct Keypair {
     pub secret: StaticSecret,
     pub public: PublicKey,
@@ -22,7 +40,7 @@ pub struct Keypair {
 impl Keypair {
     /// Generate a new keypair
     pub fn generate() -> Self {
-        let secret = StaticSecret::new(thread_rng());
+        let secret = StaticSecret::random_from_rng(thread_rng());
         let public = PublicKey::from(&secret);
 
         Self { secret, public }
@@ -35,6 +53,461 @@ impl Keypair {
     }
 }
 
+/// Name of the AEAD every channel encrypts with
+pub const CIPHER: &str = "XChaCha20-Poly1305";
+
+/// Length of an XChaCha20-Poly1305 nonce in bytes
+pub(super) const NONCE_LEN: usize = 24;
+
+/// Length of a Poly1305 authentication tag in bytes
+const TAG_LEN: usize = 16;
+
+/// Length of the cleartext packet header (message kind + sequence number)
+pub(super) const HEADER_LEN: usize = 1 + 8;
+
+/// Length of the epoch and index of a ratcheted packet's key, sent after the header
+pub(super) const RATCHET_LEN: usize = 4 + 4;
+
+/// Most bytes an encrypted packet adds around its serialized message
+pub(super) const PACKET_OVERHEAD: usize = HEADER_LEN + RATCHET_LEN + NONCE_LEN + TAG_LEN;
+
+/// Bytes a packet under the static key adds, as MTU probes always are
+pub(super) const STATIC_PACKET_OVERHEAD: usize = HEADER_LEN + NONCE_LEN + TAG_LEN;
+
+/// Bytes a fragment message adds around its data when serialized
+const FRAGMENT_OVERHEAD: usize = 4 + 4 + 2 + 2 + 8;
+
+/// Header flag marking a packet encrypted with a ratcheted message key
+pub(super) const RATCHET_FLAG: u8 = 0x80;
+
+/// How long the side starting rekeys keeps an epoch's ratchets before
+/// agreeing fresh ones
+const REKEY_INTERVAL: Duration = Duration::from_secs(120);
+
+/// How long to wait for the peer's half of a rekey before asking again
+const REKEY_RETRY: Duration = Duration::from_secs(2);
+
+/// How per-message nonces are generated by a [`CryptoProvider`]
+///
+/// The nonce travels in the clear with every packet, so the strategy is a
+/// purely local choice and both ends interoperate regardless of what the
+/// other side picked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum NonceStrategy {
+    /// A fresh 192-bit random nonce per message
+    #[default]
+    Random,
+    /// A random per-channel prefix followed by a 64-bit message counter,
+    /// which can never repeat under the same key
+    Counter,
+}
+
+impl fmt::Display for NonceStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NonceStrategy::Random => f.write_str("random"),
+            NonceStrategy::Counter => f.write_str("counter"),
+        }
+    }
+}
+
+impl FromStr for NonceStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "random" => Ok(NonceStrategy::Random),
+            "counter" => Ok(NonceStrategy::Counter),
+            _ => Err(format!("Unknown nonce strategy: {}", s)),
+        }
+    }
+}
+
+/// AEAD encryption for an established channel
+pub struct CryptoProvider {
+    cipher: XChaCha20Poly1305,
+    /// Key the cipher was made with, kept so the channel can be handed off
+    key: [u8; 32],
+    strategy: NonceStrategy,
+    /// Random prefix used by the counter strategy
+    nonce_prefix: [u8; NONCE_LEN - 8],
+    /// Number of messages encrypted so far
+    send_counter: AtomicU64,
+}
+
+impl CryptoProvider {
+    /// Create a provider for the given shared key
+    pub fn new(key: &[u8; 32], strategy: NonceStrategy) -> Self {
+        let mut nonce_prefix = [0u8; NONCE_LEN - 8];
+        thread_rng().fill_bytes(&mut nonce_prefix);
+
+        Self {
+            cipher: XChaCha20Poly1305::new(key.into()),
+            key: *key,
+            strategy,
+            nonce_prefix,
+            send_counter: AtomicU64::new(0),
+        }
+    }
+
+    /// Get the nonce strategy in use
+    pub fn strategy(&self) -> NonceStrategy {
+        self.strategy
+    }
+
+    /// Get the number of messages encrypted with this provider
+    pub fn messages_sent(&self) -> u64 {
+        self.send_counter.load(Ordering::SeqCst)
+    }
+
+    /// Produce the nonce for the next outgoing message
+    fn next_nonce(&self) -> Result<[u8; NONCE_LEN]> {
+        let counter = self.send_counter.fetch_add(1, Ordering::SeqCst);
+        let mut nonce = [0u8; NONCE_LEN];
+
+        match self.strategy {
+            NonceStrategy::Random => thread_rng().fill_bytes(&mut nonce),
+            NonceStrategy::Counter => {
+                if counter == u64::MAX {
+                    return Err(anyhow!("Nonce counter exhausted, channel must be rekeyed"));
+                }
+                nonce[..NONCE_LEN - 8].copy_from_slice(&self.nonce_prefix);
+                nonce[NONCE_LEN - 8..].copy_from_slice(&counter.to_be_bytes());
+            }
+        }
+
+        Ok(nonce)
+    }
+
+    /// Encrypt a plaintext, returning the nonce followed by the ciphertext
+    ///
+    /// `associated_data` is authenticated but not encrypted; the same bytes
+    /// must be supplied to [`CryptoProvider::decrypt`].
+    pub fn encrypt(&self, plaintext: &[u8], associated_data: &[u8]) -> Result<Vec<u8>> {
+        let nonce = self.next_nonce()?;
+        let payload = Payload {
+            msg: plaintext,
+            aad: associated_data,
+        };
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce.into(), payload)
+            .map_err(|e| anyhow!("Encryption error: {}", e))?;
+
+        let mut packet = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+        packet.extend_from_slice(&nonce);
+        packet.extend_from_slice(&ciphertext);
+
+        Ok(packet)
+    }
+
+    /// Encrypt the end of `packet` in place, in the layout [`CryptoProvider::encrypt`] produces
+    ///
+    /// `packet[offset..offset + NONCE_LEN]` is overwritten with the nonce and
+    /// everything after it is encrypted; the tag is appended.
+    pub fn encrypt_in_place(
+        &self,
+        packet: &mut BytesMut,
+        offset: usize,
+        associated_data: &[u8],
+    ) -> Result<()> {
+        let start = offset + NONCE_LEN;
+        if packet.len() < start {
+            return Err(anyhow!("No room for the nonce"));
+        }
+
+        let nonce = self.next_nonce()?;
+        let tag = self
+            .cipher
+            .encrypt_in_place_detached(&nonce.into(), associated_data, &mut packet[start..])
+            .map_err(|e| anyhow!("Encryption error: {}", e))?;
+        packet[offset..start].copy_from_slice(&nonce);
+        packet.extend_from_slice(&tag);
+
+        Ok(())
+    }
+
+    /// Decrypt a packet produced by [`CryptoProvider::encrypt`]
+    pub fn decrypt(&self, packet: &[u8], associated_data: &[u8]) -> Result<Vec<u8>> {
+        if packet.len() < NONCE_LEN {
+            return Err(anyhow!("Received packet too small"));
+        }
+
+        let (nonce, ciphertext) = packet.split_at(NONCE_LEN);
+        let payload = Payload {
+            msg: ciphertext,
+            aad: associated_data,
+        };
+        self.cipher
+            .decrypt(nonce.into(), payload)
+            .map_err(|e| anyhow!("Decryption error: {}", e))
+    }
+
+    /// Decrypt a packet in place, returning the plaintext within it
+    pub fn decrypt_in_place<'a>(
+        &self,
+        packet: &'a mut [u8],
+        associated_data: &[u8],
+    ) -> Result<&'a [u8]> {
+        if packet.len() < NONCE_LEN + TAG_LEN {
+            return Err(anyhow!("Received packet too small"));
+        }
+
+        let (nonce, body) = packet.split_at_mut(NONCE_LEN);
+        let (ciphertext, tag) = body.split_at_mut(body.len() - TAG_LEN);
+        self.cipher
+            .decrypt_in_place_detached(
+                XNonce::from_slice(nonce),
+                associated_data,
+                ciphertext,
+                (&*tag).into(),
+            )
+            .map_err(|e| anyhow!("Decryption error: {}", e))?;
+
+        Ok(ciphertext)
+    }
+}
+
+/// Per-direction hash ratchets for a channel, replaced at each rekey
+///
+/// Each epoch's ratchets are rooted in a fresh ephemeral Diffie-Hellman
+/// exchange, so once an epoch's keys are dropped, not even the channel's
+/// static keys recover its traffic.
+#[derive(Clone, Serialize, Deserialize)]
+struct Ratchets {
+    /// Epoch outgoing messages are keyed in
+    send_epoch: u32,
+    send: HashRatchet,
+    /// Chain agreed for the next epoch, sent with once the peer is heard using it
+    next_send: Option<HashRatchet>,
+    /// Chains for the latest epochs the peer may send in, so late packets
+    /// from the one before still decrypt
+    receive: Vec<(u32, HashRatchet)>,
+    /// Our half of a rekey we started, until the peer answers
+    pending: Option<PendingRekey>,
+    /// Our half of the last rekey we answered, sent again if the peer asks again
+    answered: Option<(u32, [u8; 32])>,
+    /// When the sending epoch's keys were agreed
+    #[serde(skip, default = "Instant::now")]
+    agreed_at: Instant,
+}
+
+/// Our ephemeral key for a rekey we started
+#[derive(Clone, Serialize, Deserialize)]
+struct PendingRekey {
+    epoch: u32,
+    secret: [u8; 32],
+    public: [u8; 32],
+    #[serde(skip, default = "Instant::now")]
+    sent_at: Instant,
+}
+
+impl Ratchets {
+    /// Epoch 0's ratchets, rooted in the channel's key exchange
+    fn new(shared: &[u8; 32], public_key: &[u8; 32], remote_public_key: &[u8; 32]) -> Self {
+        Self {
+            send_epoch: 0,
+            send: HashRatchet::new(shared, public_key),
+            next_send: None,
+            receive: vec![(0, HashRatchet::new(shared, remote_public_key))],
+            pending: None,
+            answered: None,
+            agreed_at: Instant::now(),
+        }
+    }
+
+    /// Epoch, index and key for the next outgoing message
+    fn send_key(&mut self) -> Result<(u32, u32, [u8; 32])> {
+        let (index, key) = self.send.next_key();
+        let index = u32::try_from(index)
+            .map_err(|_| anyhow!("Ratchet epoch {} is used up", self.send_epoch))?;
+        Ok((self.send_epoch, index, key))
+    }
+
+    /// Key for a message the peer sent in `epoch`, left in the chain until
+    /// [`consume_receive_key`](Self::consume_receive_key) once the packet checks out
+    fn receive_key(&self, epoch: u32, index: u32) -> Result<[u8; 32]> {
+        let (_, ratchet) = self
+            .receive
+            .iter()
+            .find(|(e, _)| *e == epoch)
+            .ok_or_else(|| anyhow!("No keys for ratchet epoch {}", epoch))?;
+        ratchet.peek(index as u64)
+    }
+
+    /// Advances the peer's chain past a message that authenticated
+    fn consume_receive_key(&mut self, epoch: u32, index: u32) {
+        if let Some((_, ratchet)) = self.receive.iter_mut().find(|(e, _)| *e == epoch) {
+            let _ = ratchet.key_for(index as u64);
+        }
+    }
+
+    /// Notes the peer authenticated a packet in `epoch`, so it has the
+    /// epoch's keys and we can send with them too
+    fn heard(&mut self, epoch: u32) {
+        if epoch == self.send_epoch + 1 {
+            if let Some(send) = self.next_send.take() {
+                self.send = send;
+                self.send_epoch = epoch;
+                self.agreed_at = Instant::now();
+            }
+        }
+    }
+
+    /// Starts a rekey for the next epoch, returning our half for the peer
+    fn start_rekey(&mut self) -> Message {
+        let keypair = Keypair::generate();
+        let pending = PendingRekey {
+            epoch: self.send_epoch + 1,
+            secret: keypair.secret.to_bytes(),
+            public: keypair.public.to_bytes(),
+            sent_at: Instant::now(),
+        };
+        let request = Message::Rekey {
+            epoch: pending.epoch,
+            public_key: pending.public,
+        };
+        self.pending = Some(pending);
+        request
+    }
+
+    /// Takes the peer's half of a rekey, returning ours if it is to be sent back
+    fn accept_rekey(&mut self, epoch: u32, remote_public_key: [u8; 32]) -> Option<Message> {
+        // The answer to a rekey we started: both halves are in, and the peer
+        // has them too, so we send with the new keys at once
+        if let Some(pending) = self.pending.take() {
+            if pending.epoch == epoch {
+                self.send = self.agree(epoch, pending.secret, &pending.public, &remote_public_key);
+                self.send_epoch = epoch;
+                self.agreed_at = Instant::now();
+                return None;
+            }
+            self.pending = Some(pending);
+        }
+
+        // Asked again, as our answer went missing
+        if let Some((answered, public_key)) = self.answered {
+            if answered == epoch {
+                return Some(Message::Rekey { epoch, public_key });
+            }
+        }
+
+        if epoch != self.send_epoch + 1 || self.next_send.is_some() {
+            return None;
+        }
+        let keypair = Keypair::generate();
+        let public_key = keypair.public.to_bytes();
+        self.next_send = Some(self.agree(
+            epoch,
+            keypair.secret.to_bytes(),
+            &public_key,
+            &remote_public_key,
+        ));
+        self.answered = Some((epoch, public_key));
+        Some(Message::Rekey { epoch, public_key })
+    }
+
+    /// Roots `epoch`'s ratchets in an ephemeral exchange, returning the sending chain
+    fn agree(
+        &mut self,
+        epoch: u32,
+        secret: [u8; 32],
+        public_key: &[u8; 32],
+        remote_public_key: &[u8; 32],
+    ) -> HashRatchet {
+        let shared =
+            StaticSecret::from(secret).diffie_hellman(&PublicKey::from(*remote_public_key));
+        let root = derive_key(shared.as_bytes(), b"resonance rekey");
+        self.receive.retain(|(e, _)| e + 1 >= epoch);
+        self.receive
+            .push((epoch, HashRatchet::new(&root, remote_public_key)));
+        HashRatchet::new(&root, public_key)
+    }
+}
+
+/// Everything needed to carry on a channel from another machine
+///
+/// Holds the channel's keys, so it must only travel encrypted. Taken with
+/// [`SecureChannel::export_state`] and picked up again with
+/// [`SecureChannel::resume`].
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ChannelState {
+    pub session_id: String,
+    /// Address the peer was last reached at
+    pub remote: SocketAddr,
+    pub remote_public_key: [u8; 32],
+    secret_key: [u8; 32],
+    static_key: [u8; 32],
+    nonce_strategy: NonceStrategy,
+    send_sequence: u64,
+    forward_secrecy: bool,
+    ratchets: Option<Ratchets>,
+    replay_window: ReplayWindow,
+    max_datagram: usize,
+}
+
+/// Associated data bound into an encrypted packet
+struct AssociatedData {
+    bytes: [u8; HEADER_LEN + RATCHET_LEN + 32],
+    len: usize,
+}
+
+impl std::ops::Deref for AssociatedData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+/// Build the associated data bound into every encrypted packet
+///
+/// Covers the cleartext header (message kind and sequence number, and the
+/// key's epoch and index if ratcheted) and the sender's public key, so a
+/// ciphertext cannot be replayed as a different message type, under a
+/// different sequence number, or as coming from the other direction of the
+/// channel.
+fn associated_data(header: &[u8], sender_public_key: &[u8; 32]) -> AssociatedData {
+    let mut bytes = [0u8; HEADER_LEN + RATCHET_LEN + 32];
+    let len = header.len() + 32;
+    bytes[..header.len()].copy_from_slice(header);
+    bytes[header.len()..len].copy_from_slice(sender_public_key);
+    AssociatedData { bytes, len }
+}
+
+// Where bincode numbers the audio variants of `Message`, as their wire tags
+const AUDIO_TAG: u32 = 2;
+const AUDIO_BUNDLE_TAG: u32 = 19;
+
+/// The fields of [`Message::Audio`] as laid out on the wire, borrowing the
+/// payload from the received packet
+#[derive(Deserialize)]
+struct WireAudio<'a> {
+    data: &'a [u8],
+    timestamp: u64,
+    seq: u64,
+    sample_rate: u32,
+    channels: u16,
+    codec: AudioCodec,
+}
+
+/// The fields of [`Message::AudioBundle`] as laid out on the wire
+#[derive(Deserialize)]
+struct WireAudioBundle<'a> {
+    sample_rate: u32,
+    channels: u16,
+    codec: AudioCodec,
+    #[serde(borrow)]
+    frames: Vec<WireFrame<'a>>,
+}
+
+#[derive(Deserialize)]
+struct WireFrame<'a> {
+    data: &'a [u8],
+    timestamp: u64,
+    seq: u64,
+}
+
 /// Session message types
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Message {
@@ -45,8 +518,15 @@ pub enum Message {
     },
     /// Joining a session
     Join { name: String, public_key: [u8; 32] },
-    /// Audio data
-    Audio { data: Vec<u8>, timestamp: u64 },
+    /// Audio data, with `timestamp` being the sender's capture time in microseconds
+    Audio {
+        data: Bytes,
+        timestamp: u64,
+        seq: u64,
+        sample_rate: u32,
+        channels: u16,
+        codec: AudioCodec,
+    },
     /// Position update
     Position { x: f32, y: f32, z: f32 },
     /// Heartbeat to keep connection alive
@@ -61,6 +541,293 @@ pub enum Message {
     NewPeer { peer: crate::app::session::Peer },
     /// Peer left the session (from peer to all)
     PeerLeft { peer_id: String },
+    /// Peer's presence changed (from peer to all)
+    Presence {
+        peer_id: String,
+        status: crate::app::presence::PresenceStatus,
+    },
+    /// Seats for a spatial layout, keyed by peer ID (from host to peers)
+    Layout {
+        layout: crate::audio::SpatialLayout,
+        positions: Vec<(String, (f32, f32, f32))>,
+    },
+    /// The sender's audio has ended; sent after its last frame when leaving
+    StreamEnd,
+    /// Clock probe, also serving as a heartbeat (times in microseconds)
+    Ping { sent_at: u64 },
+    /// Reply to a ping with our receive and send times
+    Pong {
+        ping_sent_at: u64,
+        received_at: u64,
+        sent_at: u64,
+    },
+    /// Sent from a new local address so the peer moves the connection there
+    PathProbe { nonce: u64, sent_at: u64 },
+    /// The peer now sends to the address the probe came from
+    PathConfirm { nonce: u64 },
+    /// Part `index` of `count` of a message too large for one datagram
+    Fragment {
+        id: u32,
+        index: u16,
+        count: u16,
+        data: Vec<u8>,
+    },
+    /// Padded probe for the largest datagram the path carries
+    MtuProbe { padding: Vec<u8> },
+    /// A probe arrived, in a datagram of `size` bytes
+    MtuAck { size: u32 },
+    /// Consecutive audio frames packed into one datagram, oldest first
+    AudioBundle {
+        sample_rate: u32,
+        channels: u16,
+        codec: AudioCodec,
+        frames: Vec<BundledFrame>,
+    },
+    /// Optional features the sender supports, sent once connected
+    Capabilities { capabilities: PeerCapabilities },
+    /// The sender is silent; play comfort noise like this in `frame_len` sample frames
+    ComfortNoise {
+        sample_rate: u32,
+        channels: u16,
+        frame_len: u32,
+        noise: NoiseDescriptor,
+    },
+    /// Everyone but the host is muted, or may speak again (from host to peers)
+    MeetingMode { enabled: bool },
+    /// A muted peer asks to speak, or changed their mind (from peer to all)
+    HandRaised { peer_id: String, raised: bool },
+    /// The host lets a muted peer speak (from host to peers)
+    SpeakGranted { peer_id: String },
+    /// Most the sender wants to receive from us, in kilobits per second; 0 for no limit
+    BandwidthLimit { kbps: u32 },
+    /// A peer started or stopped recording the room (from peer to all, or
+    /// relayed by the host to peers joining)
+    RecordingStateChanged { peer_id: String, recording: bool },
+    /// The host heard a peer started recording (from host to that peer)
+    RecordingAcknowledged { peer_id: String },
+    /// Whether recordings wait for the host to acknowledge them (from host to peers)
+    RecordingPolicy { consent: bool },
+    /// How long after capture everyone plays the sender's audio, so what it
+    /// shares is heard together; 0 to play it as soon as it arrives
+    SyncedPlayout { peer_id: String, delay_ms: u32 },
+    /// The sender's ephemeral key for ratchet epoch `epoch`, starting a
+    /// rekey or answering one
+    Rekey { epoch: u32, public_key: [u8; 32] },
+    /// Asks to be let into the room, once connected (from peer to host)
+    JoinRequest { peer_id: String, name: String },
+    /// The host's answer to a join request (from host to the peer asking)
+    JoinReply {
+        reply: crate::app::admission::JoinReply,
+    },
+}
+
+impl Message {
+    /// Wire tag identifying the message type in the packet header
+    pub fn kind(&self) -> u8 {
+        match self {
+            Message::Handshake { .. } => 0,
+            Message::Join { .. } => 1,
+            Message::Audio { .. } => 2,
+            Message::Position { .. } => 3,
+            Message::Heartbeat => 4,
+            Message::Error { .. } => 5,
+            Message::PeerList { .. } => 6,
+            Message::NewPeer { .. } => 7,
+            Message::PeerLeft { .. } => 8,
+            Message::Presence { .. } => 9,
+            Message::Ping { .. } => 10,
+            Message::Pong { .. } => 11,
+            Message::Layout { .. } => 12,
+            Message::StreamEnd => 13,
+            Message::PathProbe { .. } => 14,
+            Message::PathConfirm { .. } => 15,
+            Message::Fragment { .. } => 16,
+            Message::MtuProbe { .. } => 17,
+            Message::MtuAck { .. } => 18,
+            Message::AudioBundle { .. } => 19,
+            Message::Capabilities { .. } => 20,
+            Message::ComfortNoise { .. } => 21,
+            Message::MeetingMode { .. } => 22,
+            Message::HandRaised { .. } => 23,
+            Message::SpeakGranted { .. } => 24,
+            Message::BandwidthLimit { .. } => 25,
+            Message::RecordingStateChanged { .. } => 26,
+            Message::RecordingAcknowledged { .. } => 27,
+            Message::RecordingPolicy { .. } => 28,
+            Message::SyncedPlayout { .. } => 29,
+            Message::Rekey { .. } => 30,
+            Message::JoinRequest { .. } => 31,
+            Message::JoinReply { .. } => 32,
+        }
+    }
+
+    /// How urgently the message should be sent when the uplink is busy
+    pub fn priority(&self) -> SendPriority {
+        match self {
+            Message::Handshake { .. }
+            | Message::Join { .. }
+            | Message::Heartbeat
+            | Message::Ping { .. }
+            | Message::Pong { .. }
+            | Message::PathProbe { .. }
+            | Message::PathConfirm { .. }
+            | Message::Capabilities { .. }
+            | Message::Rekey { .. }
+            | Message::BandwidthLimit { .. }
+            | Message::JoinRequest { .. }
+            | Message::JoinReply { .. }
+            | Message::PeerLeft { .. } => SendPriority::Control,
+            // Queued behind the audio it ends
+            Message::Audio { .. }
+            | Message::AudioBundle { .. }
+            | Message::ComfortNoise { .. }
+            | Message::StreamEnd => SendPriority::Audio,
+            Message::Position { .. }
+            | Message::Error { .. }
+            | Message::PeerList { .. }
+            | Message::NewPeer { .. }
+            | Message::Presence { .. }
+            | Message::Layout { .. }
+            | Message::MeetingMode { .. }
+            | Message::HandRaised { .. }
+            | Message::SpeakGranted { .. }
+            | Message::RecordingStateChanged { .. }
+            | Message::RecordingAcknowledged { .. }
+            | Message::RecordingPolicy { .. }
+            | Message::SyncedPlayout { .. }
+            | Message::MtuProbe { .. }
+            | Message::MtuAck { .. } => SendPriority::Bulk,
+            // Fragments are sent at the priority of the message they're part of
+            Message::Fragment { .. } => SendPriority::Bulk,
+        }
+    }
+
+    /// Appends the message's wire encoding to `buf`
+    pub fn serialize_into(&self, buf: &mut BytesMut) -> Result<()> {
+        bincode::serialize_into(buf.writer(), self)?;
+        Ok(())
+    }
+
+    /// Decodes a message from its wire encoding
+    pub fn deserialize(bytes: &[u8]) -> Result<Self> {
+        Ok(bincode::deserialize(bytes)?)
+    }
+
+    /// Like [`Message::deserialize`], with audio payloads sliced out of
+    /// `bytes` rather than copied
+    pub fn from_bytes(bytes: Bytes) -> Result<Self> {
+        let tag = bytes
+            .get(..4)
+            .map(|tag| u32::from_le_bytes(tag.try_into().unwrap()));
+        match tag {
+            Some(AUDIO_TAG) => {
+                let audio: WireAudio = bincode::deserialize(&bytes[4..])?;
+                Ok(Message::Audio {
+                    data: bytes.slice_ref(audio.data),
+                    timestamp: audio.timestamp,
+                    seq: audio.seq,
+                    sample_rate: audio.sample_rate,
+                    channels: audio.channels,
+                    codec: audio.codec,
+                })
+            }
+            Some(AUDIO_BUNDLE_TAG) => {
+                let bundle: WireAudioBundle = bincode::deserialize(&bytes[4..])?;
+                Ok(Message::AudioBundle {
+                    sample_rate: bundle.sample_rate,
+                    channels: bundle.channels,
+                    codec: bundle.codec,
+                    frames: bundle
+                        .frames
+                        .into_iter()
+                        .map(|frame| BundledFrame {
+                            data: bytes.slice_ref(frame.data),
+                            timestamp: frame.timestamp,
+                            seq: frame.seq,
+                        })
+                        .collect(),
+                })
+            }
+            _ => Self::deserialize(&bytes),
+        }
+    }
+
+    /// Builds an audio message carrying `frame` and its metadata
+    ///
+    /// Samples are sent as 8-bit PCM, which every peer understands.
+    pub fn from_audio_frame(frame: &AudioFrame) -> Self {
+        Self::encode_audio(frame, AudioCodec::Pcm8, &mut AudioEncoder::default())
+    }
+
+    /// Builds an audio message with `frame` encoded by `codec`, or 16-bit PCM
+    /// if the codec can't take the frame
+    pub fn encode_audio(frame: &AudioFrame, codec: AudioCodec, encoder: &mut AudioEncoder) -> Self {
+        let (codec, data) = encoder.encode(codec, frame);
+        Message::Audio {
+            data: data.into(),
+            timestamp: frame.capture_ts,
+            seq: frame.seq,
+            sample_rate: frame.sample_rate,
+            channels: frame.channels,
+            codec,
+        }
+    }
+
+    /// Splits an audio bundle back into one audio message per frame
+    ///
+    /// Any other message is returned as it is.
+    pub fn unbundle(self) -> Vec<Message> {
+        match self {
+            Message::AudioBundle {
+                sample_rate,
+                channels,
+                codec,
+                frames,
+            } => frames
+                .into_iter()
+                .map(|frame| Message::Audio {
+                    data: frame.data,
+                    timestamp: frame.timestamp,
+                    seq: frame.seq,
+                    sample_rate,
+                    channels,
+                    codec,
+                })
+                .collect(),
+            message => vec![message],
+        }
+    }
+
+    /// Returns the frame carried by an audio message, attributed to `peer_id`
+    pub fn to_audio_frame(&self, peer_id: &str) -> Option<AudioFrame> {
+        self.decode_audio(&mut AudioDecoder::new(), peer_id)
+    }
+
+    /// Like [`Message::to_audio_frame`], with a decoder kept for the peer
+    ///
+    /// Opus decodes each frame from the state the last one left, so a
+    /// stream sounds right only through the same decoder.
+    pub fn decode_audio(&self, decoder: &mut AudioDecoder, peer_id: &str) -> Option<AudioFrame> {
+        match self {
+            Message::Audio {
+                data,
+                timestamp,
+                seq,
+                sample_rate,
+                channels,
+                codec,
+            } => {
+                let samples = decoder.decode(*codec, data, *sample_rate, *channels)?;
+                Some(
+                    AudioFrame::new(samples, *sample_rate, *channels)
+                        .with_capture_ts(*timestamp)
+                        .with_seq(*seq)
+                        .with_peer_id(peer_id),
+                )
+            }
+            _ => None,
+        }
+    }
 }
 
 /// Rate limiting configuration
@@ -114,32 +881,93 @@ pub struct SecureChannel {
     remote: SocketAddr,
     /// Encryption key pair
     keypair: Keypair,
-    /// Shared secret (after key exchange)
-    shared_secret: Option<[u8; 32]>,
+    /// AEAD provider (after key exchange)
+    crypto: Option<CryptoProvider>,
+    /// Nonce strategy used when the provider is created
+    nonce_strategy: NonceStrategy,
+    /// Remote public key (after key exchange)
+    remote_public_key: Option<[u8; 32]>,
+    /// Sequence number of the next outgoing encrypted packet
+    send_sequence: AtomicU64,
+    /// Message key ratchets (after key exchange)
+    ratchets: Option<Mutex<Ratchets>>,
+    /// Whether outgoing messages use ratcheted keys
+    forward_secrecy: bool,
     /// Session ID
     pub session_id: String,
     /// Connection state
     state: ConnectionState,
     /// Rate limiter
     rate_limiter: Arc<Mutex<RateLimiter>>,
-    /// Last heartbeat time
-    last_heartbeat: Instant,
+    /// Sequence numbers already received, so replayed packets are dropped
+    replay_window: Mutex<ReplayWindow>,
+    /// Largest datagram sent; bigger messages are fragmented
+    max_datagram: AtomicUsize,
+    /// ID of the next fragmented message
+    next_fragment_id: AtomicU32,
+    /// Fragmented messages from the peer still being received
+    reassembler: Mutex<Reassembler>,
+    /// Paces outgoing datagrams once enabled
+    pacer: Option<SendPacer>,
+    /// Pacing settings, kept so a rebound socket is paced the same way
+    pacing: Option<PacingConfig>,
+    /// Reused for building outgoing packets so sends don't allocate
+    send_buffer: Mutex<BytesMut>,
+    /// Counts bytes sent towards the room's upload, if metered
+    bandwidth: Option<BandwidthMonitor>,
+    /// When the keys were agreed, or the channel was resumed here
+    keyed_at: Option<Instant>,
 }
 
 impl SecureChannel {
     /// Create a new secure channel
     pub async fn new(socket: UdpSocket, remote: SocketAddr) -> Self {
-        let keypair = Keypair::generate();
+        Self::with_keypair(Arc::new(socket), remote, Keypair::generate())
+    }
 
+    /// A channel for a peer whose handshake reached us on a socket shared with others
+    ///
+    /// The peer agreed keys with the public half of `keypair`, which it had
+    /// from our link, so the channel is keyed from its handshake alone and
+    /// nothing needs sending back.
+    pub fn accept(
+        socket: Arc<UdpSocket>,
+        remote: SocketAddr,
+        keypair: Keypair,
+        session_id: String,
+        remote_public_key: [u8; 32],
+        nonce_strategy: NonceStrategy,
+    ) -> Result<Self> {
+        let mut channel = Self::with_keypair(socket, remote, keypair);
+        channel.set_nonce_strategy(nonce_strategy);
+        channel.session_id = session_id;
+        channel.compute_shared_secret(remote_public_key)?;
+        Ok(channel)
+    }
+
+    fn with_keypair(socket: Arc<UdpSocket>, remote: SocketAddr, keypair: Keypair) -> Self {
         Self {
-            socket: Arc::new(socket),
+            socket,
             remote,
             keypair,
-            shared_secret: None,
+            crypto: None,
+            nonce_strategy: NonceStrategy::default(),
+            remote_public_key: None,
+            send_sequence: AtomicU64::new(0),
+            ratchets: None,
+            forward_secrecy: false,
             session_id: uuid::Uuid::new_v4().to_string(),
             state: ConnectionState::Disconnected,
             rate_limiter: Arc::new(Mutex::new(RateLimiter::new(100, 50.0))),
-            last_heartbeat: Instant::now(),
+            replay_window: Mutex::new(ReplayWindow::default()),
+            max_datagram: AtomicUsize::new(MIN_DATAGRAM_SIZE),
+            next_fragment_id: AtomicU32::new(0),
+            reassembler: Mutex::new(Reassembler::new()),
+            pacer: None,
+            pacing: None,
+            send_buffer: Mutex::new(BytesMut::new()),
+            bandwidth: None,
+            keyed_at: None,
         }
     }
 
@@ -153,17 +981,219 @@ impl SecureChannel {
         self.state.clone()
     }
 
+    /// Paces and prioritizes outgoing messages instead of sending them immediately
+    ///
+    /// Must be called from within a tokio runtime.
+    pub fn enable_pacing(&mut self, config: PacingConfig) {
+        self.pacer = Some(SendPacer::new(self.socket.clone(), config.clone()));
+        self.pacing = Some(config);
+    }
+
+    /// Counts every datagram sent towards a room's upload usage
+    pub fn set_bandwidth_monitor(&mut self, monitor: Option<BandwidthMonitor>) {
+        self.bandwidth = monitor;
+    }
+
+    /// Sends from a new socket from now on, keeping the session keys
+    ///
+    /// Used when our local address changes. The peer keeps sending to the
+    /// old address until it accepts a path probe from the new one.
+    pub fn rebind(&mut self, socket: UdpSocket) {
+        self.socket = Arc::new(socket);
+        if let Some(config) = self.pacing.take() {
+            self.enable_pacing(config);
+        }
+    }
+
+    /// Sets the largest datagram sent, usually from path MTU discovery
+    pub fn set_max_datagram(&self, size: usize) {
+        self.max_datagram.store(size, Ordering::Relaxed);
+    }
+
+    /// Largest datagram sent; bigger messages are split into fragments
+    pub fn max_datagram(&self) -> usize {
+        self.max_datagram.load(Ordering::Relaxed)
+    }
+
+    /// Packets the peer has sent on this channel, and how many arrived
+    pub fn packet_counts(&self) -> PacketCounts {
+        self.replay_window.lock().unwrap().packet_counts()
+    }
+
+    /// Sends to the peer at a new address, after it has proven it moved there
+    pub fn set_remote_addr(&mut self, remote: SocketAddr) {
+        self.remote = remote;
+    }
+
+    /// Select the nonce strategy for this channel
+    ///
+    /// Takes effect the next time the shared secret is computed.
+    pub fn set_nonce_strategy(&mut self, strategy: NonceStrategy) {
+        self.nonce_strategy = strategy;
+    }
+
+    /// Get the nonce strategy for this channel
+    pub fn nonce_strategy(&self) -> NonceStrategy {
+        self.nonce_strategy
+    }
+
+    /// Enable or disable per-message key ratcheting for outgoing messages
+    ///
+    /// Ratcheted messages are flagged in the packet header, so the remote
+    /// side follows automatically. Intended for long-running sessions where
+    /// compromise of the current key must not expose earlier traffic; see
+    /// [`SecureChannel::rekey_request`] for how fresh keys are agreed.
+    pub fn set_forward_secrecy(&mut self, enabled: bool) {
+        self.forward_secrecy = enabled;
+    }
+
+    /// Check whether outgoing messages use ratcheted keys
+    pub fn forward_secrecy(&self) -> bool {
+        self.forward_secrecy
+    }
+
+    /// Ratchet epoch outgoing messages are keyed in, once keys are agreed
+    pub fn ratchet_epoch(&self) -> Option<u32> {
+        self.ratchets
+            .as_ref()
+            .map(|ratchets| ratchets.lock().unwrap().send_epoch)
+    }
+
+    /// A rekey to send the peer, if one is due
+    ///
+    /// While forward secrecy is on, the side with the lower public key starts
+    /// a rekey every [`REKEY_INTERVAL`], so both never start one at once, and
+    /// asks again until the peer answers.
+    pub fn rekey_request(&self) -> Option<Message> {
+        let remote_public_key = self.remote_public_key?;
+        if !self.forward_secrecy || self.public_key() > remote_public_key {
+            return None;
+        }
+        let mut ratchets = self.ratchets.as_ref()?.lock().unwrap();
+        let due = ratchets.agreed_at.elapsed() >= REKEY_INTERVAL;
+        match &mut ratchets.pending {
+            Some(pending) if pending.sent_at.elapsed() >= REKEY_RETRY => {
+                pending.sent_at = Instant::now();
+                Some(Message::Rekey {
+                    epoch: pending.epoch,
+                    public_key: pending.public,
+                })
+            }
+            Some(_) => None,
+            None if due => Some(ratchets.start_rekey()),
+            None => None,
+        }
+    }
+
+    /// Takes the peer's half of a rekey, returning ours if it must be sent back
+    pub fn accept_rekey(&self, epoch: u32, public_key: [u8; 32]) -> Option<Message> {
+        self.ratchets
+            .as_ref()?
+            .lock()
+            .unwrap()
+            .accept_rekey(epoch, public_key)
+    }
+
+    /// Captures the keys and counters needed to carry on the channel elsewhere
+    ///
+    /// Nothing may be sent on this channel afterwards: the resumed channel
+    /// carries on from the same sequence numbers, and the peer would drop
+    /// its packets as replays.
+    pub fn export_state(&self) -> Result<ChannelState> {
+        let crypto = self
+            .crypto
+            .as_ref()
+            .ok_or_else(|| anyhow!("No keys to hand off before the handshake"))?;
+        let remote_public_key = self
+            .remote_public_key
+            .ok_or_else(|| anyhow!("No keys to hand off before the handshake"))?;
+
+        Ok(ChannelState {
+            session_id: self.session_id.clone(),
+            remote: self.remote,
+            remote_public_key,
+            secret_key: self.keypair.secret.to_bytes(),
+            static_key: crypto.key,
+            nonce_strategy: crypto.strategy,
+            send_sequence: self.send_sequence.load(Ordering::SeqCst),
+            forward_secrecy: self.forward_secrecy,
+            ratchets: self
+                .ratchets
+                .as_ref()
+                .map(|ratchets| ratchets.lock().unwrap().clone()),
+            replay_window: self.replay_window.lock().unwrap().clone(),
+            max_datagram: self.max_datagram(),
+        })
+    }
+
+    /// Carries on a channel exported with [`SecureChannel::export_state`], sending from `socket`
+    ///
+    /// No handshake is needed; the peer moves over once it accepts a path
+    /// probe from the new socket, as it would if our address had changed.
+    pub fn resume(socket: UdpSocket, state: ChannelState) -> Self {
+        let secret = StaticSecret::from(state.secret_key);
+        let keypair = Keypair {
+            public: PublicKey::from(&secret),
+            secret,
+        };
+
+        Self {
+            socket: Arc::new(socket),
+            remote: state.remote,
+            keypair,
+            crypto: Some(CryptoProvider::new(&state.static_key, state.nonce_strategy)),
+            nonce_strategy: state.nonce_strategy,
+            remote_public_key: Some(state.remote_public_key),
+            send_sequence: AtomicU64::new(state.send_sequence),
+            ratchets: state.ratchets.map(Mutex::new),
+            forward_secrecy: state.forward_secrecy,
+            session_id: state.session_id,
+            state: ConnectionState::Connected,
+            rate_limiter: Arc::new(Mutex::new(RateLimiter::new(100, 50.0))),
+            replay_window: Mutex::new(state.replay_window),
+            max_datagram: AtomicUsize::new(state.max_datagram),
+            next_fragment_id: AtomicU32::new(0),
+            reassembler: Mutex::new(Reassembler::new()),
+            pacer: None,
+            pacing: None,
+            send_buffer: Mutex::new(BytesMut::new()),
+            bandwidth: None,
+            keyed_at: Some(Instant::now()),
+        }
+    }
+
     /// Compute shared secret with remote public key
+    ///
+    /// The raw DH output is only used to derive independent keys for the
+    /// static cipher and for each ratchet direction, and is then dropped.
     pub fn compute_shared_secret(&mut self, remote_public_key: [u8; 32]) -> Result<()> {
         let public_key = PublicKey::from(remote_public_key);
         let shared = self.keypair.dh(&public_key);
 
-        self.shared_secret = Some(shared);
+        let static_key = derive_key(&shared, b"resonance static");
+        self.crypto = Some(CryptoProvider::new(&static_key, self.nonce_strategy));
+        self.ratchets = Some(Mutex::new(Ratchets::new(
+            &shared,
+            &self.public_key(),
+            &remote_public_key,
+        )));
+        self.remote_public_key = Some(remote_public_key);
+        self.keyed_at = Some(Instant::now());
         self.state = ConnectionState::Connected;
 
         Ok(())
     }
 
+    /// AEAD the channel encrypts with, once the keys are agreed
+    pub fn cipher(&self) -> Option<&'static str> {
+        self.crypto.as_ref().map(|_| CIPHER)
+    }
+
+    /// Time since the keys were agreed, or since the channel was resumed here
+    pub fn key_age(&self) -> Option<Duration> {
+        self.keyed_at.map(|at| at.elapsed())
+    }
+
     /// Perform key exchange with remote peer
     pub async fn perform_key_exchange(
         &mut self,
@@ -228,8 +1258,8 @@ impl SecureChannel {
     /// Validate a packet before processing
     fn validate_packet(&self, packet: &[u8]) -> Result<()> {
         // Check packet minimum size
-        if packet.len() < 24 + 16 {
-            // nonce + minimum AEAD tag
+        if packet.len() < HEADER_LEN + NONCE_LEN + TAG_LEN {
+            // header + nonce + minimum AEAD tag
             return Err(anyhow!("Packet too small"));
         }
 
@@ -244,43 +1274,139 @@ impl SecureChannel {
 
     /// Send a message to the remote peer
     pub async fn send(&self, message: &Message) -> Result<()> {
-        // Serialize message
-        let message_data = bincode::serialize(message)?;
+        self.send_to(self.remote, message).await
+    }
 
+    /// Send a message to another of the peer's addresses, such as for connectivity checks
+    ///
+    /// Messages too large for one datagram are sent as fragments. MTU
+    /// probes never are, as their size is the point.
+    pub async fn send_to(&self, addr: SocketAddr, message: &Message) -> Result<()> {
         // Check if secure channel is established
-        if let Some(shared_secret) = self.shared_secret {
-            // Generate random nonce
-            let mut nonce = [0u8; 24];
-            thread_rng().fill_bytes(&mut nonce);
-
-            // Encrypt data
-            let cipher = XChaCha20Poly1305::new((&shared_secret).into());
-            let ciphertext = cipher
-                .encrypt(&nonce.into(), message_data.as_ref())
-                .map_err(|e| anyhow!("Encryption error: {}", e))?;
-
-            // Combine nonce and ciphertext
-            let mut packet = Vec::with_capacity(nonce.len() + ciphertext.len());
-            packet.extend_from_slice(&nonce);
-            packet.extend_from_slice(&ciphertext);
-
-            // Send encrypted data
-            self.socket.send_to(&packet, self.remote).await?;
-
-            Ok(())
-        } else {
-            Err(anyhow!("Secure channel not established"))
+        let Some(crypto) = &self.crypto else {
+            return Err(anyhow!("Secure channel not established"));
+        };
+
+        let max_datagram = self.max_datagram();
+        let size = PACKET_OVERHEAD + bincode::serialized_size(message)? as usize;
+        if size <= max_datagram || matches!(message, Message::MtuProbe { .. }) {
+            let packet = self.build_packet(crypto, message)?;
+            return self.transmit(addr, message.priority(), packet).await;
         }
+
+        let bytes = bincode::serialize(message)?;
+        let chunk = max_datagram
+            .saturating_sub(PACKET_OVERHEAD + FRAGMENT_OVERHEAD)
+            .max(1);
+        let count = bytes.len().div_ceil(chunk);
+        if count > MAX_FRAGMENTS {
+            return Err(anyhow!(
+                "Message of {} bytes is too large to send",
+                bytes.len()
+            ));
+        }
+
+        let id = self.next_fragment_id.fetch_add(1, Ordering::Relaxed);
+        for (index, data) in bytes.chunks(chunk).enumerate() {
+            let fragment = Message::Fragment {
+                id,
+                index: index as u16,
+                count: count as u16,
+                data: data.to_vec(),
+            };
+            let packet = self.build_packet(crypto, &fragment)?;
+            self.transmit(addr, message.priority(), packet).await?;
+        }
+        Ok(())
     }
 
-    /// Send raw audio data
-    pub async fn send_audio(&self, audio_data: &[u8], timestamp: u64) -> Result<()> {
-        let message = Message::Audio {
-            data: audio_data.to_vec(),
-            timestamp,
+    /// Sends an encrypted packet, through the pacer if there is one
+    ///
+    /// While the network is impaired for testing, packets skip the pacer and
+    /// go out when the impairment says, if at all.
+    async fn transmit(
+        &self,
+        addr: SocketAddr,
+        priority: SendPriority,
+        packet: Bytes,
+    ) -> Result<()> {
+        if let Some(monitor) = &self.bandwidth {
+            monitor.record_sent(packet.len());
+        }
+        if let Some(delays) = impair(packet.len()) {
+            for delay in delays {
+                let socket = self.socket.clone();
+                let packet = packet.clone();
+                tokio::spawn(async move {
+                    tokio::time::sleep(delay).await;
+                    let _ = socket.send_to(&packet, addr).await;
+                });
+            }
+            return Ok(());
+        }
+        match &self.pacer {
+            Some(pacer) => pacer.send(addr, priority, packet)?,
+            None => {
+                self.socket.send_to(&packet, addr).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Serialize and encrypt a message into the reusable send buffer
+    ///
+    /// The buffer's allocation is reclaimed on the next send once the
+    /// returned packet has been dropped.
+    fn build_packet(&self, crypto: &CryptoProvider, message: &Message) -> Result<Bytes> {
+        let mut buffer = self.send_buffer.lock().unwrap();
+        buffer.clear();
+        buffer.reserve(HEADER_LEN + NONCE_LEN + TAG_LEN + 64);
+
+        // MTU probes are sized to the byte, and carry nothing worth ratcheting
+        let ratchet = match &self.ratchets {
+            Some(ratchets)
+                if self.forward_secrecy && !matches!(message, Message::MtuProbe { .. }) =>
+            {
+                Some(ratchets.lock().unwrap().send_key()?)
+            }
+            _ => None,
         };
 
-        self.send(&message).await
+        // Cleartext header: message kind followed by sequence number, then
+        // the epoch and index of a ratcheted key
+        let sequence = self.send_sequence.fetch_add(1, Ordering::SeqCst);
+        match ratchet {
+            Some((epoch, index, _)) => {
+                buffer.put_u8(message.kind() | RATCHET_FLAG);
+                buffer.put_u64(sequence);
+                buffer.put_u32(epoch);
+                buffer.put_u32(index);
+            }
+            None => {
+                buffer.put_u8(message.kind());
+                buffer.put_u64(sequence);
+            }
+        }
+        let header_len = buffer.len();
+        let aad = associated_data(&buffer, &self.public_key());
+
+        // Room for the nonce, then the message to be encrypted in place
+        buffer.put_bytes(0, NONCE_LEN);
+        message.serialize_into(&mut buffer)?;
+
+        // Encrypt data, binding the header and our identity
+        match ratchet {
+            Some((_, _, key)) => CryptoProvider::new(&key, NonceStrategy::Random)
+                .encrypt_in_place(&mut buffer, header_len, &aad)?,
+            None => crypto.encrypt_in_place(&mut buffer, header_len, &aad)?,
+        }
+
+        Ok(buffer.split().freeze())
+    }
+
+    /// Send an audio frame
+    pub async fn send_audio(&self, frame: &AudioFrame) -> Result<()> {
+        self.send(&Message::from_audio_frame(frame)).await
     }
 
     /// Send heartbeat to keep connection alive
@@ -288,44 +1414,147 @@ impl SecureChannel {
         self.send(&Message::Heartbeat).await
     }
 
+    /// Send a bare datagram that only refreshes NAT bindings along the path
+    pub async fn send_keepalive(&self) -> Result<()> {
+        match &self.pacer {
+            Some(pacer) => pacer.send(
+                self.remote,
+                SendPriority::Control,
+                Bytes::from_static(&KEEPALIVE_DATAGRAM),
+            )?,
+            None => {
+                self.socket
+                    .send_to(&KEEPALIVE_DATAGRAM, self.remote)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Send a clock probe stamped with the current time
+    pub async fn send_ping(&self) -> Result<()> {
+        self.send(&Message::Ping {
+            sent_at: now_micros(),
+        })
+        .await
+    }
+
     /// Receive a message from the remote peer
     pub async fn receive(&self) -> Result<Message> {
-        let mut buf = [0u8; 65536]; // Large buffer for audio data
+        let mut buf = BytesMut::with_capacity(65536); // Large buffer for audio data
 
-        let (size, addr) = self.socket.recv_from(&mut buf).await?;
+        let (_, addr) = self.socket.recv_buf_from(&mut buf).await?;
 
         // Verify sender
         if addr != self.remote {
             return Err(anyhow!("Received packet from unexpected address"));
         }
 
+        self.decode_packet(buf)
+    }
+
+    /// Validate, decrypt and deserialize a packet received from the remote peer
+    ///
+    /// The packet is decrypted in place, and audio payloads are slices of it
+    /// rather than copies. Packets rejected by the cryptography fail with a
+    /// [`CryptoFailure`].
+    pub fn decode_packet(&self, mut packet: BytesMut) -> Result<Message> {
         // Validate packet
-        self.validate_packet(&buf[..size])?;
+        self.validate_packet(&packet)?;
 
         // Check if secure channel is established
-        if let Some(shared_secret) = self.shared_secret {
-            if size < 24 {
-                return Err(anyhow!("Received packet too small"));
+        if let Some(crypto) = &self.crypto {
+            let remote_public_key = self
+                .remote_public_key
+                .ok_or_else(|| anyhow!("Remote public key unknown"))?;
+
+            let ratcheted = packet[0] & RATCHET_FLAG != 0;
+            let header_len = if ratcheted {
+                HEADER_LEN + RATCHET_LEN
+            } else {
+                HEADER_LEN
+            };
+            if packet.len() < header_len + NONCE_LEN + TAG_LEN {
+                return Err(anyhow!("Packet too small"));
             }
 
-            // Extract nonce and ciphertext
-            let nonce = &buf[..24];
-            let ciphertext = &buf[24..size];
+            let kind = packet[0] & !RATCHET_FLAG;
+            let (header, body) = packet.split_at_mut(header_len);
+            let field = |at: usize, len: usize| {
+                let mut bytes = [0u8; 8];
+                bytes[8 - len..].copy_from_slice(&header[at..at + len]);
+                u64::from_be_bytes(bytes)
+            };
+            let sequence = field(1, 8);
+            if self.replay_window.lock().unwrap().is_replay(sequence) {
+                return Err(CryptoFailure::Replay.into());
+            }
 
-            // Decrypt data
-            let cipher = XChaCha20Poly1305::new((&shared_secret).into());
-            let plaintext = cipher
-                .decrypt(nonce.into(), ciphertext)
-                .map_err(|e| anyhow!("Decryption error: {}", e))?;
+            // Decrypt data, verifying the header and the sender identity
+            let aad = associated_data(header, &remote_public_key);
+            let epoch = ratcheted.then(|| field(HEADER_LEN, 4) as u32);
+            let plaintext = match (epoch, &self.ratchets) {
+                (Some(epoch), Some(ratchets)) => {
+                    // The chain only moves on once the packet proves genuine,
+                    // so forged indices can't use up or evict real keys
+                    let index = field(HEADER_LEN + 4, 4) as u32;
+                    let mut ratchets = ratchets.lock().unwrap();
+                    let key = ratchets
+                        .receive_key(epoch, index)
+                        .map_err(|_| CryptoFailure::Authentication)?;
+                    let plaintext = CryptoProvider::new(&key, NonceStrategy::Random)
+                        .decrypt_in_place(body, &aad);
+                    if plaintext.is_ok() {
+                        ratchets.consume_receive_key(epoch, index);
+                    }
+                    plaintext
+                }
+                (Some(_), None) => Err(anyhow!("No ratchets before the key exchange")),
+                (None, _) => crypto.decrypt_in_place(body, &aad),
+            }
+            .map_err(|_| CryptoFailure::Authentication)?;
+
+            // Only authenticated sequence numbers count, so forged ones can't block real packets
+            self.replay_window.lock().unwrap().mark(sequence);
+            if let (Some(epoch), Some(ratchets)) = (epoch, &self.ratchets) {
+                ratchets.lock().unwrap().heard(epoch);
+            }
 
-            // Deserialize message
-            let message = bincode::deserialize(&plaintext)?;
+            // Deserialize message; the plaintext follows the nonce
+            let plaintext = header_len + NONCE_LEN..header_len + NONCE_LEN + plaintext.len();
+            let message = Message::from_bytes(packet.freeze().slice(plaintext))
+                .map_err(|_| CryptoFailure::Malformed)?;
+
+            // The authenticated header must describe the enclosed message
+            if message.kind() != kind {
+                return Err(CryptoFailure::Malformed.into());
+            }
+
+            // The last fragment of a message stands in for the whole message
+            if let Message::Fragment {
+                id,
+                index,
+                count,
+                data,
+            } = &message
+            {
+                let whole = self.reassembler.lock().unwrap().push(
+                    *id,
+                    *index,
+                    *count,
+                    data,
+                    Instant::now(),
+                );
+                if let Some(whole) = whole {
+                    return Message::from_bytes(whole.into())
+                        .map_err(|_| CryptoFailure::Malformed.into());
+                }
+            }
 
             Ok(message)
         } else {
             // During initial handshake, messages are not encrypted
-            let message = bincode::deserialize(&buf[..size])?;
-            Ok(message)
+            Message::from_bytes(packet.freeze())
         }
     }
 
@@ -343,12 +1572,21 @@ impl SecureChannel {
                             // Just acknowledge heartbeat received
                             continue;
                         }
+                        Message::Ping { sent_at } => {
+                            // Answer clock probes so the peer can estimate its offset
+                            self.send(&Message::Pong {
+                                ping_sent_at: *sent_at,
+                                received_at: now_micros(),
+                                sent_at: now_micros(),
+                            })
+                            .await?;
+                        }
                         _ => handler(message)?,
                     }
                 }
                 Err(e) => {
                     // Some errors are expected (timeouts, etc)
-                    eprintln!("Error receiving message: {}", e);
+                    log::warn!("Error receiving message: {}", e);
                     tokio::time::sleep(Duration::from_millis(100)).await;
                 }
             }
@@ -419,6 +1657,525 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_audio_is_sliced_from_the_packet() {
+        let audio = Message::Audio {
+            data: Bytes::from_static(&[1, 2, 3, 4]),
+            timestamp: 5,
+            seq: 6,
+            sample_rate: 48000,
+            channels: 2,
+            codec: AudioCodec::Pcm,
+        };
+        let bundle = Message::AudioBundle {
+            sample_rate: 48000,
+            channels: 1,
+            codec: AudioCodec::Pcm8,
+            frames: vec![
+                BundledFrame {
+                    data: Bytes::from_static(&[7, 8]),
+                    timestamp: 1,
+                    seq: 2,
+                },
+                BundledFrame {
+                    data: Bytes::from_static(&[9]),
+                    timestamp: 3,
+                    seq: 4,
+                },
+            ],
+        };
+
+        let within = |data: &Bytes, packet: &Bytes| {
+            let range = packet.as_ptr_range();
+            range.contains(&data.as_ptr()) && data.as_ptr_range().end <= range.end
+        };
+        for message in [audio, bundle] {
+            let packet = Bytes::from(bincode::serialize(&message).unwrap());
+            let decoded = Message::from_bytes(packet.clone()).unwrap();
+            assert_eq!(
+                bincode::serialize(&decoded).unwrap(),
+                bincode::serialize(&message).unwrap()
+            );
+            match decoded {
+                Message::Audio { data, .. } => assert!(within(&data, &packet)),
+                Message::AudioBundle { frames, .. } => {
+                    assert!(frames.iter().all(|frame| within(&frame.data, &packet)))
+                }
+                _ => panic!("Wrong message type after deserialization"),
+            }
+        }
+
+        // Anything else decodes as usual
+        let heartbeat = Bytes::from(bincode::serialize(&Message::Heartbeat).unwrap());
+        assert!(matches!(
+            Message::from_bytes(heartbeat).unwrap(),
+            Message::Heartbeat
+        ));
+    }
+
+    #[test]
+    fn test_crypto_provider_round_trip() {
+        let key = [7u8; 32];
+
+        for strategy in [NonceStrategy::Random, NonceStrategy::Counter] {
+            let sender = CryptoProvider::new(&key, strategy);
+            let receiver = CryptoProvider::new(&key, strategy);
+
+            let packet = sender.encrypt(b"spatial audio", b"aad").unwrap();
+            assert_eq!(receiver.decrypt(&packet, b"aad").unwrap(), b"spatial audio");
+            assert_eq!(sender.messages_sent(), 1);
+        }
+    }
+
+    #[test]
+    fn test_associated_data_is_bound() {
+        let provider = CryptoProvider::new(&[3u8; 32], NonceStrategy::Random);
+        let sender_key = [9u8; 32];
+
+        let audio_header = [Message::Heartbeat.kind(), 0, 0, 0, 0, 0, 0, 0, 5];
+        let packet = provider
+            .encrypt(b"payload", &associated_data(&audio_header, &sender_key))
+            .unwrap();
+
+        // Same header and sender decrypts
+        assert!(provider
+            .decrypt(&packet, &associated_data(&audio_header, &sender_key))
+            .is_ok());
+
+        // A different sequence number is rejected
+        let mut other_header = audio_header;
+        other_header[8] = 6;
+        assert!(provider
+            .decrypt(&packet, &associated_data(&other_header, &sender_key))
+            .is_err());
+
+        // A different sender is rejected
+        assert!(provider
+            .decrypt(&packet, &associated_data(&audio_header, &[8u8; 32]))
+            .is_err());
+    }
+
+    #[test]
+    fn test_in_place_matches_allocating_api() {
+        let key = [5u8; 32];
+        let sender = CryptoProvider::new(&key, NonceStrategy::Random);
+        let receiver = CryptoProvider::new(&key, NonceStrategy::Random);
+        let message = Message::Position {
+            x: 1.0,
+            y: 2.0,
+            z: 3.0,
+        };
+
+        // Header, room for the nonce, then the message encrypted in place
+        let mut buf = BytesMut::new();
+        buf.put_slice(b"hdr");
+        buf.put_bytes(0, NONCE_LEN);
+        message.serialize_into(&mut buf).unwrap();
+        sender.encrypt_in_place(&mut buf, 3, b"aad").unwrap();
+
+        // Readable by both decryption paths
+        let plaintext = receiver.decrypt(&buf[3..], b"aad").unwrap();
+        assert_eq!(plaintext, bincode::serialize(&message).unwrap());
+        let plaintext = receiver.decrypt_in_place(&mut buf[3..], b"aad").unwrap();
+        assert!(matches!(
+            Message::deserialize(plaintext).unwrap(),
+            Message::Position { z, .. } if z == 3.0
+        ));
+    }
+
+    #[test]
+    fn test_counter_nonces_never_repeat() {
+        let provider = CryptoProvider::new(&[1u8; 32], NonceStrategy::Counter);
+
+        let first = provider.encrypt(b"a", &[]).unwrap();
+        let second = provider.encrypt(b"a", &[]).unwrap();
+
+        // Same prefix, incrementing counter
+        assert_eq!(first[..NONCE_LEN - 8], second[..NONCE_LEN - 8]);
+        assert_eq!(first[NONCE_LEN - 8..NONCE_LEN], 0u64.to_be_bytes());
+        assert_eq!(second[NONCE_LEN - 8..NONCE_LEN], 1u64.to_be_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_nonce_strategies_interoperate() {
+        assert_eq!("counter".parse(), Ok(NonceStrategy::Counter));
+        assert_eq!(NonceStrategy::Random.to_string(), "random");
+        assert!("sequential".parse::<NonceStrategy>().is_err());
+
+        let socket_a = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let socket_b = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr_a = socket_a.local_addr().unwrap();
+        let addr_b = socket_b.local_addr().unwrap();
+
+        let mut channel_a = SecureChannel::new(socket_a, addr_b).await;
+        let mut channel_b = SecureChannel::new(socket_b, addr_a).await;
+        channel_a.set_nonce_strategy(NonceStrategy::Counter);
+        let key_a = channel_a.public_key();
+        let key_b = channel_b.public_key();
+        channel_a.compute_shared_secret(key_b).unwrap();
+        channel_b.compute_shared_secret(key_a).unwrap();
+        assert_eq!(
+            channel_a.crypto.as_ref().unwrap().strategy(),
+            NonceStrategy::Counter
+        );
+
+        // Each end picks its own; the nonce travels with the packet
+        channel_a.send(&Message::Heartbeat).await.unwrap();
+        assert!(matches!(
+            channel_b.receive().await.unwrap(),
+            Message::Heartbeat
+        ));
+        channel_b.send(&Message::StreamEnd).await.unwrap();
+        assert!(matches!(
+            channel_a.receive().await.unwrap(),
+            Message::StreamEnd
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_ratcheted_messages_round_trip() {
+        let socket_a = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let socket_b = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr_a = socket_a.local_addr().unwrap();
+        let addr_b = socket_b.local_addr().unwrap();
+
+        let mut channel_a = SecureChannel::new(socket_a, addr_b).await;
+        let mut channel_b = SecureChannel::new(socket_b, addr_a).await;
+        let key_a = channel_a.public_key();
+        let key_b = channel_b.public_key();
+        channel_a.compute_shared_secret(key_b).unwrap();
+        channel_b.compute_shared_secret(key_a).unwrap();
+
+        channel_a.set_forward_secrecy(true);
+
+        for i in 0..3 {
+            channel_a
+                .send(&Message::Position {
+                    x: i as f32,
+                    y: 0.0,
+                    z: 0.0,
+                })
+                .await
+                .unwrap();
+
+            match channel_b.receive().await.unwrap() {
+                Message::Position { x, .. } => assert_eq!(x, i as f32),
+                _ => panic!("Wrong message type after decryption"),
+            }
+        }
+
+        // The receiver follows the sender's choice per packet
+        channel_a.set_forward_secrecy(false);
+        channel_a.send(&Message::Heartbeat).await.unwrap();
+        assert!(matches!(
+            channel_b.receive().await.unwrap(),
+            Message::Heartbeat
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_forged_packets_leave_the_ratchet_alone() {
+        use crate::network::ratchet::MAX_SKIP;
+
+        let socket_a = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let socket_b = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr_a = socket_a.local_addr().unwrap();
+        let addr_b = socket_b.local_addr().unwrap();
+
+        let mut channel_a = SecureChannel::new(socket_a, addr_b).await;
+        let mut channel_b = SecureChannel::new(socket_b, addr_a).await;
+        let key_a = channel_a.public_key();
+        let key_b = channel_b.public_key();
+        channel_a.compute_shared_secret(key_b).unwrap();
+        channel_b.compute_shared_secret(key_a).unwrap();
+        channel_a.set_forward_secrecy(true);
+
+        let crypto = channel_a.crypto.as_ref().unwrap();
+        let packets: Vec<_> = (0..4)
+            .map(|_| {
+                BytesMut::from(&channel_a.build_packet(crypto, &Message::Heartbeat).unwrap()[..])
+            })
+            .collect();
+        let forge = |packet: &BytesMut, index: u32| {
+            let mut forged = packet.clone();
+            forged[HEADER_LEN + 4..HEADER_LEN + 8].copy_from_slice(&index.to_be_bytes());
+            forged
+        };
+
+        // Message 2 arrives first, so the keys for 0 and 1 wait in the cache
+        assert!(channel_b.decode_packet(packets[2].clone()).is_ok());
+
+        // A forged packet as far ahead as the ratchet skips, and one
+        // claiming a cached key, are both turned away...
+        for forged in [
+            forge(&packets[3], 3 + MAX_SKIP as u32),
+            forge(&packets[3], 0),
+        ] {
+            let error = channel_b.decode_packet(forged).unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<CryptoFailure>(),
+                Some(CryptoFailure::Authentication)
+            ));
+        }
+
+        // ...without evicting or using up the keys genuine packets need
+        for packet in [&packets[0], &packets[1], &packets[3]] {
+            assert!(matches!(
+                channel_b.decode_packet(packet.clone()).unwrap(),
+                Message::Heartbeat
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ratchet_counts_its_own_messages() {
+        let socket_a = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let socket_b = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr_a = socket_a.local_addr().unwrap();
+        let addr_b = socket_b.local_addr().unwrap();
+
+        let mut channel_a = SecureChannel::new(socket_a, addr_b).await;
+        let mut channel_b = SecureChannel::new(socket_b, addr_a).await;
+        let key_a = channel_a.public_key();
+        let key_b = channel_b.public_key();
+        channel_a.compute_shared_secret(key_b).unwrap();
+        channel_b.compute_shared_secret(key_a).unwrap();
+
+        // Far more unratcheted packets than the ratchet may skip
+        let crypto = channel_a.crypto.as_ref().unwrap();
+        for _ in 0..300 {
+            channel_a.build_packet(crypto, &Message::Heartbeat).unwrap();
+        }
+
+        channel_a.set_forward_secrecy(true);
+        channel_a.send(&Message::Heartbeat).await.unwrap();
+        assert!(matches!(
+            channel_b.receive().await.unwrap(),
+            Message::Heartbeat
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_rekey_agrees_fresh_keys() {
+        let socket_a = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let socket_b = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr_a = socket_a.local_addr().unwrap();
+        let addr_b = socket_b.local_addr().unwrap();
+
+        let mut channel_a = SecureChannel::new(socket_a, addr_b).await;
+        let mut channel_b = SecureChannel::new(socket_b, addr_a).await;
+        let key_a = channel_a.public_key();
+        let key_b = channel_b.public_key();
+        channel_a.compute_shared_secret(key_b).unwrap();
+        channel_b.compute_shared_secret(key_a).unwrap();
+        channel_a.set_forward_secrecy(true);
+        channel_b.set_forward_secrecy(true);
+
+        // The side with the lower key starts rekeys, once they're due
+        let (starter, other) = if key_a < key_b {
+            (&channel_a, &channel_b)
+        } else {
+            (&channel_b, &channel_a)
+        };
+        assert!(starter.rekey_request().is_none());
+        assert!(other.rekey_request().is_none());
+
+        let request = starter
+            .ratchets
+            .as_ref()
+            .unwrap()
+            .lock()
+            .unwrap()
+            .start_rekey();
+        starter.send(&request).await.unwrap();
+        let Message::Rekey { epoch, public_key } = other.receive().await.unwrap() else {
+            panic!("Expected the rekey");
+        };
+        assert_eq!(epoch, 1);
+        let answer = other.accept_rekey(epoch, public_key).unwrap();
+
+        // Asked again, the answer is the same
+        let (
+            Message::Rekey {
+                public_key: first, ..
+            },
+            Some(Message::Rekey {
+                public_key: again, ..
+            }),
+        ) = (&answer, other.accept_rekey(epoch, public_key))
+        else {
+            panic!("Expected the answer again");
+        };
+        assert_eq!(*first, again);
+
+        // The answer and whatever follows it go out in the old epoch
+        other.send(&answer).await.unwrap();
+        other.send(&Message::Heartbeat).await.unwrap();
+        assert_eq!(other.ratchet_epoch(), Some(0));
+
+        let Message::Rekey { epoch, public_key } = starter.receive().await.unwrap() else {
+            panic!("Expected the answer");
+        };
+        assert!(starter.accept_rekey(epoch, public_key).is_none());
+        assert_eq!(starter.ratchet_epoch(), Some(1));
+
+        // Late packets from the old epoch still decrypt
+        assert!(matches!(
+            starter.receive().await.unwrap(),
+            Message::Heartbeat
+        ));
+
+        // The other side moves over once it hears the new epoch
+        starter.send(&Message::Heartbeat).await.unwrap();
+        assert!(matches!(other.receive().await.unwrap(), Message::Heartbeat));
+        assert_eq!(other.ratchet_epoch(), Some(1));
+        other.send(&Message::StreamEnd).await.unwrap();
+        assert!(matches!(
+            starter.receive().await.unwrap(),
+            Message::StreamEnd
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_rebound_channel_keeps_its_keys() {
+        let socket_a = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let socket_b = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr_a = socket_a.local_addr().unwrap();
+        let addr_b = socket_b.local_addr().unwrap();
+
+        let mut channel_a = SecureChannel::new(socket_a, addr_b).await;
+        let mut channel_b = SecureChannel::new(socket_b, addr_a).await;
+        let key_a = channel_a.public_key();
+        let key_b = channel_b.public_key();
+        channel_a.compute_shared_secret(key_b).unwrap();
+        channel_b.compute_shared_secret(key_a).unwrap();
+
+        let new_socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let new_addr = new_socket.local_addr().unwrap();
+        channel_a.rebind(new_socket);
+        channel_a
+            .send(&Message::PathProbe {
+                nonce: 1,
+                sent_at: 0,
+            })
+            .await
+            .unwrap();
+
+        // The probe comes from an unknown address but still decrypts
+        let mut buf = BytesMut::with_capacity(65536);
+        let (_, from) = channel_b
+            .clone_socket()
+            .recv_buf_from(&mut buf)
+            .await
+            .unwrap();
+        assert_eq!(from, new_addr);
+        assert!(matches!(
+            channel_b.decode_packet(buf).unwrap(),
+            Message::PathProbe { nonce: 1, .. }
+        ));
+
+        channel_b.set_remote_addr(from);
+        channel_b
+            .send(&Message::PathConfirm { nonce: 1 })
+            .await
+            .unwrap();
+        assert!(matches!(
+            channel_a.receive().await.unwrap(),
+            Message::PathConfirm { nonce: 1 }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_resumed_channel_carries_on() {
+        let socket_a = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let socket_b = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr_a = socket_a.local_addr().unwrap();
+        let addr_b = socket_b.local_addr().unwrap();
+
+        let mut channel_a = SecureChannel::new(socket_a, addr_b).await;
+        let mut channel_b = SecureChannel::new(socket_b, addr_a).await;
+        let key_a = channel_a.public_key();
+        let key_b = channel_b.public_key();
+        channel_a.compute_shared_secret(key_b).unwrap();
+        channel_b.compute_shared_secret(key_a).unwrap();
+        channel_a.set_forward_secrecy(true);
+
+        channel_a.send(&Message::Heartbeat).await.unwrap();
+        assert!(matches!(
+            channel_b.receive().await.unwrap(),
+            Message::Heartbeat
+        ));
+
+        // Moved to another socket, as if on another machine
+        let state = channel_a.export_state().unwrap();
+        drop(channel_a);
+        let new_socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let new_addr = new_socket.local_addr().unwrap();
+        let resumed = SecureChannel::resume(new_socket, state);
+        assert_eq!(resumed.public_key(), key_a);
+        resumed
+            .send(&Message::PathProbe {
+                nonce: 2,
+                sent_at: 0,
+            })
+            .await
+            .unwrap();
+
+        // The peer accepts it as the same channel, with later sequence numbers
+        let mut buf = BytesMut::with_capacity(65536);
+        let (_, from) = channel_b
+            .clone_socket()
+            .recv_buf_from(&mut buf)
+            .await
+            .unwrap();
+        assert_eq!(from, new_addr);
+        assert!(matches!(
+            channel_b.decode_packet(buf).unwrap(),
+            Message::PathProbe { nonce: 2, .. }
+        ));
+
+        channel_b.set_remote_addr(new_addr);
+        channel_b.send(&Message::Heartbeat).await.unwrap();
+        assert!(matches!(
+            resumed.receive().await.unwrap(),
+            Message::Heartbeat
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_large_messages_are_fragmented() {
+        let socket_a = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let socket_b = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr_a = socket_a.local_addr().unwrap();
+        let addr_b = socket_b.local_addr().unwrap();
+
+        let mut channel_a = SecureChannel::new(socket_a, addr_b).await;
+        let mut channel_b = SecureChannel::new(socket_b, addr_a).await;
+        let key_a = channel_a.public_key();
+        let key_b = channel_b.public_key();
+        channel_a.compute_shared_secret(key_b).unwrap();
+        channel_b.compute_shared_secret(key_a).unwrap();
+
+        // Probes fill the datagram exactly
+        let crypto = channel_a.crypto.as_ref().unwrap();
+        let probe = channel_a.build_packet(crypto, &crate::network::mtu::mtu_probe(1400));
+        assert_eq!(probe.unwrap().len(), 1400);
+
+        // 100 ms of 48 kHz audio doesn't fit in one datagram
+        let frame = AudioFrame::new(vec![0.5; 4800], 48000, 1);
+        channel_a.send_audio(&frame).await.unwrap();
+
+        let mut fragments = 0;
+        let message = loop {
+            match channel_b.receive().await.unwrap() {
+                Message::Fragment { .. } => fragments += 1,
+                message => break message,
+            }
+        };
+        assert_eq!(fragments, 4);
+        assert_eq!(message.to_audio_frame("a").unwrap().samples.len(), 4800);
+    }
+
     #[test]
     fn test_rate_limiter() {
         let mut limiter = RateLimiter::new(10, 1.0);
@@ -12,6 +12,12 @@ pub struct SecurityModule {
     current_key_pair: Option<KeyPair>,
 }
 
+impl Default for SecurityModule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SecurityModule {
     /// Create a new security module
     pub fn new() -> Self {
@@ -2,7 +2,6 @@ use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
 use uuid::Uuid;
 
 /// Session information returned after creating or joining a session
@@ -101,6 +100,12 @@ impl SignalingInterface for SignalingService {
     }
 }
 
+impl Default for SignalingService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SignalingService {
     /// Create a new signaling service
     pub fn new() -> Self {
@@ -144,7 +149,6 @@ impl SignalingService {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use tokio::time::timeout;
 
     #[tokio::test]
     async fn test_signaling_connection() {
@@ -194,7 +198,7 @@ mod tests {
         let mut signaling = SignalingService::new();
         signaling.connect().await.unwrap();
 
-        let session_info = signaling.create_session().await.unwrap();
+        signaling.create_session().await.unwrap();
         assert!(signaling.current_session_id().is_some());
 
         let result = signaling.disconnect().await;
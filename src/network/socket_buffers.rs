@@ -0,0 +1,155 @@
+use socket2::SockRef;
+use std::fmt;
+use std::io;
+use std::sync::Mutex;
+use tokio::net::UdpSocket;
+
+/// Kernel buffer sizes asked of every UDP socket we bind, in bytes; `None`
+/// leaves the OS default
+///
+/// The defaults are small enough that a burst from several peers at once
+/// overflows the receive buffer, and the kernel drops what doesn't fit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SocketBuffers {
+    pub receive: Option<usize>,
+    pub send: Option<usize>,
+}
+
+impl SocketBuffers {
+    pub const NONE: SocketBuffers = SocketBuffers {
+        receive: None,
+        send: None,
+    };
+
+    /// Sizes given in KiB, as settings hold them
+    pub fn from_kib(receive: Option<u32>, send: Option<u32>) -> Self {
+        let bytes = |kib: u32| kib as usize * 1024;
+        Self {
+            receive: receive.map(bytes),
+            send: send.map(bytes),
+        }
+    }
+}
+
+/// Buffer sizes the OS actually gave a socket, in bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GrantedBuffers {
+    pub receive: usize,
+    pub send: usize,
+}
+
+impl GrantedBuffers {
+    /// Reads back the sizes of `socket`'s buffers
+    pub fn of(socket: &UdpSocket) -> io::Result<Self> {
+        let socket = SockRef::from(socket);
+        Ok(Self {
+            receive: usable(socket.recv_buffer_size()?),
+            send: usable(socket.send_buffer_size()?),
+        })
+    }
+
+    /// Whether the OS gave less than was asked of either buffer
+    pub fn capped(&self, requested: SocketBuffers) -> bool {
+        requested.receive.is_some_and(|size| self.receive < size)
+            || requested.send.is_some_and(|size| self.send < size)
+    }
+}
+
+impl fmt::Display for GrantedBuffers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "receive {} KiB, send {} KiB",
+            self.receive / 1024,
+            self.send / 1024
+        )
+    }
+}
+
+/// Linux reports double the size it was asked for, keeping the other half
+/// for its own bookkeeping
+fn usable(reported: usize) -> usize {
+    if cfg!(target_os = "linux") {
+        reported / 2
+    } else {
+        reported
+    }
+}
+
+static REQUESTED: Mutex<SocketBuffers> = Mutex::new(SocketBuffers::NONE);
+
+// The request last warned about being capped, so each is warned about once
+// rather than for every socket
+static WARNED: Mutex<Option<SocketBuffers>> = Mutex::new(None);
+
+/// Sets the buffer sizes asked of sockets bound from now on
+pub fn set_socket_buffers(buffers: SocketBuffers) {
+    *REQUESTED.lock().unwrap() = buffers;
+}
+
+/// Buffer sizes asked of newly bound sockets
+pub fn socket_buffers() -> SocketBuffers {
+    *REQUESTED.lock().unwrap()
+}
+
+/// Asks for the configured buffer sizes on a newly bound socket, warning if
+/// the OS gives less
+pub(crate) fn apply_socket_buffers(socket: &UdpSocket) {
+    let requested = socket_buffers();
+    if requested == SocketBuffers::NONE {
+        return;
+    }
+    match resize(socket, requested) {
+        Ok(granted) if granted.capped(requested) => {
+            let mut warned = WARNED.lock().unwrap();
+            if *warned != Some(requested) {
+                log::warn!(
+                    "The OS capped the UDP socket buffers at {}; raise its limit \
+                     (net.core.rmem_max and wmem_max on Linux) to avoid drops",
+                    granted
+                );
+                *warned = Some(requested);
+            }
+        }
+        Ok(granted) => log::debug!("UDP socket buffers: {}", granted),
+        Err(e) => log::warn!("Couldn't resize the UDP socket buffers: {}", e),
+    }
+}
+
+fn resize(socket: &UdpSocket, requested: SocketBuffers) -> io::Result<GrantedBuffers> {
+    let sock = SockRef::from(socket);
+    if let Some(size) = requested.receive {
+        sock.set_recv_buffer_size(size)?;
+    }
+    if let Some(size) = requested.send {
+        sock.set_send_buffer_size(size)?;
+    }
+    GrantedBuffers::of(socket)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    #[tokio::test]
+    async fn test_reads_back_granted_sizes() {
+        let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let requested = SocketBuffers::from_kib(Some(64), None);
+        assert_eq!(requested.receive, Some(65536));
+
+        // Well under any OS's limit
+        let granted = resize(&socket, requested).unwrap();
+        assert!(granted.receive >= 65536, "granted {}", granted);
+        assert!(!granted.capped(requested));
+        assert_eq!(GrantedBuffers::of(&socket).unwrap(), granted);
+
+        let capped = GrantedBuffers {
+            receive: 212_992,
+            send: 212_992,
+        };
+        assert!(capped.capped(SocketBuffers::from_kib(Some(4096), None)));
+        assert!(!capped.capped(SocketBuffers::NONE));
+        assert_eq!(capped.to_string(), "receive 208 KiB, send 208 KiB");
+    }
+}
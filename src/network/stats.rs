@@ -0,0 +1,89 @@
+use std::ops::Add;
+use std::time::Duration;
+
+use super::bandwidth::BandwidthUsage;
+use super::peer_table::EvictionStats;
+
+/// How often the room's stats are sampled for the status area
+pub const STATS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Packets a peer has sent us since the channel opened, and how many arrived
+///
+/// Sent is read from the sequence numbers authenticated so far, so
+/// packets lost after the newest one that arrived aren't counted yet.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PacketCounts {
+    pub sent: u64,
+    pub received: u64,
+}
+
+impl PacketCounts {
+    /// Share of the packets sent since `earlier` that never arrived, from 0 to 1
+    pub fn loss_since(&self, earlier: PacketCounts) -> f64 {
+        let sent = self.sent.saturating_sub(earlier.sent);
+        let received = self.received.saturating_sub(earlier.received).min(sent);
+        if sent == 0 {
+            0.0
+        } else {
+            1.0 - received as f64 / sent as f64
+        }
+    }
+}
+
+impl Add for PacketCounts {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            sent: self.sent + other.sent,
+            received: self.received + other.received,
+        }
+    }
+}
+
+/// The room's traffic as a whole, as sampled for the status area
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NetworkStats {
+    pub usage: BandwidthUsage,
+    /// Peers we have a connection to
+    pub peers: usize,
+    /// Packets from all of them, added up
+    pub packets: PacketCounts,
+    /// Mean round trip to the peers that have answered a clock probe
+    pub rtt: Option<Duration>,
+    /// Peers the session's table dropped without them leaving
+    pub evictions: EvictionStats,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::tamper::ReplayWindow;
+
+    #[test]
+    fn test_loss_counts_gaps_in_sequence_numbers() {
+        let mut window = ReplayWindow::default();
+        for sequence in [10, 11, 13, 14] {
+            window.mark(sequence);
+        }
+        let earlier = window.packet_counts();
+        assert_eq!(
+            earlier,
+            PacketCounts {
+                sent: 5,
+                received: 4
+            }
+        );
+
+        // Late arrivals fill their gap rather than counting twice
+        for sequence in [12, 15, 18, 19] {
+            window.mark(sequence);
+        }
+        let now = window.packet_counts();
+        assert_eq!(now.sent, 10);
+        assert_eq!(now.received, 8);
+        assert!((now.loss_since(earlier) - 0.2).abs() < 1e-9);
+        assert_eq!(now.loss_since(now), 0.0);
+        assert_eq!((now + earlier).sent, 15);
+    }
+}
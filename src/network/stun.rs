@@ -0,0 +1,517 @@
+use anyhow::{anyhow, Result};
+use rand::{thread_rng, RngCore};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::time::Instant;
+
+use super::p2p::{local_ipv4, Endpoint};
+
+/// Public STUN servers queried when none are configured
+pub const DEFAULT_STUN_SERVERS: &[&str] = &[
+    "stun1.l.google.com:19302",
+    "stun2.l.google.com:19302",
+    "stun.stunprotocol.org:3478",
+];
+
+const MAGIC_COOKIE: [u8; 4] = [0x21, 0x12, 0xA4, 0x42];
+const BINDING_REQUEST: u16 = 0x0001;
+const BINDING_RESPONSE: u16 = 0x0101;
+const MAPPED_ADDRESS: u16 = 0x0001;
+const XOR_MAPPED_ADDRESS: u16 = 0x0020;
+const OTHER_ADDRESS: u16 = 0x802C;
+
+/// Which STUN servers to ask and how persistently
+#[derive(Debug, Clone)]
+pub struct StunConfig {
+    /// Servers as `host:port`
+    pub servers: Vec<String>,
+    /// Requests sent to each server before giving up on it
+    pub attempts: u32,
+    /// Wait for the first attempt; doubled after each retry
+    pub initial_timeout: Duration,
+}
+
+impl Default for StunConfig {
+    fn default() -> Self {
+        Self {
+            servers: DEFAULT_STUN_SERVERS.iter().map(|s| s.to_string()).collect(),
+            attempts: 3,
+            initial_timeout: Duration::from_millis(500),
+        }
+    }
+}
+
+impl StunConfig {
+    /// Uses these servers instead of the defaults
+    pub fn with_servers(mut self, servers: Vec<String>) -> Self {
+        self.servers = servers;
+        self
+    }
+
+    /// Sets the number of attempts per server and the first timeout
+    pub fn with_retries(mut self, attempts: u32, initial_timeout: Duration) -> Self {
+        self.attempts = attempts.max(1);
+        self.initial_timeout = initial_timeout;
+        self
+    }
+}
+
+/// How a NAT picks the external address for outgoing traffic (RFC 5780 §4.3)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NatMapping {
+    /// The socket is reachable at its own address
+    NoNat,
+    /// One external address regardless of destination; hole punching works
+    EndpointIndependent,
+    /// A new external address for each destination IP
+    AddressDependent,
+    /// A new external address for each destination IP and port
+    AddressAndPortDependent,
+    /// Not enough servers answered to tell
+    Unknown,
+}
+
+/// How to reach peers given our NAT's behaviour
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionStrategy {
+    /// Connect straight to the advertised address
+    Direct,
+    /// The advertised address only works for the STUN server; use a relay
+    Relay,
+}
+
+impl NatMapping {
+    /// Whether peers can use the address STUN reported for us
+    pub fn strategy(&self) -> ConnectionStrategy {
+        match self {
+            NatMapping::AddressDependent | NatMapping::AddressAndPortDependent => {
+                ConnectionStrategy::Relay
+            }
+            // An unknown NAT is worth a direct attempt before anything else
+            NatMapping::NoNat | NatMapping::EndpointIndependent | NatMapping::Unknown => {
+                ConnectionStrategy::Direct
+            }
+        }
+    }
+}
+
+/// What STUN told us about our public address and NAT
+#[derive(Debug, Clone)]
+pub struct NatReport {
+    /// Public address seen by the first server that answered
+    pub mapped: Endpoint,
+    pub mapping: NatMapping,
+}
+
+/// A parsed Binding response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BindingResponse {
+    mapped: SocketAddr,
+    /// Alternate address of the server, if it supports RFC 5780
+    other: Option<SocketAddr>,
+}
+
+/// Asks the configured servers for our public address, returning the first answer
+pub async fn mapped_address(socket: &UdpSocket, config: &StunConfig) -> Result<Endpoint> {
+    let servers = resolve_servers(socket, &config.servers).await;
+    let answers = query_servers(socket, &servers, config, 1).await;
+
+    answers
+        .first()
+        .map(|(_, response)| endpoint(response.mapped))
+        .ok_or_else(|| anyhow!("Failed to discover public endpoint"))
+}
+
+/// Finds our public address and classifies the NAT's mapping behaviour
+///
+/// Uses the RFC 5780 mapping tests when a server reports an alternate
+/// address. Otherwise mappings from different servers are compared; a
+/// mapping that changes between servers is reported as address dependent,
+/// since the two dependent behaviours can't be told apart that way.
+pub async fn detect_nat(socket: &UdpSocket, config: &StunConfig) -> Result<NatReport> {
+    let servers = resolve_servers(socket, &config.servers).await;
+    let answers = query_servers(socket, &servers, config, servers.len()).await;
+    let (primary_server, primary) = *answers
+        .first()
+        .ok_or_else(|| anyhow!("Failed to discover public endpoint"))?;
+
+    let report = |mapping| NatReport {
+        mapped: endpoint(primary.mapped),
+        mapping,
+    };
+
+    // Mapped to our own address means there is no NAT
+    let local = socket.local_addr()?;
+    let local_ip = if local.ip().is_unspecified() {
+        local_ipv4().await.ok().map(IpAddr::V4)
+    } else {
+        Some(local.ip())
+    };
+    if local_ip == Some(primary.mapped.ip()) && local.port() == primary.mapped.port() {
+        return Ok(report(NatMapping::NoNat));
+    }
+
+    if let Some(other) = primary.other {
+        // Test II: alternate IP, primary port
+        let alternate_ip = SocketAddr::new(other.ip(), primary_server.port());
+        if let Some(second) = query_one(socket, alternate_ip, config).await {
+            if second.mapped == primary.mapped {
+                return Ok(report(NatMapping::EndpointIndependent));
+            }
+
+            // Test III: alternate IP and port
+            if let Some(third) = query_one(socket, other, config).await {
+                return Ok(report(if third.mapped == second.mapped {
+                    NatMapping::AddressDependent
+                } else {
+                    NatMapping::AddressAndPortDependent
+                }));
+            }
+        }
+    }
+
+    let others: Vec<_> = answers
+        .iter()
+        .filter(|(server, _)| server.ip() != primary_server.ip())
+        .collect();
+    let mapping = if others.is_empty() {
+        NatMapping::Unknown
+    } else if others
+        .iter()
+        .all(|(_, response)| response.mapped == primary.mapped)
+    {
+        NatMapping::EndpointIndependent
+    } else {
+        NatMapping::AddressDependent
+    };
+
+    Ok(report(mapping))
+}
+
+/// Resolves server names to addresses of the socket's family
+async fn resolve_servers(socket: &UdpSocket, servers: &[String]) -> Vec<SocketAddr> {
+    let ipv4 = socket.local_addr().map(|a| a.is_ipv4()).unwrap_or(true);
+    let mut resolved = Vec::new();
+
+    for server in servers {
+        match tokio::net::lookup_host(server.as_str()).await {
+            Ok(mut addrs) => {
+                if let Some(addr) = addrs.find(|a| a.is_ipv4() == ipv4) {
+                    if !resolved.contains(&addr) {
+                        resolved.push(addr);
+                    }
+                }
+            }
+            Err(e) => log::debug!("Failed to resolve STUN server {}: {}", server, e),
+        }
+    }
+
+    resolved
+}
+
+/// Queries a single server, with retries
+async fn query_one(
+    socket: &UdpSocket,
+    server: SocketAddr,
+    config: &StunConfig,
+) -> Option<BindingResponse> {
+    query_servers(socket, &[server], config, 1)
+        .await
+        .first()
+        .map(|(_, response)| *response)
+}
+
+/// Sends Binding requests to all servers at once, resending to those that
+/// haven't answered with a doubling timeout
+///
+/// Returns answers in arrival order, stopping once `wanted` have arrived.
+async fn query_servers(
+    socket: &UdpSocket,
+    servers: &[SocketAddr],
+    config: &StunConfig,
+    wanted: usize,
+) -> Vec<(SocketAddr, BindingResponse)> {
+    let mut answers: Vec<(SocketAddr, BindingResponse)> = Vec::new();
+    if servers.is_empty() {
+        return answers;
+    }
+
+    // Requests from earlier attempts stay valid, in case their answer is just late
+    let mut requests: HashMap<[u8; 12], SocketAddr> = HashMap::new();
+    let mut timeout = config.initial_timeout;
+    let mut buf = [0u8; 512];
+
+    for _ in 0..config.attempts {
+        let unanswered: Vec<SocketAddr> = servers
+            .iter()
+            .filter(|server| !answers.iter().any(|(answered, _)| answered == *server))
+            .copied()
+            .collect();
+
+        for server in unanswered {
+            let (transaction_id, request) = binding_request();
+            if let Err(e) = socket.send_to(&request, server).await {
+                log::debug!("STUN request to {} failed: {}", server, e);
+                continue;
+            }
+            requests.insert(transaction_id, server);
+        }
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            let size = match tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await {
+                Ok(Ok((size, _))) => size,
+                _ => break,
+            };
+
+            let Some(server) = transaction_id(&buf[..size]).and_then(|id| requests.get(&id)) else {
+                continue;
+            };
+            if answers.iter().any(|(answered, _)| answered == server) {
+                continue;
+            }
+
+            if let Some(response) = parse_binding_response(&buf[..size]) {
+                answers.push((*server, response));
+                if answers.len() >= wanted.min(servers.len()) {
+                    return answers;
+                }
+            }
+        }
+
+        timeout *= 2;
+    }
+
+    answers
+}
+
+fn endpoint(addr: SocketAddr) -> Endpoint {
+    Endpoint {
+        ip: addr.ip(),
+        port: addr.port(),
+    }
+}
+
+/// Creates a Binding request, returning its transaction ID and bytes
+fn binding_request() -> ([u8; 12], Vec<u8>) {
+    let mut transaction_id = [0u8; 12];
+    thread_rng().fill_bytes(&mut transaction_id);
+
+    let mut request = Vec::with_capacity(20);
+    request.extend_from_slice(&BINDING_REQUEST.to_be_bytes());
+    request.extend_from_slice(&[0x00, 0x00]); // No attributes
+    request.extend_from_slice(&MAGIC_COOKIE);
+    request.extend_from_slice(&transaction_id);
+
+    (transaction_id, request)
+}
+
+/// Transaction ID of a STUN message
+fn transaction_id(data: &[u8]) -> Option<[u8; 12]> {
+    if data.len() < 20 || data[4..8] != MAGIC_COOKIE {
+        return None;
+    }
+    data[8..20].try_into().ok()
+}
+
+/// Parses a Binding success response
+fn parse_binding_response(data: &[u8]) -> Option<BindingResponse> {
+    if data.len() < 20 || u16::from_be_bytes([data[0], data[1]]) != BINDING_RESPONSE {
+        return None;
+    }
+
+    let length = u16::from_be_bytes([data[2], data[3]]) as usize;
+    if data.len() < 20 + length {
+        return None;
+    }
+
+    let mut xor_mapped = None;
+    let mut mapped = None;
+    let mut other = None;
+
+    // Attributes start after the 20-byte header
+    let mut pos = 20;
+    while pos + 4 <= 20 + length {
+        let attr_type = u16::from_be_bytes([data[pos], data[pos + 1]]);
+        let attr_length = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        pos += 4;
+
+        if pos + attr_length > data.len() {
+            break;
+        }
+        let value = &data[pos..pos + attr_length];
+
+        match attr_type {
+            XOR_MAPPED_ADDRESS => xor_mapped = parse_address(value, Some(&data[4..20])),
+            MAPPED_ADDRESS => mapped = parse_address(value, None),
+            OTHER_ADDRESS => other = parse_address(value, None),
+            _ => {}
+        }
+
+        // Attributes are padded to 4 bytes
+        pos += (attr_length + 3) & !3;
+    }
+
+    Some(BindingResponse {
+        mapped: xor_mapped.or(mapped)?,
+        other,
+    })
+}
+
+/// Parses an address attribute, undoing the XOR with the magic cookie and
+/// transaction ID (`mask`) for XOR-MAPPED-ADDRESS
+fn parse_address(value: &[u8], mask: Option<&[u8]>) -> Option<SocketAddr> {
+    if value.len() < 4 {
+        return None;
+    }
+
+    let unmask = |i: usize, byte: u8| mask.map_or(byte, |mask| byte ^ mask[i]);
+    let port = u16::from_be_bytes([unmask(0, value[2]), unmask(1, value[3])]);
+
+    let ip = match value[1] {
+        0x01 if value.len() >= 8 => {
+            let mut octets = [0u8; 4];
+            for (i, octet) in octets.iter_mut().enumerate() {
+                *octet = unmask(i, value[4 + i]);
+            }
+            IpAddr::from(octets)
+        }
+        0x02 if value.len() >= 20 => {
+            let mut octets = [0u8; 16];
+            for (i, octet) in octets.iter_mut().enumerate() {
+                *octet = unmask(i, value[4 + i]);
+            }
+            IpAddr::from(octets)
+        }
+        _ => return None,
+    };
+
+    Some(SocketAddr::new(ip, port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a Binding response to `request` with the given attributes
+    fn response(request: &[u8], attributes: &[(u16, Vec<u8>)]) -> Vec<u8> {
+        let body: Vec<u8> = attributes
+            .iter()
+            .flat_map(|(attr_type, value)| {
+                let mut attr = attr_type.to_be_bytes().to_vec();
+                attr.extend_from_slice(&(value.len() as u16).to_be_bytes());
+                attr.extend_from_slice(value);
+                attr
+            })
+            .collect();
+
+        let mut data = BINDING_RESPONSE.to_be_bytes().to_vec();
+        data.extend_from_slice(&(body.len() as u16).to_be_bytes());
+        data.extend_from_slice(&request[4..20]);
+        data.extend_from_slice(&body);
+        data
+    }
+
+    fn xor_address(addr: SocketAddr, header: &[u8]) -> Vec<u8> {
+        let SocketAddr::V4(addr) = addr else {
+            unreachable!()
+        };
+        let mut value = vec![0x00, 0x01];
+        for (i, byte) in addr.port().to_be_bytes().iter().enumerate() {
+            value.push(byte ^ header[4 + i]);
+        }
+        for (i, byte) in addr.ip().octets().iter().enumerate() {
+            value.push(byte ^ header[4 + i]);
+        }
+        value
+    }
+
+    #[test]
+    fn test_stun_binding_request_format() {
+        let (id, request) = binding_request();
+
+        // Verify STUN message header
+        assert_eq!(request[0], 0x00); // Message Type: Binding Request
+        assert_eq!(request[1], 0x01);
+        assert_eq!(request[2], 0x00); // Message Length: 0
+        assert_eq!(request[3], 0x00);
+        assert_eq!(request[4], 0x21); // Magic Cookie
+        assert_eq!(request[5], 0x12);
+        assert_eq!(request[6], 0xA4);
+        assert_eq!(request[7], 0x42);
+
+        // Check that we have a transaction ID (12 bytes)
+        assert_eq!(request.len(), 20);
+        assert_eq!(transaction_id(&request), Some(id));
+    }
+
+    #[test]
+    fn test_parse_response_attributes() {
+        let (_, request) = binding_request();
+        let mapped: SocketAddr = "203.0.113.7:40000".parse().unwrap();
+        let other: SocketAddr = "198.51.100.2:3479".parse().unwrap();
+
+        let mut other_value = vec![0x00, 0x01];
+        other_value.extend_from_slice(&other.port().to_be_bytes());
+        other_value.extend_from_slice(&[198, 51, 100, 2]);
+
+        let data = response(
+            &request,
+            &[
+                (0x8022, b"test".to_vec()), // SOFTWARE, ignored
+                (XOR_MAPPED_ADDRESS, xor_address(mapped, &request)),
+                (OTHER_ADDRESS, other_value),
+            ],
+        );
+
+        let parsed = parse_binding_response(&data).unwrap();
+        assert_eq!(parsed.mapped, mapped);
+        assert_eq!(parsed.other, Some(other));
+    }
+
+    #[test]
+    fn test_rejects_requests_and_short_packets() {
+        let (_, request) = binding_request();
+        assert!(parse_binding_response(&request).is_none());
+        assert!(parse_binding_response(&request[..12]).is_none());
+        assert!(parse_binding_response(&response(&request, &[])).is_none());
+    }
+
+    #[test]
+    fn test_strategy_for_mapping() {
+        assert_eq!(
+            NatMapping::EndpointIndependent.strategy(),
+            ConnectionStrategy::Direct
+        );
+        assert_eq!(
+            NatMapping::AddressAndPortDependent.strategy(),
+            ConnectionStrategy::Relay
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retries_unanswered_server() {
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let client_addr = client.local_addr().unwrap();
+
+        // Ignore the first request, answer the retry
+        tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            server.recv_from(&mut buf).await.unwrap();
+            let (size, from) = server.recv_from(&mut buf).await.unwrap();
+            let data = response(
+                &buf[..size],
+                &[(XOR_MAPPED_ADDRESS, xor_address(from, &buf[..size]))],
+            );
+            server.send_to(&data, from).await.unwrap();
+        });
+
+        let config = StunConfig::default().with_retries(3, Duration::from_millis(50));
+        let answer = query_one(&client, server_addr, &config).await.unwrap();
+        assert_eq!(answer.mapped, client_addr);
+    }
+}
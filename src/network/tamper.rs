@@ -0,0 +1,198 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, VecDeque};
+use std::time::{Duration, Instant};
+
+use super::stats::PacketCounts;
+
+/// Failures within [`TAMPER_WINDOW`] that count as possible tampering
+///
+/// An authenticated channel should see next to no failures from a peer's
+/// own address, so even a handful is suspicious.
+pub const TAMPER_THRESHOLD: usize = 10;
+
+/// Period failures are counted over, and the least time between alerts
+pub const TAMPER_WINDOW: Duration = Duration::from_secs(60);
+
+/// How far behind the newest sequence number packets are still accepted
+///
+/// Wide enough for bulk messages the pacer held back behind audio.
+const REPLAY_WINDOW: u64 = 1024;
+
+/// Why a packet from the peer was rejected by the channel's cryptography
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum CryptoFailure {
+    /// The authentication tag didn't verify, so the packet was forged or corrupted
+    #[error("Packet failed authentication")]
+    Authentication,
+    /// The packet authenticated but its contents didn't make sense
+    #[error("Authenticated packet was malformed")]
+    Malformed,
+    /// The packet's sequence number was already seen, or is too old to tell
+    #[error("Packet was replayed")]
+    Replay,
+}
+
+/// Counts of packets from one peer rejected by the channel's cryptography
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CryptoStats {
+    pub authentication_failures: u64,
+    pub malformed: u64,
+    pub replays: u64,
+}
+
+impl CryptoStats {
+    pub fn total(&self) -> u64 {
+        self.authentication_failures + self.malformed + self.replays
+    }
+}
+
+/// Tracks a peer's rejected packets and decides when they look like tampering
+#[derive(Debug, Default)]
+pub struct TamperMonitor {
+    stats: CryptoStats,
+    recent: VecDeque<Instant>,
+    alerted_at: Option<Instant>,
+}
+
+impl TamperMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn stats(&self) -> CryptoStats {
+        self.stats
+    }
+
+    /// Records a rejected packet, returning true if an alert should be raised
+    ///
+    /// Alerts are raised when more than [`TAMPER_THRESHOLD`] failures land
+    /// within [`TAMPER_WINDOW`], at most once per window.
+    pub fn record(&mut self, failure: CryptoFailure, now: Instant) -> bool {
+        match failure {
+            CryptoFailure::Authentication => self.stats.authentication_failures += 1,
+            CryptoFailure::Malformed => self.stats.malformed += 1,
+            CryptoFailure::Replay => self.stats.replays += 1,
+        }
+
+        self.recent.push_back(now);
+        while self
+            .recent
+            .front()
+            .is_some_and(|&at| now.duration_since(at) > TAMPER_WINDOW)
+        {
+            self.recent.pop_front();
+        }
+
+        let recently_alerted = self
+            .alerted_at
+            .is_some_and(|at| now.duration_since(at) < TAMPER_WINDOW);
+        if self.recent.len() > TAMPER_THRESHOLD && !recently_alerted {
+            self.alerted_at = Some(now);
+            return true;
+        }
+        false
+    }
+}
+
+/// Sequence numbers recently accepted from the peer, for dropping replays
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReplayWindow {
+    highest: Option<u64>,
+    seen: BTreeSet<u64>,
+    // Lowest sequence number accepted and how many have been, for counting loss
+    #[serde(default)]
+    lowest: Option<u64>,
+    #[serde(default)]
+    received: u64,
+}
+
+impl ReplayWindow {
+    /// Whether a packet with `sequence` should be dropped as a replay
+    pub fn is_replay(&self, sequence: u64) -> bool {
+        match self.highest {
+            Some(highest) => {
+                sequence.saturating_add(REPLAY_WINDOW) <= highest || self.seen.contains(&sequence)
+            }
+            None => false,
+        }
+    }
+
+    /// Marks `sequence` as received; only call once the packet has authenticated
+    pub fn mark(&mut self, sequence: u64) {
+        if self.seen.insert(sequence) {
+            self.received += 1;
+        }
+        if self.lowest.is_none_or(|lowest| sequence < lowest) {
+            self.lowest = Some(sequence);
+        }
+        if self.highest.is_none_or(|highest| sequence > highest) {
+            self.highest = Some(sequence);
+            let oldest = sequence.saturating_sub(REPLAY_WINDOW);
+            while self.seen.first().is_some_and(|&first| first < oldest) {
+                self.seen.pop_first();
+            }
+        }
+    }
+
+    /// Packets the peer sent from the lowest sequence number accepted to the
+    /// highest, and how many of them were
+    pub fn packet_counts(&self) -> PacketCounts {
+        match (self.lowest, self.highest) {
+            (Some(lowest), Some(highest)) => PacketCounts {
+                sent: highest - lowest + 1,
+                received: self.received,
+            },
+            _ => PacketCounts::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alerts_once_failures_pass_threshold() {
+        let mut monitor = TamperMonitor::new();
+        let start = Instant::now();
+
+        for i in 0..TAMPER_THRESHOLD {
+            assert!(!monitor.record(
+                CryptoFailure::Authentication,
+                start + Duration::from_millis(i as u64)
+            ));
+        }
+        assert!(monitor.record(CryptoFailure::Replay, start + Duration::from_secs(1)));
+        assert!(!monitor.record(CryptoFailure::Malformed, start + Duration::from_secs(2)));
+
+        let stats = monitor.stats();
+        assert_eq!(stats.authentication_failures, TAMPER_THRESHOLD as u64);
+        assert_eq!((stats.replays, stats.malformed), (1, 1));
+
+        // Failures spread out over time don't add up
+        let mut monitor = TamperMonitor::new();
+        for i in 0..TAMPER_THRESHOLD as u64 * 2 {
+            assert!(!monitor.record(
+                CryptoFailure::Authentication,
+                start + TAMPER_WINDOW / 4 * i as u32
+            ));
+        }
+    }
+
+    #[test]
+    fn test_replay_window() {
+        let mut window = ReplayWindow::default();
+        assert!(!window.is_replay(5));
+        window.mark(5);
+        assert!(window.is_replay(5));
+
+        // Late packets inside the window are still fine, once
+        assert!(!window.is_replay(3));
+        window.mark(3);
+        assert!(window.is_replay(3));
+
+        window.mark(5 + REPLAY_WINDOW);
+        assert!(window.is_replay(4));
+        assert!(!window.is_replay(6));
+    }
+}
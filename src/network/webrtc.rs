@@ -2,12 +2,14 @@ use anyhow::{anyhow, Result};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use uuid;
-use webrtc::api::{media_engine::MediaEngine, APIBuilder, API};
+use webrtc::api::{media_engine::MediaEngine, setting_engine::SettingEngine, APIBuilder};
 use webrtc::ice_transport::ice_server::RTCIceServer;
-use webrtc::peer_connection::peer_connection_state::RTCPeerConnectionState;
 use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
 use webrtc::peer_connection::{configuration::RTCConfiguration, RTCPeerConnection};
 
+use super::ice::{IceServer, IceServerList};
+use super::interfaces::BindAddress;
+
 /// Wrapper around a WebRTC peer connection with additional metadata
 #[derive(Clone)]
 pub struct PeerConnection {
@@ -77,6 +79,8 @@ impl PeerConnection {
 pub struct WebRtcManager {
     api: Option<webrtc::api::API>,
     connections: Arc<Mutex<HashMap<String, PeerConnection>>>,
+    ice_servers: IceServerList,
+    bind: BindAddress,
 }
 
 impl Clone for WebRtcManager {
@@ -85,19 +89,39 @@ impl Clone for WebRtcManager {
         Self {
             api: None,
             connections: Arc::clone(&self.connections),
+            ice_servers: self.ice_servers.clone(),
+            bind: self.bind.clone(),
         }
     }
 }
 
+impl Default for WebRtcManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl WebRtcManager {
     /// Creates a new WebRTC manager
     pub fn new() -> Self {
         Self {
             api: None,
             connections: Arc::new(Mutex::new(HashMap::new())),
+            ice_servers: IceServerList::default(),
+            bind: BindAddress::Auto,
         }
     }
 
+    /// Sets the STUN and TURN servers used by new connections; empty uses a public STUN server
+    pub fn set_ice_servers(&mut self, servers: IceServerList) {
+        self.ice_servers = servers;
+    }
+
+    /// Limits ICE gathering to one interface or address; takes effect on the next `initialize`
+    pub fn set_bind_address(&mut self, bind: BindAddress) {
+        self.bind = bind;
+    }
+
     /// Initializes the WebRTC API
     pub fn initialize(&mut self) -> Result<()> {
         let media_engine = MediaEngine::default();
@@ -105,7 +129,22 @@ impl WebRtcManager {
         // In a real implementation, we would register codecs here
         // media_engine.register_default_codecs()?;
 
-        let api = APIBuilder::new().with_media_engine(media_engine).build();
+        // Only gather candidates where our sockets are bound
+        let mut setting_engine = SettingEngine::default();
+        match self.bind.clone() {
+            BindAddress::Auto => {}
+            BindAddress::Interface(name) => {
+                setting_engine.set_interface_filter(Box::new(move |interface| interface == name))
+            }
+            BindAddress::Address(address) => {
+                setting_engine.set_ip_filter(Box::new(move |ip| ip == address))
+            }
+        }
+
+        let api = APIBuilder::new()
+            .with_media_engine(media_engine)
+            .with_setting_engine(setting_engine)
+            .build();
 
         self.api = Some(api);
         Ok(())
@@ -119,11 +158,34 @@ impl WebRtcManager {
             .ok_or_else(|| anyhow!("WebRTC API not initialized"))?;
 
         // Configure ICE servers (STUN/TURN)
-        let config = RTCConfiguration {
-            ice_servers: vec![RTCIceServer {
+        let ice_servers = if self.ice_servers.is_empty() {
+            vec![RTCIceServer {
                 urls: vec!["stun:stun.l.google.com:19302".to_string()],
                 ..Default::default()
-            }],
+            }]
+        } else {
+            self.ice_servers
+                .iter()
+                .map(|server| match server {
+                    IceServer::Stun { .. } => RTCIceServer {
+                        urls: vec![server.url()],
+                        ..Default::default()
+                    },
+                    IceServer::Turn {
+                        username,
+                        credential,
+                        ..
+                    } => RTCIceServer {
+                        urls: vec![server.url()],
+                        username: username.clone(),
+                        credential: credential.clone(),
+                        ..Default::default()
+                    },
+                })
+                .collect()
+        };
+        let config = RTCConfiguration {
+            ice_servers,
             ..Default::default()
         };
 
@@ -169,9 +231,9 @@ impl WebRtcManager {
 
     /// Closes a specific connection
     pub async fn close_connection(&self, conn_id: &str) -> Result<()> {
-        let mut connections = self.connections.lock().unwrap();
+        let connection = self.connections.lock().unwrap().remove(conn_id);
 
-        if let Some(connection) = connections.remove(conn_id) {
+        if let Some(connection) = connection {
             connection.connection.close().await?;
         }
 
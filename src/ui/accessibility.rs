@@ -0,0 +1,257 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::Span,
+};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::app::config::Config;
+use crate::ui::i18n::t;
+
+/// How long the screen flashes when someone asks to join
+pub const FLASH_DURATION: Duration = Duration::from_millis(1200);
+
+/// The flash is lit, then dark, for this long each
+const FLASH_PERIOD: Duration = Duration::from_millis(200);
+
+/// A contrast ratio between two colours as WCAG measures it, from 1 for
+/// none to 21 for black on white, to a tenth
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ContrastRatio(u16);
+
+impl ContrastRatio {
+    pub fn ratio(self) -> f64 {
+        self.0 as f64 / 10.0
+    }
+}
+
+impl fmt::Display for ContrastRatio {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.0 / 10, self.0 % 10)
+    }
+}
+
+impl FromStr for ContrastRatio {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().parse::<f64>() {
+            Ok(ratio) if (1.0..=21.0).contains(&ratio) => {
+                Ok(ContrastRatio((ratio * 10.0).round() as u16))
+            }
+            _ => Err(format!("Contrast ratio must be from 1 to 21: {}", s)),
+        }
+    }
+}
+
+/// Options that make the interface easier to see
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Accessibility {
+    /// Speaking participants stand out in reverse video, with a label
+    pub large_speaking_indicators: bool,
+    /// The whole screen flashes when someone asks to join
+    pub flash_join_requests: bool,
+    /// Text in the participant list and notifications is brought up to this contrast
+    pub min_contrast: Option<ContrastRatio>,
+}
+
+impl Accessibility {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            large_speaking_indicators: config.large_speaking_indicators,
+            flash_join_requests: config.flash_join_requests,
+            min_contrast: config.min_contrast,
+        }
+    }
+
+    /// Style of a speaking participant's name
+    pub fn speaking_style(&self) -> Style {
+        if self.large_speaking_indicators {
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::LightGreen)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::Green)
+        }
+    }
+
+    /// Label after a speaking participant's name, when indicators are large
+    pub fn speaking_marker(&self) -> Option<Span<'static>> {
+        self.large_speaking_indicators.then(|| {
+            Span::styled(
+                format!(" ◀ {}", t("presence.speaking")),
+                self.speaking_style(),
+            )
+        })
+    }
+
+    /// Raises the text in `area` to the minimum contrast, if there is one
+    pub fn apply_contrast(&self, buf: &mut Buffer, area: Rect) {
+        let Some(min_contrast) = self.min_contrast else {
+            return;
+        };
+        let area = area.intersection(buf.area);
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                let cell = buf.get_mut(x, y);
+                let fg = with_contrast(cell.fg, cell.bg, min_contrast.ratio());
+                cell.set_fg(fg);
+            }
+        }
+    }
+}
+
+/// Whether a flash `elapsed` since it began is lit
+pub fn flash_lit(elapsed: Duration) -> bool {
+    elapsed < FLASH_DURATION && (elapsed.as_millis() / FLASH_PERIOD.as_millis()).is_multiple_of(2)
+}
+
+/// Lights up all of `area`, leaving its text readable
+pub fn flash(buf: &mut Buffer, area: Rect) {
+    buf.set_style(area, Style::default().fg(Color::Black).bg(Color::Yellow));
+}
+
+/// Approximately how a colour looks, as xterm draws the named ones; the
+/// terminal's own default is taken to be light text on black
+fn rgb(color: Color, is_background: bool) -> (u8, u8, u8) {
+    match color {
+        Color::Reset if is_background => (0, 0, 0),
+        Color::Reset => (229, 229, 229),
+        Color::Black => (0, 0, 0),
+        Color::Red => (205, 0, 0),
+        Color::Green => (0, 205, 0),
+        Color::Yellow => (205, 205, 0),
+        Color::Blue => (0, 0, 238),
+        Color::Magenta => (205, 0, 205),
+        Color::Cyan => (0, 205, 205),
+        Color::Gray => (229, 229, 229),
+        Color::DarkGray => (127, 127, 127),
+        Color::LightRed => (255, 0, 0),
+        Color::LightGreen => (0, 255, 0),
+        Color::LightYellow => (255, 255, 0),
+        Color::LightBlue => (92, 92, 255),
+        Color::LightMagenta => (255, 0, 255),
+        Color::LightCyan => (0, 255, 255),
+        Color::White => (255, 255, 255),
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Indexed(index) => indexed_rgb(index),
+    }
+}
+
+/// The xterm 256-colour palette beyond the first sixteen, which are approximated as grey
+fn indexed_rgb(index: u8) -> (u8, u8, u8) {
+    let level = |step: u8| if step == 0 { 0 } else { 55 + step * 40 };
+    match index {
+        16..=231 => {
+            let cube = index - 16;
+            (level(cube / 36), level(cube / 6 % 6), level(cube % 6))
+        }
+        232..=255 => {
+            let grey = 8 + (index - 232) * 10;
+            (grey, grey, grey)
+        }
+        _ => (192, 192, 192),
+    }
+}
+
+/// Relative luminance, as WCAG defines it
+fn luminance((r, g, b): (u8, u8, u8)) -> f64 {
+    let linear = |channel: u8| {
+        let c = channel as f64 / 255.0;
+        if c <= 0.039_28 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+}
+
+/// Contrast ratio of text in `fg` on `bg`
+pub fn contrast(fg: Color, bg: Color) -> f64 {
+    let (a, b) = (luminance(rgb(fg, false)), luminance(rgb(bg, true)));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// The brighter or darker shade of a named colour, if it has one
+fn shade(color: Color, brighter: bool) -> Option<Color> {
+    let pairs = [
+        (Color::DarkGray, Color::Gray),
+        (Color::Red, Color::LightRed),
+        (Color::Green, Color::LightGreen),
+        (Color::Yellow, Color::LightYellow),
+        (Color::Blue, Color::LightBlue),
+        (Color::Magenta, Color::LightMagenta),
+        (Color::Cyan, Color::LightCyan),
+    ];
+    pairs.iter().find_map(|&(dark, light)| match brighter {
+        true if color == dark => Some(light),
+        false if color == light => Some(dark),
+        _ => None,
+    })
+}
+
+/// The colour nearest `fg` whose text stands out from `bg` by at least `min`
+///
+/// Tries the colour itself, then its brighter shade on a dark background or
+/// its darker one on a light background, then white or black.
+pub fn with_contrast(fg: Color, bg: Color, min: f64) -> Color {
+    let dark_background = luminance(rgb(bg, true)) < 0.18;
+    let (named, extreme) = if dark_background {
+        (Color::White, Color::Rgb(255, 255, 255))
+    } else {
+        (Color::Black, Color::Rgb(0, 0, 0))
+    };
+    [Some(fg), shade(fg, dark_background), Some(named)]
+        .into_iter()
+        .flatten()
+        .find(|&candidate| contrast(candidate, bg) >= min)
+        .unwrap_or(extreme)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raises_text_to_the_minimum_contrast() {
+        assert_eq!("4.5".parse(), Ok(ContrastRatio(45)));
+        assert_eq!(ContrastRatio(45).to_string(), "4.5");
+        assert!("0.5".parse::<ContrastRatio>().is_err());
+        assert!((contrast(Color::White, Color::Black) - 21.0).abs() < 0.01);
+
+        // Dim grey on the default background brightens a shade, then to white
+        assert_eq!(
+            with_contrast(Color::DarkGray, Color::Reset, 3.0),
+            Color::DarkGray
+        );
+        assert_eq!(
+            with_contrast(Color::DarkGray, Color::Reset, 7.0),
+            Color::Gray
+        );
+        assert_eq!(
+            with_contrast(Color::DarkGray, Color::Reset, 18.0),
+            Color::White
+        );
+        // White text on a notification's grey background turns black instead
+        assert_eq!(with_contrast(Color::White, Color::Gray, 4.5), Color::Black);
+
+        let accessibility = Accessibility {
+            min_contrast: Some(ContrastRatio(70)),
+            ..Accessibility::default()
+        };
+        let area = Rect::new(0, 0, 4, 1);
+        let mut buf = Buffer::empty(area);
+        buf.set_string(0, 0, "Bob", Style::default().fg(Color::DarkGray));
+        accessibility.apply_contrast(&mut buf, area);
+        assert_eq!(buf.get(0, 0).fg, Color::Gray);
+
+        assert!(flash_lit(Duration::ZERO));
+        assert!(!flash_lit(FLASH_PERIOD));
+        assert!(!flash_lit(FLASH_DURATION));
+    }
+}
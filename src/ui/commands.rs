@@ -30,6 +30,12 @@ pub struct CommandProcessor {
     commands: HashMap<String, String>,
 }
 
+impl Default for CommandProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl CommandProcessor {
     pub fn new() -> Self {
         let mut commands = HashMap::new();
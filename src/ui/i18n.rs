@@ -0,0 +1,1112 @@
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Languages the UI can be shown in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    #[default]
+    English,
+    Spanish,
+}
+
+impl Locale {
+    /// Gets the language code used in settings
+    pub fn code(&self) -> &'static str {
+        match self {
+            Locale::English => "en",
+            Locale::Spanish => "es",
+        }
+    }
+
+    fn catalog(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Locale::English => EN,
+            Locale::Spanish => ES,
+        }
+    }
+}
+
+impl FromStr for Locale {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "en" => Ok(Locale::English),
+            "es" => Ok(Locale::Spanish),
+            _ => Err(format!("Unsupported locale: {}", s)),
+        }
+    }
+}
+
+static CURRENT_LOCALE: AtomicU8 = AtomicU8::new(0);
+
+/// Sets the language used for UI strings
+pub fn set_locale(locale: Locale) {
+    CURRENT_LOCALE.store(locale as u8, Ordering::Relaxed);
+}
+
+/// Gets the language used for UI strings
+pub fn locale() -> Locale {
+    match CURRENT_LOCALE.load(Ordering::Relaxed) {
+        1 => Locale::Spanish,
+        _ => Locale::English,
+    }
+}
+
+fn lookup(locale: Locale, key: &str) -> Option<&'static str> {
+    locale
+        .catalog()
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, text)| *text)
+}
+
+/// Gets the text for a key in the current locale
+///
+/// Falls back to English, then to the key itself, so a missing translation
+/// never hides a string.
+pub fn t(key: &'static str) -> &'static str {
+    lookup(locale(), key)
+        .or_else(|| lookup(Locale::English, key))
+        .unwrap_or(key)
+}
+
+/// Gets the text for a key with each `{}` replaced by the next argument
+pub fn t_args(key: &'static str, args: &[&dyn Display]) -> String {
+    let mut parts = t(key).split("{}");
+    let mut text = parts.next().unwrap_or_default().to_string();
+
+    for (i, part) in parts.enumerate() {
+        if let Some(arg) = args.get(i) {
+            text.push_str(&arg.to_string());
+        }
+        text.push_str(part);
+    }
+
+    text
+}
+
+const EN: &[(&str, &str)] = &[
+    ("menu.create", "Create Session"),
+    ("menu.join", "Join Session"),
+    ("menu.leave", "Leave Session"),
+    ("menu.copy_link", "Copy Link"),
+    ("menu.copy_local_link", "Copy LAN Link"),
+    ("menu.toggle_mute", "Mute/Unmute Mic"),
+    ("menu.settings", "Settings"),
+    ("menu.quit", "Quit"),
+    ("panel.menu", "Menu"),
+    ("panel.participants", "Participants"),
+    ("panel.status", "Status"),
+    ("panel.warning", "Warning"),
+    ("panel.enter_link", "Enter Session Link"),
+    ("status.join_link", "Join Link: {} (Press 'c' to copy)"),
+    ("status.muted", "[Mic muted] "),
+    (
+        "status.muted_by_host",
+        "[Muted by host - press h to ask to speak] ",
+    ),
+    ("status.hand_raised", "[Hand raised - press h to lower it] "),
+    ("status.away", "[Be right back - press b to return] "),
+    ("status.solo", "[Solo: {} - press 0 to hear everyone] "),
+    ("status.solo_marker", "solo"),
+    ("status.elapsed", "[{}] "),
+    ("status.time_left", "[{} left] "),
+    ("status.dnd", "[Do not disturb] "),
+    ("status.low_bandwidth", "[Low bandwidth - press B to leave] "),
+    ("status.recording", "[REC {}] "),
+    (
+        "status.join_queue",
+        "[{} waiting to join - y lets them in, n turns them away] ",
+    ),
+    ("status.bandwidth", "[Up {} Down {}] "),
+    ("stats.title", "Up {} Down {} kbps, {} peers"),
+    ("stats.dropped", ", {} dropped"),
+    ("stats.loss", "Loss {}%"),
+    ("stats.rtt", "RTT {} ms"),
+    ("stats.rtt_unknown", "RTT -"),
+    (
+        "status.not_connected",
+        "Not connected - use Join to create a session",
+    ),
+    ("presence.idle", "idle"),
+    ("presence.away", "away"),
+    ("presence.speaking", "speaking"),
+    ("meeting.hand_raised", "hand raised"),
+    ("meeting.muted", "muted"),
+    ("prompt.join_link", "Enter session link to join:"),
+    ("settings.test_session", "1. Create Test Session"),
+    ("settings.devices", "2. Audio Devices"),
+    ("settings.preferences", "3. Preferences"),
+    ("settings.cancel", "4. Cancel"),
+    ("settings.device_line", "{}: {} kHz, min latency {}, {}"),
+    ("settings.latency_unknown", "unknown"),
+    ("settings.exclusive", "exclusive mode available"),
+    ("settings.shared_only", "shared mode only"),
+    ("settings.no_devices", "No audio devices could be probed"),
+    ("settings.page_general", "General"),
+    ("settings.page_audio", "Audio"),
+    ("settings.page_network", "Network"),
+    ("settings.page_ice", "ICE servers"),
+    ("settings.field_username", "Name"),
+    ("settings.field_locale", "Language"),
+    ("settings.field_do_not_disturb", "Do not disturb"),
+    ("settings.field_keep_awake", "Keep awake in a room"),
+    (
+        "settings.field_session_time_limit_minutes",
+        "Room time limit (minutes)",
+    ),
+    (
+        "settings.field_recording_consent",
+        "Guests' recordings need my OK",
+    ),
+    ("settings.field_audit_log_file", "Room event log file"),
+    ("settings.field_crash_reports", "Crash reports"),
+    (
+        "settings.field_large_speaking_indicators",
+        "Large speaking indicators",
+    ),
+    ("settings.field_flash_join_requests", "Flash on join requests"),
+    ("settings.field_min_contrast", "Minimum contrast (1-21)"),
+    ("settings.field_input_device", "Microphone (next start)"),
+    ("settings.field_output_device", "Speakers (next start)"),
+    ("settings.field_audio_codec", "Codec"),
+    ("settings.field_audio_bitrate_kbps", "Opus bitrate (kbps)"),
+    (
+        "settings.field_frame_duration_ms",
+        "Frame size, ms (shorter: less delay, more bandwidth)",
+    ),
+    (
+        "settings.field_cpu_load_limit_percent",
+        "CPU load limit (%)",
+    ),
+    (
+        "settings.field_auto_microphone",
+        "Pick a microphone if yours is missing",
+    ),
+    ("settings.field_network_monitor", "Hear yourself as others do"),
+    ("settings.field_output_channels", "Output channels"),
+    ("settings.field_system_audio_percent", "Share system audio (%)"),
+    ("settings.field_sync_shared_audio", "Play shared audio in sync"),
+    ("settings.field_stream_role", "Stream type (next start)"),
+    (
+        "settings.field_communications_device",
+        "Windows communications device (next start)",
+    ),
+    ("settings.field_port_mapping", "Port mapping"),
+    ("settings.field_share_lan_addresses", "Share LAN addresses"),
+    ("settings.field_bind_address", "Network interface"),
+    (
+        "settings.field_proxy",
+        "Signaling proxy (direct or socks5://)",
+    ),
+    ("settings.field_low_bandwidth", "Low bandwidth mode"),
+    ("settings.field_upload_cap_kbps", "Upload cap (kbps)"),
+    ("settings.field_download_cap_kbps", "Download cap (kbps)"),
+    ("settings.field_udp_receive_buffer_kb", "UDP receive buffer (KiB)"),
+    ("settings.field_udp_send_buffer_kb", "UDP send buffer (KiB)"),
+    ("settings.field_forward_secrecy", "Forward secrecy"),
+    ("settings.field_nonce_strategy", "Nonces (random or counter)"),
+    (
+        "settings.field_link_shortener_url",
+        "Link shortener (http:// URL)",
+    ),
+    (
+        "settings.field_max_datagram_size",
+        "Largest datagram (bytes)",
+    ),
+    ("settings.system_default", "system default"),
+    ("settings.not_set", "not set"),
+    ("settings.on", "on"),
+    ("settings.off", "off"),
+    ("settings.required", "A value is required"),
+    (
+        "settings.hint",
+        "Tab: page  Up/Down: field  Enter: edit  Esc: save and close",
+    ),
+    (
+        "settings.ice_none",
+        "No servers; the built-in public STUN servers are used",
+    ),
+    (
+        "settings.ice_add",
+        "Add (stun:host:port or turn:user:password@host:port)",
+    ),
+    ("settings.ice_testing", "testing..."),
+    ("settings.ice_passed", "ok, {} ms"),
+    ("settings.ice_failed", "failed: {}"),
+    (
+        "settings.ice_hint",
+        "a: add  x: remove  +/-: priority  t: test all  Tab: page  Esc: save and close",
+    ),
+    (
+        "settings.preview_start",
+        "p: hear a voice walk around you, to check headphones and spatial audio",
+    ),
+    ("settings.preview_question", "Is the voice {}?"),
+    ("settings.preview_front", "in front of you"),
+    ("settings.preview_right", "to your right"),
+    ("settings.preview_back", "behind you"),
+    ("settings.preview_left", "to your left"),
+    (
+        "settings.preview_hint",
+        "y: yes  n: no  r: play again  Esc: stop",
+    ),
+    (
+        "settings.preview_passed",
+        "The voice was where it should be every time",
+    ),
+    (
+        "settings.preview_missed",
+        "Not heard where it should be: {}. Check the headphones are the right way round",
+    ),
+    ("notify.settings_saved", "Settings saved to {}"),
+    ("notify.inspector_no_peers", "No one to inspect yet"),
+    ("inspector.title", "Inspector: {}"),
+    ("inspector.no_connection", "No connection to this peer"),
+    ("inspector.handshake", "Handshake"),
+    ("inspector.state_disconnected", "disconnected"),
+    ("inspector.state_connecting", "in progress"),
+    ("inspector.state_connected", "complete"),
+    ("inspector.cipher", "Cipher"),
+    (
+        "inspector.cipher_value",
+        "{}, {} nonces, forward secrecy {}",
+    ),
+    ("inspector.key_age", "Key age"),
+    ("inspector.none_yet", "none yet"),
+    ("inspector.codec", "Codec"),
+    ("inspector.codec_value", "{} at {} kbps"),
+    ("inspector.path", "Candidate pair"),
+    ("inspector.path_value", "{} -> {}, datagrams up to {} bytes"),
+    ("inspector.buffers", "Socket buffers"),
+    ("inspector.buffers_value", "receive {} KiB, send {} KiB"),
+    ("inspector.lost", "Lost in transit"),
+    ("inspector.lost_value", "{} of {} packets ({}%)"),
+    ("inspector.rejected", "Rejected packets"),
+    (
+        "inspector.rejected_value",
+        "{} failed authentication, {} malformed, {} replayed",
+    ),
+    ("inspector.jitter", "Jitter buffer"),
+    (
+        "inspector.jitter_value",
+        "aiming for {} ms, holding {} ms; jitter {} ms, {}% late",
+    ),
+    ("inspector.drift", "Clock drift"),
+    ("inspector.drift_value", "{} ppm, resampled away"),
+    ("inspector.events", "Recent events (time ago)"),
+    ("inspector.hint", "Tab: next peer  i: close"),
+    ("history.title", "Host actions"),
+    ("history.empty", "Nothing done yet"),
+    ("history.denied", "Denied {}"),
+    ("history.requeued", "Invited back {}"),
+    ("history.muted_all", "Muted everyone"),
+    ("history.unmuted_all", "Let everyone speak"),
+    ("history.granted", "Let {} speak"),
+    ("history.undone", "(undone)"),
+    ("history.hint", "Up/Down: select  r: do again  u: undo  H: close"),
+    ("timeline.title", "Session timeline"),
+    ("timeline.empty", "Not in a room yet"),
+    ("timeline.entered", "You entered the room"),
+    ("timeline.left", "You left the room"),
+    ("timeline.peer_joined", "{} joined"),
+    ("timeline.peer_left", "{} left"),
+    ("timeline.became_host", "You became the host"),
+    ("timeline.active", "{} is back"),
+    ("timeline.idle", "{} is idle"),
+    ("timeline.away", "{} is away"),
+    ("timeline.recording_started", "{} started recording"),
+    ("timeline.recording_stopped", "{} stopped recording"),
+    ("timeline.meeting_on", "Everyone but the host was muted"),
+    ("timeline.meeting_off", "Everyone may speak"),
+    (
+        "timeline.hint",
+        "Up/Down/PgUp/PgDn: scroll  x: export report  T: close",
+    ),
+    ("notify.action_replayed", "Done again"),
+    ("notify.action_undone", "Undone"),
+    ("notify.woke", "Back after {} asleep; reconnecting"),
+    ("error.bind_address", "Network interface unavailable: {}"),
+    ("error.network_monitor", "Couldn't start the network monitor: {}"),
+    ("error.system_audio", "Couldn't share system audio: {}"),
+    ("error.session_report", "Couldn't write the session report: {}"),
+    ("error.settings_save_failed", "Failed to save settings: {}"),
+    ("notify.link_copied", "Link copied to clipboard!"),
+    ("notify.copy_failed", "Failed to copy link!"),
+    ("notify.no_link", "No active link to copy"),
+    (
+        "notify.no_shareable_address",
+        "The link has no address that may be shared",
+    ),
+    ("notify.log_opened", "Opened log: {}"),
+    ("notify.log_file", "Log file: {}"),
+    ("notify.report_written", "Session report written to {}"),
+    ("notify.logging_disabled", "Logging is not enabled"),
+    ("notify.joined", "Successfully joined session"),
+    ("notify.leaving", "Leaving session..."),
+    ("notify.left", "Session left successfully"),
+    ("notify.not_in_session", "Not in a session"),
+    ("notify.muted", "Microphone muted"),
+    ("notify.unmuted", "Microphone unmuted"),
+    ("notify.solo", "Only hearing {}"),
+    ("notify.layout", "Room arranged: {}"),
+    (
+        "notify.calibrate_quiet",
+        "Calibrating: stay quiet while room noise is measured...",
+    ),
+    (
+        "notify.calibrate_speak",
+        "Now speak normally until the calibration finishes...",
+    ),
+    ("notify.calibrated", "Microphone calibrated (gain x{})"),
+    ("notify.calibration_failed", "Calibration failed: {}"),
+    (
+        "notify.layout_host_only",
+        "Only the host can arrange the room",
+    ),
+    ("layout.circle", "circle"),
+    ("layout.grid", "grid"),
+    ("layout.presenter", "presenter in front"),
+    ("notify.away", "Be right back - others hear your hold audio"),
+    ("notify.back", "Welcome back"),
+    ("notify.hold_audio_failed", "Couldn't load hold audio: {}"),
+    ("notify.solo_off", "Hearing everyone again"),
+    (
+        "notify.meeting_on",
+        "The host muted everyone - press h to ask to speak",
+    ),
+    (
+        "notify.meeting_on_host",
+        "Everyone else is muted - press g to let a raised hand speak",
+    ),
+    ("notify.meeting_off", "Everyone may speak again"),
+    (
+        "notify.meeting_host_only",
+        "Only the host can mute everyone or let people speak",
+    ),
+    (
+        "notify.echo_bot_on",
+        "Echo bot joined: it plays back what you say a second later",
+    ),
+    ("notify.echo_bot_off", "Echo bot left"),
+    ("notify.impairment", "Network impairment: {} ({})"),
+    (
+        "notify.echo_bot_host_only",
+        "Only the host can add the echo bot",
+    ),
+    (
+        "notify.hand_raised",
+        "{} raised their hand - press g to let them speak",
+    ),
+    ("notify.hand_not_needed", "You aren't muted by the host"),
+    ("notify.no_hands", "Nobody has raised their hand"),
+    ("notify.granted", "{} may speak now"),
+    ("notify.granted_self", "The host let you speak"),
+    ("notify.recording_started", "{} started recording the room"),
+    ("notify.recording_stopped", "{} stopped recording"),
+    ("notify.recording_self", "Recording - everyone in the room sees it"),
+    (
+        "notify.dnd_on",
+        "Do not disturb - join requests are held back",
+    ),
+    ("notify.dnd_off", "Do not disturb is off"),
+    (
+        "notify.low_bandwidth_on",
+        "Low bandwidth mode - lower bitrate, fuller packets and simple panning",
+    ),
+    ("notify.low_bandwidth_off", "Low bandwidth mode is off"),
+    ("notify.join_requested", "{} asks to join the session"),
+    (
+        "notify.input_missing",
+        "Your microphone {} isn't connected, so the default one is used - press s to choose another",
+    ),
+    (
+        "notify.input_replaced",
+        "Your microphone {} isn't connected, so {} is used instead - press s to choose another",
+    ),
+    (
+        "notify.output_missing",
+        "Your speakers {} aren't connected, so the default ones are used - press s to choose others",
+    ),
+    (
+        "notify.capture_stalled",
+        "Your microphone stopped sending audio - reopening it",
+    ),
+    ("notify.capture_recovered", "Your microphone is back"),
+    (
+        "notify.capture_failed",
+        "Couldn't reopen your microphone ({}) - trying again",
+    ),
+    (
+        "notify.join_queue",
+        "Waiting to join: {} - press y to let everyone in or n to turn everyone away",
+    ),
+    ("notify.approved_all", "Let {} in"),
+    ("notify.denied_all", "Turned {} away"),
+    (
+        "notify.connection_retry",
+        "Couldn't reach {} - trying again ({} of {})",
+    ),
+    (
+        "notify.handshake_timeout",
+        "Gave up on {}: it never answered after we connected",
+    ),
+    (
+        "notify.one_way_audio",
+        "No audio from {} though we're sending ours: ask them to check their microphone isn't muted or blocked, or try a TURN server in Settings if a firewall drops their traffic",
+    ),
+    (
+        "notify.upload_saturated",
+        "Upload is near its {} kbps cap - audio quality lowered",
+    ),
+    (
+        "notify.download_saturated",
+        "Download is near its {} kbps cap - peers are sending less",
+    ),
+    (
+        "notify.simple_panning",
+        "Audio is using too much CPU - switched to simple panning",
+    ),
+    (
+        "notify.low_complexity",
+        "Audio is still using too much CPU - lowered codec complexity",
+    ),
+    (
+        "notify.test_session_created",
+        "Test session created with 3 simulated participants",
+    ),
+    ("notify.peer_joined", "{} joined the session"),
+    ("notify.peer_left", "A participant left the session"),
+    ("notify.now_host", "You are now the session host"),
+    ("notify.time_warning", "The session ends in {} min"),
+    ("notify.time_limit", "The session reached its time limit"),
+    (
+        "notify.tampering",
+        "A participant's packets keep failing security checks: possible tampering",
+    ),
+    ("error.join_failed", "Failed to join session: {}"),
+    ("error.leave_failed", "Error leaving session: {}"),
+    ("error.test_session_failed", "Failed to create test session"),
+    ("error.create_failed", "Failed to create session: {}"),
+    ("hint.no_session", "Create or join a session first"),
+    ("hint.not_host", "Ask the host to do this"),
+    (
+        "hint.invalid_link",
+        "Check the whole link was copied; it starts with resonance://join?",
+    ),
+    (
+        "hint.nat_discovery",
+        "UDP may be blocked on this network: check the firewall, or add a TURN server under Settings > ICE servers",
+    ),
+    (
+        "hint.bind_failed",
+        "Pick another network interface under Settings > Network, or set it to auto",
+    ),
+    (
+        "hint.connection_failed",
+        "The host may be offline or behind a strict NAT: ask them to turn on port mapping, or to share LAN addresses if you're on the same network",
+    ),
+    (
+        "hint.handshake_timeout",
+        "Our packets got there but nothing came back: check the link is from the room as it is now, and ask them to let resonance through their firewall or add a TURN server in Settings",
+    ),
+    (
+        "hint.peer_not_found",
+        "They may have left; check the participant list",
+    ),
+    (
+        "hint.handoff",
+        "Export the call again and resume it within two minutes, with the key shown alongside it",
+    ),
+    (
+        "hint.recording_consent",
+        "This room needs the host to acknowledge recordings: try again once you're connected to the host",
+    ),
+    ("health.title", "Startup checks"),
+    ("health.dismiss", "Press any key to continue"),
+    ("health.config", "Configuration"),
+    ("health.audio", "Audio devices"),
+    ("health.udp", "UDP socket"),
+    ("health.stun", "STUN server"),
+    ("health.clock", "System clock"),
+    (
+        "health.hint_config",
+        "Defaults are in use; fix or delete the configuration file, or save settings to replace it",
+    ),
+    (
+        "health.hint_audio",
+        "Connect a microphone and headphones, then check the system's sound settings",
+    ),
+    (
+        "health.hint_audio_missing",
+        "Reconnect the chosen device or pick another in Settings",
+    ),
+    (
+        "health.hint_udp",
+        "Choose another bind address in Settings, or close whatever holds the port",
+    ),
+    (
+        "health.hint_udp_buffers",
+        "The OS limits socket buffers below the sizes in Settings: raise net.core.rmem_max and wmem_max, or ask for less",
+    ),
+    (
+        "health.hint_stun",
+        "Check the internet connection and firewall, or add another STUN server in Settings",
+    ),
+    (
+        "health.hint_clock",
+        "Set the system clock; join links and session limits depend on it",
+    ),
+];
+
+const ES: &[(&str, &str)] = &[
+    ("menu.create", "Crear sesión"),
+    ("menu.join", "Unirse a sesión"),
+    ("menu.leave", "Salir de la sesión"),
+    ("menu.copy_link", "Copiar enlace"),
+    ("menu.copy_local_link", "Copiar enlace LAN"),
+    ("menu.toggle_mute", "Silenciar/activar micrófono"),
+    ("menu.settings", "Ajustes"),
+    ("menu.quit", "Salir"),
+    ("panel.menu", "Menú"),
+    ("panel.participants", "Participantes"),
+    ("panel.status", "Estado"),
+    ("panel.warning", "Advertencia"),
+    ("panel.enter_link", "Introducir enlace de sesión"),
+    ("status.join_link", "Enlace: {} (pulsa 'c' para copiar)"),
+    ("status.muted", "[Micrófono silenciado] "),
+    (
+        "status.muted_by_host",
+        "[Silenciado por el anfitrión - pulsa h para pedir la palabra] ",
+    ),
+    (
+        "status.hand_raised",
+        "[Mano levantada - pulsa h para bajarla] ",
+    ),
+    ("status.away", "[Vuelvo enseguida - pulsa b para volver] "),
+    ("status.solo", "[Solo: {} - pulsa 0 para oír a todos] "),
+    ("status.solo_marker", "solo"),
+    ("status.elapsed", "[{}] "),
+    ("status.time_left", "[quedan {}] "),
+    ("status.dnd", "[No molestar] "),
+    ("status.low_bandwidth", "[Poco ancho de banda - pulsa B para salir] "),
+    ("status.recording", "[GRAB {}] "),
+    (
+        "status.join_queue",
+        "[{} esperando para entrar - y para admitirlos, n para rechazarlos] ",
+    ),
+    ("status.bandwidth", "[Subida {} Bajada {}] "),
+    ("stats.title", "Subida {} Bajada {} kbps, {} pares"),
+    ("stats.dropped", ", {} descartados"),
+    ("stats.loss", "Pérdida {}%"),
+    ("stats.rtt", "RTT {} ms"),
+    ("stats.rtt_unknown", "RTT -"),
+    (
+        "status.not_connected",
+        "Sin conexión - usa Unirse para crear una sesión",
+    ),
+    ("presence.idle", "inactivo"),
+    ("presence.away", "ausente"),
+    ("presence.speaking", "hablando"),
+    ("meeting.hand_raised", "mano levantada"),
+    ("meeting.muted", "silenciado"),
+    ("prompt.join_link", "Introduce el enlace de la sesión:"),
+    ("settings.test_session", "1. Crear sesión de prueba"),
+    ("settings.devices", "2. Dispositivos de audio"),
+    ("settings.preferences", "3. Preferencias"),
+    ("settings.cancel", "4. Cancelar"),
+    ("settings.device_line", "{}: {} kHz, latencia mínima {}, {}"),
+    ("settings.latency_unknown", "desconocida"),
+    ("settings.exclusive", "modo exclusivo disponible"),
+    ("settings.shared_only", "solo modo compartido"),
+    (
+        "settings.no_devices",
+        "No se pudo consultar ningún dispositivo de audio",
+    ),
+    ("settings.page_general", "General"),
+    ("settings.page_audio", "Audio"),
+    ("settings.page_network", "Red"),
+    ("settings.page_ice", "Servidores ICE"),
+    ("settings.field_username", "Nombre"),
+    ("settings.field_locale", "Idioma"),
+    ("settings.field_do_not_disturb", "No molestar"),
+    (
+        "settings.field_keep_awake",
+        "Mantener el equipo despierto en una sala",
+    ),
+    (
+        "settings.field_session_time_limit_minutes",
+        "Límite de la sala (minutos)",
+    ),
+    (
+        "settings.field_recording_consent",
+        "Las grabaciones de invitados necesitan mi visto bueno",
+    ),
+    (
+        "settings.field_audit_log_file",
+        "Archivo de registro de eventos de la sala",
+    ),
+    ("settings.field_crash_reports", "Informes de fallos"),
+    (
+        "settings.field_large_speaking_indicators",
+        "Indicadores de voz grandes",
+    ),
+    (
+        "settings.field_flash_join_requests",
+        "Destello al pedir unirse",
+    ),
+    ("settings.field_min_contrast", "Contraste mínimo (1-21)"),
+    ("settings.field_input_device", "Micrófono (próximo inicio)"),
+    ("settings.field_output_device", "Altavoces (próximo inicio)"),
+    ("settings.field_audio_codec", "Códec"),
+    ("settings.field_audio_bitrate_kbps", "Tasa de Opus (kbps)"),
+    (
+        "settings.field_frame_duration_ms",
+        "Tamaño de trama, ms (menor: menos retardo, más ancho de banda)",
+    ),
+    (
+        "settings.field_cpu_load_limit_percent",
+        "Límite de carga de CPU (%)",
+    ),
+    (
+        "settings.field_auto_microphone",
+        "Elegir un micrófono si falta el tuyo",
+    ),
+    (
+        "settings.field_network_monitor",
+        "Escucharte como te oyen los demás",
+    ),
+    ("settings.field_output_channels", "Canales de salida"),
+    ("settings.field_system_audio_percent", "Compartir audio del sistema (%)"),
+    (
+        "settings.field_sync_shared_audio",
+        "Reproducir el audio compartido sincronizado",
+    ),
+    ("settings.field_stream_role", "Tipo de flujo (al reiniciar)"),
+    (
+        "settings.field_communications_device",
+        "Dispositivo de comunicaciones de Windows (al reiniciar)",
+    ),
+    ("settings.field_port_mapping", "Redirección de puertos"),
+    (
+        "settings.field_share_lan_addresses",
+        "Compartir direcciones LAN",
+    ),
+    ("settings.field_bind_address", "Interfaz de red"),
+    (
+        "settings.field_proxy",
+        "Proxy de señalización (direct o socks5://)",
+    ),
+    ("settings.field_low_bandwidth", "Modo de poco ancho de banda"),
+    ("settings.field_upload_cap_kbps", "Límite de subida (kbps)"),
+    (
+        "settings.field_download_cap_kbps",
+        "Límite de bajada (kbps)",
+    ),
+    (
+        "settings.field_udp_receive_buffer_kb",
+        "Búfer de recepción UDP (KiB)",
+    ),
+    ("settings.field_udp_send_buffer_kb", "Búfer de envío UDP (KiB)"),
+    ("settings.field_forward_secrecy", "Secreto perfecto hacia adelante"),
+    ("settings.field_nonce_strategy", "Nonces (aleatorios o contador)"),
+    (
+        "settings.field_link_shortener_url",
+        "Acortador de enlaces (URL http://)",
+    ),
+    (
+        "settings.field_max_datagram_size",
+        "Datagrama más grande (bytes)",
+    ),
+    ("settings.system_default", "predeterminado del sistema"),
+    ("settings.not_set", "sin definir"),
+    ("settings.on", "sí"),
+    ("settings.off", "no"),
+    ("settings.required", "Hace falta un valor"),
+    (
+        "settings.hint",
+        "Tab: página  Arriba/Abajo: campo  Enter: editar  Esc: guardar y cerrar",
+    ),
+    (
+        "settings.ice_none",
+        "Sin servidores; se usan los servidores STUN públicos incluidos",
+    ),
+    (
+        "settings.ice_add",
+        "Añadir (stun:host:puerto o turn:usuario:contraseña@host:puerto)",
+    ),
+    ("settings.ice_testing", "probando..."),
+    ("settings.ice_passed", "bien, {} ms"),
+    ("settings.ice_failed", "falló: {}"),
+    (
+        "settings.ice_hint",
+        "a: añadir  x: quitar  +/-: prioridad  t: probar todos  Tab: página  Esc: guardar y cerrar",
+    ),
+    (
+        "settings.preview_start",
+        "p: oír una voz que camina a tu alrededor, para comprobar los auriculares y el audio espacial",
+    ),
+    ("settings.preview_question", "¿Está la voz {}?"),
+    ("settings.preview_front", "delante de ti"),
+    ("settings.preview_right", "a tu derecha"),
+    ("settings.preview_back", "detrás de ti"),
+    ("settings.preview_left", "a tu izquierda"),
+    (
+        "settings.preview_hint",
+        "y: sí  n: no  r: repetir  Esc: parar",
+    ),
+    (
+        "settings.preview_passed",
+        "La voz estuvo siempre donde debía",
+    ),
+    (
+        "settings.preview_missed",
+        "No se oyó donde debía: {}. Comprueba que los auriculares estén bien puestos",
+    ),
+    ("notify.settings_saved", "Ajustes guardados en {}"),
+    ("notify.inspector_no_peers", "Todavía no hay nadie que inspeccionar"),
+    ("inspector.title", "Inspector: {}"),
+    ("inspector.no_connection", "Sin conexión con este participante"),
+    ("inspector.handshake", "Negociación"),
+    ("inspector.state_disconnected", "desconectado"),
+    ("inspector.state_connecting", "en curso"),
+    ("inspector.state_connected", "completa"),
+    ("inspector.cipher", "Cifrado"),
+    (
+        "inspector.cipher_value",
+        "{}, nonces {}, secreto perfecto hacia adelante {}",
+    ),
+    ("inspector.key_age", "Edad de la clave"),
+    ("inspector.none_yet", "todavía ninguna"),
+    ("inspector.codec", "Códec"),
+    ("inspector.codec_value", "{} a {} kbps"),
+    ("inspector.path", "Par de candidatos"),
+    ("inspector.path_value", "{} -> {}, datagramas de hasta {} bytes"),
+    ("inspector.buffers", "Búferes del socket"),
+    ("inspector.buffers_value", "recepción {} KiB, envío {} KiB"),
+    ("inspector.lost", "Perdidos en tránsito"),
+    ("inspector.lost_value", "{} de {} paquetes ({}%)"),
+    ("inspector.rejected", "Paquetes rechazados"),
+    (
+        "inspector.rejected_value",
+        "{} sin autenticar, {} mal formados, {} repetidos",
+    ),
+    ("inspector.jitter", "Búfer de jitter"),
+    (
+        "inspector.jitter_value",
+        "objetivo {} ms, retiene {} ms; jitter {} ms, {}% tarde",
+    ),
+    ("inspector.drift", "Deriva del reloj"),
+    ("inspector.drift_value", "{} ppm, compensada al remuestrear"),
+    ("inspector.events", "Eventos recientes (hace)"),
+    ("inspector.hint", "Tab: siguiente participante  i: cerrar"),
+    ("history.title", "Acciones del anfitrión"),
+    ("history.empty", "Todavía no has hecho nada"),
+    ("history.denied", "Rechazaste a {}"),
+    ("history.requeued", "Volviste a invitar a {}"),
+    ("history.muted_all", "Silenciaste a todos"),
+    ("history.unmuted_all", "Dejaste hablar a todos"),
+    ("history.granted", "Dejaste hablar a {}"),
+    ("history.undone", "(deshecho)"),
+    ("history.hint", "Arriba/Abajo: elegir  r: repetir  u: deshacer  H: cerrar"),
+    ("timeline.title", "Cronología de la sesión"),
+    ("timeline.empty", "Todavía no estás en una sala"),
+    ("timeline.entered", "Entraste en la sala"),
+    ("timeline.left", "Saliste de la sala"),
+    ("timeline.peer_joined", "{} se unió"),
+    ("timeline.peer_left", "{} se fue"),
+    ("timeline.became_host", "Pasaste a ser el anfitrión"),
+    ("timeline.active", "{} ha vuelto"),
+    ("timeline.idle", "{} está inactivo"),
+    ("timeline.away", "{} está ausente"),
+    ("timeline.recording_started", "{} empezó a grabar"),
+    ("timeline.recording_stopped", "{} dejó de grabar"),
+    ("timeline.meeting_on", "Se silenció a todos menos al anfitrión"),
+    ("timeline.meeting_off", "Todos pueden hablar"),
+    (
+        "timeline.hint",
+        "Arriba/Abajo/RePág/AvPág: desplazar  x: exportar informe  T: cerrar",
+    ),
+    ("notify.action_replayed", "Hecho de nuevo"),
+    ("notify.action_undone", "Deshecho"),
+    ("notify.woke", "De vuelta tras {} en reposo; reconectando"),
+    ("error.bind_address", "Interfaz de red no disponible: {}"),
+    (
+        "error.network_monitor",
+        "No se pudo iniciar la escucha de red: {}",
+    ),
+    ("error.system_audio", "No se pudo compartir el audio del sistema: {}"),
+    (
+        "error.session_report",
+        "No se pudo guardar el informe de la sesión: {}",
+    ),
+    (
+        "error.settings_save_failed",
+        "No se pudieron guardar los ajustes: {}",
+    ),
+    ("notify.link_copied", "¡Enlace copiado al portapapeles!"),
+    ("notify.copy_failed", "¡No se pudo copiar el enlace!"),
+    ("notify.no_link", "No hay ningún enlace para copiar"),
+    (
+        "notify.no_shareable_address",
+        "El enlace no tiene ninguna dirección que se pueda compartir",
+    ),
+    ("notify.log_opened", "Registro abierto: {}"),
+    ("notify.log_file", "Archivo de registro: {}"),
+    ("notify.report_written", "Informe de la sesión guardado en {}"),
+    ("notify.logging_disabled", "El registro no está activado"),
+    ("notify.joined", "Te has unido a la sesión"),
+    ("notify.leaving", "Saliendo de la sesión..."),
+    ("notify.left", "Has salido de la sesión"),
+    ("notify.not_in_session", "No estás en ninguna sesión"),
+    ("notify.muted", "Micrófono silenciado"),
+    ("notify.unmuted", "Micrófono activado"),
+    ("notify.solo", "Solo se oye a {}"),
+    ("notify.layout", "Sala organizada: {}"),
+    (
+        "notify.calibrate_quiet",
+        "Calibrando: guarda silencio mientras se mide el ruido de la sala...",
+    ),
+    (
+        "notify.calibrate_speak",
+        "Ahora habla con normalidad hasta que termine la calibración...",
+    ),
+    ("notify.calibrated", "Micrófono calibrado (ganancia x{})"),
+    ("notify.calibration_failed", "Falló la calibración: {}"),
+    (
+        "notify.layout_host_only",
+        "Solo el anfitrión puede organizar la sala",
+    ),
+    ("layout.circle", "círculo"),
+    ("layout.grid", "cuadrícula"),
+    ("layout.presenter", "presentador al frente"),
+    (
+        "notify.away",
+        "Vuelvo enseguida - los demás oyen tu audio de espera",
+    ),
+    ("notify.back", "Bienvenido de nuevo"),
+    (
+        "notify.hold_audio_failed",
+        "No se pudo cargar el audio de espera: {}",
+    ),
+    ("notify.solo_off", "Se oye a todos de nuevo"),
+    (
+        "notify.meeting_on",
+        "El anfitrión ha silenciado a todos - pulsa h para pedir la palabra",
+    ),
+    (
+        "notify.meeting_on_host",
+        "Los demás están silenciados - pulsa g para dar la palabra a una mano levantada",
+    ),
+    ("notify.meeting_off", "Todos pueden hablar de nuevo"),
+    (
+        "notify.meeting_host_only",
+        "Solo el anfitrión puede silenciar a todos o dar la palabra",
+    ),
+    (
+        "notify.echo_bot_on",
+        "Bot de eco añadido: repite lo que dices un segundo después",
+    ),
+    ("notify.echo_bot_off", "El bot de eco se ha ido"),
+    ("notify.impairment", "Degradación de red: {} ({})"),
+    (
+        "notify.echo_bot_host_only",
+        "Solo el anfitrión puede añadir el bot de eco",
+    ),
+    (
+        "notify.hand_raised",
+        "{} ha levantado la mano - pulsa g para darle la palabra",
+    ),
+    ("notify.hand_not_needed", "El anfitrión no te ha silenciado"),
+    ("notify.no_hands", "Nadie ha levantado la mano"),
+    ("notify.granted", "{} puede hablar ahora"),
+    ("notify.granted_self", "El anfitrión te ha dado la palabra"),
+    ("notify.recording_started", "{} empezó a grabar la sala"),
+    ("notify.recording_stopped", "{} dejó de grabar"),
+    ("notify.recording_self", "Grabando: todos en la sala lo ven"),
+    (
+        "notify.dnd_on",
+        "No molestar - las solicitudes para unirse quedan en espera",
+    ),
+    ("notify.dnd_off", "No molestar desactivado"),
+    (
+        "notify.low_bandwidth_on",
+        "Modo de poco ancho de banda: menos bitrate, paquetes más llenos y panoramización simple",
+    ),
+    ("notify.low_bandwidth_off", "Modo de poco ancho de banda desactivado"),
+    ("notify.join_requested", "{} pide unirse a la sesión"),
+    (
+        "notify.input_missing",
+        "Tu micrófono {} no está conectado, se usa el predeterminado - pulsa s para elegir otro",
+    ),
+    (
+        "notify.input_replaced",
+        "Tu micrófono {} no está conectado, se usa {} en su lugar - pulsa s para elegir otro",
+    ),
+    (
+        "notify.output_missing",
+        "Tus altavoces {} no están conectados, se usan los predeterminados - pulsa s para elegir otros",
+    ),
+    (
+        "notify.capture_stalled",
+        "Tu micrófono dejó de enviar audio - reabriéndolo",
+    ),
+    ("notify.capture_recovered", "Tu micrófono ha vuelto"),
+    (
+        "notify.capture_failed",
+        "No se pudo reabrir tu micrófono ({}) - reintentando",
+    ),
+    (
+        "notify.join_queue",
+        "Esperando para entrar: {} - pulsa y para admitir a todos o n para rechazarlos",
+    ),
+    ("notify.approved_all", "Admitidos: {}"),
+    ("notify.denied_all", "Rechazados: {}"),
+    (
+        "notify.connection_retry",
+        "No se pudo conectar con {} - reintentando ({} de {})",
+    ),
+    (
+        "notify.handshake_timeout",
+        "Se abandonó {}: no respondió después de conectar",
+    ),
+    (
+        "notify.one_way_audio",
+        "No llega audio de {} aunque enviamos el nuestro: pídele que revise que su micrófono no esté silenciado o bloqueado, o prueba un servidor TURN en Ajustes si un cortafuegos bloquea su tráfico",
+    ),
+    (
+        "notify.upload_saturated",
+        "La subida está cerca de su límite de {} kbps - calidad de audio reducida",
+    ),
+    (
+        "notify.download_saturated",
+        "La bajada está cerca de su límite de {} kbps - los demás envían menos",
+    ),
+    (
+        "notify.simple_panning",
+        "El audio usa demasiada CPU - se cambió a panorama simple",
+    ),
+    (
+        "notify.low_complexity",
+        "El audio sigue usando demasiada CPU - se redujo la complejidad del códec",
+    ),
+    (
+        "notify.test_session_created",
+        "Sesión de prueba creada con 3 participantes simulados",
+    ),
+    ("notify.peer_joined", "{} se ha unido a la sesión"),
+    ("notify.peer_left", "Un participante ha salido de la sesión"),
+    ("notify.now_host", "Ahora eres el anfitrión de la sesión"),
+    ("notify.time_warning", "La sesión termina en {} min"),
+    ("notify.time_limit", "La sesión alcanzó su límite de tiempo"),
+    (
+        "notify.tampering",
+        "Los paquetes de un participante no pasan los controles: posible manipulación",
+    ),
+    ("error.join_failed", "No se pudo unir a la sesión: {}"),
+    ("error.leave_failed", "Error al salir de la sesión: {}"),
+    (
+        "error.test_session_failed",
+        "No se pudo crear la sesión de prueba",
+    ),
+    ("error.create_failed", "No se pudo crear la sesión: {}"),
+    ("hint.no_session", "Crea una sesión o únete a una primero"),
+    ("hint.not_host", "Pide al anfitrión que lo haga"),
+    (
+        "hint.invalid_link",
+        "Comprueba que copiaste el enlace entero; empieza por resonance://join?",
+    ),
+    (
+        "hint.nat_discovery",
+        "Puede que UDP esté bloqueado en esta red: revisa el cortafuegos o añade un servidor TURN en Ajustes > Servidores ICE",
+    ),
+    (
+        "hint.bind_failed",
+        "Elige otra interfaz de red en Ajustes > Red, o ponla en auto",
+    ),
+    (
+        "hint.connection_failed",
+        "Puede que el anfitrión no esté conectado o esté tras un NAT estricto: pídele que active la redirección de puertos, o que comparta direcciones LAN si estáis en la misma red",
+    ),
+    (
+        "hint.handshake_timeout",
+        "Nuestros paquetes llegaron pero no volvió nada: comprueba que el enlace sea de la sala actual, y pide que dejen pasar resonance por su cortafuegos o añade un servidor TURN en Ajustes",
+    ),
+    (
+        "hint.peer_not_found",
+        "Puede que se haya ido; revisa la lista de participantes",
+    ),
+    (
+        "hint.handoff",
+        "Exporta la llamada de nuevo y reanúdala en menos de dos minutos, con la clave que se muestra junto a ella",
+    ),
+    (
+        "hint.recording_consent",
+        "Esta sala necesita que el anfitrión confirme las grabaciones: inténtalo de nuevo cuando estés conectado al anfitrión",
+    ),
+    ("health.title", "Comprobaciones de inicio"),
+    ("health.dismiss", "Pulsa cualquier tecla para continuar"),
+    ("health.config", "Configuración"),
+    ("health.audio", "Dispositivos de audio"),
+    ("health.udp", "Socket UDP"),
+    ("health.stun", "Servidor STUN"),
+    ("health.clock", "Reloj del sistema"),
+    (
+        "health.hint_config",
+        "Se usan los valores predeterminados; corrige o borra el archivo de configuración, o guarda los ajustes para reemplazarlo",
+    ),
+    (
+        "health.hint_audio",
+        "Conecta un micrófono y auriculares, y revisa los ajustes de sonido del sistema",
+    ),
+    (
+        "health.hint_audio_missing",
+        "Vuelve a conectar el dispositivo elegido o elige otro en Ajustes",
+    ),
+    (
+        "health.hint_udp",
+        "Elige otra dirección de enlace en Ajustes, o cierra lo que ocupa el puerto",
+    ),
+    (
+        "health.hint_udp_buffers",
+        "El sistema limita los búferes del socket por debajo de los tamaños de Ajustes: sube net.core.rmem_max y wmem_max, o pide menos",
+    ),
+    (
+        "health.hint_stun",
+        "Revisa la conexión a internet y el cortafuegos, o añade otro servidor STUN en Ajustes",
+    ),
+    (
+        "health.hint_clock",
+        "Ajusta el reloj del sistema; los enlaces de invitación y los límites de sesión dependen de él",
+    ),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalogs_cover_the_same_keys() {
+        for (key, _) in EN {
+            assert!(
+                lookup(Locale::Spanish, key).is_some(),
+                "missing es: {}",
+                key
+            );
+        }
+        assert_eq!(EN.len(), ES.len());
+    }
+
+    #[test]
+    fn test_lookup_and_formatting() {
+        assert_eq!(lookup(Locale::Spanish, "menu.quit"), Some("Salir"));
+        assert_eq!(t("no.such.key"), "no.such.key");
+        assert_eq!(
+            t_args("error.join_failed", &[&"timeout"]),
+            "Failed to join session: timeout"
+        );
+        assert_eq!("es".parse::<Locale>(), Ok(Locale::Spanish));
+    }
+}
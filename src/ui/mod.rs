@@ -1,4 +1,6 @@
+pub mod accessibility;
 mod commands;
+pub mod i18n;
 pub mod qr_code;
 pub mod terminal_ui;
 mod tui;
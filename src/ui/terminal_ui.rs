@@ -1,29 +1,51 @@
 use clipboard::{ClipboardContext, ClipboardProvider};
 use crossterm::{
-    event::{
-        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers,
-    },
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{
-    backend::{Backend, CrosstermBackend},
+    backend::CrosstermBackend,
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Style},
-    symbols::Marker,
+    style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Widget},
-    Frame, Terminal,
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    Terminal,
 };
 use std::{
-    io::{self, stdout},
+    collections::{HashMap, HashSet},
+    io::{self},
+    path::PathBuf,
     sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
 
+use crate::app::admission::QueuedJoin;
+use crate::app::config::Config;
+use crate::app::health::HealthCheck;
+use crate::app::host_actions::RecordedAction;
+use crate::app::logging;
+use crate::app::presence::PresenceStatus;
+use crate::app::session_timer::{format_clock, SessionTimer};
+use crate::app::timeline::TimelineEntry;
 use crate::app::App;
 use crate::audio;
-use crate::ui::widgets::{AudioVisualizationWidget, Participant, ParticipantListWidget};
+use crate::network::{
+    filter_link_candidates, BandwidthUsage, ConnectionDetails, IceServer, IceServerList,
+    IceTestResult, LinkPrivacy, NetworkStats,
+};
+use crate::ui::accessibility::{self, Accessibility};
+use crate::ui::i18n::{t, t_args};
+use crate::ui::widgets::{
+    ActionHistory, AudioVisualizationWidget, HealthChecklist, Participant, PeerInspector,
+    RoomStats, SettingsEvent, SettingsForm, TimelineView,
+};
+
+/// Timeline entries PageUp and PageDown scroll by
+const TIMELINE_PAGE: usize = 10;
+
+/// Audio level above which a participant shows as speaking
+const SPEAKING_LEVEL: f32 = 0.05;
 
 /// Structure representing the layout of the UI
 #[derive(Debug, Clone, Copy)]
@@ -48,8 +70,60 @@ pub enum MenuAction {
     Join,
     Leave,
     CopyLink,
+    /// Copy the link including LAN addresses
+    CopyLocalLink,
+    ToggleMute,
+    /// Step away: send hold audio instead of the microphone and show as away
+    BeRightBack,
+    /// Seat everyone with the next layout preset (host only)
+    CycleLayout,
+    /// Mute everyone else, or let everyone speak again (host only)
+    MuteAll,
+    /// Add a bot that plays our audio back a second later, or take it out (host only)
+    EchoBot,
+    /// Impair the network with the next test preset; a hidden debug key
+    CycleImpairment,
+    /// Ask the host to let us speak, or take it back
+    RaiseHand,
+    /// Let the peer who raised their hand first speak (host only)
+    GrantSpeak,
+    /// Tell the room we started recording it, or stopped
+    Record,
+    /// Let everyone waiting to join in (host only)
+    ApproveAll,
+    /// Turn away everyone waiting to join (host only)
+    DenyAll,
+    /// Hold back join requests and notification sounds, or stop
+    DoNotDisturb,
+    /// Trade quality for bandwidth with a lower bitrate, fuller packets and plain panning, or stop
+    LowBandwidth,
+    /// Hear only this participant, or everyone again with `None`
+    Solo(Option<String>),
+    /// Measure the microphone and room to set gain, noise gate and AGC
+    Calibrate,
     Settings,
+    /// A setting was changed on the settings screen and should take effect
+    ApplySettings(Box<Config>),
+    /// The settings screen was closed; its configuration should be saved
+    CloseSettings(Box<Config>),
+    /// Each of these servers should be tested and the results passed to `set_ice_result`
+    TestIceServers(IceServerList),
+    /// Show or hide the connection details of a participant
+    Inspect,
+    /// Show or hide our recent host actions
+    HostActions,
+    /// Show or hide the session's timeline of who came and went
+    Timeline,
+    /// Write the session report, with its timeline, next to the log
+    ExportTimeline,
+    /// Take this host action again (host only)
+    ReplayHostAction(u64),
+    /// Reverse this host action, where it can be (host only)
+    UndoHostAction(u64),
+    /// Play the spatial preview voice walking to this direction, or stop it with `None`
+    SpatialPreview(Option<audio::PreviewDirection>),
     TestSession,
+    OpenLog,
     Quit,
 }
 
@@ -59,6 +133,8 @@ struct Notification {
     message: String,
     start_time: Instant,
     duration: Duration,
+    /// Shown in warning colours, for things the user must not miss
+    warning: bool,
 }
 
 /// Represents a text input popup
@@ -77,11 +153,61 @@ pub struct TerminalUI {
     menu_items: Vec<MenuItem>,
     menu_state: ListState,
     participants: Arc<Mutex<Vec<Participant>>>,
+    // Latest presence for each participant, by name
+    presence: HashMap<String, PresenceStatus>,
+    // Names allowed to speak while the host has muted everyone, or `None` when nobody is muted
+    meeting_speakers: Option<HashSet<String>>,
+    // Names of participants asking to speak
+    raised_hands: HashSet<String>,
+    // Peer ids of participants last heard above a murmur
+    speaking: HashSet<String>,
     audio_visualizer: AudioVisualizationWidget,
     connection_link: Arc<Mutex<Option<String>>>,
     notification: Option<Notification>,
     clipboard: Option<ClipboardContext>,
     text_input: Option<TextInput>,
+    log_file: Option<PathBuf>,
+    // Addresses included in the link shown and copied by default
+    link_privacy: LinkPrivacy,
+    // A shareable link and its shortened form, used while it's still the one shared
+    short_link: Option<(String, String)>,
+    muted: bool,
+    // In "be right back" mode
+    away: bool,
+    // Join requests and notification sounds held back
+    do_not_disturb: bool,
+    // Names of everyone recording the room, in the order they started
+    recorders: Vec<String>,
+    // Sending at a lower bitrate with fuller packets, and panning plainly
+    low_bandwidth: bool,
+    // Peers waiting for us to let them in, in order
+    join_queue: Vec<QueuedJoin>,
+    // Traffic against the room's caps, shown while a cap is set
+    bandwidth: Option<BandwidthUsage>,
+    // The room's traffic, loss and round trips, while in a session
+    room_stats: Option<RoomStats>,
+    // Participant heard alone, if any
+    solo: Option<String>,
+    session_timer: Option<SessionTimer>,
+    // Settings screen, while it's open
+    settings: Option<SettingsForm>,
+    // Connection inspector, while it's open
+    inspector: Option<PeerInspector>,
+    // Our recent host actions, while they're shown
+    action_history: Option<ActionHistory>,
+    // The session's timeline, while it's shown
+    timeline: Option<TimelineView>,
+    // Startup checks that need attention, until a key is pressed
+    health: Option<HealthChecklist>,
+    accessibility: Accessibility,
+    // When the screen last started flashing for a join request
+    flash_started: Option<Instant>,
+}
+
+impl Default for TerminalUI {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl TerminalUI {
@@ -102,11 +228,35 @@ impl TerminalUI {
             menu_items,
             menu_state,
             participants: Arc::new(Mutex::new(Vec::new())),
+            presence: HashMap::new(),
+            meeting_speakers: None,
+            raised_hands: HashSet::new(),
+            speaking: HashSet::new(),
             audio_visualizer: AudioVisualizationWidget::new(),
             connection_link: Arc::new(Mutex::new(None)),
             notification: None,
             clipboard,
             text_input: None,
+            log_file: None,
+            link_privacy: LinkPrivacy::default(),
+            short_link: None,
+            muted: false,
+            away: false,
+            do_not_disturb: false,
+            recorders: Vec::new(),
+            low_bandwidth: false,
+            join_queue: Vec::new(),
+            bandwidth: None,
+            room_stats: None,
+            solo: None,
+            session_timer: None,
+            settings: None,
+            inspector: None,
+            action_history: None,
+            timeline: None,
+            health: None,
+            accessibility: Accessibility::default(),
+            flash_started: None,
         }
     }
 
@@ -144,11 +294,294 @@ impl TerminalUI {
     }
 
     /// Updates the list of participants
-    pub fn update_participants(&self, participants: Vec<Participant>) {
+    pub fn update_participants(&self, mut participants: Vec<Participant>) {
+        for participant in &mut participants {
+            if let Some(status) = self.presence.get(&participant.name) {
+                participant.presence = *status;
+            }
+            participant.hand_raised = self.raised_hands.contains(&participant.name);
+            participant.is_speaking = self.speaking.contains(&participant.id);
+            participant.muted_by_host = self.is_muted_by_host(&participant.name);
+        }
+
         let mut lock = self.participants.lock().unwrap();
         *lock = participants;
     }
 
+    /// Records a participant's presence, applied on the next participant update
+    pub fn set_presence(&mut self, name: &str, status: PresenceStatus) {
+        self.presence.insert(name.to_string(), status);
+    }
+
+    /// Records how loud a peer was just now, shown as speaking on the next participant update
+    pub fn set_audio_level(&mut self, peer_id: &str, level: f32) {
+        if level >= SPEAKING_LEVEL {
+            self.speaking.insert(peer_id.to_string());
+        } else {
+            self.speaking.remove(peer_id);
+        }
+    }
+
+    /// Shows everyone but `host` as muted by the host, or nobody
+    pub fn set_meeting_mode(&mut self, enabled: bool, host: &str) {
+        self.meeting_speakers = enabled.then(|| HashSet::from([host.to_string()]));
+        self.raised_hands.clear();
+    }
+
+    /// Shows a participant as asking to speak, or not
+    pub fn set_hand_raised(&mut self, name: &str, raised: bool) {
+        if raised {
+            self.raised_hands.insert(name.to_string());
+        } else {
+            self.raised_hands.remove(name);
+        }
+    }
+
+    /// Shows a participant as allowed to speak while others are muted
+    pub fn grant_speaker(&mut self, name: &str) {
+        if let Some(speakers) = &mut self.meeting_speakers {
+            speakers.insert(name.to_string());
+        }
+        self.raised_hands.remove(name);
+    }
+
+    fn is_muted_by_host(&self, name: &str) -> bool {
+        self.meeting_speakers
+            .as_ref()
+            .is_some_and(|speakers| !speakers.contains(name))
+    }
+
+    /// Shows whether the microphone is muted
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
+
+    /// Shows whether we're in "be right back" mode
+    pub fn set_away(&mut self, away: bool) {
+        self.away = away;
+    }
+
+    /// Shows whether do-not-disturb is on
+    pub fn set_do_not_disturb(&mut self, enabled: bool) {
+        self.do_not_disturb = enabled;
+    }
+
+    /// Whether do-not-disturb is on, so notification sounds stay quiet
+    pub fn do_not_disturb(&self) -> bool {
+        self.do_not_disturb
+    }
+
+    /// Shows whether low-bandwidth mode is on
+    pub fn set_low_bandwidth(&mut self, enabled: bool) {
+        self.low_bandwidth = enabled;
+    }
+
+    /// Sets how speaking, join requests and contrast are shown
+    pub fn set_accessibility(&mut self, accessibility: Accessibility) {
+        self.accessibility = accessibility;
+    }
+
+    /// Whether a join-request flash lights the screen right now
+    pub fn flashing(&self) -> bool {
+        self.flash_started
+            .is_some_and(|started| accessibility::flash_lit(started.elapsed()))
+    }
+
+    /// Shows who is recording the room, for as long as anyone is
+    pub fn set_recorders(&mut self, recorders: Vec<String>) {
+        self.recorders = recorders;
+    }
+
+    /// Shows the peers waiting to join, telling the host when someone new is in line
+    pub fn set_join_queue(&mut self, queue: Vec<QueuedJoin>) {
+        let newcomers: Vec<&str> = queue
+            .iter()
+            .filter(|queued| {
+                !self
+                    .join_queue
+                    .iter()
+                    .any(|known| known.peer_id == queued.peer_id)
+            })
+            .map(|queued| queued.name.as_str())
+            .collect();
+        if !newcomers.is_empty() && self.accessibility.flash_join_requests && !self.do_not_disturb {
+            self.flash_started = Some(Instant::now());
+        }
+        match newcomers.as_slice() {
+            [] => {}
+            [name] if queue.len() == 1 => self.show_notification(
+                t_args("notify.join_requested", &[name]),
+                Duration::from_secs(5),
+            ),
+            _ => {
+                let names: Vec<String> = queue
+                    .iter()
+                    .map(|queued| format!("{}. {}", queued.position, queued.name))
+                    .collect();
+                self.show_notification(
+                    t_args("notify.join_queue", &[&names.join(", ")]),
+                    Duration::from_secs(5),
+                )
+            }
+        }
+        self.join_queue = queue;
+    }
+
+    /// Shows traffic against the room's bandwidth caps
+    ///
+    /// Warns when upload or download becomes saturated, once each time.
+    pub fn set_bandwidth(&mut self, usage: BandwidthUsage) {
+        let previous = self.bandwidth.unwrap_or_default();
+        if usage.upload_saturated() && !previous.upload_saturated() {
+            let cap = usage.caps.upload_kbps.unwrap_or_default();
+            self.show_warning(
+                t_args("notify.upload_saturated", &[&cap]),
+                Duration::from_secs(4),
+            );
+        } else if usage.download_saturated() && !previous.download_saturated() {
+            let cap = usage.caps.download_kbps.unwrap_or_default();
+            self.show_warning(
+                t_args("notify.download_saturated", &[&cap]),
+                Duration::from_secs(4),
+            );
+        }
+
+        let capped = usage.caps.upload_kbps.is_some() || usage.caps.download_kbps.is_some();
+        self.bandwidth = capped.then_some(usage);
+    }
+
+    /// Adds the latest second of the room's traffic to the stats beside the
+    /// status bar, or hides them with `None` outside a session
+    pub fn set_network_stats(&mut self, stats: Option<NetworkStats>) {
+        match stats {
+            Some(stats) => self
+                .room_stats
+                .get_or_insert_with(RoomStats::new)
+                .push(stats),
+            None => self.room_stats = None,
+        }
+    }
+
+    /// Shows which participant is soloed, if any
+    pub fn set_solo(&mut self, participant: Option<String>) {
+        self.solo = participant;
+    }
+
+    /// Action for solo key `n` (1-9): solos the nth other participant, or ends the solo if it's theirs
+    fn solo_action(&self, n: usize) -> Option<MenuAction> {
+        let participants = self.participants.lock().unwrap();
+        let name = participants
+            .iter()
+            .filter(|participant| participant.name != "Me")
+            .nth(n.checked_sub(1)?)?
+            .name
+            .clone();
+
+        if self.solo.as_deref() == Some(name.as_str()) {
+            Some(MenuAction::Solo(None))
+        } else {
+            Some(MenuAction::Solo(Some(name)))
+        }
+    }
+
+    /// Shows time in the session, and time left if it has a limit
+    pub fn set_session_timer(&mut self, timer: Option<SessionTimer>) {
+        self.session_timer = timer;
+    }
+
+    /// Opens the settings screen over everything else
+    pub fn open_settings(&mut self, form: SettingsForm) {
+        self.settings = Some(form);
+    }
+
+    pub fn is_settings_open(&self) -> bool {
+        self.settings.is_some()
+    }
+
+    /// Shows how testing an ICE server went, if the settings screen is still open
+    pub fn set_ice_result(&mut self, server: IceServer, result: IceTestResult) {
+        if let Some(form) = self.settings.as_mut() {
+            form.set_ice_result(server, result);
+        }
+    }
+
+    /// Participant whose connection the inspector shows, while it's open
+    pub fn inspected_peer(&self) -> Option<String> {
+        self.inspector
+            .as_ref()
+            .map(|inspector| inspector.peer().to_string())
+    }
+
+    /// Shows the latest details of the inspected connection and jitter buffer
+    pub fn set_inspector_details(
+        &mut self,
+        details: Option<ConnectionDetails>,
+        jitter: Option<audio::JitterStats>,
+    ) {
+        if let Some(inspector) = self.inspector.as_mut() {
+            inspector.set_details(details, jitter);
+        }
+    }
+
+    /// Shows the startup checks until any key is pressed
+    pub fn show_health_checks(&mut self, checks: Vec<HealthCheck>) {
+        self.health = Some(HealthChecklist::new(checks));
+    }
+
+    /// Whether our recent host actions are shown, and so need keeping current
+    pub fn showing_host_actions(&self) -> bool {
+        self.action_history.is_some()
+    }
+
+    /// Shows our host actions as they now stand
+    pub fn set_host_actions(&mut self, actions: Vec<RecordedAction>) {
+        if let Some(history) = self.action_history.as_mut() {
+            history.set_actions(actions);
+        }
+    }
+
+    /// Whether the session timeline is shown, and so needs keeping current
+    pub fn showing_timeline(&self) -> bool {
+        self.timeline.is_some()
+    }
+
+    /// Shows the session timeline as it now stands
+    pub fn set_timeline(&mut self, entries: Vec<TimelineEntry>) {
+        if let Some(timeline) = self.timeline.as_mut() {
+            timeline.set_entries(entries);
+        }
+    }
+
+    /// Opens the inspector on the soloed participant, or the first other
+    /// one; closes it if it's open
+    fn toggle_inspector(&mut self) {
+        if self.inspector.take().is_some() {
+            return;
+        }
+        let peer = self.solo.clone().or_else(|| self.next_peer_after(None));
+        match peer {
+            Some(peer) => self.inspector = Some(PeerInspector::new(peer)),
+            None => self.show_notification(
+                t("notify.inspector_no_peers").to_string(),
+                Duration::from_secs(2),
+            ),
+        }
+    }
+
+    /// The other participant listed after `current`, wrapping round; the first with `None`
+    fn next_peer_after(&self, current: Option<&str>) -> Option<String> {
+        let participants = self.participants.lock().unwrap();
+        let others: Vec<&str> = participants
+            .iter()
+            .map(|participant| participant.name.as_str())
+            .filter(|name| *name != "Me")
+            .collect();
+        let next = current
+            .and_then(|current| others.iter().position(|name| *name == current))
+            .map_or(0, |index| (index + 1) % others.len());
+        others.get(next).map(|name| name.to_string())
+    }
+
     /// Updates the audio visualization data
     pub fn update_audio_data(&self, data: &[f32]) {
         self.audio_visualizer.update_data(data);
@@ -160,18 +593,55 @@ impl TerminalUI {
         *lock = link;
     }
 
+    /// Sets which addresses the shown and copied link includes
+    pub fn set_link_privacy(&mut self, privacy: LinkPrivacy) {
+        self.link_privacy = privacy;
+    }
+
+    /// The link shown and copied by default, with the addresses it may share
+    pub fn shareable_link(&self) -> Option<String> {
+        let connection_link = self.connection_link.lock().unwrap().clone();
+        connection_link.and_then(|link| filter_link_candidates(&link, self.link_privacy).ok())
+    }
+
+    /// Shares `short` in place of `link` while `link` is the shareable link
+    pub fn set_short_link(&mut self, link: String, short: String) {
+        self.short_link = Some((link, short));
+    }
+
+    /// `link` as shared: shortened if it has been
+    fn shortened(&self, link: String) -> String {
+        match &self.short_link {
+            Some((full, short)) if *full == link => short.clone(),
+            _ => link,
+        }
+    }
+
+    /// Copies the connection link, keeping only the addresses `privacy` allows
+    fn copy_link(&mut self, privacy: LinkPrivacy) {
+        let connection_link = self.connection_link.lock().unwrap().clone();
+        match connection_link.map(|link| filter_link_candidates(&link, privacy)) {
+            Some(Ok(link)) => {
+                let link = self.shortened(link);
+                self.copy_to_clipboard(&link);
+            }
+            Some(Err(_)) => self.show_notification(
+                t("notify.no_shareable_address").to_string(),
+                Duration::from_secs(2),
+            ),
+            None => self.show_notification(t("notify.no_link").to_string(), Duration::from_secs(2)),
+        }
+    }
+
     /// Copy text to clipboard
     fn copy_to_clipboard(&mut self, text: &str) -> bool {
         if let Some(clipboard) = &mut self.clipboard {
             if clipboard.set_contents(text.to_owned()).is_ok() {
-                self.show_notification(
-                    "Link copied to clipboard!".to_string(),
-                    Duration::from_secs(2),
-                );
+                self.show_notification(t("notify.link_copied").to_string(), Duration::from_secs(2));
                 return true;
             }
         }
-        self.show_notification("Failed to copy link!".to_string(), Duration::from_secs(2));
+        self.show_notification(t("notify.copy_failed").to_string(), Duration::from_secs(2));
         false
     }
 
@@ -181,6 +651,17 @@ impl TerminalUI {
             message,
             start_time: Instant::now(),
             duration,
+            warning: false,
+        });
+    }
+
+    /// Show a warning that stands out from ordinary notifications
+    pub fn show_warning(&mut self, message: String, duration: Duration) {
+        self.notification = Some(Notification {
+            message,
+            start_time: Instant::now(),
+            duration,
+            warning: true,
         });
     }
 
@@ -215,7 +696,7 @@ impl TerminalUI {
 
     /// Checks if the text input is still active
     pub fn is_text_input_active(&self) -> bool {
-        self.text_input.as_ref().map_or(false, |input| input.active)
+        self.text_input.as_ref().is_some_and(|input| input.active)
     }
 
     /// Handles key events for text input
@@ -271,14 +752,88 @@ impl TerminalUI {
 
     /// Handles key events
     pub fn handle_key_event(&mut self, key: KeyCode) -> Option<MenuAction> {
+        // Any key dismisses the startup checks, before the menu takes keys
+        if self.health.take().is_some() {
+            return None;
+        }
+
+        // The settings screen takes every key while it's open
+        if let Some(form) = self.settings.as_mut() {
+            return match form.handle_key(key) {
+                SettingsEvent::None => None,
+                SettingsEvent::Changed => {
+                    Some(MenuAction::ApplySettings(Box::new(form.config().clone())))
+                }
+                SettingsEvent::Closed => {
+                    let config = form.config().clone();
+                    self.settings = None;
+                    Some(MenuAction::CloseSettings(Box::new(config)))
+                }
+                SettingsEvent::TestIceServers => Some(MenuAction::TestIceServers(
+                    form.config().ice_servers.clone(),
+                )),
+                SettingsEvent::SpatialPreview(direction) => {
+                    Some(MenuAction::SpatialPreview(direction))
+                }
+            };
+        }
+
         // If text input is active, handle that first
-        if self.text_input.is_some() {
-            if self.handle_text_input_key(key) {
-                return None;
-            }
+        if self.text_input.is_some() && self.handle_text_input_key(key) {
+            return None;
         }
 
         match key {
+            KeyCode::Up if self.action_history.is_some() => {
+                self.action_history.as_mut()?.select_newer();
+                None
+            }
+            KeyCode::Down if self.action_history.is_some() => {
+                self.action_history.as_mut()?.select_older();
+                None
+            }
+            KeyCode::Char('r') if self.action_history.is_some() => self
+                .action_history
+                .as_ref()?
+                .selected()
+                .map(MenuAction::ReplayHostAction),
+            KeyCode::Char('u') if self.action_history.is_some() => self
+                .action_history
+                .as_ref()?
+                .selected()
+                .map(MenuAction::UndoHostAction),
+            KeyCode::Esc if self.action_history.is_some() => {
+                self.action_history = None;
+                None
+            }
+            KeyCode::Up if self.timeline.is_some() => {
+                self.timeline.as_mut()?.scroll_up(1);
+                None
+            }
+            KeyCode::Down if self.timeline.is_some() => {
+                self.timeline.as_mut()?.scroll_down(1);
+                None
+            }
+            KeyCode::PageUp if self.timeline.is_some() => {
+                self.timeline.as_mut()?.scroll_up(TIMELINE_PAGE);
+                None
+            }
+            KeyCode::PageDown if self.timeline.is_some() => {
+                self.timeline.as_mut()?.scroll_down(TIMELINE_PAGE);
+                None
+            }
+            KeyCode::Char('x') if self.timeline.is_some() => Some(MenuAction::ExportTimeline),
+            KeyCode::Esc if self.timeline.is_some() => {
+                self.timeline = None;
+                None
+            }
+            KeyCode::Tab if self.inspector.is_some() => {
+                let current = self.inspected_peer();
+                if let Some(peer) = self.next_peer_after(current.as_deref()) {
+                    self.inspector = Some(PeerInspector::new(peer));
+                }
+                None
+            }
             KeyCode::Up => {
                 // Move menu selection up
                 let current = self.menu_state.selected().unwrap_or(0);
@@ -307,27 +862,78 @@ impl TerminalUI {
             KeyCode::Char('j') => Some(MenuAction::Join),
             KeyCode::Char('l') => Some(MenuAction::Leave),
             KeyCode::Char('c') => Some(MenuAction::CopyLink),
+            KeyCode::Char('C') => Some(MenuAction::CopyLocalLink),
+            KeyCode::Char('m') => Some(MenuAction::ToggleMute),
+            KeyCode::Char('b') => Some(MenuAction::BeRightBack),
+            KeyCode::Char('a') => Some(MenuAction::CycleLayout),
+            KeyCode::Char('M') => Some(MenuAction::MuteAll),
+            KeyCode::Char('e') => Some(MenuAction::EchoBot),
+            KeyCode::Char('i') => Some(MenuAction::Inspect),
+            KeyCode::Char('H') => Some(MenuAction::HostActions),
+            KeyCode::Char('T') => Some(MenuAction::Timeline),
+            KeyCode::F(12) => Some(MenuAction::CycleImpairment),
+            KeyCode::Char('h') => Some(MenuAction::RaiseHand),
+            KeyCode::Char('g') => Some(MenuAction::GrantSpeak),
+            KeyCode::Char('r') => Some(MenuAction::Record),
+            KeyCode::Char('y') if !self.join_queue.is_empty() => Some(MenuAction::ApproveAll),
+            KeyCode::Char('n') if !self.join_queue.is_empty() => Some(MenuAction::DenyAll),
+            KeyCode::Char('d') => Some(MenuAction::DoNotDisturb),
+            KeyCode::Char('B') => Some(MenuAction::LowBandwidth),
+            KeyCode::Char('k') => Some(MenuAction::Calibrate),
             KeyCode::Char('s') => Some(MenuAction::Settings),
             KeyCode::Char('t') => Some(MenuAction::TestSession),
+            KeyCode::Char('o') => Some(MenuAction::OpenLog),
+            KeyCode::Char('0') => Some(MenuAction::Solo(None)),
+            KeyCode::Char(c @ '1'..='9') => self.solo_action(c as usize - '0' as usize),
             _ => None,
         }
     }
 
+    /// Sets the log file opened by the OpenLog action
+    pub fn set_log_file(&mut self, path: Option<PathBuf>) {
+        self.log_file = path;
+    }
+
     /// Handle menu actions
     pub fn handle_menu_action(&mut self, action: MenuAction) -> bool {
         match action {
+            MenuAction::OpenLog => {
+                let message = match &self.log_file {
+                    Some(path) => match logging::reveal(path) {
+                        Ok(()) => t_args("notify.log_opened", &[&path.display()]),
+                        Err(_) => t_args("notify.log_file", &[&path.display()]),
+                    },
+                    None => t("notify.logging_disabled").to_string(),
+                };
+                self.show_notification(message, Duration::from_secs(3));
+                true // Nothing else to do for this action
+            }
             MenuAction::CopyLink => {
-                let connection_link = self.connection_link.lock().unwrap().clone();
-                if let Some(link) = connection_link {
-                    self.copy_to_clipboard(&link);
-                } else {
-                    self.show_notification(
-                        "No active link to copy".to_string(),
-                        Duration::from_secs(2),
-                    );
-                }
+                self.copy_link(self.link_privacy);
                 false // Don't exit after copying
             }
+            MenuAction::CopyLocalLink => {
+                self.copy_link(LinkPrivacy::LOCAL);
+                false
+            }
+            MenuAction::Inspect => {
+                self.toggle_inspector();
+                true
+            }
+            MenuAction::HostActions => {
+                self.action_history = match self.action_history.take() {
+                    Some(_) => None,
+                    None => Some(ActionHistory::new()),
+                };
+                true
+            }
+            MenuAction::Timeline => {
+                self.timeline = match self.timeline.take() {
+                    Some(_) => None,
+                    None => Some(TimelineView::new()),
+                };
+                true
+            }
             _ => false, // Let other actions be handled externally
         }
     }
@@ -345,15 +951,40 @@ impl TerminalUI {
         // Update notification state
         self.update_notification();
 
+        let muted_by_host = self.is_muted_by_host("Me");
+        let hand_raised = self.raised_hands.contains("Me");
+        let connection_link = self
+            .connection_link
+            .lock()
+            .unwrap()
+            .clone()
+            .map(|link| filter_link_candidates(&link, self.link_privacy).unwrap_or(link))
+            .map(|link| self.shortened(link));
+        let flash_lit = self.flashing();
         if let Some(terminal) = self.terminal.as_mut() {
             // Create local copies of all the data we need
             let menu_items = self.menu_items.clone();
             let mut menu_state = self.menu_state.clone();
             let participants = self.participants.lock().unwrap().clone();
-            let connection_link = self.connection_link.lock().unwrap().clone();
+            let muted = self.muted;
+            let away = self.away;
+            let do_not_disturb = self.do_not_disturb;
+            let recorders = self.recorders.join(", ");
+            let low_bandwidth = self.low_bandwidth;
+            let waiting = self.join_queue.len();
+            let bandwidth = self.bandwidth;
+            let room_stats = self.room_stats;
+            let solo = self.solo.clone();
+            let session_timer = self.session_timer.clone();
             let audio_visualizer = self.audio_visualizer.clone();
             let notification = self.notification.clone();
             let text_input = self.text_input.clone();
+            let settings = self.settings.clone();
+            let inspector = self.inspector.clone();
+            let action_history = self.action_history.clone();
+            let timeline = self.timeline.clone();
+            let health = self.health.clone();
+            let accessibility = self.accessibility;
 
             terminal.draw(|frame| {
                 let area = frame.size();
@@ -402,66 +1033,226 @@ impl TerminalUI {
                     .collect();
 
                 let menu = List::new(menu_items)
-                    .block(Block::default().title("Menu").borders(Borders::ALL))
+                    .block(
+                        Block::default()
+                            .title(t("panel.menu"))
+                            .borders(Borders::ALL),
+                    )
                     .highlight_style(Style::default().fg(Color::Yellow));
 
                 frame.render_stateful_widget(menu, layout.menu_area, &mut menu_state);
 
                 // Render participants list (top right)
+                // Others are numbered with their solo key
+                let mut solo_key = 0;
                 let participant_items: Vec<ListItem> = participants
                     .iter()
                     .map(|p| {
-                        let style = if p.is_speaking {
-                            Style::default().fg(Color::Green)
+                        let silenced = solo.as_ref().is_some_and(|solo| *solo != p.name);
+                        let style = if silenced {
+                            Style::default().fg(Color::DarkGray)
+                        } else if p.is_speaking {
+                            accessibility.speaking_style()
                         } else {
                             Style::default().fg(Color::White)
                         };
 
-                        ListItem::new(Line::from(vec![Span::styled(&p.name, style)]))
+                        let mut spans = Vec::new();
+                        if p.name != "Me" {
+                            solo_key += 1;
+                            if solo_key <= 9 {
+                                spans.push(Span::styled(
+                                    format!("{} ", solo_key),
+                                    Style::default().fg(Color::DarkGray),
+                                ));
+                            }
+                        }
+                        spans.extend(p.display_spans(style));
+                        if p.is_speaking && !silenced {
+                            spans.extend(accessibility.speaking_marker());
+                        }
+                        if solo.as_deref() == Some(p.name.as_str()) {
+                            spans.push(Span::styled(
+                                format!(" ({})", t("status.solo_marker")),
+                                Style::default().fg(Color::Yellow),
+                            ));
+                        }
+
+                        ListItem::new(Line::from(spans))
                     })
                     .collect();
 
-                let participant_list = List::new(participant_items)
-                    .block(Block::default().title("Participants").borders(Borders::ALL));
+                let participant_list = List::new(participant_items).block(
+                    Block::default()
+                        .title(t("panel.participants"))
+                        .borders(Borders::ALL),
+                );
 
                 frame.render_widget(participant_list, layout.participants_area);
+                accessibility.apply_contrast(frame.buffer_mut(), layout.participants_area);
 
                 // Render audio visualization (bottom)
                 frame.render_widget(audio_visualizer, layout.audio_area);
 
                 // Render status bar (very bottom - connection link and status)
-                let status_text = match &connection_link {
-                    Some(link) => {
-                        format!("Join Link: {} (Press 'c' to copy)", link)
-                    }
-                    None => "Not connected - use Join to create a session".to_string(),
+                let mut status_text = match &connection_link {
+                    Some(link) => t_args("status.join_link", &[link]),
+                    None => t("status.not_connected").to_string(),
                 };
+                if let Some(timer) = &session_timer {
+                    if let Some(remaining) = timer.remaining() {
+                        status_text.insert_str(
+                            0,
+                            &t_args("status.time_left", &[&format_clock(remaining)]),
+                        );
+                    }
+                    status_text.insert_str(
+                        0,
+                        &t_args("status.elapsed", &[&format_clock(timer.elapsed())]),
+                    );
+                }
+                if let Some(usage) = &bandwidth {
+                    let upload = format_rate(usage.upload_bps, usage.caps.upload_kbps);
+                    let download = format_rate(usage.download_bps, usage.caps.download_kbps);
+                    status_text.insert_str(0, &t_args("status.bandwidth", &[&upload, &download]));
+                }
+                if let Some(solo) = &solo {
+                    status_text.insert_str(0, &t_args("status.solo", &[solo]));
+                }
+                if low_bandwidth {
+                    status_text.insert_str(0, t("status.low_bandwidth"));
+                }
+                if do_not_disturb {
+                    status_text.insert_str(0, t("status.dnd"));
+                }
+                if waiting > 0 {
+                    status_text.insert_str(0, &t_args("status.join_queue", &[&waiting]));
+                }
+                if away {
+                    status_text.insert_str(0, t("status.away"));
+                } else if hand_raised {
+                    status_text.insert_str(0, t("status.hand_raised"));
+                } else if muted_by_host {
+                    status_text.insert_str(0, t("status.muted_by_host"));
+                } else if muted {
+                    status_text.insert_str(0, t("status.muted"));
+                }
+                // Always first, so nobody misses that they're being recorded
+                if !recorders.is_empty() {
+                    status_text.insert_str(0, &t_args("status.recording", &[&recorders]));
+                }
 
-                let status_bar = Paragraph::new(status_text)
-                    .style(Style::default())
-                    .block(Block::default().borders(Borders::ALL).title("Status"));
+                let status_bar = Paragraph::new(status_text).style(Style::default()).block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(t("panel.status")),
+                );
 
-                frame.render_widget(status_bar, layout.status_bar);
+                // Room stats sit at the right of the status bar while in a session
+                let mut status_area = layout.status_bar;
+                if let Some(stats) = room_stats {
+                    let split = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints([Constraint::Min(20), Constraint::Length(56)])
+                        .split(layout.status_bar);
+                    status_area = split[0];
+                    frame.render_widget(stats, split[1]);
+                }
+                frame.render_widget(status_bar, status_area);
+
+                if let Some(form) = settings {
+                    let width = area.width.saturating_sub(4).min(70);
+                    let height = area.height.saturating_sub(2).min(14);
+                    let settings_area = Rect::new(
+                        (area.width - width) / 2,
+                        (area.height - height) / 2,
+                        width,
+                        height,
+                    );
+                    frame.render_widget(form, settings_area);
+                }
+
+                if let Some(inspector) = inspector {
+                    let width = area.width.saturating_sub(4).min(76);
+                    let height = area.height.saturating_sub(2).min(20);
+                    let inspector_area = Rect::new(
+                        (area.width - width) / 2,
+                        (area.height - height) / 2,
+                        width,
+                        height,
+                    );
+                    frame.render_widget(inspector, inspector_area);
+                }
+
+                if let Some(history) = action_history {
+                    let width = area.width.saturating_sub(4).min(60);
+                    let height = area.height.saturating_sub(2).min(16);
+                    let history_area = Rect::new(
+                        (area.width - width) / 2,
+                        (area.height - height) / 2,
+                        width,
+                        height,
+                    );
+                    frame.render_widget(history, history_area);
+                }
+
+                if let Some(timeline) = timeline {
+                    let width = area.width.saturating_sub(4).min(64);
+                    let height = area.height.saturating_sub(2).min(24);
+                    let timeline_area = Rect::new(
+                        (area.width - width) / 2,
+                        (area.height - height) / 2,
+                        width,
+                        height,
+                    );
+                    frame.render_widget(timeline, timeline_area);
+                }
+
+                if let Some(health) = health {
+                    let width = area.width.saturating_sub(4).min(72);
+                    let height = area.height.saturating_sub(2).min(health.height() + 2);
+                    let health_area = Rect::new(
+                        (area.width - width) / 2,
+                        (area.height - height) / 2,
+                        width,
+                        height,
+                    );
+                    frame.render_widget(health, health_area);
+                }
 
                 // If there's an active notification, render it as an overlay
                 if let Some(notif) = notification {
                     // Create a centered popup for the notification
-                    let notif_width = notif.message.len() as u16 + 4; // Add padding
+                    let notif_width = (notif.message.len() as u16 + 4).min(area.width); // Add padding
                     let notif_height = 3;
                     let notif_x = (area.width - notif_width) / 2;
                     let notif_y = (area.height - notif_height) / 2;
 
                     let notif_area = Rect::new(notif_x, notif_y, notif_width, notif_height);
 
-                    let notification_widget = Paragraph::new(notif.message)
-                        .style(Style::default().fg(Color::White))
-                        .block(
+                    let (style, block) = if notif.warning {
+                        (
+                            Style::default()
+                                .fg(Color::White)
+                                .add_modifier(Modifier::BOLD),
+                            Block::default()
+                                .borders(Borders::ALL)
+                                .title(t("panel.warning"))
+                                .style(Style::default().bg(Color::Red)),
+                        )
+                    } else {
+                        (
+                            Style::default().fg(Color::White),
                             Block::default()
                                 .borders(Borders::ALL)
                                 .style(Style::default().bg(Color::DarkGray)),
-                        );
+                        )
+                    };
+                    let notification_widget =
+                        Paragraph::new(notif.message).style(style).block(block);
 
                     frame.render_widget(notification_widget, notif_area);
+                    accessibility.apply_contrast(frame.buffer_mut(), notif_area);
                 }
 
                 // Render text input if active
@@ -481,7 +1272,7 @@ impl TerminalUI {
                         .block(
                             Block::default()
                                 .borders(Borders::ALL)
-                                .title("Enter Session Link")
+                                .title(t("panel.enter_link"))
                                 .style(Style::default().bg(Color::Black)),
                         );
 
@@ -498,6 +1289,10 @@ impl TerminalUI {
                         frame.set_cursor(cursor_x, cursor_y);
                     }
                 }
+
+                if flash_lit {
+                    accessibility::flash(frame.buffer_mut(), area);
+                }
             })?;
         }
         Ok(())
@@ -509,19 +1304,27 @@ impl TerminalUI {
             // In a session menu options
             self.menu_items = vec![
                 MenuItem {
-                    label: "Leave Session".to_string(),
+                    label: t("menu.leave").to_string(),
                     action: MenuAction::Leave,
                 },
                 MenuItem {
-                    label: "Copy Link".to_string(),
+                    label: t("menu.copy_link").to_string(),
                     action: MenuAction::CopyLink,
                 },
                 MenuItem {
-                    label: "Settings".to_string(),
+                    label: t("menu.copy_local_link").to_string(),
+                    action: MenuAction::CopyLocalLink,
+                },
+                MenuItem {
+                    label: t("menu.toggle_mute").to_string(),
+                    action: MenuAction::ToggleMute,
+                },
+                MenuItem {
+                    label: t("menu.settings").to_string(),
                     action: MenuAction::Settings,
                 },
                 MenuItem {
-                    label: "Quit".to_string(),
+                    label: t("menu.quit").to_string(),
                     action: MenuAction::Quit,
                 },
             ];
@@ -529,19 +1332,19 @@ impl TerminalUI {
             // Not in a session menu options
             self.menu_items = vec![
                 MenuItem {
-                    label: "Create Session".to_string(),
+                    label: t("menu.create").to_string(),
                     action: MenuAction::Create,
                 },
                 MenuItem {
-                    label: "Join Session".to_string(),
+                    label: t("menu.join").to_string(),
                     action: MenuAction::Join,
                 },
                 MenuItem {
-                    label: "Settings".to_string(),
+                    label: t("menu.settings").to_string(),
                     action: MenuAction::Settings,
                 },
                 MenuItem {
-                    label: "Quit".to_string(),
+                    label: t("menu.quit").to_string(),
                     action: MenuAction::Quit,
                 },
             ];
@@ -559,10 +1362,19 @@ impl TerminalUI {
     }
 }
 
+/// Formats a rate in kilobits per second, against its cap if there is one
+fn format_rate(bits_per_second: u64, cap_kbps: Option<u32>) -> String {
+    let kbps = bits_per_second / 1000;
+    match cap_kbps {
+        Some(cap) => format!("{}/{} kbps", kbps, cap),
+        None => format!("{} kbps", kbps),
+    }
+}
+
 use std::sync::atomic::{AtomicBool, Ordering};
 
 /// Run the TUI application
-pub async fn run_tui(app: Arc<Mutex<App>>) -> io::Result<()> {
+pub async fn run_tui(app: Arc<tokio::sync::Mutex<App>>) -> io::Result<()> {
     // Initialize terminal
     let mut terminal_ui = TerminalUI::new();
     terminal_ui.initialize()?;
@@ -578,68 +1390,91 @@ pub async fn run_tui(app: Arc<Mutex<App>>) -> io::Result<()> {
             .unwrap_or_else(|| Duration::from_secs(0));
 
         // Poll for events
-        if let Some(event) = terminal_ui.poll_events(timeout)? {
-            match event {
-                Event::Key(key_event) => {
-                    // Handle Ctrl+C for exit
-                    if key_event.modifiers.contains(KeyModifiers::CONTROL)
-                        && key_event.code == KeyCode::Char('c')
-                    {
-                        break;
-                    }
+        if let Some(Event::Key(key_event)) = terminal_ui.poll_events(timeout)? {
+            // Handle Ctrl+C for exit
+            if key_event.modifiers.contains(KeyModifiers::CONTROL)
+                && key_event.code == KeyCode::Char('c')
+            {
+                break;
+            }
 
-                    if let Some(action) = terminal_ui.handle_key_event(key_event.code) {
-                        // First handle internal actions like copy
-                        if terminal_ui.handle_menu_action(action.clone()) {
-                            continue;
-                        }
+            if let Some(action) = terminal_ui.handle_key_event(key_event.code) {
+                // First handle internal actions like copy
+                if terminal_ui.handle_menu_action(action.clone()) {
+                    continue;
+                }
 
-                        let mut app_lock = app.lock().unwrap();
-                        match action {
-                            MenuAction::Create => {
-                                // Create a new session
-                                if let Ok(session) = app_lock.create_p2p_session().await {
-                                    terminal_ui
-                                        .set_connection_link(Some(session.connection_link.clone()));
-                                    terminal_ui.update_participants(session.participants.clone());
-                                    // Update menu for active connection
-                                    terminal_ui.update_menu_items(true);
-                                }
-                            }
-                            MenuAction::Join => {
-                                // For this example, we'll just create a new session
-                                // In a real implementation, this would prompt for a link
-                                if let Ok(session) = app_lock.create_p2p_session().await {
-                                    terminal_ui
-                                        .set_connection_link(Some(session.connection_link.clone()));
-                                    terminal_ui.update_participants(session.participants.clone());
-                                    // Update menu for active connection
-                                    terminal_ui.update_menu_items(true);
-                                }
-                            }
-                            MenuAction::Leave => {
-                                if app_lock.has_active_connection().await {
-                                    let _ = app_lock.leave_session().await;
-                                    terminal_ui.set_connection_link(None);
-                                    terminal_ui.update_participants(vec![]);
-                                    // Update menu for no active connection
-                                    terminal_ui.update_menu_items(false);
-                                }
-                            }
-                            MenuAction::CopyLink => {
-                                // Already handled in handle_menu_action
-                            }
-                            MenuAction::Settings => {
-                                // This is handled in main.rs
-                            }
-                            MenuAction::TestSession => {
-                                // This is handled in main.rs
-                            }
-                            MenuAction::Quit => break,
+                let mut app_lock = app.lock().await;
+                match action {
+                    MenuAction::Create => {
+                        // Create a new session
+                        if let Ok(session) = app_lock.create_p2p_session().await {
+                            terminal_ui.set_connection_link(Some(session.connection_link.clone()));
+                            terminal_ui.update_participants(session.participants.clone());
+                            // Update menu for active connection
+                            terminal_ui.update_menu_items(true);
+                        }
+                    }
+                    MenuAction::Join => {
+                        // For this example, we'll just create a new session
+                        // In a real implementation, this would prompt for a link
+                        if let Ok(session) = app_lock.create_p2p_session().await {
+                            terminal_ui.set_connection_link(Some(session.connection_link.clone()));
+                            terminal_ui.update_participants(session.participants.clone());
+                            // Update menu for active connection
+                            terminal_ui.update_menu_items(true);
+                        }
+                    }
+                    MenuAction::Leave => {
+                        if app_lock.has_active_connection().await {
+                            let _ = app_lock.leave_session().await;
+                            terminal_ui.set_connection_link(None);
+                            terminal_ui.update_participants(vec![]);
+                            // Update menu for no active connection
+                            terminal_ui.update_menu_items(false);
                         }
                     }
+                    MenuAction::CopyLink | MenuAction::CopyLocalLink => {
+                        // Already handled in handle_menu_action
+                    }
+                    MenuAction::ToggleMute | MenuAction::BeRightBack | MenuAction::Solo(_) => {
+                        // Needs the audio manager, which this loop doesn't have
+                    }
+                    MenuAction::CycleLayout
+                    | MenuAction::MuteAll
+                    | MenuAction::EchoBot
+                    | MenuAction::CycleImpairment
+                    | MenuAction::RaiseHand
+                    | MenuAction::GrantSpeak
+                    | MenuAction::Record
+                    | MenuAction::ApproveAll
+                    | MenuAction::DenyAll
+                    | MenuAction::DoNotDisturb
+                    | MenuAction::LowBandwidth
+                    | MenuAction::Calibrate => {
+                        // This is handled in main.rs
+                    }
+                    MenuAction::Settings
+                    | MenuAction::ApplySettings(_)
+                    | MenuAction::CloseSettings(_)
+                    | MenuAction::TestIceServers(_)
+                    | MenuAction::SpatialPreview(_)
+                    | MenuAction::Inspect
+                    | MenuAction::HostActions
+                    | MenuAction::Timeline
+                    | MenuAction::ExportTimeline
+                    | MenuAction::ReplayHostAction(_)
+                    | MenuAction::UndoHostAction(_) => {
+                        // This is handled in main.rs
+                    }
+                    MenuAction::TestSession => {
+                        // This is handled in main.rs
+                    }
+                    MenuAction::OpenLog => {
+                        // Already handled in handle_menu_action
+                    }
+                    MenuAction::Quit => break,
                 }
-                _ => {}
             }
         }
 
@@ -647,7 +1482,7 @@ pub async fn run_tui(app: Arc<Mutex<App>>) -> io::Result<()> {
         if last_tick.elapsed() >= tick_rate {
             // Update app state
             {
-                let app_lock = app.lock().unwrap();
+                let app_lock = app.lock().await;
 
                 // Update participants if in a session
                 if let Some(session) = app_lock.current_session() {
@@ -659,7 +1494,7 @@ pub async fn run_tui(app: Arc<Mutex<App>>) -> io::Result<()> {
             }
 
             // Render UI
-            terminal_ui.render(&app.lock().unwrap())?;
+            terminal_ui.render(&*app.lock().await)?;
 
             last_tick = Instant::now();
         }
@@ -1,10 +1,10 @@
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{
-    backend::{Backend, CrosstermBackend},
+    backend::CrosstermBackend,
     layout::{Constraint, Direction, Layout, Rect},
     Terminal,
 };
@@ -23,6 +23,12 @@ pub struct TerminalUI {
     running: Arc<AtomicBool>,
 }
 
+impl Default for TerminalUI {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TerminalUI {
     pub fn new() -> Self {
         Self {
@@ -98,10 +104,7 @@ impl TerminalUI {
         if let Some(terminal) = &self.terminal {
             return terminal.size();
         }
-        Err(io::Error::new(
-            io::ErrorKind::Other,
-            "Terminal not initialized",
-        ))
+        Err(io::Error::other("Terminal not initialized"))
     }
 }
 
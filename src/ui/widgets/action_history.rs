@@ -0,0 +1,161 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Widget, Wrap},
+};
+use std::time::Instant;
+
+use crate::app::dnd::JoinRequest;
+use crate::app::host_actions::{HostAction, RecordedAction};
+use crate::app::session_timer::format_clock;
+use crate::ui::i18n::{t, t_args};
+
+/// The host's recent actions, most recent first, to take again or undo
+#[derive(Debug, Clone, Default)]
+pub struct ActionHistory {
+    // Oldest first, as the session keeps them
+    actions: Vec<RecordedAction>,
+    selected: Option<u64>,
+}
+
+impl ActionHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Shows the actions as they now stand, keeping the selection if it's still there
+    pub fn set_actions(&mut self, actions: Vec<RecordedAction>) {
+        let kept = self
+            .selected
+            .filter(|id| actions.iter().any(|recorded| recorded.id == *id));
+        self.selected = kept.or_else(|| actions.last().map(|recorded| recorded.id));
+        self.actions = actions;
+    }
+
+    /// Id of the action selected
+    pub fn selected(&self) -> Option<u64> {
+        self.selected
+    }
+
+    /// Moves the selection to an older action
+    pub fn select_older(&mut self) {
+        self.step(-1);
+    }
+
+    /// Moves the selection to a more recent action
+    pub fn select_newer(&mut self) {
+        self.step(1);
+    }
+
+    fn step(&mut self, by: isize) {
+        let Some(index) = self
+            .actions
+            .iter()
+            .position(|recorded| Some(recorded.id) == self.selected)
+        else {
+            return;
+        };
+        let index = (index as isize + by).clamp(0, self.actions.len() as isize - 1);
+        self.selected = Some(self.actions[index as usize].id);
+    }
+}
+
+/// What the host did, in the user's language
+fn describe(action: &HostAction) -> String {
+    let names = |requests: &[JoinRequest]| {
+        requests
+            .iter()
+            .map(|request| request.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    match action {
+        HostAction::DenyJoins { requests } => t_args("history.denied", &[&names(requests)]),
+        HostAction::RequeueJoins { requests } => t_args("history.requeued", &[&names(requests)]),
+        HostAction::MuteAll { enabled: true } => t("history.muted_all").to_string(),
+        HostAction::MuteAll { enabled: false } => t("history.unmuted_all").to_string(),
+        HostAction::GrantSpeak { name, .. } => t_args("history.granted", &[name]),
+    }
+}
+
+impl Widget for ActionHistory {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Clear.render(area, buf);
+
+        let now = Instant::now();
+        let mut lines = Vec::new();
+        if self.actions.is_empty() {
+            lines.push(Line::from(Span::styled(
+                t("history.empty"),
+                Style::default().fg(Color::DarkGray),
+            )));
+        }
+        for recorded in self.actions.iter().rev() {
+            let mut style = Style::default();
+            if recorded.undone {
+                style = style.fg(Color::DarkGray);
+            }
+            if Some(recorded.id) == self.selected {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            let mut spans = vec![
+                Span::styled(
+                    format!(
+                        "{:>7}  ",
+                        format_clock(now.saturating_duration_since(recorded.at))
+                    ),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::styled(describe(&recorded.action), style),
+            ];
+            if recorded.undone {
+                spans.push(Span::styled(
+                    format!(" {}", t("history.undone")),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+            lines.push(Line::from(spans));
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            t("history.hint"),
+            Style::default().fg(Color::DarkGray),
+        )));
+
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(
+                Block::default()
+                    .title(t("history.title"))
+                    .borders(Borders::ALL)
+                    .style(Style::default().bg(Color::Black)),
+            )
+            .render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::host_actions::HostActionHistory;
+
+    #[test]
+    fn test_selection_follows_actions() {
+        let mut history = HostActionHistory::new();
+        let muted = history.record(HostAction::MuteAll { enabled: true });
+        let mut widget = ActionHistory::new();
+        widget.set_actions(history.actions());
+        assert_eq!(widget.selected(), Some(muted));
+
+        let unmuted = history.record(HostAction::MuteAll { enabled: false });
+        widget.set_actions(history.actions());
+        assert_eq!(widget.selected(), Some(muted));
+        widget.select_newer();
+        widget.select_newer();
+        assert_eq!(widget.selected(), Some(unmuted));
+        widget.select_older();
+        assert_eq!(widget.selected(), Some(muted));
+    }
+}
@@ -20,6 +20,12 @@ pub struct AudioVisualizationWidget {
     history_length: usize,
 }
 
+impl Default for AudioVisualizationWidget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl AudioVisualizationWidget {
     pub fn new() -> Self {
         Self {
@@ -87,7 +93,7 @@ impl AudioVisualizationWidget {
         for i in 0..fft_input.len() {
             let window = 0.5
                 * (1.0 - (2.0 * std::f32::consts::PI * i as f32 / fft_input.len() as f32).cos());
-            fft_input[i] = fft_input[i] * window;
+            fft_input[i] *= window;
         }
 
         // Create FFT planner and run FFT
@@ -122,7 +128,7 @@ impl AudioVisualizationWidget {
 
                 // Apply non-linear curve to enhance mid-range values (speech volumes)
                 *mag = (*mag * *mag * 0.7) + (*mag * 0.3); // Blend of square curve and linear
-                *mag = mag.max(0.0).min(1.0); // Clamp to 0-1
+                *mag = mag.clamp(0.0, 1.0); // Clamp to 0-1
             }
         }
 
@@ -167,10 +173,9 @@ impl AudioVisualizationWidget {
 
                 if start < end {
                     // Use peak value in the band rather than average for better responsiveness
-                    let mut peak_magnitude = 0.0f32;
-                    for j in start..end {
-                        peak_magnitude = peak_magnitude.max(magnitudes[j]);
-                    }
+                    let peak_magnitude = magnitudes[start..end]
+                        .iter()
+                        .fold(0.0f32, |peak, &magnitude| peak.max(magnitude));
 
                     // Adjust magnitude based on perceptual importance of frequency range
                     let mut adjusted_magnitude = peak_magnitude;
@@ -184,7 +189,7 @@ impl AudioVisualizationWidget {
                         if adjusted_magnitude < 0.25 {
                             adjusted_magnitude = 0.0;
                         }
-                    } else if i >= 3 && i < 12 {
+                    } else if (3..12).contains(&i) {
                         // Vocal fundamental range (roughly 250-1200 Hz)
                         adjusted_magnitude *= 1.2;
                     } else if i >= 20 {
@@ -0,0 +1,112 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Widget, Wrap},
+};
+
+use crate::app::health::{CheckStatus, HealthCheck};
+use crate::ui::i18n::t;
+
+/// The startup checks, each with what to try if it didn't pass
+#[derive(Debug, Clone)]
+pub struct HealthChecklist {
+    checks: Vec<HealthCheck>,
+}
+
+impl HealthChecklist {
+    pub fn new(checks: Vec<HealthCheck>) -> Self {
+        Self { checks }
+    }
+
+    /// Rows the checklist needs inside its border
+    pub fn height(&self) -> u16 {
+        let hints = self
+            .checks
+            .iter()
+            .filter(|check| check.hint.is_some())
+            .count();
+        (self.checks.len() + hints + 2) as u16
+    }
+}
+
+fn marker(status: CheckStatus) -> Span<'static> {
+    match status {
+        CheckStatus::Passed => Span::styled("✓ ", Style::default().fg(Color::Green)),
+        CheckStatus::Warning => Span::styled("! ", Style::default().fg(Color::Yellow)),
+        CheckStatus::Failed => Span::styled("✗ ", Style::default().fg(Color::Red)),
+    }
+}
+
+impl Widget for HealthChecklist {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Clear.render(area, buf);
+
+        let mut lines = Vec::new();
+        for check in &self.checks {
+            let mut spans = vec![marker(check.status), Span::raw(t(check.label))];
+            if !check.detail.is_empty() {
+                spans.push(Span::styled(
+                    format!("  {}", check.detail),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+            lines.push(Line::from(spans));
+            if let Some(hint) = check.hint {
+                lines.push(Line::from(format!("  {}", t(hint))));
+            }
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            t("health.dismiss"),
+            Style::default().fg(Color::DarkGray),
+        )));
+
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(
+                Block::default()
+                    .title(t("health.title"))
+                    .borders(Borders::ALL)
+                    .style(Style::default().bg(Color::Black)),
+            )
+            .render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hints_follow_their_checks() {
+        let checklist = HealthChecklist::new(vec![
+            HealthCheck {
+                label: "health.udp",
+                status: CheckStatus::Passed,
+                detail: "0.0.0.0:40000".to_string(),
+                hint: None,
+            },
+            HealthCheck {
+                label: "health.stun",
+                status: CheckStatus::Warning,
+                detail: "timed out".to_string(),
+                hint: Some("health.hint_stun"),
+            },
+        ]);
+        assert_eq!(checklist.height(), 5);
+
+        let area = Rect::new(0, 0, 120, checklist.height() + 2);
+        let mut buf = Buffer::empty(area);
+        checklist.render(area, &mut buf);
+        let row = |y: u16| -> String {
+            (0..area.width)
+                .map(|x| buf.get(x, y).symbol.clone())
+                .collect()
+        };
+        assert!(row(1).contains("✓"));
+        assert!(row(2).contains("timed out"));
+        assert!(row(3).contains(t("health.hint_stun")));
+    }
+}
@@ -0,0 +1,220 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Widget, Wrap},
+};
+use std::time::Instant;
+
+use crate::app::session_timer::format_clock;
+use crate::audio::JitterStats;
+use crate::network::{ConnectionDetails, ConnectionState, PacketCounts};
+use crate::ui::i18n::{t, t_args};
+
+/// Events listed, most recent first; the rest are kept but not shown
+const EVENTS_SHOWN: usize = 8;
+
+/// Developer view of the connection to one peer
+///
+/// Shows the handshake, cipher and key age, the codec negotiated, the
+/// candidate pair in use, socket buffers and packets lost, the peer's
+/// jitter buffer and the connection's recent events.
+#[derive(Debug, Clone)]
+pub struct PeerInspector {
+    peer: String,
+    details: Option<ConnectionDetails>,
+    jitter: Option<JitterStats>,
+}
+
+impl PeerInspector {
+    pub fn new(peer: String) -> Self {
+        Self {
+            peer,
+            details: None,
+            jitter: None,
+        }
+    }
+
+    /// Name of the participant being inspected
+    pub fn peer(&self) -> &str {
+        &self.peer
+    }
+
+    /// Shows the latest details, or none if there's no connection to the
+    /// peer, with their jitter buffer if we've heard them
+    pub fn set_details(&mut self, details: Option<ConnectionDetails>, jitter: Option<JitterStats>) {
+        self.details = details;
+        self.jitter = jitter;
+    }
+
+    fn lines(&self, now: Instant) -> Vec<Line<'static>> {
+        let Some(details) = &self.details else {
+            return vec![Line::from(Span::styled(
+                t("inspector.no_connection"),
+                Style::default().fg(Color::DarkGray),
+            ))];
+        };
+
+        let state = match details.state {
+            ConnectionState::Disconnected => t("inspector.state_disconnected"),
+            ConnectionState::Connecting => t("inspector.state_connecting"),
+            ConnectionState::Connected => t("inspector.state_connected"),
+        };
+        let cipher = match details.cipher {
+            Some(cipher) => t_args(
+                "inspector.cipher_value",
+                &[
+                    &cipher,
+                    &details.nonce_strategy.to_string(),
+                    &if details.forward_secrecy {
+                        t("settings.on")
+                    } else {
+                        t("settings.off")
+                    },
+                ],
+            ),
+            None => t("inspector.none_yet").to_string(),
+        };
+        let key_age = details
+            .key_age
+            .map_or_else(|| t("inspector.none_yet").to_string(), format_clock);
+        let local = details
+            .local_addr
+            .map_or_else(|| "?".to_string(), |addr| addr.to_string());
+        let crypto = details.crypto;
+
+        let mut lines = vec![
+            field("inspector.handshake", state.to_string()),
+            field("inspector.cipher", cipher),
+            field("inspector.key_age", key_age),
+            field(
+                "inspector.codec",
+                t_args(
+                    "inspector.codec_value",
+                    &[&details.codec, &details.bitrate_kbps],
+                ),
+            ),
+            field(
+                "inspector.path",
+                t_args(
+                    "inspector.path_value",
+                    &[&local, &details.remote_addr, &details.max_datagram],
+                ),
+            ),
+            field(
+                "inspector.buffers",
+                details.socket_buffers.map_or_else(
+                    || "?".to_string(),
+                    |buffers| {
+                        t_args(
+                            "inspector.buffers_value",
+                            &[&(buffers.receive / 1024), &(buffers.send / 1024)],
+                        )
+                    },
+                ),
+            ),
+            field(
+                "inspector.lost",
+                t_args(
+                    "inspector.lost_value",
+                    &[
+                        &details
+                            .packets
+                            .sent
+                            .saturating_sub(details.packets.received),
+                        &details.packets.sent,
+                        &format!(
+                            "{:.1}",
+                            details.packets.loss_since(PacketCounts::default()) * 100.0
+                        ),
+                    ],
+                ),
+            ),
+            field(
+                "inspector.rejected",
+                t_args(
+                    "inspector.rejected_value",
+                    &[
+                        &crypto.authentication_failures,
+                        &crypto.malformed,
+                        &crypto.replays,
+                    ],
+                ),
+            ),
+        ];
+        if let Some(jitter) = self.jitter {
+            let millis = |duration: std::time::Duration| duration.as_millis().to_string();
+            lines.push(field(
+                "inspector.jitter",
+                t_args(
+                    "inspector.jitter_value",
+                    &[
+                        &millis(jitter.target),
+                        &jitter.achieved.map_or_else(|| "?".to_string(), millis),
+                        &millis(jitter.jitter),
+                        &format!("{:.1}", jitter.late_rate * 100.0),
+                    ],
+                ),
+            ));
+            lines.push(field(
+                "inspector.drift",
+                t_args(
+                    "inspector.drift_value",
+                    &[&format!("{:+.0}", jitter.drift * 1e6)],
+                ),
+            ));
+        }
+        lines.extend([
+            Line::from(""),
+            Line::from(Span::styled(
+                t("inspector.events"),
+                Style::default().add_modifier(Modifier::BOLD),
+            )),
+        ]);
+        for event in details.history.iter().rev().take(EVENTS_SHOWN) {
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!(
+                        "{:>7}  ",
+                        format_clock(now.saturating_duration_since(event.at))
+                    ),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::raw(event.description.clone()),
+            ]));
+        }
+        lines
+    }
+}
+
+/// A labelled value
+fn field(label: &'static str, value: String) -> Line<'static> {
+    Line::from(vec![
+        Span::styled(format!("{}: ", t(label)), Style::default().fg(Color::Cyan)),
+        Span::raw(value),
+    ])
+}
+
+impl Widget for PeerInspector {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Clear.render(area, buf);
+
+        let mut lines = self.lines(Instant::now());
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            t("inspector.hint"),
+            Style::default().fg(Color::DarkGray),
+        )));
+
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(
+                Block::default()
+                    .title(t_args("inspector.title", &[&self.peer]))
+                    .borders(Borders::ALL)
+                    .style(Style::default().bg(Color::Black)),
+            )
+            .render(area, buf);
+    }
+}
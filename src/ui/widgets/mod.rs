@@ -1,5 +1,17 @@
+mod action_history;
 mod audio_visualization;
+mod health_checklist;
+mod inspector;
 mod participant_list;
+mod room_stats;
+mod settings;
+mod timeline;
 
+pub use action_history::ActionHistory;
 pub use audio_visualization::AudioVisualizationWidget;
+pub use health_checklist::HealthChecklist;
+pub use inspector::PeerInspector;
 pub use participant_list::{Participant, ParticipantListWidget};
+pub use room_stats::RoomStats;
+pub use settings::{FieldKind, SettingsEvent, SettingsField, SettingsForm, SettingsPage};
+pub use timeline::TimelineView;
@@ -1,19 +1,69 @@
 use ratatui::{
     buffer::Buffer,
     layout::Rect,
-    style::{Color, Style},
+    style::{Color, Modifier, Style},
     text::{Line, Span},
     widgets::{Block, Borders, List, ListItem, ListState, StatefulWidget, Widget},
 };
 use std::sync::{Arc, Mutex};
 
+use crate::app::presence::PresenceStatus;
+use crate::ui::i18n::t;
+
 /// Represents a participant in the audio session
 #[derive(Clone, Debug)]
 pub struct Participant {
+    /// The peer id the session's roster knows them by, or a random one for
+    /// participants that aren't peers
     pub id: String,
     pub name: String,
     pub is_speaking: bool,
     pub position: (f32, f32, f32), // (x, y, z) position in virtual space
+    pub avatar: Option<String>,    // 1-2 character initials shown next to the name
+    pub presence: PresenceStatus,
+    /// Asking the host to let them speak
+    pub hand_raised: bool,
+    /// Muted by the host for everyone
+    pub muted_by_host: bool,
+}
+
+// Colors avatars are drawn from; green is left out as it marks speaking participants
+const AVATAR_COLORS: [Color; 10] = [
+    Color::Red,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::LightRed,
+    Color::LightYellow,
+    Color::LightBlue,
+    Color::LightMagenta,
+    Color::LightCyan,
+];
+
+/// Gets a color for a peer id that is the same on every client in the room
+pub fn avatar_color(peer_id: &str) -> Color {
+    // FNV-1a, so the result doesn't depend on the std hasher's random seed
+    let hash = peer_id.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    AVATAR_COLORS[(hash % AVATAR_COLORS.len() as u64) as usize]
+}
+
+/// Gets up to two uppercase initials from a username
+pub fn avatar_initials(name: &str) -> Option<String> {
+    let initials: String = name
+        .split_whitespace()
+        .filter_map(|word| word.chars().next())
+        .take(2)
+        .flat_map(char::to_uppercase)
+        .collect();
+
+    if initials.is_empty() {
+        None
+    } else {
+        Some(initials)
+    }
 }
 
 impl Participant {
@@ -23,13 +73,77 @@ impl Participant {
             name: name.to_string(),
             is_speaking: false,
             position: (0.0, 0.0, 0.0),
+            avatar: avatar_initials(name),
+            presence: PresenceStatus::Active,
+            hand_raised: false,
+            muted_by_host: false,
         }
     }
 
+    /// Ties the participant to the peer the roster knows by `peer_id`
+    pub fn with_id(mut self, peer_id: &str) -> Self {
+        self.id = peer_id.to_string();
+        self
+    }
+
     pub fn with_position(mut self, x: f32, y: f32, z: f32) -> Self {
         self.position = (x, y, z);
         self
     }
+
+    /// Gets this participant's avatar color, from their peer id
+    pub fn color(&self) -> Color {
+        avatar_color(&self.id)
+    }
+
+    /// Builds the styled name shown in participant lists: avatar, then name
+    pub fn display_spans(&self, name_style: Style) -> Vec<Span<'_>> {
+        let mut spans = Vec::new();
+        if let Some(avatar) = &self.avatar {
+            spans.push(Span::styled(
+                format!("[{}]", avatar),
+                Style::default().fg(self.color()),
+            ));
+            spans.push(Span::raw(" "));
+        }
+
+        // Quiet participants are dimmed and labelled
+        match self.presence {
+            PresenceStatus::Active => spans.push(Span::styled(&self.name, name_style)),
+            PresenceStatus::Idle => {
+                spans.push(Span::styled(&self.name, Style::default().fg(Color::Gray)));
+                spans.push(Span::styled(
+                    format!(" ({})", t("presence.idle")),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+            PresenceStatus::Away => {
+                spans.push(Span::styled(
+                    &self.name,
+                    Style::default()
+                        .fg(Color::DarkGray)
+                        .add_modifier(Modifier::ITALIC),
+                ));
+                spans.push(Span::styled(
+                    format!(" ({})", t("presence.away")),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+        }
+
+        if self.hand_raised {
+            spans.push(Span::styled(
+                format!(" ({})", t("meeting.hand_raised")),
+                Style::default().fg(Color::Yellow),
+            ));
+        } else if self.muted_by_host {
+            spans.push(Span::styled(
+                format!(" ({})", t("meeting.muted")),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        spans
+    }
 }
 
 #[derive(Clone)]
@@ -38,6 +152,12 @@ pub struct ParticipantListWidget {
     state: ListState,
 }
 
+impl Default for ParticipantListWidget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ParticipantListWidget {
     pub fn new() -> Self {
         Self {
@@ -119,11 +239,10 @@ impl StatefulWidget for ParticipantListWidget {
                     p.position.0, p.position.1, p.position.2
                 );
 
-                let line = Line::from(vec![
-                    Span::styled(&p.name, style),
-                    Span::raw(" "),
-                    Span::styled(pos_text, Style::default().fg(Color::DarkGray)),
-                ]);
+                let mut spans = p.display_spans(style);
+                spans.push(Span::raw(" "));
+                spans.push(Span::styled(pos_text, Style::default().fg(Color::DarkGray)));
+                let line = Line::from(spans);
 
                 ListItem::new(line)
             })
@@ -160,6 +279,23 @@ mod tests {
         assert_eq!(participants[0].name, displayed[0].name);
     }
 
+    #[test]
+    fn test_avatar_metadata() {
+        assert_eq!(avatar_initials("ada lovelace"), Some("AL".to_string()));
+        assert_eq!(avatar_initials("Me"), Some("M".to_string()));
+        assert_eq!(avatar_initials("   "), None);
+
+        // Colors depend only on the peer id, not on the name or who made the participant
+        let participant = Participant::new("User1").with_id("peer-1");
+        assert_eq!(participant.color(), avatar_color("peer-1"));
+        assert_eq!(
+            Participant::new("Alias for User1")
+                .with_id("peer-1")
+                .color(),
+            participant.color()
+        );
+    }
+
     #[test]
     fn test_participant_selection() {
         let mut widget = ParticipantListWidget::new();
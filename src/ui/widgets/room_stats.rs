@@ -0,0 +1,246 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Style},
+    symbols::bar,
+    widgets::{Block, Borders, Widget},
+};
+
+use crate::network::{NetworkStats, PacketCounts};
+use crate::ui::i18n::{t, t_args};
+
+/// Seconds of loss and round trips the sparklines cover
+const HISTORY: usize = 60;
+
+/// Sparkline columns, from the lowest to the highest
+const LEVELS: [&str; 8] = [
+    bar::ONE_EIGHTH,
+    bar::ONE_QUARTER,
+    bar::THREE_EIGHTHS,
+    bar::HALF,
+    bar::FIVE_EIGHTHS,
+    bar::THREE_QUARTERS,
+    bar::SEVEN_EIGHTHS,
+    bar::FULL,
+];
+
+/// Loss, in hundredths of a percent, and round trip, in milliseconds, that
+/// fill a sparkline when nothing worse was seen
+const LOSS_SCALE: u16 = 500;
+const RTT_SCALE: u16 = 200;
+
+/// The room's traffic at a glance, for the status area
+///
+/// Shows upload and download, how many peers we're connected to, and
+/// packet loss and round trips over the last minute as sparklines. Fed
+/// one [`NetworkStats`] a second, kept in fixed rings so it's `Copy` and
+/// sampling it never allocates.
+#[derive(Debug, Clone, Copy)]
+pub struct RoomStats {
+    latest: NetworkStats,
+    previous: Option<PacketCounts>,
+    // Loss in hundredths of a percent and round trips in milliseconds, one per second
+    loss: [u16; HISTORY],
+    rtt: [u16; HISTORY],
+    // Slot the next second goes in, and how many are filled
+    next: usize,
+    filled: usize,
+}
+
+impl RoomStats {
+    pub fn new() -> Self {
+        Self {
+            latest: NetworkStats::default(),
+            previous: None,
+            loss: [0; HISTORY],
+            rtt: [0; HISTORY],
+            next: 0,
+            filled: 0,
+        }
+    }
+
+    /// Adds the stats sampled a second after the last
+    pub fn push(&mut self, stats: NetworkStats) {
+        let loss = self
+            .previous
+            .map_or(0.0, |previous| stats.packets.loss_since(previous));
+        self.loss[self.next] = (loss * 10_000.0).round() as u16;
+        self.rtt[self.next] = stats
+            .rtt
+            .map_or(0, |rtt| rtt.as_millis().min(u16::MAX as u128) as u16);
+        self.next = (self.next + 1) % HISTORY;
+        self.filled = (self.filled + 1).min(HISTORY);
+        self.previous = Some(stats.packets);
+        self.latest = stats;
+    }
+
+    /// Share of packets lost over the latest second, as a percentage
+    pub fn loss_percent(&self) -> f64 {
+        match self.filled {
+            0 => 0.0,
+            _ => self.loss[(self.next + HISTORY - 1) % HISTORY] as f64 / 100.0,
+        }
+    }
+
+    /// The seconds recorded in `ring`, oldest first
+    fn series(&self, ring: &[u16; HISTORY]) -> impl Iterator<Item = u16> + '_ {
+        let ring = *ring;
+        let start = (self.next + HISTORY - self.filled) % HISTORY;
+        (0..self.filled).map(move |i| ring[(start + i) % HISTORY])
+    }
+
+    /// Draws `label` and then `ring` as a sparkline on the first row of `area`
+    ///
+    /// A column stands for as many seconds as it takes to fit the minute,
+    /// showing the worst of them, with the newest on the right.
+    fn draw_series(
+        &self,
+        area: Rect,
+        buf: &mut Buffer,
+        label: &str,
+        ring: &[u16; HISTORY],
+        scale: u16,
+        style: Style,
+    ) {
+        let (x, y) = buf.set_stringn(area.x, area.y, label, area.width as usize, Style::default());
+        let width = area.right().saturating_sub(x + 1) as usize;
+        if width == 0 || self.filled == 0 {
+            return;
+        }
+
+        let per_column = HISTORY.div_ceil(width.min(HISTORY));
+        let columns = self.filled.div_ceil(per_column);
+        let scale = self.series(ring).max().unwrap_or(0).max(scale) as usize;
+        let mut column_x = area.right() - columns as u16;
+        let mut worst = 0;
+        for (i, value) in self.series(ring).enumerate() {
+            worst = worst.max(value as usize);
+            if (i + 1) % per_column == 0 || i + 1 == self.filled {
+                let level = (worst * (LEVELS.len() - 1) / scale).min(LEVELS.len() - 1);
+                buf.get_mut(column_x, y)
+                    .set_symbol(LEVELS[level])
+                    .set_style(style);
+                column_x += 1;
+                worst = 0;
+            }
+        }
+    }
+}
+
+impl Default for RoomStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Widget for RoomStats {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let usage = self.latest.usage;
+        let mut title = t_args(
+            "stats.title",
+            &[
+                &(usage.upload_bps / 1000),
+                &(usage.download_bps / 1000),
+                &self.latest.peers,
+            ],
+        );
+        // Peers dropped by the table rather than leaving mean it's too small
+        // for the room, or handshakes are failing
+        let evictions = self.latest.evictions;
+        let dropped = evictions.expired + evictions.evicted;
+        if dropped > 0 {
+            title.push_str(&t_args("stats.dropped", &[&dropped]));
+        }
+        let block = Block::default().title(title).borders(Borders::ALL);
+        let inner = block.inner(area);
+        block.render(area, buf);
+        if inner.width < 2 || inner.height == 0 {
+            return;
+        }
+
+        let loss = t_args("stats.loss", &[&format!("{:.1}", self.loss_percent())]);
+        let rtt = match self.latest.rtt {
+            Some(rtt) => t_args("stats.rtt", &[&rtt.as_millis()]),
+            None => t("stats.rtt_unknown").to_string(),
+        };
+        let half = inner.width / 2;
+        self.draw_series(
+            Rect::new(inner.x, inner.y, half, 1),
+            buf,
+            &loss,
+            &self.loss,
+            LOSS_SCALE,
+            Style::default().fg(Color::Yellow),
+        );
+        self.draw_series(
+            Rect::new(inner.x + half + 1, inner.y, inner.width - half - 1, 1),
+            buf,
+            &rtt,
+            &self.rtt,
+            RTT_SCALE,
+            Style::default().fg(Color::Cyan),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::EvictionStats;
+    use std::time::Duration;
+
+    #[test]
+    fn test_sparklines_keep_the_last_minute() {
+        let mut stats = RoomStats::new();
+        let mut packets = PacketCounts::default();
+        for second in 0..HISTORY + 10 {
+            // Every tenth second loses half its packets
+            packets.sent += 50;
+            packets.received += if second % 10 == 9 { 25 } else { 50 };
+            stats.push(NetworkStats {
+                packets,
+                rtt: Some(Duration::from_millis(40)),
+                peers: 2,
+                ..Default::default()
+            });
+        }
+        assert_eq!(stats.loss_percent(), 50.0);
+        assert_eq!(stats.series(&stats.loss).count(), HISTORY);
+        assert_eq!(
+            stats.series(&stats.loss).filter(|&loss| loss > 0).count(),
+            6
+        );
+
+        let area = Rect::new(0, 0, 70, 3);
+        let mut buf = Buffer::empty(area);
+        stats.render(area, &mut buf);
+        let row: String = (0..area.width)
+            .map(|x| buf.get(x, 1).symbol.clone())
+            .collect();
+        assert!(row.contains(bar::FULL));
+        assert!(row.contains("40"));
+    }
+
+    #[test]
+    fn test_title_counts_dropped_peers() {
+        let title = |stats: RoomStats| {
+            let area = Rect::new(0, 0, 60, 3);
+            let mut buf = Buffer::empty(area);
+            stats.render(area, &mut buf);
+            (0..area.width)
+                .map(|x| buf.get(x, 0).symbol.clone())
+                .collect::<String>()
+        };
+        assert!(!title(RoomStats::new()).contains("dropped"));
+
+        let mut stats = RoomStats::new();
+        stats.push(NetworkStats {
+            evictions: EvictionStats {
+                expired: 2,
+                evicted: 1,
+            },
+            ..Default::default()
+        });
+        assert!(title(stats).contains("3 dropped"));
+    }
+}
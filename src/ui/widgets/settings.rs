@@ -0,0 +1,971 @@
+use crossterm::event::KeyCode;
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Widget},
+};
+use std::collections::HashMap;
+
+use crate::app::config::Config;
+use crate::audio::{AudioCodec, CodecSet, PreviewDirection};
+use crate::network::{IceServer, IceTestResult};
+use crate::ui::i18n::{t, t_args};
+
+/// A page of the settings screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsPage {
+    General,
+    Audio,
+    Network,
+    /// STUN and TURN servers, edited as a list rather than fields
+    IceServers,
+}
+
+impl SettingsPage {
+    /// All pages, in tab order
+    pub const ALL: [SettingsPage; 4] = [
+        SettingsPage::General,
+        SettingsPage::Audio,
+        SettingsPage::Network,
+        SettingsPage::IceServers,
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            SettingsPage::General => t("settings.page_general"),
+            SettingsPage::Audio => t("settings.page_audio"),
+            SettingsPage::Network => t("settings.page_network"),
+            SettingsPage::IceServers => t("settings.page_ice"),
+        }
+    }
+}
+
+/// How a setting is edited
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldKind {
+    /// Typed in; an optional field left empty is saved as `none`
+    Text { optional: bool },
+    /// On or off
+    Toggle,
+    /// One of a fixed list of values, stepped through in turn
+    Choice(Vec<String>),
+}
+
+/// One setting on a page, edited as its configuration file value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SettingsField {
+    pub page: SettingsPage,
+    /// Configuration key the field edits
+    pub key: &'static str,
+    /// Text key for the label shown next to the value
+    pub label: &'static str,
+    pub kind: FieldKind,
+    /// Why the last value entered was rejected, if it was
+    pub error: Option<String>,
+}
+
+impl SettingsField {
+    fn new(page: SettingsPage, key: &'static str, label: &'static str, kind: FieldKind) -> Self {
+        Self {
+            page,
+            key,
+            label,
+            kind,
+            error: None,
+        }
+    }
+}
+
+/// What a key press in the settings screen did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsEvent {
+    /// Nothing that needs acting on
+    None,
+    /// A setting was changed and should be applied
+    Changed,
+    /// The screen was closed; its configuration should be saved
+    Closed,
+    /// Every listed ICE server should be tested, reporting back through
+    /// [`SettingsForm::set_ice_result`]
+    TestIceServers,
+    /// The spatial preview voice should walk to this direction, or stop with `None`
+    SpatialPreview(Option<PreviewDirection>),
+}
+
+/// How far through the spatial preview the user has got
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PreviewRun {
+    /// Waiting to hear whether the voice is where it should be at stop `step`
+    Asking {
+        step: usize,
+        missed: Vec<PreviewDirection>,
+    },
+    /// Every stop answered, with those where the voice was heard elsewhere
+    Finished { missed: Vec<PreviewDirection> },
+}
+
+/// Settings screen with General, Audio, Network and ICE server pages
+///
+/// Edits a copy of the configuration. Every value goes through the same
+/// checks as loading the configuration file, so a value the file would
+/// reject is shown with its error and never applied.
+#[derive(Debug, Clone)]
+pub struct SettingsForm {
+    config: Config,
+    fields: Vec<SettingsField>,
+    page: SettingsPage,
+    // Index into the current page's fields
+    selected: usize,
+    // Text being typed into the selected field, while editing
+    editing: Option<String>,
+    // Latest test of each ICE server; `None` while one is running
+    ice_results: HashMap<IceServer, Option<IceTestResult>>,
+    // Why the last ICE server entered was rejected, if it was
+    ice_error: Option<String>,
+    // The spatial preview on the Audio page, once started
+    preview: Option<PreviewRun>,
+}
+
+impl SettingsForm {
+    /// Creates the screen for `config`, offering the named devices and
+    /// interfaces to choose from
+    pub fn new(
+        config: Config,
+        input_devices: Vec<String>,
+        output_devices: Vec<String>,
+        bind_addresses: Vec<String>,
+    ) -> Self {
+        use FieldKind::*;
+        use SettingsPage::*;
+
+        let with_default = |devices: Vec<String>| {
+            let mut choices = vec!["none".to_string()];
+            choices.extend(devices);
+            Choice(choices)
+        };
+        let mut bind_choices = vec!["auto".to_string()];
+        bind_choices.extend(bind_addresses);
+        let available = CodecSet::available();
+        let codecs = [AudioCodec::Opus, AudioCodec::Adpcm, AudioCodec::Pcm]
+            .into_iter()
+            .filter(|codec| available.contains(*codec))
+            .map(|codec| codec.to_string())
+            .collect();
+
+        let fields = vec![
+            SettingsField::new(
+                General,
+                "username",
+                "settings.field_username",
+                Text { optional: false },
+            ),
+            SettingsField::new(
+                General,
+                "locale",
+                "settings.field_locale",
+                Choice(vec!["en".into(), "es".into()]),
+            ),
+            SettingsField::new(
+                General,
+                "do_not_disturb",
+                "settings.field_do_not_disturb",
+                Toggle,
+            ),
+            SettingsField::new(
+                General,
+                "session_time_limit_minutes",
+                "settings.field_session_time_limit_minutes",
+                Text { optional: true },
+            ),
+            SettingsField::new(
+                General,
+                "recording_consent",
+                "settings.field_recording_consent",
+                Toggle,
+            ),
+            SettingsField::new(
+                General,
+                "audit_log_file",
+                "settings.field_audit_log_file",
+                Text { optional: true },
+            ),
+            SettingsField::new(
+                General,
+                "crash_reports",
+                "settings.field_crash_reports",
+                Toggle,
+            ),
+            SettingsField::new(General, "keep_awake", "settings.field_keep_awake", Toggle),
+            SettingsField::new(
+                General,
+                "large_speaking_indicators",
+                "settings.field_large_speaking_indicators",
+                Toggle,
+            ),
+            SettingsField::new(
+                General,
+                "flash_join_requests",
+                "settings.field_flash_join_requests",
+                Toggle,
+            ),
+            SettingsField::new(
+                General,
+                "min_contrast",
+                "settings.field_min_contrast",
+                Text { optional: true },
+            ),
+            SettingsField::new(
+                Audio,
+                "input_device",
+                "settings.field_input_device",
+                with_default(input_devices),
+            ),
+            SettingsField::new(
+                Audio,
+                "output_device",
+                "settings.field_output_device",
+                with_default(output_devices),
+            ),
+            SettingsField::new(
+                Audio,
+                "stream_role",
+                "settings.field_stream_role",
+                Choice(vec!["communication".into(), "media".into()]),
+            ),
+            SettingsField::new(
+                Audio,
+                "communications_device",
+                "settings.field_communications_device",
+                Toggle,
+            ),
+            SettingsField::new(
+                Audio,
+                "audio_codec",
+                "settings.field_audio_codec",
+                Choice(codecs),
+            ),
+            SettingsField::new(
+                Audio,
+                "audio_bitrate_kbps",
+                "settings.field_audio_bitrate_kbps",
+                Text { optional: false },
+            ),
+            SettingsField::new(
+                Audio,
+                "frame_duration_ms",
+                "settings.field_frame_duration_ms",
+                Choice(vec!["10".into(), "20".into(), "40".into()]),
+            ),
+            SettingsField::new(
+                Audio,
+                "cpu_load_limit_percent",
+                "settings.field_cpu_load_limit_percent",
+                Text { optional: true },
+            ),
+            SettingsField::new(
+                Audio,
+                "auto_microphone",
+                "settings.field_auto_microphone",
+                Toggle,
+            ),
+            SettingsField::new(
+                Audio,
+                "network_monitor",
+                "settings.field_network_monitor",
+                Toggle,
+            ),
+            SettingsField::new(
+                Audio,
+                "output_channels",
+                "settings.field_output_channels",
+                Text { optional: false },
+            ),
+            SettingsField::new(
+                Audio,
+                "system_audio_percent",
+                "settings.field_system_audio_percent",
+                Text { optional: true },
+            ),
+            SettingsField::new(
+                Audio,
+                "sync_shared_audio",
+                "settings.field_sync_shared_audio",
+                Toggle,
+            ),
+            SettingsField::new(
+                Network,
+                "port_mapping",
+                "settings.field_port_mapping",
+                Toggle,
+            ),
+            SettingsField::new(
+                Network,
+                "share_lan_addresses",
+                "settings.field_share_lan_addresses",
+                Toggle,
+            ),
+            SettingsField::new(
+                Network,
+                "bind_address",
+                "settings.field_bind_address",
+                Choice(bind_choices),
+            ),
+            SettingsField::new(
+                Network,
+                "proxy",
+                "settings.field_proxy",
+                Text { optional: false },
+            ),
+            SettingsField::new(
+                Network,
+                "low_bandwidth",
+                "settings.field_low_bandwidth",
+                Toggle,
+            ),
+            SettingsField::new(
+                Network,
+                "upload_cap_kbps",
+                "settings.field_upload_cap_kbps",
+                Text { optional: true },
+            ),
+            SettingsField::new(
+                Network,
+                "download_cap_kbps",
+                "settings.field_download_cap_kbps",
+                Text { optional: true },
+            ),
+            SettingsField::new(
+                Network,
+                "link_shortener_url",
+                "settings.field_link_shortener_url",
+                Text { optional: true },
+            ),
+            SettingsField::new(
+                Network,
+                "max_datagram_size",
+                "settings.field_max_datagram_size",
+                Text { optional: false },
+            ),
+            SettingsField::new(
+                Network,
+                "udp_receive_buffer_kb",
+                "settings.field_udp_receive_buffer_kb",
+                Text { optional: true },
+            ),
+            SettingsField::new(
+                Network,
+                "udp_send_buffer_kb",
+                "settings.field_udp_send_buffer_kb",
+                Text { optional: true },
+            ),
+            SettingsField::new(
+                Network,
+                "forward_secrecy",
+                "settings.field_forward_secrecy",
+                Toggle,
+            ),
+            SettingsField::new(
+                Network,
+                "nonce_strategy",
+                "settings.field_nonce_strategy",
+                Choice(vec!["random".into(), "counter".into()]),
+            ),
+        ];
+
+        Self {
+            config,
+            fields,
+            page: General,
+            selected: 0,
+            editing: None,
+            ice_results: HashMap::new(),
+            ice_error: None,
+            preview: None,
+        }
+    }
+
+    /// The configuration with every accepted change
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn page(&self) -> SettingsPage {
+        self.page
+    }
+
+    /// Fields on the current page
+    pub fn page_fields(&self) -> Vec<&SettingsField> {
+        self.fields
+            .iter()
+            .filter(|field| field.page == self.page)
+            .collect()
+    }
+
+    /// Value a field shows, as the configuration file has it
+    pub fn value(&self, key: &str) -> String {
+        self.config.value(key).unwrap_or_default()
+    }
+
+    pub fn is_editing(&self) -> bool {
+        self.editing.is_some()
+    }
+
+    /// Latest test of an ICE server: `None` if never tested, `Some(None)` while testing
+    pub fn ice_result(&self, server: &IceServer) -> Option<Option<&IceTestResult>> {
+        self.ice_results.get(server).map(Option::as_ref)
+    }
+
+    /// Records how testing an ICE server went
+    pub fn set_ice_result(&mut self, server: IceServer, result: IceTestResult) {
+        self.ice_results.insert(server, Some(result));
+    }
+
+    // Rows the selection moves over on the current page
+    fn row_count(&self) -> usize {
+        match self.page {
+            SettingsPage::IceServers => self.config.ice_servers.len(),
+            _ => self.page_fields().len(),
+        }
+    }
+
+    /// Handles a key press
+    ///
+    /// Tab and Shift+Tab switch pages, Up and Down pick a field, and Enter
+    /// edits it: text is typed and confirmed with Enter, toggles flip and
+    /// choices step to the next value. Esc stops editing, or closes the screen.
+    ///
+    /// On the ICE server page `a` adds a server, `x` removes the selected
+    /// one, `+` and `-` move it up and down the priority order and `t`
+    /// tests them all. On the Audio page `p` starts the spatial preview,
+    /// which asks at each stop whether the voice was heard there.
+    pub fn handle_key(&mut self, key: KeyCode) -> SettingsEvent {
+        if let Some(text) = self.editing.as_mut() {
+            match key {
+                KeyCode::Char(c) => text.push(c),
+                KeyCode::Backspace => {
+                    text.pop();
+                }
+                KeyCode::Enter => {
+                    let text = self.editing.take().unwrap_or_default();
+                    if self.page == SettingsPage::IceServers {
+                        return self.add_ice_server(&text);
+                    }
+                    return self.commit(text);
+                }
+                KeyCode::Esc => self.editing = None,
+                _ => {}
+            }
+            return SettingsEvent::None;
+        }
+
+        if let Some(PreviewRun::Asking { .. }) = self.preview {
+            return self.handle_preview_key(key);
+        }
+
+        let count = self.row_count();
+        match key {
+            KeyCode::Tab | KeyCode::Right => self.switch_page(1),
+            KeyCode::BackTab | KeyCode::Left => self.switch_page(SettingsPage::ALL.len() - 1),
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => self.selected = (self.selected + 1).min(count.saturating_sub(1)),
+            KeyCode::Esc => return SettingsEvent::Closed,
+            _ if self.page == SettingsPage::IceServers => return self.handle_ice_key(key),
+            KeyCode::Char('p') if self.page == SettingsPage::Audio => {
+                self.preview = Some(PreviewRun::Asking {
+                    step: 0,
+                    missed: Vec::new(),
+                });
+                return SettingsEvent::SpatialPreview(Some(PreviewDirection::ALL[0]));
+            }
+            KeyCode::Enter | KeyCode::Char(' ') => {
+                let Some(field) = self
+                    .page_fields()
+                    .get(self.selected)
+                    .map(|field| (*field).clone())
+                else {
+                    return SettingsEvent::None;
+                };
+                let current = self.value(field.key);
+                match &field.kind {
+                    FieldKind::Text { .. } => {
+                        self.editing = Some(if current == "none" {
+                            String::new()
+                        } else {
+                            current
+                        });
+                    }
+                    FieldKind::Toggle => {
+                        let flipped = (current != "true").to_string();
+                        return self.commit(flipped);
+                    }
+                    FieldKind::Choice(choices) => {
+                        let next = choices
+                            .iter()
+                            .position(|choice| *choice == current)
+                            .map_or(0, |i| (i + 1) % choices.len());
+                        if let Some(choice) = choices.get(next) {
+                            return self.commit(choice.clone());
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        SettingsEvent::None
+    }
+
+    /// Answers, replays or stops the spatial preview; other keys wait until it's done
+    fn handle_preview_key(&mut self, key: KeyCode) -> SettingsEvent {
+        let Some(PreviewRun::Asking { step, missed }) = self.preview.as_mut() else {
+            return SettingsEvent::None;
+        };
+        let direction = PreviewDirection::ALL[*step];
+        match key {
+            KeyCode::Char('y') | KeyCode::Char('n') => {
+                if key == KeyCode::Char('n') {
+                    missed.push(direction);
+                }
+                *step += 1;
+                match PreviewDirection::ALL.get(*step) {
+                    Some(next) => SettingsEvent::SpatialPreview(Some(*next)),
+                    None => {
+                        self.preview = Some(PreviewRun::Finished {
+                            missed: std::mem::take(missed),
+                        });
+                        SettingsEvent::SpatialPreview(None)
+                    }
+                }
+            }
+            KeyCode::Char('r') => SettingsEvent::SpatialPreview(Some(direction)),
+            KeyCode::Esc => {
+                self.preview = None;
+                SettingsEvent::SpatialPreview(None)
+            }
+            _ => SettingsEvent::None,
+        }
+    }
+
+    fn handle_ice_key(&mut self, key: KeyCode) -> SettingsEvent {
+        let servers = &mut self.config.ice_servers;
+        match key {
+            KeyCode::Char('a') => {
+                self.editing = Some(String::new());
+                SettingsEvent::None
+            }
+            KeyCode::Char('x') | KeyCode::Delete => {
+                let Some(removed) = servers.remove(self.selected) else {
+                    return SettingsEvent::None;
+                };
+                self.ice_results.remove(&removed);
+                self.selected = self.selected.min(servers.len().saturating_sub(1));
+                SettingsEvent::Changed
+            }
+            KeyCode::Char('+') if self.selected > 0 && self.selected < servers.len() => {
+                self.selected = servers.move_up(self.selected);
+                SettingsEvent::Changed
+            }
+            KeyCode::Char('-') if self.selected + 1 < servers.len() => {
+                self.selected = servers.move_down(self.selected);
+                SettingsEvent::Changed
+            }
+            KeyCode::Char('t') if !servers.is_empty() => {
+                for server in servers.iter() {
+                    self.ice_results.insert(server.clone(), None);
+                }
+                SettingsEvent::TestIceServers
+            }
+            _ => SettingsEvent::None,
+        }
+    }
+
+    /// Parses and adds an ICE server at the lowest priority
+    fn add_ice_server(&mut self, text: &str) -> SettingsEvent {
+        match text.trim().parse::<IceServer>() {
+            Ok(server) => {
+                self.ice_error = None;
+                let before = self.config.ice_servers.len();
+                self.config.ice_servers.add(server);
+                if self.config.ice_servers.len() == before {
+                    return SettingsEvent::None;
+                }
+                self.selected = before;
+                SettingsEvent::Changed
+            }
+            Err(message) => {
+                self.ice_error = Some(message);
+                SettingsEvent::None
+            }
+        }
+    }
+
+    fn switch_page(&mut self, step: usize) {
+        let current = SettingsPage::ALL
+            .iter()
+            .position(|page| *page == self.page)
+            .unwrap_or(0);
+        self.page = SettingsPage::ALL[(current + step) % SettingsPage::ALL.len()];
+        self.selected = 0;
+        self.preview = None;
+    }
+
+    /// Checks and applies a value for the selected field
+    fn commit(&mut self, value: String) -> SettingsEvent {
+        let index = match self
+            .fields
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| field.page == self.page)
+            .nth(self.selected)
+        {
+            Some((index, _)) => index,
+            None => return SettingsEvent::None,
+        };
+        let field = &mut self.fields[index];
+
+        let value = value.trim();
+        let value = match (&field.kind, value.is_empty()) {
+            (FieldKind::Text { optional: true }, true) => "none",
+            (FieldKind::Text { optional: false }, true) => {
+                field.error = Some(t("settings.required").to_string());
+                return SettingsEvent::None;
+            }
+            _ => value,
+        };
+
+        match self.config.with_value(field.key, value) {
+            Ok(config) => {
+                field.error = None;
+                if config == self.config {
+                    return SettingsEvent::None;
+                }
+                self.config = config;
+                SettingsEvent::Changed
+            }
+            Err(e) => {
+                field.error = Some(e.message().to_string());
+                SettingsEvent::None
+            }
+        }
+    }
+}
+
+impl Widget for SettingsForm {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Clear.render(area, buf);
+
+        let mut lines = Vec::new();
+        let tabs: Vec<Span> = SettingsPage::ALL
+            .iter()
+            .flat_map(|page| {
+                let style = if *page == self.page {
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(Color::DarkGray)
+                };
+                [
+                    Span::styled(format!(" {} ", page.title()), style),
+                    Span::raw("|"),
+                ]
+            })
+            .collect();
+        lines.push(Line::from(tabs));
+        lines.push(Line::from(""));
+
+        if self.page == SettingsPage::IceServers {
+            self.render_ice_servers(area, buf, lines);
+            return;
+        }
+
+        for (i, field) in self.page_fields().into_iter().enumerate() {
+            let selected = i == self.selected;
+            let value = match (&self.editing, selected) {
+                (Some(text), true) => format!("{}_", text),
+                _ => match (&field.kind, self.value(field.key).as_str()) {
+                    (FieldKind::Choice(_), "none") => t("settings.system_default").to_string(),
+                    (_, "none") => t("settings.not_set").to_string(),
+                    (FieldKind::Toggle, "true") => t("settings.on").to_string(),
+                    (FieldKind::Toggle, _) => t("settings.off").to_string(),
+                    (_, value) => value.to_string(),
+                },
+            };
+            let style = if selected {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!("{}: ", t(field.label)), style),
+                Span::styled(value, style.add_modifier(Modifier::BOLD)),
+            ]));
+            if let Some(error) = &field.error {
+                lines.push(Line::from(Span::styled(
+                    format!("  {}", error),
+                    Style::default().fg(Color::Red),
+                )));
+            }
+        }
+
+        lines.push(Line::from(""));
+        if self.page == SettingsPage::Audio {
+            self.push_preview_lines(&mut lines);
+        }
+        let hint = match self.preview {
+            Some(PreviewRun::Asking { .. }) => t("settings.preview_hint"),
+            _ => t("settings.hint"),
+        };
+        lines.push(Line::from(Span::styled(
+            hint,
+            Style::default().fg(Color::DarkGray),
+        )));
+        render_lines(lines, area, buf);
+    }
+}
+
+impl SettingsForm {
+    fn push_preview_lines(&self, lines: &mut Vec<Line>) {
+        let (text, color) = match &self.preview {
+            None => (t("settings.preview_start").to_string(), Color::DarkGray),
+            Some(PreviewRun::Asking { step, .. }) => (
+                t_args(
+                    "settings.preview_question",
+                    &[&direction_label(PreviewDirection::ALL[*step])],
+                ),
+                Color::Yellow,
+            ),
+            Some(PreviewRun::Finished { missed }) if missed.is_empty() => {
+                (t("settings.preview_passed").to_string(), Color::Green)
+            }
+            Some(PreviewRun::Finished { missed }) => {
+                let missed: Vec<&str> = missed.iter().map(|d| direction_label(*d)).collect();
+                (
+                    t_args("settings.preview_missed", &[&missed.join(", ")]),
+                    Color::Red,
+                )
+            }
+        };
+        lines.push(Line::from(Span::styled(text, Style::default().fg(color))));
+    }
+
+    fn render_ice_servers(&self, area: Rect, buf: &mut Buffer, mut lines: Vec<Line>) {
+        if self.config.ice_servers.is_empty() {
+            lines.push(Line::from(Span::styled(
+                t("settings.ice_none"),
+                Style::default().fg(Color::DarkGray),
+            )));
+        }
+        for (i, server) in self.config.ice_servers.iter().enumerate() {
+            let style = if i == self.selected {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            let (status, color) = match self.ice_result(server) {
+                None => (String::new(), Color::DarkGray),
+                Some(None) => (t("settings.ice_testing").to_string(), Color::Yellow),
+                Some(Some(IceTestResult::Passed(latency))) => (
+                    t_args("settings.ice_passed", &[&latency.as_millis()]),
+                    Color::Green,
+                ),
+                Some(Some(IceTestResult::Failed(reason))) => {
+                    (t_args("settings.ice_failed", &[reason]), Color::Red)
+                }
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!("{}. {}  ", i + 1, hide_credential(server)), style),
+                Span::styled(status, Style::default().fg(color)),
+            ]));
+        }
+
+        if let Some(text) = &self.editing {
+            lines.push(Line::from(Span::styled(
+                format!("{}: {}_", t("settings.ice_add"), text),
+                Style::default().fg(Color::Yellow),
+            )));
+        }
+        if let Some(error) = &self.ice_error {
+            lines.push(Line::from(Span::styled(
+                format!("  {}", error),
+                Style::default().fg(Color::Red),
+            )));
+        }
+
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            t("settings.ice_hint"),
+            Style::default().fg(Color::DarkGray),
+        )));
+        render_lines(lines, area, buf);
+    }
+}
+
+/// Where the preview voice is, as the question about it puts it
+fn direction_label(direction: PreviewDirection) -> &'static str {
+    t(match direction {
+        PreviewDirection::Front => "settings.preview_front",
+        PreviewDirection::Right => "settings.preview_right",
+        PreviewDirection::Back => "settings.preview_back",
+        PreviewDirection::Left => "settings.preview_left",
+    })
+}
+
+/// A server as it's listed on screen, without its TURN password
+fn hide_credential(server: &IceServer) -> String {
+    match server {
+        IceServer::Stun { .. } => server.to_string(),
+        IceServer::Turn {
+            address, username, ..
+        } => format!("turn:{}@{}", username, address),
+    }
+}
+
+fn render_lines(lines: Vec<Line>, area: Rect, buf: &mut Buffer) {
+    Paragraph::new(lines)
+        .block(
+            Block::default()
+                .title(t("menu.settings"))
+                .borders(Borders::ALL)
+                .style(Style::default().bg(Color::Black)),
+        )
+        .render(area, buf);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_form_validates_and_applies() {
+        let mut form = SettingsForm::new(
+            Config::default(),
+            vec![],
+            vec!["Headphones".into()],
+            vec!["lo".into()],
+        );
+        assert_eq!(form.page(), SettingsPage::General);
+
+        // Typing a new name
+        assert_eq!(form.handle_key(KeyCode::Enter), SettingsEvent::None);
+        assert!(form.is_editing());
+        for _ in 0.."User".len() {
+            form.handle_key(KeyCode::Backspace);
+        }
+        for c in "Alice".chars() {
+            form.handle_key(KeyCode::Char(c));
+        }
+        assert_eq!(form.handle_key(KeyCode::Enter), SettingsEvent::Changed);
+        assert_eq!(form.config().username, "Alice");
+
+        // A value the config file would reject is kept out, with its error
+        form.handle_key(KeyCode::Down);
+        form.handle_key(KeyCode::Down);
+        form.handle_key(KeyCode::Down);
+        form.handle_key(KeyCode::Enter);
+        form.handle_key(KeyCode::Char('x'));
+        assert_eq!(form.handle_key(KeyCode::Enter), SettingsEvent::None);
+        assert!(form.page_fields()[3].error.is_some());
+        assert_eq!(form.config().session_time_limit_minutes, None);
+
+        // Toggles and choices on other pages
+        form.handle_key(KeyCode::Tab);
+        form.handle_key(KeyCode::Down);
+        assert_eq!(form.handle_key(KeyCode::Enter), SettingsEvent::Changed);
+        assert_eq!(form.config().output_device.as_deref(), Some("Headphones"));
+
+        form.handle_key(KeyCode::Tab);
+        assert_eq!(form.page(), SettingsPage::Network);
+        assert_eq!(form.handle_key(KeyCode::Char(' ')), SettingsEvent::Changed);
+        assert!(form.config().port_mapping);
+        form.handle_key(KeyCode::Down);
+        form.handle_key(KeyCode::Down);
+        assert_eq!(form.handle_key(KeyCode::Enter), SettingsEvent::Changed);
+        assert_eq!(form.config().bind_address.to_string(), "lo");
+
+        // ICE servers are added, reordered, tested and removed as a list
+        form.handle_key(KeyCode::Tab);
+        assert_eq!(form.page(), SettingsPage::IceServers);
+        for entry in [
+            "stun:stun.example.org:3478",
+            "turn:alice:secret@turn.example.org:3478",
+        ] {
+            form.handle_key(KeyCode::Char('a'));
+            for c in entry.chars() {
+                form.handle_key(KeyCode::Char(c));
+            }
+            assert_eq!(form.handle_key(KeyCode::Enter), SettingsEvent::Changed);
+        }
+        form.handle_key(KeyCode::Char('a'));
+        form.handle_key(KeyCode::Char('x'));
+        assert_eq!(form.handle_key(KeyCode::Enter), SettingsEvent::None);
+        assert!(form.ice_error.is_some());
+        assert_eq!(form.config().ice_servers.len(), 2);
+
+        assert_eq!(form.handle_key(KeyCode::Char('+')), SettingsEvent::Changed);
+        let turn = form.config().ice_servers.get(0).cloned().unwrap();
+        assert!(matches!(turn, IceServer::Turn { .. }));
+        assert!(!hide_credential(&turn).contains("secret"));
+
+        assert_eq!(
+            form.handle_key(KeyCode::Char('t')),
+            SettingsEvent::TestIceServers
+        );
+        assert_eq!(form.ice_result(&turn), Some(None));
+        let result = IceTestResult::Passed(std::time::Duration::from_millis(40));
+        form.set_ice_result(turn.clone(), result.clone());
+        assert_eq!(form.ice_result(&turn), Some(Some(&result)));
+
+        assert_eq!(form.handle_key(KeyCode::Delete), SettingsEvent::Changed);
+        assert_eq!(form.config().ice_servers.len(), 1);
+        assert_eq!(form.ice_result(&turn), None);
+
+        assert_eq!(form.handle_key(KeyCode::Esc), SettingsEvent::Closed);
+    }
+
+    #[test]
+    fn test_spatial_preview_asks_at_each_stop() {
+        let mut form = SettingsForm::new(Config::default(), vec![], vec![], vec![]);
+        assert_eq!(form.handle_key(KeyCode::Char('p')), SettingsEvent::None);
+        form.handle_key(KeyCode::Tab);
+
+        let walk = |direction| SettingsEvent::SpatialPreview(Some(direction));
+        assert_eq!(
+            form.handle_key(KeyCode::Char('p')),
+            walk(PreviewDirection::Front)
+        );
+        // Other keys wait until the preview is done
+        form.handle_key(KeyCode::Tab);
+        assert_eq!(form.page(), SettingsPage::Audio);
+        assert_eq!(
+            form.handle_key(KeyCode::Char('r')),
+            walk(PreviewDirection::Front)
+        );
+        assert_eq!(
+            form.handle_key(KeyCode::Char('y')),
+            walk(PreviewDirection::Right)
+        );
+        form.handle_key(KeyCode::Char('y'));
+        form.handle_key(KeyCode::Char('n'));
+        assert_eq!(
+            form.handle_key(KeyCode::Char('y')),
+            SettingsEvent::SpatialPreview(None)
+        );
+        assert_eq!(
+            form.preview,
+            Some(PreviewRun::Finished {
+                missed: vec![PreviewDirection::Back]
+            })
+        );
+
+        // Esc stops a preview part way without closing the screen
+        form.handle_key(KeyCode::Char('p'));
+        assert_eq!(
+            form.handle_key(KeyCode::Esc),
+            SettingsEvent::SpatialPreview(None)
+        );
+        assert_eq!(form.preview, None);
+        assert_eq!(form.handle_key(KeyCode::Esc), SettingsEvent::Closed);
+    }
+}
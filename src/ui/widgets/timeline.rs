@@ -0,0 +1,174 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Widget},
+};
+
+use crate::app::presence::PresenceStatus;
+use crate::app::session_timer::format_clock;
+use crate::app::timeline::{TimelineEntry, TimelineEvent};
+use crate::ui::i18n::{t, t_args};
+
+/// Rows taken by the border, and the hint below the entries
+const CHROME_ROWS: u16 = 4;
+
+/// The session's comings and goings, oldest first, scrolled to the newest
+/// until scrolled back
+#[derive(Debug, Clone, Default)]
+pub struct TimelineView {
+    entries: Vec<TimelineEntry>,
+    // Entries hidden below the bottom of the pane; 0 follows the newest
+    from_end: usize,
+}
+
+impl TimelineView {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Shows the timeline as it now stands, holding the view on the same
+    /// entries if scrolled back
+    pub fn set_entries(&mut self, entries: Vec<TimelineEntry>) {
+        if self.from_end > 0 {
+            self.from_end += entries.len().saturating_sub(self.entries.len());
+        }
+        self.entries = entries;
+        self.from_end = self.from_end.min(self.entries.len().saturating_sub(1));
+    }
+
+    /// Scrolls back towards the start of the session by `rows`
+    pub fn scroll_up(&mut self, rows: usize) {
+        self.from_end = (self.from_end + rows).min(self.entries.len().saturating_sub(1));
+    }
+
+    /// Scrolls on towards the newest entry by `rows`
+    pub fn scroll_down(&mut self, rows: usize) {
+        self.from_end = self.from_end.saturating_sub(rows);
+    }
+
+    /// Entries that fit in `rows`, ending `from_end` short of the newest
+    fn visible(&self, rows: usize) -> &[TimelineEntry] {
+        let end = self.entries.len() - self.from_end;
+        &self.entries[end.saturating_sub(rows)..end]
+    }
+}
+
+/// What happened, in the user's language
+fn describe(event: &TimelineEvent) -> String {
+    match event {
+        TimelineEvent::Entered => t("timeline.entered").to_string(),
+        TimelineEvent::Left => t("timeline.left").to_string(),
+        TimelineEvent::PeerJoined { name } => t_args("timeline.peer_joined", &[name]),
+        TimelineEvent::PeerLeft { name } => t_args("timeline.peer_left", &[name]),
+        TimelineEvent::BecameHost => t("timeline.became_host").to_string(),
+        TimelineEvent::PresenceChanged { name, status } => {
+            let key = match status {
+                PresenceStatus::Active => "timeline.active",
+                PresenceStatus::Idle => "timeline.idle",
+                PresenceStatus::Away => "timeline.away",
+            };
+            t_args(key, &[name])
+        }
+        TimelineEvent::RecordingChanged {
+            name,
+            recording: true,
+        } => t_args("timeline.recording_started", &[name]),
+        TimelineEvent::RecordingChanged {
+            name,
+            recording: false,
+        } => t_args("timeline.recording_stopped", &[name]),
+        TimelineEvent::MeetingModeChanged { enabled: true } => t("timeline.meeting_on").to_string(),
+        TimelineEvent::MeetingModeChanged { enabled: false } => {
+            t("timeline.meeting_off").to_string()
+        }
+    }
+}
+
+impl Widget for TimelineView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Clear.render(area, buf);
+
+        let rows = area.height.saturating_sub(CHROME_ROWS) as usize;
+        let mut lines = Vec::new();
+        if self.entries.is_empty() {
+            lines.push(Line::from(Span::styled(
+                t("timeline.empty"),
+                Style::default().fg(Color::DarkGray),
+            )));
+        }
+        for entry in self.visible(rows) {
+            let style = match entry.event {
+                TimelineEvent::PeerJoined { .. } | TimelineEvent::Entered => {
+                    Style::default().fg(Color::Green)
+                }
+                TimelineEvent::PeerLeft { .. } | TimelineEvent::Left => {
+                    Style::default().fg(Color::Yellow)
+                }
+                TimelineEvent::RecordingChanged {
+                    recording: true, ..
+                } => Style::default().fg(Color::Red),
+                _ => Style::default(),
+            };
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!("{:>7}  ", format_clock(entry.offset)),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::styled(describe(&entry.event), style),
+            ]));
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            t("timeline.hint"),
+            Style::default().fg(Color::DarkGray),
+        )));
+
+        Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .title(t("timeline.title"))
+                    .borders(Borders::ALL)
+                    .style(Style::default().bg(Color::Black)),
+            )
+            .render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    fn entries(count: u64) -> Vec<TimelineEntry> {
+        (0..count)
+            .map(|n| TimelineEntry {
+                at: SystemTime::UNIX_EPOCH + Duration::from_secs(n),
+                offset: Duration::from_secs(n),
+                event: TimelineEvent::PeerJoined {
+                    name: format!("Peer {}", n),
+                },
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_follows_the_newest_until_scrolled_back() {
+        let mut view = TimelineView::new();
+        view.set_entries(entries(10));
+        assert_eq!(view.visible(3)[2].offset, Duration::from_secs(9));
+
+        view.scroll_up(4);
+        assert_eq!(view.visible(3)[2].offset, Duration::from_secs(5));
+        // Scrolled back, new entries don't move what's shown
+        view.set_entries(entries(12));
+        assert_eq!(view.visible(3)[2].offset, Duration::from_secs(5));
+
+        view.scroll_up(100);
+        assert_eq!(view.visible(3).len(), 1);
+        view.scroll_down(100);
+        view.set_entries(entries(13));
+        assert_eq!(view.visible(3)[2].offset, Duration::from_secs(12));
+    }
+}
@@ -0,0 +1,258 @@
+//! Golden-file tests for the DSP chain
+//!
+//! Each case runs a stored input WAV through part of the audio pipeline and
+//! compares the result against a stored output WAV. Set
+//! `RESONANCE_UPDATE_GOLDEN=1` to regenerate the golden files after an
+//! intentional change to the processing.
+
+use resonance::app::mixing::HostMixer;
+use resonance::audio::{AudioFrame, DriftCompensator, SpatialAudioProcessor, VoiceProcessor};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+const SAMPLE_RATE: u32 = 48000;
+
+/// Largest per-sample difference allowed against the golden output
+const TOLERANCE: f32 = 1e-5;
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+fn update_mode() -> bool {
+    std::env::var_os("RESONANCE_UPDATE_GOLDEN").is_some()
+}
+
+// Writes interleaved samples as a 32-bit float WAV
+fn write_wav(path: &PathBuf, channels: u16, samples: &[f32]) {
+    let data_len = (samples.len() * 4) as u32;
+    let mut bytes = Vec::with_capacity(44 + data_len as usize);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&3u16.to_le_bytes()); // IEEE float
+    bytes.extend_from_slice(&channels.to_le_bytes());
+    bytes.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    bytes.extend_from_slice(&(SAMPLE_RATE * channels as u32 * 4).to_le_bytes());
+    bytes.extend_from_slice(&(channels * 4).to_le_bytes());
+    bytes.extend_from_slice(&32u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        bytes.extend_from_slice(&sample.to_le_bytes());
+    }
+    fs::write(path, bytes).unwrap();
+}
+
+// Reads a WAV written by `write_wav`, returning the channel count and samples
+fn read_wav(path: &PathBuf) -> (u16, Vec<f32>) {
+    let bytes = fs::read(path).unwrap_or_else(|e| {
+        panic!(
+            "Missing golden file {} ({}); run with RESONANCE_UPDATE_GOLDEN=1",
+            path.display(),
+            e
+        )
+    });
+    assert_eq!(&bytes[0..4], b"RIFF");
+    assert_eq!(&bytes[8..12], b"WAVE");
+    assert_eq!(
+        u16::from_le_bytes([bytes[20], bytes[21]]),
+        3,
+        "not a float WAV"
+    );
+    let channels = u16::from_le_bytes([bytes[22], bytes[23]]);
+    let samples = bytes[44..]
+        .chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect();
+    (channels, samples)
+}
+
+// Known test signals, written once and then read back from disk
+fn input(name: &str) -> Vec<f32> {
+    let path = golden_dir().join(format!("input_{}.wav", name));
+    if update_mode() && !path.exists() {
+        let samples: Vec<f32> = match name {
+            // Exponential sweep from 100Hz to 8kHz
+            "sweep" => (0..2400)
+                .map(|i| {
+                    let t = i as f32 / SAMPLE_RATE as f32;
+                    let duration = 2400.0 / SAMPLE_RATE as f32;
+                    let k = (8000.0f32 / 100.0).ln() / duration;
+                    let phase = 2.0 * std::f32::consts::PI * 100.0 * ((k * t).exp() - 1.0) / k;
+                    phase.sin() * 0.5
+                })
+                .collect(),
+            // Deterministic white noise
+            "noise" => {
+                let mut state: u32 = 0x1234_5678;
+                (0..2400)
+                    .map(|_| {
+                        state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                        (state >> 8) as f32 / (1u32 << 24) as f32 * 0.6 - 0.3
+                    })
+                    .collect()
+            }
+            _ => panic!("Unknown input {}", name),
+        };
+        write_wav(&path, 1, &samples);
+    }
+
+    let (channels, samples) = read_wav(&path);
+    assert_eq!(channels, 1, "inputs are mono");
+    samples
+}
+
+fn assert_matches_golden(name: &str, channels: u16, output: &[f32]) {
+    let path = golden_dir().join(format!("{}.wav", name));
+    if update_mode() {
+        write_wav(&path, channels, output);
+        return;
+    }
+
+    let (golden_channels, golden) = read_wav(&path);
+    assert_eq!(golden_channels, channels, "{}: channel count changed", name);
+    assert_eq!(golden.len(), output.len(), "{}: length changed", name);
+
+    let (index, diff) = output
+        .iter()
+        .zip(&golden)
+        .map(|(a, b)| (a - b).abs())
+        .enumerate()
+        .fold(
+            (0, 0.0f32),
+            |worst, (i, d)| if d > worst.1 { (i, d) } else { worst },
+        );
+    assert!(
+        diff <= TOLERANCE,
+        "{}: sample {} differs from golden by {}",
+        name,
+        index,
+        diff
+    );
+}
+
+// A compensator that has followed a queue growing by `drift` seconds a second
+fn drift_compensator(drift: f64) -> DriftCompensator {
+    let mut compensator = DriftCompensator::new();
+    let start = Instant::now();
+    for n in 0..600u32 {
+        let at = Duration::from_millis(20) * n;
+        let queued = 0.06 + drift * at.as_secs_f64();
+        compensator.observe(start + at, Duration::from_secs_f64(queued));
+    }
+    compensator
+}
+
+fn spatializer(x: f32, y: f32, z: f32) -> SpatialAudioProcessor {
+    let mut processor = SpatialAudioProcessor::new();
+    processor.set_sample_rate(SAMPLE_RATE);
+    processor.set_source_position(x, y, z);
+    processor
+}
+
+#[test]
+fn test_echo_cancellation_golden() {
+    let mut processor = VoiceProcessor::new().with_echo_cancellation(true);
+    processor.set_far_end_audio(&input("sweep"));
+
+    let output = processor.process(input("noise"));
+    assert_matches_golden("voice_echo_cancellation", 1, &output);
+}
+
+#[test]
+fn test_spatializer_positions_golden() {
+    let sweep = input("sweep");
+
+    assert_matches_golden(
+        "spatial_front",
+        2,
+        &spatializer(0.0, 0.0, 1.0).process(&sweep),
+    );
+    assert_matches_golden(
+        "spatial_left",
+        2,
+        &spatializer(-1.0, 0.0, 0.0).process(&sweep),
+    );
+    assert_matches_golden(
+        "spatial_right_far",
+        2,
+        &spatializer(3.0, 0.0, 1.0).process(&sweep),
+    );
+}
+
+#[test]
+fn test_spatializer_reverb_golden() {
+    let mut processor = spatializer(1.0, 0.0, 1.0);
+    processor.set_room_size(4.0, 3.0, 5.0);
+    processor.set_reverb_amount(0.5);
+
+    let output = processor.process(&input("noise"));
+    assert_matches_golden("spatial_reverb", 2, &output);
+}
+
+#[test]
+fn test_full_chain_golden() {
+    // Voice processing followed by spatialization, as for a remote participant
+    let mut voice = VoiceProcessor::new().with_echo_cancellation(true);
+    voice.set_far_end_audio(&input("noise"));
+    let processed = voice.process(input("sweep"));
+
+    let output = spatializer(-0.5, 0.0, 1.0).process(&processed);
+    assert_matches_golden("chain_voice_spatial", 2, &output);
+}
+
+#[test]
+fn test_host_mix_golden() {
+    // 10 ms frames, as the host mixes them
+    let frame = 480;
+    let sweep = input("sweep");
+    let noise = input("noise");
+    let reversed: Vec<f32> = sweep.iter().rev().copied().collect();
+
+    // Everyone together is loud enough to clip
+    let recipients = ["host", "alice", "bob", "carol"].map(String::from);
+    let mut mixer = HostMixer::new("host");
+    let mut mixes = vec![Vec::new(); recipients.len()];
+    for start in (0..sweep.len()).step_by(frame) {
+        let end = start + frame;
+        mixer.push(AudioFrame::new(sweep[start..end].to_vec(), SAMPLE_RATE, 1));
+        mixer.push(
+            AudioFrame::new(noise[start..end].to_vec(), SAMPLE_RATE, 1).with_peer_id("alice"),
+        );
+        mixer.push(
+            AudioFrame::new(reversed[start..end].to_vec(), SAMPLE_RATE, 1).with_peer_id("bob"),
+        );
+        for (recipient, mix) in mixer.mix(&recipients) {
+            let index = recipients.iter().position(|r| *r == recipient).unwrap();
+            mixes[index].extend(mix.samples);
+        }
+    }
+
+    for (recipient, mix) in recipients.iter().zip(&mixes) {
+        assert_matches_golden(&format!("host_mix_{}", recipient), 1, mix);
+    }
+}
+
+#[test]
+fn test_drift_resampler_golden() {
+    // The sweep on the left and noise on the right, in 10 ms blocks
+    let stereo: Vec<f32> = input("sweep")
+        .into_iter()
+        .zip(input("noise"))
+        .flat_map(|(left, right)| [left, right])
+        .collect();
+
+    for (name, drift) in [("drift_fast", 0.002), ("drift_slow", -0.002)] {
+        let mut compensator = drift_compensator(drift);
+        assert!((compensator.drift() - drift).abs() < 1e-9);
+
+        let output: Vec<f32> = stereo
+            .chunks(480 * 2)
+            .flat_map(|block| compensator.process(block, SAMPLE_RATE))
+            .collect();
+        assert_matches_golden(name, 2, &output);
+    }
+}
@@ -1,21 +1,18 @@
-use resonance::app::session::{Peer, Session, SessionManager};
-use resonance::audio::AudioCapture;
-use resonance::network::p2p::Endpoint;
-use resonance::ui::Participant;
+use resonance::app::session::SessionManager;
+use resonance::audio::{AudioCapture, AudioFrame};
 use std::sync::{Arc, Mutex};
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use tokio::task::JoinHandle;
+use std::time::Duration;
 use tokio::time;
 
 // Helper function to create test audio data
-fn create_test_audio() -> Vec<f32> {
+fn create_test_audio() -> AudioFrame {
     let mut audio = Vec::with_capacity(1024);
     for i in 0..1024 {
         let t = i as f32 / 44100.0;
         let sample = (440.0 * t * 2.0 * std::f32::consts::PI).sin() * 0.5;
         audio.push(sample);
     }
-    audio
+    AudioFrame::new(audio, 44100, 1)
 }
 
 #[tokio::test]
@@ -57,16 +54,7 @@ async fn test_multiple_instances_audio_communication() {
         "Failed to establish connection between host and client"
     );
 
-    // Step 4: Setup audio recording and playback mocks
-    // For host - create a buffer to hold received audio from client
-    let host_received_audio = Arc::new(Mutex::new(Vec::<f32>::new()));
-    let host_received_clone = host_received_audio.clone();
-
-    // For client - create a buffer to hold received audio from host
-    let client_received_audio = Arc::new(Mutex::new(Vec::<f32>::new()));
-    let client_received_clone = client_received_audio.clone();
-
-    // Step 5: Simulate host sending audio
+    // Step 4: Simulate host sending audio
     let host_audio = create_test_audio();
     host_manager
         .update_audio_stream("Host", host_audio.clone())
@@ -78,7 +66,7 @@ async fn test_multiple_instances_audio_communication() {
         .await
         .expect("Failed to send host audio");
 
-    // Step 6: Simulate client sending audio
+    // Step 5: Simulate client sending audio
     let client_audio = create_test_audio();
     client_manager
         .update_audio_stream("Client", client_audio.clone())
@@ -90,10 +78,10 @@ async fn test_multiple_instances_audio_communication() {
         .await
         .expect("Failed to send client audio");
 
-    // Step 7: Wait for audio to be transmitted
+    // Step 6: Wait for audio to be transmitted
     time::sleep(Duration::from_secs(1)).await;
 
-    // Step 8: Verify audio was received
+    // Step 7: Verify audio was received
     // In a real test, we would setup callbacks to capture received audio
     // For this test, we'll check that the connection remained active
     assert!(
@@ -105,7 +93,7 @@ async fn test_multiple_instances_audio_communication() {
         "Client lost connection"
     );
 
-    // Step 9: Clean up - both instances leave their sessions
+    // Step 8: Clean up - both instances leave their sessions
     host_manager
         .leave_session()
         .await
@@ -166,13 +154,13 @@ async fn test_audio_playback_verification() {
     let host_received_clone = host_received.clone();
     host_capture.set_data_callback(move |data| {
         let mut buffer = host_received_clone.lock().unwrap();
-        buffer.extend_from_slice(&data);
+        buffer.extend_from_slice(&data.samples);
     });
 
     let client_received_clone = client_received.clone();
     client_capture.set_data_callback(move |data| {
         let mut buffer = client_received_clone.lock().unwrap();
-        buffer.extend_from_slice(&data);
+        buffer.extend_from_slice(&data.samples);
     });
 
     // Step 6: Start audio capture
@@ -1,3 +1,4 @@
+use resonance::app::mixing::MixingTopology;
 use resonance::app::session::{Peer, Session, SessionManager};
 use resonance::network::p2p::Endpoint;
 use resonance::ui::Participant;
@@ -13,6 +14,7 @@ async fn test_session_peer_integration() {
         is_host: true,
         original_host_id: "host-id".to_string(),
         created_at: 0,
+        topology: MixingTopology::FullMesh,
     };
 
     // Create a session manager
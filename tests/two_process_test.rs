@@ -0,0 +1,137 @@
+//! End-to-end test that runs two real resonance processes on this machine.
+//!
+//! Each instance runs in headless mode with a generated test tone in place of
+//! the microphone, and is driven through its stdin/stdout control interface.
+//! Session creation uses STUN, so this needs network access and only runs with
+//! `cargo test --features process-tests`.
+#![cfg(feature = "process-tests")]
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const STEP_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// A headless instance and the lines it has written
+struct Instance {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+}
+
+impl Instance {
+    fn spawn() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_resonance"))
+            .arg("headless")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("Failed to start resonance");
+
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let (tx, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut instance = Self {
+            child,
+            stdin,
+            lines,
+        };
+        instance.expect(|line| line == "ready");
+        instance
+    }
+
+    /// Waits for a line matching the predicate, skipping others
+    fn expect(&mut self, matches: impl Fn(&str) -> bool) -> String {
+        let deadline = Instant::now() + STEP_TIMEOUT;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match self.lines.recv_timeout(remaining) {
+                Ok(line) if matches(&line) => return line,
+                Ok(line) => assert!(!line.starts_with("error"), "Instance failed: {}", line),
+                Err(e) => panic!("Timed out waiting for instance output: {}", e),
+            }
+        }
+    }
+
+    /// Sends a command and waits for its reply
+    fn command(&mut self, command: &str) -> String {
+        writeln!(self.stdin, "{}", command).unwrap();
+        self.expect(|line| line.starts_with("ok") || line.starts_with("status"))
+    }
+
+    /// Polls `status` until the predicate holds
+    fn wait_for_status(&mut self, ready: impl Fn(&str) -> bool) -> String {
+        let deadline = Instant::now() + STEP_TIMEOUT;
+        loop {
+            let status = self.command("status");
+            if ready(&status) {
+                return status;
+            }
+            assert!(
+                Instant::now() < deadline,
+                "Status never settled: {}",
+                status
+            );
+            thread::sleep(Duration::from_millis(250));
+        }
+    }
+}
+
+impl Drop for Instance {
+    fn drop(&mut self) {
+        let _ = writeln!(self.stdin, "quit");
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        let _ = self.child.kill();
+    }
+}
+
+#[test]
+fn test_join_approve_audio_leave() {
+    let mut host = Instance::spawn();
+    let mut client = Instance::spawn();
+
+    // Host creates a room and hands out its link
+    let link = host
+        .command("create")
+        .strip_prefix("ok ")
+        .expect("create returned no link")
+        .to_string();
+
+    // Client asks to join, and is in once the host approves the request
+    writeln!(client.stdin, "join {}", link).unwrap();
+    let request = host.expect(|line| line.starts_with("event join_request "));
+    let peer_id = request.split_whitespace().nth(2).unwrap().to_string();
+    host.command(&format!("approve {}", peer_id));
+    client.expect(|line| line.starts_with("ok"));
+    host.expect(|line| line.starts_with(&format!("event joined {} ", peer_id)));
+
+    client.wait_for_status(|status| status.contains("connected=true"));
+    host.wait_for_status(|status| status.contains("connected=true"));
+
+    // Both sides send a tone and should receive the other's
+    host.command("tone 500");
+    client.command("tone 500");
+    client.wait_for_status(|status| !status.ends_with("receiving="));
+    host.wait_for_status(|status| !status.ends_with("receiving="));
+
+    // Client leaves and the host sees them go
+    client.command("leave");
+    host.expect(|line| line == format!("event left {}", peer_id));
+    client.wait_for_status(|status| status.contains("connected=false"));
+}